@@ -0,0 +1,70 @@
+// cargo bench --bench order_lookups > /dev/null
+// 10_000 lookups of 50-item orders, through get_order (clones the whole
+// order) and through order_exists / order_total (clones nothing).
+// The orders come from a seeded OrderGenerator: every run measures the
+// same data.
+//
+// The adapters print every call, hence the redirection: results go to stderr.
+// No criterion here (the crate has no dependencies): a warm-up pass then the
+// best of a few timed runs is enough to see the difference.
+use hexa_lite::prelude::*;
+use hexa_lite::testing::OrderGenerator;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ORDERS: u32 = 100;
+const ITEMS_PER_ORDER: usize = 50;
+const SEED: u64 = 2024;
+const LOOKUPS: u32 = 10_000;
+const RUNS: usize = 5;
+
+fn best_of(mut run: impl FnMut()) -> Duration {
+    run(); // warm-up
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    OrderGenerator::new(SEED)
+        .items_per_cart(ITEMS_PER_ORDER..=ITEMS_PER_ORDER)
+        .generate_orders(&mut service, ORDERS as usize)
+        .unwrap();
+    let ids = || (0..LOOKUPS).map(|n| OrderId(n % ORDERS + 1));
+
+    let get_exists = best_of(|| {
+        for id in ids() {
+            black_box(service.get_order(id).unwrap().is_some());
+        }
+    });
+    let exists = best_of(|| {
+        for id in ids() {
+            black_box(service.order_exists(id).unwrap());
+        }
+    });
+    let get_total = best_of(|| {
+        for id in ids() {
+            black_box(service.get_order(id).unwrap().map(|order| order.total));
+        }
+    });
+    let total = best_of(|| {
+        for id in ids() {
+            black_box(service.order_total(id).unwrap());
+        }
+    });
+
+    eprintln!("{LOOKUPS} lookups, {ITEMS_PER_ORDER} items per order, best of {RUNS}:");
+    eprintln!("  get_order().is_some()   {get_exists:>12?}");
+    eprintln!("  order_exists()          {exists:>12?}");
+    eprintln!("  get_order().map(total)  {get_total:>12?}");
+    eprintln!("  order_total()           {total:>12?}");
+}
@@ -0,0 +1,78 @@
+// cargo bench --bench storage_formats > /dev/null
+// 10_000 generated orders in each storage format of the file repository:
+// how large the file is, how long encoding it takes, and how long one more
+// save takes (the whole file is rewritten every time).
+//
+// The repository prints every call, hence the redirection: results go to
+// stderr. Same approach as order_lookups: a warm-up, then the best of a few
+// runs.
+use hexa_lite::adapters::file_repository::{
+    Envelope, FileOrderRepository, JsonFormat, MsgPackFormat, RonFormat, StorageFormat,
+};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::OrderGenerator;
+use std::fs;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const ORDERS: u32 = 10_000;
+const SEED: u64 = 2024;
+const RUNS: usize = 5;
+
+fn best_of(mut run: impl FnMut()) -> Duration {
+    run(); // warm-up
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn measure<F: StorageFormat>(format: F, envelope: &Envelope, extra: &Order) {
+    let name = format.name();
+    let bytes = format.serialize(envelope);
+    let encode = best_of(|| {
+        black_box(format.serialize(envelope));
+    });
+    let path = std::env::temp_dir().join(format!(
+        "hexa_lite_{}_bench_{name}.orders",
+        std::process::id()
+    ));
+    fs::write(&path, &bytes).unwrap();
+    let repo = FileOrderRepository::open(&path, format).unwrap();
+    let save = best_of(|| repo.save(extra).unwrap());
+    let _ = fs::remove_file(&path);
+
+    eprintln!(
+        "  {name:<12} {:>10} bytes  {encode:>12?}  {save:>12?}",
+        bytes.len()
+    );
+}
+
+fn main() {
+    let mut generator = OrderGenerator::new(SEED);
+    let orders: Vec<Order> = (1..=ORDERS)
+        .map(|id| {
+            let mut order = Order::new(OrderId(id), generator.next_cart()).unwrap();
+            order.placed_at = Some(Timestamp(1_700_000_000 + u64::from(id)));
+            order
+        })
+        .collect();
+    let extra = Order::new(OrderId(ORDERS + 1), generator.next_cart()).unwrap();
+    let envelope = Envelope {
+        orders,
+        deleted: Vec::new(),
+    };
+
+    eprintln!("{ORDERS} orders, best of {RUNS}:");
+    eprintln!(
+        "  {:<12} {:>16}  {:>12}  {:>12}",
+        "format", "file size", "encode", "one save"
+    );
+    measure(JsonFormat, &envelope, &extra);
+    measure(RonFormat, &envelope, &extra);
+    measure(MsgPackFormat, &envelope, &extra);
+}
@@ -0,0 +1,142 @@
+// cargo bench --bench wiring
+// One scenario, the library's bench_profile (10_000 carts of 10 items, in
+// memory), through each way of wiring an OrderService:
+// - generic: OrderService<InMemoryOrderRepository, ...>, every call known
+//   at compile time
+// - dyn: a DynOrderService over adapters kept in a Box, every call through
+//   a vtable
+// - shared: the adapters in an Arc, handed to services on other threads;
+//   the repository keeps its orders behind a Mutex, so that is all sharing
+//   takes. Once on one thread, then split over THREADS of them, contending
+//   for that Mutex.
+// - batch / parallel: one service, all the carts handed over at once, to
+//   place_batch, one after the other, then to place_orders_parallel on
+//   THREADS workers.
+//
+// Every adapter is given Console::silent(), and the sender prints nothing:
+// what is measured is the wiring, not the terminal. tests/wiring checks that
+// the three leave the same orders behind.
+use hexa_lite::adapters::Console;
+use hexa_lite::application::DynOrderService;
+use hexa_lite::ports::{CancelToken, ServiceState};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::bench_profile;
+use std::hint::black_box;
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+const THREADS: usize = 4;
+
+fn repository() -> InMemoryOrderRepository {
+    InMemoryOrderRepository::new().with_console(Console::silent())
+}
+
+fn payment() -> MockPaymentGateway {
+    MockPaymentGateway::new().with_console(Console::silent())
+}
+
+fn sender() -> ConsoleSender {
+    ConsoleSender::new().with_console(Console::silent())
+}
+
+fn generic(carts: &[Vec<LineItem>]) {
+    let (repo, payment, sender) = (repository(), payment(), sender());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    for cart in carts {
+        black_box(service.place_order(cart.clone()).unwrap());
+    }
+}
+
+fn dynamic(carts: &[Vec<LineItem>]) {
+    let repo: Box<dyn OrderWriter + Sync> = Box::new(repository());
+    let payment: Box<dyn PaymentGateway + Sync> = Box::new(payment());
+    let sender: Box<dyn Sender + Sync> = Box::new(sender());
+    let mut service = DynOrderService::new(&*repo, &*payment, &*sender);
+    for cart in carts {
+        black_box(service.place_order(cart.clone()).unwrap());
+    }
+}
+
+// Each thread places its share of the carts under ids of its own: thread t
+// starts where thread t - 1 stops.
+fn shared(carts: &[Vec<LineItem>], threads: usize) {
+    let adapters = Arc::new((repository(), payment(), sender()));
+    let share = carts.len().div_ceil(threads);
+    let ready = Arc::new(Barrier::new(threads));
+    let workers: Vec<_> = carts
+        .chunks(share)
+        .enumerate()
+        .map(|(t, chunk)| {
+            let (adapters, ready, chunk) =
+                (Arc::clone(&adapters), Arc::clone(&ready), chunk.to_vec());
+            thread::spawn(move || {
+                let (repo, payment, sender) = &*adapters;
+                let state = ServiceState {
+                    next_id: (t * share + 1) as u32,
+                    open_drafts: Vec::new(),
+                };
+                let mut service = OrderService::restore(repo, payment, sender, &state).unwrap();
+                // Nobody places anything before everyone knows where to start.
+                ready.wait();
+                for cart in chunk {
+                    black_box(service.place_order(cart).unwrap());
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+}
+
+fn batch(carts: &[Vec<LineItem>]) {
+    let (repo, payment, sender) = (repository(), payment(), sender());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let outcomes = service.place_batch(carts.to_vec(), &CancelToken::new());
+    black_box(outcomes.unwrap());
+}
+
+fn parallel(carts: &[Vec<LineItem>], workers: usize) {
+    let (repo, payment, sender) = (repository(), payment(), sender());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    black_box(service.place_orders_parallel(carts.to_vec(), workers));
+}
+
+fn main() {
+    let profile = bench_profile();
+    let generic = profile.measure(generic);
+    let dynamic = profile.measure(dynamic);
+    let shared_one = profile.measure(|carts| shared(carts, 1));
+    let shared_many = profile.measure(|carts| shared(carts, THREADS));
+    let batch = profile.measure(batch);
+    let parallel = profile.measure(|carts| parallel(carts, THREADS));
+
+    eprintln!(
+        "{} orders of {} items, best of {}:",
+        profile.orders, profile.items_per_cart, profile.runs
+    );
+    eprintln!(
+        "  generic              {:>8.0} ns/order",
+        generic.ns_per_order()
+    );
+    eprintln!(
+        "  dyn                  {:>8.0} ns/order",
+        dynamic.ns_per_order()
+    );
+    eprintln!(
+        "  shared, 1 thread     {:>8.0} ns/order",
+        shared_one.ns_per_order()
+    );
+    eprintln!(
+        "  shared, {THREADS} threads    {:>8.0} ns/order",
+        shared_many.ns_per_order()
+    );
+    eprintln!(
+        "  batch                {:>8.0} ns/order",
+        batch.ns_per_order()
+    );
+    eprintln!(
+        "  parallel, {THREADS} workers  {:>8.0} ns/order",
+        parallel.ns_per_order()
+    );
+}
@@ -0,0 +1,334 @@
+// Generates hexa_lite::ports::generated from ports.toml: a trait per port,
+// and one Spy and one Noop adapter implementing all of them. See the top of
+// ports.toml for what it may contain.
+//
+// A mistake in the file stops the build, with the line it is on.
+use std::env;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+const SCHEMA: &str = "ports.toml";
+
+#[derive(Default)]
+struct Schema {
+    error: String,
+    imports: Vec<String>,
+    ports: Vec<Port>,
+}
+
+#[derive(Default)]
+struct Port {
+    name: String,
+    doc: Option<String>,
+    extends: Option<String>,
+    methods: Vec<Method>,
+}
+
+#[derive(Default)]
+struct Method {
+    name: String,
+    // (name, type)
+    params: Vec<(String, String)>,
+    returns: String,
+    noop: Option<String>,
+}
+
+enum Value {
+    Text(String),
+    List(Vec<String>),
+}
+
+fn main() {
+    println!("cargo::rerun-if-changed={SCHEMA}");
+    println!("cargo::rerun-if-changed=build.rs");
+    let text = fs::read_to_string(SCHEMA).unwrap_or_else(|e| panic!("{SCHEMA}: {e}"));
+    let schema = parse(&text).unwrap_or_else(|e| panic!("{SCHEMA}: {e}"));
+    let out = Path::new(&env::var("OUT_DIR").expect("cargo sets OUT_DIR")).join("ports.rs");
+    fs::write(&out, generate(&schema)).unwrap_or_else(|e| panic!("{}: {e}", out.display()));
+}
+
+// --- Reading ports.toml ---
+
+fn parse(text: &str) -> Result<Schema, String> {
+    let mut schema = Schema::default();
+    let mut lines = text.lines().enumerate();
+    while let Some((at, line)) = lines.next() {
+        let line_no = at + 1;
+        let fail = |reason: &str| format!("line {line_no}: {reason}");
+        let line = strip_comment(line);
+        match line {
+            "" => continue,
+            "[[port]]" => {
+                schema.ports.push(Port::default());
+                continue;
+            }
+            "[[port.method]]" => {
+                let port = schema
+                    .ports
+                    .last_mut()
+                    .ok_or_else(|| fail("a method before any [[port]]"))?;
+                port.methods.push(Method::default());
+                continue;
+            }
+            _ => {}
+        }
+        let (key, value) = line
+            .split_once('=')
+            .ok_or_else(|| fail("expected key = value"))?;
+        let mut value = value.trim().to_string();
+        // A list goes on until its closing bracket.
+        if value.starts_with('[') {
+            while !value.ends_with(']') {
+                let (_, next) = lines.next().ok_or_else(|| fail("a list never closed"))?;
+                value.push_str(strip_comment(next));
+            }
+        }
+        let value =
+            parse_value(&value).ok_or_else(|| fail("expected \"text\" or [\"text\", ...]"))?;
+        set(&mut schema, key.trim(), value).map_err(|reason| fail(&reason))?;
+    }
+    for port in &schema.ports {
+        if port.name.is_empty() {
+            return Err("a [[port]] without a name".to_string());
+        }
+        if let Some(method) = port
+            .methods
+            .iter()
+            .find(|m| m.name.is_empty() || m.returns.is_empty())
+        {
+            return Err(format!(
+                "{}: every method needs a name and what it returns, not {:?}",
+                port.name, method.name
+            ));
+        }
+    }
+    if schema.error.is_empty() {
+        return Err("no error type".to_string());
+    }
+    Ok(schema)
+}
+
+// ports.toml's strings hold no '#', so the first one starts a comment.
+fn strip_comment(line: &str) -> &str {
+    line.split('#').next().unwrap_or_default().trim()
+}
+
+fn parse_value(text: &str) -> Option<Value> {
+    let Some(mut rest) = text.strip_prefix('[').and_then(|t| t.strip_suffix(']')) else {
+        return unquote(text).map(|text| Value::Text(text.to_string()));
+    };
+    // Quoted items, each followed by a comma but maybe the last: the commas
+    // inside the quotes are the items'.
+    let mut items = Vec::new();
+    loop {
+        rest = rest.trim_start();
+        if rest.is_empty() {
+            return Some(Value::List(items));
+        }
+        let (item, after) = rest.strip_prefix('"')?.split_once('"')?;
+        items.push(item.to_string());
+        let after = after.trim_start();
+        rest = match after.strip_prefix(',') {
+            Some(after) => after,
+            None if after.is_empty() => after,
+            None => return None,
+        };
+    }
+}
+
+fn unquote(text: &str) -> Option<&str> {
+    text.strip_prefix('"')?.strip_suffix('"')
+}
+
+// The key goes to the last method, the last port or the schema, whichever
+// was opened last.
+fn set(schema: &mut Schema, key: &str, value: Value) -> Result<(), String> {
+    let port = schema.ports.last_mut();
+    let in_method = port.as_ref().is_some_and(|port| !port.methods.is_empty());
+    match (key, value, port) {
+        (key, value, Some(port)) if in_method => {
+            let method = port.methods.last_mut().expect("in_method");
+            match (key, value) {
+                ("name", Value::Text(name)) => method.name = name,
+                ("returns", Value::Text(returns)) => method.returns = returns,
+                ("noop", Value::Text(noop)) => method.noop = Some(noop),
+                ("params", Value::List(params)) => {
+                    method.params = params
+                        .iter()
+                        .map(|param| {
+                            param
+                                .split_once(':')
+                                .map(|(name, ty)| (name.trim().to_string(), ty.trim().to_string()))
+                                .ok_or_else(|| format!("expected \"name: Type\", got {param:?}"))
+                        })
+                        .collect::<Result<_, _>>()?;
+                }
+                (key, _) => return Err(format!("a method has no {key:?}")),
+            }
+        }
+        ("name", Value::Text(name), Some(port)) => port.name = name,
+        ("doc", Value::Text(doc), Some(port)) => port.doc = Some(doc),
+        ("extends", Value::Text(extends), Some(port)) => port.extends = Some(extends),
+        (key, _, Some(_)) => return Err(format!("a port has no {key:?}")),
+        ("error", Value::Text(error), None) => schema.error = error,
+        ("imports", Value::List(imports), None) => schema.imports = imports,
+        (key, _, None) => return Err(format!("the schema has no {key:?}")),
+    }
+    Ok(())
+}
+
+// --- Writing ports::generated ---
+
+fn generate(schema: &Schema) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "// Generated by build.rs from {SCHEMA}: edit that instead."
+    );
+    for import in &schema.imports {
+        let _ = writeln!(out, "use {import};");
+    }
+    out.push_str("use std::sync::{Mutex, PoisonError};\n");
+
+    for port in &schema.ports {
+        out.push('\n');
+        if let Some(doc) = &port.doc {
+            let _ = writeln!(out, "// {doc}");
+        }
+        let extends = port
+            .extends
+            .as_ref()
+            .map_or(String::new(), |parent| format!(": {parent}"));
+        let _ = writeln!(out, "pub trait {}{extends} {{", port.name);
+        for method in &port.methods {
+            let _ = writeln!(out, "    {};", signature(schema, method, ""));
+        }
+        out.push_str("}\n");
+    }
+
+    out.push_str(
+        "
+// Notes every call made through it, then hands the call to `inner`.
+pub struct Spy<I> {
+    inner: I,
+    calls: Mutex<Vec<String>>,
+}
+
+impl<I> Spy<I> {
+    pub fn new(inner: I) -> Self {
+        Self {
+            inner,
+            calls: Mutex::new(Vec::new()),
+        }
+    }
+
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    // Oldest first, as \"method(argument, ...)\", the arguments in Debug
+    // form, and `..` for those that have none.
+    pub fn calls(&self) -> Vec<String> {
+        self.calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn record(&self, call: String) {
+        self.calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(call);
+    }
+}
+",
+    );
+    for port in &schema.ports {
+        let _ = writeln!(out, "\nimpl<I: {0}> {0} for Spy<I> {{", port.name);
+        for (n, method) in port.methods.iter().enumerate() {
+            if n > 0 {
+                out.push('\n');
+            }
+            let shown: Vec<String> = method
+                .params
+                .iter()
+                .map(|(name, ty)| {
+                    if ty.contains("dyn ") {
+                        "..".to_string()
+                    } else {
+                        format!("{{{name}:?}}")
+                    }
+                })
+                .collect();
+            let names: Vec<&str> = method
+                .params
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect();
+            let _ = writeln!(out, "    {} {{", signature(schema, method, ""));
+            let call = format!("{}({})", method.name, shown.join(", "));
+            // format! only when there is an argument to show.
+            let call = if call.contains('{') {
+                format!("format!(\"{call}\")")
+            } else {
+                format!("\"{call}\".to_string()")
+            };
+            let _ = writeln!(out, "        self.record({call});");
+            let _ = writeln!(
+                out,
+                "        self.inner.{}({})",
+                method.name,
+                names.join(", ")
+            );
+            out.push_str("    }\n");
+        }
+        out.push_str("}\n");
+    }
+
+    out.push_str(
+        "
+// Does nothing, and says it went well.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Noop;
+",
+    );
+    for port in &schema.ports {
+        let _ = writeln!(out, "\nimpl {} for Noop {{", port.name);
+        for (n, method) in port.methods.iter().enumerate() {
+            if n > 0 {
+                out.push('\n');
+            }
+            let answer = match (&method.noop, method.returns.as_str()) {
+                (Some(noop), _) => noop.as_str(),
+                (None, "()") => "()",
+                (None, _) => "Default::default()",
+            };
+            let _ = writeln!(out, "    {} {{", signature(schema, method, "_"));
+            let _ = writeln!(out, "        Ok({answer})");
+            out.push_str("    }\n");
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+// "fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError>", each
+// parameter name after `prefix`.
+fn signature(schema: &Schema, method: &Method, prefix: &str) -> String {
+    let mut params = vec!["&self".to_string()];
+    params.extend(
+        method
+            .params
+            .iter()
+            .map(|(name, ty)| format!("{prefix}{name}: {ty}")),
+    );
+    format!(
+        "fn {}({}) -> Result<{}, {}>",
+        method.name,
+        params.join(", "),
+        method.returns,
+        schema.error
+    )
+}
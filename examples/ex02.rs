@@ -1,7 +1,7 @@
 // test only
 // cargo test --example ex02
 
-mod domain {
+pub mod domain {
     use std::fmt;
 
     #[derive(Debug, Clone)]
@@ -22,7 +22,7 @@ mod domain {
     }
 }
 
-mod ports {
+pub mod ports {
     use crate::domain::{Order, OrderError};
 
     pub trait OrderNotifier {
@@ -30,7 +30,7 @@ mod ports {
     }
 }
 
-mod application {
+pub mod application {
     use crate::domain::{Order, OrderError};
     use crate::ports::OrderNotifier;
 
@@ -64,18 +64,18 @@ mod tests {
     use crate::application::OrderService;
     use crate::domain::{Order, OrderError};
     use crate::ports::OrderNotifier;
+    use hexa_lite::testing::stubs::OkNotifier;
 
-    struct TestNotifier;
-
-    impl OrderNotifier for TestNotifier {
-        fn process(&self, _order: &Order) -> Result<(), OrderError> {
-            Ok(())
+    // The stub is shared by every example; the port is ours, so is the impl.
+    impl OrderNotifier for OkNotifier {
+        fn process(&self, order: &Order) -> Result<(), OrderError> {
+            self.notify(order)
         }
     }
 
     #[test]
     fn process_order_successfully() {
-        let mut service = OrderService::new(TestNotifier);
+        let mut service = OrderService::new(OkNotifier);
 
         let order = service.process_order(4999).unwrap();
 
@@ -1,6 +1,6 @@
 // Architectural template: one port, one adapter, one application service
 
-mod domain {
+pub mod domain {
     #[derive(Debug, Clone, PartialEq)]
     pub struct Stuff {
         pub value: u32,
@@ -10,7 +10,7 @@ mod domain {
     pub enum StuffError {}
 }
 
-mod ports {
+pub mod ports {
     use crate::domain::{Stuff, StuffError};
 
     pub trait StuffHandler {
@@ -18,7 +18,7 @@ mod ports {
     }
 }
 
-mod adapters {
+pub mod adapters {
     use crate::domain::{Stuff, StuffError};
     use crate::ports::StuffHandler;
 
@@ -31,7 +31,7 @@ mod adapters {
     }
 }
 
-mod application {
+pub mod application {
     use crate::domain::{Stuff, StuffError};
     use crate::ports::StuffHandler;
 
@@ -57,18 +57,18 @@ mod tests {
     use crate::application::StuffService;
     use crate::domain::{Stuff, StuffError};
     use crate::ports::StuffHandler;
+    use hexa_lite::testing::stubs::OkHandler;
 
-    struct TestHandler;
-
-    impl StuffHandler for TestHandler {
-        fn handle(&self, _stuff: &Stuff) -> Result<(), StuffError> {
-            Ok(())
+    // The stub is shared by every example; the port is ours, so is the impl.
+    impl StuffHandler for OkHandler {
+        fn handle(&self, stuff: &Stuff) -> Result<(), StuffError> {
+            OkHandler::handle(self, stuff)
         }
     }
 
     #[test]
     fn process_stuff_successfully() {
-        let service = StuffService::new(&TestHandler);
+        let service = StuffService::new(&OkHandler);
 
         let stuff = service.process(42).unwrap();
 
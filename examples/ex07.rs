@@ -39,6 +39,7 @@ mod domain {
     pub struct Money(pub u32); // stored in cents
 
     #[derive(Debug, Clone)]
+    #[allow(dead_code)] // name is business data, none of the adapters below read it
     pub struct LineItem {
         pub name: String,
         pub price: Money,
@@ -57,6 +58,7 @@ mod domain {
     // Domain-level errors describe business failures,
     // not technical ones (no SQL errors, no HTTP codes).
     #[derive(Debug)]
+    #[allow(dead_code)] // the simulated adapters below never fail
     pub enum OrderError {
         InvalidOrder,
         PaymentFailed,
@@ -0,0 +1,71 @@
+// cargo run --example ex08
+
+// Until now main() called the use case directly.
+// Here main() plays the part of a message producer: it pushes PlaceOrder
+// commands on a channel and a consumer thread drives the hexagon.
+//
+// This is the "driving side" of the hexagon. The consumer is an adapter like
+// any other: it depends on the PlaceOrderUseCase input port, and the
+// application has no idea its commands come from a queue.
+//
+// From now on the hexagon itself comes from the library (src/), so the
+// examples only contain what they are about.
+use hexa_lite::adapters::queue::{self, QueueMessage};
+use hexa_lite::prelude::*;
+use std::sync::mpsc;
+use std::thread;
+
+fn main() {
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+
+    let carts = vec![
+        vec![LineItem {
+            name: "Rust Book".to_string(),
+            price: Money(4999),
+        }],
+        vec![], // an empty cart: the domain rejects it
+        vec![
+            LineItem {
+                name: "Keyboard".to_string(),
+                price: Money(12999),
+            },
+            LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            },
+        ],
+    ];
+
+    let (command_tx, command_rx) = mpsc::channel();
+    let (response_tx, response_rx) = mpsc::channel();
+
+    // A scope because the service borrows its adapters from main().
+    thread::scope(|scope| {
+        let service = OrderService::new(&repo, &payment, &sender);
+        let consumer = queue::spawn_scoped(scope, service, command_rx, response_tx);
+
+        for items in carts {
+            println!("Producer: sending a cart of {} item(s)", items.len());
+            command_tx
+                .send(QueueMessage::Place(PlaceOrder {
+                    items,
+                    placed_at: None,
+                }))
+                .unwrap();
+        }
+        println!("Producer: asking the consumer to stop\n");
+        command_tx.send(QueueMessage::Shutdown).unwrap();
+
+        consumer.join().unwrap();
+    });
+
+    println!("\nResponses:");
+    for response in response_rx {
+        match response {
+            Ok(order) => println!("  Order {} placed, total {}", order.id, order.total),
+            Err(e) => println!("  Error: {e}"),
+        }
+    }
+}
@@ -0,0 +1,65 @@
+// cargo run --example ex09
+
+// One repository, two application services, one thread.
+//
+// Think of a desktop app: an "order form" places orders while an "order list"
+// panel browses them. No threads, so Arc<Mutex<..>> would be overkill:
+// Rc<RefCell<..>> is enough, wrapped in SharedRepository. (Two services
+// borrowing from main() could share a plain &repo; handles are for when
+// they cannot borrow, and must own what they hold.)
+//
+// The classic RefCell pitfall is holding a borrow while someone else writes:
+// it panics at runtime. SharedRepository makes that impossible to write by
+// accident: the only way to reach the repository is with_repo(|repo| ...),
+// and the borrow ends with the closure.
+use hexa_lite::adapters::shared::SharedRepository;
+use hexa_lite::application::OrderBrowser;
+use hexa_lite::prelude::*;
+
+fn main() {
+    let shared = SharedRepository::new(InMemoryOrderRepository::new());
+
+    // Each service gets its own handle on the same repository.
+    let form_handle = shared.clone();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut order_form = OrderService::new(&form_handle, &payment, &sender);
+    let order_list = OrderBrowser::new(&shared);
+
+    println!("--- Placing a first order ---\n");
+    order_form
+        .place_order(vec![LineItem {
+            name: "Rust Book".to_string(),
+            price: Money(4999),
+        }])
+        .unwrap();
+
+    println!("\n--- The list panel refreshes ---\n");
+    for order in order_list.list().unwrap() {
+        println!("  {} {}", order.id, order.total);
+    }
+
+    println!("\n--- Placing a second order ---\n");
+    order_form
+        .place_order(vec![
+            LineItem {
+                name: "Keyboard".to_string(),
+                price: Money(12999),
+            },
+            LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            },
+        ])
+        .unwrap();
+
+    println!("\n--- The user searches for \"mouse\" ---\n");
+    for order in order_list.search("mouse").unwrap() {
+        println!("  {} {}", order.id, order.total);
+    }
+
+    // The guarded accessor: a read borrow that cannot outlive the closure.
+    let count = shared.with_repo(|repo| repo.list().map(|orders| orders.len()));
+    println!("\n  {} order(s) in the repository", count.unwrap().unwrap());
+    println!("  {} handle(s) on it", shared.handles());
+}
@@ -0,0 +1,77 @@
+// cargo run --example ex10
+
+// Typestate: let the compiler reject invalid flows.
+//
+// Until now "charge the order" took a Money and "notify" took an &Order.
+// Nothing stopped a use case from charging a cart nobody had validated.
+// Here the cart is an OrderDraft and only OrderDraft::confirm() can turn it
+// into a ConfirmedOrder. The payment and notification ports take a
+// &ConfirmedOrder: passing a draft is a type error, not a runtime bug.
+//
+// Try it: in main(), replace `&order` with `&draft` in the charge_confirmed
+// call and read what rustc says.
+use hexa_lite::adapters::in_memory::InMemoryDraftRepository;
+use hexa_lite::application::CheckoutService;
+use hexa_lite::domain::{ConfirmPolicy, OrderDraft, StoredOrder};
+use hexa_lite::ports::ChargeConfirmed;
+use hexa_lite::prelude::*;
+
+fn main() {
+    println!("--- The typestate on its own ---\n");
+    let mut draft = OrderDraft::new(OrderId(100));
+    draft.add_item(LineItem {
+        name: "Sticker".to_string(),
+        price: Money(150),
+    });
+
+    // At least $5.00 per order.
+    let policy = ConfirmPolicy {
+        min_total: Money(500),
+        ..ConfirmPolicy::default()
+    };
+
+    // A failed confirmation hands the draft back: nothing is lost.
+    let mut draft = match draft.confirm(&policy) {
+        Ok(_) => unreachable!("$1.50 is below the minimum"),
+        Err((draft, error)) => {
+            println!(
+                "  Rejected: {error}, the cart still has {} item(s)",
+                draft.items().len()
+            );
+            draft
+        }
+    };
+    draft.add_item(LineItem {
+        name: "Rust Book".to_string(),
+        price: Money(4999),
+    });
+    let order = draft.confirm(&policy).unwrap();
+    MockPaymentGateway::new().charge_confirmed(&order).unwrap();
+
+    println!("\n--- The same, through CheckoutService ---\n");
+    let mut repo = InMemoryDraftRepository::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut checkout = CheckoutService::new(&mut repo, &payment, &sender);
+
+    let id = checkout.start_draft().unwrap();
+    checkout
+        .add_item(
+            id,
+            LineItem {
+                name: "Keyboard".to_string(),
+                price: Money(12999),
+            },
+        )
+        .unwrap();
+    let order = checkout.confirm(id, &policy).unwrap();
+
+    if let Some(StoredOrder::Confirmed(stored)) = checkout.get(id).unwrap() {
+        println!(
+            "\n  Stored as confirmed: {} {}",
+            stored.id(),
+            stored.status()
+        );
+    }
+    println!("  Total charged: {}", order.total());
+}
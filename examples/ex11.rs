@@ -0,0 +1,57 @@
+// cargo run --example ex11
+
+// Restarts.
+//
+// OrderService keeps one thing in memory that no repository knows about:
+// the next order id. Drop the service and start a new one, and it hands
+// out id 1 again, on top of an order that already has it.
+//
+// ServiceState is that memory, made explicit. The service gives it away
+// with state(), a StateStore keeps it (here a file), and restore() builds
+// a service that carries on where the previous one stopped.
+use hexa_lite::adapters::state_file::FileStateStore;
+use hexa_lite::ports::StateStore;
+use hexa_lite::prelude::*;
+
+fn cable(cents: u32) -> Vec<LineItem> {
+    vec![LineItem {
+        name: "USB cable".to_string(),
+        price: Money(cents),
+    }]
+}
+
+fn main() {
+    let path = std::env::temp_dir().join("hexa_lite_ex11.state");
+    let _ = std::fs::remove_file(&path);
+    let store = FileStateStore::new(&path);
+
+    // The repository stands for the database: it outlives both "processes".
+    let repo = InMemoryOrderRepository::new();
+
+    println!("--- First run ---\n");
+    {
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service = match store.load_state().unwrap() {
+            Some(state) => OrderService::restore(&repo, &payment, &sender, &state).unwrap(),
+            None => OrderService::new(&repo, &payment, &sender),
+        };
+        for cents in [999, 1499, 1999] {
+            let order = service.place_order(cable(cents)).unwrap();
+            println!("  Placed {}\n", order.id);
+        }
+        store.save_state(&service.state()).unwrap();
+        println!("  State saved to {}", store.path().display());
+    }
+
+    println!("\n--- Second run ---\n");
+    let state = store.load_state().unwrap().expect("saved by the first run");
+    println!("  Restored: next id is {}\n", state.next_id);
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::restore(&repo, &payment, &sender, &state).unwrap();
+    let order = service.place_order(cable(2499)).unwrap();
+    println!("  Placed {} (not #000001 again)", order.id);
+
+    let _ = std::fs::remove_file(&path);
+}
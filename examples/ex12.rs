@@ -0,0 +1,80 @@
+// cargo test --example ex12 --features wasm
+// ./scripts/check_wasm.sh
+
+// The hexagon in a browser.
+//
+// Nothing in the domain, the ports or the application knows what it runs
+// on, so the same code builds for wasm32-unknown-unknown. Only the edge
+// changes: no file, no thread, no system clock, and the "driving adapter"
+// is a JavaScript call instead of a CLI line or an HTTP request.
+//
+// place_order_json is that call. It takes the cart as JSON and answers in
+// JSON, reusing the HTTP adapter's parsing and error mapping:
+//
+//     place_order_json(r#"[{"name":"Keyboard","price_cents":12999}]"#)
+//     -> {"id":1,"status":"Paid","total_cents":12999}
+//
+// To export it to JavaScript, add wasm-bindgen to the dependencies, put
+// #[wasm_bindgen] on the function and build with wasm-pack. This crate
+// keeps zero dependencies, so the attribute is left out here.
+use hexa_lite::adapters::http::{HttpAdapter, HttpRequest};
+use hexa_lite::application::OrderBrowser;
+use hexa_lite::facade::DefaultFacade;
+use hexa_lite::ports::ServiceState;
+use hexa_lite::prelude::*;
+use std::cell::RefCell;
+
+// The page is the process: orders live as long as it stays open.
+thread_local! {
+    static ORDERS: RefCell<InMemoryOrderRepository> = RefCell::new(InMemoryOrderRepository::new());
+}
+
+pub fn place_order_json(items_json: &str) -> String {
+    ORDERS.with_borrow_mut(|repository| {
+        // A new service per call; restore() picks the next free id.
+        let fresh = ServiceState {
+            next_id: 1,
+            open_drafts: Vec::new(),
+        };
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let service = match OrderService::restore(repository, &payment, &sender, &fresh) {
+            Ok(service) => service,
+            Err(_) => return r#"{"error":"StorageFailed"}"#.to_string(),
+        };
+        let request = HttpRequest {
+            method: "POST".to_string(),
+            path: "/orders".to_string(),
+            headers: Vec::new(),
+            body: format!(r#"{{"items":{items_json}}}"#).into_bytes(),
+        };
+        let facade = DefaultFacade::new(service, OrderBrowser::new(&*repository));
+        HttpAdapter::new(facade).handle(&request).body
+    })
+}
+
+// Run natively with `cargo test`; under wasm-bindgen-test they would use
+// #[wasm_bindgen_test] instead of #[test], with the same bodies.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn orders_placed_from_javascript_get_consecutive_ids() {
+        let first = place_order_json(r#"[{"name":"Keyboard","price_cents":12999}]"#);
+        let second = place_order_json(r#"[{"name":"Mouse","price_cents":2500}]"#);
+
+        assert_eq!(first, r#"{"id":1,"status":"Paid","total_cents":12999}"#);
+        assert_eq!(second, r#"{"id":2,"status":"Paid","total_cents":2500}"#);
+    }
+
+    #[test]
+    fn malformed_cart_gets_an_error_object() {
+        for cart in [r#"[]"#, r#"[{"name":"Keyboard"}]"#, r#"]"#, ""] {
+            assert_eq!(
+                place_order_json(cart),
+                r#"{"error":"InvalidOrder","code":"ORD-001"}"#
+            );
+        }
+    }
+}
@@ -0,0 +1,38 @@
+// cargo run --example ex13
+// HEXLITE_SENDER=webhook HEXLITE_WEBHOOK_URL=https://example.test/hook cargo run --example ex13
+
+// The composition root, seen from outside.
+//
+// Only the composition root knows which adapter sits behind which port; the
+// application never does. That knowledge is worth seeing: build_adapters
+// records it in a Wiring, and the first thing this program does is print
+// it, before a single order is placed. The last thing it does is shut
+// them down, each in its turn, and print how that went.
+use hexa_lite::composition::{EnvConfig, build_adapters, build_service};
+use hexa_lite::prelude::*;
+
+fn main() {
+    let loaded = EnvConfig::load().and_then(|config| build_adapters(&config));
+    let mut adapters = match loaded {
+        Ok(adapters) => adapters,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(2);
+        }
+    };
+
+    println!("--- Wiring ---\n");
+    println!("{}", adapters.wiring);
+
+    println!("\n--- Placing an order ---\n");
+    let order = build_service(&adapters)
+        .place_order(vec![LineItem {
+            name: "Rust Book".to_string(),
+            price: Money(4999),
+        }])
+        .unwrap();
+    println!("\n  {} is {}", order.id, order.status);
+
+    println!("\n--- Shutdown ---\n");
+    print!("{}", adapters.shutdown_coordinator().run());
+}
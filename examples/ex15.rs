@@ -0,0 +1,138 @@
+// cargo run --example ex15
+// cargo test --example ex15
+
+// The hexagon, assembled one piece at a time.
+//
+// The article draws the hexagon all at once: domain in the middle, ports
+// around it, adapters at the edge. Here it is built in the order one would
+// write it, and each step is checked before the next one is added:
+//
+//   1. only the domain: an Order is plain data with rules, nothing to plug
+//   2. a repository: orders survive the function that made them
+//   3. a payment gateway: OrderService can now take money
+//   4. a notification: the customer hears about it
+//
+// Every checkpoint prints PASS or FAIL; the summary comes last. Nothing
+// here needs the outside world, so the test below runs the same tutorial,
+// headless, and asks for every checkpoint to pass.
+use hexa_lite::adapters::{Console, SharedBuffer};
+use hexa_lite::ports::ChargeLog;
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use hexa_lite::testing::stubs::OkSender;
+use hexa_lite::tutorial::TutorialRunner;
+
+fn cart() -> Vec<LineItem> {
+    vec![
+        LineItem {
+            name: "Rust Book".to_string(),
+            price: Money(4999),
+        },
+        LineItem {
+            name: "Keyboard".to_string(),
+            price: Money(12999),
+        },
+    ]
+}
+
+fn tutorial(runner: &mut TutorialRunner) {
+    runner.step("Only the domain");
+    runner.note("No trait, no adapter: Order::new checks the cart and adds it up.");
+    runner.checkpoint("a cart becomes an order with its total", || {
+        let order = Order::new(OrderId(1), cart()).unwrap();
+        assert_order(&order)
+            .has_total_cents(17998)
+            .has_status(OrderStatus::Placed);
+        true
+    });
+    runner.checkpoint("an empty cart is refused", || {
+        matches!(
+            Order::new(OrderId(1), vec![]),
+            Err(OrderError::InvalidOrder)
+        )
+    });
+    runner.checkpoint("a paid order cannot be paid twice", || {
+        let mut order = Order::new(OrderId(1), cart()).unwrap();
+        order.mark_paid().is_ok() && order.mark_paid().is_err()
+    });
+
+    runner.step("Plugging in a repository");
+    runner.note(
+        "The first port, in two halves: OrderReader and OrderWriter, fulfilled by a HashMap.",
+    );
+    let repo = InMemoryOrderRepository::new().with_console(runner.console());
+    runner.checkpoint("a saved order is found again", || {
+        let order = Order::new(OrderId(7), cart()).unwrap();
+        repo.save(&order).unwrap();
+        repo.find(OrderId(7)).unwrap() == Some(order)
+    });
+    runner.checkpoint("an unknown id finds nothing", || {
+        repo.find(OrderId(8)).unwrap().is_none()
+    });
+
+    runner.step("Plugging in a payment gateway");
+    runner.note("OrderService needs a repository, a gateway and a sender:");
+    runner.note("a sender that does nothing stands in until step 4.");
+    let payment = MockPaymentGateway::new().with_console(runner.console());
+    let placed = {
+        let mut service = OrderService::new(&repo, &payment, &OkSender);
+        service.place_order(cart())
+    };
+    let placed_id = placed.as_ref().map(|order| order.id).ok();
+    runner.checkpoint("place_order charges and stores a paid order", || {
+        let order = placed.unwrap();
+        assert_order(&order).has_status(OrderStatus::Paid);
+        repo.find(order.id).unwrap() == Some(order)
+    });
+    runner.checkpoint("the gateway charged that order its total", || {
+        let charges = payment.charges().unwrap();
+        charges.len() == 1
+            && Some(charges[0].order_id) == placed_id
+            && charges[0].amount == Money(17998)
+    });
+
+    runner.step("Plugging in a notification");
+    let receipts = SharedBuffer::new();
+    let sender = ConsoleSender::new().with_console(Console::to(receipts.clone()));
+    let order = OrderService::new(&repo, &payment, &sender).place_order(cart());
+    runner.note("What the customer receives:");
+    runner.note(&receipts.contents());
+    runner.checkpoint("the customer gets a confirmation", || {
+        let order = order.unwrap();
+        receipts
+            .contents()
+            .contains(&format!("Order {} confirmed", order.id))
+    });
+    runner.checkpoint("the receipt shows the total", || {
+        receipts.contents().contains("$179.98")
+    });
+}
+
+fn main() {
+    let mut runner = TutorialRunner::new();
+    tutorial(&mut runner);
+    println!("\n--- Summary ---\n");
+    println!("{}", runner.summary());
+    if !runner.all_passed() {
+        std::process::exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_checkpoint_passes() {
+        let transcript = SharedBuffer::new();
+        let mut runner = TutorialRunner::new().with_console(Console::to(transcript.clone()));
+
+        tutorial(&mut runner);
+
+        assert!(runner.all_passed(), "{}", runner.summary());
+        assert_eq!(runner.checkpoints().len(), 9);
+        let steps = runner.checkpoints().iter().map(|c| c.step).max();
+        assert_eq!(steps, Some(4));
+        assert!(!transcript.contents().contains("[FAIL]"));
+    }
+}
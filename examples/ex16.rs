@@ -0,0 +1,39 @@
+// cargo run --example ex16
+// cargo run --example ex16 -- target/hexagon.mmd
+
+// The hexagon, drawn by the code itself.
+//
+// Every port says what it is (ports::PortInfo), the Wiring says which
+// adapter is plugged into it: composition::hexagon_diagram turns both into
+// a Mermaid flowchart. This program wires the adapters the environment asks
+// for (see ex13), adds the driving adapter it would be served by, and
+// writes the picture to a file, hexagon.mmd unless another path is given.
+// Render it with mmdc, or paste it in a ```mermaid block.
+use hexa_lite::composition::{EnvConfig, build_adapters, hexagon_diagram};
+use hexa_lite::ports::PlaceOrderUseCase;
+
+fn main() {
+    let path = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "hexagon.mmd".to_string());
+    let loaded = EnvConfig::load().and_then(|config| build_adapters(&config));
+    let mut adapters = match loaded {
+        Ok(adapters) => adapters,
+        Err(error) => {
+            eprintln!("{error}");
+            std::process::exit(2);
+        }
+    };
+    adapters
+        .wiring
+        .bind::<dyn PlaceOrderUseCase>("HttpAdapter")
+        .expect("nothing else drives the use case");
+
+    let diagram = hexagon_diagram(&adapters.wiring);
+    if let Err(error) = std::fs::write(&path, format!("{diagram}\n")) {
+        eprintln!("cannot write {path}: {error}");
+        std::process::exit(1);
+    }
+    println!("{diagram}\n");
+    println!("--- Written to {path} ---");
+}
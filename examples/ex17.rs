@@ -0,0 +1,234 @@
+// cargo run --example ex17
+
+// A network partition, in the middle of a batch of orders.
+//
+// The Stripe gateway, the SQL executor and the webhook sender share one
+// NetworkConditions: some latency, one call in twenty lost on the way, and,
+// a third of the way into the batch, a partition that cuts all three off
+// until it heals two thirds in. Nothing is retried by hand while it lasts:
+// - a charge that cannot reach Stripe is deferred, the order saved as
+//   PaymentPending (when the database can be reached at all)
+// - a confirmation that cannot be posted waits in the outbox
+// - a cart the shop could not take is handed back to the customer, who
+//   places it again later
+//
+// Once healed, a few rounds of settlement, outbox dispatch, resubmitted
+// carts and reconciliation (refunding what was charged for an order never
+// saved) bring every order to a consistent state. The summary says so, or
+// says which order still needs a look.
+//
+// The repository, the gateway and the webhook are each wrapped in Timed,
+// which records how long every call took (the simulated delay, nothing
+// sleeps) in a histogram; a table of them follows the run.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::NetworkConditions;
+use hexa_lite::adapters::external::StripePaymentGateway;
+use hexa_lite::adapters::metrics::InMemoryMetrics;
+use hexa_lite::adapters::offline::{InMemoryPendingCharges, OfflineCapablePaymentGateway};
+use hexa_lite::adapters::outbox::{DispatchReport, InMemoryDeadLetters, OutboxSender, RetryPolicy};
+use hexa_lite::adapters::sql::{FakeExecutor, SqlOrderRepository};
+use hexa_lite::adapters::timing::Timed;
+use hexa_lite::adapters::webhook::WebhookSender;
+use hexa_lite::application::Reconciliation;
+use hexa_lite::composition::{ShutdownCoordinator, ShutdownPriority};
+use hexa_lite::ports::{PaymentGateway, PendingCharges};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use hexa_lite::testing::OrderGenerator;
+use std::sync::Arc;
+use std::time::Duration;
+
+const SEED: u64 = 17;
+const BATCH: usize = 24;
+const MAX_ROUNDS: usize = 20;
+
+// The dead letters live in memory and never refuse one.
+fn dispatch<S: Sender>(outbox: &OutboxSender<'_, S, SteppingClock>) -> DispatchReport {
+    outbox
+        .run_dispatcher(outbox.clock().now())
+        .expect("the dead letters live in memory")
+}
+
+fn main() {
+    let network = Arc::new(
+        NetworkConditions::new(SEED)
+            .with_latency(Duration::from_millis(20)..=Duration::from_millis(150))
+            .with_failure_probability(Percent::try_from(5).expect("5 is a percentage")),
+    );
+
+    let metrics = InMemoryMetrics::new();
+    let waited = || network.stats().delay;
+
+    // Creating the table goes over the network too: try until it gets there.
+    let repo = loop {
+        let executor = FakeExecutor::new().with_network(network.clone());
+        if let Ok(repo) = SqlOrderRepository::new(executor) {
+            break repo.with_console(Console::silent());
+        }
+    };
+    let repo = Timed::new(repo, &metrics, "repository", waited);
+    let stripe = StripePaymentGateway::new()
+        .with_network(network.clone())
+        .with_console(Console::silent());
+    let pending = InMemoryPendingCharges::new();
+    let payment =
+        OfflineCapablePaymentGateway::new(stripe.clone(), &pending).with_console(Console::silent());
+    let payment = Timed::new(payment, &metrics, "payment", waited);
+    let webhook = WebhookSender::new("https://hooks.example.com/orders")
+        .expect("a valid webhook URL")
+        .with_network(network.clone())
+        .with_console(Console::silent());
+    let dead_letters = InMemoryDeadLetters::new();
+    let mut outbox = OutboxSender::new(
+        Timed::new(webhook, &metrics, "webhook", waited),
+        SteppingClock::starting_at(Timestamp(1_700_000_000)),
+        &dead_letters,
+    )
+    .with_policy(RetryPolicy::new(
+        Duration::from_secs(1),
+        Duration::from_secs(8),
+        20,
+    ))
+    .with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &outbox).with_pending_charges(&pending);
+
+    println!("--- A batch of {BATCH} orders ---\n");
+    let mut generator = OrderGenerator::new(SEED);
+    let mut handed_back = Vec::new();
+    for i in 0..BATCH {
+        if i == BATCH / 3 {
+            network.partitioned();
+            println!("  ~~ partitioned ~~");
+        }
+        if i == 2 * BATCH / 3 {
+            network.heal();
+            println!("  ~~ healed ~~");
+        }
+        let cart = generator.next_cart();
+        match service.place_order(cart.clone()) {
+            Ok(order) => println!("  order {}: {:?}", order.id, order.status),
+            Err(error) => {
+                println!("  cart {}: handed back ({error})", i + 1);
+                handed_back.push(cart);
+            }
+        }
+        outbox.clock().advance_secs(1);
+        dispatch(&outbox);
+    }
+
+    println!("\n--- Catching up ---\n");
+    let mut refunded = 0;
+    let mut rounds = 0;
+    loop {
+        rounds += 1;
+        let settled = service
+            .settle_pending()
+            .expect("the pending charges live in memory");
+        outbox.clock().advance_secs(8);
+        let dispatched = dispatch(&outbox);
+        let mut placed_again = 0;
+        for cart in std::mem::take(&mut handed_back) {
+            match service.place_order(cart.clone()) {
+                Ok(_) => placed_again += 1,
+                Err(_) => handed_back.push(cart),
+            }
+        }
+        let mut orphans = 0;
+        if let Ok(report) = Reconciliation::new(&repo, &stripe).reconcile() {
+            for charge in report.orphan_charges {
+                orphans += 1;
+                if stripe.refund_for(charge.order_id, charge.amount).is_ok() {
+                    refunded += 1;
+                    orphans -= 1;
+                }
+            }
+        }
+        println!(
+            "  round {rounds}: {} settled, {} sent, {placed_again} placed again, {refunded} refunded so far",
+            settled.paid.len(),
+            dispatched.sent,
+        );
+
+        let done = handed_back.is_empty()
+            && pending.pending().is_ok_and(|charges| charges.is_empty())
+            && outbox.entries().is_empty()
+            && orphans == 0;
+        if done || rounds == MAX_ROUNDS {
+            break;
+        }
+    }
+
+    println!("\n--- Latency, per port ---\n");
+    println!(
+        "  {:<10}  {:>5}  {:>8}  {:>8}  {:>8}",
+        "port", "calls", "mean ms", "p50 ms", "p99 ms"
+    );
+    let ms = |value: Option<f64>| value.map_or("-".to_string(), |ms| format!("{ms:.1}"));
+    for (port, latency) in metrics.histogram_snapshot() {
+        println!(
+            "  {port:<10}  {:>5}  {:>8}  {:>8}  {:>8}",
+            latency.count,
+            ms(latency.mean()),
+            ms(latency.p50),
+            ms(latency.p99)
+        );
+    }
+
+    println!("\n--- Summary ---\n");
+    let orders = loop {
+        if let Ok(orders) = repo.list() {
+            break orders;
+        }
+    };
+    let report = loop {
+        if let Ok(report) = Reconciliation::new(&repo, &stripe).reconcile() {
+            break report;
+        }
+    };
+    let delivered = outbox.inner().inner().delivered();
+    let confirmed = |id: OrderId| {
+        let prefix = format!("{{\"order_id\":{},", id.0);
+        delivered
+            .iter()
+            .any(|request| request.body.starts_with(&prefix))
+    };
+    let paid: Vec<&Order> = orders
+        .iter()
+        .filter(|order| order.status == OrderStatus::Paid)
+        .collect();
+    let unconfirmed = paid.iter().filter(|order| !confirmed(order.id)).count();
+    let stats = network.stats();
+
+    println!("  carts:                 {BATCH}");
+    println!("  orders paid:           {}", paid.len());
+    println!("  still payment pending: {}", orders.len() - paid.len());
+    println!("  paid but not charged:  {}", report.missing_charges.len());
+    println!(
+        "  charged the wrong sum: {}",
+        report.amount_mismatches.len()
+    );
+    println!("  paid, not confirmed:   {unconfirmed}");
+    println!("  dead letters:          {}", dead_letters.letters().len());
+    println!("  orphan charges:        {refunded} refunded");
+    println!(
+        "  network:               {} calls, {} refused by the partition, {} lost, {:?} waited",
+        stats.calls, stats.partitioned, stats.lost, stats.delay
+    );
+
+    let consistent = paid.len() == BATCH
+        && report.is_consistent()
+        && unconfirmed == 0
+        && dead_letters.letters().is_empty();
+    if consistent {
+        println!("\nEvery order got there: charged once, saved as Paid, confirmed.");
+    } else {
+        println!("\nSome orders need a look: see above.");
+    }
+
+    // Whatever the outbox still holds gets one last try before exiting.
+    println!("\n--- Shutdown ---\n");
+    let report = ShutdownCoordinator::new()
+        .register("outbox", ShutdownPriority::FlushSenders, &mut outbox)
+        .run();
+    print!("{report}");
+}
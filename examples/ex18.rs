@@ -0,0 +1,103 @@
+// cargo run --example ex18
+
+// Extending place_order without editing it.
+//
+// Two hooks, plugged into the service from the outside:
+// - FraudFlags notes every order over a threshold before it is charged,
+//   for someone to look at later. It does not stop them: stopping an order
+//   is the ApprovalPolicy's job, a hook only watches.
+// - Timings measures how long each order took from the charge to the save.
+//
+// CompositeHooks hands both to one service. OrderService itself is the
+// same as in every other example.
+use hexa_lite::adapters::Console;
+use hexa_lite::application::{CompositeHooks, Hooks};
+use hexa_lite::prelude::*;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+struct FraudFlags {
+    threshold: Money,
+    flagged: Mutex<Vec<(OrderId, Money)>>,
+}
+
+impl Hooks for FraudFlags {
+    fn before_charge(&self, order: &Order) -> Result<(), OrderError> {
+        if order.total.0 > self.threshold.0 {
+            self.flagged.lock().unwrap().push((order.id, order.total));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Timings {
+    started: Mutex<HashMap<OrderId, Instant>>,
+    took: Mutex<Vec<(OrderId, Duration)>>,
+}
+
+impl Hooks for Timings {
+    fn before_charge(&self, order: &Order) -> Result<(), OrderError> {
+        self.started
+            .lock()
+            .unwrap()
+            .insert(order.id, Instant::now());
+        Ok(())
+    }
+
+    fn after_save(&self, order: &Order) -> Result<(), OrderError> {
+        if let Some(started) = self.started.lock().unwrap().remove(&order.id) {
+            self.took
+                .lock()
+                .unwrap()
+                .push((order.id, started.elapsed()));
+        }
+        Ok(())
+    }
+
+    fn on_failure(&self, error: &OrderError) -> Result<(), OrderError> {
+        println!("  (timings: an order failed: {error})");
+        Ok(())
+    }
+}
+
+fn main() {
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let fraud = FraudFlags {
+        threshold: Money(100_000),
+        flagged: Mutex::default(),
+    };
+    let timings = Timings::default();
+    let hooks = CompositeHooks::new().with(&fraud).with(&timings);
+    let mut service = OrderService::new(&repo, &payment, &sender).with_hooks(&hooks);
+
+    println!("--- Placing orders ---\n");
+    let carts = [
+        vec![LineItem::new("Mouse", Money(2_500))],
+        vec![LineItem::new("Workstation", Money(349_900))],
+        vec![],
+        vec![
+            LineItem::new("Keyboard", Money(12_999)),
+            LineItem::new("Monitor", Money(119_000)),
+        ],
+    ];
+    for cart in carts {
+        match service.place_order(cart) {
+            Ok(order) => println!("  order {}: {:?}, {}", order.id, order.status, order.total),
+            Err(error) => println!("  refused: {error}"),
+        }
+    }
+
+    println!("\n--- Flagged for review ---\n");
+    for (id, total) in fraud.flagged.lock().unwrap().iter() {
+        println!("  order {id}: {total}");
+    }
+
+    println!("\n--- From charge to save ---\n");
+    for (id, took) in timings.took.lock().unwrap().iter() {
+        println!("  order {id}: {took:?}");
+    }
+}
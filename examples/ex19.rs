@@ -0,0 +1,31 @@
+// cargo run --example ex19
+// printf 'order add Mouse 2500\norder checkout\norder list\n' | cargo run --example ex19
+
+// A shell driving the application through its input ports.
+//
+// adapters::repl only knows the OrderFacade: placing, cancelling, listing,
+// the revenue report and the health check all go through it. Type `help` (or anything else it does
+// not know) for the commands; Ctrl-D, or `quit`, ends the session.
+//
+// Built with `--features ctrl-c`, Ctrl-C stops the command under way
+// rather than the shell.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::repl::Repl;
+use hexa_lite::application::OrderBrowser;
+use hexa_lite::facade::DefaultFacade;
+use hexa_lite::prelude::*;
+use std::io;
+
+fn main() -> io::Result<()> {
+    // The adapters keep quiet: the shell's replies are the only output.
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let service = OrderService::new(&repo, &payment, &sender);
+
+    let repl = Repl::new(DefaultFacade::new(service, OrderBrowser::new(&repo)));
+    #[cfg(all(feature = "ctrl-c", unix))]
+    let repl = repl.with_interrupt(hexa_lite::adapters::ctrl_c::arm);
+    let mut repl = repl;
+    repl.run(io::stdin().lock(), io::stdout().lock())
+}
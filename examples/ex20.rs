@@ -0,0 +1,296 @@
+// cargo run --example ex20
+// cargo test --example ex20
+
+// Moving a big ball of mud into the hexagon, without anyone noticing.
+//
+// `legacy::process_order_legacy` is the function every codebase has: it
+// checks the cart, builds SQL by hand, calls the card processor and writes
+// the confirmation email, all in one go, each step tangled with the next.
+// `migrated` does the same job through the library: OrderService for the
+// rules, SqlOrderRepository for the storage, and two small adapters around
+// what the old code called directly (the card processor, the email).
+//
+// What must not change is what people see: the emails and the messages.
+// testing::characterize runs both on the same carts and demands identical
+// bytes, which is what the test below checks; main prints the transcript.
+// The SQL, on the other hand, did change: that was the point.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::sql::{FakeExecutor, SqlOrderRepository};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::characterize;
+use std::io::{self, Write};
+
+// A cart as the old checkout page posts it: names and prices in cents.
+type Cart = Vec<(&'static str, u32)>;
+
+// The outside world, which neither version owns: the card processor the
+// shop has a contract with. It refuses anything above its limit.
+pub struct CardProcessor {
+    pub limit_cents: u32,
+}
+
+impl CardProcessor {
+    // An authorization code, or why not.
+    pub fn authorize(&self, cents: u32) -> Result<String, String> {
+        if cents > self.limit_cents {
+            return Err(format!("amount {cents} over limit"));
+        }
+        Ok(format!("AUTH-{cents:08}"))
+    }
+}
+
+pub mod legacy {
+    use super::{CardProcessor, Cart};
+    use std::cell::{Cell, RefCell};
+    use std::io::{self, Write};
+
+    // The database, as the old code knew it: a connection taking strings.
+    // This one only remembers them, and keeps the id sequence.
+    #[derive(Default)]
+    pub struct Connection {
+        pub log: RefCell<Vec<String>>,
+        next_id: Cell<u32>,
+    }
+
+    impl Connection {
+        pub fn run(&self, sql: &str) -> u32 {
+            self.log.borrow_mut().push(sql.to_string());
+            if sql.starts_with("UPDATE sequences") {
+                self.next_id.set(self.next_id.get() + 1);
+                return self.next_id.get();
+            }
+            1
+        }
+    }
+
+    // Don't write this. Do recognize it.
+    pub fn process_order_legacy(
+        cart: &Cart,
+        db: &Connection,
+        card: &CardProcessor,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        // the id comes first, so the logs of a failed checkout have one too
+        let id =
+            db.run("UPDATE sequences SET value = value + 1 WHERE name = 'orders' RETURNING value");
+        if cart.is_empty() {
+            writeln!(out, "rejected: invalid cart")?;
+            return Ok(());
+        }
+        let mut total: u32 = 0;
+        let mut items_sql = String::new();
+        let mut email_lines: Vec<String> = Vec::new();
+        for (name, cents) in cart.iter() {
+            // overflow is how the 2019 incident happened, keep this
+            total = match total.checked_add(*cents) {
+                Some(t) => t,
+                None => {
+                    writeln!(out, "rejected: invalid cart")?;
+                    return Ok(());
+                }
+            };
+            if !items_sql.is_empty() {
+                items_sql.push_str(", ");
+            }
+            // escape quotes for SQL (TODO use parameters some day)
+            items_sql.push_str(&format!(
+                "({}, '{}', {})",
+                id,
+                name.replace('\'', "''"),
+                cents
+            ));
+            // the email data is gathered here too, it was faster to write
+            let mut shown = name.to_string();
+            if shown.chars().count() > 40 {
+                shown = shown.chars().take(39).collect::<String>() + "…";
+            }
+            let price = format!("${}.{:02}", cents / 100, cents % 100);
+            email_lines.push(format!("{:<40} {:>10}", shown, price));
+        }
+        if total == 0 {
+            writeln!(out, "rejected: nothing to pay")?;
+            return Ok(());
+        }
+
+        // payment
+        let auth = match card.authorize(total) {
+            Ok(code) => code,
+            Err(_why) => {
+                // the customer must not see the processor's wording
+                writeln!(out, "declined: card refused")?;
+                return Ok(());
+            }
+        };
+
+        // save, now that the money is there
+        db.run(&format!(
+            "INSERT INTO orders (id, status, total, auth) VALUES ({}, 'Paid', {}, '{}')",
+            id, total, auth
+        ));
+        db.run(&format!(
+            "INSERT INTO order_items (order_id, name, cents) VALUES {}",
+            items_sql
+        ));
+
+        // the email, which marketing asked to keep exactly like this
+        let number = format!("#{:06}", id);
+        let mut email = String::new();
+        email.push_str(&format!("Subject: Your order {} is confirmed\n", number));
+        email.push('\n');
+        for line in &email_lines {
+            email.push_str(line);
+            email.push('\n');
+        }
+        email.push_str(&"-".repeat(51));
+        email.push('\n');
+        let total_text = format!("${}.{:02}", total / 100, total % 100);
+        email.push_str(&format!("{:<40} {:>10}\n", "Total", total_text));
+        write!(out, "{}", email)?;
+        writeln!(out)?;
+
+        writeln!(out, "order {} paid, {}", number, total_text)?;
+        Ok(())
+    }
+}
+
+pub mod migrated {
+    use super::{CardProcessor, Cart};
+    use hexa_lite::prelude::*;
+    use std::io::{self, Write};
+    use std::sync::{Mutex, PoisonError};
+
+    // The card processor behind the PaymentGateway port. Its own wording
+    // stays here: the application only learns that the payment failed.
+    pub struct CardGateway<'a>(pub &'a CardProcessor);
+
+    impl PaymentGateway for CardGateway<'_> {
+        fn charge(&self, amount: Money) -> Result<(), OrderError> {
+            self.0
+                .authorize(amount.0)
+                .map(|_| ())
+                .map_err(|_| OrderError::PaymentFailed)
+        }
+    }
+
+    // The email, behind the Sender port. Sent emails wait in an outbox
+    // until the driving side writes them out, in the order they were sent.
+    #[derive(Default)]
+    pub struct EmailSender {
+        outbox: Mutex<Vec<String>>,
+    }
+
+    impl EmailSender {
+        pub fn take_sent(&self) -> Vec<String> {
+            std::mem::take(&mut *self.outbox.lock().unwrap_or_else(PoisonError::into_inner))
+        }
+    }
+
+    impl Sender for EmailSender {
+        fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+            let mut email = format!(
+                "Subject: Your order {} is confirmed\n\n",
+                confirmation.order_id
+            );
+            for line in confirmation.receipt_lines() {
+                email.push_str(&line);
+                email.push('\n');
+            }
+            self.outbox
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .push(email);
+            Ok(())
+        }
+    }
+
+    // The driving side: the checkout page, over the input port. The
+    // messages are the old ones, word for word.
+    pub fn process_order(
+        service: &mut impl PlaceOrderUseCase,
+        sender: &EmailSender,
+        cart: &Cart,
+        out: &mut dyn Write,
+    ) -> io::Result<()> {
+        let items = cart
+            .iter()
+            .map(|&(name, cents)| LineItem::new(name, Money(cents)))
+            .collect();
+        let placed = service.place_order(PlaceOrder {
+            items,
+            placed_at: None,
+        });
+        for email in sender.take_sent() {
+            writeln!(out, "{email}")?;
+        }
+        match placed {
+            Ok(order) => writeln!(out, "order {} paid, {}", order.id, order.total),
+            Err(OrderError::InvalidOrder) => writeln!(out, "rejected: invalid cart"),
+            Err(OrderError::ZeroTotalNotAllowed) => writeln!(out, "rejected: nothing to pay"),
+            Err(OrderError::PaymentFailed) => writeln!(out, "declined: card refused"),
+            Err(error) => writeln!(out, "error: {error}"),
+        }
+    }
+}
+
+// Every path of the old function at least once, ids consumed by refused
+// carts included.
+fn carts() -> Vec<Cart> {
+    vec![
+        vec![("Rust Book", 4_999), ("Keyboard", 12_999)],
+        vec![],
+        vec![("Gift wrapping", 0)],
+        vec![("Workstation", 349_900)],
+        vec![("Rounding error", u32::MAX), ("Pen", 150)],
+        vec![("Mouse", 2_500)],
+        vec![(
+            "Ergonomic split mechanical keyboard with wrist rest",
+            21_900,
+        )],
+        vec![("Kid's \"first\" desk", 8_900), ("Café au lait mug", 1_250)],
+    ]
+}
+
+// Both versions, each with its own storage, on the same card processor.
+fn compare(carts: Vec<Cart>) -> io::Result<String> {
+    let card = CardProcessor {
+        limit_cents: 200_000,
+    };
+
+    let db = legacy::Connection::default();
+
+    let repo = SqlOrderRepository::new(FakeExecutor::new())
+        .map_err(|error| io::Error::other(format!("{error}")))?
+        .with_console(Console::silent());
+    let payment = migrated::CardGateway(&card);
+    let sender = migrated::EmailSender::default();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    Ok(characterize(
+        |cart, out| legacy::process_order_legacy(cart, &db, &card, out),
+        |cart, out| migrated::process_order(&mut service, &sender, cart, out),
+        carts,
+    ))
+}
+
+fn main() -> io::Result<()> {
+    let transcript = compare(carts())?;
+    print!("{transcript}");
+    println!(
+        "\n--- Legacy and migrated agree on {} carts ---",
+        carts().len()
+    );
+    io::stdout().flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_migration_changes_nothing_anyone_can_see() {
+        let transcript = compare(carts()).unwrap();
+
+        assert_eq!(transcript.matches("--- input").count(), carts().len());
+        assert!(transcript.contains("order #000008 paid, $101.50"));
+    }
+}
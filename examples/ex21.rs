@@ -0,0 +1,68 @@
+// cargo run --example ex21
+
+// Reading your own writes, or not.
+//
+// The back office lists orders from a read model, fed by the events the
+// service publishes. Here the events reach it the way they would across a
+// real message broker: later. A DelayedProjection holds them back until
+// advanced, so the listing is stale right after a change, and says by how
+// much.
+//
+// A shopper left two carts open. The shop merges them into one: the
+// repository has the new total at once, the read model still shows the
+// old one until it catches up.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::projection::DelayedProjection;
+use hexa_lite::application::OrderReadModel;
+use hexa_lite::domain::OrderEvent;
+use hexa_lite::ports::EventPublisher;
+use hexa_lite::prelude::*;
+use hexa_lite::testing::SteppingClock;
+
+// What the back office sees of an order.
+fn listed(projection: &DelayedProjection<'_, OrderReadModel>, id: OrderId) -> String {
+    match projection.model().get(id) {
+        Some(order) => format!("order {id} at {}", order.total),
+        None => format!("order {id} not listed"),
+    }
+}
+
+fn main() -> Result<(), OrderError> {
+    let clock = SteppingClock::starting_at(Timestamp(1_700_000_000));
+    let projection = DelayedProjection::new(OrderReadModel::new(), &clock);
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &sender).with_event_publisher(&projection);
+
+    // The two carts, as the old shop left them: stored, announced, never
+    // charged.
+    for (id, item, cents) in [(1, "Keyboard", 12_999), (2, "Mouse", 2_500)] {
+        let cart = Order::new(OrderId(id), vec![LineItem::new(item, Money(cents))])?;
+        repo.save(&cart)?;
+        projection.publish(&OrderEvent::OrderPlaced {
+            order_id: cart.id,
+            item_count: cart.items.len(),
+            total: cart.total,
+        })?;
+    }
+    projection.catch_up();
+
+    println!("--- Merging the carts ---\n");
+    clock.advance_secs(5);
+    let merged = service.merge_drafts(OrderId(1), OrderId(2))?;
+    println!("  repository: order {} at {}", merged.id, merged.total);
+    println!("  read model: {}", listed(&projection, merged.id));
+    let freshness = projection.freshness();
+    println!(
+        "  {} event(s) pending, the oldest from {:?}",
+        freshness.pending_events, freshness.oldest_pending
+    );
+
+    println!("\n--- Two seconds later ---\n");
+    clock.advance_secs(2);
+    projection.advance_until(clock.now());
+    println!("  read model: {}", listed(&projection, merged.id));
+    println!("  up to date: {}", projection.freshness().is_current());
+    Ok(())
+}
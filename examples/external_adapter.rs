@@ -0,0 +1,89 @@
+// cargo run --example external_adapter
+// cargo test --example external_adapter
+
+// A payment adapter as another crate would write it: nothing but the
+// public API of hexa_lite. The shop's card terminal takes amounts up to its
+// limit, and says in its own words why it refused the others
+// (OrderError::custom); the shop's clock is a fixed instant
+// (Timestamp::from_secs).
+//
+// tests/external_impl.rs does the same for every open trait of the library,
+// and lists the few that are sealed.
+use hexa_lite::prelude::*;
+use std::sync::Mutex;
+
+// The card terminal on the counter, with what it took so far.
+pub struct CardTerminal {
+    pub limit: Money,
+    pub slips: Mutex<Vec<String>>,
+}
+
+impl PaymentGateway for CardTerminal {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        if amount.0 > self.limit.0 {
+            return Err(OrderError::custom(format!(
+                "the terminal only takes up to {}",
+                self.limit
+            )));
+        }
+        self.slips.lock().unwrap().push(format!("slip {amount}"));
+        Ok(())
+    }
+}
+
+pub struct ShopClock;
+
+impl Clock for ShopClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::from_secs(1_700_000_000)
+    }
+}
+
+fn terminal() -> CardTerminal {
+    CardTerminal {
+        limit: Money(5_000),
+        slips: Mutex::new(Vec::new()),
+    }
+}
+
+// What the service makes of a cart, paid at the terminal.
+fn checkout(terminal: &CardTerminal, price: Money) -> Result<Order, OrderError> {
+    let (repo, sender) = (InMemoryOrderRepository::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, terminal, &sender).with_clock(&ShopClock);
+    service.place_order(vec![LineItem::new("Teapot", price)])
+}
+
+fn main() {
+    let terminal = terminal();
+    for price in [Money(2_400), Money(12_000)] {
+        match checkout(&terminal, price) {
+            Ok(order) => println!(
+                "order {} {:?} at {:?}",
+                order.id, order.status, order.placed_at
+            ),
+            Err(OrderError::Custom { message }) => println!("refused: {message}"),
+            Err(other) => println!("failed: {other}"),
+        }
+    }
+    println!("{:?}", terminal.slips.lock().unwrap());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_terminal_takes_what_is_under_its_limit_only() {
+        let terminal = terminal();
+
+        let order = checkout(&terminal, Money(2_400)).unwrap();
+        assert_eq!(order.status, OrderStatus::Paid);
+        assert_eq!(order.placed_at, Some(Timestamp::from_secs(1_700_000_000)));
+
+        assert!(matches!(
+            checkout(&terminal, Money(12_000)),
+            Err(OrderError::Custom { message }) if message == "the terminal only takes up to $50.00"
+        ));
+        assert_eq!(terminal.slips.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,10 @@
+#!/usr/bin/env sh
+# Checks that the library, and the wasm example, build for the browser.
+# Needs the target once: rustup target add wasm32-unknown-unknown
+set -eu
+cd "$(dirname "$0")/.."
+
+cargo check --lib --target wasm32-unknown-unknown
+cargo check --example ex12 --features wasm --target wasm32-unknown-unknown
+# The JSON entry point itself is tested natively.
+cargo test --example ex12 --features wasm
@@ -0,0 +1,48 @@
+// --- Threshold approval ---
+// The default ApprovalPolicy: every order above a fixed total waits for
+// someone to approve it. An order of exactly the threshold goes through.
+use crate::domain::{Money, Order};
+use crate::ports::{ApprovalPolicy, Capability};
+
+pub struct ThresholdApproval {
+    threshold: Money,
+}
+
+impl ThresholdApproval {
+    pub fn new(threshold: Money) -> Self {
+        Self { threshold }
+    }
+}
+
+impl ApprovalPolicy for ThresholdApproval {
+    fn requires_approval(&self, order: &Order) -> bool {
+        order.total.0 > self.threshold.0
+    }
+}
+
+impl Capability for ThresholdApproval {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, OrderId};
+
+    fn order_of(cents: u32) -> Order {
+        Order::new(
+            OrderId(1),
+            vec![LineItem {
+                name: "Monitor".to_string(),
+                price: Money(cents),
+            }],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn only_orders_above_the_threshold_wait() {
+        let policy = ThresholdApproval::new(Money(100_000));
+        assert!(!policy.requires_approval(&order_of(99_999)));
+        assert!(!policy.requires_approval(&order_of(100_000)));
+        assert!(policy.requires_approval(&order_of(100_001)));
+    }
+}
@@ -0,0 +1,50 @@
+// Replacing a whole file without ever leaving half of it.
+// The new contents go to <path>.tmp first, then are renamed over <path>:
+// a crash while writing leaves the previous file untouched, and a reader
+// sees either the old contents or the new, never a mix.
+use crate::ports::ShutdownError;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub(crate) fn replace(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let temporary = aside(path, ".tmp");
+    fs::write(&temporary, contents)?;
+    fs::rename(&temporary, path)
+}
+
+// What was written to `path` down on disk, for a shutdown. A file never
+// written has nothing to sync.
+pub(crate) fn sync(path: &Path) -> Result<(), ShutdownError> {
+    match File::open(path).and_then(|file| file.sync_all()) {
+        Err(error) if error.kind() != io::ErrorKind::NotFound => Err(ShutdownError::Io {
+            path: path.to_path_buf(),
+            kind: error.kind(),
+        }),
+        _ => Ok(()),
+    }
+}
+
+// "orders.keys" -> "orders.keys.tmp"
+pub(crate) fn aside(path: &Path, suffix: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(suffix);
+    PathBuf::from(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn replaces_the_file_and_leaves_no_temporary_behind() {
+        let path =
+            std::env::temp_dir().join(format!("hexa_lite_{}_atomic.txt", std::process::id()));
+        replace(&path, b"first").unwrap();
+        replace(&path, b"second").unwrap();
+
+        assert_eq!(fs::read(&path).unwrap(), b"second");
+        assert!(!aside(&path, ".tmp").exists());
+        let _ = fs::remove_file(&path);
+    }
+}
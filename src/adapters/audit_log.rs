@@ -0,0 +1,234 @@
+// --- File audit log ---
+// What happened to each order, and when, kept on disk for as long as the
+// rotation allows. FileAuditLog appends one JSON line per AuditEntry to
+// audit.jsonl in its directory:
+//
+//     {"at":1700000000,"type":"OrderPaid","order_id":3,"amount_cents":4999}
+//
+// the event as the event log writes it, with the time it was recorded
+// in front.
+//
+// Once the active file grows past max_bytes it is rotated: audit.jsonl
+// becomes audit.1.jsonl, audit.1.jsonl becomes audit.2.jsonl, and so on up
+// to `keep` rotated files; the oldest one past that is deleted. A batch is
+// never split across files, so a file may end a little over max_bytes.
+//
+// Each append() is one batch: every line written, then one fsync. A line
+// torn by a crash in the middle of a batch does not parse and query()
+// skips it, like the event log's replay does.
+use super::ConfigError;
+use super::atomic_file;
+use super::clock::SystemClock;
+use super::event_json::{event_from_json, event_json};
+use super::json::{self, Value};
+use crate::domain::{OrderError, OrderEvent, OrderId, Timestamp};
+use crate::ports::{Capability, Clock, EventPublisher, Shutdown, ShutdownError};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+pub const ACTIVE_AUDIT_FILE: &str = "audit.jsonl";
+pub const DEFAULT_ROTATED_FILES: usize = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub at: Timestamp,
+    pub event: OrderEvent,
+}
+
+impl AuditEntry {
+    pub fn order_id(&self) -> OrderId {
+        self.event.order_id()
+    }
+}
+
+pub struct FileAuditLog {
+    dir: PathBuf,
+    max_bytes: u64,
+    keep: usize,
+    clock: Box<dyn Clock>,
+}
+
+impl FileAuditLog {
+    // Opens the active file once, creating it if needed, so a directory that
+    // does not exist or cannot be written to is known before the first
+    // entry.
+    pub fn open(dir: impl Into<PathBuf>, max_bytes: u64) -> Result<Self, ConfigError> {
+        let log = Self {
+            dir: dir.into(),
+            max_bytes,
+            keep: DEFAULT_ROTATED_FILES,
+            clock: Box::new(SystemClock),
+        };
+        let path = log.active();
+        match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(_) => Ok(log),
+            Err(error) => Err(ConfigError::IoError {
+                path,
+                kind: error.kind(),
+            }),
+        }
+    }
+
+    // How many rotated files are kept. DEFAULT_ROTATED_FILES by default;
+    // none at all drops the active file whole when it is full.
+    pub fn with_keep(mut self, keep: usize) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    // When a published event is said to have happened. The system clock by
+    // default.
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn active(&self) -> PathBuf {
+        self.dir.join(ACTIVE_AUDIT_FILE)
+    }
+
+    // audit.<n>.jsonl, 1 being the most recently rotated.
+    pub fn rotated(&self, n: usize) -> PathBuf {
+        self.dir.join(format!("audit.{n}.jsonl"))
+    }
+
+    // Every file of the log there is, oldest first, the active one last.
+    pub fn files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = (1..=self.keep)
+            .rev()
+            .map(|n| self.rotated(n))
+            .filter(|path| path.exists())
+            .collect();
+        files.push(self.active());
+        files
+    }
+
+    pub fn append(&self, entries: &[AuditEntry]) -> Result<(), OrderError> {
+        let mut batch = String::new();
+        for entry in entries {
+            batch.push_str(&entry_json(entry));
+            batch.push('\n');
+        }
+        let size = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.active())
+            .and_then(|mut file| {
+                file.write_all(batch.as_bytes())?;
+                file.sync_data()?;
+                file.metadata()
+            })
+            .map_err(|_| OrderError::StorageFailed)?
+            .len();
+        if size > self.max_bytes {
+            self.rotate().map_err(|_| OrderError::StorageFailed)?;
+        }
+        Ok(())
+    }
+
+    // Everything recorded about the order, oldest first, across the rotated
+    // files still kept and the active one.
+    pub fn query(&self, order_id: OrderId) -> Result<Vec<AuditEntry>, OrderError> {
+        let mut found = Vec::new();
+        for path in self.files() {
+            let text = match fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(error) if error.kind() == io::ErrorKind::NotFound => continue,
+                Err(_) => return Err(OrderError::StorageFailed),
+            };
+            found.extend(
+                text.lines()
+                    .filter_map(entry_from_json)
+                    .filter(|entry| entry.order_id() == order_id),
+            );
+        }
+        Ok(found)
+    }
+
+    fn rotate(&self) -> io::Result<()> {
+        if self.keep == 0 {
+            return fs::remove_file(self.active());
+        }
+        match fs::remove_file(self.rotated(self.keep)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => return Err(error),
+            _ => {}
+        }
+        for n in (1..self.keep).rev() {
+            let from = self.rotated(n);
+            if from.exists() {
+                fs::rename(from, self.rotated(n + 1))?;
+            }
+        }
+        fs::rename(self.active(), self.rotated(1))
+    }
+}
+
+impl EventPublisher for FileAuditLog {
+    fn publish(&self, event: &OrderEvent) -> Result<(), OrderError> {
+        self.append(&[AuditEntry {
+            at: self.clock.now(),
+            event: event.clone(),
+        }])
+    }
+}
+
+impl Capability for FileAuditLog {}
+
+// Every batch is synced already; this only makes sure of it.
+impl Shutdown for FileAuditLog {
+    fn shutdown(&mut self) -> Result<(), ShutdownError> {
+        atomic_file::sync(&self.active())
+    }
+}
+
+fn entry_json(entry: &AuditEntry) -> String {
+    // event_json is always an object: "{...}" becomes {"at":N,...}.
+    let event = event_json(&entry.event);
+    format!(r#"{{"at":{},{}"#, entry.at.0, &event[1..])
+}
+
+fn entry_from_json(line: &str) -> Option<AuditEntry> {
+    let at = json::parse_flat_object(line)?
+        .into_iter()
+        .find_map(|(key, value)| match (key.as_str(), value) {
+            ("at", Value::Number(at)) => Some(Timestamp(at)),
+            _ => None,
+        })?;
+    Some(AuditEntry {
+        at,
+        event: event_from_json(line)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Money;
+
+    #[test]
+    fn an_entry_is_its_event_with_the_time_in_front() {
+        let entry = AuditEntry {
+            at: Timestamp(1_700_000_000),
+            event: OrderEvent::OrderPaid {
+                order_id: OrderId(3),
+                amount: Money(4999),
+            },
+        };
+
+        let line = entry_json(&entry);
+
+        assert_eq!(
+            line,
+            r#"{"at":1700000000,"type":"OrderPaid","order_id":3,"amount_cents":4999}"#
+        );
+        assert_eq!(entry_from_json(&line), Some(entry));
+        assert_eq!(entry_from_json(&line[..line.len() - 1]), None);
+        assert_eq!(
+            entry_from_json(&event_json(&OrderEvent::OrderExpired {
+                order_id: OrderId(3)
+            })),
+            None
+        );
+    }
+}
@@ -0,0 +1,129 @@
+// --- Directory blob store ---
+// The files attached to orders, one file each in a directory of their own:
+//
+//     attachments/1.blob, attachments/2.blob...
+//
+// named after their BlobId, which counts up from the highest one found at
+// open: a restart never reuses the id of a blob still there. Each is
+// written to a temporary file first, then renamed, so a crash never leaves
+// half a blob under its id.
+//
+// The directory holds nothing else the store cares about: the original
+// name of each file is the order's to keep, with its BlobId.
+use super::ConfigError;
+use super::atomic_file;
+use crate::domain::{BlobId, OrderError};
+use crate::ports::{BlobStore, Capability};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+const EXTENSION: &str = "blob";
+
+pub struct FileBlobStore {
+    dir: PathBuf,
+    // The highest id given out or found, under which the next put is chosen.
+    last: Mutex<u64>,
+}
+
+impl FileBlobStore {
+    // The directory is created if need be.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Self, ConfigError> {
+        let dir = dir.into();
+        let io_error = |error: io::Error| ConfigError::IoError {
+            path: dir.clone(),
+            kind: error.kind(),
+        };
+        fs::create_dir_all(&dir).map_err(io_error)?;
+        let mut last = 0;
+        for entry in fs::read_dir(&dir).map_err(io_error)? {
+            let path = entry.map_err(io_error)?.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == EXTENSION)
+                && let Some(id) = path
+                    .file_stem()
+                    .and_then(|stem| stem.to_str())
+                    .and_then(|stem| stem.parse::<u64>().ok())
+            {
+                last = last.max(id);
+            }
+        }
+        Ok(Self {
+            dir,
+            last: Mutex::new(last),
+        })
+    }
+
+    fn path(&self, id: BlobId) -> PathBuf {
+        self.dir.join(format!("{}.{EXTENSION}", id.0))
+    }
+}
+
+impl BlobStore for FileBlobStore {
+    fn put(&self, bytes: &[u8]) -> Result<BlobId, OrderError> {
+        // Held while writing: two puts never pick the same id.
+        let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+        let id = BlobId(*last + 1);
+        atomic_file::replace(&self.path(id), bytes).map_err(|_| OrderError::StorageFailed)?;
+        *last = id.0;
+        Ok(id)
+    }
+
+    fn get(&self, id: BlobId) -> Result<Option<Vec<u8>>, OrderError> {
+        match fs::read(self.path(id)) {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(_) => Err(OrderError::StorageFailed),
+        }
+    }
+
+    fn delete(&self, id: BlobId) -> Result<(), OrderError> {
+        match fs::remove_file(self.path(id)) {
+            Err(error) if error.kind() != io::ErrorKind::NotFound => Err(OrderError::StorageFailed),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Capability for FileBlobStore {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let dir = env::temp_dir().join(format!("hexa_blobs_{name}_{}", std::process::id()));
+            let _ = fs::remove_dir_all(&dir);
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn ids_of_blobs_still_there_are_not_given_again_after_a_restart() {
+        let dir = TempDir::new("restart");
+        let store = FileBlobStore::open(&dir.0).unwrap();
+        let first = store.put(b"one").unwrap();
+        let second = store.put(b"two").unwrap();
+        store.delete(first).unwrap();
+
+        let reopened = FileBlobStore::open(&dir.0).unwrap();
+        let third = reopened.put(b"three").unwrap();
+
+        assert_eq!((first, second, third), (BlobId(1), BlobId(2), BlobId(3)));
+        assert_eq!(reopened.get(second).unwrap().as_deref(), Some(&b"two"[..]));
+        assert_eq!(reopened.get(first).unwrap(), None);
+        assert!(reopened.delete(first).is_ok());
+    }
+}
@@ -0,0 +1,337 @@
+// --- Circuit breaker (decorator) ---
+// Stops calling a service that keeps failing, so every order does not wait
+// on it in turn. Around a PaymentGateway or a Sender alike:
+//
+// - Closed: calls go through. Enough failures in a row open the circuit.
+// - Open: calls fail fast with CircuitOpen, saying when to come back,
+//   without touching the inner adapter. The open duration is measured with
+//   the Clock port, so tests drive it with a SteppingClock.
+// - HalfOpen: the open duration is over. The next call is a probe and goes
+//   through: if it succeeds the circuit closes, if it fails it opens again
+//   for another full duration. Other calls fail fast while the probe runs.
+//
+// Only the errors saying the service could not be reached count as
+// failures (see NetworkConditions): a declined card is an answer, and the
+// service that gave it is up.
+use crate::domain::{Money, OrderConfirmation, OrderError, OrderId, Timestamp};
+use crate::ports::{
+    Capability, ChargeLog, ChargeOutcome, ChargeRecord, Clock, PaymentGateway, Sender,
+};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+pub const DEFAULT_FAILURE_THRESHOLD: u32 = 5;
+pub const DEFAULT_OPEN_DURATION: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    Closed,
+    Open { until: Timestamp },
+    HalfOpen,
+}
+
+struct Circuit {
+    state: CircuitState,
+    // Failures in a row, while closed.
+    failures: u32,
+    probing: bool,
+}
+
+pub struct CircuitBreaker<I, C: Clock> {
+    inner: I,
+    clock: C,
+    failure_threshold: u32,
+    open_secs: u64,
+    circuit: Mutex<Circuit>,
+}
+
+impl<I, C: Clock> CircuitBreaker<I, C> {
+    pub fn new(inner: I, clock: C) -> Self {
+        Self {
+            inner,
+            clock,
+            failure_threshold: DEFAULT_FAILURE_THRESHOLD,
+            open_secs: DEFAULT_OPEN_DURATION.as_secs(),
+            circuit: Mutex::new(Circuit {
+                state: CircuitState::Closed,
+                failures: 0,
+                probing: false,
+            }),
+        }
+    }
+
+    // At least 1: a threshold of 0 would open on the first success.
+    pub fn with_failure_threshold(mut self, failures: u32) -> Self {
+        self.failure_threshold = failures.max(1);
+        self
+    }
+
+    // Whole seconds, like the Clock.
+    pub fn with_open_duration(mut self, duration: Duration) -> Self {
+        self.open_secs = duration.as_secs();
+        self
+    }
+
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    // HalfOpen as soon as the open duration is over, even before a call
+    // comes to probe the service.
+    pub fn state(&self) -> CircuitState {
+        match self.lock().state {
+            CircuitState::Open { until } if until <= self.clock.now() => CircuitState::HalfOpen,
+            state => state,
+        }
+    }
+
+    // The one place calls go through, whichever port is wrapped.
+    pub fn invoke<T>(
+        &self,
+        call: impl FnOnce(&I) -> Result<T, OrderError>,
+    ) -> Result<T, OrderError> {
+        let now = self.clock.now();
+        {
+            let mut circuit = self.lock();
+            match circuit.state {
+                CircuitState::Open { until } if now < until => {
+                    return Err(OrderError::CircuitOpen {
+                        retry_after_secs: until.0 - now.0,
+                    });
+                }
+                CircuitState::Closed => {}
+                CircuitState::Open { .. } | CircuitState::HalfOpen if !circuit.probing => {
+                    circuit.state = CircuitState::HalfOpen;
+                    circuit.probing = true;
+                }
+                CircuitState::Open { .. } | CircuitState::HalfOpen => {
+                    return Err(OrderError::CircuitOpen {
+                        retry_after_secs: 0,
+                    });
+                }
+            }
+        }
+
+        // Not holding the lock: the call may take its time.
+        let result = call(&self.inner);
+        let mut circuit = self.lock();
+        let probe = std::mem::take(&mut circuit.probing);
+        match &result {
+            Err(error) if unreachable(error) => {
+                circuit.failures += 1;
+                if probe || circuit.failures >= self.failure_threshold {
+                    circuit.state = CircuitState::Open {
+                        until: self.clock.now().plus_secs(self.open_secs),
+                    };
+                    circuit.failures = 0;
+                }
+            }
+            _ => {
+                circuit.state = CircuitState::Closed;
+                circuit.failures = 0;
+            }
+        }
+        result
+    }
+
+    // A panic elsewhere while holding the lock leaves the state as it was.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Circuit> {
+        self.circuit.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn unreachable(error: &OrderError) -> bool {
+    matches!(
+        error,
+        OrderError::PaymentUnavailable | OrderError::StorageFailed | OrderError::NotificationFailed
+    )
+}
+
+impl<G: PaymentGateway, C: Clock> PaymentGateway for CircuitBreaker<G, C> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.charge(amount))
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.charge_for(order_id, amount))
+    }
+
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.invoke(|inner| inner.charge_order(order_id, amount))
+    }
+
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.refund_for(order_id, amount))
+    }
+}
+
+// Reading the records is not a call the breaker guards.
+impl<G: PaymentGateway + ChargeLog, C: Clock> ChargeLog for CircuitBreaker<G, C> {
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        self.inner.charges()
+    }
+}
+
+impl<S: Sender, C: Clock> Sender for CircuitBreaker<S, C> {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.send(confirmation))
+    }
+}
+
+impl<I, C: Clock> Capability for CircuitBreaker<I, C> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_err_variant;
+    use crate::domain::Currency;
+    use crate::testing::SteppingClock;
+    use crate::testing::stubs::ErrNotifier;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Answers with the next outcome in line; counts the calls that reached it.
+    #[derive(Default)]
+    struct Scripted {
+        outcomes: Mutex<Vec<Result<(), OrderError>>>,
+        calls: AtomicU32,
+    }
+
+    impl Scripted {
+        fn then(self, outcome: Result<(), OrderError>, times: usize) -> Self {
+            self.outcomes
+                .lock()
+                .unwrap()
+                .extend(std::iter::repeat_n(outcome, times));
+            self
+        }
+
+        fn calls(&self) -> u32 {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl PaymentGateway for Scripted {
+        fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            self.outcomes.lock().unwrap().remove(0)
+        }
+    }
+
+    fn breaker(inner: Scripted) -> CircuitBreaker<Scripted, SteppingClock> {
+        CircuitBreaker::new(inner, SteppingClock::starting_at(Timestamp(1_000)))
+            .with_failure_threshold(3)
+            .with_open_duration(Duration::from_secs(60))
+    }
+
+    const DOWN: Result<(), OrderError> = Err(OrderError::PaymentUnavailable);
+
+    #[test]
+    fn the_threshold_trips_the_circuit_then_calls_fail_fast() {
+        let breaker = breaker(Scripted::default().then(DOWN, 3));
+
+        for _ in 0..2 {
+            assert_err_variant!(breaker.charge(Money(100)), OrderError::PaymentUnavailable);
+            assert_eq!(breaker.state(), CircuitState::Closed);
+        }
+        assert_err_variant!(breaker.charge(Money(100)), OrderError::PaymentUnavailable);
+        assert_eq!(
+            breaker.state(),
+            CircuitState::Open {
+                until: Timestamp(1_060)
+            }
+        );
+
+        breaker.clock().advance_secs(45);
+        assert_err_variant!(
+            breaker.charge(Money(100)),
+            OrderError::CircuitOpen {
+                retry_after_secs: 15
+            }
+        );
+        assert_eq!(breaker.inner().calls(), 3);
+    }
+
+    #[test]
+    fn a_successful_probe_closes_the_circuit() {
+        let breaker = breaker(Scripted::default().then(DOWN, 3).then(Ok(()), 2));
+        for _ in 0..3 {
+            let _ = breaker.charge(Money(100));
+        }
+
+        breaker.clock().advance_secs(60);
+        assert_eq!(breaker.state(), CircuitState::HalfOpen);
+        breaker.charge(Money(100)).unwrap();
+
+        assert_eq!(breaker.state(), CircuitState::Closed);
+        breaker.charge(Money(100)).unwrap();
+        assert_eq!(breaker.inner().calls(), 5);
+    }
+
+    #[test]
+    fn a_failed_probe_opens_it_again_for_a_full_duration() {
+        let breaker = breaker(Scripted::default().then(DOWN, 4));
+        for _ in 0..3 {
+            let _ = breaker.charge(Money(100));
+        }
+
+        breaker.clock().advance_secs(70);
+        assert_err_variant!(breaker.charge(Money(100)), OrderError::PaymentUnavailable);
+
+        // One failure was enough: no threshold to reach again.
+        assert_eq!(
+            breaker.state(),
+            CircuitState::Open {
+                until: Timestamp(1_130)
+            }
+        );
+        assert_err_variant!(breaker.charge(Money(100)), OrderError::CircuitOpen { .. });
+        assert_eq!(breaker.inner().calls(), 4);
+    }
+
+    #[test]
+    fn declined_cards_and_successes_do_not_count() {
+        let declined = Err(OrderError::PaymentFailed);
+        let breaker = breaker(
+            Scripted::default()
+                .then(DOWN, 2)
+                .then(declined, 1)
+                .then(DOWN, 2)
+                .then(Ok(()), 1)
+                .then(DOWN, 2),
+        );
+
+        for _ in 0..8 {
+            let _ = breaker.charge(Money(100));
+        }
+
+        assert_eq!(breaker.state(), CircuitState::Closed);
+    }
+
+    #[test]
+    fn a_sender_is_guarded_the_same_way() {
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let breaker = CircuitBreaker::new(ErrNotifier(OrderError::NotificationFailed), clock)
+            .with_failure_threshold(1);
+        let confirmation = OrderConfirmation {
+            order_id: OrderId(1),
+            items: Vec::new(),
+            total: Money(100),
+            tax: Money(0),
+            currency: Currency::Usd,
+            customer_email: None,
+            reference: None,
+        };
+
+        assert_err_variant!(breaker.send(&confirmation), OrderError::NotificationFailed);
+        assert_err_variant!(
+            breaker.send(&confirmation),
+            OrderError::CircuitOpen {
+                retry_after_secs: 30
+            }
+        );
+    }
+}
@@ -0,0 +1,174 @@
+// --- Within budget (decorator) ---
+// Checks the use case's Budget before every call to the port it wraps: a
+// call that would start with nothing left is refused with BudgetExhausted,
+// without touching the inner adapter. A call already under way is never
+// cut short; the next one is refused instead.
+//
+// Wrap every port a use case calls, or only the slow ones: each decorator
+// checks the same budget, so "checkout in 2 seconds" holds however the
+// time was spent.
+use crate::domain::{
+    Money, Notice, Order, OrderConfirmation, OrderError, OrderId, OrderKey, OrderReference,
+};
+use crate::ports::{
+    Budget, CancelToken, Capability, ChargeLog, ChargeOutcome, ChargeRecord, OrderReader,
+    OrderWriter, PaymentGateway, Sender,
+};
+
+pub struct WithinBudget<'a, I> {
+    inner: I,
+    budget: &'a Budget<'a>,
+}
+
+impl<'a, I> WithinBudget<'a, I> {
+    pub fn new(inner: I, budget: &'a Budget<'a>) -> Self {
+        Self { inner, budget }
+    }
+
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    pub fn budget(&self) -> &'a Budget<'a> {
+        self.budget
+    }
+
+    fn invoke<T>(&self, call: impl FnOnce(&I) -> Result<T, OrderError>) -> Result<T, OrderError> {
+        self.budget.check()?;
+        call(&self.inner)
+    }
+}
+
+impl<G: PaymentGateway> PaymentGateway for WithinBudget<'_, G> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.charge(amount))
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.charge_for(order_id, amount))
+    }
+
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.invoke(|inner| inner.charge_order(order_id, amount))
+    }
+
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.refund_for(order_id, amount))
+    }
+}
+
+// Reading the records is not part of any use case.
+impl<G: PaymentGateway + ChargeLog> ChargeLog for WithinBudget<'_, G> {
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        self.inner.charges()
+    }
+}
+
+impl<S: Sender> Sender for WithinBudget<'_, S> {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.send(confirmation))
+    }
+
+    fn send_notice(&self, notice: &Notice) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.send_notice(notice))
+    }
+}
+
+// Forwarded one by one, so the inner adapter's cheap versions are used.
+impl<R: OrderReader> OrderReader for WithinBudget<'_, R> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.invoke(|inner| inner.find(id))
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.invoke(|inner| inner.list())
+    }
+
+    fn exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        self.invoke(|inner| inner.exists(id))
+    }
+
+    fn total_of(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        self.invoke(|inner| inner.total_of(id))
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.for_each(visit))
+    }
+
+    fn for_each_cancellable(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.for_each_cancellable(cancel, visit))
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        self.invoke(|inner| inner.list_deleted())
+    }
+
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.invoke(|inner| inner.find_by_key(key))
+    }
+
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        self.invoke(|inner| inner.find_by_reference(reference))
+    }
+}
+
+impl<R: OrderWriter> OrderWriter for WithinBudget<'_, R> {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.save(order))
+    }
+
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.update(order))
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.soft_delete(id))
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.restore(id))
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.purge(id))
+    }
+}
+
+impl<I> Capability for WithinBudget<'_, I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::Console;
+    use crate::adapters::in_memory::MockPaymentGateway;
+    use crate::assert_err_variant;
+    use crate::domain::Timestamp;
+    use crate::testing::SteppingClock;
+    use std::time::Duration;
+
+    #[test]
+    fn calls_go_through_until_the_budget_is_spent() {
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let budget = Budget::new(&clock, Duration::from_secs(2));
+        let inner = MockPaymentGateway::new().with_console(Console::silent());
+        let payment = WithinBudget::new(inner, &budget);
+
+        budget.start();
+        payment.charge_for(OrderId(1), Money(100)).unwrap();
+        clock.advance_secs(2);
+        assert_err_variant!(
+            payment.charge_for(OrderId(2), Money(100)),
+            OrderError::BudgetExhausted { spent_ms: 2_000 }
+        );
+        assert_eq!(payment.charges().unwrap().len(), 1);
+
+        // Outside a use case, nothing is refused.
+        budget.stop();
+        payment.charge_for(OrderId(3), Money(100)).unwrap();
+    }
+}
@@ -0,0 +1,156 @@
+// --- Buffered sender (decorator) ---
+// Holds notifications back instead of sending them one by one, and sends the
+// whole batch on flush(). Think of an SMTP connection reused for a batch, or
+// a rate-limited API better called once a second than once an order.
+//
+// The application keeps calling Sender::send and never learns about the
+// buffer: flushing is the composition root's job, through the Flushable
+// capability.
+use super::Console;
+use crate::domain::{OrderConfirmation, OrderError};
+use crate::ports::{Capability, Flushable, Sender, Shutdown, ShutdownError};
+use std::cell::RefCell;
+
+pub struct BufferedSender<S: Sender> {
+    inner: S,
+    pending: RefCell<Vec<OrderConfirmation>>,
+    console: Console,
+}
+
+impl<S: Sender> BufferedSender<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            pending: RefCell::new(Vec::new()),
+            console: Console::stdout(),
+        }
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn pending(&self) -> usize {
+        self.pending.borrow().len()
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: Sender> Sender for BufferedSender<S> {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.console.line(format_args!(
+            "  [Buffered] Holding confirmation for order {:?}",
+            confirmation.order_id
+        ));
+        self.pending.borrow_mut().push(confirmation.clone());
+        Ok(())
+    }
+}
+
+impl<S: Sender> Flushable for BufferedSender<S> {
+    // Sends in arrival order. On failure the unsent confirmations, including
+    // the one that failed, stay buffered for the next flush.
+    fn flush(&mut self) -> Result<(), OrderError> {
+        let pending = self.pending.get_mut();
+        self.console.line(format_args!(
+            "  [Buffered] Flushing {} confirmation(s)",
+            pending.len()
+        ));
+        while let Some(confirmation) = pending.first() {
+            self.inner.send(confirmation)?;
+            pending.remove(0);
+        }
+        Ok(())
+    }
+}
+
+// A flush; what it could not send is lost with the process.
+impl<S: Sender> Shutdown for BufferedSender<S> {
+    fn shutdown(&mut self) -> Result<(), ShutdownError> {
+        Ok(self.flush()?)
+    }
+}
+
+impl<S: Sender> Capability for BufferedSender<S> {
+    fn as_flushable(&mut self) -> Option<&mut dyn Flushable> {
+        Some(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, Money, Order, OrderId};
+    use std::cell::Cell;
+
+    // Fails every call after the first `succeed` ones.
+    struct FlakySender {
+        succeed: Cell<u32>,
+        sent: RefCell<Vec<OrderId>>,
+    }
+
+    impl Sender for FlakySender {
+        fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+            if self.succeed.get() == 0 {
+                return Err(OrderError::NotificationFailed);
+            }
+            self.succeed.set(self.succeed.get() - 1);
+            self.sent.borrow_mut().push(confirmation.order_id);
+            Ok(())
+        }
+    }
+
+    fn confirmation(id: u32) -> OrderConfirmation {
+        Order::new(
+            OrderId(id),
+            vec![LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            }],
+        )
+        .unwrap()
+        .confirmation()
+    }
+
+    #[test]
+    fn nothing_is_sent_before_flush() {
+        let mut sender = BufferedSender::new(FlakySender {
+            succeed: Cell::new(10),
+            sent: RefCell::new(Vec::new()),
+        });
+
+        sender.send(&confirmation(1)).unwrap();
+        sender.send(&confirmation(2)).unwrap();
+        assert!(sender.inner().sent.borrow().is_empty());
+
+        sender.flush().unwrap();
+        assert_eq!(*sender.inner().sent.borrow(), vec![OrderId(1), OrderId(2)]);
+        assert_eq!(sender.pending(), 0);
+    }
+
+    #[test]
+    fn failed_flush_keeps_unsent_orders() {
+        let mut sender = BufferedSender::new(FlakySender {
+            succeed: Cell::new(1),
+            sent: RefCell::new(Vec::new()),
+        });
+        for id in 1..=3 {
+            sender.send(&confirmation(id)).unwrap();
+        }
+
+        assert!(sender.flush().is_err());
+        assert_eq!(*sender.inner().sent.borrow(), vec![OrderId(1)]);
+        assert_eq!(sender.pending(), 2);
+
+        sender.inner().succeed.set(10);
+        sender.flush().unwrap();
+        assert_eq!(
+            *sender.inner().sent.borrow(),
+            vec![OrderId(1), OrderId(2), OrderId(3)]
+        );
+    }
+}
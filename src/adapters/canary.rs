@@ -0,0 +1,360 @@
+// --- Canary rollout (decorator) ---
+// Replacing an adapter in production, a little at a time: Canary holds the
+// adapter in use (old) and its replacement (new), and sends `percent` of
+// the orders to the new one. Which side an order goes to is a hash of its
+// id and a seed, so the same order always goes to the same side: a refund
+// reaches the provider that made the charge, a read finds the order where
+// it was written. Raise the percentage and more orders move over; 0 sends
+// every one to the old adapter, 100 every one to the new.
+//
+//     let gateway = Canary::new(stripe, adyen, Percent::try_from(5)?).with_seed(rollout);
+//
+// Every read keeps to that split. A read by id asks the order's side; one
+// that names no id asks both, and takes from each only the orders on its
+// side: a list is the two merged by ascending id, a lookup by key or by
+// reference the order found where its id says it lives. A bare charge,
+// with no order to route by, goes to the old adapter. The two sides
+// implement the same port, and need not be the same type.
+//
+// Compare mode is for reads: every read goes to both adapters, the old
+// one's answer is the one returned, and where the new one answered
+// otherwise the ErrorReporter hears of it, as the port Repository, with
+// the use case "canary". The caller never does. Charges are never made
+// twice: a gateway in compare mode is routed as without it.
+use crate::domain::{Money, Order, OrderError, OrderId, OrderKey, OrderReference, Percent};
+use crate::ports::{
+    CancelToken, Capability, ChargeOutcome, Clock, ErrorContext, ErrorReporter, OrderReader,
+    PaymentGateway, Port,
+};
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+pub const DEFAULT_SEED: u64 = 0x5eed_ca4a_2b1e_0001;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Side {
+    Old,
+    New,
+}
+
+struct Telemetry<'a> {
+    reporter: &'a (dyn ErrorReporter + Sync),
+    clock: &'a (dyn Clock + Sync),
+}
+
+pub struct Canary<'a, O, N = O> {
+    old: O,
+    new: N,
+    percent: Percent,
+    seed: u64,
+    compare: bool,
+    mismatches: AtomicU64,
+    telemetry: Option<Telemetry<'a>>,
+}
+
+impl<'a, O, N> Canary<'a, O, N> {
+    pub fn new(old: O, new: N, percent: Percent) -> Self {
+        Self {
+            old,
+            new,
+            percent,
+            seed: DEFAULT_SEED,
+            compare: false,
+            mismatches: AtomicU64::new(0),
+            telemetry: None,
+        }
+    }
+
+    // Another seed picks other orders for the new side, as many of them.
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn with_compare_mode(mut self, compare: bool) -> Self {
+        self.compare = compare;
+        self
+    }
+
+    // Where the reads answered differently are reported.
+    pub fn with_error_reporter(
+        mut self,
+        reporter: &'a (dyn ErrorReporter + Sync),
+        clock: &'a (dyn Clock + Sync),
+    ) -> Self {
+        self.telemetry = Some(Telemetry { reporter, clock });
+        self
+    }
+
+    pub fn old(&self) -> &O {
+        &self.old
+    }
+
+    pub fn new_side(&self) -> &N {
+        &self.new
+    }
+
+    pub fn side_for(&self, id: OrderId) -> Side {
+        if mix(self.seed ^ u64::from(id.0)) % 100 < u64::from(self.percent.value()) {
+            Side::New
+        } else {
+            Side::Old
+        }
+    }
+
+    // Reads the two sides answered differently, reported or not.
+    pub fn mismatches(&self) -> u64 {
+        self.mismatches.load(Ordering::SeqCst)
+    }
+
+    fn routed<T>(&self, id: OrderId, old: impl FnOnce(&O) -> T, new: impl FnOnce(&N) -> T) -> T {
+        match self.side_for(id) {
+            Side::Old => old(&self.old),
+            Side::New => new(&self.new),
+        }
+    }
+
+    fn read<T: PartialEq>(
+        &self,
+        operation: &'static str,
+        id: OrderId,
+        old: impl FnOnce(&O) -> Result<T, OrderError>,
+        new: impl FnOnce(&N) -> Result<T, OrderError>,
+    ) -> Result<T, OrderError> {
+        if !self.compare {
+            return self.routed(id, old, new);
+        }
+        self.compared(operation, Some(id), old, new)
+    }
+
+    // A read naming no id: `keep` takes what each side answered for its
+    // own orders.
+    fn gathered<T: PartialEq>(
+        &self,
+        operation: &'static str,
+        old: impl FnOnce(&O) -> Result<T, OrderError>,
+        new: impl FnOnce(&N) -> Result<T, OrderError>,
+        keep: impl FnOnce(T, T) -> T,
+    ) -> Result<T, OrderError> {
+        if !self.compare {
+            return Ok(keep(old(&self.old)?, new(&self.new)?));
+        }
+        self.compared(operation, None, old, new)
+    }
+
+    fn compared<T: PartialEq>(
+        &self,
+        operation: &'static str,
+        id: Option<OrderId>,
+        old: impl FnOnce(&O) -> Result<T, OrderError>,
+        new: impl FnOnce(&N) -> Result<T, OrderError>,
+    ) -> Result<T, OrderError> {
+        let old = old(&self.old);
+        let new = new(&self.new);
+        if !agree(&old, &new) {
+            self.mismatches.fetch_add(1, Ordering::SeqCst);
+            self.report(operation, id);
+        }
+        old
+    }
+
+    // An order the other side still holds, from before the split moved it,
+    // is not this side's to give out.
+    fn on_side(&self, side: Side) -> impl Fn(&Order) -> bool + '_ {
+        move |order| self.side_for(order.id) == side
+    }
+
+    fn merged(&self, old: Vec<Order>, new: Vec<Order>) -> Vec<Order> {
+        let mut orders: Vec<Order> = old
+            .into_iter()
+            .filter(self.on_side(Side::Old))
+            .chain(new.into_iter().filter(self.on_side(Side::New)))
+            .collect();
+        orders.sort_by_key(|order| order.id);
+        orders
+    }
+
+    fn owned(&self, old: Option<Order>, new: Option<Order>) -> Option<Order> {
+        old.filter(self.on_side(Side::Old))
+            .or(new.filter(self.on_side(Side::New)))
+    }
+
+    fn report(&self, operation: &'static str, order_id: Option<OrderId>) {
+        let Some(telemetry) = &self.telemetry else {
+            return;
+        };
+        let context = ErrorContext {
+            use_case: "canary",
+            port: Some(Port::Repository),
+            operation,
+            order_id,
+            at: telemetry.clock.now(),
+            error: OrderError::custom(format!(
+                "{operation}: the new adapter answered otherwise than the old one"
+            )),
+        };
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| telemetry.reporter.report(context)));
+    }
+}
+
+// Two failures agree when they are the same failure, by their code.
+fn agree<T: PartialEq>(old: &Result<T, OrderError>, new: &Result<T, OrderError>) -> bool {
+    match (old, new) {
+        (Ok(old), Ok(new)) => old == new,
+        (Err(old), Err(new)) => old.code() == new.code(),
+        _ => false,
+    }
+}
+
+// splitmix64's finalizer: consecutive ids land all over 0..100, and the
+// split does not change between releases as std's hashers may.
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+impl<O: PaymentGateway, N: PaymentGateway> PaymentGateway for Canary<'_, O, N> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.old.charge(amount)
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.routed(
+            order_id,
+            |old| old.charge_for(order_id, amount),
+            |new| new.charge_for(order_id, amount),
+        )
+    }
+
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.routed(
+            order_id,
+            |old| old.charge_order(order_id, amount),
+            |new| new.charge_order(order_id, amount),
+        )
+    }
+
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.routed(
+            order_id,
+            |old| old.refund_for(order_id, amount),
+            |new| new.refund_for(order_id, amount),
+        )
+    }
+}
+
+impl<O: OrderReader, N: OrderReader> OrderReader for Canary<'_, O, N> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.read("find", id, |old| old.find(id), |new| new.find(id))
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.gathered(
+            "list",
+            |old| old.list(),
+            |new| new.list(),
+            |old, new| self.merged(old, new),
+        )
+    }
+
+    fn exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        self.read("exists", id, |old| old.exists(id), |new| new.exists(id))
+    }
+
+    fn total_of(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        self.read(
+            "total_of",
+            id,
+            |old| old.total_of(id),
+            |new| new.total_of(id),
+        )
+    }
+
+    // Both go through list(): the merge needs the two sides whole, and
+    // compare mode sees the scans as the lists they are.
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        self.list()?.iter().for_each(visit);
+        Ok(())
+    }
+
+    fn for_each_cancellable(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        for (processed, order) in self.list()?.iter().enumerate() {
+            cancel.check(processed)?;
+            visit(order);
+        }
+        Ok(())
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        self.gathered(
+            "list_deleted",
+            |old| old.list_deleted(),
+            |new| new.list_deleted(),
+            |old, new| self.merged(old, new),
+        )
+    }
+
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.gathered(
+            "find_by_key",
+            |old| old.find_by_key(key),
+            |new| new.find_by_key(key),
+            |old, new| self.owned(old, new),
+        )
+    }
+
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        self.gathered(
+            "find_by_reference",
+            |old| old.find_by_reference(reference),
+            |new| new.find_by_reference(reference),
+            |old, new| self.owned(old, new),
+        )
+    }
+}
+
+impl<O, N> Capability for Canary<'_, O, N> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canary(percent: u32) -> Canary<'static, ()> {
+        Canary::new((), (), Percent::try_from(percent).unwrap())
+    }
+
+    #[test]
+    fn none_and_all_are_none_and_all() {
+        let (none, all) = (canary(0), canary(100));
+        for id in 0..500 {
+            assert_eq!(none.side_for(OrderId(id)), Side::Old);
+            assert_eq!(all.side_for(OrderId(id)), Side::New);
+        }
+    }
+
+    #[test]
+    fn the_split_is_pinned_for_a_seed() {
+        let sides: Vec<Side> = (1..=8)
+            .map(|id| canary(50).with_seed(7).side_for(OrderId(id)))
+            .collect();
+        assert_eq!(
+            sides,
+            [
+                Side::Old,
+                Side::New,
+                Side::Old,
+                Side::Old,
+                Side::New,
+                Side::Old,
+                Side::New,
+                Side::New
+            ]
+        );
+    }
+}
@@ -0,0 +1,189 @@
+// --- Capped payment gateway (decorator) ---
+// Limits what can be charged in any 24 hours, across all orders: a safety
+// net against a runaway batch job or a stolen API key. A charge that would
+// take the spend over the cap is refused whole, with what is left in the
+// window; it is never split into a smaller charge.
+//
+// The window rolls: a charge counts for exactly 24 hours after it was made,
+// by the Clock port, so tests drive it with a SteppingClock.
+use crate::domain::{Money, OrderError, OrderId, Timestamp};
+use crate::ports::{Capability, ChargeLog, ChargeOutcome, ChargeRecord, Clock, PaymentGateway};
+use std::collections::VecDeque;
+use std::sync::{Mutex, PoisonError};
+
+pub const CAP_WINDOW_SECS: u64 = 24 * 60 * 60;
+
+pub struct CappedPaymentGateway<G: PaymentGateway, C: Clock> {
+    inner: G,
+    clock: C,
+    cap: Money,
+    // (when, how much), oldest first.
+    ledger: Mutex<VecDeque<(Timestamp, Money)>>,
+}
+
+impl<G: PaymentGateway, C: Clock> CappedPaymentGateway<G, C> {
+    pub fn new(inner: G, clock: C, cap: Money) -> Self {
+        Self {
+            inner,
+            clock,
+            cap,
+            ledger: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn inner(&self) -> &G {
+        &self.inner
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    // What was charged in the last 24 hours.
+    pub fn spent_in_window(&self) -> Money {
+        let mut ledger = self.ledger.lock().unwrap_or_else(PoisonError::into_inner);
+        Money(self.spent(&mut ledger, self.clock.now()))
+    }
+
+    // Forgets what fell out of the window and sums the rest. The total is
+    // at most the cap, so it fits in Money.
+    fn spent(&self, ledger: &mut VecDeque<(Timestamp, Money)>, now: Timestamp) -> u32 {
+        while ledger
+            .front()
+            .is_some_and(|(at, _)| at.plus_secs(CAP_WINDOW_SECS) <= now)
+        {
+            ledger.pop_front();
+        }
+        ledger.iter().map(|(_, amount)| amount.0).sum()
+    }
+
+    // Holds the ledger while `charge` runs, so two concurrent charges cannot
+    // both fit in the same remaining amount.
+    fn within_cap<T>(
+        &self,
+        amount: Money,
+        charge: impl FnOnce() -> Result<T, OrderError>,
+    ) -> Result<T, OrderError> {
+        let now = self.clock.now();
+        let mut ledger = self.ledger.lock().unwrap_or_else(PoisonError::into_inner);
+        let remaining = self.cap.0.saturating_sub(self.spent(&mut ledger, now));
+        if amount.0 > remaining {
+            return Err(OrderError::DailyCapExceeded {
+                remaining: Money(remaining),
+            });
+        }
+        let outcome = charge()?;
+        ledger.push_back((now, amount));
+        Ok(outcome)
+    }
+}
+
+impl<G: PaymentGateway, C: Clock> PaymentGateway for CappedPaymentGateway<G, C> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.within_cap(amount, || self.inner.charge(amount))
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.within_cap(amount, || self.inner.charge_for(order_id, amount))
+    }
+
+    // A deferred charge is owed all the same: it counts against the cap.
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.within_cap(amount, || self.inner.charge_order(order_id, amount))
+    }
+
+    // What was charged still counts against the cap: a refund does not
+    // make room for more spending today.
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.inner.refund_for(order_id, amount)
+    }
+}
+
+// The inner gateway's records; the cap's own ledger forgets after 24 hours.
+impl<G: PaymentGateway + ChargeLog, C: Clock> ChargeLog for CappedPaymentGateway<G, C> {
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        self.inner.charges()
+    }
+}
+
+impl<G: PaymentGateway, C: Clock> Capability for CappedPaymentGateway<G, C> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_err_variant;
+    use crate::testing::SteppingClock;
+    use crate::testing::stubs::OkPaymentGateway;
+
+    fn gateway(cap: u32) -> CappedPaymentGateway<OkPaymentGateway, SteppingClock> {
+        CappedPaymentGateway::new(
+            OkPaymentGateway,
+            SteppingClock::starting_at(Timestamp(1_700_000_000)),
+            Money(cap),
+        )
+    }
+
+    #[test]
+    fn charges_up_to_exactly_the_cap_go_through() {
+        let gateway = gateway(10_000);
+
+        gateway.charge(Money(6_000)).unwrap();
+        gateway.charge(Money(4_000)).unwrap();
+
+        assert_eq!(gateway.spent_in_window(), Money(10_000));
+    }
+
+    #[test]
+    fn one_cent_over_is_refused_with_what_remains() {
+        let gateway = gateway(10_000);
+        gateway.charge(Money(6_000)).unwrap();
+
+        assert_err_variant!(
+            gateway.charge(Money(4_001)),
+            OrderError::DailyCapExceeded {
+                remaining: Money(4_000)
+            }
+        );
+        // Refused whole: nothing of it was charged.
+        assert_eq!(gateway.spent_in_window(), Money(6_000));
+    }
+
+    #[test]
+    fn spend_leaves_the_window_after_24_hours() {
+        let gateway = gateway(10_000);
+        gateway.charge(Money(10_000)).unwrap();
+        gateway.clock().advance_secs(12 * 60 * 60);
+        assert_err_variant!(
+            gateway.charge(Money(1)),
+            OrderError::DailyCapExceeded {
+                remaining: Money(0)
+            }
+        );
+
+        gateway.clock().advance_secs(12 * 60 * 60 - 1);
+        assert_eq!(gateway.spent_in_window(), Money(10_000));
+        gateway.clock().advance_secs(1);
+        assert_eq!(gateway.spent_in_window(), Money(0));
+        gateway.charge(Money(10_000)).unwrap();
+    }
+
+    #[test]
+    fn failed_charges_do_not_count() {
+        struct Declining;
+
+        impl PaymentGateway for Declining {
+            fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+                Err(OrderError::PaymentFailed)
+            }
+        }
+
+        let gateway = CappedPaymentGateway::new(
+            Declining,
+            SteppingClock::starting_at(Timestamp(0)),
+            Money(10_000),
+        );
+
+        assert_err_variant!(gateway.charge(Money(5_000)), OrderError::PaymentFailed);
+        assert_eq!(gateway.spent_in_window(), Money(0));
+    }
+}
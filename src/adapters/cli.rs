@@ -0,0 +1,285 @@
+// --- Command line (driving adapter) ---
+// One command per line, one reply per command:
+//
+//     place Keyboard=12999, USB cable=999
+//     ok #000001 $139.98
+//
+// Input comes from a terminal, a pipe or a file: anything may show up,
+// including bytes that are not UTF-8 and lines with no end. Every such
+// problem becomes an "error InvalidOrder" reply; the loop keeps going.
+//
+// A declined card says why, by the reason's code:
+//
+//     place Keyboard=12901
+//     error PaymentDeclined insufficient_funds
+//
+// A file of commands is a batch: cancelling the adapter's CancelToken stops
+// it before the next line, with a last "error Cancelled" reply.
+//
+// A service that also amends orders takes one more command, through
+// handle_line_with_amendments and run_with_amendments:
+//
+//     amend #000001 address=1 Main St|Springfield|12345|US; note=Happy birthday
+//     ok #000001 amended
+//
+// Either part may be left out; the note, when there is one, is the rest of
+// the line. What the customer got wrong is listed field by field:
+//
+//     error gift_note: too long (312/200); new_address.country: ...
+use super::inbound::{self, MAX_ITEMS};
+use crate::domain::{Address, OrderError, OrderId};
+use crate::ports::{AmendOrder, AmendOrderUseCase, CancelToken, PlaceOrder, PlaceOrderUseCase};
+use std::io::{self, BufRead, Read, Write};
+
+// Longer lines are refused without being read into memory.
+pub const MAX_LINE_BYTES: usize = 16 * 1024;
+
+pub struct CliAdapter<S: PlaceOrderUseCase> {
+    service: S,
+    cancel: CancelToken,
+}
+
+impl<S: PlaceOrderUseCase> CliAdapter<S> {
+    pub fn new(service: S) -> Self {
+        Self {
+            service,
+            cancel: CancelToken::new(),
+        }
+    }
+
+    pub fn with_cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn into_inner(self) -> S {
+        self.service
+    }
+
+    // None for a blank line: nothing to answer.
+    pub fn handle_line(&mut self, line: &str) -> Option<String> {
+        if line.trim().is_empty() {
+            return None;
+        }
+        let reply = match parse_line(line).and_then(|command| self.service.place_order(command)) {
+            Ok(order) => format!("ok {} {}", order.id, order.total),
+            Err(OrderError::PaymentDeclined { reason }) => {
+                format!("error PaymentDeclined {}", reason.code())
+            }
+            Err(error) => format!("error {error}"),
+        };
+        Some(reply)
+    }
+
+    // Reads until end of input. Only I/O errors and a cancellation stop it.
+    pub fn run(&mut self, input: impl BufRead, output: impl Write) -> io::Result<()> {
+        self.run_with(input, output, Self::handle_line)
+    }
+
+    fn run_with(
+        &mut self,
+        mut input: impl BufRead,
+        mut output: impl Write,
+        handle_line: fn(&mut Self, &str) -> Option<String>,
+    ) -> io::Result<()> {
+        let mut line = Vec::new();
+        let mut processed = 0;
+        loop {
+            if let Err(cancelled) = self.cancel.check(processed) {
+                writeln!(output, "error {cancelled}")?;
+                return Ok(());
+            }
+            line.clear();
+            let read = (&mut input)
+                .take(MAX_LINE_BYTES as u64 + 1)
+                .read_until(b'\n', &mut line)?;
+            if read == 0 {
+                return Ok(());
+            }
+            let reply = if line.len() > MAX_LINE_BYTES {
+                skip_rest_of_line(&mut input)?;
+                Some(format!("error {}", OrderError::InvalidOrder))
+            } else {
+                match std::str::from_utf8(&line) {
+                    Ok(text) => handle_line(self, text.trim_end_matches(['\n', '\r'])),
+                    Err(_) => Some(format!("error {}", OrderError::InvalidOrder)),
+                }
+            };
+            if let Some(reply) = reply {
+                writeln!(output, "{reply}")?;
+            }
+            processed += 1;
+        }
+    }
+}
+
+impl<S: PlaceOrderUseCase + AmendOrderUseCase> CliAdapter<S> {
+    // "amend ..." amends; every other line goes to handle_line.
+    pub fn handle_line_with_amendments(&mut self, line: &str) -> Option<String> {
+        if !line.trim_start().starts_with("amend ") {
+            return self.handle_line(line);
+        }
+        let reply =
+            match parse_amend_line(line).and_then(|command| self.service.amend_order(command)) {
+                Ok(order) => format!("ok {} amended", order.id),
+                Err(OrderError::InvalidFields { errors }) => format!("error {errors}"),
+                Err(error) => format!("error {error}"),
+            };
+        Some(reply)
+    }
+
+    pub fn run_with_amendments(
+        &mut self,
+        input: impl BufRead,
+        output: impl Write,
+    ) -> io::Result<()> {
+        self.run_with(input, output, Self::handle_line_with_amendments)
+    }
+}
+
+fn skip_rest_of_line(input: &mut impl BufRead) -> io::Result<()> {
+    loop {
+        let buffer = input.fill_buf()?;
+        if buffer.is_empty() {
+            return Ok(());
+        }
+        if let Some(newline) = buffer.iter().position(|&b| b == b'\n') {
+            input.consume(newline + 1);
+            return Ok(());
+        }
+        let len = buffer.len();
+        input.consume(len);
+    }
+}
+
+// "place <name>=<cents>, <name>=<cents>, ..."
+// A name may contain '=': the price is whatever follows the last one.
+pub fn parse_line(line: &str) -> Result<PlaceOrder, OrderError> {
+    let items = line
+        .trim()
+        .strip_prefix("place ")
+        .ok_or(OrderError::InvalidOrder)?;
+    // Counted before anything is allocated per item.
+    inbound::check_item_count(items.split(',').take(MAX_ITEMS + 1).count())?;
+    let items = items
+        .split(',')
+        .map(|item| {
+            let (name, cents) = item.rsplit_once('=').ok_or(OrderError::InvalidOrder)?;
+            inbound::line_item(name, cents.trim())
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(PlaceOrder {
+        items,
+        placed_at: None,
+    })
+}
+
+// "amend <id> address=<street>|<city>|<postal code>|<country>; note=<text>"
+// The id is 1 or #000001. Only the shape is checked here: what is in each
+// field is for the domain to judge.
+pub fn parse_amend_line(line: &str) -> Result<AmendOrder, OrderError> {
+    let rest = line
+        .trim()
+        .strip_prefix("amend ")
+        .ok_or(OrderError::InvalidOrder)?
+        .trim_start();
+    let (id, rest) = rest.split_once(' ').unwrap_or((rest, ""));
+    let digits = id.strip_prefix('#').unwrap_or(id);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(OrderError::InvalidOrder);
+    }
+    let order_id = OrderId(digits.parse().map_err(|_| OrderError::InvalidOrder)?);
+    let mut rest = rest.trim_start();
+    let mut new_address = None;
+    if let Some(address) = rest.strip_prefix("address=") {
+        let (address, after) = address.split_once(';').unwrap_or((address, ""));
+        let parts: Vec<&str> = address.split('|').map(str::trim).collect();
+        let [street, city, postal_code, country] = parts[..] else {
+            return Err(OrderError::InvalidOrder);
+        };
+        new_address = Some(Address {
+            street: street.to_string(),
+            city: city.to_string(),
+            postal_code: postal_code.to_string(),
+            country: country.to_string(),
+        });
+        rest = after.trim_start();
+    }
+    let gift_note = match rest.strip_prefix("note=") {
+        Some(note) => Some(note.to_string()),
+        None if rest.is_empty() => None,
+        None => return Err(OrderError::InvalidOrder),
+    };
+    Ok(AmendOrder {
+        order_id,
+        new_address,
+        gift_note,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, Money};
+
+    #[test]
+    fn parses_items_separated_by_commas() {
+        let command = parse_line("place Keyboard=12999, USB cable=999").unwrap();
+        assert_eq!(
+            command.items,
+            vec![
+                LineItem {
+                    name: "Keyboard".to_string(),
+                    price: Money(12999),
+                },
+                LineItem {
+                    name: "USB cable".to_string(),
+                    price: Money(999),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_commands_and_malformed_items() {
+        for line in [
+            "ship 1",
+            "place",
+            "place Keyboard",
+            "place =5",
+            "place A=1,",
+        ] {
+            assert!(parse_line(line).is_err(), "{line:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn parses_an_amendment_with_either_part_or_both() {
+        let command = parse_amend_line(
+            "amend #000001 address=1 Main St|Springfield|12345|US; note=Hi; there",
+        )
+        .unwrap();
+        assert_eq!(command.order_id, OrderId(1));
+        assert_eq!(command.new_address.unwrap().country, "US");
+        assert_eq!(command.gift_note.as_deref(), Some("Hi; there"));
+
+        let command = parse_amend_line("amend 12 note=").unwrap();
+        assert_eq!(command.order_id, OrderId(12));
+        assert_eq!(command.new_address, None);
+        assert_eq!(command.gift_note.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn rejects_amendments_of_the_wrong_shape() {
+        for line in [
+            "amend",
+            "amend x note=hi",
+            "amend #abc note=hi",
+            "amend 1 address=1 Main St|Springfield",
+            "amend 1 colour=red",
+            "amend 1 address=a|b|c|d; colour=red",
+        ] {
+            assert!(parse_amend_line(line).is_err(), "{line:?} was accepted");
+        }
+    }
+}
@@ -0,0 +1,20 @@
+// --- Clock adapters ---
+// The real clock. Tests use testing::SteppingClock instead.
+use crate::domain::Timestamp;
+use crate::ports::{Capability, Clock};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Timestamp {
+        // A clock set before 1970 is not worth an error path.
+        let secs = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        Timestamp(secs)
+    }
+}
+
+impl Capability for SystemClock {}
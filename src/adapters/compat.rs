@@ -0,0 +1,149 @@
+// --- V1Compat (adapter shim) ---
+// Sender used to take the whole Order; it now takes an OrderConfirmation.
+// Adapters still written against the old port (SenderV1) do not have to be
+// migrated on the same day: wrap them in V1Compat and they plug into the
+// new port.
+//
+// The shim rebuilds an Order from the confirmation. It cannot be the real
+// one, only what an old adapter could reasonably look at: id, items, total.
+// Senders are called once the order is charged, hence the Paid status.
+//
+// Every call is counted through the Metrics port, so you can tell when the
+// last v1 adapter is gone, and the first call logs a warning, the runtime
+// cousin of the deprecation lint.
+#![allow(deprecated)]
+
+use super::Console;
+use crate::domain::{Currency, Order, OrderConfirmation, OrderError, OrderStatus};
+use crate::ports::{Capability, Level, Metrics, Sender, SenderV1};
+use std::cell::Cell;
+
+pub const V1_SEND_COUNTER: &str = "sender.v1_compat.send";
+
+pub struct V1Compat<'a, S: SenderV1> {
+    inner: S,
+    metrics: &'a dyn Metrics,
+    warned: Cell<bool>,
+    console: Console,
+}
+
+impl<'a, S: SenderV1> V1Compat<'a, S> {
+    pub fn new(inner: S, metrics: &'a dyn Metrics) -> Self {
+        Self {
+            inner,
+            metrics,
+            warned: Cell::new(false),
+            console: Console::default(),
+        }
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: SenderV1> Sender for V1Compat<'_, S> {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        if !self.warned.replace(true) {
+            self.console.log(
+                Level::Warn,
+                "V1Compat",
+                format_args!(
+                    "  [V1Compat] warning: {} still implements the deprecated SenderV1 port",
+                    std::any::type_name::<S>()
+                ),
+            );
+        }
+        self.metrics.increment(V1_SEND_COUNTER);
+
+        let order = Order {
+            id: confirmation.order_id,
+            items: confirmation.items.clone(),
+            total: confirmation.total,
+            // A confirmation does not say: the v1 port only ever saw dollars.
+            currency: Currency::default(),
+            status: OrderStatus::Paid,
+            shipments: Vec::new(),
+            placed_at: None,
+            uuid: None,
+            reference: confirmation.reference.clone(),
+            version: 0,
+            approval: None,
+            shipping_address: None,
+            gift_note: None,
+            notes: Vec::new(),
+            attachments: Vec::new(),
+        };
+        self.inner.send(&order)
+    }
+}
+
+impl<S: SenderV1> Capability for V1Compat<'_, S> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::VecLogger;
+    use crate::adapters::metrics::InMemoryMetrics;
+    use crate::domain::{LineItem, Money, OrderId};
+    use std::cell::RefCell;
+
+    #[derive(Default)]
+    struct LegacySender {
+        seen: RefCell<Vec<Order>>,
+    }
+
+    impl SenderV1 for LegacySender {
+        fn send(&self, order: &Order) -> Result<(), OrderError> {
+            self.seen.borrow_mut().push(order.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn old_adapter_sees_a_paid_order_and_every_call_is_counted() {
+        let metrics = InMemoryMetrics::new();
+        let sender = V1Compat::new(LegacySender::default(), &metrics);
+        let mut order = Order::new(
+            OrderId(4),
+            vec![LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            }],
+        )
+        .unwrap();
+        order.mark_paid().unwrap();
+
+        sender.send(&order.confirmation()).unwrap();
+        sender.send(&order.confirmation()).unwrap();
+
+        assert_eq!(sender.inner().seen.borrow()[0], order);
+        assert_eq!(metrics.counter(V1_SEND_COUNTER), 2);
+    }
+
+    #[test]
+    fn only_the_first_call_logs_a_warning() {
+        let (metrics, logger) = (InMemoryMetrics::new(), VecLogger::new());
+        let sender = V1Compat::new(LegacySender::default(), &metrics)
+            .with_console(Console::logger(logger.clone()));
+        let confirmation = Order::new(OrderId(4), vec![LineItem::new("Mouse", Money(2500))])
+            .unwrap()
+            .confirmation();
+
+        sender.send(&confirmation).unwrap();
+        sender.send(&confirmation).unwrap();
+
+        let entries = logger.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(
+            (entries[0].level, entries[0].target.as_str()),
+            (Level::Warn, "V1Compat")
+        );
+        assert!(entries[0].message.contains("deprecated SenderV1 port"));
+    }
+}
@@ -0,0 +1,46 @@
+// Why an adapter could not be built.
+// An adapter whose settings cannot work refuses to exist: a malformed URL
+// or an empty API key is reported when the application starts, not in the
+// middle of the first order.
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    InvalidUrl {
+        url: String,
+        reason: &'static str,
+    },
+    // `name` is what the credential is, never its value.
+    MissingCredential {
+        adapter: &'static str,
+        name: &'static str,
+    },
+    IoError {
+        path: PathBuf,
+        kind: io::ErrorKind,
+    },
+    // Read, but not understood: starting empty would lose what it holds.
+    InvalidFile {
+        path: PathBuf,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::InvalidUrl { url, reason } => write!(f, "invalid URL {url:?}: {reason}"),
+            ConfigError::MissingCredential { adapter, name } => {
+                write!(f, "{adapter} needs a non-empty {name}")
+            }
+            ConfigError::IoError { path, kind } => {
+                write!(f, "cannot open {}: {kind}", path.display())
+            }
+            ConfigError::InvalidFile { path, reason } => {
+                write!(f, "cannot read {}: {reason}", path.display())
+            }
+        }
+    }
+}
@@ -0,0 +1,263 @@
+// --- Confirmations on the wire ---
+// What a webhook posts and an outbox keeps is a versioned JSON envelope, so
+// that consumers written for an older version keep reading newer payloads:
+//
+//     v1  {"order_id":3,"total_cents":199,"items":[{"name":"Ruler","price_cents":199}]}
+//     v2  {"v":2, the same fields, "tax_cents":0,"currency":"USD","customer_email":null}
+//
+// A version only ever adds fields, and a reader ignores the ones it does
+// not know: a v1 consumer reads a v2 payload as the v1 it always was. The
+// first payloads had no "v" at all; they are v1.
+//
+// An optional field that older readers can do without is added to the
+// current version instead of starting a new one: "reference", written only
+// for an order that has one, is still v2, and every v2 reader deployed
+// before it keeps reading the payloads that carry it.
+//
+// parse_any_confirmation reads every version this crate knows, and upgrades
+// the older ones, a field missing from them taking its default: no tax,
+// USD, no email, no reference. A payload from a newer producer is refused
+// rather than guessed at.
+use super::json::{self, Value};
+use crate::domain::{Currency, LineItem, Money, OrderConfirmation, OrderId, OrderReference};
+use std::fmt;
+
+// The version confirmation_envelope writes.
+pub const CONFIRMATION_VERSION: u64 = 2;
+
+// The first version, as its consumers still read it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderConfirmationV1 {
+    pub order_id: OrderId,
+    pub items: Vec<LineItem>,
+    pub total: Money,
+}
+
+// The current version is the domain's own.
+pub type OrderConfirmationV2 = OrderConfirmation;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WireError {
+    // Not JSON, or not a confirmation.
+    Malformed,
+    MissingField(&'static str),
+    // Written by a producer newer than this crate.
+    UnknownVersion { version: u64, newest: u64 },
+}
+
+impl fmt::Display for WireError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+impl OrderConfirmationV1 {
+    // Any version's payload, read the way a v1 consumer does.
+    pub fn from_json(text: &str) -> Result<Self, WireError> {
+        v1(&object(text)?)
+    }
+
+    pub fn upgrade(self) -> OrderConfirmationV2 {
+        OrderConfirmation {
+            order_id: self.order_id,
+            items: self.items,
+            total: self.total,
+            tax: Money(0),
+            currency: Currency::default(),
+            customer_email: None,
+            reference: None,
+        }
+    }
+}
+
+// {"v":2,...}, the newest version.
+pub fn confirmation_envelope(confirmation: &OrderConfirmation) -> String {
+    let items: Vec<String> = confirmation
+        .items
+        .iter()
+        .map(|item| {
+            format!(
+                r#"{{"name":"{}","price_cents":{}}}"#,
+                json::escape(&item.name),
+                item.price.0
+            )
+        })
+        .collect();
+    let email = confirmation
+        .customer_email
+        .as_ref()
+        .map_or("null".to_string(), |email| {
+            format!(r#""{}""#, json::escape(email))
+        });
+    let reference = confirmation
+        .reference
+        .as_ref()
+        .map_or(String::new(), |reference| {
+            format!(r#","reference":"{}""#, json::escape(&reference.0))
+        });
+    format!(
+        r#"{{"v":{CONFIRMATION_VERSION},"order_id":{},"total_cents":{},"items":[{}],"tax_cents":{},"currency":"{}","customer_email":{email}{reference}}}"#,
+        confirmation.order_id.0,
+        confirmation.total.0,
+        items.join(","),
+        confirmation.tax.0,
+        confirmation.currency,
+    )
+}
+
+// A payload of any version up to VERSION, upgraded to the newest.
+pub fn parse_any_confirmation(bytes: &[u8]) -> Result<OrderConfirmation, WireError> {
+    let text = std::str::from_utf8(bytes).map_err(|_| WireError::Malformed)?;
+    let fields = object(text)?;
+    let version = match field(&fields, "v") {
+        None => 1,
+        Some(Value::Number(version)) => *version,
+        Some(_) => return Err(WireError::Malformed),
+    };
+    match version {
+        1 => Ok(v1(&fields)?.upgrade()),
+        2 => v2(&fields),
+        version => Err(WireError::UnknownVersion {
+            version,
+            newest: CONFIRMATION_VERSION,
+        }),
+    }
+}
+
+fn object(text: &str) -> Result<Vec<(String, Value)>, WireError> {
+    match json::parse(text) {
+        Some(Value::Object(fields)) => Ok(fields),
+        _ => Err(WireError::Malformed),
+    }
+}
+
+fn field<'a>(fields: &'a [(String, Value)], name: &str) -> Option<&'a Value> {
+    fields
+        .iter()
+        .find(|(key, _)| key == name)
+        .map(|(_, value)| value)
+}
+
+fn cents(fields: &[(String, Value)], name: &'static str) -> Result<u32, WireError> {
+    match field(fields, name) {
+        Some(Value::Number(n)) => u32::try_from(*n).map_err(|_| WireError::Malformed),
+        Some(_) => Err(WireError::Malformed),
+        None => Err(WireError::MissingField(name)),
+    }
+}
+
+fn v1(fields: &[(String, Value)]) -> Result<OrderConfirmationV1, WireError> {
+    let Some(Value::Array(lines)) = field(fields, "items") else {
+        return Err(WireError::MissingField("items"));
+    };
+    let items = lines
+        .iter()
+        .map(|line| {
+            let Value::Object(line) = line else {
+                return Err(WireError::Malformed);
+            };
+            let Some(Value::String(name)) = field(line, "name") else {
+                return Err(WireError::MissingField("name"));
+            };
+            Ok(LineItem::new(name, Money(cents(line, "price_cents")?)))
+        })
+        .collect::<Result<_, _>>()?;
+    Ok(OrderConfirmationV1 {
+        order_id: OrderId(cents(fields, "order_id")?),
+        items,
+        total: Money(cents(fields, "total_cents")?),
+    })
+}
+
+// v1, and what v2 added, each with its default when it is missing.
+fn v2(fields: &[(String, Value)]) -> Result<OrderConfirmation, WireError> {
+    let mut confirmation = v1(fields)?.upgrade();
+    if field(fields, "tax_cents").is_some() {
+        confirmation.tax = Money(cents(fields, "tax_cents")?);
+    }
+    match field(fields, "currency") {
+        None => {}
+        Some(Value::String(code)) => {
+            confirmation.currency = Currency::from_code(code).ok_or(WireError::Malformed)?;
+        }
+        Some(_) => return Err(WireError::Malformed),
+    }
+    confirmation.customer_email = optional_text(fields, "customer_email")?;
+    confirmation.reference = optional_text(fields, "reference")?.map(OrderReference);
+    Ok(confirmation)
+}
+
+// Missing and null alike are none.
+fn optional_text(fields: &[(String, Value)], name: &str) -> Result<Option<String>, WireError> {
+    match field(fields, name) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(text)) => Ok(Some(text.clone())),
+        Some(_) => Err(WireError::Malformed),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ruler() -> OrderConfirmation {
+        OrderConfirmation {
+            order_id: OrderId(3),
+            items: vec![LineItem::new("12\" \"Ruler\"", Money(199))],
+            total: Money(199),
+            tax: Money(33),
+            currency: Currency::Gbp,
+            customer_email: Some("ada@example.test".to_string()),
+            reference: Some(OrderReference("2024-000003".to_string())),
+        }
+    }
+
+    #[test]
+    fn the_envelope_reads_back_whole() {
+        let mut plain = ruler();
+        plain.customer_email = None;
+        plain.reference = None;
+
+        for confirmation in [ruler(), plain] {
+            let text = confirmation_envelope(&confirmation);
+            assert!(text.starts_with(r#"{"v":2,"#));
+            assert_eq!(parse_any_confirmation(text.as_bytes()), Ok(confirmation));
+        }
+    }
+
+    #[test]
+    fn a_v2_without_its_new_fields_takes_their_defaults() {
+        let bare = r#"{"v":2,"order_id":3,"total_cents":199,"items":[]}"#;
+
+        let confirmation = parse_any_confirmation(bare.as_bytes()).unwrap();
+        assert_eq!(confirmation.tax, Money(0));
+        assert_eq!(confirmation.currency, Currency::Usd);
+        assert_eq!(confirmation.customer_email, None);
+        assert_eq!(confirmation.reference, None);
+    }
+
+    #[test]
+    fn what_is_not_a_confirmation_is_refused() {
+        for text in [
+            "",
+            "[]",
+            r#"{"v":"2","order_id":3,"total_cents":199,"items":[]}"#,
+            r#"{"v":2,"order_id":3,"total_cents":199,"items":[],"currency":"XXX"}"#,
+            r#"{"order_id":3,"total_cents":4294967296,"items":[]}"#,
+        ] {
+            assert_eq!(
+                parse_any_confirmation(text.as_bytes()),
+                Err(WireError::Malformed),
+                "{text}"
+            );
+        }
+        assert_eq!(
+            parse_any_confirmation(br#"{"order_id":3,"items":[]}"#),
+            Err(WireError::MissingField("total_cents"))
+        );
+        assert_eq!(
+            parse_any_confirmation(&[0xff, 0xfe]),
+            Err(WireError::Malformed)
+        );
+    }
+}
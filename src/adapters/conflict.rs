@@ -0,0 +1,105 @@
+// --- Conflict resolvers ---
+// LastWriteWins: the edit arriving last is the one kept, whatever the other
+// did. Simple, and right when one client is the authority anyway.
+//
+// ItemUnionMerge: when both sides only added items, the draft gets both
+// additions, theirs first. Anything else is a conflict it will not guess
+// at: a removed item (was it meant to stay removed?) or the same item
+// added on both sides (once or twice?).
+use crate::domain::{LineItem, Order};
+use crate::ports::{Capability, ConflictResolver, Resolution};
+
+pub struct LastWriteWins;
+
+impl ConflictResolver for LastWriteWins {
+    fn resolve(&self, _base: &Order, _ours: &Order, _theirs: &Order) -> Resolution {
+        Resolution::TakeOurs
+    }
+}
+
+impl Capability for LastWriteWins {}
+
+pub struct ItemUnionMerge;
+
+impl ConflictResolver for ItemUnionMerge {
+    fn resolve(&self, base: &Order, ours: &Order, theirs: &Order) -> Resolution {
+        let (Some(added_by_us), Some(added_by_them)) = (
+            additions(&base.items, &ours.items),
+            additions(&base.items, &theirs.items),
+        ) else {
+            return Resolution::Fail;
+        };
+        if added_by_us.iter().any(|item| added_by_them.contains(item)) {
+            return Resolution::Fail;
+        }
+        let mut merged = theirs.clone();
+        merged.items.extend(added_by_us);
+        Resolution::Merged(Box::new(merged))
+    }
+}
+
+impl Capability for ItemUnionMerge {}
+
+// What `edited` has on top of `base`, counting identical lines one by one,
+// in `edited`'s order. None when it lost any line of `base`.
+fn additions(base: &[LineItem], edited: &[LineItem]) -> Option<Vec<LineItem>> {
+    let mut left = edited.to_vec();
+    for item in base {
+        let at = left.iter().position(|line| line == item)?;
+        left.remove(at);
+    }
+    Some(left)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Money, OrderId};
+
+    fn draft(items: &[(&str, u32)]) -> Order {
+        let items = items
+            .iter()
+            .map(|&(name, cents)| LineItem::new(name, Money(cents)))
+            .collect();
+        Order::new(OrderId(1), items).unwrap()
+    }
+
+    fn names(resolution: Resolution) -> Vec<String> {
+        match resolution {
+            Resolution::Merged(order) => order.items.into_iter().map(|item| item.name).collect(),
+            other => panic!("expected a merge, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn additions_on_both_sides_are_merged() {
+        let base = draft(&[("Pen", 150), ("Pen", 150)]);
+        let ours = draft(&[("Pen", 150), ("Ink", 300), ("Pen", 150)]);
+        let theirs = draft(&[("Pen", 150), ("Pen", 150), ("Pad", 400), ("Pen", 150)]);
+
+        assert_eq!(
+            names(ItemUnionMerge.resolve(&base, &ours, &theirs)),
+            ["Pen", "Pen", "Pad", "Pen", "Ink"]
+        );
+    }
+
+    #[test]
+    fn removals_and_twin_additions_are_not_guessed_at() {
+        let base = draft(&[("Pen", 150), ("Pad", 400)]);
+        let removed = draft(&[("Pen", 150)]);
+        let with_ink = draft(&[("Pen", 150), ("Pad", 400), ("Ink", 300)]);
+
+        assert_eq!(
+            ItemUnionMerge.resolve(&base, &removed, &with_ink),
+            Resolution::Fail
+        );
+        assert_eq!(
+            ItemUnionMerge.resolve(&base, &with_ink, &removed),
+            Resolution::Fail
+        );
+        assert_eq!(
+            ItemUnionMerge.resolve(&base, &with_ink, &with_ink),
+            Resolution::Fail
+        );
+    }
+}
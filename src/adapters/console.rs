@@ -0,0 +1,230 @@
+// Where the simulated adapters write their "  [Name] ..." lines.
+// Every line goes through a Logger: standard output by default, any
+// io::Write, nothing at all, or a VecLogger a test can read back entry by
+// entry.
+//
+// A handle, cheap to clone: several adapters of one scenario share the same
+// logger, and their lines come out interleaved in call order. It is Sync,
+// like the adapters holding it, so services can still move to a consumer
+// thread.
+use crate::ports::{Level, Logger};
+use std::fmt;
+use std::io::{self, Write};
+use std::sync::{Arc, Mutex, PoisonError};
+
+#[derive(Clone)]
+pub struct Console {
+    logger: Arc<dyn Logger + Send + Sync>,
+}
+
+impl Console {
+    pub fn stdout() -> Self {
+        Self::logger(ConsoleLogger::stdout())
+    }
+
+    pub fn to(writer: impl Write + Send + 'static) -> Self {
+        Self::logger(ConsoleLogger::to(writer))
+    }
+
+    pub fn silent() -> Self {
+        Self::logger(SilentLogger)
+    }
+
+    pub fn logger(logger: impl Logger + Send + Sync + 'static) -> Self {
+        Self {
+            logger: Arc::new(logger),
+        }
+    }
+
+    // An Info line, its target read from the "[Name]" it starts with.
+    pub fn line(&self, args: fmt::Arguments<'_>) {
+        let line = args.to_string();
+        self.logger
+            .log(Level::Info, target_of(&line), format_args!("{line}"));
+    }
+
+    pub fn log(&self, level: Level, target: &str, args: fmt::Arguments<'_>) {
+        self.logger.log(level, target, args);
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::stdout()
+    }
+}
+
+impl fmt::Debug for Console {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Console")
+    }
+}
+
+// "  [InMemory] Saving order #000001" -> "InMemory"; "" without brackets.
+fn target_of(line: &str) -> &str {
+    line.trim_start()
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once(']'))
+        .map_or("", |(target, _)| target)
+}
+
+// Prints every message as it is. Standard output, Warn and Error on
+// standard error; or all of them to one writer.
+pub struct ConsoleLogger {
+    // None is stdout, looked up on every write so the test harness can
+    // capture it as usual.
+    out: Option<Mutex<Box<dyn Write + Send>>>,
+}
+
+impl ConsoleLogger {
+    pub fn stdout() -> Self {
+        Self { out: None }
+    }
+
+    pub fn to(writer: impl Write + Send + 'static) -> Self {
+        Self {
+            out: Some(Mutex::new(Box::new(writer))),
+        }
+    }
+}
+
+impl Logger for ConsoleLogger {
+    // Output is best effort: a closed pipe must not fail an order.
+    fn log(&self, level: Level, _target: &str, message: fmt::Arguments<'_>) {
+        match &self.out {
+            None if level >= Level::Warn => eprintln!("{message}"),
+            None => println!("{message}"),
+            Some(out) => {
+                let mut out = out.lock().unwrap_or_else(PoisonError::into_inner);
+                let _ = writeln!(out, "{message}");
+            }
+        }
+    }
+}
+
+// Drops everything: adapters in a test that only looks at the results.
+pub struct SilentLogger;
+
+impl Logger for SilentLogger {
+    fn log(&self, _level: Level, _target: &str, _message: fmt::Arguments<'_>) {}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogEntry {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+// Keeps every entry, for assertions. Clones share the entries: hand one
+// to Console::logger, keep the other to read them.
+#[derive(Clone, Default)]
+pub struct VecLogger(Arc<Mutex<Vec<LogEntry>>>);
+
+impl VecLogger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl Logger for VecLogger {
+    fn log(&self, level: Level, target: &str, message: fmt::Arguments<'_>) {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(LogEntry {
+                level,
+                target: target.to_string(),
+                message: message.to_string(),
+            });
+    }
+}
+
+// An in-memory writer whose clones share one buffer: hand a clone to
+// Console::to, keep the other to read what was written.
+#[derive(Clone, Default)]
+pub struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn contents(&self) -> String {
+        let bytes = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+        self.0
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .extend_from_slice(bytes);
+        Ok(bytes.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clones_write_to_the_same_buffer() {
+        let buffer = SharedBuffer::new();
+        let console = Console::to(buffer.clone());
+        let other = console.clone();
+
+        console.line(format_args!("  [A] one"));
+        other.line(format_args!("  [B] {}", 2));
+
+        assert_eq!(buffer.contents(), "  [A] one\n  [B] 2\n");
+    }
+
+    #[test]
+    fn lines_are_info_entries_named_after_their_prefix() {
+        let logger = VecLogger::new();
+        let console = Console::logger(logger.clone());
+
+        console.line(format_args!("  [InMemory] Saving order {}", 7));
+        console.line(format_args!("no prefix"));
+        console.log(Level::Warn, "V1Compat", format_args!("careful"));
+
+        let entry = |level, target: &str, message: &str| LogEntry {
+            level,
+            target: target.to_string(),
+            message: message.to_string(),
+        };
+        assert_eq!(
+            logger.entries(),
+            vec![
+                entry(Level::Info, "InMemory", "  [InMemory] Saving order 7"),
+                entry(Level::Info, "", "no prefix"),
+                entry(Level::Warn, "V1Compat", "careful"),
+            ]
+        );
+    }
+
+    #[test]
+    fn a_writer_gets_every_level() {
+        let buffer = SharedBuffer::new();
+        let console = Console::to(buffer.clone());
+
+        console.log(Level::Debug, "A", format_args!("debug"));
+        console.log(Level::Error, "A", format_args!("error"));
+
+        assert_eq!(buffer.contents(), "debug\nerror\n");
+    }
+}
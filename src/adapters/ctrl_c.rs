@@ -0,0 +1,58 @@
+// --- Ctrl-C (feature "ctrl-c", Unix only) ---
+// Turns SIGINT into a cancelled CancelToken: Ctrl-C stops the command under
+// way, between two items, instead of the whole process. With no command
+// running it still ends the process, as a shell user expects.
+//
+// A signal handler may do next to nothing safely, so it only sets a flag;
+// a watcher thread, started by the first arm(), does the rest.
+//
+// Made for Repl::with_interrupt:
+//
+//     Repl::new(facade).with_interrupt(ctrl_c::arm)
+use crate::ports::CancelToken;
+use std::ffi::c_int;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, Once, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+const SIGINT: c_int = 2;
+// What a shell reports for a process ended by SIGINT: 128 + 2.
+const INTERRUPTED: i32 = 130;
+
+static PRESSED: AtomicBool = AtomicBool::new(false);
+static ARMED: Mutex<Option<CancelToken>> = Mutex::new(None);
+static INSTALL: Once = Once::new();
+
+unsafe extern "C" {
+    fn signal(signum: c_int, handler: extern "C" fn(c_int)) -> usize;
+}
+
+extern "C" fn on_sigint(_signum: c_int) {
+    PRESSED.store(true, Ordering::SeqCst);
+}
+
+// The token the next Ctrl-C cancels, or None for "end the process".
+pub fn arm(token: Option<&CancelToken>) {
+    INSTALL.call_once(|| {
+        // SAFETY: on_sigint only stores to an atomic, which is
+        // async-signal-safe; nothing else runs in the handler.
+        unsafe {
+            signal(SIGINT, on_sigint);
+        }
+        thread::spawn(watch);
+    });
+    *ARMED.lock().unwrap_or_else(PoisonError::into_inner) = token.cloned();
+}
+
+fn watch() {
+    loop {
+        thread::sleep(Duration::from_millis(50));
+        if PRESSED.swap(false, Ordering::SeqCst) {
+            match &*ARMED.lock().unwrap_or_else(PoisonError::into_inner) {
+                Some(token) => token.cancel(),
+                None => std::process::exit(INTERRUPTED),
+            }
+        }
+    }
+}
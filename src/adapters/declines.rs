@@ -0,0 +1,109 @@
+// Scripted card declines, for the simulated gateways.
+// A test of a payment flow needs the provider to say no, and to say why.
+// MockPaymentGateway and StripePaymentGateway take a DeclineScript telling
+// them which amounts to decline, and with what reason.
+//
+// By the last two digits of the amount in cents, as payment providers'
+// test modes do it:
+//
+//     ...01  InsufficientFunds      $12.01, $999.01
+//     ...02  CardExpired
+//     ...03  SuspectedFraud
+//     ...04  TryAgainLater
+//
+// Any other amount goes through. An amount named in decline() is declined
+// with its own reason, whatever its digits say.
+//
+//     let gateway = MockPaymentGateway::new()
+//         .with_declines(DeclineScript::by_cents().decline(Money(5_000), DeclineReason::CardExpired));
+use crate::domain::{DeclineReason, Money};
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeclineScript {
+    amounts: Vec<(Money, DeclineReason)>,
+    by_cents: bool,
+}
+
+impl DeclineScript {
+    // Declines nothing, until told which amounts to.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The convention above.
+    pub fn by_cents() -> Self {
+        Self {
+            amounts: Vec::new(),
+            by_cents: true,
+        }
+    }
+
+    pub fn decline(mut self, amount: Money, reason: DeclineReason) -> Self {
+        self.amounts.push((amount, reason));
+        self
+    }
+
+    // None: the charge goes through.
+    pub fn reason_for(&self, amount: Money) -> Option<DeclineReason> {
+        if let Some(&(_, reason)) = self.amounts.iter().find(|(named, _)| *named == amount) {
+            return Some(reason);
+        }
+        if !self.by_cents {
+            return None;
+        }
+        match amount.0 % 100 {
+            1 => Some(DeclineReason::InsufficientFunds),
+            2 => Some(DeclineReason::CardExpired),
+            3 => Some(DeclineReason::SuspectedFraud),
+            4 => Some(DeclineReason::TryAgainLater),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_last_two_digits_of_the_cents_pick_the_reason() {
+        let script = DeclineScript::by_cents();
+        assert_eq!(
+            script.reason_for(Money(1_201)),
+            Some(DeclineReason::InsufficientFunds)
+        );
+        assert_eq!(
+            script.reason_for(Money(2)),
+            Some(DeclineReason::CardExpired)
+        );
+        assert_eq!(
+            script.reason_for(Money(99_903)),
+            Some(DeclineReason::SuspectedFraud)
+        );
+        assert_eq!(
+            script.reason_for(Money(404)),
+            Some(DeclineReason::TryAgainLater)
+        );
+        for amount in [0, 100, 105, 1_299, 12_999] {
+            assert_eq!(script.reason_for(Money(amount)), None, "{amount}");
+        }
+    }
+
+    #[test]
+    fn an_amount_named_outright_wins_over_its_digits() {
+        let script = DeclineScript::by_cents()
+            .decline(Money(501), DeclineReason::TryAgainLater)
+            .decline(Money(5_000), DeclineReason::CardExpired);
+        assert_eq!(
+            script.reason_for(Money(501)),
+            Some(DeclineReason::TryAgainLater)
+        );
+        assert_eq!(
+            script.reason_for(Money(5_000)),
+            Some(DeclineReason::CardExpired)
+        );
+
+        let named_only = DeclineScript::new().decline(Money(5_000), DeclineReason::CardExpired);
+        assert_eq!(named_only.reason_for(Money(1_201)), None);
+    }
+}
@@ -0,0 +1,139 @@
+// --- Error reporters ---
+// Where services send the description of every failure.
+// In real life: Sentry, a log pipeline, an alerting system...
+use super::{Console, json};
+use crate::ports::{Capability, ErrorContext, ErrorReporter, Port};
+use std::sync::{Mutex, PoisonError};
+
+// Keeps every report, for tests and for "last errors" screens.
+// A Mutex rather than a RefCell: services driven from another thread
+// (see adapters::queue) share their reporter.
+#[derive(Default)]
+pub struct InMemoryErrorReporter {
+    reports: Mutex<Vec<ErrorContext>>,
+}
+
+impl InMemoryErrorReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn reports(&self) -> Vec<ErrorContext> {
+        self.lock().clone()
+    }
+
+    // A panic elsewhere while holding the lock leaves the list intact.
+    fn lock(&self) -> std::sync::MutexGuard<'_, Vec<ErrorContext>> {
+        self.reports.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl ErrorReporter for InMemoryErrorReporter {
+    fn report(&self, context: ErrorContext) {
+        self.lock().push(context);
+    }
+}
+
+impl Capability for InMemoryErrorReporter {}
+
+// One JSON object per line on the console, ready for a log shipper.
+#[derive(Clone, Default)]
+pub struct ConsoleErrorReporter {
+    console: Console,
+}
+
+impl ConsoleErrorReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+}
+
+impl ErrorReporter for ConsoleErrorReporter {
+    fn report(&self, context: ErrorContext) {
+        self.console
+            .line(format_args!("{}", to_json_line(&context)));
+    }
+}
+
+impl Capability for ConsoleErrorReporter {}
+
+pub fn to_json_line(context: &ErrorContext) -> String {
+    let port = match context.port {
+        Some(port) => format!("\"{}\"", port_name(port)),
+        None => "null".to_string(),
+    };
+    let order_id = match context.order_id {
+        Some(id) => id.0.to_string(),
+        None => "null".to_string(),
+    };
+    format!(
+        r#"{{"use_case":"{}","port":{},"operation":"{}","order_id":{},"at":{},"error":"{}"}}"#,
+        json::escape(context.use_case),
+        port,
+        json::escape(context.operation),
+        order_id,
+        context.at.0,
+        json::escape(&context.error.to_string())
+    )
+}
+
+fn port_name(port: Port) -> &'static str {
+    match port {
+        Port::Repository => "repository",
+        Port::Payment => "payment",
+        Port::Sender => "sender",
+        Port::Events => "events",
+        Port::ExchangeRates => "exchange_rates",
+        Port::PendingCharges => "pending_charges",
+        Port::Idempotency => "idempotency",
+        Port::FraudScreen => "fraud_screen",
+        Port::Inventory => "inventory",
+        Port::Blobs => "blobs",
+        Port::Hooks => "hooks",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{OrderError, OrderId, Timestamp};
+
+    #[test]
+    fn json_line_is_pinned() {
+        let context = ErrorContext {
+            use_case: "place_order",
+            port: Some(Port::Payment),
+            operation: "charge",
+            order_id: Some(OrderId(7)),
+            at: Timestamp(1_700_000_000),
+            error: OrderError::PaymentFailed,
+        };
+
+        assert_eq!(
+            to_json_line(&context),
+            r#"{"use_case":"place_order","port":"payment","operation":"charge","order_id":7,"at":1700000000,"error":"PaymentFailed"}"#
+        );
+    }
+
+    #[test]
+    fn domain_failures_have_no_port() {
+        let context = ErrorContext {
+            use_case: "place_order",
+            port: None,
+            operation: "validate",
+            order_id: None,
+            at: Timestamp(0),
+            error: OrderError::InvalidOrder,
+        };
+
+        let line = to_json_line(&context);
+
+        assert!(line.contains(r#""port":null"#));
+        assert!(line.contains(r#""order_id":null"#));
+    }
+}
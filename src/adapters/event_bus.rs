@@ -0,0 +1,316 @@
+// --- In-memory event bus ---
+// One EventPublisher for the service to publish to, any number of
+// subscribers behind it, each picking the events it wants with an
+// EventFilter. A read model, the ops' low-stock notices, a bridge to
+// another bounded context: each subscribes instead of implementing
+// EventPublisher on its own.
+//
+// Every subscriber gets its events in the order they were published. A
+// publish made while the bus is delivering, from another thread or from a
+// subscriber publishing in turn, waits in line: whoever is delivering
+// already delivers it too, after what came before it, and the publish
+// returns at once.
+//
+// A subscriber is on its own: one that fails or panics is reported to the
+// ErrorReporter, when there is one, and the others still get the event.
+// The publisher never hears of it, so publish always succeeds.
+use crate::domain::{InventoryEvent, OrderError, OrderEvent, OrderId};
+use crate::ports::{
+    Capability, Clock, ErrorContext, ErrorReporter, EventPublisher, EventSubscriber, Port,
+};
+use std::collections::VecDeque;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+pub type OrderHandler<'a> = Box<dyn Fn(&OrderEvent) -> Result<(), OrderError> + Send + Sync + 'a>;
+pub type InventoryHandler<'a> =
+    Box<dyn Fn(&InventoryEvent) -> Result<(), OrderError> + Send + Sync + 'a>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SubscriptionId(pub u64);
+
+// Which order events a subscriber gets.
+#[derive(Debug, Clone, Copy)]
+pub enum EventFilter {
+    All,
+    // Everything about one order.
+    Order(OrderId),
+    // The events the function says yes to:
+    // EventFilter::Matching(|event| matches!(event, OrderEvent::OrderPaid { .. })).
+    Matching(fn(&OrderEvent) -> bool),
+}
+
+impl EventFilter {
+    pub fn accepts(&self, event: &OrderEvent) -> bool {
+        match self {
+            EventFilter::All => true,
+            EventFilter::Order(id) => event.order_id() == *id,
+            EventFilter::Matching(accepts) => accepts(event),
+        }
+    }
+}
+
+enum Handler<'a> {
+    Orders(EventFilter, OrderHandler<'a>),
+    Inventory(InventoryHandler<'a>),
+}
+
+struct Subscription<'a> {
+    id: SubscriptionId,
+    handler: Handler<'a>,
+    // Cleared by unsubscribe, so that an event already on its way is not
+    // delivered after it.
+    active: AtomicBool,
+}
+
+enum Published {
+    Order(OrderEvent),
+    Inventory(InventoryEvent),
+}
+
+#[derive(Default)]
+struct Queue {
+    waiting: VecDeque<Published>,
+    delivering: bool,
+}
+
+struct Telemetry<'a> {
+    reporter: &'a (dyn ErrorReporter + Sync),
+    clock: &'a (dyn Clock + Sync),
+}
+
+pub struct InMemoryEventBus<'a> {
+    subscriptions: Mutex<Vec<Arc<Subscription<'a>>>>,
+    queue: Mutex<Queue>,
+    next_id: AtomicU64,
+    telemetry: Option<Telemetry<'a>>,
+}
+
+impl Default for InMemoryEventBus<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<'a> InMemoryEventBus<'a> {
+    pub fn new() -> Self {
+        Self {
+            subscriptions: Mutex::new(Vec::new()),
+            queue: Mutex::new(Queue::default()),
+            next_id: AtomicU64::new(1),
+            telemetry: None,
+        }
+    }
+
+    // Where failing subscribers are reported, as the port Events.
+    pub fn with_error_reporter(
+        mut self,
+        reporter: &'a (dyn ErrorReporter + Sync),
+        clock: &'a (dyn Clock + Sync),
+    ) -> Self {
+        self.telemetry = Some(Telemetry { reporter, clock });
+        self
+    }
+
+    pub fn subscribe(&self, filter: EventFilter, handler: OrderHandler<'a>) -> SubscriptionId {
+        self.add(Handler::Orders(filter, handler))
+    }
+
+    // Every inventory event.
+    pub fn subscribe_inventory(&self, handler: InventoryHandler<'a>) -> SubscriptionId {
+        self.add(Handler::Inventory(handler))
+    }
+
+    // An EventSubscriber, such as a read model, fed the events `filter`
+    // accepts. It stays in its Mutex for whoever reads it.
+    pub fn feed<S: EventSubscriber + Send>(
+        &self,
+        filter: EventFilter,
+        subscriber: &'a Mutex<S>,
+    ) -> SubscriptionId {
+        self.subscribe(
+            filter,
+            Box::new(move |event| {
+                subscriber
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .on_event(event);
+                Ok(())
+            }),
+        )
+    }
+
+    // Takes effect at once, even for an event being delivered. False when
+    // there was no such subscription.
+    pub fn unsubscribe(&self, id: SubscriptionId) -> bool {
+        let mut subscriptions = self.subscriptions();
+        let Some(at) = subscriptions.iter().position(|s| s.id == id) else {
+            return false;
+        };
+        subscriptions
+            .remove(at)
+            .active
+            .store(false, Ordering::SeqCst);
+        true
+    }
+
+    pub fn subscriber_count(&self) -> usize {
+        self.subscriptions().len()
+    }
+
+    fn add(&self, handler: Handler<'a>) -> SubscriptionId {
+        let id = SubscriptionId(self.next_id.fetch_add(1, Ordering::SeqCst));
+        self.subscriptions().push(Arc::new(Subscription {
+            id,
+            handler,
+            active: AtomicBool::new(true),
+        }));
+        id
+    }
+
+    fn enqueue(&self, event: Published) {
+        {
+            let mut queue = self.queue();
+            queue.waiting.push_back(event);
+            if queue.delivering {
+                return;
+            }
+            queue.delivering = true;
+        }
+        loop {
+            let next = {
+                let mut queue = self.queue();
+                let next = queue.waiting.pop_front();
+                queue.delivering = next.is_some();
+                next
+            };
+            match next {
+                Some(event) => self.deliver(&event),
+                None => return,
+            }
+        }
+    }
+
+    // To the subscribers as they are now, none of the locks held, so that
+    // a subscriber may publish, subscribe or unsubscribe.
+    fn deliver(&self, event: &Published) {
+        let subscriptions = self.subscriptions().clone();
+        for subscription in subscriptions {
+            if !subscription.active.load(Ordering::SeqCst) {
+                continue;
+            }
+            let (operation, order_id) = match event {
+                Published::Order(event) => ("publish", Some(event.order_id())),
+                Published::Inventory(_) => ("publish_inventory", None),
+            };
+            let outcome =
+                panic::catch_unwind(AssertUnwindSafe(|| match (&subscription.handler, event) {
+                    (Handler::Orders(filter, handler), Published::Order(event))
+                        if filter.accepts(event) =>
+                    {
+                        handler(event)
+                    }
+                    (Handler::Inventory(handler), Published::Inventory(event)) => handler(event),
+                    _ => Ok(()),
+                }));
+            let error = match outcome {
+                Ok(Ok(())) => continue,
+                Ok(Err(error)) => error,
+                Err(panicked) => OrderError::custom(format!(
+                    "subscriber {} panicked: {}",
+                    subscription.id.0,
+                    panic_message(panicked.as_ref())
+                )),
+            };
+            self.report(operation, order_id, error);
+        }
+    }
+
+    fn report(&self, operation: &'static str, order_id: Option<OrderId>, error: OrderError) {
+        let Some(telemetry) = &self.telemetry else {
+            return;
+        };
+        let context = ErrorContext {
+            use_case: "event_bus",
+            port: Some(Port::Events),
+            operation,
+            order_id,
+            at: telemetry.clock.now(),
+            error,
+        };
+        let _ = panic::catch_unwind(AssertUnwindSafe(|| telemetry.reporter.report(context)));
+    }
+
+    fn subscriptions(&self) -> MutexGuard<'_, Vec<Arc<Subscription<'a>>>> {
+        self.subscriptions
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn queue(&self) -> MutexGuard<'_, Queue> {
+        self.queue.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// What panic! was given, when it was a message.
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("no message")
+}
+
+impl EventPublisher for InMemoryEventBus<'_> {
+    fn publish(&self, event: &OrderEvent) -> Result<(), OrderError> {
+        self.enqueue(Published::Order(event.clone()));
+        Ok(())
+    }
+
+    fn publish_inventory(&self, event: &InventoryEvent) -> Result<(), OrderError> {
+        self.enqueue(Published::Inventory(event.clone()));
+        Ok(())
+    }
+}
+
+impl Capability for InMemoryEventBus<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Money;
+
+    fn paid(id: u32) -> OrderEvent {
+        OrderEvent::OrderPaid {
+            order_id: OrderId(id),
+            amount: Money(150),
+        }
+    }
+
+    #[test]
+    fn what_a_subscriber_publishes_is_heard_after_the_event_it_handles() {
+        // A subscriber reaching the bus it is on needs it for good.
+        let heard: &'static Mutex<Vec<OrderId>> = Box::leak(Box::default());
+        let bus: &'static InMemoryEventBus<'static> = Box::leak(Box::default());
+        bus.subscribe(
+            EventFilter::Order(OrderId(1)),
+            Box::new(|_| bus.publish(&paid(9))),
+        );
+        bus.subscribe(
+            EventFilter::All,
+            Box::new(|event| {
+                heard.lock().unwrap().push(event.order_id());
+                Ok(())
+            }),
+        );
+
+        bus.publish(&paid(1)).unwrap();
+        bus.publish(&paid(2)).unwrap();
+
+        assert_eq!(
+            heard.lock().unwrap()[..],
+            [OrderId(1), OrderId(9), OrderId(2)]
+        );
+    }
+}
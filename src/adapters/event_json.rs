@@ -0,0 +1,205 @@
+// Order events as one flat JSON object each, "type" naming the event:
+//
+//     {"type":"OrderPaid","order_id":3,"amount_cents":4999}
+//
+// shared by the adapters that keep events in files. Fields a reader does
+// not know are ignored, so a record may carry more than its event.
+use super::json::{self, Value};
+use crate::domain::{Money, OrderEvent, OrderId, TrackingId};
+
+pub(crate) fn event_json(event: &OrderEvent) -> String {
+    match event {
+        OrderEvent::OrderPlaced {
+            order_id,
+            item_count,
+            total,
+        } => format!(
+            r#"{{"type":"OrderPlaced","order_id":{},"item_count":{item_count},"total_cents":{}}}"#,
+            order_id.0, total.0
+        ),
+        OrderEvent::ApprovalRequested { order_id, total } => format!(
+            r#"{{"type":"ApprovalRequested","order_id":{},"total_cents":{}}}"#,
+            order_id.0, total.0
+        ),
+        OrderEvent::ReviewRequested { order_id, total } => format!(
+            r#"{{"type":"ReviewRequested","order_id":{},"total_cents":{}}}"#,
+            order_id.0, total.0
+        ),
+        OrderEvent::OrderRejected { order_id, reason } => format!(
+            r#"{{"type":"OrderRejected","order_id":{},"reason":"{}"}}"#,
+            order_id.0,
+            json::escape(reason)
+        ),
+        OrderEvent::PaymentDeferred { order_id, amount } => format!(
+            r#"{{"type":"PaymentDeferred","order_id":{},"amount_cents":{}}}"#,
+            order_id.0, amount.0
+        ),
+        OrderEvent::ChargeSkipped { order_id, covered } => format!(
+            r#"{{"type":"ChargeSkipped","order_id":{},"covered_cents":{}}}"#,
+            order_id.0, covered.0
+        ),
+        OrderEvent::OrderPaid { order_id, amount } => format!(
+            r#"{{"type":"OrderPaid","order_id":{},"amount_cents":{}}}"#,
+            order_id.0, amount.0
+        ),
+        OrderEvent::OrderCancelled { order_id, refunded } => format!(
+            r#"{{"type":"OrderCancelled","order_id":{},"refunded_cents":{}}}"#,
+            order_id.0, refunded.0
+        ),
+        OrderEvent::ItemsShipped {
+            order_id,
+            tracking,
+            item_count,
+        } => format!(
+            r#"{{"type":"ItemsShipped","order_id":{},"tracking":"{}","item_count":{item_count}}}"#,
+            order_id.0,
+            json::escape(&tracking.0)
+        ),
+        OrderEvent::OrderExpired { order_id } => {
+            format!(r#"{{"type":"OrderExpired","order_id":{}}}"#, order_id.0)
+        }
+        OrderEvent::OrdersMerged {
+            order_id,
+            from,
+            item_count,
+            total,
+        } => format!(
+            r#"{{"type":"OrdersMerged","order_id":{},"from":{},"item_count":{item_count},"total_cents":{}}}"#,
+            order_id.0, from.0, total.0
+        ),
+    }
+}
+
+pub(crate) fn event_from_json(text: &str) -> Option<OrderEvent> {
+    let fields = json::parse_flat_object(text)?;
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    };
+    let number = |name: &str| match field(name) {
+        Some(Value::Number(n)) => Some(*n),
+        _ => None,
+    };
+    let cents = |name: &str| number(name).and_then(|n| u32::try_from(n).ok()).map(Money);
+    let order_id = OrderId(u32::try_from(number("order_id")?).ok()?);
+
+    match field("type")? {
+        Value::String(kind) if kind == "OrderPlaced" => Some(OrderEvent::OrderPlaced {
+            order_id,
+            item_count: usize::try_from(number("item_count")?).ok()?,
+            total: cents("total_cents")?,
+        }),
+        Value::String(kind) if kind == "ApprovalRequested" => Some(OrderEvent::ApprovalRequested {
+            order_id,
+            total: cents("total_cents")?,
+        }),
+        Value::String(kind) if kind == "ReviewRequested" => Some(OrderEvent::ReviewRequested {
+            order_id,
+            total: cents("total_cents")?,
+        }),
+        Value::String(kind) if kind == "OrderRejected" => Some(OrderEvent::OrderRejected {
+            order_id,
+            reason: match field("reason")? {
+                Value::String(reason) => reason.clone(),
+                _ => return None,
+            },
+        }),
+        Value::String(kind) if kind == "PaymentDeferred" => Some(OrderEvent::PaymentDeferred {
+            order_id,
+            amount: cents("amount_cents")?,
+        }),
+        Value::String(kind) if kind == "ChargeSkipped" => Some(OrderEvent::ChargeSkipped {
+            order_id,
+            covered: cents("covered_cents")?,
+        }),
+        Value::String(kind) if kind == "OrderPaid" => Some(OrderEvent::OrderPaid {
+            order_id,
+            amount: cents("amount_cents")?,
+        }),
+        Value::String(kind) if kind == "OrderCancelled" => Some(OrderEvent::OrderCancelled {
+            order_id,
+            refunded: cents("refunded_cents")?,
+        }),
+        Value::String(kind) if kind == "ItemsShipped" => Some(OrderEvent::ItemsShipped {
+            order_id,
+            tracking: match field("tracking")? {
+                Value::String(tracking) => TrackingId(tracking.clone()),
+                _ => return None,
+            },
+            item_count: usize::try_from(number("item_count")?).ok()?,
+        }),
+        Value::String(kind) if kind == "OrderExpired" => {
+            Some(OrderEvent::OrderExpired { order_id })
+        }
+        Value::String(kind) if kind == "OrdersMerged" => Some(OrderEvent::OrdersMerged {
+            order_id,
+            from: OrderId(u32::try_from(number("from")?).ok()?),
+            item_count: usize::try_from(number("item_count")?).ok()?,
+            total: cents("total_cents")?,
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_event_survives_the_round_trip() {
+        let events = [
+            OrderEvent::OrderPlaced {
+                order_id: OrderId(1),
+                item_count: 2,
+                total: Money(7499),
+            },
+            OrderEvent::ApprovalRequested {
+                order_id: OrderId(1),
+                total: Money(7499),
+            },
+            OrderEvent::ReviewRequested {
+                order_id: OrderId(1),
+                total: Money(7499),
+            },
+            OrderEvent::OrderRejected {
+                order_id: OrderId(1),
+                reason: "over \"budget\"".to_string(),
+            },
+            OrderEvent::PaymentDeferred {
+                order_id: OrderId(1),
+                amount: Money(7499),
+            },
+            OrderEvent::ChargeSkipped {
+                order_id: OrderId(1),
+                covered: Money(7499),
+            },
+            OrderEvent::OrderPaid {
+                order_id: OrderId(1),
+                amount: Money(7499),
+            },
+            OrderEvent::OrderCancelled {
+                order_id: OrderId(1),
+                refunded: Money(7499),
+            },
+            OrderEvent::ItemsShipped {
+                order_id: OrderId(1),
+                tracking: TrackingId("TRK \"1\"".to_string()),
+                item_count: 2,
+            },
+            OrderEvent::OrderExpired {
+                order_id: OrderId(1),
+            },
+            OrderEvent::OrdersMerged {
+                order_id: OrderId(1),
+                from: OrderId(2),
+                item_count: 3,
+                total: Money(9999),
+            },
+        ];
+        for event in events {
+            assert_eq!(event_from_json(&event_json(&event)), Some(event));
+        }
+    }
+}
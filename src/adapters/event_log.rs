@@ -0,0 +1,157 @@
+// --- File event log ---
+// Events survive a restart: FileEventLog appends every published event to a
+// file, EventLogReader streams them back into any EventSubscriber, typically
+// a read model rebuilt from scratch.
+//
+// One record per line:
+//
+//     <length> <json>\n
+//
+// where <length> is the byte length of <json>. A crash in the middle of a
+// write leaves a torn last record: no newline, or a length that does not
+// match. Replay skips such records and counts them instead of giving up on
+// the whole log.
+use super::ConfigError;
+use super::atomic_file;
+use super::event_json::{event_from_json, event_json};
+use crate::domain::{OrderError, OrderEvent};
+use crate::ports::{Capability, EventPublisher, EventSubscriber, Shutdown, ShutdownError};
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+pub struct FileEventLog {
+    path: PathBuf,
+}
+
+impl FileEventLog {
+    // Opens the file once, creating it if needed, so a directory that does
+    // not exist or cannot be written to is known before the first event.
+    pub fn new(path: impl Into<PathBuf>) -> Result<Self, ConfigError> {
+        let path = path.into();
+        match OpenOptions::new().create(true).append(true).open(&path) {
+            Ok(_) => Ok(Self { path }),
+            Err(error) => Err(ConfigError::IoError {
+                path,
+                kind: error.kind(),
+            }),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl EventPublisher for FileEventLog {
+    // Opens in append mode on every call: simple, and what was written
+    // before a crash stays written.
+    fn publish(&self, event: &OrderEvent) -> Result<(), OrderError> {
+        let json = event_json(event);
+        let record = format!("{} {json}\n", json.len());
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(record.as_bytes()))
+            .map_err(|_| OrderError::StorageFailed)
+    }
+}
+
+impl Capability for FileEventLog {}
+
+// publish leaves the flushing to the system; the last events are synced
+// here.
+impl Shutdown for FileEventLog {
+    fn shutdown(&mut self) -> Result<(), ShutdownError> {
+        atomic_file::sync(&self.path)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ReplayReport {
+    pub replayed: usize,
+    pub skipped: usize,
+}
+
+pub struct EventLogReader {
+    path: PathBuf,
+}
+
+impl EventLogReader {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    // A missing file is an empty log: nothing was ever published.
+    pub fn replay_into(
+        &self,
+        subscriber: &mut impl EventSubscriber,
+    ) -> Result<ReplayReport, OrderError> {
+        let bytes = match fs::read(&self.path) {
+            Ok(bytes) => bytes,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(_) => return Err(OrderError::StorageFailed),
+        };
+
+        let mut report = ReplayReport::default();
+        let mut records = bytes.split(|&byte| byte == b'\n').peekable();
+        while let Some(record) = records.next() {
+            let is_last = records.peek().is_none();
+            if is_last && record.is_empty() {
+                break; // the newline closing the last complete record
+            }
+            // The last chunk has no newline: the write was interrupted.
+            match (!is_last).then(|| decode_record(record)).flatten() {
+                Some(event) => {
+                    subscriber.on_event(&event);
+                    report.replayed += 1;
+                }
+                None => report.skipped += 1,
+            }
+        }
+        Ok(report)
+    }
+}
+
+fn decode_record(record: &[u8]) -> Option<OrderEvent> {
+    let record = std::str::from_utf8(record).ok()?;
+    let (length, json) = record.split_once(' ')?;
+    if length.parse::<usize>().ok()? != json.len() {
+        return None;
+    }
+    event_from_json(json)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Money, OrderId};
+
+    #[test]
+    fn missing_directory_is_refused_at_construction() {
+        let path = std::env::temp_dir()
+            .join("hexa-lite-no-such-dir")
+            .join("events.log");
+
+        assert_eq!(
+            FileEventLog::new(&path).err(),
+            Some(ConfigError::IoError {
+                path,
+                kind: io::ErrorKind::NotFound,
+            })
+        );
+    }
+
+    #[test]
+    fn length_prefix_must_match() {
+        let json = event_json(&OrderEvent::OrderPaid {
+            order_id: OrderId(3),
+            amount: Money(100),
+        });
+
+        assert!(decode_record(format!("{} {json}", json.len()).as_bytes()).is_some());
+        assert!(decode_record(format!("{} {json}", json.len() + 1).as_bytes()).is_none());
+        assert!(decode_record(json.as_bytes()).is_none());
+    }
+}
@@ -0,0 +1,169 @@
+// --- External services (for production) ---
+// Same ports as the in-memory adapters, completely different implementations.
+// Orders go to a database through adapters::sql.
+use super::{ConfigError, Console, DeclineScript, NetworkConditions, SecretString};
+use crate::domain::{Currency, Money, OrderConfirmation, OrderError, OrderId};
+use crate::ports::{Capability, ChargeLog, ChargeRecord, PaymentGateway, Sender};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+// A "simulated" Stripe adapter.
+// In real life, this would call the Stripe API.
+// Charges made for an order are kept, as Stripe's records would be; clones
+// share them, like two clients of one account. Put on a simulated network
+// (see adapters::network), a call that does not get through is
+// PaymentUnavailable, and was never made. Amounts go to the API as they
+// are, in the minor units of the account's currency (dollars by default):
+// Stripe, too, counts yen in yen and dinars in fils. Like Stripe's test
+// mode, it declines the amounts a DeclineScript says, with the reason
+// Stripe would give.
+#[derive(Clone, Default)]
+pub struct StripePaymentGateway {
+    charges: Arc<Mutex<Vec<ChargeRecord>>>,
+    network: Option<Arc<NetworkConditions>>,
+    currency: Currency,
+    declines: DeclineScript,
+    console: Console,
+}
+
+impl StripePaymentGateway {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_network(mut self, network: Arc<NetworkConditions>) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn with_currency(mut self, currency: Currency) -> Self {
+        self.currency = currency;
+        self
+    }
+
+    pub fn with_declines(mut self, declines: DeclineScript) -> Self {
+        self.declines = declines;
+        self
+    }
+
+    fn reach(&self) -> Result<(), OrderError> {
+        if let Some(network) = &self.network
+            && let Err(fault) = network.call()
+        {
+            self.console
+                .line(format_args!("  [Stripe] Unreachable ({fault:?})"));
+            return Err(OrderError::PaymentUnavailable);
+        }
+        Ok(())
+    }
+
+    fn records(&self) -> MutexGuard<'_, Vec<ChargeRecord>> {
+        self.charges.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Capability for StripePaymentGateway {}
+
+impl PaymentGateway for StripePaymentGateway {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.reach()?;
+        if let Some(reason) = self.declines.reason_for(amount) {
+            self.console.line(format_args!(
+                "  [Stripe] Declined {}: {}",
+                amount.in_currency(self.currency),
+                reason.code()
+            ));
+            return Err(OrderError::PaymentDeclined { reason });
+        }
+        self.console.line(format_args!(
+            "  [Stripe] Charging {}",
+            amount.in_currency(self.currency)
+        ));
+        Ok(())
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.charge(amount)?;
+        self.records().push(ChargeRecord { order_id, amount });
+        Ok(())
+    }
+
+    // PaymentFailed when the order has no charge that large to refund. A
+    // refund of part of a charge leaves the rest on record.
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.reach()?;
+        self.console.line(format_args!(
+            "  [Stripe] Refunding {} for order {order_id}",
+            amount.in_currency(self.currency)
+        ));
+        let mut charges = self.records();
+        let charge = charges
+            .iter()
+            .position(|charge| charge.order_id == order_id && charge.amount.0 >= amount.0);
+        match charge {
+            Some(at) if charges[at].amount == amount => {
+                charges.remove(at);
+                Ok(())
+            }
+            Some(at) => {
+                charges[at].amount = Money(charges[at].amount.0 - amount.0);
+                Ok(())
+            }
+            None => Err(OrderError::PaymentFailed),
+        }
+    }
+}
+
+// Listing the charges is an API call too.
+impl ChargeLog for StripePaymentGateway {
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        self.reach()?;
+        Ok(self.records().clone())
+    }
+}
+
+// A "simulated" SendGrid adapter for sending emails.
+// Same Sender trait as ConsoleSender, but talks to an email API.
+// The API key would go in the Authorization header; it is kept as a
+// SecretString so printing the adapter never leaks it.
+#[derive(Debug)]
+pub struct SendGridSender {
+    api_key: SecretString,
+    console: Console,
+}
+
+impl SendGridSender {
+    pub fn new(api_key: SecretString) -> Result<Self, ConfigError> {
+        if api_key.expose().trim().is_empty() {
+            return Err(ConfigError::MissingCredential {
+                adapter: "SendGridSender",
+                name: "API key",
+            });
+        }
+        Ok(Self {
+            api_key,
+            console: Console::stdout(),
+        })
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+}
+
+impl Capability for SendGridSender {}
+
+impl Sender for SendGridSender {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.console.line(format_args!(
+            "  [SendGrid] Sending confirmation for order {} (key {})",
+            confirmation.order_id, self.api_key
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,351 @@
+// --- File order repository ---
+// Every order, deleted ones included, in a single file that survives a
+// restart. How the file is encoded is a StorageFormat, chosen when the
+// repository is built:
+//
+//     FileOrderRepository::open("orders.json", JsonFormat)?
+//     FileOrderRepository::open("orders.ron", RonFormat)?
+//     FileOrderRepository::open("orders.msgpack", MsgPackFormat)?
+//
+// Each format comes with its own cargo feature (json, ron, msgpack), all on
+// by default. What they share is here and in document.rs: the layout of an
+// Envelope, the upgrade of files written by older versions, and the atomic
+// rewrite of the file.
+//
+// The whole file is rewritten on every change. That is fine for thousands
+// of orders, not millions: `cargo bench --bench storage_formats` tells what
+// a save costs with 10_000 of them.
+//
+// Where the sequence and the outbox are kept on disk too, next to the
+// orders, a JournaledRepository keeps the three of them in step across a
+// crash: see journal.rs.
+use super::atomic_file;
+use super::{ConfigError, Console};
+use crate::domain::{Order, OrderError, OrderId};
+use crate::ports::{Capability, OrderReader, OrderWriter, Shutdown, ShutdownError};
+use std::fmt;
+use std::fs;
+use std::io;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+// Built without a single format, nothing encodes the document.
+#[cfg_attr(
+    not(any(feature = "json", feature = "ron", feature = "msgpack")),
+    allow(dead_code)
+)]
+mod document;
+mod journal;
+#[cfg(feature = "json")]
+mod json_format;
+#[cfg(feature = "msgpack")]
+mod msgpack;
+#[cfg(feature = "ron")]
+mod ron;
+
+pub use journal::{Fs, JournaledRepository, RealFs};
+#[cfg(feature = "json")]
+pub use json_format::JsonFormat;
+#[cfg(feature = "msgpack")]
+pub use msgpack::MsgPackFormat;
+#[cfg(feature = "ron")]
+pub use ron::RonFormat;
+
+// What a file holds: the live orders and the soft-deleted ones, each by
+// ascending id, no id twice.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Envelope {
+    pub orders: Vec<Order>,
+    pub deleted: Vec<Order>,
+}
+
+// Bytes to and from an Envelope. A format only encodes the document; which
+// fields an order has, and which versions of the document are still read,
+// is the same for all of them.
+pub trait StorageFormat {
+    fn name(&self) -> &'static str;
+    fn serialize(&self, envelope: &Envelope) -> Vec<u8>;
+    fn deserialize(&self, bytes: &[u8]) -> Result<Envelope, FormatError>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FormatError {
+    // Not this format at all: truncated, or written in another one.
+    Malformed { format: &'static str },
+    // Well formed, but not orders: a field missing or of the wrong kind.
+    NotAnEnvelope { field: &'static str },
+    // Written by a newer version of the crate, which this one cannot read.
+    UnsupportedVersion { found: u64 },
+}
+
+impl fmt::Display for FormatError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FormatError::Malformed { format } => write!(f, "not a {format} document"),
+            FormatError::NotAnEnvelope { field } => write!(f, "missing or invalid {field}"),
+            FormatError::UnsupportedVersion { found } => write!(
+                f,
+                "version {found} is newer than this program, which reads up to {}",
+                document::CURRENT_VERSION
+            ),
+        }
+    }
+}
+
+pub struct FileOrderRepository<F: StorageFormat> {
+    path: PathBuf,
+    format: F,
+    envelope: Mutex<Envelope>,
+    console: Console,
+}
+
+impl<F: StorageFormat> FileOrderRepository<F> {
+    // A missing file is an empty repository. A file that cannot be read, or
+    // not understood, is an error: starting empty would overwrite its
+    // orders with the next save.
+    pub fn open(path: impl Into<PathBuf>, format: F) -> Result<Self, ConfigError> {
+        let path = path.into();
+        let envelope = match fs::read(&path) {
+            Ok(bytes) => match format.deserialize(&bytes) {
+                Ok(envelope) => envelope,
+                Err(error) => {
+                    return Err(ConfigError::InvalidFile {
+                        path,
+                        reason: error.to_string(),
+                    });
+                }
+            },
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Envelope::default(),
+            Err(error) => {
+                return Err(ConfigError::IoError {
+                    path,
+                    kind: error.kind(),
+                });
+            }
+        };
+        Ok(Self {
+            path,
+            format,
+            envelope: Mutex::new(envelope),
+            console: Console::default(),
+        })
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // Held for the whole of a change, its write included: two changes
+    // through the same repository never interleave on disk.
+    fn envelope(&self) -> MutexGuard<'_, Envelope> {
+        self.envelope.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn write(&self, envelope: &Envelope) -> Result<(), OrderError> {
+        atomic_file::replace(&self.path, &self.format.serialize(envelope))
+            .map_err(|_| OrderError::StorageFailed)
+    }
+
+    // Saving, once the caller holds the envelope.
+    fn put(&self, envelope: &mut Envelope, order: &Order) -> Result<(), OrderError> {
+        let orders = &mut envelope.orders;
+        let (at, previous) = match position(orders, order.id) {
+            Ok(at) => (at, Some(mem::replace(&mut orders[at], order.clone()))),
+            Err(at) => {
+                orders.insert(at, order.clone());
+                (at, None)
+            }
+        };
+        self.write(envelope).inspect_err(|_| {
+            let orders = &mut envelope.orders;
+            match previous {
+                Some(previous) => orders[at] = previous,
+                None => {
+                    orders.remove(at);
+                }
+            }
+        })
+    }
+}
+
+// Both lists stay sorted by id, so a lookup is a binary search and listing
+// needs no sort.
+fn position(orders: &[Order], id: OrderId) -> Result<usize, usize> {
+    orders.binary_search_by_key(&id, |order| order.id)
+}
+
+// Moves order `id`, if `from` has it, to its place in `to`.
+fn transfer(from: &mut Vec<Order>, to: &mut Vec<Order>, id: OrderId) -> bool {
+    let Ok(at) = position(from, id) else {
+        return false;
+    };
+    let order = from.remove(at);
+    let (Ok(into) | Err(into)) = position(to, id);
+    to.insert(into, order);
+    true
+}
+
+// A change is only kept once it is on disk: when the write fails, it is
+// undone and the repository is as before.
+impl<F: StorageFormat> OrderReader for FileOrderRepository<F> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.console
+            .line(format_args!("  [File] Finding order {id:?}"));
+        let envelope = self.envelope();
+        let orders = &envelope.orders;
+        Ok(position(orders, id).ok().map(|at| orders[at].clone()))
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        let envelope = self.envelope();
+        self.console.line(format_args!(
+            "  [File] Listing {} order(s)",
+            envelope.orders.len()
+        ));
+        Ok(envelope.orders.clone())
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        self.envelope().orders.iter().for_each(visit);
+        Ok(())
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        Ok(self.envelope().deleted.clone())
+    }
+}
+
+impl<F: StorageFormat> OrderWriter for FileOrderRepository<F> {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.console.line(format_args!(
+            "  [File] Saving order {:?} ({})",
+            order.id,
+            self.format.name()
+        ));
+        self.put(&mut self.envelope(), order)
+    }
+
+    // Checked and written under one lock, as the in-memory repository does:
+    // of two writers that read the same version, only one gets through.
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        let id = order.id;
+        self.console
+            .line(format_args!("  [File] Updating order {id:?}"));
+        let mut envelope = self.envelope();
+        let stored = match position(&envelope.orders, id) {
+            Ok(at) => envelope.orders[at].version,
+            Err(_) => return Err(OrderError::NotFound { id }),
+        };
+        if stored != order.version {
+            return Err(OrderError::VersionConflict {
+                id,
+                expected: order.version,
+                found: stored,
+            });
+        }
+        let mut next = order.clone();
+        next.version += 1;
+        self.put(&mut envelope, &next)
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [File] Deleting order {id:?}"));
+        let mut envelope = self.envelope();
+        let Envelope { orders, deleted } = &mut *envelope;
+        if !transfer(orders, deleted, id) {
+            return match position(deleted, id) {
+                Ok(_) => Ok(()),
+                Err(_) => Err(OrderError::NotFound { id }),
+            };
+        }
+        self.write(&envelope).inspect_err(|_| {
+            let Envelope { orders, deleted } = &mut *envelope;
+            transfer(deleted, orders, id);
+        })
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [File] Restoring order {id:?}"));
+        let mut envelope = self.envelope();
+        let Envelope { orders, deleted } = &mut *envelope;
+        if !transfer(deleted, orders, id) {
+            return match position(orders, id) {
+                Ok(_) => Err(OrderError::NotDeleted { id }),
+                Err(_) => Err(OrderError::NotFound { id }),
+            };
+        }
+        self.write(&envelope).inspect_err(|_| {
+            let Envelope { orders, deleted } = &mut *envelope;
+            transfer(orders, deleted, id);
+        })
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [File] Purging order {id:?}"));
+        let mut envelope = self.envelope();
+        let Envelope { orders, deleted } = &mut *envelope;
+        let (from, was_deleted) = match position(orders, id) {
+            Ok(at) => (orders.remove(at), false),
+            Err(_) => match position(deleted, id) {
+                Ok(at) => (deleted.remove(at), true),
+                Err(_) => return Err(OrderError::NotFound { id }),
+            },
+        };
+        self.write(&envelope).inspect_err(|_| {
+            let Envelope { orders, deleted } = &mut *envelope;
+            let to = if was_deleted { deleted } else { orders };
+            let (Ok(at) | Err(at)) = position(to, id);
+            to.insert(at, from.clone());
+        })
+    }
+}
+
+impl<F: StorageFormat> Capability for FileOrderRepository<F> {}
+
+impl<F: StorageFormat> Shutdown for FileOrderRepository<F> {
+    // The file is replaced whole on every change; its last version is what
+    // must be on disk.
+    fn shutdown(&mut self) -> Result<(), ShutdownError> {
+        atomic_file::sync(&self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, Money};
+
+    fn order(id: u32) -> Order {
+        Order::new(
+            OrderId(id),
+            vec![LineItem {
+                name: "Pen".to_string(),
+                price: Money(150),
+            }],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn transfer_keeps_both_lists_in_id_order() {
+        let mut orders = vec![order(1), order(2), order(3)];
+        let mut deleted = vec![order(4)];
+
+        assert!(transfer(&mut orders, &mut deleted, OrderId(2)));
+        assert!(!transfer(&mut orders, &mut deleted, OrderId(2)));
+        assert!(transfer(&mut orders, &mut deleted, OrderId(1)));
+
+        let ids = |orders: &[Order]| orders.iter().map(|o| o.id.0).collect::<Vec<_>>();
+        assert_eq!(ids(&orders), [3]);
+        assert_eq!(ids(&deleted), [1, 2, 4]);
+    }
+}
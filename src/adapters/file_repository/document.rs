@@ -0,0 +1,602 @@
+// The document every format encodes, as a tree of values:
+//
+//     {version: 5,
+//      orders: [{id, items: [{name, price}], total, currency, status,
+//                shipments: [{tracking, items: [index], shipped_at}],
+//                placed_at, uuid, reference, version, approval,
+//                shipping_address: {street, city, postal_code, country},
+//                gift_note, notes: [{author, text, at}],
+//                attachments: [{blob, filename, bytes}]}],
+//      deleted: [...]}
+//
+// placed_at, uuid, reference, approval, shipping_address and gift_note are
+// null when the order has none; approval is otherwise {approved_by: "..."} or
+// {rejected: "reason"}.
+// Amounts are in the order's currency's minor units, written by its code
+// ("EUR"); times are in seconds since the epoch.
+use super::{Envelope, FormatError};
+use crate::adapters::json::Value;
+use crate::domain::{
+    Address, Approval, ApproverId, Attachment, BlobId, Currency, LineItem, Money, Order, OrderId,
+    OrderNote, OrderReference, OrderStatus, Shipment, Timestamp, TrackingId, Uuid128,
+};
+
+// The version written. Files from older versions are upgraded as they are
+// read, see `migrate`; files from newer ones are refused.
+pub(super) const CURRENT_VERSION: u64 = 5;
+
+pub(super) fn to_value(envelope: &Envelope) -> Value {
+    let orders = |orders: &[Order]| Value::Array(orders.iter().map(order_to_value).collect());
+    object([
+        ("version", Value::Number(CURRENT_VERSION)),
+        ("orders", orders(&envelope.orders)),
+        ("deleted", orders(&envelope.deleted)),
+    ])
+}
+
+pub(super) fn from_value(document: Value) -> Result<Envelope, FormatError> {
+    let version = Fields::of(&document, "document")?.number("version")?;
+    let document = migrate(version, document)?;
+    let fields = Fields::of(&document, "document")?;
+    let orders = |name: &'static str| -> Result<Vec<Order>, FormatError> {
+        let mut orders = fields
+            .array(name)?
+            .iter()
+            .map(order_from_value)
+            .collect::<Result<Vec<_>, _>>()?;
+        orders.sort_by_key(|order| order.id);
+        Ok(orders)
+    };
+    let envelope = Envelope {
+        orders: orders("orders")?,
+        deleted: orders("deleted")?,
+    };
+    let mut ids: Vec<OrderId> = envelope
+        .orders
+        .iter()
+        .chain(&envelope.deleted)
+        .map(|order| order.id)
+        .collect();
+    ids.sort();
+    if ids.windows(2).any(|pair| pair[0] == pair[1]) {
+        return Err(FormatError::NotAnEnvelope { field: "order.id" });
+    }
+    Ok(envelope)
+}
+
+// One step per version, oldest first, each bringing a document up to the
+// next version: a change to the layout bumps CURRENT_VERSION and adds the
+// step from the previous one here, so that every format reads old files the
+// same way.
+fn migrate(version: u64, document: Value) -> Result<Value, FormatError> {
+    match version {
+        CURRENT_VERSION => Ok(document),
+        1 => migrate(2, v1_to_v2(document)?),
+        2 => migrate(3, v2_to_v3(document)?),
+        3 => migrate(4, v3_to_v4(document)?),
+        4 => migrate(5, v4_to_v5(document)?),
+        found => Err(FormatError::UnsupportedVersion { found }),
+    }
+}
+
+// Version 2 gave orders a shipping_address and a gift_note: none, for the
+// orders of a version 1 file.
+fn v1_to_v2(document: Value) -> Result<Value, FormatError> {
+    add_to_orders(
+        document,
+        2,
+        &[
+            ("shipping_address", Value::Null),
+            ("gift_note", Value::Null),
+        ],
+    )
+}
+
+// Version 3 gave orders a currency: all of them were in dollars before.
+fn v2_to_v3(document: Value) -> Result<Value, FormatError> {
+    add_to_orders(
+        document,
+        3,
+        &[("currency", Value::String(Currency::Usd.to_string()))],
+    )
+}
+
+// Version 4 gave orders notes and attachments: none before.
+fn v3_to_v4(document: Value) -> Result<Value, FormatError> {
+    add_to_orders(
+        document,
+        4,
+        &[
+            ("notes", Value::Array(Vec::new())),
+            ("attachments", Value::Array(Vec::new())),
+        ],
+    )
+}
+
+// Version 5 gave orders a reference: none before.
+fn v4_to_v5(document: Value) -> Result<Value, FormatError> {
+    add_to_orders(document, 5, &[("reference", Value::Null)])
+}
+
+// The document at `version`, every order, deleted or not, given `added`.
+fn add_to_orders(
+    document: Value,
+    version: u64,
+    added: &[(&str, Value)],
+) -> Result<Value, FormatError> {
+    let Value::Object(fields) = document else {
+        return Err(FormatError::NotAnEnvelope { field: "document" });
+    };
+    let upgrade = |orders: Value| match orders {
+        Value::Array(orders) => Value::Array(
+            orders
+                .into_iter()
+                .map(|order| match order {
+                    Value::Object(mut fields) => {
+                        fields.extend(
+                            added
+                                .iter()
+                                .map(|(name, value)| (name.to_string(), value.clone())),
+                        );
+                        Value::Object(fields)
+                    }
+                    other => other,
+                })
+                .collect(),
+        ),
+        other => other,
+    };
+    Ok(Value::Object(
+        fields
+            .into_iter()
+            .map(|(name, value)| match name.as_str() {
+                "version" => (name, Value::Number(version)),
+                "orders" | "deleted" => (name, upgrade(value)),
+                _ => (name, value),
+            })
+            .collect(),
+    ))
+}
+
+fn object<const N: usize>(fields: [(&str, Value); N]) -> Value {
+    Value::Object(
+        fields
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value))
+            .collect(),
+    )
+}
+
+fn order_to_value(order: &Order) -> Value {
+    let items = order
+        .items
+        .iter()
+        .map(|item| {
+            object([
+                ("name", Value::String(item.name.clone())),
+                ("price", Value::Number(u64::from(item.price.0))),
+            ])
+        })
+        .collect();
+    let shipments = order
+        .shipments
+        .iter()
+        .map(|shipment| {
+            let items = shipment
+                .item_indices
+                .iter()
+                .map(|&index| Value::Number(index as u64))
+                .collect();
+            object([
+                ("tracking", Value::String(shipment.tracking.0.clone())),
+                ("items", Value::Array(items)),
+                ("shipped_at", Value::Number(shipment.shipped_at.0)),
+            ])
+        })
+        .collect();
+    let notes = order
+        .notes
+        .iter()
+        .map(|note| {
+            object([
+                ("author", Value::String(note.author.clone())),
+                ("text", Value::String(note.text.clone())),
+                ("at", Value::Number(note.at.0)),
+            ])
+        })
+        .collect();
+    let attachments = order
+        .attachments
+        .iter()
+        .map(|attachment| {
+            object([
+                ("blob", Value::Number(attachment.blob.0)),
+                ("filename", Value::String(attachment.filename.clone())),
+                ("bytes", Value::Number(attachment.bytes)),
+            ])
+        })
+        .collect();
+    let approval = match &order.approval {
+        None => Value::Null,
+        Some(Approval::Approved { by }) => object([("approved_by", Value::String(by.0.clone()))]),
+        Some(Approval::Rejected { reason }) => {
+            object([("rejected", Value::String(reason.clone()))])
+        }
+    };
+    object([
+        ("id", Value::Number(u64::from(order.id.0))),
+        ("items", Value::Array(items)),
+        ("total", Value::Number(u64::from(order.total.0))),
+        ("currency", Value::String(order.currency.to_string())),
+        ("status", Value::String(order.status.to_string())),
+        ("shipments", Value::Array(shipments)),
+        (
+            "placed_at",
+            order
+                .placed_at
+                .map_or(Value::Null, |at| Value::Number(at.0)),
+        ),
+        (
+            "uuid",
+            order
+                .uuid
+                .map_or(Value::Null, |uuid| Value::String(uuid.to_string())),
+        ),
+        (
+            "reference",
+            order
+                .reference
+                .as_ref()
+                .map_or(Value::Null, |reference| Value::String(reference.0.clone())),
+        ),
+        ("version", Value::Number(u64::from(order.version))),
+        ("approval", approval),
+        (
+            "shipping_address",
+            order
+                .shipping_address
+                .as_ref()
+                .map_or(Value::Null, |address| {
+                    object([
+                        ("street", Value::String(address.street.clone())),
+                        ("city", Value::String(address.city.clone())),
+                        ("postal_code", Value::String(address.postal_code.clone())),
+                        ("country", Value::String(address.country.clone())),
+                    ])
+                }),
+        ),
+        (
+            "gift_note",
+            order
+                .gift_note
+                .as_ref()
+                .map_or(Value::Null, |note| Value::String(note.clone())),
+        ),
+        ("notes", Value::Array(notes)),
+        ("attachments", Value::Array(attachments)),
+    ])
+}
+
+fn order_from_value(value: &Value) -> Result<Order, FormatError> {
+    let fields = Fields::of(value, "order")?;
+    let items = fields
+        .array("items")?
+        .iter()
+        .map(|item| {
+            let item = Fields::of(item, "item")?;
+            Ok(LineItem {
+                name: item.string("name")?.to_string(),
+                price: Money(item.u32("price")?),
+            })
+        })
+        .collect::<Result<_, FormatError>>()?;
+    let shipments = fields
+        .array("shipments")?
+        .iter()
+        .map(|shipment| {
+            let shipment = Fields::of(shipment, "shipment")?;
+            let item_indices = shipment
+                .array("items")?
+                .iter()
+                .map(|index| match index {
+                    Value::Number(index) => usize::try_from(*index).ok(),
+                    _ => None,
+                })
+                .collect::<Option<_>>()
+                .ok_or(FormatError::NotAnEnvelope {
+                    field: "shipment.items",
+                })?;
+            Ok(Shipment {
+                tracking: TrackingId(shipment.string("tracking")?.to_string()),
+                item_indices,
+                shipped_at: Timestamp(shipment.number("shipped_at")?),
+            })
+        })
+        .collect::<Result<_, FormatError>>()?;
+    let placed_at = match fields.get("placed_at")? {
+        Value::Null => None,
+        Value::Number(at) => Some(Timestamp(*at)),
+        _ => return Err(FormatError::NotAnEnvelope { field: "placed_at" }),
+    };
+    let uuid = match fields.get("uuid")? {
+        Value::Null => None,
+        Value::String(uuid) => Some(
+            uuid.parse::<Uuid128>()
+                .map_err(|_| FormatError::NotAnEnvelope { field: "uuid" })?,
+        ),
+        _ => return Err(FormatError::NotAnEnvelope { field: "uuid" }),
+    };
+    let reference = match fields.get("reference")? {
+        Value::Null => None,
+        Value::String(reference) => Some(OrderReference(reference.clone())),
+        _ => return Err(FormatError::NotAnEnvelope { field: "reference" }),
+    };
+    let approval = match fields.get("approval")? {
+        Value::Null => None,
+        Value::Object(decision) => match decision.as_slice() {
+            [(kind, Value::String(by))] if kind == "approved_by" => Some(Approval::Approved {
+                by: ApproverId(by.clone()),
+            }),
+            [(kind, Value::String(reason))] if kind == "rejected" => Some(Approval::Rejected {
+                reason: reason.clone(),
+            }),
+            _ => return Err(FormatError::NotAnEnvelope { field: "approval" }),
+        },
+        _ => return Err(FormatError::NotAnEnvelope { field: "approval" }),
+    };
+    let shipping_address = match fields.get("shipping_address")? {
+        Value::Null => None,
+        address => {
+            let address = Fields::of(address, "shipping_address")?;
+            Some(Address {
+                street: address.string("street")?.to_string(),
+                city: address.string("city")?.to_string(),
+                postal_code: address.string("postal_code")?.to_string(),
+                country: address.string("country")?.to_string(),
+            })
+        }
+    };
+    let gift_note = match fields.get("gift_note")? {
+        Value::Null => None,
+        Value::String(note) => Some(note.clone()),
+        _ => return Err(FormatError::NotAnEnvelope { field: "gift_note" }),
+    };
+    let notes = fields
+        .array("notes")?
+        .iter()
+        .map(|note| {
+            let note = Fields::of(note, "note")?;
+            Ok(OrderNote {
+                author: note.string("author")?.to_string(),
+                text: note.string("text")?.to_string(),
+                at: Timestamp(note.number("at")?),
+            })
+        })
+        .collect::<Result<_, FormatError>>()?;
+    let attachments = fields
+        .array("attachments")?
+        .iter()
+        .map(|attachment| {
+            let attachment = Fields::of(attachment, "attachment")?;
+            Ok(Attachment {
+                blob: BlobId(attachment.number("blob")?),
+                filename: attachment.string("filename")?.to_string(),
+                bytes: attachment.number("bytes")?,
+            })
+        })
+        .collect::<Result<_, FormatError>>()?;
+    let currency = Currency::from_code(fields.string("currency")?)
+        .ok_or(FormatError::NotAnEnvelope { field: "currency" })?;
+    Ok(Order {
+        id: OrderId(fields.u32("id")?),
+        items,
+        total: Money(fields.u32("total")?),
+        currency,
+        status: status(fields.string("status")?)?,
+        shipments,
+        placed_at,
+        uuid,
+        reference,
+        version: fields.u32("version")?,
+        approval,
+        shipping_address,
+        gift_note,
+        notes,
+        attachments,
+    })
+}
+
+fn status(name: &str) -> Result<OrderStatus, FormatError> {
+    [
+        OrderStatus::Placed,
+        OrderStatus::PendingApproval,
+        OrderStatus::UnderReview,
+        OrderStatus::PaymentPending,
+        OrderStatus::Paid,
+        OrderStatus::Shipped,
+        OrderStatus::Cancelled,
+    ]
+    .into_iter()
+    .find(|status| status.to_string() == name)
+    .ok_or(FormatError::NotAnEnvelope { field: "status" })
+}
+
+// The fields of an object, each failing with its own name when it is
+// missing or of the wrong kind.
+struct Fields<'a>(&'a [(String, Value)]);
+
+impl<'a> Fields<'a> {
+    fn of(value: &'a Value, what: &'static str) -> Result<Self, FormatError> {
+        match value {
+            Value::Object(fields) => Ok(Fields(fields)),
+            _ => Err(FormatError::NotAnEnvelope { field: what }),
+        }
+    }
+
+    fn get(&self, name: &'static str) -> Result<&'a Value, FormatError> {
+        self.0
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+            .ok_or(FormatError::NotAnEnvelope { field: name })
+    }
+
+    fn number(&self, name: &'static str) -> Result<u64, FormatError> {
+        match self.get(name)? {
+            Value::Number(n) => Ok(*n),
+            _ => Err(FormatError::NotAnEnvelope { field: name }),
+        }
+    }
+
+    fn u32(&self, name: &'static str) -> Result<u32, FormatError> {
+        u32::try_from(self.number(name)?).map_err(|_| FormatError::NotAnEnvelope { field: name })
+    }
+
+    fn string(&self, name: &'static str) -> Result<&'a str, FormatError> {
+        match self.get(name)? {
+            Value::String(text) => Ok(text),
+            _ => Err(FormatError::NotAnEnvelope { field: name }),
+        }
+    }
+
+    fn array(&self, name: &'static str) -> Result<&'a [Value], FormatError> {
+        match self.get(name)? {
+            Value::Array(values) => Ok(values),
+            _ => Err(FormatError::NotAnEnvelope { field: name }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn order(id: u32) -> Order {
+        Order::new(
+            OrderId(id),
+            vec![LineItem {
+                name: "Pen".to_string(),
+                price: Money(150),
+            }],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn every_field_survives_the_tree() {
+        let mut shipped = order(1);
+        shipped.status = OrderStatus::Shipped;
+        shipped.shipments = vec![Shipment {
+            tracking: TrackingId("1Z999".to_string()),
+            item_indices: vec![0],
+            shipped_at: Timestamp(1_700_000_100),
+        }];
+        shipped.placed_at = Some(Timestamp(1_700_000_000));
+        shipped.uuid = Some(Uuid128::v4(0x0123_4567_89ab_cdef, 0xfedc_ba98_7654_3210));
+        shipped.reference = Some(OrderReference("ORD-7F3K9Q".to_string()));
+        shipped.version = 3;
+        shipped.approval = Some(Approval::Approved {
+            by: ApproverId("alice".to_string()),
+        });
+        shipped.shipping_address = Some(Address {
+            street: "1 Main St".to_string(),
+            city: "Springfield".to_string(),
+            postal_code: "12345".to_string(),
+            country: "US".to_string(),
+        });
+        shipped.gift_note = Some("Happy birthday!\nLove, Sam".to_string());
+        shipped.currency = Currency::Kwd;
+        shipped
+            .add_note("bob", "Left with the neighbour", Timestamp(1_700_000_200))
+            .unwrap();
+        shipped.attachments.push(Attachment {
+            blob: BlobId(7),
+            filename: "receipt.pdf".to_string(),
+            bytes: 2048,
+        });
+        let mut rejected = order(2);
+        rejected.status = OrderStatus::Cancelled;
+        rejected.approval = Some(Approval::Rejected {
+            reason: "too large".to_string(),
+        });
+        let envelope = Envelope {
+            orders: vec![shipped],
+            deleted: vec![rejected],
+        };
+
+        assert_eq!(from_value(to_value(&envelope)), Ok(envelope));
+    }
+
+    #[test]
+    fn newer_versions_and_duplicate_ids_are_refused() {
+        let with = |version: u64, orders: Vec<Order>, deleted: Vec<Order>| {
+            let Value::Object(mut fields) = to_value(&Envelope { orders, deleted }) else {
+                unreachable!()
+            };
+            fields[0].1 = Value::Number(version);
+            from_value(Value::Object(fields))
+        };
+
+        assert_eq!(
+            with(6, vec![], vec![]),
+            Err(FormatError::UnsupportedVersion { found: 6 })
+        );
+        assert_eq!(
+            with(5, vec![order(1)], vec![order(1)]),
+            Err(FormatError::NotAnEnvelope { field: "order.id" })
+        );
+    }
+
+    #[test]
+    fn a_version_1_document_reads_with_no_address_and_no_note() {
+        let Value::Object(mut fields) = to_value(&Envelope {
+            orders: vec![order(1)],
+            deleted: vec![order(2)],
+        }) else {
+            unreachable!()
+        };
+        fields[0].1 = Value::Number(1);
+        for (_, orders) in &mut fields[1..] {
+            let Value::Array(orders) = orders else {
+                unreachable!()
+            };
+            for order in orders {
+                let Value::Object(order) = order else {
+                    unreachable!()
+                };
+                order.retain(|(name, _)| {
+                    ![
+                        "shipping_address",
+                        "gift_note",
+                        "currency",
+                        "notes",
+                        "attachments",
+                        "reference",
+                    ]
+                    .contains(&name.as_str())
+                });
+            }
+        }
+
+        let envelope = from_value(Value::Object(fields)).unwrap();
+
+        assert_eq!(envelope.orders, [order(1)]);
+        assert_eq!(envelope.deleted, [order(2)]);
+    }
+
+    #[test]
+    fn orders_are_put_back_in_id_order() {
+        let envelope = Envelope {
+            orders: vec![order(3), order(1), order(2)],
+            deleted: vec![],
+        };
+        let ids: Vec<u32> = from_value(to_value(&envelope))
+            .unwrap()
+            .orders
+            .iter()
+            .map(|order| order.id.0)
+            .collect();
+
+        assert_eq!(ids, [1, 2, 3]);
+    }
+}
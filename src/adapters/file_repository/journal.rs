@@ -0,0 +1,700 @@
+// --- Journaled file repository ---
+// Orders, the id sequence and the outbox, each in a file of one directory:
+//
+//     orders       the Envelope, in the StorageFormat given
+//     sequence     the next order id, then the open drafts, one a line
+//     outbox       the confirmations not dispatched yet, one envelope a line
+//     journal.log  the change being made
+//
+// Each file is replaced whole, through a temporary file and a rename, so
+// none of them is ever half written. Changed together they could still be
+// left out of step by a crash between two renames: an order saved, the
+// sequence not moved past it, its confirmation never queued. So a change
+// goes through the journal first:
+//
+//     1. the intent: the new contents of every file the change touches,
+//        written to journal.log before any of them
+//     2. the files, replaced one after the other
+//     3. DONE, appended to the intent
+//
+// open() reads the journal before the files. An intent without its DONE is
+// played again, whole: the files end up as the change left them, however
+// far it got. An intent the crash cut short is dropped: nothing was
+// replaced yet. Either way, the three files agree.
+//
+// begin() gathers the changes up to commit() into one intent: placing an
+// order is saving it, moving the sequence on and queuing its confirmation,
+// all of it or none. Outside of a unit of work, each change is an intent
+// of its own. Once its intent is in the journal a change is made, even if
+// a file then cannot be replaced: the next change, shutdown or open
+// finishes it first.
+//
+// The files are read and written through an Fs, the real one by default;
+// testing::FailpointFs is one that stops dead after so many writes.
+use super::{Envelope, StorageFormat, position, transfer};
+use crate::adapters::{ConfigError, Console, confirmation_envelope, parse_any_confirmation};
+use crate::domain::{Order, OrderConfirmation, OrderError, OrderId};
+use crate::ports::{
+    Capability, Level, OrderReader, OrderWriter, Sender, ServiceState, Shutdown, ShutdownError,
+    StateStore, UnitOfWork,
+};
+use std::collections::BTreeSet;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+const JOURNAL: &str = "journal.log";
+
+// The file operations the repository needs, and nothing else.
+pub trait Fs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>>;
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()>;
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()>;
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()>;
+    fn create_dir_all(&self, path: &Path) -> io::Result<()>;
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealFs;
+
+impl Fs for RealFs {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        fs::read(path)
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        fs::write(path, bytes)
+    }
+
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?
+            .write_all(bytes)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        fs::rename(from, to)
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        fs::create_dir_all(path)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Part {
+    Orders,
+    Sequence,
+    Outbox,
+}
+
+impl Part {
+    const ALL: [Part; 3] = [Part::Orders, Part::Sequence, Part::Outbox];
+
+    fn file_name(self) -> &'static str {
+        match self {
+            Part::Orders => "orders",
+            Part::Sequence => "sequence",
+            Part::Outbox => "outbox",
+        }
+    }
+
+    fn named(name: &str) -> Option<Part> {
+        Part::ALL.into_iter().find(|part| part.file_name() == name)
+    }
+}
+
+// What the three files hold.
+#[derive(Debug, Clone, Default)]
+struct Files {
+    envelope: Envelope,
+    state: Option<ServiceState>,
+    // One confirmation envelope each, oldest first.
+    outbox: Vec<String>,
+}
+
+// The changes since begin(), on a copy of the files.
+struct Unit {
+    files: Files,
+    touched: BTreeSet<Part>,
+}
+
+struct State {
+    files: Files,
+    unit: Option<Unit>,
+    // Logged, but with some of its files still to replace.
+    unfinished: Option<Intent>,
+    // Numbers the intents, so that a DONE names the one it ends.
+    last_intent: u64,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Intent {
+    number: u64,
+    writes: Vec<(Part, Vec<u8>)>,
+}
+
+impl Intent {
+    //     INTENT 7
+    //     sequence 2
+    //     8
+    //     END 7
+    //
+    // A length before each file's contents, which may hold any byte.
+    fn encode(&self) -> Vec<u8> {
+        let mut bytes = format!("INTENT {}\n", self.number).into_bytes();
+        for (part, contents) in &self.writes {
+            bytes.extend(format!("{} {}\n", part.file_name(), contents.len()).as_bytes());
+            bytes.extend(contents);
+            bytes.push(b'\n');
+        }
+        bytes.extend(format!("END {}\n", self.number).as_bytes());
+        bytes
+    }
+
+    fn done(&self) -> Vec<u8> {
+        format!("DONE {}\n", self.number).into_bytes()
+    }
+
+    // The intent a journal holds, if it is there whole, and whether its
+    // DONE is too.
+    fn decode(mut rest: &[u8]) -> Option<(Intent, bool)> {
+        let number = line(&mut rest)?.strip_prefix("INTENT ")?.parse().ok()?;
+        let end = format!("END {number}");
+        let mut writes = Vec::new();
+        loop {
+            let header = line(&mut rest)?;
+            if header == end {
+                break;
+            }
+            let (name, length) = header.split_once(' ')?;
+            let part = Part::named(name)?;
+            let length: usize = length.parse().ok()?;
+            if rest.get(length) != Some(&b'\n') {
+                return None;
+            }
+            writes.push((part, rest[..length].to_vec()));
+            rest = &rest[length + 1..];
+        }
+        let done = line(&mut rest) == Some(format!("DONE {number}").as_str());
+        Some((Intent { number, writes }, done))
+    }
+}
+
+// The next line, without its '\n'; None when there is no whole one.
+fn line<'a>(rest: &mut &'a [u8]) -> Option<&'a str> {
+    let at = rest.iter().position(|&byte| byte == b'\n')?;
+    let text = std::str::from_utf8(&rest[..at]).ok()?;
+    *rest = &rest[at + 1..];
+    Some(text)
+}
+
+pub struct JournaledRepository<F: StorageFormat, S: Fs = RealFs> {
+    dir: PathBuf,
+    format: F,
+    fs: S,
+    state: Mutex<State>,
+    console: Console,
+}
+
+impl<F: StorageFormat> JournaledRepository<F> {
+    pub fn open(dir: impl Into<PathBuf>, format: F) -> Result<Self, ConfigError> {
+        Self::open_on(dir, format, RealFs)
+    }
+}
+
+impl<F: StorageFormat, S: Fs> JournaledRepository<F, S> {
+    // A missing directory, or missing files, are an empty repository. The
+    // journal is recovered before anything is read: a file that then cannot
+    // be read or understood is an error, as for FileOrderRepository.
+    pub fn open_on(dir: impl Into<PathBuf>, format: F, fs: S) -> Result<Self, ConfigError> {
+        let dir = dir.into();
+        let io_error = |path: &Path| {
+            let path = path.to_path_buf();
+            move |error: io::Error| ConfigError::IoError {
+                path,
+                kind: error.kind(),
+            }
+        };
+        fs.create_dir_all(&dir).map_err(io_error(&dir))?;
+        let repository = Self {
+            dir,
+            format,
+            fs,
+            state: Mutex::new(State {
+                files: Files::default(),
+                unit: None,
+                unfinished: None,
+                last_intent: 0,
+            }),
+            console: Console::default(),
+        };
+        let journal = repository.path(JOURNAL);
+        let logged = read_or_empty(&repository.fs, &journal).map_err(io_error(&journal))?;
+        let mut last_intent = 0;
+        if let Some((intent, done)) = Intent::decode(&logged) {
+            if !done {
+                repository.play(&intent).map_err(io_error(&journal))?;
+            }
+            last_intent = intent.number;
+        }
+        let files = repository.load()?;
+        *repository.state() = State {
+            files,
+            unit: None,
+            unfinished: None,
+            last_intent,
+        };
+        Ok(repository)
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn fs(&self) -> &S {
+        &self.fs
+    }
+
+    // The confirmations sent and not dispatched yet, oldest first.
+    pub fn outbox(&self) -> Result<Vec<OrderConfirmation>, OrderError> {
+        let state = self.state();
+        visible(&state)
+            .outbox
+            .iter()
+            .map(|line| parse_any_confirmation(line.as_bytes()))
+            .collect::<Result<_, _>>()
+            .map_err(|_| OrderError::StorageFailed)
+    }
+
+    // The first `count` of the outbox are dispatched and need not be kept.
+    pub fn acknowledge(&self, count: usize) -> Result<(), OrderError> {
+        self.console.line(format_args!(
+            "  [Journal] Acknowledging {count} confirmation(s)"
+        ));
+        self.change(Part::Outbox, |files| {
+            let count = count.min(files.outbox.len());
+            files.outbox.drain(..count);
+            Ok(())
+        })
+    }
+
+    fn path(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    // Held for the whole of a change, its writes included.
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn load(&self) -> Result<Files, ConfigError> {
+        let mut files = Files::default();
+        for part in Part::ALL {
+            let path = self.path(part.file_name());
+            let bytes = read_or_empty(&self.fs, &path).map_err(|error| ConfigError::IoError {
+                path: path.clone(),
+                kind: error.kind(),
+            })?;
+            let invalid = |reason: String| ConfigError::InvalidFile {
+                path: path.clone(),
+                reason,
+            };
+            match part {
+                Part::Orders if !bytes.is_empty() => {
+                    files.envelope = self
+                        .format
+                        .deserialize(&bytes)
+                        .map_err(|error| invalid(error.to_string()))?;
+                }
+                Part::Orders => {}
+                Part::Sequence => {
+                    files.state = decode_state(&bytes)
+                        .ok_or_else(|| invalid("not a sequence".to_string()))?;
+                }
+                Part::Outbox => {
+                    let text =
+                        String::from_utf8(bytes).map_err(|_| invalid("not UTF-8".to_string()))?;
+                    files.outbox = text.lines().map(str::to_string).collect();
+                }
+            }
+        }
+        Ok(files)
+    }
+
+    fn encode(&self, files: &Files, part: Part) -> Vec<u8> {
+        match part {
+            Part::Orders => self.format.serialize(&files.envelope),
+            Part::Sequence => files.state.as_ref().map_or_else(Vec::new, encode_state),
+            Part::Outbox => files
+                .outbox
+                .iter()
+                .flat_map(|line| [line.as_bytes(), b"\n"])
+                .flatten()
+                .copied()
+                .collect(),
+        }
+    }
+
+    // Replaces the files of `intent`, then marks it done.
+    fn play(&self, intent: &Intent) -> io::Result<()> {
+        for (part, contents) in &intent.writes {
+            let path = self.path(part.file_name());
+            let temporary = self.path(&format!("{}.tmp", part.file_name()));
+            self.fs.write(&temporary, contents)?;
+            self.fs.rename(&temporary, &path)?;
+        }
+        self.fs.append(&self.path(JOURNAL), &intent.done())
+    }
+
+    fn finish(&self, state: &mut State) -> Result<(), OrderError> {
+        if let Some(intent) = &state.unfinished {
+            self.play(intent).map_err(|_| OrderError::StorageFailed)?;
+            state.unfinished = None;
+        }
+        Ok(())
+    }
+
+    // Makes `files` the repository's, through an intent for the `touched`
+    // ones. Nothing is kept when the intent cannot be logged.
+    fn commit_files(
+        &self,
+        state: &mut State,
+        files: Files,
+        touched: &BTreeSet<Part>,
+    ) -> Result<(), OrderError> {
+        self.finish(state)?;
+        if touched.is_empty() {
+            return Ok(());
+        }
+        let intent = Intent {
+            number: state.last_intent + 1,
+            writes: touched
+                .iter()
+                .map(|&part| (part, self.encode(&files, part)))
+                .collect(),
+        };
+        self.fs
+            .write(&self.path(JOURNAL), &intent.encode())
+            .map_err(|_| OrderError::StorageFailed)?;
+        state.last_intent = intent.number;
+        state.files = files;
+        state.unfinished = Some(intent);
+        if self.finish(state).is_err() {
+            self.console.log(
+                Level::Warn,
+                "Journal",
+                format_args!(
+                    "  [Journal] Intent {} logged but not applied yet: finishing it later",
+                    state.last_intent
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    // `edit` on the unit of work when one is open, or as a change of its
+    // own. An edit that fails must leave the files it was given alone.
+    fn change<T>(
+        &self,
+        part: Part,
+        edit: impl FnOnce(&mut Files) -> Result<T, OrderError>,
+    ) -> Result<T, OrderError> {
+        let mut state = self.state();
+        if let Some(unit) = &mut state.unit {
+            let result = edit(&mut unit.files)?;
+            unit.touched.insert(part);
+            return Ok(result);
+        }
+        let mut files = state.files.clone();
+        let result = edit(&mut files)?;
+        self.commit_files(&mut state, files, &BTreeSet::from([part]))?;
+        Ok(result)
+    }
+}
+
+// What reads see: a unit of work sees its own changes.
+fn visible(state: &State) -> &Files {
+    state.unit.as_ref().map_or(&state.files, |unit| &unit.files)
+}
+
+fn read_or_empty(fs: &impl Fs, path: &Path) -> io::Result<Vec<u8>> {
+    match fs.read(path) {
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        result => result,
+    }
+}
+
+//     4
+//     2
+//
+// The next id, then the open drafts.
+fn encode_state(state: &ServiceState) -> Vec<u8> {
+    let mut text = format!("{}\n", state.next_id);
+    for draft in &state.open_drafts {
+        text.push_str(&format!("{}\n", draft.0));
+    }
+    text.into_bytes()
+}
+
+// Some(None) for a sequence never saved.
+fn decode_state(bytes: &[u8]) -> Option<Option<ServiceState>> {
+    let text = std::str::from_utf8(bytes).ok()?;
+    let mut numbers = text.lines().map(str::parse::<u32>);
+    let Some(next_id) = numbers.next() else {
+        return Some(None);
+    };
+    let open_drafts = numbers
+        .map(|id| id.map(OrderId))
+        .collect::<Result<_, _>>()
+        .ok()?;
+    Some(Some(ServiceState {
+        next_id: next_id.ok()?,
+        open_drafts,
+    }))
+}
+
+impl<F: StorageFormat, S: Fs> OrderReader for JournaledRepository<F, S> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.console
+            .line(format_args!("  [Journal] Finding order {id:?}"));
+        let state = self.state();
+        let orders = &visible(&state).envelope.orders;
+        Ok(position(orders, id).ok().map(|at| orders[at].clone()))
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        let state = self.state();
+        let orders = &visible(&state).envelope.orders;
+        self.console.line(format_args!(
+            "  [Journal] Listing {} order(s)",
+            orders.len()
+        ));
+        Ok(orders.clone())
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        visible(&self.state())
+            .envelope
+            .orders
+            .iter()
+            .for_each(visit);
+        Ok(())
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        Ok(visible(&self.state()).envelope.deleted.clone())
+    }
+}
+
+impl<F: StorageFormat, S: Fs> OrderWriter for JournaledRepository<F, S> {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [Journal] Saving order {:?}", order.id));
+        self.change(Part::Orders, |files| {
+            let orders = &mut files.envelope.orders;
+            match position(orders, order.id) {
+                Ok(at) => orders[at] = order.clone(),
+                Err(at) => orders.insert(at, order.clone()),
+            }
+            Ok(())
+        })
+    }
+
+    // The version is checked inside the change, under the lock its write
+    // takes: of two writers that read the same version, only one gets
+    // through.
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        let id = order.id;
+        self.console
+            .line(format_args!("  [Journal] Updating order {id:?}"));
+        self.change(Part::Orders, |files| {
+            let orders = &mut files.envelope.orders;
+            let at = position(orders, id).map_err(|_| OrderError::NotFound { id })?;
+            if orders[at].version != order.version {
+                return Err(OrderError::VersionConflict {
+                    id,
+                    expected: order.version,
+                    found: orders[at].version,
+                });
+            }
+            orders[at] = order.clone();
+            orders[at].version += 1;
+            Ok(())
+        })
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [Journal] Deleting order {id:?}"));
+        self.change(Part::Orders, |files| {
+            let Envelope { orders, deleted } = &mut files.envelope;
+            if transfer(orders, deleted, id) || position(deleted, id).is_ok() {
+                Ok(())
+            } else {
+                Err(OrderError::NotFound { id })
+            }
+        })
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [Journal] Restoring order {id:?}"));
+        self.change(Part::Orders, |files| {
+            let Envelope { orders, deleted } = &mut files.envelope;
+            if transfer(deleted, orders, id) {
+                Ok(())
+            } else if position(orders, id).is_ok() {
+                Err(OrderError::NotDeleted { id })
+            } else {
+                Err(OrderError::NotFound { id })
+            }
+        })
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [Journal] Purging order {id:?}"));
+        self.change(Part::Orders, |files| {
+            let Envelope { orders, deleted } = &mut files.envelope;
+            if let Ok(at) = position(orders, id) {
+                orders.remove(at);
+            } else if let Ok(at) = position(deleted, id) {
+                deleted.remove(at);
+            } else {
+                return Err(OrderError::NotFound { id });
+            }
+            Ok(())
+        })
+    }
+}
+
+impl<F: StorageFormat, S: Fs> StateStore for JournaledRepository<F, S> {
+    fn save_state(&self, state: &ServiceState) -> Result<(), OrderError> {
+        self.change(Part::Sequence, |files| {
+            files.state = Some(state.clone());
+            Ok(())
+        })
+    }
+
+    fn load_state(&self) -> Result<Option<ServiceState>, OrderError> {
+        Ok(visible(&self.state()).state.clone())
+    }
+}
+
+// Sending is queuing: whatever dispatches the outbox acknowledges what it
+// delivered.
+impl<F: StorageFormat, S: Fs> Sender for JournaledRepository<F, S> {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.console.line(format_args!(
+            "  [Journal] Queuing the confirmation of order {:?}",
+            confirmation.order_id
+        ));
+        let envelope = confirmation_envelope(confirmation);
+        self.change(Part::Outbox, |files| {
+            files.outbox.push(envelope);
+            Ok(())
+        })
+    }
+}
+
+impl<F: StorageFormat, S: Fs> UnitOfWork for JournaledRepository<F, S> {
+    // A unit already open goes on: its changes and the next ones are
+    // committed together.
+    fn begin(&mut self) {
+        self.console.line(format_args!("  [Journal] Begin"));
+        let mut state = self.state();
+        if state.unit.is_none() {
+            state.unit = Some(Unit {
+                files: state.files.clone(),
+                touched: BTreeSet::new(),
+            });
+        }
+    }
+
+    fn commit(&mut self) -> Result<(), OrderError> {
+        self.console.line(format_args!("  [Journal] Commit"));
+        let mut state = self.state();
+        let Some(unit) = state.unit.take() else {
+            return Ok(());
+        };
+        self.commit_files(&mut state, unit.files, &unit.touched)
+    }
+
+    fn rollback(&mut self) {
+        self.console.line(format_args!("  [Journal] Rollback"));
+        self.state().unit = None;
+    }
+}
+
+impl<F: StorageFormat, S: Fs> Capability for JournaledRepository<F, S> {
+    fn as_transactional(&mut self) -> Option<&mut dyn UnitOfWork> {
+        Some(self)
+    }
+}
+
+impl<F: StorageFormat, S: Fs> Shutdown for JournaledRepository<F, S> {
+    // What a unit of work left uncommitted is lost, as it would be with a
+    // database.
+    fn shutdown(&mut self) -> Result<(), ShutdownError> {
+        let mut state = self.state();
+        self.finish(&mut state).map_err(ShutdownError::Failed)?;
+        match state.unit.take() {
+            Some(_) => Err(ShutdownError::Abandoned {
+                count: 1,
+                what: "uncommitted unit of work",
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn intent() -> Intent {
+        Intent {
+            number: 3,
+            writes: vec![
+                (Part::Sequence, b"8\n".to_vec()),
+                (Part::Outbox, b"a\nb\n".to_vec()),
+            ],
+        }
+    }
+
+    #[test]
+    fn an_intent_cut_short_anywhere_is_no_intent() {
+        let logged = intent().encode();
+        for end in 0..logged.len() {
+            assert_eq!(Intent::decode(&logged[..end]), None, "{end}");
+        }
+        assert_eq!(Intent::decode(&logged), Some((intent(), false)));
+
+        let mut done = logged;
+        done.extend(intent().done());
+        assert_eq!(Intent::decode(&done), Some((intent(), true)));
+        // Half a DONE is not one.
+        done.truncate(done.len() - 2);
+        assert_eq!(Intent::decode(&done), Some((intent(), false)));
+    }
+
+    #[test]
+    fn the_sequence_reads_back_as_written() {
+        let state = ServiceState {
+            next_id: 9,
+            open_drafts: vec![OrderId(4), OrderId(7)],
+        };
+        assert_eq!(decode_state(&encode_state(&state)), Some(Some(state)));
+        assert_eq!(decode_state(b""), Some(None));
+        assert_eq!(decode_state(b"nine\n"), None);
+    }
+}
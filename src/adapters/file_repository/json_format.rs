@@ -0,0 +1,89 @@
+// JSON: the largest of the three, and the one a human can read and fix
+// with any editor.
+use super::{Envelope, FormatError, StorageFormat, document};
+use crate::adapters::json::{self, Value};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct JsonFormat;
+
+impl StorageFormat for JsonFormat {
+    fn name(&self) -> &'static str {
+        "JSON"
+    }
+
+    fn serialize(&self, envelope: &Envelope) -> Vec<u8> {
+        let mut text = String::new();
+        write(&document::to_value(envelope), &mut text);
+        text.into_bytes()
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Envelope, FormatError> {
+        let malformed = FormatError::Malformed { format: "JSON" };
+        let text = std::str::from_utf8(bytes).map_err(|_| malformed.clone())?;
+        document::from_value(json::parse(text).ok_or(malformed)?)
+    }
+}
+
+fn write(value: &Value, out: &mut String) {
+    match value {
+        Value::String(text) => {
+            out.push('"');
+            out.push_str(&json::escape(text));
+            out.push('"');
+        }
+        Value::Number(n) => out.push_str(&n.to_string()),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Null => out.push_str("null"),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(fields) => {
+            out.push('{');
+            for (i, (key, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push('"');
+                out.push_str(&json::escape(key));
+                out.push_str("\":");
+                write(value, out);
+            }
+            out.push('}');
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_what_the_parser_reads() {
+        let value = Value::Object(vec![
+            (
+                "name".to_string(),
+                Value::String("12\" \"Ruler\"\n".to_string()),
+            ),
+            (
+                "list".to_string(),
+                Value::Array(vec![Value::Number(7), Value::Bool(true), Value::Null]),
+            ),
+            ("empty".to_string(), Value::Object(vec![])),
+        ]);
+        let mut text = String::new();
+        write(&value, &mut text);
+
+        assert_eq!(
+            text,
+            r#"{"name":"12\" \"Ruler\"\n","list":[7,true,null],"empty":{}}"#
+        );
+        assert_eq!(json::parse(&text), Some(value));
+    }
+}
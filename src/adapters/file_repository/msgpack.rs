@@ -0,0 +1,253 @@
+// MessagePack: binary, the smallest and the fastest to write, unreadable
+// without a tool. The subset a document needs: nil, booleans, unsigned
+// integers, UTF-8 strings, arrays and maps with string keys, each in the
+// shortest encoding that holds it. Anything else is refused, and so is a
+// length running past the end of the file.
+use super::{Envelope, FormatError, StorageFormat, document};
+use crate::adapters::json::{MAX_DEPTH, Value};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MsgPackFormat;
+
+impl StorageFormat for MsgPackFormat {
+    fn name(&self) -> &'static str {
+        "MessagePack"
+    }
+
+    fn serialize(&self, envelope: &Envelope) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write(&document::to_value(envelope), &mut bytes);
+        bytes
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Envelope, FormatError> {
+        let mut reader = Reader { bytes, at: 0 };
+        let value =
+            reader
+                .value(0)
+                .filter(|_| reader.at == bytes.len())
+                .ok_or(FormatError::Malformed {
+                    format: "MessagePack",
+                })?;
+        document::from_value(value)
+    }
+}
+
+// The header of a string, an array or a map of `len` entries: the `fix`
+// byte with the length in it when it is below `fix_max`, else a marker
+// from `markers` (8, 16, 32 bits) followed by the length.
+fn write_len(len: usize, fix: u8, fix_max: usize, markers: [Option<u8>; 3], out: &mut Vec<u8>) {
+    let len32 = u32::try_from(len).expect("no document part holds 2^32 entries");
+    match (len, markers) {
+        (len, _) if len < fix_max => out.push(fix | len as u8),
+        (len, [Some(marker), _, _]) if len <= 0xff => out.extend([marker, len as u8]),
+        (len, [_, Some(marker), _]) if len <= 0xffff => {
+            out.push(marker);
+            out.extend((len as u16).to_be_bytes());
+        }
+        (_, [_, _, Some(marker)]) => {
+            out.push(marker);
+            out.extend(len32.to_be_bytes());
+        }
+        _ => unreachable!("every length has a 32-bit marker"),
+    }
+}
+
+fn write_str(text: &str, out: &mut Vec<u8>) {
+    write_len(
+        text.len(),
+        0xa0,
+        32,
+        [Some(0xd9), Some(0xda), Some(0xdb)],
+        out,
+    );
+    out.extend(text.as_bytes());
+}
+
+fn write(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::Null => out.push(0xc0),
+        Value::Bool(b) => out.push(if *b { 0xc3 } else { 0xc2 }),
+        Value::Number(n) => match *n {
+            n if n < 0x80 => out.push(n as u8),
+            n if n <= 0xff => out.extend([0xcc, n as u8]),
+            n if n <= 0xffff => {
+                out.push(0xcd);
+                out.extend((n as u16).to_be_bytes());
+            }
+            n if n <= 0xffff_ffff => {
+                out.push(0xce);
+                out.extend((n as u32).to_be_bytes());
+            }
+            n => {
+                out.push(0xcf);
+                out.extend(n.to_be_bytes());
+            }
+        },
+        Value::String(text) => write_str(text, out),
+        Value::Array(items) => {
+            write_len(items.len(), 0x90, 16, [None, Some(0xdc), Some(0xdd)], out);
+            items.iter().for_each(|item| write(item, out));
+        }
+        Value::Object(fields) => {
+            write_len(fields.len(), 0x80, 16, [None, Some(0xde), Some(0xdf)], out);
+            for (key, value) in fields {
+                write_str(key, out);
+                write(value, out);
+            }
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, n: usize) -> Option<&[u8]> {
+        let taken = self.bytes.get(self.at..self.at.checked_add(n)?)?;
+        self.at += n;
+        Some(taken)
+    }
+
+    fn uint(&mut self, n: usize) -> Option<u64> {
+        Some(
+            self.take(n)?
+                .iter()
+                .fold(0, |value, &byte| value << 8 | u64::from(byte)),
+        )
+    }
+
+    fn string(&mut self, len: usize) -> Option<String> {
+        String::from_utf8(self.take(len)?.to_vec()).ok()
+    }
+
+    // Every entry takes at least one byte: a count larger than what is
+    // left is a lie, refused before anything is allocated for it.
+    fn count(&mut self, n: usize) -> Option<usize> {
+        let count = usize::try_from(self.uint(n)?).ok()?;
+        (count <= self.bytes.len() - self.at).then_some(count)
+    }
+
+    fn value(&mut self, depth: usize) -> Option<Value> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let marker = *self.take(1)?.first()?;
+        let value = match marker {
+            0x00..=0x7f => Value::Number(u64::from(marker)),
+            0x80..=0x8f => self.map(usize::from(marker & 0x0f), depth)?,
+            0x90..=0x9f => self.array(usize::from(marker & 0x0f), depth)?,
+            0xa0..=0xbf => Value::String(self.string(usize::from(marker & 0x1f))?),
+            0xc0 => Value::Null,
+            0xc2 => Value::Bool(false),
+            0xc3 => Value::Bool(true),
+            0xcc => Value::Number(self.uint(1)?),
+            0xcd => Value::Number(self.uint(2)?),
+            0xce => Value::Number(self.uint(4)?),
+            0xcf => Value::Number(self.uint(8)?),
+            0xd9..=0xdb => {
+                let len = self.count(1 << (marker - 0xd9))?;
+                Value::String(self.string(len)?)
+            }
+            0xdc..=0xdd => {
+                let len = self.count(2 << (marker - 0xdc))?;
+                self.array(len, depth)?
+            }
+            0xde..=0xdf => {
+                let len = self.count(2 << (marker - 0xde))?;
+                self.map(len, depth)?
+            }
+            _ => return None,
+        };
+        Some(value)
+    }
+
+    fn array(&mut self, len: usize, depth: usize) -> Option<Value> {
+        let items = (0..len)
+            .map(|_| self.value(depth + 1))
+            .collect::<Option<_>>()?;
+        Some(Value::Array(items))
+    }
+
+    fn map(&mut self, len: usize, depth: usize) -> Option<Value> {
+        let fields = (0..len)
+            .map(|_| match self.value(depth + 1)? {
+                Value::String(key) => Some((key, self.value(depth + 1)?)),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+        Some(Value::Object(fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded(value: &Value) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        write(value, &mut bytes);
+        bytes
+    }
+
+    fn decoded(bytes: &[u8]) -> Option<Value> {
+        let mut reader = Reader { bytes, at: 0 };
+        reader.value(0).filter(|_| reader.at == bytes.len())
+    }
+
+    #[test]
+    fn uses_the_shortest_encoding() {
+        assert_eq!(encoded(&Value::Number(127)), [0x7f]);
+        assert_eq!(encoded(&Value::Number(128)), [0xcc, 0x80]);
+        assert_eq!(encoded(&Value::Number(4999)), [0xcd, 0x13, 0x87]);
+        assert_eq!(
+            encoded(&Value::Number(1 << 32)),
+            [0xcf, 0, 0, 0, 1, 0, 0, 0, 0]
+        );
+        assert_eq!(encoded(&Value::String("Pen".to_string())), b"\xa3Pen");
+        assert_eq!(
+            encoded(&Value::Object(vec![("a".to_string(), Value::Null)])),
+            [0x81, 0xa1, b'a', 0xc0]
+        );
+    }
+
+    #[test]
+    fn every_size_class_survives_the_round_trip() {
+        for value in [
+            Value::Number(0),
+            Value::Number(u64::MAX),
+            Value::Number(70_000),
+            Value::Bool(true),
+            Value::String("x".repeat(31)),
+            Value::String("x".repeat(32)),
+            Value::String("é".repeat(200)),
+            Value::String("x".repeat(70_000)),
+            Value::Array(vec![Value::Null; 15]),
+            Value::Array(vec![Value::Number(1); 16]),
+            Value::Array(vec![Value::Bool(false); 70_000]),
+            Value::Object((0..20).map(|i| (i.to_string(), Value::Number(i))).collect()),
+        ] {
+            assert_eq!(decoded(&encoded(&value)), Some(value));
+        }
+    }
+
+    #[test]
+    fn refuses_truncated_or_foreign_bytes() {
+        let bytes = encoded(&Value::Array(vec![Value::String("Pen".to_string())]));
+        for cut in 0..bytes.len() {
+            assert_eq!(decoded(&bytes[..cut]), None, "cut at {cut}");
+        }
+        // Negative integers, floats, binaries are outside the subset.
+        assert_eq!(decoded(&[0xff]), None);
+        assert_eq!(decoded(&[0xcb, 0, 0, 0, 0, 0, 0, 0, 0]), None);
+        assert_eq!(decoded(&[0xc4, 0]), None);
+        // A map key must be a string, a string valid UTF-8.
+        assert_eq!(decoded(&[0x81, 0x01, 0xc0]), None);
+        assert_eq!(decoded(&[0xa1, 0xff]), None);
+        // Four billion entries announced, none there.
+        assert_eq!(decoded(&[0xdd, 0xff, 0xff, 0xff, 0xff]), None);
+        assert_eq!(decoded(&[0x90, 0x90]), None);
+    }
+}
@@ -0,0 +1,328 @@
+// RON, Rust Object Notation: as readable as JSON, with Rust's syntax.
+// Objects are anonymous structs, `(name: "Pen", price: 150)`, lists are
+// `[...]`, and an absent value is `None`. A present one is written bare,
+// which is what the implicit_some extension in the header allows:
+//
+//     #![enable(implicit_some)]
+//     (version: 5, orders: [(id: 1, ..., placed_at: None, ...)], deleted: [])
+//
+// Only that subset is read back, plus `Some(...)`, struct names before
+// `(` and comments, so a file edited by hand is still accepted.
+use super::{Envelope, FormatError, StorageFormat, document};
+use crate::adapters::json::{MAX_DEPTH, Value};
+
+const HEADER: &str = "#![enable(implicit_some)]\n";
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RonFormat;
+
+impl StorageFormat for RonFormat {
+    fn name(&self) -> &'static str {
+        "RON"
+    }
+
+    fn serialize(&self, envelope: &Envelope) -> Vec<u8> {
+        let mut text = HEADER.to_string();
+        write(&document::to_value(envelope), &mut text);
+        text.push('\n');
+        text.into_bytes()
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Envelope, FormatError> {
+        let value = std::str::from_utf8(bytes)
+            .ok()
+            .and_then(parse)
+            .ok_or(FormatError::Malformed { format: "RON" })?;
+        document::from_value(value)
+    }
+}
+
+fn write(value: &Value, out: &mut String) {
+    match value {
+        Value::String(text) => {
+            out.push('"');
+            for c in text.chars() {
+                match c {
+                    '"' => out.push_str("\\\""),
+                    '\\' => out.push_str("\\\\"),
+                    '\n' => out.push_str("\\n"),
+                    '\r' => out.push_str("\\r"),
+                    '\t' => out.push_str("\\t"),
+                    c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+                    c => out.push(c),
+                }
+            }
+            out.push('"');
+        }
+        Value::Number(n) => out.push_str(&n.to_string()),
+        Value::Bool(b) => out.push_str(if *b { "true" } else { "false" }),
+        Value::Null => out.push_str("None"),
+        Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write(item, out);
+            }
+            out.push(']');
+        }
+        Value::Object(fields) => {
+            out.push('(');
+            for (i, (key, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                out.push_str(key);
+                out.push_str(": ");
+                write(value, out);
+            }
+            out.push(')');
+        }
+    }
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+// None for anything outside the subset, including trailing garbage.
+fn parse(text: &str) -> Option<Value> {
+    let mut chars = text.chars().peekable();
+    skip_blank(&mut chars)?;
+    // Attributes only switch extensions on; what they say is not needed.
+    while chars.peek() == Some(&'#') {
+        while chars.next()? != ']' {}
+        skip_blank(&mut chars)?;
+    }
+    let value = parse_value(&mut chars, 0)?;
+    skip_blank(&mut chars)?;
+    chars.next().is_none().then_some(value)
+}
+
+// Whitespace and comments. None for a block comment left open.
+fn skip_blank(chars: &mut Chars<'_>) -> Option<()> {
+    loop {
+        match chars.peek() {
+            Some(c) if c.is_whitespace() => {
+                chars.next();
+            }
+            Some('/') => {
+                chars.next();
+                match chars.next()? {
+                    '/' => while chars.next().is_some_and(|c| c != '\n') {},
+                    '*' => {
+                        let mut previous = ' ';
+                        loop {
+                            let c = chars.next()?;
+                            if previous == '*' && c == '/' {
+                                break;
+                            }
+                            previous = c;
+                        }
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return Some(()),
+        }
+    }
+}
+
+fn parse_value(chars: &mut Chars<'_>, depth: usize) -> Option<Value> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    skip_blank(chars)?;
+    match *chars.peek()? {
+        '"' => parse_string(chars).map(Value::String),
+        '[' => {
+            chars.next();
+            let items = parse_list(chars, ']', |chars| parse_value(chars, depth + 1))?;
+            Some(Value::Array(items))
+        }
+        '(' => parse_struct(chars, depth),
+        c if c.is_ascii_digit() => parse_number(chars).map(Value::Number),
+        _ => match parse_identifier(chars)?.as_str() {
+            "true" => Some(Value::Bool(true)),
+            "false" => Some(Value::Bool(false)),
+            "None" => Some(Value::Null),
+            "Some" => {
+                skip_blank(chars)?;
+                expect(chars, '(')?;
+                let value = parse_value(chars, depth + 1)?;
+                skip_blank(chars)?;
+                expect(chars, ')')?;
+                Some(value)
+            }
+            // A named struct: `Order(id: 1, ...)`.
+            _ => {
+                skip_blank(chars)?;
+                (chars.peek() == Some(&'('))
+                    .then(|| parse_struct(chars, depth))
+                    .flatten()
+            }
+        },
+    }
+}
+
+fn parse_struct(chars: &mut Chars<'_>, depth: usize) -> Option<Value> {
+    expect(chars, '(')?;
+    let fields = parse_list(chars, ')', |chars| {
+        let key = parse_identifier(chars)?;
+        skip_blank(chars)?;
+        expect(chars, ':')?;
+        Some((key, parse_value(chars, depth + 1)?))
+    })?;
+    Some(Value::Object(fields))
+}
+
+// Items separated by commas up to `close`, a trailing comma allowed.
+fn parse_list<T>(
+    chars: &mut Chars<'_>,
+    close: char,
+    mut item: impl FnMut(&mut Chars<'_>) -> Option<T>,
+) -> Option<Vec<T>> {
+    let mut items = Vec::new();
+    loop {
+        skip_blank(chars)?;
+        if chars.peek() == Some(&close) {
+            chars.next();
+            return Some(items);
+        }
+        items.push(item(chars)?);
+        skip_blank(chars)?;
+        match chars.next()? {
+            ',' => continue,
+            c if c == close => return Some(items),
+            _ => return None,
+        }
+    }
+}
+
+fn expect(chars: &mut Chars<'_>, expected: char) -> Option<()> {
+    (chars.next()? == expected).then_some(())
+}
+
+fn parse_identifier(chars: &mut Chars<'_>) -> Option<String> {
+    skip_blank(chars)?;
+    let mut name = String::new();
+    while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric() || **c == '_') {
+        name.push(c);
+        chars.next();
+    }
+    name.chars()
+        .next()
+        .is_some_and(|first| !first.is_ascii_digit())
+        .then_some(name)
+}
+
+fn parse_number(chars: &mut Chars<'_>) -> Option<u64> {
+    let mut digits = String::new();
+    while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '_') {
+        if c != '_' {
+            digits.push(c);
+        }
+        chars.next();
+    }
+    digits.parse().ok()
+}
+
+fn parse_string(chars: &mut Chars<'_>) -> Option<String> {
+    expect(chars, '"')?;
+    let mut text = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(text),
+            '\\' => match chars.next()? {
+                '"' => text.push('"'),
+                '\\' => text.push('\\'),
+                'n' => text.push('\n'),
+                'r' => text.push('\r'),
+                't' => text.push('\t'),
+                '0' => text.push('\0'),
+                'u' => {
+                    expect(chars, '{')?;
+                    let mut hex = String::new();
+                    loop {
+                        match chars.next()? {
+                            '}' => break,
+                            c if c.is_ascii_hexdigit() && hex.len() < 6 => hex.push(c),
+                            _ => return None,
+                        }
+                    }
+                    text.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                _ => return None,
+            },
+            c => text.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn written(value: &Value) -> String {
+        let mut text = String::new();
+        write(value, &mut text);
+        text
+    }
+
+    #[test]
+    fn writes_rust_syntax_and_reads_it_back() {
+        let value = Value::Object(vec![
+            (
+                "name".to_string(),
+                Value::String("say \"hi\"\n\u{1}".to_string()),
+            ),
+            (
+                "list".to_string(),
+                Value::Array(vec![Value::Number(7), Value::Bool(false), Value::Null]),
+            ),
+            ("empty".to_string(), Value::Object(vec![])),
+        ]);
+
+        let text = written(&value);
+        assert_eq!(
+            text,
+            r#"(name: "say \"hi\"\n\u{1}", list: [7, false, None], empty: ())"#
+        );
+        assert_eq!(parse(&format!("{HEADER}{text}\n")), Some(value));
+    }
+
+    #[test]
+    fn accepts_what_a_hand_edited_file_may_contain() {
+        let text = "// orders\n#![enable(implicit_some)]\nOrder(\n  at: Some(1_000), /* soon */\n  tags: [\"a\",],\n)";
+
+        assert_eq!(
+            parse(text),
+            Some(Value::Object(vec![
+                ("at".to_string(), Value::Number(1000)),
+                (
+                    "tags".to_string(),
+                    Value::Array(vec![Value::String("a".to_string())])
+                ),
+            ]))
+        );
+    }
+
+    #[test]
+    fn refuses_anything_else() {
+        for text in [
+            "",
+            "(a: 1",
+            "(a: 1) x",
+            "(1: 2)",
+            "{\"a\":1}",
+            "(a: -1)",
+            "(a: \"open)",
+            "/* open (a: 1)",
+            "Unit",
+        ] {
+            assert_eq!(parse(text), None, "{text}");
+        }
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_some());
+        assert_eq!(parse(&nested(100_000)), None);
+    }
+}
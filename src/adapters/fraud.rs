@@ -0,0 +1,137 @@
+// --- Fraud screens ---
+// AlwaysAccept lets everything through, for shops without a screen worth
+// the name. RuleBasedScreen applies fixed rules on the spot: a denylisted
+// customer is refused; an order above the amount or item-count threshold
+// is reviewed. SpyFraudScreen answers what it is told to and remembers
+// what it was asked, for tests.
+use crate::domain::{Customer, Money, Order, OrderError, OrderId};
+use crate::ports::{Capability, FraudScreen, RiskVerdict};
+use std::sync::{Mutex, PoisonError};
+
+pub struct AlwaysAccept;
+
+impl FraudScreen for AlwaysAccept {
+    fn assess(&self, _order: &Order, _customer: &Customer) -> Result<RiskVerdict, OrderError> {
+        Ok(RiskVerdict::Accept)
+    }
+}
+
+impl Capability for AlwaysAccept {}
+
+// No rule until one is added. Thresholds are exclusive: an order of exactly
+// the maximum amount, or item count, is accepted.
+#[derive(Default)]
+pub struct RuleBasedScreen {
+    max_total: Option<Money>,
+    max_items: Option<usize>,
+    denied: Vec<String>,
+}
+
+impl RuleBasedScreen {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_total(mut self, max_total: Money) -> Self {
+        self.max_total = Some(max_total);
+        self
+    }
+
+    pub fn with_max_items(mut self, max_items: usize) -> Self {
+        self.max_items = Some(max_items);
+        self
+    }
+
+    // Compared without regard to case.
+    pub fn with_denied_name(mut self, name: impl Into<String>) -> Self {
+        self.denied.push(name.into().to_lowercase());
+        self
+    }
+}
+
+impl FraudScreen for RuleBasedScreen {
+    fn assess(&self, order: &Order, customer: &Customer) -> Result<RiskVerdict, OrderError> {
+        if self.denied.contains(&customer.name.to_lowercase()) {
+            return Ok(RiskVerdict::Reject {
+                reason: format!("{} is denylisted", customer.name),
+            });
+        }
+        let too_large = self.max_total.is_some_and(|max| order.total.0 > max.0);
+        let too_many = self.max_items.is_some_and(|max| order.items.len() > max);
+        Ok(if too_large || too_many {
+            RiskVerdict::Review
+        } else {
+            RiskVerdict::Accept
+        })
+    }
+}
+
+impl Capability for RuleBasedScreen {}
+
+pub struct SpyFraudScreen {
+    verdict: RiskVerdict,
+    assessed: Mutex<Vec<(OrderId, Customer)>>,
+}
+
+impl SpyFraudScreen {
+    pub fn new(verdict: RiskVerdict) -> Self {
+        Self {
+            verdict,
+            assessed: Mutex::default(),
+        }
+    }
+
+    // Every order assessed, with who placed it, oldest first.
+    pub fn assessed(&self) -> Vec<(OrderId, Customer)> {
+        self.assessed
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl FraudScreen for SpyFraudScreen {
+    fn assess(&self, order: &Order, customer: &Customer) -> Result<RiskVerdict, OrderError> {
+        self.assessed
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push((order.id, customer.clone()));
+        Ok(self.verdict.clone())
+    }
+}
+
+impl Capability for SpyFraudScreen {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::LineItem;
+
+    fn order(items: usize, price: u32) -> Order {
+        Order::new(
+            OrderId(1),
+            vec![LineItem::new("Gift card", Money(price)); items],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn rules_review_large_orders_and_refuse_denied_customers() {
+        let screen = RuleBasedScreen::new()
+            .with_max_total(Money(50_000))
+            .with_max_items(5)
+            .with_denied_name("Mallory");
+        let alice = Customer::new("Alice");
+        let verdict = |order: &Order, customer: &Customer| screen.assess(order, customer).unwrap();
+
+        assert_eq!(verdict(&order(5, 10_000), &alice), RiskVerdict::Accept);
+        assert_eq!(verdict(&order(1, 50_001), &alice), RiskVerdict::Review);
+        assert_eq!(verdict(&order(6, 100), &alice), RiskVerdict::Review);
+        assert_eq!(
+            verdict(&order(1, 100), &Customer::new("MALLORY")),
+            RiskVerdict::Reject {
+                reason: "MALLORY is denylisted".to_string()
+            }
+        );
+    }
+}
@@ -0,0 +1,478 @@
+// --- HTTP handler (driving adapter) ---
+// The part of an HTTP endpoint that is ours: request in, response out.
+// Listening on a socket and framing requests is a server library's job;
+// whichever one is used hands its requests to HttpAdapter::handle.
+//
+//     POST /orders
+//     {"items":[{"name":"Keyboard","price_cents":12999}]}
+//
+//     201 {"id":1,"status":"Paid","total_cents":12999}
+//
+// The body may also say when the order was placed, in seconds since the
+// Unix epoch: "placed_at":1700000000. Too far ahead of the service's clock,
+// the answer is 422 {"error":"ClockSkew","code":"REQ-019","delta_secs":<seconds ahead>}.
+//
+// The body comes straight from the network. Whatever is wrong with it,
+// wrong types, unknown fields, absurd nesting, the answer is 422 with
+// InvalidOrder: a 500 would mean *we* failed.
+//
+// PATCH amends an order:
+//
+//     PATCH /orders/1
+//     {"new_address":{"street":"1 Main St","city":"Springfield",
+//      "postal_code":"12345","country":"US"},"gift_note":"Happy birthday"}
+//
+//     200 {"id":1,"status":"Paid","total_cents":12999}
+//
+// Either field may be left out. What the customer got wrong comes back
+// field by field, for them to read:
+//
+//     422 {"error":"InvalidFields","code":"REQ-031","fields":[{"field":"gift_note","message":"too long (312/200)"}]}
+//
+// and an order already on its way is 409 {"error":"NotAmendable","code":"ORD-030","status":"Shipped"}.
+//
+// Every OrderError comes back with its code from the catalogue, the part of
+// the body a client should match on: {"error":"PaymentFailed","code":"PAY-002"}.
+// A card declined for a reason the provider gave has it next to the code,
+// 402 {"error":"PaymentDeclined","code":"PAY-039","reason":"insufficient_funds"}.
+// Only a path or a method this adapter does not serve has no code, being no
+// OrderError: 404 {"error":"NotFound"}, 405 {"error":"MethodNotAllowed"}.
+//
+// The adapter drives an OrderFacade and nothing else, through &self: a
+// server may hand it requests from as many threads as it likes, the facade
+// runs the commands one at a time.
+//
+// With the service's Tracer, a request's traceparent header, when there is
+// one, makes the use case it runs part of the caller's trace.
+use super::inbound;
+use super::json::{self, Value};
+use crate::domain::{Address, FieldErrors, LineItem, Order, OrderError, OrderId, Timestamp};
+use crate::ports::{AmendOrder, OrderFacade, PlaceOrder, TRACEPARENT_HEADER, TraceContext, Tracer};
+
+pub const MAX_BODY_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpRequest {
+    pub method: String,
+    pub path: String,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    // Header names are case-insensitive in HTTP.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+pub struct HttpAdapter<F: OrderFacade> {
+    facade: F,
+    tracer: Option<Tracer>,
+}
+
+impl<F: OrderFacade> HttpAdapter<F> {
+    pub fn new(facade: F) -> Self {
+        Self {
+            facade,
+            tracer: None,
+        }
+    }
+
+    // The service's Tracer: a request with a traceparent header joins the
+    // caller's trace.
+    pub fn with_tracer(mut self, tracer: Tracer) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+
+    pub fn into_inner(self) -> F {
+        self.facade
+    }
+
+    // A header that does not parse is left out, and the trace starts here.
+    fn join_caller_trace(&self, request: &HttpRequest) {
+        let caller = request
+            .header(TRACEPARENT_HEADER)
+            .and_then(TraceContext::parse_traceparent);
+        if let (Some(tracer), Some(caller)) = (&self.tracer, caller) {
+            tracer.continue_from(caller);
+        }
+    }
+
+    // POST /orders places, PATCH /orders/<id> amends.
+    pub fn handle(&self, request: &HttpRequest) -> HttpResponse {
+        if let Some(id) = request.path.strip_prefix("/orders/") {
+            return self.amend(id, request);
+        }
+        if request.path != "/orders" {
+            return route_error(404, "NotFound");
+        }
+        if request.method != "POST" {
+            return route_error(405, "MethodNotAllowed");
+        }
+        self.join_caller_trace(request);
+        match parse_body(&request.body).and_then(|command| self.facade.place_order(command)) {
+            Ok(order) => HttpResponse {
+                status: 201,
+                body: order_json(&order),
+            },
+            Err(OrderError::ClockSkew { delta_secs }) => HttpResponse {
+                status: 422,
+                body: format!(
+                    r#"{{"error":"ClockSkew","code":"REQ-019","delta_secs":{delta_secs}}}"#
+                ),
+            },
+            Err(OrderError::PaymentDeclined { reason }) => HttpResponse {
+                status: 402,
+                body: format!(
+                    r#"{{"error":"PaymentDeclined","code":"PAY-039","reason":"{}"}}"#,
+                    reason.code()
+                ),
+            },
+            Err(error) => error_response(status_for(&error), &error),
+        }
+    }
+
+    fn amend(&self, id: &str, request: &HttpRequest) -> HttpResponse {
+        let Some(order_id) = parse_order_id(id) else {
+            return route_error(404, "NotFound");
+        };
+        if request.method != "PATCH" {
+            return route_error(405, "MethodNotAllowed");
+        }
+        self.join_caller_trace(request);
+        match parse_amendment(order_id, &request.body)
+            .and_then(|command| self.facade.amend_order(command))
+        {
+            Ok(order) => HttpResponse {
+                status: 200,
+                body: order_json(&order),
+            },
+            Err(OrderError::InvalidFields { errors }) => HttpResponse {
+                status: 422,
+                body: field_errors_json(&errors),
+            },
+            Err(OrderError::NotAmendable { status }) => HttpResponse {
+                status: 409,
+                body: format!(r#"{{"error":"NotAmendable","code":"ORD-030","status":"{status}"}}"#),
+            },
+            Err(error) => error_response(status_for(&error), &error),
+        }
+    }
+}
+
+// Problems in the request are the client's (4xx); the rest are ours.
+fn status_for(error: &OrderError) -> u16 {
+    match error {
+        OrderError::InvalidOrder
+        | OrderError::NoExchangeRate { .. }
+        | OrderError::ClockSkew { .. }
+        | OrderError::InvalidFields { .. } => 422,
+        OrderError::NotAmendable { .. } => 409,
+        OrderError::PaymentFailed
+        | OrderError::PaymentDeclined { .. }
+        | OrderError::DailyCapExceeded { .. } => 402,
+        OrderError::PaymentUnavailable => 503,
+        OrderError::NotFound { .. } => 404,
+        _ => 500,
+    }
+}
+
+// "NotFound { id: .. }" -> "NotFound"
+fn variant_name(error: &OrderError) -> String {
+    let debug = format!("{error:?}");
+    debug
+        .split([' ', '{', '('])
+        .next()
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn error_response(status: u16, error: &OrderError) -> HttpResponse {
+    HttpResponse {
+        status,
+        body: format!(
+            r#"{{"error":"{}","code":"{}"}}"#,
+            json::escape(&variant_name(error)),
+            error.code()
+        ),
+    }
+}
+
+// A request this adapter has no handler for.
+fn route_error(status: u16, error: &str) -> HttpResponse {
+    HttpResponse {
+        status,
+        body: format!(r#"{{"error":"{}"}}"#, json::escape(error)),
+    }
+}
+
+fn field_errors_json(errors: &FieldErrors) -> String {
+    let fields: Vec<String> = errors
+        .iter()
+        .map(|error| {
+            format!(
+                r#"{{"field":"{}","message":"{}"}}"#,
+                json::escape(&error.field),
+                json::escape(&error.message)
+            )
+        })
+        .collect();
+    format!(
+        r#"{{"error":"InvalidFields","code":"REQ-031","fields":[{}]}}"#,
+        fields.join(",")
+    )
+}
+
+// With a "uuid" after the id, as a 36-character string, when the order has
+// one.
+fn order_json(order: &Order) -> String {
+    let uuid = order
+        .uuid
+        .map(|uuid| format!(r#","uuid":"{uuid}""#))
+        .unwrap_or_default();
+    format!(
+        r#"{{"id":{}{uuid},"status":"{}","total_cents":{}}}"#,
+        order.id.0, order.status, order.total.0
+    )
+}
+
+// {"items":[{"name":<string>,"price_cents":<u32>}, ...]}, and optionally
+// "placed_at":<seconds>, nothing more.
+pub fn parse_body(body: &[u8]) -> Result<PlaceOrder, OrderError> {
+    if body.len() > MAX_BODY_BYTES {
+        return Err(OrderError::InvalidOrder);
+    }
+    let text = std::str::from_utf8(body).map_err(|_| OrderError::InvalidOrder)?;
+    let Some(Value::Object(fields)) = json::parse(text) else {
+        return Err(OrderError::InvalidOrder);
+    };
+    let mut items = None;
+    let mut placed_at = None;
+    for (key, value) in &fields {
+        match (key.as_str(), value) {
+            ("items", Value::Array(values)) if items.is_none() => items = Some(values),
+            ("placed_at", Value::Number(secs)) if placed_at.is_none() => {
+                placed_at = Some(Timestamp(*secs));
+            }
+            _ => return Err(OrderError::InvalidOrder),
+        }
+    }
+    let items = items.ok_or(OrderError::InvalidOrder)?;
+    inbound::check_item_count(items.len())?;
+    let items = items.iter().map(item_from_json).collect::<Result<_, _>>()?;
+    Ok(PlaceOrder { items, placed_at })
+}
+
+// Digits only, no leading '+', and a u32.
+fn parse_order_id(id: &str) -> Option<OrderId> {
+    if id.is_empty() || !id.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    id.parse().ok().map(OrderId)
+}
+
+// {"new_address":{"street":..,"city":..,"postal_code":..,"country":..},
+// "gift_note":<string>}, either or both. A body that is not a JSON object
+// is InvalidOrder; a field of the wrong shape is one more FieldError, so
+// the customer hears about it with the rest.
+pub fn parse_amendment(order_id: OrderId, body: &[u8]) -> Result<AmendOrder, OrderError> {
+    if body.len() > MAX_BODY_BYTES {
+        return Err(OrderError::InvalidOrder);
+    }
+    let text = std::str::from_utf8(body).map_err(|_| OrderError::InvalidOrder)?;
+    let Some(Value::Object(fields)) = json::parse(text) else {
+        return Err(OrderError::InvalidOrder);
+    };
+    let mut errors = FieldErrors::new();
+    let mut new_address = None;
+    let mut gift_note = None;
+    for (key, value) in &fields {
+        match (key.as_str(), value) {
+            ("new_address", Value::Object(parts)) if new_address.is_none() => {
+                new_address = Some(address_from_json(parts, &mut errors));
+            }
+            ("new_address", _) if new_address.is_none() => {
+                errors.push("new_address", "must be an object");
+            }
+            ("gift_note", Value::String(note)) if gift_note.is_none() => {
+                gift_note = Some(note.clone());
+            }
+            ("gift_note", _) if gift_note.is_none() => errors.push("gift_note", "must be a string"),
+            ("new_address" | "gift_note", _) => errors.push(key.as_str(), "given twice"),
+            _ => errors.push(key.as_str(), "unknown field"),
+        }
+    }
+    errors.into_result()?;
+    Ok(AmendOrder {
+        order_id,
+        new_address,
+        gift_note,
+    })
+}
+
+// A part left out is an empty one: the domain reports it as required.
+fn address_from_json(parts: &[(String, Value)], errors: &mut FieldErrors) -> Address {
+    let mut address = Address {
+        street: String::new(),
+        city: String::new(),
+        postal_code: String::new(),
+        country: String::new(),
+    };
+    for (key, value) in parts {
+        let field = format!("new_address.{key}");
+        let part = match key.as_str() {
+            "street" => &mut address.street,
+            "city" => &mut address.city,
+            "postal_code" => &mut address.postal_code,
+            "country" => &mut address.country,
+            _ => {
+                errors.push(field, "unknown field");
+                continue;
+            }
+        };
+        match value {
+            Value::String(text) => *part = text.clone(),
+            _ => errors.push(field, "must be a string"),
+        }
+    }
+    address
+}
+
+fn item_from_json(value: &Value) -> Result<LineItem, OrderError> {
+    let Value::Object(fields) = value else {
+        return Err(OrderError::InvalidOrder);
+    };
+    let mut name = None;
+    let mut cents = None;
+    for (key, value) in fields {
+        match (key.as_str(), value) {
+            ("name", Value::String(text)) if name.is_none() => name = Some(text),
+            ("price_cents", Value::Number(n)) if cents.is_none() => cents = Some(*n),
+            _ => return Err(OrderError::InvalidOrder),
+        }
+    }
+    match (name, cents) {
+        (Some(name), Some(cents)) => inbound::line_item(name, &cents.to_string()),
+        _ => Err(OrderError::InvalidOrder),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Money;
+
+    #[test]
+    fn parses_the_documented_body() {
+        let command =
+            parse_body(br#"{"items":[{"name":"Keyboard","price_cents":12999}]}"#).unwrap();
+        assert_eq!(
+            command.items,
+            vec![LineItem {
+                name: "Keyboard".to_string(),
+                price: Money(12999),
+            }]
+        );
+    }
+
+    #[test]
+    fn order_body_carries_the_uuid_when_there_is_one() {
+        let mut order = Order::new(
+            crate::domain::OrderId(1),
+            vec![LineItem {
+                name: "Keyboard".to_string(),
+                price: Money(12999),
+            }],
+        )
+        .unwrap();
+        assert_eq!(
+            order_json(&order),
+            r#"{"id":1,"status":"Placed","total_cents":12999}"#
+        );
+
+        order.uuid = Some(crate::domain::Uuid128::v4(0, 0));
+        assert_eq!(
+            order_json(&order),
+            r#"{"id":1,"uuid":"00000000-0000-4000-8000-000000000000","status":"Placed","total_cents":12999}"#
+        );
+    }
+
+    #[test]
+    fn parses_an_amendment_and_names_every_malformed_field() {
+        let command = parse_amendment(
+            crate::domain::OrderId(7),
+            br#"{"gift_note":"Happy birthday","new_address":{"street":"1 Main St","city":"Springfield","postal_code":"12345","country":"US"}}"#,
+        )
+        .unwrap();
+        assert_eq!(command.gift_note.as_deref(), Some("Happy birthday"));
+        assert_eq!(command.new_address.unwrap().city, "Springfield");
+
+        let Err(OrderError::InvalidFields { errors }) = parse_amendment(
+            crate::domain::OrderId(7),
+            br#"{"gift_note":5,"new_address":{"city":1,"floor":"2"},"colour":"red"}"#,
+        ) else {
+            panic!("a malformed amendment was accepted");
+        };
+        assert_eq!(
+            errors.to_string(),
+            "gift_note: must be a string; new_address.city: must be a string; \
+             new_address.floor: unknown field; colour: unknown field"
+        );
+        assert!(matches!(
+            parse_amendment(crate::domain::OrderId(7), b"[]"),
+            Err(OrderError::InvalidOrder)
+        ));
+    }
+
+    #[test]
+    fn order_ids_in_paths_are_plain_digits() {
+        assert_eq!(parse_order_id("12"), Some(crate::domain::OrderId(12)));
+        for bad in ["", "+1", "-1", "1x", "4294967296"] {
+            assert_eq!(parse_order_id(bad), None, "{bad:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn field_errors_are_listed_in_order() {
+        let mut errors = FieldErrors::new();
+        errors.push("gift_note", "too long (312/200)");
+        errors.push("new_address.country", "say \"US\"");
+        assert_eq!(
+            field_errors_json(&errors),
+            r#"{"error":"InvalidFields","code":"REQ-031","fields":[{"field":"gift_note","message":"too long (312/200)"},{"field":"new_address.country","message":"say \"US\""}]}"#
+        );
+    }
+
+    #[test]
+    fn error_bodies_name_the_variant_only() {
+        assert_eq!(variant_name(&OrderError::InvalidOrder), "InvalidOrder");
+        assert_eq!(
+            variant_name(&OrderError::NotFound {
+                id: crate::domain::OrderId(1)
+            }),
+            "NotFound"
+        );
+    }
+
+    #[test]
+    fn error_bodies_carry_the_code_but_no_details() {
+        let response = error_response(
+            503,
+            &OrderError::CircuitOpen {
+                retry_after_secs: 30,
+            },
+        );
+        assert_eq!(response.body, r#"{"error":"CircuitOpen","code":"SYS-026"}"#);
+    }
+}
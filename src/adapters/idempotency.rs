@@ -0,0 +1,191 @@
+// --- File idempotency store ---
+// Idempotency keys survive a restart: a client retrying just after a
+// deployment still gets its first order back. One key per line:
+//
+//     {"key":"7f3e-retry-1","order_id":4,"at":1700000000}
+//
+// The whole file is rewritten on every change, to a temporary file first,
+// then renamed over the previous one: two threads remembering keys at once
+// take turns, and a crash leaves the old file, never half of the new.
+//
+// A file that cannot be understood is not a reason to refuse to start: at
+// worst a retried request is served twice, which is what happens without
+// the store anyway. It is moved aside to <path>.corrupt for a human to look
+// at, the store starts empty, and the problem goes to the ErrorReporter.
+use super::ConfigError;
+use super::atomic_file::{self, aside};
+use super::json::{self, Value};
+use crate::domain::{OrderError, OrderId, Timestamp};
+use crate::ports::{Capability, Clock, ErrorContext, ErrorReporter, IdempotencyStore, Port};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+type Keys = BTreeMap<String, (OrderId, Timestamp)>;
+
+pub struct FileIdempotencyStore {
+    path: PathBuf,
+    keys: Mutex<Keys>,
+}
+
+impl FileIdempotencyStore {
+    // A missing file is an empty store. A corrupt one is reported, with
+    // `clock`'s time, and set aside; only a file that cannot be read at all
+    // is an error.
+    pub fn open(
+        path: impl Into<PathBuf>,
+        reporter: &dyn ErrorReporter,
+        clock: &dyn Clock,
+    ) -> Result<Self, ConfigError> {
+        let path = path.into();
+        let keys = match fs::read_to_string(&path) {
+            Ok(text) => keys_from_lines(&text).unwrap_or_else(|| {
+                let _ = fs::rename(&path, aside(&path, ".corrupt"));
+                reporter.report(ErrorContext {
+                    use_case: "open_idempotency_store",
+                    port: Some(Port::Idempotency),
+                    operation: "load",
+                    order_id: None,
+                    at: clock.now(),
+                    error: OrderError::StorageFailed,
+                });
+                Keys::new()
+            }),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Keys::new(),
+            Err(error) => {
+                return Err(ConfigError::IoError {
+                    path,
+                    kind: error.kind(),
+                });
+            }
+        };
+        Ok(Self {
+            path,
+            keys: Mutex::new(keys),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    // The new keys are kept only once they are on disk.
+    fn replace(&self, keys: &mut Keys, updated: Keys) -> Result<(), OrderError> {
+        atomic_file::replace(&self.path, keys_lines(&updated).as_bytes())
+            .map_err(|_| OrderError::StorageFailed)?;
+        *keys = updated;
+        Ok(())
+    }
+}
+
+impl IdempotencyStore for FileIdempotencyStore {
+    fn find(&self, key: &str) -> Result<Option<OrderId>, OrderError> {
+        let keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(keys.get(key).map(|&(id, _)| id))
+    }
+
+    fn remember(&self, key: &str, id: OrderId, at: Timestamp) -> Result<(), OrderError> {
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut updated = keys.clone();
+        updated.insert(key.to_string(), (id, at));
+        self.replace(&mut keys, updated)
+    }
+
+    fn purge_older_than(&self, max_age: Duration, now: Timestamp) -> Result<usize, OrderError> {
+        let oldest_kept = now.minus_secs(max_age.as_secs());
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut updated = keys.clone();
+        updated.retain(|_, &mut (_, at)| at >= oldest_kept);
+        let purged = keys.len() - updated.len();
+        if purged > 0 {
+            self.replace(&mut keys, updated)?;
+        }
+        Ok(purged)
+    }
+
+    fn forget_order(&self, id: OrderId) -> Result<usize, OrderError> {
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut updated = keys.clone();
+        updated.retain(|_, &mut (order, _)| order != id);
+        let forgotten = keys.len() - updated.len();
+        if forgotten > 0 {
+            self.replace(&mut keys, updated)?;
+        }
+        Ok(forgotten)
+    }
+}
+
+impl Capability for FileIdempotencyStore {}
+
+fn keys_lines(keys: &Keys) -> String {
+    keys.iter()
+        .map(|(key, (id, at))| {
+            format!(
+                "{{\"key\":\"{}\",\"order_id\":{},\"at\":{}}}\n",
+                json::escape(key),
+                id.0,
+                at.0
+            )
+        })
+        .collect()
+}
+
+// None as soon as one line is not a key.
+fn keys_from_lines(text: &str) -> Option<Keys> {
+    text.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let fields = json::parse_flat_object(line)?;
+            let field = |name: &str| {
+                fields
+                    .iter()
+                    .find(|(key, _)| key == name)
+                    .map(|(_, value)| value)
+            };
+            let (Value::String(key), Value::Number(id), Value::Number(at)) =
+                (field("key")?, field("order_id")?, field("at")?)
+            else {
+                return None;
+            };
+            Some((
+                key.clone(),
+                (OrderId(u32::try_from(*id).ok()?), Timestamp(*at)),
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keys_survive_the_round_trip() {
+        let keys = Keys::from([
+            (
+                "retry \"1\"".to_string(),
+                (OrderId(4), Timestamp(1_700_000_000)),
+            ),
+            ("b".to_string(), (OrderId(5), Timestamp(0))),
+        ]);
+        assert_eq!(keys_from_lines(&keys_lines(&keys)), Some(keys));
+        assert_eq!(keys_from_lines(""), Some(Keys::new()));
+    }
+
+    #[test]
+    fn one_bad_line_spoils_the_file() {
+        let good = r#"{"key":"a","order_id":4,"at":10}"#;
+        assert!(keys_from_lines(good).is_some());
+        for bad in [
+            r#"{"key":"a","order_id":4}"#,
+            r#"{"key":"a","order_id":4294967296,"at":10}"#,
+            r#"{"key":4,"order_id":4,"at":10}"#,
+            r#"{"key":"a","order_id":4,"#,
+        ] {
+            assert_eq!(keys_from_lines(&format!("{good}\n{bad}\n")), None, "{bad}");
+        }
+    }
+}
@@ -0,0 +1,365 @@
+// --- Order import from another system (driving adapter) ---
+// Moving off a legacy store: its export is a JSON array of orders, under
+// field names of its own. A FieldMapping says which of them is what:
+//
+//     [{"orderNumber":1001,"amount_cents":2400,"state":"shipped",
+//       "lines":[{"title":"Teapot","price":2400}]}]
+//
+//     let mapping = FieldMapping {
+//         id: "orderNumber",
+//         items: "lines",
+//         item_name: "title",
+//         item_price: "price",
+//         total: Some("amount_cents"),
+//         status: Some("state"),
+//         ..FieldMapping::default()
+//     };
+//     let report = import_external(&repo, file, &mapping, ImportMode::DryRun { overwrite: false })?;
+//
+// Every record goes through the domain as an order placed here would:
+// Order::new for the items, with the same limits as the other driving
+// adapters, then the fewest moves ORDER_TRANSITIONS allows to its status:
+// Placed -> Paid -> Shipped for a shipped one. A
+// record the domain refuses, or whose stated total is not what its items
+// come to, is rejected and reported with its place in the array; the rest
+// still go in. Without a status field, an order is taken to have been paid
+// in the old system.
+//
+// An id already in the repository is left alone and reported as a
+// duplicate, unless the mode says to overwrite it. An id that comes twice
+// in the file is a duplicate the second time, whatever the mode: which of
+// the two is right is for a person to say.
+//
+// DryRun reads the repository as Commit does, to find the duplicates, and
+// writes nothing: its report is the one Commit would give. Imported ids are
+// kept as they are; OrderService::restore on the repository afterwards
+// carries on past the highest one.
+use super::inbound;
+use super::json::{self, Value};
+use crate::domain::{
+    LineItem, Money, ORDER_TRANSITIONS, Order, OrderError, OrderId, OrderStatus, Timestamp,
+};
+use crate::ports::OrderWriter;
+use std::collections::BTreeSet;
+use std::io::{self, Read};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FieldMapping<'a> {
+    pub id: &'a str,
+    pub items: &'a str,
+    // Inside each of the items.
+    pub item_name: &'a str,
+    pub item_price: &'a str,
+    // Checked against what the items add up to.
+    pub total: Option<&'a str>,
+    // A status name, in any case: "paid", "Shipped", "CANCELLED".
+    pub status: Option<&'a str>,
+    // Seconds since the Unix epoch.
+    pub placed_at: Option<&'a str>,
+}
+
+// The names this crate's own HTTP adapter uses.
+impl Default for FieldMapping<'static> {
+    fn default() -> Self {
+        Self {
+            id: "id",
+            items: "items",
+            item_name: "name",
+            item_price: "price_cents",
+            total: None,
+            status: None,
+            placed_at: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportMode {
+    // Only reads: what Commit would do, with nothing written.
+    DryRun { overwrite: bool },
+    Commit { overwrite: bool },
+}
+
+impl ImportMode {
+    fn overwrite(self) -> bool {
+        match self {
+            ImportMode::DryRun { overwrite } | ImportMode::Commit { overwrite } => overwrite,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Rejection {
+    NotAnObject,
+    MissingField { field: String },
+    // There, but not what the mapping says it holds.
+    WrongType { field: String },
+    UnknownStatus { status: String },
+    TotalMismatch { stated: Money, computed: Money },
+    // The domain refused the order the record describes.
+    Invalid { error: OrderError },
+}
+
+#[derive(Debug, Clone)]
+pub struct Rejected {
+    // Its place in the array, from 0.
+    pub record: usize,
+    pub reason: Rejection,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    pub imported: Vec<OrderId>,
+    // Were in the repository, and replaced.
+    pub overwritten: Vec<OrderId>,
+    // Were in the repository, or earlier in the file: left as they were.
+    pub duplicates: Vec<OrderId>,
+    pub rejected: Vec<Rejected>,
+    // Could not be looked up or written.
+    pub failed: Vec<(OrderId, OrderError)>,
+}
+
+// Only input that is no JSON array at all, or cannot be read, fails the
+// whole import; per-record problems end up in the report.
+pub fn import_external<W, R>(
+    repository: &W,
+    mut reader: R,
+    mapping: &FieldMapping<'_>,
+    mode: ImportMode,
+) -> Result<ImportReport, OrderError>
+where
+    W: OrderWriter + ?Sized,
+    R: Read,
+{
+    let mut text = String::new();
+    reader
+        .read_to_string(&mut text)
+        .map_err(|e| match e.kind() {
+            io::ErrorKind::InvalidData => OrderError::InvalidOrder,
+            _ => OrderError::StorageFailed,
+        })?;
+    let Some(Value::Array(records)) = json::parse(&text) else {
+        return Err(OrderError::InvalidOrder);
+    };
+
+    let mut report = ImportReport::default();
+    let mut seen = BTreeSet::new();
+    for (record, value) in records.iter().enumerate() {
+        let order = match order_from(value, mapping) {
+            Ok(order) => order,
+            Err(reason) => {
+                report.rejected.push(Rejected { record, reason });
+                continue;
+            }
+        };
+        let id = order.id;
+        if !seen.insert(id) {
+            report.duplicates.push(id);
+            continue;
+        }
+        let exists = match repository.exists(id) {
+            Ok(exists) => exists,
+            Err(e) => {
+                report.failed.push((id, e));
+                continue;
+            }
+        };
+        if exists && !mode.overwrite() {
+            report.duplicates.push(id);
+            continue;
+        }
+        if let ImportMode::Commit { .. } = mode
+            && let Err(e) = repository.save(&order)
+        {
+            report.failed.push((id, e));
+            continue;
+        }
+        if exists {
+            report.overwritten.push(id);
+        } else {
+            report.imported.push(id);
+        }
+    }
+    Ok(report)
+}
+
+fn order_from(value: &Value, mapping: &FieldMapping<'_>) -> Result<Order, Rejection> {
+    let Value::Object(fields) = value else {
+        return Err(Rejection::NotAnObject);
+    };
+    let id = match field(fields, mapping.id)? {
+        Value::Number(n) => u32::try_from(*n).ok(),
+        Value::String(digits) if digits.bytes().all(|b| b.is_ascii_digit()) => digits.parse().ok(),
+        _ => None,
+    }
+    .ok_or_else(|| wrong_type(mapping.id))?;
+    let items = items_from(fields, mapping)?;
+
+    let mut order = Order::new(OrderId(id), items).map_err(|error| Rejection::Invalid { error })?;
+    if let Some(name) = mapping.total {
+        let stated = number(fields, name)
+            .and_then(|n| u32::try_from(n).map(Money).map_err(|_| wrong_type(name)))?;
+        if stated != order.total {
+            return Err(Rejection::TotalMismatch {
+                stated,
+                computed: order.total,
+            });
+        }
+    }
+    let status = match mapping.status {
+        Some(name) => match field(fields, name)? {
+            Value::String(status) => {
+                parse_status(status).ok_or_else(|| Rejection::UnknownStatus {
+                    status: status.clone(),
+                })?
+            }
+            _ => return Err(wrong_type(name)),
+        },
+        None => OrderStatus::Paid,
+    };
+    reach(&mut order, status).map_err(|error| Rejection::Invalid { error })?;
+    if let Some(name) = mapping.placed_at {
+        order.placed_at = Some(Timestamp(number(fields, name)?));
+    }
+    Ok(order)
+}
+
+fn items_from(
+    fields: &[(String, Value)],
+    mapping: &FieldMapping<'_>,
+) -> Result<Vec<LineItem>, Rejection> {
+    let Value::Array(items) = field(fields, mapping.items)? else {
+        return Err(wrong_type(mapping.items));
+    };
+    inbound::check_item_count(items.len()).map_err(|error| Rejection::Invalid { error })?;
+    items
+        .iter()
+        .enumerate()
+        .map(|(n, item)| {
+            let at = |name: &str| format!("{}[{n}].{name}", mapping.items);
+            let Value::Object(item) = item else {
+                return Err(Rejection::WrongType {
+                    field: format!("{}[{n}]", mapping.items),
+                });
+            };
+            let name = match item.iter().find(|(key, _)| key == mapping.item_name) {
+                Some((_, Value::String(name))) => name,
+                Some((_, Value::Null)) | None => {
+                    return Err(Rejection::MissingField {
+                        field: at(mapping.item_name),
+                    });
+                }
+                Some(_) => {
+                    return Err(Rejection::WrongType {
+                        field: at(mapping.item_name),
+                    });
+                }
+            };
+            let price = match item.iter().find(|(key, _)| key == mapping.item_price) {
+                Some((_, Value::Number(price))) => price,
+                Some((_, Value::Null)) | None => {
+                    return Err(Rejection::MissingField {
+                        field: at(mapping.item_price),
+                    });
+                }
+                Some(_) => {
+                    return Err(Rejection::WrongType {
+                        field: at(mapping.item_price),
+                    });
+                }
+            };
+            inbound::line_item(name, &price.to_string())
+                .map_err(|error| Rejection::Invalid { error })
+        })
+        .collect()
+}
+
+// A null is as good as missing.
+fn field<'v>(fields: &'v [(String, Value)], name: &str) -> Result<&'v Value, Rejection> {
+    match fields.iter().find(|(key, _)| key == name) {
+        Some((_, Value::Null)) | None => Err(Rejection::MissingField {
+            field: name.to_string(),
+        }),
+        Some((_, value)) => Ok(value),
+    }
+}
+
+fn number(fields: &[(String, Value)], name: &str) -> Result<u64, Rejection> {
+    match field(fields, name)? {
+        Value::Number(n) => Ok(*n),
+        _ => Err(wrong_type(name)),
+    }
+}
+
+fn wrong_type(name: &str) -> Rejection {
+    Rejection::WrongType {
+        field: name.to_string(),
+    }
+}
+
+fn parse_status(name: &str) -> Option<OrderStatus> {
+    OrderStatus::ALL
+        .into_iter()
+        .find(|status| status.to_string().eq_ignore_ascii_case(name.trim()))
+}
+
+// The way an order placed here would have come to `status`.
+fn reach(order: &mut Order, status: OrderStatus) -> Result<(), OrderError> {
+    let path =
+        ORDER_TRANSITIONS
+            .path(order.status, status)
+            .ok_or(OrderError::InvalidTransition {
+                from: order.status,
+                to: status,
+            })?;
+    path.into_iter().try_for_each(|to| order.transition_to(to))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(text: &str) -> Value {
+        json::parse(text).unwrap()
+    }
+
+    #[test]
+    fn a_status_is_reached_the_way_the_domain_allows() {
+        let mapping = FieldMapping {
+            status: Some("state"),
+            ..FieldMapping::default()
+        };
+        let with_state = |state: &str| {
+            record(&format!(
+                r#"{{"id":7,"state":"{state}","items":[{{"name":"Pen","price_cents":150}}]}}"#
+            ))
+        };
+
+        for (state, status) in [
+            ("placed", OrderStatus::Placed),
+            ("PAID", OrderStatus::Paid),
+            ("Shipped", OrderStatus::Shipped),
+            ("cancelled", OrderStatus::Cancelled),
+        ] {
+            let order = order_from(&with_state(state), &mapping).unwrap();
+            assert_eq!(order.status, status, "{state}");
+        }
+        assert!(matches!(
+            order_from(&with_state("lost"), &mapping),
+            Err(Rejection::UnknownStatus { status }) if status == "lost"
+        ));
+        // Without a status field, it was paid for.
+        let order = order_from(&with_state("x"), &FieldMapping::default()).unwrap();
+        assert_eq!(order.status, OrderStatus::Paid);
+    }
+
+    #[test]
+    fn an_item_field_that_is_missing_is_named_with_its_place() {
+        let value = record(r#"{"id":7,"items":[{"name":"Pen","price_cents":150},{"name":"Ink"}]}"#);
+
+        assert!(matches!(
+            order_from(&value, &FieldMapping::default()),
+            Err(Rejection::MissingField { field }) if field == "items[1].price_cents"
+        ));
+    }
+}
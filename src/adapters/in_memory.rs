@@ -0,0 +1,798 @@
+// --- In-memory adapters (testing / development) ---
+// Each one narrates what it does on a Console: stdout unless with_console
+// says otherwise.
+use super::{Console, DeclineScript};
+use crate::domain::{
+    Address, BlobId, Currency, LineItem, Money, Notice, Order, OrderConfirmation, OrderError,
+    OrderId, OrderKey, OrderReference, SagaId, StoredOrder, Timestamp, TrackingId, Uuid128,
+};
+use crate::ports::{
+    BlobStore, CancelToken, Capability, ChargeLog, ChargeRecord, Clock, DraftRepository,
+    IdempotencyStore, Inventory, OrderReader, OrderWriter, PaymentGateway, ReservationId,
+    SagaEntry, SagaLog, Sender, ShippingProvider,
+};
+use std::cell::RefCell;
+use std::collections::btree_map::Entry;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+// A simple BTreeMap-based repository.
+// Perfect for unit tests: no database needed!
+// Keyed by id, so listing is in id order without sorting, whatever order
+// the orders were saved in.
+// Deleted orders move to a side map, out of sight of every lookup.
+// UUIDs and references are indexed, so find_by_key and find_by_reference
+// do not scan.
+// All behind one Mutex: writes come through &self, from any thread.
+#[derive(Default)]
+pub struct InMemoryOrderRepository {
+    store: Mutex<OrderStore>,
+    console: Console,
+}
+
+#[derive(Default)]
+struct OrderStore {
+    orders: BTreeMap<OrderId, Order>,
+    deleted: BTreeMap<OrderId, Order>,
+    uuids: HashMap<Uuid128, OrderId>,
+    references: HashMap<OrderReference, OrderId>,
+}
+
+impl OrderStore {
+    fn save(&mut self, order: &Order) {
+        if let Some(uuid) = order.uuid {
+            self.uuids.insert(uuid, order.id);
+        }
+        if let Some(reference) = &order.reference {
+            self.references.insert(reference.clone(), order.id);
+        }
+        self.orders.insert(order.id, order.clone());
+    }
+}
+
+impl InMemoryOrderRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    fn store(&self) -> MutexGuard<'_, OrderStore> {
+        self.store.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// It implements the repository port, both halves.
+// The application doesn't know (or care) that this is a BTreeMap.
+impl OrderReader for InMemoryOrderRepository {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.console
+            .line(format_args!("  [InMemory] Finding order {id:?}"));
+        Ok(self.store().orders.get(&id).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        let store = self.store();
+        self.console.line(format_args!(
+            "  [InMemory] Listing {} order(s)",
+            store.orders.len()
+        ));
+        Ok(store.orders.values().cloned().collect())
+    }
+
+    // No clone of the items: only the key, or a Copy field, is read.
+    fn exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        self.console
+            .line(format_args!("  [InMemory] Checking order {id:?}"));
+        Ok(self.store().orders.contains_key(&id))
+    }
+
+    fn total_of(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        self.console
+            .line(format_args!("  [InMemory] Reading total of order {id:?}"));
+        Ok(self.store().orders.get(&id).map(|order| order.total))
+    }
+
+    // The lock is held while `visit` runs: it must not call back into
+    // this repository.
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        let store = self.store();
+        self.console.line(format_args!(
+            "  [InMemory] Scanning {} order(s)",
+            store.orders.len()
+        ));
+        store.orders.values().for_each(visit);
+        Ok(())
+    }
+
+    fn for_each_cancellable(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        let store = self.store();
+        self.console.line(format_args!(
+            "  [InMemory] Scanning {} order(s)",
+            store.orders.len()
+        ));
+        for (processed, order) in store.orders.values().enumerate() {
+            cancel.check(processed)?;
+            visit(order);
+        }
+        Ok(())
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        Ok(self.store().deleted.values().cloned().collect())
+    }
+
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.console
+            .line(format_args!("  [InMemory] Finding order {key}"));
+        let store = self.store();
+        let found = match key {
+            OrderKey::Sequential(id) => store.orders.get(&id),
+            // Deleted orders are out of `orders`, and so out of reach.
+            OrderKey::Random(uuid) => store
+                .uuids
+                .get(&uuid)
+                .and_then(|id| store.orders.get(id))
+                .filter(|order| order.uuid == Some(uuid)),
+        };
+        Ok(found.cloned())
+    }
+
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        self.console
+            .line(format_args!("  [InMemory] Finding order {reference}"));
+        let store = self.store();
+        Ok(store
+            .references
+            .get(reference)
+            .and_then(|id| store.orders.get(id))
+            .filter(|order| order.reference.as_ref() == Some(reference))
+            .cloned())
+    }
+}
+
+impl OrderWriter for InMemoryOrderRepository {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [InMemory] Saving order {:?}", order.id));
+        self.store().save(order);
+        Ok(())
+    }
+
+    // Checked and written under one lock, so two services sharing the
+    // repository cannot both win.
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        let id = order.id;
+        self.console
+            .line(format_args!("  [InMemory] Updating order {id:?}"));
+        let mut store = self.store();
+        let stored = store.orders.get(&id).ok_or(OrderError::NotFound { id })?;
+        if stored.version != order.version {
+            return Err(OrderError::VersionConflict {
+                id,
+                expected: order.version,
+                found: stored.version,
+            });
+        }
+        let mut next = order.clone();
+        next.version += 1;
+        store.save(&next);
+        Ok(())
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [InMemory] Deleting order {id:?}"));
+        let mut store = self.store();
+        match store.orders.remove(&id) {
+            Some(order) => {
+                store.deleted.insert(id, order);
+                Ok(())
+            }
+            None if store.deleted.contains_key(&id) => Ok(()),
+            None => Err(OrderError::NotFound { id }),
+        }
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [InMemory] Restoring order {id:?}"));
+        let mut store = self.store();
+        match store.deleted.remove(&id) {
+            Some(order) => {
+                store.orders.insert(id, order);
+                Ok(())
+            }
+            None if store.orders.contains_key(&id) => Err(OrderError::NotDeleted { id }),
+            None => Err(OrderError::NotFound { id }),
+        }
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [InMemory] Purging order {id:?}"));
+        let mut store = self.store();
+        let live = store.orders.remove(&id);
+        match live.or_else(|| store.deleted.remove(&id)) {
+            Some(_) => Ok(()),
+            None => Err(OrderError::NotFound { id }),
+        }
+    }
+}
+
+impl Capability for InMemoryOrderRepository {}
+
+// Same idea for the draft/confirm flow: drafts and confirmed orders side by side.
+#[derive(Default)]
+pub struct InMemoryDraftRepository {
+    orders: HashMap<OrderId, StoredOrder>,
+    console: Console,
+}
+
+impl InMemoryDraftRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+}
+
+impl DraftRepository for InMemoryDraftRepository {
+    fn store(&mut self, order: StoredOrder) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [InMemory] Storing order {:?}", order.id()));
+        self.orders.insert(order.id(), order);
+        Ok(())
+    }
+
+    fn load(&self, id: OrderId) -> Result<Option<StoredOrder>, OrderError> {
+        self.console
+            .line(format_args!("  [InMemory] Loading order {id:?}"));
+        Ok(self.orders.get(&id).cloned())
+    }
+}
+
+impl Capability for InMemoryDraftRepository {}
+
+// A mock payment gateway: always succeeds, unless scripted to decline
+// (see DeclineScript). Great for testing the happy path!
+// Charges made for an order are kept, as the provider's records would be.
+// Amounts are in the minor units of the account's currency, dollars unless
+// set otherwise; only how they are printed depends on it.
+#[derive(Default)]
+pub struct MockPaymentGateway {
+    charges: Mutex<Vec<ChargeRecord>>,
+    currency: Currency,
+    declines: DeclineScript,
+    console: Console,
+}
+
+impl MockPaymentGateway {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn with_currency(mut self, currency: Currency) -> Self {
+        self.currency = currency;
+        self
+    }
+
+    pub fn with_declines(mut self, declines: DeclineScript) -> Self {
+        self.declines = declines;
+        self
+    }
+}
+
+impl Capability for MockPaymentGateway {}
+
+impl PaymentGateway for MockPaymentGateway {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        if let Some(reason) = self.declines.reason_for(amount) {
+            self.console.line(format_args!(
+                "  [MockPayment] Declining {}: {}",
+                amount.in_currency(self.currency),
+                reason.code()
+            ));
+            return Err(OrderError::PaymentDeclined { reason });
+        }
+        self.console.line(format_args!(
+            "  [MockPayment] Charging {}",
+            amount.in_currency(self.currency)
+        ));
+        Ok(())
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.charge(amount)?;
+        self.charges
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(ChargeRecord { order_id, amount });
+        Ok(())
+    }
+
+    // The refund is taken out of its charge in the log: refunded in full,
+    // the charge no longer counts as charged when reconciling; in part, the
+    // rest of it still does.
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.console.line(format_args!(
+            "  [MockPayment] Refunding {}",
+            amount.in_currency(self.currency)
+        ));
+        let mut charges = self.charges.lock().unwrap_or_else(PoisonError::into_inner);
+        let charge = charges
+            .iter()
+            .position(|charge| charge.order_id == order_id && charge.amount.0 >= amount.0);
+        match charge {
+            Some(at) if charges[at].amount == amount => {
+                charges.remove(at);
+                Ok(())
+            }
+            Some(at) => {
+                charges[at].amount = Money(charges[at].amount.0 - amount.0);
+                Ok(())
+            }
+            None => Err(OrderError::PaymentFailed),
+        }
+    }
+}
+
+impl ChargeLog for MockPaymentGateway {
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        Ok(self
+            .charges
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone())
+    }
+}
+
+// Console-based notification: just prints the receipt.
+#[derive(Clone, Default)]
+pub struct ConsoleSender {
+    console: Console,
+}
+
+impl ConsoleSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+}
+
+impl Capability for ConsoleSender {}
+
+impl Sender for ConsoleSender {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.console.line(format_args!(
+            "  [Console] Order {} confirmed",
+            confirmation.order_id
+        ));
+        for line in confirmation.receipt_lines() {
+            self.console.line(format_args!("  [Console]   {line}"));
+        }
+        Ok(())
+    }
+
+    fn send_notice(&self, notice: &Notice) -> Result<(), OrderError> {
+        self.console.line(format_args!(
+            "  [Console] To {}: {}",
+            notice.to, notice.subject
+        ));
+        for line in &notice.lines {
+            self.console.line(format_args!("  [Console]   {line}"));
+        }
+        Ok(())
+    }
+}
+
+// A mock carrier: accepts every parcel and hands out sequential tracking ids.
+// It remembers what it shipped so tests can count the calls.
+#[derive(Default)]
+pub struct MockShippingProvider {
+    shipped: RefCell<Vec<(OrderId, TrackingId, usize)>>,
+    console: Console,
+}
+
+impl MockShippingProvider {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    // (order, tracking id, number of items) for every parcel, oldest first.
+    pub fn shipped(&self) -> Vec<(OrderId, TrackingId, usize)> {
+        self.shipped.borrow().clone()
+    }
+}
+
+impl ShippingProvider for MockShippingProvider {
+    fn ship(
+        &self,
+        order_id: OrderId,
+        items: &[LineItem],
+        address: &Address,
+    ) -> Result<TrackingId, OrderError> {
+        let mut shipped = self.shipped.borrow_mut();
+        let tracking = TrackingId(format!("TRK{:06}", shipped.len() + 1));
+        self.console.line(format_args!(
+            "  [MockShipping] {} item(s) of order {:?} to {}, tracking {}",
+            items.len(),
+            order_id,
+            address.city,
+            tracking.0
+        ));
+        shipped.push((order_id, tracking.clone(), items.len()));
+        Ok(tracking)
+    }
+}
+
+impl Capability for MockShippingProvider {}
+
+// Idempotency keys in a HashMap: gone on restart, which is fine for tests
+// and single-run tools. adapters::idempotency keeps them in a file.
+#[derive(Default)]
+pub struct InMemoryIdempotencyStore {
+    keys: Mutex<HashMap<String, (OrderId, Timestamp)>>,
+    console: Console,
+}
+
+impl InMemoryIdempotencyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.keys
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl IdempotencyStore for InMemoryIdempotencyStore {
+    fn find(&self, key: &str) -> Result<Option<OrderId>, OrderError> {
+        let keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(keys.get(key).map(|&(id, _)| id))
+    }
+
+    fn remember(&self, key: &str, id: OrderId, at: Timestamp) -> Result<(), OrderError> {
+        self.console.line(format_args!(
+            "  [InMemory] Key {key:?} stands for order {id:?}"
+        ));
+        self.keys
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(key.to_string(), (id, at));
+        Ok(())
+    }
+
+    fn purge_older_than(&self, max_age: Duration, now: Timestamp) -> Result<usize, OrderError> {
+        let oldest_kept = now.minus_secs(max_age.as_secs());
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        let before = keys.len();
+        keys.retain(|_, &mut (_, at)| at >= oldest_kept);
+        let purged = before - keys.len();
+        self.console.line(format_args!(
+            "  [InMemory] Purged {purged} idempotency key(s)"
+        ));
+        Ok(purged)
+    }
+
+    fn forget_order(&self, id: OrderId) -> Result<usize, OrderError> {
+        let mut keys = self.keys.lock().unwrap_or_else(PoisonError::into_inner);
+        let before = keys.len();
+        keys.retain(|_, &mut (order, _)| order != id);
+        let forgotten = before - keys.len();
+        self.console.line(format_args!(
+            "  [InMemory] Forgot {forgotten} idempotency key(s) of order {id:?}"
+        ));
+        Ok(forgotten)
+    }
+}
+
+impl Capability for InMemoryIdempotencyStore {}
+
+// Attached files in a map, numbered from 1. adapters::blob_store keeps
+// them in a directory.
+#[derive(Default)]
+pub struct InMemoryBlobStore {
+    blobs: Mutex<Blobs>,
+    console: Console,
+}
+
+#[derive(Default)]
+struct Blobs {
+    by_id: BTreeMap<BlobId, Vec<u8>>,
+    last: u64,
+}
+
+impl InMemoryBlobStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.blobs().by_id.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn blobs(&self) -> MutexGuard<'_, Blobs> {
+        self.blobs.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl BlobStore for InMemoryBlobStore {
+    fn put(&self, bytes: &[u8]) -> Result<BlobId, OrderError> {
+        let mut blobs = self.blobs();
+        blobs.last += 1;
+        let id = BlobId(blobs.last);
+        blobs.by_id.insert(id, bytes.to_vec());
+        self.console.line(format_args!(
+            "  [InMemory] Keeping {} byte(s) as blob {}",
+            bytes.len(),
+            id.0
+        ));
+        Ok(id)
+    }
+
+    fn get(&self, id: BlobId) -> Result<Option<Vec<u8>>, OrderError> {
+        Ok(self.blobs().by_id.get(&id).cloned())
+    }
+
+    fn delete(&self, id: BlobId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [InMemory] Deleting blob {}", id.0));
+        self.blobs().by_id.remove(&id);
+        Ok(())
+    }
+}
+
+impl Capability for InMemoryBlobStore {}
+
+// Stock by item name: one unit per line item of that name. Reserved units
+// are off the shelf until released; confirmed ones are sold for good.
+//
+// With a clock, each reservation is dated when it is made, and
+// release_expired puts back what was held too long. Without one, nothing
+// is dated and nothing expires.
+#[derive(Default)]
+pub struct InMemoryInventory {
+    stock: Mutex<Stock>,
+    clock: Option<Box<dyn Clock + Send + Sync>>,
+    console: Console,
+}
+
+#[derive(Default)]
+struct Stock {
+    on_shelf: HashMap<String, u32>,
+    reservations: BTreeMap<ReservationId, Reservation>,
+}
+
+#[derive(Clone)]
+struct Reservation {
+    held: HashMap<String, u32>,
+    confirmed: bool,
+    reserved_at: Option<Timestamp>,
+}
+
+impl InMemoryInventory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_stock(self, name: impl Into<String>, units: u32) -> Self {
+        self.stock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .on_shelf
+            .insert(name.into(), units);
+        self
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    // What dates the reservations. A SteppingClock the test still holds
+    // comes in through a closure: `move || clock.now()`.
+    pub fn with_clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    // Units neither reserved nor sold.
+    pub fn available(&self, name: &str) -> u32 {
+        let stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        stock.on_shelf.get(name).copied().unwrap_or(0)
+    }
+}
+
+impl Inventory for InMemoryInventory {
+    fn reserve(&self, reservation: ReservationId, items: &[LineItem]) -> Result<(), OrderError> {
+        let mut stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        if stock.reservations.contains_key(&reservation) {
+            return Ok(());
+        }
+        let mut wanted: HashMap<String, u32> = HashMap::new();
+        for item in items {
+            *wanted.entry(item.name.clone()).or_default() += 1;
+        }
+        if let Some((item, _)) = wanted
+            .iter()
+            .find(|&(name, &units)| stock.on_shelf.get(name).copied().unwrap_or(0) < units)
+        {
+            return Err(OrderError::OutOfStock { item: item.clone() });
+        }
+        for (name, units) in &wanted {
+            *stock.on_shelf.entry(name.clone()).or_default() -= units;
+        }
+        self.console.line(format_args!(
+            "  [InMemory] Reserved {} item(s) under {reservation:?}",
+            items.len()
+        ));
+        let reserved_at = self.clock.as_ref().map(|clock| clock.now());
+        stock.reservations.insert(
+            reservation,
+            Reservation {
+                held: wanted,
+                confirmed: false,
+                reserved_at,
+            },
+        );
+        Ok(())
+    }
+
+    fn confirm(&self, reservation: ReservationId) -> Result<(), OrderError> {
+        let mut stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        stock
+            .reservations
+            .get_mut(&reservation)
+            .ok_or(OrderError::InvalidOrder)?
+            .confirmed = true;
+        self.console
+            .line(format_args!("  [InMemory] Confirmed {reservation:?}"));
+        Ok(())
+    }
+
+    fn release(&self, reservation: ReservationId) -> Result<(), OrderError> {
+        let mut stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        if stock.put_back(reservation) {
+            self.console
+                .line(format_args!("  [InMemory] Released {reservation:?}"));
+        }
+        Ok(())
+    }
+
+    fn restock(&self, item: &str, units: u32) -> Result<(), OrderError> {
+        let mut stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        let level = stock.on_shelf.entry(item.to_string()).or_default();
+        *level += units;
+        self.console.line(format_args!(
+            "  [InMemory] Restocked {units} {item}, {level} available"
+        ));
+        Ok(())
+    }
+
+    fn stock_level(&self, item: &str) -> Result<u32, OrderError> {
+        Ok(self.available(item))
+    }
+
+    fn release_expired(
+        &self,
+        now: Timestamp,
+        ttl: Duration,
+    ) -> Result<Vec<ReservationId>, OrderError> {
+        let mut stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        let expired: Vec<ReservationId> = stock
+            .reservations
+            .iter()
+            .filter(|(_, held)| {
+                !held.confirmed
+                    && held
+                        .reserved_at
+                        .is_some_and(|at| at.plus_secs(ttl.as_secs()) <= now)
+            })
+            .map(|(&reservation, _)| reservation)
+            .collect();
+        for &reservation in &expired {
+            stock.put_back(reservation);
+            self.console
+                .line(format_args!("  [InMemory] {reservation:?} expired"));
+        }
+        Ok(expired)
+    }
+}
+
+impl Stock {
+    // false when there was nothing to put back: the reservation is unknown,
+    // or its stock sold.
+    fn put_back(&mut self, reservation: ReservationId) -> bool {
+        let Entry::Occupied(entry) = self.reservations.entry(reservation) else {
+            return false;
+        };
+        if entry.get().confirmed {
+            return false;
+        }
+        for (name, units) in entry.remove().held {
+            *self.on_shelf.entry(name).or_default() += units;
+        }
+        true
+    }
+}
+
+impl Capability for InMemoryInventory {}
+
+// Saga logs in a map: enough to resume a saga within one process, not
+// after it died.
+#[derive(Default)]
+pub struct InMemorySagaLog {
+    entries: Mutex<BTreeMap<SagaId, Vec<SagaEntry>>>,
+}
+
+impl InMemorySagaLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl SagaLog for InMemorySagaLog {
+    fn append(&self, saga: SagaId, entry: SagaEntry) -> Result<(), OrderError> {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .entry(saga)
+            .or_default()
+            .push(entry);
+        Ok(())
+    }
+
+    fn entries(&self, saga: SagaId) -> Result<Vec<SagaEntry>, OrderError> {
+        let entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(entries.get(&saga).cloned().unwrap_or_default())
+    }
+}
+
+impl Capability for InMemorySagaLog {}
@@ -0,0 +1,65 @@
+// Limits shared by the driving adapters that parse untrusted input.
+// The domain accepts any non-empty cart whose total fits in Money; these
+// are stricter, so a hostile client cannot make us allocate, log or store
+// arbitrary amounts of data through one request.
+use crate::domain::{LineItem, Money, OrderError};
+
+pub const MAX_ITEMS: usize = 100;
+pub const MAX_NAME_CHARS: usize = 200;
+
+// One line item from its raw parts. Prices are plain cents: digits only,
+// so "-5", "+5", "1e3" and anything above u32::MAX are all refused.
+pub(crate) fn line_item(name: &str, cents: &str) -> Result<LineItem, OrderError> {
+    let name = name.trim();
+    if name.is_empty()
+        || name.chars().count() > MAX_NAME_CHARS
+        || name.chars().any(char::is_control)
+    {
+        return Err(OrderError::InvalidOrder);
+    }
+    Ok(LineItem {
+        name: name.to_string(),
+        price: price(cents)?,
+    })
+}
+
+fn price(cents: &str) -> Result<Money, OrderError> {
+    if cents.is_empty() || !cents.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(OrderError::InvalidOrder);
+    }
+    // Only digits left: the one way this can fail is overflow.
+    cents
+        .parse::<u32>()
+        .map(Money)
+        .map_err(|_| OrderError::InvalidOrder)
+}
+
+pub(crate) fn check_item_count(count: usize) -> Result<(), OrderError> {
+    if count == 0 || count > MAX_ITEMS {
+        return Err(OrderError::InvalidOrder);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prices_are_digits_that_fit_in_u32() {
+        assert_eq!(price("12999").unwrap(), Money(12999));
+        assert_eq!(price("4294967295").unwrap(), Money(u32::MAX));
+        for bad in ["", "-5", "+5", "1e3", "12.99", "4294967296", " 5"] {
+            assert!(price(bad).is_err(), "{bad:?} was accepted");
+        }
+    }
+
+    #[test]
+    fn names_are_bounded_and_printable() {
+        assert!(line_item("Keyboard", "1").is_ok());
+        assert!(line_item(&"é".repeat(MAX_NAME_CHARS), "1").is_ok());
+        assert!(line_item(&"é".repeat(MAX_NAME_CHARS + 1), "1").is_err());
+        assert!(line_item("   ", "1").is_err());
+        assert!(line_item("bell\u{7}", "1").is_err());
+    }
+}
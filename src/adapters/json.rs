@@ -0,0 +1,243 @@
+// Just enough JSON for the adapters that emit or accept it, without serde.
+// Reading is limited to what they need: non-negative integers only, and a
+// bounded nesting depth, since some of it comes from untrusted clients.
+
+// Escapes a string for use between double quotes.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Value {
+    String(String),
+    Number(u64),
+    Bool(bool),
+    Null,
+    Array(Vec<Value>),
+    // Fields in document order.
+    Object(Vec<(String, Value)>),
+}
+
+// Deeper documents are refused rather than risking the stack:
+// nothing this crate reads nests more than a few levels.
+pub(crate) const MAX_DEPTH: usize = 32;
+
+// Any JSON document, within the limits above: no negative or fractional
+// numbers, no more than MAX_DEPTH levels. None for anything else,
+// including trailing garbage.
+pub(crate) fn parse(text: &str) -> Option<Value> {
+    let mut chars = text.trim().chars().peekable();
+    let value = parse_value(&mut chars, 0)?;
+    if chars.next().is_some() {
+        return None;
+    }
+    Some(value)
+}
+
+// {"key":value,...} -> [(key, value), ...] in document order, where every
+// value is a string, a number or null.
+#[cfg(not(target_arch = "wasm32"))] // only the file adapters read these
+pub(crate) fn parse_flat_object(text: &str) -> Option<Vec<(String, Value)>> {
+    match parse(text)? {
+        Value::Object(fields)
+            if fields.iter().all(|(_, value)| {
+                matches!(value, Value::String(_) | Value::Number(_) | Value::Null)
+            }) =>
+        {
+            Some(fields)
+        }
+        _ => None,
+    }
+}
+
+fn parse_value(chars: &mut Chars<'_>, depth: usize) -> Option<Value> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    skip_whitespace(chars);
+    let value = match chars.peek()? {
+        '"' => Value::String(parse_string(chars)?),
+        'n' => {
+            expect_word(chars, "null")?;
+            Value::Null
+        }
+        't' => {
+            expect_word(chars, "true")?;
+            Value::Bool(true)
+        }
+        'f' => {
+            expect_word(chars, "false")?;
+            Value::Bool(false)
+        }
+        '[' => {
+            chars.next();
+            let mut items = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&']') {
+                chars.next();
+            } else {
+                loop {
+                    items.push(parse_value(chars, depth + 1)?);
+                    skip_whitespace(chars);
+                    match chars.next()? {
+                        ',' => continue,
+                        ']' => break,
+                        _ => return None,
+                    }
+                }
+            }
+            Value::Array(items)
+        }
+        '{' => {
+            chars.next();
+            let mut fields = Vec::new();
+            skip_whitespace(chars);
+            if chars.peek() == Some(&'}') {
+                chars.next();
+            } else {
+                loop {
+                    skip_whitespace(chars);
+                    let key = parse_string(chars)?;
+                    skip_whitespace(chars);
+                    expect(chars, ':')?;
+                    fields.push((key, parse_value(chars, depth + 1)?));
+                    skip_whitespace(chars);
+                    match chars.next()? {
+                        ',' => continue,
+                        '}' => break,
+                        _ => return None,
+                    }
+                }
+            }
+            Value::Object(fields)
+        }
+        _ => Value::Number(parse_number(chars)?),
+    };
+    Some(value)
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::Chars<'a>>;
+
+fn expect(chars: &mut Chars<'_>, expected: char) -> Option<()> {
+    (chars.next()? == expected).then_some(())
+}
+
+fn expect_word(chars: &mut Chars<'_>, word: &str) -> Option<()> {
+    word.chars()
+        .try_for_each(|expected| expect(chars, expected))
+}
+
+fn skip_whitespace(chars: &mut Chars<'_>) {
+    while chars.peek().is_some_and(|c| c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn parse_string(chars: &mut Chars<'_>) -> Option<String> {
+    expect(chars, '"')?;
+    let mut text = String::new();
+    loop {
+        match chars.next()? {
+            '"' => return Some(text),
+            '\\' => match chars.next()? {
+                '"' => text.push('"'),
+                '\\' => text.push('\\'),
+                '/' => text.push('/'),
+                'n' => text.push('\n'),
+                'r' => text.push('\r'),
+                't' => text.push('\t'),
+                'u' => {
+                    let hex: String = (0..4).map(|_| chars.next()).collect::<Option<_>>()?;
+                    text.push(char::from_u32(u32::from_str_radix(&hex, 16).ok()?)?);
+                }
+                _ => return None,
+            },
+            c => text.push(c),
+        }
+    }
+}
+
+fn parse_number(chars: &mut Chars<'_>) -> Option<u64> {
+    let mut digits = String::new();
+    while let Some(c) = chars.peek().filter(|c| c.is_ascii_digit()) {
+        digits.push(*c);
+        chars.next();
+    }
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_quotes_backslashes_and_control_characters() {
+        assert_eq!(escape(r#"a "b" \c"#), r#"a \"b\" \\c"#);
+        assert_eq!(escape("line\nnext\u{1}"), "line\\nnext\\u0001");
+        assert_eq!(escape("café ☕"), "café ☕");
+    }
+
+    #[test]
+    fn parses_what_escape_writes() {
+        let name = "12\" \"Ruler\"\n\u{1}";
+        let text = format!(r#"{{"name":"{}","price":199,"note":null}}"#, escape(name));
+
+        assert_eq!(
+            parse_flat_object(&text),
+            Some(vec![
+                ("name".to_string(), Value::String(name.to_string())),
+                ("price".to_string(), Value::Number(199)),
+                ("note".to_string(), Value::Null),
+            ])
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_or_trailing_input() {
+        assert_eq!(parse_flat_object("{}"), Some(vec![]));
+        assert_eq!(parse_flat_object(r#"{"a":1"#), None);
+        assert_eq!(parse_flat_object(r#"{"a":"unterminated}"#), None);
+        assert_eq!(parse_flat_object(r#"{"a":1} x"#), None);
+        assert_eq!(parse_flat_object(r#"{"a":}"#), None);
+        assert_eq!(parse_flat_object(r#"{"a":[1]}"#), None);
+    }
+
+    #[test]
+    fn parses_nested_documents_up_to_the_depth_limit() {
+        assert_eq!(
+            parse(r#"{"items":[{"n":1},true,null]}"#),
+            Some(Value::Object(vec![(
+                "items".to_string(),
+                Value::Array(vec![
+                    Value::Object(vec![("n".to_string(), Value::Number(1))]),
+                    Value::Bool(true),
+                    Value::Null,
+                ])
+            )]))
+        );
+
+        let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+        assert!(parse(&nested(MAX_DEPTH + 1)).is_some());
+        assert_eq!(parse(&nested(MAX_DEPTH + 2)), None);
+        assert_eq!(parse(&nested(100_000)), None);
+    }
+
+    #[test]
+    fn refuses_numbers_it_cannot_represent() {
+        assert_eq!(parse("-1"), None);
+        assert_eq!(parse("1.5"), None);
+        assert_eq!(parse("99999999999999999999999"), None);
+    }
+}
@@ -0,0 +1,250 @@
+// --- Concurrency limited (decorator) ---
+// Lets at most `permits` calls at a time through to the port it wraps. A
+// payment provider allowing 4 concurrent calls is not helped by 64 threads
+// sharing one Arc'd gateway: the extra calls are turned down there, or
+// worse, slow everyone else down.
+//
+// When every permit is taken, the next call either waits for one to come
+// back (WhenFull::Block, the default) or is refused at once with
+// TooManyConcurrentCalls, without touching the inner adapter
+// (WhenFull::Fail). Each port gets the limit of its own decorator: wrap the
+// gateway in 4 permits and the repository in 16, and a slow provider never
+// holds up the reads.
+//
+// A permit is given back however the call ends, a panic included.
+use crate::domain::{
+    Money, Notice, Order, OrderConfirmation, OrderError, OrderId, OrderKey, OrderReference,
+};
+use crate::ports::{
+    CancelToken, Capability, ChargeLog, ChargeOutcome, ChargeRecord, OrderReader, OrderWriter,
+    PaymentGateway, Sender,
+};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhenFull {
+    #[default]
+    Block,
+    Fail,
+}
+
+pub struct ConcurrencyLimited<I> {
+    inner: I,
+    permits: usize,
+    when_full: WhenFull,
+    // Calls under way: a counting semaphore, with `released` to wake the
+    // callers waiting on it.
+    in_use: Mutex<usize>,
+    released: Condvar,
+}
+
+// Held for the length of one call.
+struct Permit<'a> {
+    in_use: &'a Mutex<usize>,
+    released: &'a Condvar,
+}
+
+impl Drop for Permit<'_> {
+    fn drop(&mut self) {
+        *lock(self.in_use) -= 1;
+        self.released.notify_one();
+    }
+}
+
+fn lock(in_use: &Mutex<usize>) -> MutexGuard<'_, usize> {
+    in_use.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+impl<I> ConcurrencyLimited<I> {
+    // At least 1: with no permit at all, every call would wait forever.
+    pub fn new(inner: I, permits: usize) -> Self {
+        Self {
+            inner,
+            permits: permits.max(1),
+            when_full: WhenFull::default(),
+            in_use: Mutex::new(0),
+            released: Condvar::new(),
+        }
+    }
+
+    pub fn with_when_full(mut self, when_full: WhenFull) -> Self {
+        self.when_full = when_full;
+        self
+    }
+
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    pub fn permits(&self) -> usize {
+        self.permits
+    }
+
+    // How many calls are under way right now.
+    pub fn in_use(&self) -> usize {
+        *lock(&self.in_use)
+    }
+
+    fn acquire(&self) -> Result<Permit<'_>, OrderError> {
+        let mut in_use = lock(&self.in_use);
+        while *in_use >= self.permits {
+            match self.when_full {
+                WhenFull::Fail => {
+                    return Err(OrderError::TooManyConcurrentCalls {
+                        limit: self.permits,
+                    });
+                }
+                WhenFull::Block => {
+                    in_use = self
+                        .released
+                        .wait(in_use)
+                        .unwrap_or_else(PoisonError::into_inner);
+                }
+            }
+        }
+        *in_use += 1;
+        Ok(Permit {
+            in_use: &self.in_use,
+            released: &self.released,
+        })
+    }
+
+    fn invoke<T>(&self, call: impl FnOnce(&I) -> Result<T, OrderError>) -> Result<T, OrderError> {
+        let _permit = self.acquire()?;
+        call(&self.inner)
+    }
+}
+
+impl<G: PaymentGateway> PaymentGateway for ConcurrencyLimited<G> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.charge(amount))
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.charge_for(order_id, amount))
+    }
+
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.invoke(|inner| inner.charge_order(order_id, amount))
+    }
+
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.refund_for(order_id, amount))
+    }
+}
+
+// Asking for the records is a call to the provider too.
+impl<G: PaymentGateway + ChargeLog> ChargeLog for ConcurrencyLimited<G> {
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        self.invoke(|inner| inner.charges())
+    }
+}
+
+impl<S: Sender> Sender for ConcurrencyLimited<S> {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.send(confirmation))
+    }
+
+    fn send_notice(&self, notice: &Notice) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.send_notice(notice))
+    }
+}
+
+// Forwarded one by one, so the inner adapter's cheap versions are used.
+impl<R: OrderReader> OrderReader for ConcurrencyLimited<R> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.invoke(|inner| inner.find(id))
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.invoke(|inner| inner.list())
+    }
+
+    fn exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        self.invoke(|inner| inner.exists(id))
+    }
+
+    fn total_of(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        self.invoke(|inner| inner.total_of(id))
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.for_each(visit))
+    }
+
+    fn for_each_cancellable(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.for_each_cancellable(cancel, visit))
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        self.invoke(|inner| inner.list_deleted())
+    }
+
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.invoke(|inner| inner.find_by_key(key))
+    }
+
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        self.invoke(|inner| inner.find_by_reference(reference))
+    }
+}
+
+impl<R: OrderWriter> OrderWriter for ConcurrencyLimited<R> {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.save(order))
+    }
+
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.update(order))
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.soft_delete(id))
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.restore(id))
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.purge(id))
+    }
+}
+
+impl<I> Capability for ConcurrencyLimited<I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_err_variant;
+    use std::panic::{self, AssertUnwindSafe};
+
+    struct Flaky;
+
+    impl PaymentGateway for Flaky {
+        fn charge(&self, amount: Money) -> Result<(), OrderError> {
+            match amount.0 {
+                0 => panic!("the driver fell over"),
+                1 => Err(OrderError::PaymentFailed),
+                _ => Ok(()),
+            }
+        }
+    }
+
+    #[test]
+    fn a_permit_comes_back_however_the_call_ends() {
+        let payment = ConcurrencyLimited::new(Flaky, 1).with_when_full(WhenFull::Fail);
+
+        assert_err_variant!(payment.charge(Money(1)), OrderError::PaymentFailed);
+        let panicked = panic::catch_unwind(AssertUnwindSafe(|| payment.charge(Money(0))));
+
+        assert!(panicked.is_err());
+        assert_eq!(payment.in_use(), 0);
+        assert!(payment.charge(Money(500)).is_ok());
+        assert_eq!(ConcurrencyLimited::new(Flaky, 0).permits(), 1);
+    }
+}
@@ -0,0 +1,219 @@
+// --- Metrics adapters ---
+// In real life: a Prometheus registry, a StatsD client...
+use crate::ports::{Capability, Metrics};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+// Upper bounds of the histogram buckets, in milliseconds, as Prometheus
+// would have them: a value lands in the first bucket it does not exceed,
+// and anything past the last one in a final, unbounded bucket.
+pub const BUCKET_BOUNDS_MS: [u64; 7] = [1, 5, 10, 50, 100, 500, 1000];
+
+// What a histogram holds at one point: how many observations fell in each
+// bucket (one more than there are bounds), how many in all and their sum.
+// A fixed set of buckets forgets the values themselves, so the percentiles
+// are estimated from them: by linear interpolation inside the bucket the
+// rank falls in, and as the last bound when it is the unbounded one.
+// None when there was nothing to estimate from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistogramSnapshot {
+    pub buckets: Vec<u64>,
+    pub count: u64,
+    pub sum: u64,
+    pub p50: Option<f64>,
+    pub p99: Option<f64>,
+}
+
+impl HistogramSnapshot {
+    fn of(buckets: &[u64; BUCKET_BOUNDS_MS.len() + 1], sum: u64) -> Self {
+        let count = buckets.iter().sum();
+        Self {
+            buckets: buckets.to_vec(),
+            count,
+            sum,
+            p50: percentile(buckets, count, 0.50),
+            p99: percentile(buckets, count, 0.99),
+        }
+    }
+
+    // The average, exact: the sum is kept, not estimated.
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+}
+
+fn percentile(buckets: &[u64], count: u64, quantile: f64) -> Option<f64> {
+    if count == 0 {
+        return None;
+    }
+    let rank = quantile * count as f64;
+    let mut below = 0;
+    for (i, &in_bucket) in buckets.iter().enumerate() {
+        if in_bucket > 0 && (below + in_bucket) as f64 >= rank {
+            let Some(&upper) = BUCKET_BOUNDS_MS.get(i) else {
+                return Some(BUCKET_BOUNDS_MS[BUCKET_BOUNDS_MS.len() - 1] as f64);
+            };
+            let lower = if i == 0 { 0 } else { BUCKET_BOUNDS_MS[i - 1] };
+            let within = (rank - below as f64) / in_bucket as f64;
+            return Some(lower as f64 + (upper - lower) as f64 * within);
+        }
+        below += in_bucket;
+    }
+    unreachable!("the buckets add up to count")
+}
+
+#[derive(Default)]
+struct Histogram {
+    buckets: [u64; BUCKET_BOUNDS_MS.len() + 1],
+    sum: u64,
+}
+
+// Counts in memory, for tests and for a "/metrics" page.
+// BTreeMaps so snapshots list the counters and histograms in a stable order.
+#[derive(Default)]
+pub struct InMemoryMetrics {
+    counters: Mutex<BTreeMap<String, u64>>,
+    histograms: Mutex<BTreeMap<String, Histogram>>,
+}
+
+impl InMemoryMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // 0 for a counter never incremented.
+    pub fn counter(&self, name: &str) -> u64 {
+        self.lock().get(name).copied().unwrap_or(0)
+    }
+
+    pub fn snapshot(&self) -> Vec<(String, u64)> {
+        self.lock()
+            .iter()
+            .map(|(name, value)| (name.clone(), *value))
+            .collect()
+    }
+
+    // Empty for a histogram never observed.
+    pub fn histogram(&self, name: &str) -> HistogramSnapshot {
+        match self.histograms().get(name) {
+            Some(histogram) => HistogramSnapshot::of(&histogram.buckets, histogram.sum),
+            None => HistogramSnapshot::of(&Default::default(), 0),
+        }
+    }
+
+    pub fn histogram_snapshot(&self) -> Vec<(String, HistogramSnapshot)> {
+        self.histograms()
+            .iter()
+            .map(|(name, histogram)| {
+                let snapshot = HistogramSnapshot::of(&histogram.buckets, histogram.sum);
+                (name.clone(), snapshot)
+            })
+            .collect()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, BTreeMap<String, u64>> {
+        self.counters.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn histograms(&self) -> MutexGuard<'_, BTreeMap<String, Histogram>> {
+        self.histograms
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Metrics for InMemoryMetrics {
+    fn increment(&self, name: &str) {
+        *self.lock().entry(name.to_string()).or_insert(0) += 1;
+    }
+
+    fn observe_histogram(&self, name: &str, value_ms: u64) {
+        let mut histograms = self.histograms();
+        let histogram = histograms.entry(name.to_string()).or_default();
+        let bucket = BUCKET_BOUNDS_MS
+            .iter()
+            .position(|&bound| value_ms <= bound)
+            .unwrap_or(BUCKET_BOUNDS_MS.len());
+        histogram.buckets[bucket] += 1;
+        histogram.sum = histogram.sum.saturating_add(value_ms);
+    }
+}
+
+impl Capability for InMemoryMetrics {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counters_start_at_zero_and_are_listed_by_name() {
+        let metrics = InMemoryMetrics::new();
+        assert_eq!(metrics.counter("orders.placed"), 0);
+
+        metrics.increment("orders.placed");
+        metrics.increment("orders.placed");
+        metrics.increment("emails.sent");
+
+        assert_eq!(metrics.counter("orders.placed"), 2);
+        assert_eq!(
+            metrics.snapshot(),
+            vec![
+                ("emails.sent".to_string(), 1),
+                ("orders.placed".to_string(), 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn values_land_in_the_first_bucket_they_do_not_exceed() {
+        let metrics = InMemoryMetrics::new();
+        for value in [0, 1, 2, 5, 7, 10, 50, 99, 500, 1000, 1001, 60_000] {
+            metrics.observe_histogram("charge", value);
+        }
+
+        let charge = metrics.histogram("charge");
+        assert_eq!(charge.buckets, [2, 2, 2, 1, 1, 1, 1, 2]);
+        assert_eq!(charge.count, 12);
+        assert_eq!(charge.sum, 62_675);
+        assert_eq!(metrics.histogram_snapshot().len(), 1);
+    }
+
+    #[test]
+    fn percentiles_are_interpolated_inside_their_bucket() {
+        let metrics = InMemoryMetrics::new();
+        // 40 calls of 2 ms, 40 of 20 ms, 20 of 200 ms.
+        for (value, times) in [(2, 40), (20, 40), (200, 20)] {
+            for _ in 0..times {
+                metrics.observe_histogram("save", value);
+            }
+        }
+
+        let save = metrics.histogram("save");
+        // The 50th of 100 is the 10th of the 40 in (10, 50]: 10 + 40 / 4.
+        assert_eq!(save.p50, Some(20.0));
+        // The 99th is the 19th of the 20 in (100, 500]: 100 + 400 * 19 / 20.
+        assert_eq!(save.p99, Some(480.0));
+        assert_eq!(save.mean(), Some(48.8));
+    }
+
+    #[test]
+    fn empty_single_and_unbounded_histograms() {
+        let metrics = InMemoryMetrics::new();
+        let never = metrics.histogram("never");
+        assert_eq!(never.buckets, [0; 8]);
+        assert_eq!((never.count, never.sum), (0, 0));
+        assert_eq!((never.p50, never.p99, never.mean()), (None, None, None));
+
+        // One value of 7 ms: all there is to go on is the bucket (5, 10].
+        metrics.observe_histogram("once", 7);
+        let once = metrics.histogram("once");
+        assert_eq!(once.p50, Some(7.5));
+        assert_eq!(once.p99, Some(9.95));
+        assert_eq!(once.mean(), Some(7.0));
+
+        // Past the last bound, the last bound is the best estimate there is.
+        metrics.observe_histogram("slow", 5_000);
+        assert_eq!(metrics.histogram("slow").p50, Some(1000.0));
+        assert!(metrics.snapshot().is_empty());
+    }
+}
@@ -0,0 +1,181 @@
+// =============================================================================
+// ADAPTERS - Concrete Implementations
+// =============================================================================
+// Adapters live at the edge of the system.
+// They depend on ports, never the other way around.
+//
+// Everything here also builds for wasm32-unknown-unknown, except the
+// adapters needing what a browser does not have: a wall clock reachable
+// from std, a file system, threads. Those are left out of wasm builds
+// rather than compiled into code that panics on first use.
+
+// In-memory adapters (testing / development)
+pub mod in_memory;
+
+// External services (for production)
+// Same ports, completely different implementations.
+pub mod external;
+
+// Orders in a SQL database, through a SqlExecutor
+pub mod sql;
+
+// The system clock
+#[cfg(not(target_arch = "wasm32"))]
+pub mod clock;
+
+// Random UUIDs for orders
+#[cfg(not(target_arch = "wasm32"))]
+pub mod ids;
+
+// References for people to quote: by year, random, or checked
+pub mod references;
+
+// Error telemetry sinks
+pub mod error_reporting;
+
+// Exchange rates from a fixed table
+pub mod rates;
+
+// Which orders wait for an approval
+pub mod approval;
+
+// Which orders get a confirmation
+pub mod notification;
+
+// Which orders are screened out before they are charged
+pub mod fraud;
+
+// How an edit that lost the race to another is settled
+pub mod conflict;
+
+// How long an order is kept before it is forgotten for good
+pub mod retention;
+
+// Events handed to every subscriber that asked for them
+pub mod event_bus;
+
+// Events held back from a read model until it is told to catch up
+pub mod projection;
+
+// Stock running low, and the ops contact told about it
+pub mod stock;
+
+// Stock spread over several warehouses, and which one each line comes from
+pub mod warehouse;
+
+// Counters and histograms
+pub mod metrics;
+
+// Events appended to a file, and replayed from it
+#[cfg(not(target_arch = "wasm32"))]
+pub mod event_log;
+
+// What happened to each order, in files rotated by size
+#[cfg(not(target_arch = "wasm32"))]
+pub mod audit_log;
+
+// Service state kept in a file across restarts
+#[cfg(not(target_arch = "wasm32"))]
+pub mod state_file;
+
+// Idempotency keys kept in a file across restarts
+#[cfg(not(target_arch = "wasm32"))]
+pub mod idempotency;
+
+// Attached files kept in a directory, one file each
+#[cfg(not(target_arch = "wasm32"))]
+pub mod blob_store;
+
+// Orders kept in one file, in a choice of formats
+#[cfg(not(target_arch = "wasm32"))]
+pub mod file_repository;
+
+// Decorator holding notifications until flushed
+pub mod buffered;
+
+// Decorator queueing notifications, retried with backoff
+pub mod outbox;
+
+// Decorator deferring charges while the payment provider is unreachable
+pub mod offline;
+
+// Decorator charging again when the provider says it may go through
+pub mod retry;
+
+// Decorator capping what is charged in any 24 hours
+pub mod capped;
+
+// Decorator failing fast while a payment provider or a sender is down
+pub mod breaker;
+
+// Decorator routing charges between two payment gateways by their latency
+pub mod routing;
+
+// Decorator refusing port calls once a use case's time budget is spent
+pub mod budget;
+
+// Decorator sending a share of the orders to an adapter being rolled out
+pub mod canary;
+
+// Decorator recording how long every port call takes
+pub mod timing;
+
+// Decorator letting only so many calls at a time through to a port
+pub mod limited;
+
+// Decorators carrying a use case's trace context through the ports
+pub mod tracing;
+
+// Shim plugging adapters written against the old SenderV1 port into Sender
+pub mod compat;
+
+// One repository shared by several single-threaded services
+pub mod shared;
+
+// Driving adapter: commands arriving on a channel
+#[cfg(not(target_arch = "wasm32"))]
+pub mod queue;
+
+// Driving adapters parsing untrusted input: command lines, HTTP bodies
+pub mod cli;
+pub mod http;
+
+// Driving adapter: orders exported by another system, under its own names
+pub mod import;
+
+// Driving adapter: an interactive shell over every input port
+pub mod repl;
+
+// Ctrl-C cancelling the command under way rather than the process
+#[cfg(all(feature = "ctrl-c", unix, not(target_arch = "wasm32")))]
+pub mod ctrl_c;
+
+// A "simulated" webhook sender, with optional HMAC signing
+#[cfg(not(target_arch = "wasm32"))]
+pub mod webhook;
+
+#[cfg(not(target_arch = "wasm32"))]
+mod atomic_file;
+mod config_error;
+mod confirmation;
+mod console;
+mod declines;
+#[cfg(not(target_arch = "wasm32"))]
+mod event_json;
+#[cfg(not(target_arch = "wasm32"))]
+mod hmac;
+mod inbound;
+mod json;
+mod network;
+mod secret;
+
+pub use config_error::ConfigError;
+pub use confirmation::{
+    CONFIRMATION_VERSION, OrderConfirmationV1, OrderConfirmationV2, WireError,
+    confirmation_envelope, parse_any_confirmation,
+};
+pub use console::{Console, ConsoleLogger, LogEntry, SharedBuffer, SilentLogger, VecLogger};
+pub use declines::DeclineScript;
+pub use inbound::{MAX_ITEMS, MAX_NAME_CHARS};
+pub use network::{NetworkConditions, NetworkFault, NetworkStats};
+pub use secret::SecretString;
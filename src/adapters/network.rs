@@ -0,0 +1,196 @@
+// --- Simulated network conditions ---
+// The external adapters are simulations: nothing leaves the process. Given
+// one NetworkConditions, shared through an Arc, they go through the same
+// simulated network, which decides for every call whether it gets there:
+// - latency, drawn from a range. Nothing sleeps: the delay is added up in
+//   stats(), so a run stays instant and still says how long it waited
+// - a failure probability: the call is lost on the way, and has no effect
+// - a partition: from partitioned() until heal(), every call fails
+//
+// A call that fails is the port's transport-class error: PaymentUnavailable
+// for a payment, StorageFailed for SQL, NotificationFailed for a webhook.
+// Those are the errors the decorators already wait out: the offline gateway
+// defers the charge, the outbox retries the notification.
+//
+// Draws come from a seeded xorshift64*: the same seed loses the same calls,
+// in the same order.
+use crate::domain::Percent;
+use std::ops::RangeInclusive;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NetworkFault {
+    Partitioned,
+    Lost,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NetworkStats {
+    pub calls: u64,
+    // Refused while partitioned.
+    pub partitioned: u64,
+    pub lost: u64,
+    // The latency of the calls that got through.
+    pub delay: Duration,
+}
+
+#[derive(Debug)]
+pub struct NetworkConditions {
+    latency: RangeInclusive<Duration>,
+    failure: Percent,
+    partitioned: AtomicBool,
+    draws: Mutex<Draws>,
+}
+
+#[derive(Debug)]
+struct Draws {
+    state: u64,
+    stats: NetworkStats,
+}
+
+impl NetworkConditions {
+    // A healthy network: instant, nothing lost.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            latency: Duration::ZERO..=Duration::ZERO,
+            failure: Percent::try_from(0).expect("0 is a percentage"),
+            partitioned: AtomicBool::new(false),
+            draws: Mutex::new(Draws {
+                state: splitmix64(seed),
+                stats: NetworkStats::default(),
+            }),
+        }
+    }
+
+    pub fn with_latency(mut self, latency: RangeInclusive<Duration>) -> Self {
+        assert!(!latency.is_empty(), "empty latency range {latency:?}");
+        self.latency = latency;
+        self
+    }
+
+    pub fn with_failure_probability(mut self, failure: Percent) -> Self {
+        self.failure = failure;
+        self
+    }
+
+    // Cuts every adapter on this network off, until heal().
+    pub fn partitioned(&self) {
+        self.partitioned.store(true, Ordering::SeqCst);
+    }
+
+    pub fn heal(&self) {
+        self.partitioned.store(false, Ordering::SeqCst);
+    }
+
+    pub fn is_partitioned(&self) -> bool {
+        self.partitioned.load(Ordering::SeqCst)
+    }
+
+    pub fn stats(&self) -> NetworkStats {
+        self.draws().stats
+    }
+
+    // One call across the network: its latency when it gets through.
+    // A partition refuses the call without drawing anything, so healing
+    // leaves the seed's sequence where it was.
+    pub fn call(&self) -> Result<Duration, NetworkFault> {
+        let mut draws = self.draws();
+        draws.stats.calls += 1;
+        if self.is_partitioned() {
+            draws.stats.partitioned += 1;
+            return Err(NetworkFault::Partitioned);
+        }
+        if draws.pick(0, 99) < u64::from(self.failure.value()) {
+            draws.stats.lost += 1;
+            return Err(NetworkFault::Lost);
+        }
+        let (low, high) = (self.latency.start(), self.latency.end());
+        let micros = draws.pick(micros_of(*low), micros_of(*high));
+        let delay = Duration::from_micros(micros);
+        draws.stats.delay += delay;
+        Ok(delay)
+    }
+
+    fn draws(&self) -> MutexGuard<'_, Draws> {
+        self.draws.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Draws {
+    // xorshift64*
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn pick(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next_u64() % (high - low + 1)
+    }
+}
+
+// Latencies past u64 microseconds (half a million years) are clamped.
+fn micros_of(duration: Duration) -> u64 {
+    u64::try_from(duration.as_micros()).unwrap_or(u64::MAX - 1)
+}
+
+// Spreads any seed, 0 included, into a valid xorshift state (never 0).
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (z ^ (z >> 31)).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn percent(value: u32) -> Percent {
+        Percent::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn a_partition_refuses_every_call_until_healed() {
+        let network = NetworkConditions::new(1);
+        network.call().unwrap();
+
+        network.partitioned();
+        assert_eq!(network.call(), Err(NetworkFault::Partitioned));
+        assert_eq!(network.call(), Err(NetworkFault::Partitioned));
+        network.heal();
+
+        network.call().unwrap();
+        let stats = network.stats();
+        assert_eq!((stats.calls, stats.partitioned, stats.lost), (4, 2, 0));
+    }
+
+    #[test]
+    fn the_same_seed_loses_the_same_calls() {
+        let outcomes = |seed| {
+            let network = NetworkConditions::new(seed)
+                .with_latency(Duration::from_millis(5)..=Duration::from_millis(50))
+                .with_failure_probability(percent(30));
+            (0..200).map(|_| network.call()).collect::<Vec<_>>()
+        };
+
+        let first = outcomes(7);
+        assert_eq!(first, outcomes(7));
+        assert_ne!(first, outcomes(8));
+        let lost = first.iter().filter(|call| call.is_err()).count();
+        assert!((30..=90).contains(&lost), "lost {lost} of 200");
+        assert!(first.iter().flatten().all(|delay| {
+            (Duration::from_millis(5)..=Duration::from_millis(50)).contains(delay)
+        }));
+    }
+
+    #[test]
+    fn certain_failure_loses_everything() {
+        let network = NetworkConditions::new(3).with_failure_probability(percent(100));
+        assert!((0..50).all(|_| network.call() == Err(NetworkFault::Lost)));
+        assert_eq!(network.stats().delay, Duration::ZERO);
+    }
+}
@@ -0,0 +1,16 @@
+// --- Charged-only confirmations ---
+// A NotificationPolicy for shops whose promotions give orders away: an
+// order a discount paid for in full is placed without a confirmation, since
+// there is no payment to confirm. Everything charged is confirmed.
+use crate::domain::{Money, Order};
+use crate::ports::{Capability, NotificationPolicy};
+
+pub struct ConfirmChargedOnly;
+
+impl NotificationPolicy for ConfirmChargedOnly {
+    fn should_confirm(&self, order: &Order) -> bool {
+        order.total != Money(0)
+    }
+}
+
+impl Capability for ConfirmChargedOnly {}
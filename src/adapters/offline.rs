@@ -0,0 +1,204 @@
+// --- Offline-capable payment gateway (decorator) ---
+// A point of sale must keep selling when the payment provider cannot be
+// reached. OfflineCapablePaymentGateway tries the inner gateway first; when
+// the provider is unavailable (PaymentUnavailable, or CircuitOpen from a
+// circuit breaker in between; not a declined card) it writes the charge down in a PendingCharges store and answers Deferred.
+// So does an issuer answering TryAgainLater: the same charge may go
+// through later.
+// The order is then placed as PaymentPending, and OrderService::settle_pending
+// retries the charge later.
+//
+// A declined card (PaymentFailed, or PaymentDeclined for any other reason)
+// still fails right away: that customer has to pay some other way, now.
+//
+// Only charge_order defers. charge() and charge_for() are the inner
+// gateway's, unchanged, so settlement, which calls charge_for(), never
+// records a charge twice.
+use super::Console;
+use crate::domain::{DeclineReason, Money, OrderError, OrderId};
+use crate::ports::{
+    Capability, ChargeLog, ChargeOutcome, ChargeRecord, PaymentGateway, PendingCharge,
+    PendingCharges,
+};
+use std::sync::{Mutex, PoisonError};
+
+pub struct OfflineCapablePaymentGateway<'a, G: PaymentGateway> {
+    inner: G,
+    pending: &'a (dyn PendingCharges + Sync),
+    console: Console,
+}
+
+impl<'a, G: PaymentGateway> OfflineCapablePaymentGateway<'a, G> {
+    pub fn new(inner: G, pending: &'a (dyn PendingCharges + Sync)) -> Self {
+        Self {
+            inner,
+            pending,
+            console: Console::stdout(),
+        }
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn inner(&self) -> &G {
+        &self.inner
+    }
+}
+
+impl<G: PaymentGateway> PaymentGateway for OfflineCapablePaymentGateway<'_, G> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.inner.charge(amount)
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.inner.charge_for(order_id, amount)
+    }
+
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        match self.inner.charge_for(order_id, amount) {
+            Ok(()) => Ok(ChargeOutcome::Charged),
+            Err(
+                e @ (OrderError::PaymentUnavailable
+                | OrderError::CircuitOpen { .. }
+                | OrderError::PaymentDeclined {
+                    reason: DeclineReason::TryAgainLater,
+                }),
+            ) => {
+                self.pending.record(PendingCharge { order_id, amount })?;
+                self.console.line(format_args!(
+                    "  [Offline] {e}, charge of {amount} for order {order_id:?} deferred"
+                ));
+                Ok(ChargeOutcome::Deferred)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.inner.refund_for(order_id, amount)
+    }
+}
+
+// The inner gateway's records: a deferred charge is not a charge yet.
+impl<G: PaymentGateway + ChargeLog> ChargeLog for OfflineCapablePaymentGateway<'_, G> {
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        self.inner.charges()
+    }
+}
+
+impl<G: PaymentGateway> Capability for OfflineCapablePaymentGateway<'_, G> {}
+
+// The PendingCharges store for tests and single-process deployments.
+// A real point of sale would keep it on disk: the charges are money owed.
+#[derive(Default)]
+pub struct InMemoryPendingCharges {
+    charges: Mutex<Vec<PendingCharge>>,
+}
+
+impl InMemoryPendingCharges {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl PendingCharges for InMemoryPendingCharges {
+    fn record(&self, charge: PendingCharge) -> Result<(), OrderError> {
+        self.charges
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(charge);
+        Ok(())
+    }
+
+    fn pending(&self) -> Result<Vec<PendingCharge>, OrderError> {
+        Ok(self
+            .charges
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone())
+    }
+
+    fn remove(&self, order_id: OrderId) -> Result<(), OrderError> {
+        self.charges
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|charge| charge.order_id != order_id);
+        Ok(())
+    }
+}
+
+impl Capability for InMemoryPendingCharges {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_err_variant;
+
+    struct Unreachable;
+
+    impl PaymentGateway for Unreachable {
+        fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+            Err(OrderError::PaymentUnavailable)
+        }
+    }
+
+    struct Declining;
+
+    impl PaymentGateway for Declining {
+        fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+            Err(OrderError::PaymentFailed)
+        }
+    }
+
+    #[test]
+    fn unreachable_provider_defers_the_charge() {
+        let pending = InMemoryPendingCharges::new();
+        let gateway = OfflineCapablePaymentGateway::new(Unreachable, &pending)
+            .with_console(Console::silent());
+
+        let outcome = gateway.charge_order(OrderId(3), Money(2500)).unwrap();
+
+        assert_eq!(outcome, ChargeOutcome::Deferred);
+        assert_eq!(
+            pending.pending().unwrap(),
+            vec![PendingCharge {
+                order_id: OrderId(3),
+                amount: Money(2500),
+            }]
+        );
+        // Settlement goes through charge(): no second record.
+        assert!(gateway.charge(Money(2500)).is_err());
+        assert_eq!(pending.pending().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn an_issuer_asking_to_try_later_defers_the_charge() {
+        let pending = InMemoryPendingCharges::new();
+        let later = |_: Money| {
+            Err(OrderError::PaymentDeclined {
+                reason: DeclineReason::TryAgainLater,
+            })
+        };
+        let gateway =
+            OfflineCapablePaymentGateway::new(later, &pending).with_console(Console::silent());
+
+        let outcome = gateway.charge_order(OrderId(3), Money(2500)).unwrap();
+
+        assert_eq!(outcome, ChargeOutcome::Deferred);
+        assert_eq!(pending.pending().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn declined_card_is_not_deferred() {
+        let pending = InMemoryPendingCharges::new();
+        let gateway = OfflineCapablePaymentGateway::new(Declining, &pending);
+
+        assert_err_variant!(
+            gateway.charge_order(OrderId(3), Money(2500)),
+            OrderError::PaymentFailed
+        );
+        assert!(pending.pending().unwrap().is_empty());
+    }
+}
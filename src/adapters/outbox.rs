@@ -0,0 +1,324 @@
+// --- Outbox sender (decorator) ---
+// Confirmations are written to an outbox instead of being sent on the spot,
+// and a dispatcher sends them later. A send that fails is not retried on
+// every run: the entry waits, longer after each failure (exponential
+// backoff), and after the last allowed attempt it is handed to the
+// DeadLetterSink instead of being tried forever.
+//
+// The application keeps calling Sender::send, which only queues. Sending is
+// up to whoever calls run_dispatcher(now): a scheduler, a loop in main, a
+// test with a SteppingClock. The Clock only dates new entries.
+//
+// An entry keeps the confirmation as its versioned envelope, the form it
+// would take on disk (see confirmation_envelope), and is read back with
+// parse_any_confirmation when it is due. Entries from an earlier release
+// (with_entries) are upgraded on the way out.
+//
+// With RetryPolicy::new(10s, 60s, 5 attempts), a confirmation queued at t:
+//
+//     attempt 1 at t        fails, next at t + 10
+//     attempt 2 at t + 10   fails, next at t + 30   (+ 20)
+//     attempt 3 at t + 30   fails, next at t + 70   (+ 40)
+//     attempt 4 at t + 70   fails, next at t + 130  (+ 80, capped to 60)
+//     attempt 5 at t + 130  fails, dead letter
+use super::{Console, WireError, confirmation_envelope, parse_any_confirmation};
+use crate::domain::{OrderConfirmation, OrderError, Timestamp};
+use crate::ports::{
+    Capability, Clock, DeadLetter, DeadLetterSink, Sender, Shutdown, ShutdownError,
+};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    base: Duration,
+    max_delay: Duration,
+    max_attempts: u32,
+}
+
+impl RetryPolicy {
+    // `max_attempts` counts the first send: 1 means no retry at all.
+    // Zero attempts makes no sense and panics.
+    pub fn new(base: Duration, max_delay: Duration, max_attempts: u32) -> Self {
+        assert!(
+            max_attempts > 0,
+            "a notification needs at least one attempt"
+        );
+        Self {
+            base,
+            max_delay,
+            max_attempts,
+        }
+    }
+
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    // How long to wait after a failure, `attempts` being the attempts that
+    // failed before this one: base * 2^attempts, never more than max_delay.
+    pub fn backoff(&self, attempts: u32) -> Duration {
+        2u32.checked_pow(attempts)
+            .and_then(|factor| self.base.checked_mul(factor))
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay))
+    }
+}
+
+// 30 seconds, doubling up to an hour, 8 attempts: a confirmation is given
+// up on a little over an hour after it was queued.
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(30), Duration::from_secs(60 * 60), 8)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OutboxEntry {
+    pub envelope: String,
+    // Failed attempts so far.
+    pub attempts: u32,
+    // Not sent by a dispatcher run before then.
+    pub next_attempt_at: Timestamp,
+    pub last_error: Option<OrderError>,
+}
+
+impl OutboxEntry {
+    // Due now, with nothing tried yet.
+    pub fn new(confirmation: &OrderConfirmation, now: Timestamp) -> Self {
+        Self {
+            envelope: confirmation_envelope(confirmation),
+            attempts: 0,
+            next_attempt_at: now,
+            last_error: None,
+        }
+    }
+
+    pub fn confirmation(&self) -> Result<OrderConfirmation, WireError> {
+        parse_any_confirmation(self.envelope.as_bytes())
+    }
+}
+
+// What one run_dispatcher did. Entries not due yet are `waiting`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DispatchReport {
+    pub sent: usize,
+    pub rescheduled: usize,
+    pub dead_lettered: usize,
+    pub waiting: usize,
+}
+
+pub struct OutboxSender<'a, S: Sender, C: Clock> {
+    inner: S,
+    clock: C,
+    dead_letters: &'a (dyn DeadLetterSink + Sync),
+    policy: RetryPolicy,
+    // In arrival order.
+    entries: Mutex<Vec<OutboxEntry>>,
+    console: Console,
+}
+
+impl<'a, S: Sender, C: Clock> OutboxSender<'a, S, C> {
+    pub fn new(inner: S, clock: C, dead_letters: &'a (dyn DeadLetterSink + Sync)) -> Self {
+        Self {
+            inner,
+            clock,
+            dead_letters,
+            policy: RetryPolicy::default(),
+            entries: Mutex::new(Vec::new()),
+            console: Console::stdout(),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: RetryPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    // What an earlier run left to send, e.g. read back from disk, ahead of
+    // anything queued from now on.
+    pub fn with_entries(self, entries: Vec<OutboxEntry>) -> Self {
+        *self.entries.lock().unwrap_or_else(PoisonError::into_inner) = entries;
+        self
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn clock(&self) -> &C {
+        &self.clock
+    }
+
+    // What is still to be sent, in arrival order.
+    pub fn entries(&self) -> Vec<OutboxEntry> {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    // Tries every entry due at `now`, in arrival order; the others keep
+    // waiting. An entry out of attempts goes to the dead letters. If the
+    // sink refuses it, the run stops with that error and the entry stays,
+    // to be handed over again, without another send, on the next run. An
+    // entry whose envelope cannot be read, written by a newer release,
+    // stops the run the same way, and stays for that release to send.
+    pub fn run_dispatcher(&self, now: Timestamp) -> Result<DispatchReport, OrderError> {
+        let mut entries = self.entries.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut report = DispatchReport::default();
+        let mut i = 0;
+        while i < entries.len() {
+            let entry = &mut entries[i];
+            if entry.next_attempt_at > now {
+                report.waiting += 1;
+                i += 1;
+                continue;
+            }
+            let confirmation = entry
+                .confirmation()
+                .map_err(|e| OrderError::custom(format!("unreadable outbox entry: {e}")))?;
+            if entry.attempts < self.policy.max_attempts {
+                match self.inner.send(&confirmation) {
+                    Ok(()) => {
+                        self.console.line(format_args!(
+                            "  [Outbox] Sent confirmation for order {:?}",
+                            confirmation.order_id
+                        ));
+                        entries.remove(i);
+                        report.sent += 1;
+                        continue;
+                    }
+                    Err(error) => {
+                        let delay = self.policy.backoff(entry.attempts);
+                        entry.attempts += 1;
+                        entry.last_error = Some(error);
+                        if entry.attempts < self.policy.max_attempts {
+                            entry.next_attempt_at = now.plus_secs(delay.as_secs());
+                            self.console.line(format_args!(
+                                "  [Outbox] Attempt {} for order {:?} failed, next at {}",
+                                entry.attempts, confirmation.order_id, entry.next_attempt_at.0
+                            ));
+                            report.rescheduled += 1;
+                            i += 1;
+                            continue;
+                        }
+                    }
+                }
+            }
+            let order_id = confirmation.order_id;
+            let letter = DeadLetter {
+                confirmation,
+                attempts: entry.attempts,
+                last_error: entry
+                    .last_error
+                    .clone()
+                    .unwrap_or(OrderError::NotificationFailed),
+                failed_at: now,
+            };
+            self.dead_letters.dead_letter(letter)?;
+            self.console.line(format_args!(
+                "  [Outbox] Gave up on order {:?} after {} attempt(s)",
+                order_id, entry.attempts
+            ));
+            entries.remove(i);
+            report.dead_lettered += 1;
+        }
+        Ok(report)
+    }
+}
+
+impl<S: Sender, C: Clock> Sender for OutboxSender<'_, S, C> {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.console.line(format_args!(
+            "  [Outbox] Queued confirmation for order {:?}",
+            confirmation.order_id
+        ));
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(OutboxEntry::new(confirmation, self.clock.now()));
+        Ok(())
+    }
+}
+
+impl<S: Sender, C: Clock> Capability for OutboxSender<'_, S, C> {}
+
+// One last run, at the clock's time. What is not due yet, or failed again,
+// is abandoned: still in entries(), for a deployment keeping its outbox on
+// disk to write out, but this process sends no more.
+impl<S: Sender, C: Clock> Shutdown for OutboxSender<'_, S, C> {
+    fn shutdown(&mut self) -> Result<(), ShutdownError> {
+        self.run_dispatcher(self.clock.now())?;
+        match self
+            .entries
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+        {
+            0 => Ok(()),
+            count => Err(ShutdownError::Abandoned {
+                count,
+                what: "confirmations",
+            }),
+        }
+    }
+}
+
+// The DeadLetterSink for tests and single-process deployments: the letters
+// are only kept until the process ends.
+#[derive(Default)]
+pub struct InMemoryDeadLetters {
+    letters: Mutex<Vec<DeadLetter>>,
+}
+
+impl InMemoryDeadLetters {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn letters(&self) -> Vec<DeadLetter> {
+        self.letters
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+}
+
+impl DeadLetterSink for InMemoryDeadLetters {
+    fn dead_letter(&self, letter: DeadLetter) -> Result<(), OrderError> {
+        self.letters
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(letter);
+        Ok(())
+    }
+}
+
+impl Capability for InMemoryDeadLetters {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_the_cap() {
+        let policy = RetryPolicy::new(Duration::from_secs(10), Duration::from_secs(60), 5);
+        let delays: Vec<u64> = (0..5).map(|n| policy.backoff(n).as_secs()).collect();
+
+        assert_eq!(delays, [10, 20, 40, 60, 60]);
+        // 2^40 overflows u32: the cap, not a panic.
+        assert_eq!(policy.backoff(40), Duration::from_secs(60));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(60));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one attempt")]
+    fn zero_attempts_is_a_bug() {
+        RetryPolicy::new(Duration::from_secs(1), Duration::from_secs(1), 0);
+    }
+}
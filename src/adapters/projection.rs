@@ -0,0 +1,132 @@
+// --- Delayed projection ---
+// A read model fed events the way a real one is: some time after they were
+// published. In-process, a read model sees every event before publish
+// returns, which hides what a user of a read side meets every day: the
+// order just amended, listed with its old total.
+//
+// DelayedProjection is the EventPublisher the service publishes to. It
+// stamps each event with the clock when it arrives and keeps it, applying
+// nothing until told to: advance(n) applies the n oldest, advance_until(t)
+// every one received by t. Events are applied in the order they were
+// published, whatever the steps they are applied in, and freshness says
+// how far behind the model is meanwhile.
+use crate::domain::{OrderError, OrderEvent, Timestamp};
+use crate::ports::{Capability, Clock, EventPublisher, EventSubscriber, Freshness};
+use std::collections::VecDeque;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+pub struct DelayedProjection<'a, S: EventSubscriber> {
+    model: Mutex<S>,
+    pending: Mutex<VecDeque<(Timestamp, OrderEvent)>>,
+    clock: &'a (dyn Clock + Sync),
+}
+
+impl<'a, S: EventSubscriber> DelayedProjection<'a, S> {
+    pub fn new(model: S, clock: &'a (dyn Clock + Sync)) -> Self {
+        Self {
+            model: Mutex::new(model),
+            pending: Mutex::new(VecDeque::new()),
+            clock,
+        }
+    }
+
+    // The model as it is now, stale or not. Held, it keeps events from
+    // being applied.
+    pub fn model(&self) -> MutexGuard<'_, S> {
+        self.model.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    // The number applied: fewer than `n` when fewer are pending.
+    pub fn advance(&self, n: usize) -> usize {
+        self.apply_while(|applied, _| applied < n)
+    }
+
+    // Applies every event received at or before `now`.
+    pub fn advance_until(&self, now: Timestamp) -> usize {
+        self.apply_while(|_, received| received <= now)
+    }
+
+    // Applies everything pending.
+    pub fn catch_up(&self) -> usize {
+        self.advance(usize::MAX)
+    }
+
+    pub fn freshness(&self) -> Freshness {
+        let pending = self.pending();
+        Freshness {
+            pending_events: pending.len(),
+            oldest_pending: pending.front().map(|(received, _)| *received),
+        }
+    }
+
+    // The model's lock is taken first and held throughout, so that two
+    // threads advancing at once still apply the events in order.
+    fn apply_while(&self, mut more: impl FnMut(usize, Timestamp) -> bool) -> usize {
+        let mut model = self.model();
+        let mut applied = 0;
+        loop {
+            let next = {
+                let mut pending = self.pending();
+                match pending.front() {
+                    Some(&(received, _)) if more(applied, received) => pending.pop_front(),
+                    _ => None,
+                }
+            };
+            let Some((_, event)) = next else {
+                return applied;
+            };
+            model.on_event(&event);
+            applied += 1;
+        }
+    }
+
+    fn pending(&self) -> MutexGuard<'_, VecDeque<(Timestamp, OrderEvent)>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<S: EventSubscriber> EventPublisher for DelayedProjection<'_, S> {
+    fn publish(&self, event: &OrderEvent) -> Result<(), OrderError> {
+        let received = self.clock.now();
+        self.pending().push_back((received, event.clone()));
+        Ok(())
+    }
+}
+
+impl<S: EventSubscriber> Capability for DelayedProjection<'_, S> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{Money, OrderId};
+    use crate::testing::SteppingClock;
+
+    #[derive(Default)]
+    struct Seen(Vec<OrderId>);
+
+    impl EventSubscriber for Seen {
+        fn on_event(&mut self, event: &OrderEvent) {
+            self.0.push(event.order_id());
+        }
+    }
+
+    #[test]
+    fn events_are_applied_in_the_order_published_whatever_the_steps() {
+        let clock = SteppingClock::starting_at(Timestamp(100));
+        let projection = DelayedProjection::new(Seen::default(), &clock);
+        for id in 1..=5 {
+            projection
+                .publish(&OrderEvent::OrderPaid {
+                    order_id: OrderId(id),
+                    amount: Money(150),
+                })
+                .unwrap();
+        }
+
+        assert_eq!(projection.advance(2), 2);
+        assert_eq!(projection.advance(0), 0);
+        assert_eq!(projection.catch_up(), 3);
+        assert_eq!(projection.advance(1), 0);
+        assert_eq!(projection.model().0, [1, 2, 3, 4, 5].map(OrderId).to_vec());
+    }
+}
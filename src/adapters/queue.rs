@@ -0,0 +1,176 @@
+// --- Queue consumer (driving adapter) ---
+// Not every request arrives through a function call. Here commands arrive on
+// a channel, as they would from a message broker, and a consumer thread
+// drives the hexagon through the PlaceOrderUseCase input port.
+//
+// The consumer knows nothing about repositories, payments or senders.
+// It only turns messages into use case calls and results into responses.
+use crate::domain::{Order, OrderError};
+use crate::ports::{PlaceOrder, PlaceOrderUseCase};
+use std::sync::mpsc::{Receiver, Sender};
+use std::thread::{self, JoinHandle, Scope, ScopedJoinHandle};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueueMessage {
+    Place(PlaceOrder),
+    // Stop after the messages already queued before this one.
+    Shutdown,
+}
+
+pub type QueueResponse = Result<Order, OrderError>;
+
+pub struct ChannelConsumer<S: PlaceOrderUseCase> {
+    service: S,
+    commands: Receiver<QueueMessage>,
+    responses: Sender<QueueResponse>,
+}
+
+impl<S: PlaceOrderUseCase> ChannelConsumer<S> {
+    pub fn new(
+        service: S,
+        commands: Receiver<QueueMessage>,
+        responses: Sender<QueueResponse>,
+    ) -> Self {
+        Self {
+            service,
+            commands,
+            responses,
+        }
+    }
+
+    // Processes messages until Shutdown arrives or every producer is gone,
+    // then hands the service back so the caller can inspect it.
+    // A dropped response receiver is not an error: nobody is listening,
+    // but the orders must still be placed.
+    pub fn run(mut self) -> S {
+        while let Ok(QueueMessage::Place(command)) = self.commands.recv() {
+            let result = self.service.place_order(command);
+            let _ = self.responses.send(result);
+        }
+        self.service
+    }
+}
+
+// Runs the consumer on its own thread.
+// The service must own its adapters ('static). For a service borrowing
+// its adapters, like OrderService, use spawn_scoped.
+pub fn spawn<S>(
+    service: S,
+    commands: Receiver<QueueMessage>,
+    responses: Sender<QueueResponse>,
+) -> JoinHandle<S>
+where
+    S: PlaceOrderUseCase + Send + 'static,
+{
+    thread::spawn(move || ChannelConsumer::new(service, commands, responses).run())
+}
+
+// Same as spawn, inside a std::thread::scope so the service may borrow.
+pub fn spawn_scoped<'scope, 'env, S>(
+    scope: &'scope Scope<'scope, 'env>,
+    service: S,
+    commands: Receiver<QueueMessage>,
+    responses: Sender<QueueResponse>,
+) -> ScopedJoinHandle<'scope, S>
+where
+    S: PlaceOrderUseCase + Send + 'scope,
+{
+    scope.spawn(move || ChannelConsumer::new(service, commands, responses).run())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::in_memory::{ConsoleSender, InMemoryOrderRepository, MockPaymentGateway};
+    use crate::application::OrderService;
+    use crate::assert_err_variant;
+    use crate::domain::{LineItem, Money, OrderId};
+    use crate::testing::assert_order;
+    use std::sync::mpsc;
+
+    fn command(prices: &[u32]) -> QueueMessage {
+        QueueMessage::Place(PlaceOrder {
+            items: prices
+                .iter()
+                .map(|&price| LineItem {
+                    name: format!("Item {price}"),
+                    price: Money(price),
+                })
+                .collect(),
+            placed_at: None,
+        })
+    }
+
+    #[test]
+    fn consumer_places_orders_and_reports_errors() {
+        let repo = InMemoryOrderRepository::new();
+        let (command_tx, command_rx) = mpsc::channel();
+        let (response_tx, response_rx) = mpsc::channel();
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+
+        thread::scope(|scope| {
+            let service = OrderService::new(&repo, &payment, &sender);
+            let consumer = spawn_scoped(scope, service, command_rx, response_tx);
+
+            command_tx.send(command(&[4999])).unwrap();
+            command_tx.send(command(&[])).unwrap(); // invalid: empty cart
+            command_tx.send(command(&[1000, 2000])).unwrap();
+            command_tx.send(QueueMessage::Shutdown).unwrap();
+
+            consumer.join().unwrap();
+        });
+
+        let responses: Vec<QueueResponse> = response_rx.iter().collect();
+        assert_eq!(responses.len(), 3);
+        assert_order(responses[0].as_ref().unwrap()).has_total_cents(4999);
+        assert_err_variant!(&responses[1], OrderError::InvalidOrder);
+        assert_order(responses[2].as_ref().unwrap()).has_total_cents(3000);
+
+        // Ids 1 and 3: the rejected cart still consumed id 2.
+        let service = OrderService::new(&repo, &payment, &sender);
+        assert!(service.get_order(OrderId(1)).unwrap().is_some());
+        assert!(service.get_order(OrderId(2)).unwrap().is_none());
+        assert!(service.get_order(OrderId(3)).unwrap().is_some());
+    }
+
+    // An owned use case, so the plain 'static spawn can be used.
+    struct CountingUseCase {
+        placed: u32,
+    }
+
+    impl PlaceOrderUseCase for CountingUseCase {
+        fn place_order(&mut self, command: PlaceOrder) -> Result<Order, OrderError> {
+            self.placed += 1;
+            Order::new(OrderId(self.placed), command.items)
+        }
+    }
+
+    #[test]
+    fn consumer_stops_when_producers_are_gone() {
+        let (command_tx, command_rx) = mpsc::channel();
+        let (response_tx, response_rx) = mpsc::channel();
+        let consumer = spawn(CountingUseCase { placed: 0 }, command_rx, response_tx);
+
+        command_tx.send(command(&[100])).unwrap();
+        command_tx.send(command(&[200])).unwrap();
+        drop(command_tx);
+
+        let use_case = consumer.join().unwrap();
+        assert_eq!(use_case.placed, 2);
+        assert_eq!(response_rx.iter().count(), 2);
+    }
+
+    #[test]
+    fn messages_after_shutdown_are_not_processed() {
+        let (command_tx, command_rx) = mpsc::channel();
+        let (response_tx, _) = mpsc::channel(); // nobody listens to responses
+        let consumer = spawn(CountingUseCase { placed: 0 }, command_rx, response_tx);
+
+        command_tx.send(command(&[100])).unwrap();
+        command_tx.send(QueueMessage::Shutdown).unwrap();
+        let _ = command_tx.send(command(&[200]));
+
+        assert_eq!(consumer.join().unwrap().placed, 1);
+    }
+}
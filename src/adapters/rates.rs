@@ -0,0 +1,119 @@
+// --- Fixed exchange rates ---
+// A rate table set at start-up, e.g. from the config file.
+// Good enough for a shop that reprices once a day, and for tests.
+// Converted amounts are rounded half up, unless told otherwise.
+use crate::domain::{Currency, ExchangeRate, Money, OrderError, Price, RoundingStrategy};
+use crate::ports::{Capability, ExchangeRates};
+use std::collections::HashMap;
+
+#[derive(Default)]
+pub struct FixedRates {
+    rates: HashMap<(Currency, Currency), ExchangeRate>,
+    rounding: RoundingStrategy,
+}
+
+impl FixedRates {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // One direction only: EUR -> USD says nothing about USD -> EUR.
+    pub fn with_rate(mut self, from: Currency, to: Currency, rate: ExchangeRate) -> Self {
+        self.rates.insert((from, to), rate);
+        self
+    }
+
+    pub fn with_rounding(mut self, rounding: RoundingStrategy) -> Self {
+        self.rounding = rounding;
+        self
+    }
+}
+
+impl ExchangeRates for FixedRates {
+    fn convert(&self, from: Price, to: Currency) -> Result<Money, OrderError> {
+        let rate = if from.currency == to {
+            ExchangeRate::IDENTITY
+        } else {
+            *self
+                .rates
+                .get(&(from.currency, to))
+                .ok_or(OrderError::NoExchangeRate {
+                    from: from.currency,
+                    to,
+                })?
+        };
+        rate.convert(from.amount, from.currency, to, self.rounding)
+            .ok_or(OrderError::InvalidOrder)
+    }
+}
+
+impl Capability for FixedRates {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_err_variant;
+
+    fn eur(cents: u32) -> Price {
+        Price {
+            amount: Money(cents),
+            currency: Currency::Eur,
+        }
+    }
+
+    #[test]
+    fn converts_with_the_configured_rate() {
+        let rates =
+            FixedRates::new().with_rate(Currency::Eur, Currency::Usd, ExchangeRate(108_500_000));
+
+        assert_eq!(
+            rates.convert(eur(10_000), Currency::Usd).unwrap(),
+            Money(10_850)
+        );
+        assert_eq!(
+            rates.convert(eur(4999), Currency::Eur).unwrap(),
+            Money(4999)
+        );
+    }
+
+    #[test]
+    fn converts_between_currencies_of_different_minor_units() {
+        let rates = FixedRates::new()
+            .with_rate(Currency::Eur, Currency::Jpy, ExchangeRate(16_000_000_000))
+            .with_rate(Currency::Jpy, Currency::Kwd, ExchangeRate(204_817));
+
+        // 12.50 EUR at 160.00: ¥2000.
+        assert_eq!(
+            rates.convert(eur(1_250), Currency::Jpy).unwrap(),
+            Money(2_000)
+        );
+        // ¥625 at 0.00204817 is KD 1.28010625: KD 1.280.
+        let yen = Money(625).in_currency(Currency::Jpy);
+        assert_eq!(rates.convert(yen, Currency::Kwd).unwrap(), Money(1_280));
+    }
+
+    #[test]
+    fn a_yen_rate_keeps_all_its_digits() {
+        // ¥12 345 at 0.00667234 is 82.3700373 USD. At four decimals the
+        // rate would be 0.0067, and the same yen 82.71 USD.
+        let rates =
+            FixedRates::new().with_rate(Currency::Jpy, Currency::Usd, ExchangeRate(667_234));
+        let yen = Money(12_345).in_currency(Currency::Jpy);
+
+        assert_eq!(rates.convert(yen, Currency::Usd).unwrap(), Money(8_237));
+    }
+
+    #[test]
+    fn missing_pair_is_an_error() {
+        let rates =
+            FixedRates::new().with_rate(Currency::Eur, Currency::Usd, ExchangeRate(108_500_000));
+
+        assert_err_variant!(
+            rates.convert(eur(100), Currency::Gbp),
+            OrderError::NoExchangeRate {
+                from: Currency::Eur,
+                to: Currency::Gbp
+            }
+        );
+    }
+}
@@ -0,0 +1,313 @@
+// --- Order references ---
+// Three ways of numbering orders for people, each a ReferenceGenerator:
+//
+//     YearSequence     2024-000042   counted from 1 again every year
+//     PrefixedRandom   ORD-7F3K9Q    six characters nobody misreads
+//     Checksummed      2024-000042C  either of them, and a check character
+//
+// Years are UTC's, as read from the Clock. A sequence lives in memory: a
+// restarted process carries on with starting_after, from the last
+// reference it finds in its repository.
+//
+// PrefixedRandom does not draw its codes: it shuffles a counter, with keys
+// from std's RandomState, so that no code comes back before all of them
+// were handed out. Unguessable enough for a reference, not for a secret.
+// Where std has no randomness to seed RandomState with, every generator
+// shuffles the same way.
+use crate::domain::{OrderReference, Timestamp};
+use crate::ports::{Capability, Clock, ReferenceGenerator};
+use std::hash::{BuildHasher, RandomState};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Mutex, PoisonError};
+
+// No 0 nor O, no 1, I nor L, no U to take for a V.
+const UNAMBIGUOUS: &[u8; 30] = b"23456789ABCDEFGHJKMNPQRSTVWXYZ";
+const CODE_LENGTH: usize = 6;
+// 30^6.
+const CODES: u64 = 729_000_000;
+// Crockford's base 32, which the check character is taken from.
+const CHECK_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+pub struct YearSequence<'a> {
+    clock: &'a (dyn Clock + Sync),
+    // The year of the last reference, and its number in that year.
+    last: Mutex<(u64, u64)>,
+}
+
+impl<'a> YearSequence<'a> {
+    pub fn new(clock: &'a (dyn Clock + Sync)) -> Self {
+        Self {
+            clock,
+            last: Mutex::new((0, 0)),
+        }
+    }
+
+    // The next reference is `count + 1` of `year`, or the first of a later
+    // year.
+    pub fn starting_after(self, year: u64, count: u64) -> Self {
+        *self.last.lock().unwrap_or_else(PoisonError::into_inner) = (year, count);
+        self
+    }
+}
+
+impl ReferenceGenerator for YearSequence<'_> {
+    fn next_reference(&self) -> OrderReference {
+        let mut last = self.last.lock().unwrap_or_else(PoisonError::into_inner);
+        // A clock put back over new year goes on counting in the new one,
+        // rather than handing out last year's numbers again.
+        let year = year_of(self.clock.now()).max(last.0);
+        let count = if year == last.0 { last.1 + 1 } else { 1 };
+        *last = (year, count);
+        OrderReference(format!("{year}-{count:06}"))
+    }
+}
+
+impl Capability for YearSequence<'_> {}
+
+#[derive(Debug)]
+pub struct PrefixedRandom {
+    prefix: String,
+    keys: RandomState,
+    counter: AtomicU64,
+}
+
+impl PrefixedRandom {
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            keys: RandomState::new(),
+            counter: AtomicU64::new(0),
+        }
+    }
+
+    // A permutation of 0..CODES: four Feistel rounds over 30 bits, walked
+    // again while the result is past the last code.
+    fn shuffle(&self, n: u64) -> u64 {
+        let mut code = n;
+        loop {
+            let (mut left, mut right) = (code >> 15, code & 0x7fff);
+            for round in 0..4u8 {
+                let mixed = self.keys.hash_one((round, right)) & 0x7fff;
+                (left, right) = (right, left ^ mixed);
+            }
+            code = left << 15 | right;
+            if code < CODES {
+                return code;
+            }
+        }
+    }
+}
+
+impl ReferenceGenerator for PrefixedRandom {
+    // The first comes back after CODES of them.
+    fn next_reference(&self) -> OrderReference {
+        let n = self.counter.fetch_add(1, Ordering::Relaxed) % CODES;
+        let mut code = self.shuffle(n);
+        let mut characters = [0u8; CODE_LENGTH];
+        for character in characters.iter_mut().rev() {
+            *character = UNAMBIGUOUS[(code % 30) as usize];
+            code /= 30;
+        }
+        let code = String::from_utf8_lossy(&characters);
+        OrderReference(format!("{}-{code}", self.prefix))
+    }
+}
+
+impl Capability for PrefixedRandom {}
+
+// Another generator's references, with a check character on the end: Luhn
+// mod 32 over the letters and digits of Crockford's alphabet, the others
+// (hyphens, and the I, L, O, U of a prefix) left out. One character typed
+// wrong and validate says no; two next to each other swapped also, unless
+// they are a 0 and a Z.
+pub struct Checksummed<G> {
+    inner: G,
+}
+
+impl<G> Checksummed<G> {
+    pub fn new(inner: G) -> Self {
+        Self { inner }
+    }
+
+    pub fn inner(&self) -> &G {
+        &self.inner
+    }
+
+    // Whether the last character is the one the rest calls for. Either
+    // case is accepted, as people type them.
+    pub fn validate(&self, reference: &OrderReference) -> bool {
+        let Some(check) = reference.0.chars().next_back() else {
+            return false;
+        };
+        let codes: Vec<u32> = checked_codes(&reference.0).collect();
+        code_of(check).is_some() && codes.len() > 1 && luhn_sum(&codes, false).is_multiple_of(32)
+    }
+}
+
+impl<G: ReferenceGenerator> ReferenceGenerator for Checksummed<G> {
+    fn next_reference(&self) -> OrderReference {
+        let OrderReference(mut text) = self.inner.next_reference();
+        let codes: Vec<u32> = checked_codes(&text).collect();
+        let check = (32 - luhn_sum(&codes, true) % 32) % 32;
+        text.push(char::from(CHECK_ALPHABET[check as usize]));
+        OrderReference(text)
+    }
+}
+
+impl<G> Capability for Checksummed<G> {}
+
+fn code_of(character: char) -> Option<u32> {
+    let upper = character.to_ascii_uppercase();
+    CHECK_ALPHABET
+        .iter()
+        .position(|&c| char::from(c) == upper)
+        .map(|at| at as u32)
+}
+
+fn checked_codes(text: &str) -> impl Iterator<Item = u32> + '_ {
+    text.chars().filter_map(code_of)
+}
+
+// From the right, every other code doubled, starting with the first when
+// the check character is still to come.
+fn luhn_sum(codes: &[u32], double_first: bool) -> u32 {
+    codes
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(at, &code)| {
+            let addend = if (at % 2 == 0) == double_first {
+                code * 2
+            } else {
+                code
+            };
+            addend / 32 + addend % 32
+        })
+        .sum()
+}
+
+// The proleptic Gregorian year, from the days since 1970 (Howard Hinnant's
+// civil_from_days, for dates after the epoch only).
+fn year_of(at: Timestamp) -> u64 {
+    let days = at.0 / 86_400 + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let march_based_month = (5 * day_of_year + 2) / 153;
+    let year = year_of_era + era * 400;
+    // January and February belong to the year after the one they start in.
+    if march_based_month >= 10 {
+        year + 1
+    } else {
+        year
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::SteppingClock;
+    use std::collections::HashSet;
+
+    // 2024-12-31T23:59:59Z.
+    const LAST_SECOND_OF_2024: Timestamp = Timestamp(1_735_689_599);
+
+    fn text(generator: &impl ReferenceGenerator) -> String {
+        generator.next_reference().0
+    }
+
+    #[test]
+    fn years_start_and_end_where_the_calendar_says() {
+        assert_eq!(year_of(Timestamp(0)), 1970);
+        assert_eq!(year_of(LAST_SECOND_OF_2024), 2024);
+        assert_eq!(year_of(LAST_SECOND_OF_2024.plus_secs(1)), 2025);
+        // 2024-02-29T12:00:00Z.
+        assert_eq!(year_of(Timestamp(1_709_208_000)), 2024);
+        // 2000-03-01T00:00:00Z, after a leap day every 400 years.
+        assert_eq!(year_of(Timestamp(951_868_800)), 2000);
+    }
+
+    #[test]
+    fn the_sequence_starts_again_at_new_year() {
+        let clock = SteppingClock::starting_at(LAST_SECOND_OF_2024.minus_secs(1));
+        let sequence = YearSequence::new(&clock);
+        assert_eq!(text(&sequence), "2024-000001");
+        assert_eq!(text(&sequence), "2024-000002");
+
+        clock.advance_secs(2);
+        assert_eq!(text(&sequence), "2025-000001");
+
+        // Put back an hour, it carries on in 2025.
+        let clock = SteppingClock::starting_at(LAST_SECOND_OF_2024.minus_secs(3_600));
+        let resumed = YearSequence::new(&clock).starting_after(2025, 41);
+        assert_eq!(text(&resumed), "2025-000042");
+    }
+
+    #[test]
+    fn codes_use_the_prefix_and_only_unambiguous_characters() {
+        let random = PrefixedRandom::new("ORD");
+        for _ in 0..100 {
+            let reference = text(&random);
+            let code = reference.strip_prefix("ORD-").unwrap();
+            assert_eq!(code.len(), CODE_LENGTH);
+            assert!(code.bytes().all(|c| UNAMBIGUOUS.contains(&c)), "{code}");
+        }
+    }
+
+    #[test]
+    fn the_check_character_catches_every_single_digit_typo() {
+        let clock = SteppingClock::starting_at(LAST_SECOND_OF_2024);
+        let checked = Checksummed::new(YearSequence::new(&clock).starting_after(2024, 4_199));
+        for _ in 0..50 {
+            let reference = checked.next_reference();
+            assert!(checked.validate(&reference), "{reference}");
+            for (at, digit) in reference
+                .0
+                .char_indices()
+                .filter(|(_, c)| c.is_ascii_digit())
+            {
+                for typo in ('0'..='9').filter(|&typo| typo != digit) {
+                    let mut wrong = reference.0.clone();
+                    wrong.replace_range(at..at + 1, typo.encode_utf8(&mut [0; 4]));
+                    assert!(
+                        !checked.validate(&OrderReference(wrong.clone())),
+                        "{wrong} passed for {reference}"
+                    );
+                }
+            }
+        }
+        assert!(!checked.validate(&OrderReference(String::new())));
+        assert!(!checked.validate(&OrderReference("2024-000001-".to_string())));
+    }
+
+    #[test]
+    fn random_codes_check_out_in_either_case() {
+        let checked = Checksummed::new(PrefixedRandom::new("ORD"));
+        let reference = checked.next_reference();
+        assert_eq!(reference.0.len(), "ORD-7F3K9Q".len() + 1);
+        assert!(checked.validate(&reference));
+        assert!(checked.validate(&OrderReference(reference.0.to_lowercase())));
+    }
+
+    #[test]
+    fn ten_thousand_references_are_all_different_whatever_the_scheme() {
+        let clock = SteppingClock::starting_at(LAST_SECOND_OF_2024.minus_secs(5_000));
+        let schemes: [&dyn ReferenceGenerator; 4] = [
+            &YearSequence::new(&clock),
+            &PrefixedRandom::new("ORD"),
+            &Checksummed::new(YearSequence::new(&clock)),
+            &Checksummed::new(PrefixedRandom::new("ORD")),
+        ];
+        for scheme in schemes {
+            let references: HashSet<String> = (0..10_000)
+                .map(|_| {
+                    clock.advance_secs(1);
+                    scheme.next_reference().0
+                })
+                .collect();
+            assert_eq!(references.len(), 10_000);
+        }
+    }
+}
@@ -0,0 +1,324 @@
+// --- Interactive shell (driving adapter) ---
+// A read-eval-print loop over an OrderFacade, and nothing else: every
+// command goes through it, so the shell never sees a repository or a
+// gateway.
+//
+//     orders> order add "USB cable" 999
+//     added USB cable $9.99, 1 item in the cart for $9.99
+//     orders> order checkout
+//     ok #000001 Paid $9.99
+//
+// The cart being filled belongs to the session, not to the application:
+// checkout hands it to the service, and the next cart starts empty whether
+// or not it became an order. End of input (Ctrl-D at a terminal)
+// ends the session.
+//
+// Every command runs under a child of the session's CancelToken: cancel
+// the session and the report under way stops. with_interrupt hands each
+// command's token to whatever stops it on demand; with the "ctrl-c"
+// feature, adapters::ctrl_c::arm turns Ctrl-C into that.
+use super::inbound::{self, MAX_ITEMS};
+use crate::domain::{LineItem, Money, OrderError, OrderId, OrderStatus};
+use crate::ports::{CancelToken, OrderFacade, PlaceOrder};
+use std::io::{self, BufRead, Write};
+
+pub const PROMPT: &str = "orders> ";
+
+pub const USAGE: &str = "\
+commands:
+  order add <name> <price in cents>   put an item in the cart; quote a name with spaces
+  order checkout                      place the cart as an order
+  order list                          every order, by id
+  order cancel <id>                   cancel an order, refunding it if paid
+  report revenue                      what paid and shipped orders brought in
+  health                              whether the orders can be read
+  help status                         where an order may go from each status
+  quit                                end the session, like end of input";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Command {
+    Add(LineItem),
+    Checkout,
+    List,
+    Cancel(OrderId),
+    Revenue,
+    Health,
+    HelpStatus,
+    Quit,
+}
+
+pub struct Repl<F: OrderFacade> {
+    facade: F,
+    cart: Vec<LineItem>,
+    quit: bool,
+    cancel: CancelToken,
+    // Given the running command's token, then None once it is done.
+    interrupt: Option<fn(Option<&CancelToken>)>,
+}
+
+impl<F: OrderFacade> Repl<F> {
+    pub fn new(facade: F) -> Self {
+        Self {
+            facade,
+            cart: Vec::new(),
+            quit: false,
+            cancel: CancelToken::new(),
+            interrupt: None,
+        }
+    }
+
+    pub fn with_cancel_token(mut self, cancel: CancelToken) -> Self {
+        self.cancel = cancel;
+        self
+    }
+
+    pub fn with_interrupt(mut self, interrupt: fn(Option<&CancelToken>)) -> Self {
+        self.interrupt = Some(interrupt);
+        self
+    }
+
+    pub fn into_inner(self) -> F {
+        self.facade
+    }
+
+    pub fn cart(&self) -> &[LineItem] {
+        &self.cart
+    }
+
+    // What the shell answers to one line: None for a blank one. Anything it
+    // cannot make sense of is answered with the usage.
+    pub fn eval(&mut self, line: &str) -> Option<String> {
+        let words = tokenize(line);
+        if words.as_ref().is_ok_and(Vec::is_empty) {
+            return None;
+        }
+        let reply = match words.ok().and_then(|words| parse(&words)) {
+            Some(command) => {
+                let cancel = self.cancel.child();
+                if let Some(interrupt) = self.interrupt {
+                    interrupt(Some(&cancel));
+                }
+                let reply = self.execute(command, &cancel);
+                if let Some(interrupt) = self.interrupt {
+                    interrupt(None);
+                }
+                reply
+            }
+            None => USAGE.to_string(),
+        };
+        Some(reply)
+    }
+
+    // Prompts, reads and answers until end of input or quit. Only I/O
+    // errors stop it early.
+    pub fn run(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        let mut line = String::new();
+        loop {
+            write!(output, "{PROMPT}")?;
+            output.flush()?;
+            line.clear();
+            if input.read_line(&mut line)? == 0 {
+                writeln!(output)?;
+                return Ok(());
+            }
+            if let Some(reply) = self.eval(&line) {
+                writeln!(output, "{reply}")?;
+            }
+            if self.quit {
+                return Ok(());
+            }
+        }
+    }
+
+    fn execute(&mut self, command: Command, cancel: &CancelToken) -> String {
+        match command {
+            Command::Add(item) => {
+                if self.cart.len() == MAX_ITEMS {
+                    return format!("error {}", OrderError::InvalidOrder);
+                }
+                let added = format!("added {} {}", item.name, item.price);
+                self.cart.push(item);
+                let cents: u64 = self.cart.iter().map(|item| u64::from(item.price.0)).sum();
+                let total = Money(u32::try_from(cents).unwrap_or(u32::MAX));
+                let count = self.cart.len();
+                let items = if count == 1 { "item" } else { "items" };
+                format!("{added}, {count} {items} in the cart for {total}")
+            }
+            Command::Checkout => {
+                let command = PlaceOrder {
+                    items: std::mem::take(&mut self.cart),
+                    placed_at: None,
+                };
+                match self.facade.place_order(command) {
+                    Ok(order) => format!("ok {} {} {}", order.id, order.status, order.total),
+                    Err(error) => format!("error {error}"),
+                }
+            }
+            Command::List => match self.facade.list_orders() {
+                Ok(orders) if orders.is_empty() => "no orders".to_string(),
+                Ok(orders) => orders
+                    .iter()
+                    .map(|order| {
+                        format!(
+                            "{}  {:<15}  {:>10}  {} item(s)",
+                            order.id,
+                            order.status,
+                            order.total,
+                            order.items.len()
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join("\n"),
+                Err(error) => format!("error {error}"),
+            },
+            Command::Cancel(id) => match self.facade.cancel_order(id) {
+                Ok(order) => format!("ok {} {}", order.id, order.status),
+                Err(error) => format!("error {error}"),
+            },
+            Command::Revenue => match self.facade.revenue_cancellable(cancel) {
+                Ok(revenue) => format!("revenue {revenue}"),
+                Err(error) => format!("error {error}"),
+            },
+            Command::Health => match self.facade.health() {
+                Ok(()) => "ok".to_string(),
+                Err(error) => format!("error {error}"),
+            },
+            Command::HelpStatus => status_help(),
+            Command::Quit => {
+                self.quit = true;
+                "bye".to_string()
+            }
+        }
+    }
+}
+
+// One line per status, read from the domain's transition table:
+//     Paid             -> Shipped, Cancelled
+//     Shipped          final
+fn status_help() -> String {
+    OrderStatus::ALL
+        .iter()
+        .map(|status| {
+            let to: Vec<String> = status
+                .allowed_from()
+                .iter()
+                .map(|to| to.to_string())
+                .collect();
+            if to.is_empty() {
+                format!("{status:<16} final")
+            } else {
+                format!("{status:<16} -> {}", to.join(", "))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+// Words separated by whitespace; double quotes keep a name with spaces in
+// one word. An unterminated quote is an error.
+pub fn tokenize(line: &str) -> Result<Vec<String>, OrderError> {
+    let mut words = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+            continue;
+        }
+        let mut word = String::new();
+        if c == '"' {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some(c) => word.push(c),
+                    None => return Err(OrderError::InvalidOrder),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                word.push(c);
+            }
+        }
+        words.push(word);
+    }
+    Ok(words)
+}
+
+// None for anything that is not a command. Item names and prices follow
+// the same limits as every other driving adapter.
+pub fn parse(words: &[String]) -> Option<Command> {
+    let words: Vec<&str> = words.iter().map(String::as_str).collect();
+    let command = match words.as_slice() {
+        ["order", "add", name, cents] => Command::Add(inbound::line_item(name, cents).ok()?),
+        ["order", "checkout"] => Command::Checkout,
+        ["order", "list"] => Command::List,
+        ["order", "cancel", id] => Command::Cancel(order_id(id)?),
+        ["report", "revenue"] => Command::Revenue,
+        ["health"] => Command::Health,
+        ["help", "status"] => Command::HelpStatus,
+        ["quit"] => Command::Quit,
+        _ => return None,
+    };
+    Some(command)
+}
+
+// "7" or "#000007", as orders are printed.
+fn order_id(text: &str) -> Option<OrderId> {
+    let digits = text.strip_prefix('#').unwrap_or(text);
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    digits.parse().ok().map(OrderId)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(line: &str) -> Vec<String> {
+        tokenize(line).unwrap()
+    }
+
+    #[test]
+    fn quotes_keep_a_name_in_one_word() {
+        assert_eq!(
+            words(r#"  order add "USB cable"  999 "#),
+            ["order", "add", "USB cable", "999"]
+        );
+        assert_eq!(words(r#"order add "" 1"#), ["order", "add", "", "1"]);
+        assert!(tokenize(r#"order add "USB cable 999"#).is_err());
+        assert!(words(" \t ").is_empty());
+    }
+
+    #[test]
+    fn parses_every_command_and_nothing_else() {
+        assert_eq!(
+            parse(&words("order add Mouse 2500")),
+            Some(Command::Add(LineItem::new("Mouse", Money(2500))))
+        );
+        assert_eq!(
+            parse(&words("order cancel #000012")),
+            Some(Command::Cancel(OrderId(12)))
+        );
+        assert_eq!(
+            parse(&words("order cancel 12")),
+            parse(&words("order cancel #12"))
+        );
+        assert_eq!(parse(&words("report revenue")), Some(Command::Revenue));
+        assert_eq!(parse(&words("help status")), Some(Command::HelpStatus));
+        for line in [
+            "order",
+            "order add Mouse",
+            "order add Mouse 25.00",
+            r#"order add "" 1"#,
+            "order cancel",
+            "order cancel -1",
+            "report",
+            "HEALTH",
+            "health now",
+            "help",
+        ] {
+            assert_eq!(parse(&words(line)), None, "{line:?} was accepted");
+        }
+    }
+}
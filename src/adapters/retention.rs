@@ -0,0 +1,76 @@
+// --- Age and status retention ---
+// The default RetentionPolicy: an order is forgotten once it was placed
+// more than `max_age` ago, and only in one of the statuses it was given:
+// Cancelled unless told otherwise. An order placed exactly max_age ago is
+// kept one more run. An order without placed_at has no age, and is kept.
+use crate::domain::{Order, OrderStatus, Timestamp};
+use crate::ports::{Capability, RetentionPolicy};
+use std::time::Duration;
+
+pub struct AgeAndStatusRetention {
+    max_age: Duration,
+    statuses: Vec<OrderStatus>,
+}
+
+impl AgeAndStatusRetention {
+    pub fn new(max_age: Duration) -> Self {
+        Self {
+            max_age,
+            statuses: vec![OrderStatus::Cancelled],
+        }
+    }
+
+    // Shipped orders too, say, once the law no longer asks for them.
+    pub fn with_status(mut self, status: OrderStatus) -> Self {
+        if !self.statuses.contains(&status) {
+            self.statuses.push(status);
+        }
+        self
+    }
+}
+
+impl RetentionPolicy for AgeAndStatusRetention {
+    fn should_purge(&self, order: &Order, now: Timestamp) -> bool {
+        let oldest_kept = now.minus_secs(self.max_age.as_secs());
+        self.statuses.contains(&order.status) && order.placed_at.is_some_and(|at| at < oldest_kept)
+    }
+}
+
+impl Capability for AgeAndStatusRetention {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, Money, OrderId};
+
+    const DAY: u64 = 24 * 60 * 60;
+
+    fn order(status: OrderStatus, placed_at: Option<u64>) -> Order {
+        let mut order = Order::new(OrderId(1), vec![LineItem::new("Pen", Money(150))]).unwrap();
+        order.status = status;
+        order.placed_at = placed_at.map(Timestamp);
+        order
+    }
+
+    #[test]
+    fn only_cancelled_orders_older_than_the_age_go_by_default() {
+        let policy = AgeAndStatusRetention::new(Duration::from_secs(30 * DAY));
+        let now = Timestamp(100 * DAY);
+
+        assert!(policy.should_purge(&order(OrderStatus::Cancelled, Some(DAY)), now));
+        assert!(!policy.should_purge(&order(OrderStatus::Cancelled, Some(70 * DAY)), now));
+        assert!(!policy.should_purge(&order(OrderStatus::Cancelled, None), now));
+        assert!(!policy.should_purge(&order(OrderStatus::Shipped, Some(DAY)), now));
+    }
+
+    #[test]
+    fn more_statuses_may_be_let_go() {
+        let policy =
+            AgeAndStatusRetention::new(Duration::from_secs(DAY)).with_status(OrderStatus::Shipped);
+        let now = Timestamp(10 * DAY);
+
+        assert!(policy.should_purge(&order(OrderStatus::Shipped, Some(0)), now));
+        assert!(policy.should_purge(&order(OrderStatus::Cancelled, Some(0)), now));
+        assert!(!policy.should_purge(&order(OrderStatus::Paid, Some(0)), now));
+    }
+}
@@ -0,0 +1,195 @@
+// --- Retrying payment gateway (decorator) ---
+// Some failed charges are worth making again at once: the provider could
+// not be reached (PaymentUnavailable), or the card's issuer answered
+// TryAgainLater. RetryingPaymentGateway makes the same charge again, up to
+// DEFAULT_ATTEMPTS times in all unless told otherwise, and answers with the
+// last failure when none of them went through.
+//
+// Every other decline fails at the first attempt: an expired card does not
+// become valid by being charged twice, and an issuer that suspects fraud
+// only grows more suspicious. Nor is anything else retried; a charge over
+// the daily cap stays over it.
+//
+//     let gateway = RetryingPaymentGateway::new(StripePaymentGateway::new()).with_attempts(5);
+//
+// Nothing waits between attempts. Refunds go through once: a refund that
+// was made but whose answer was lost must not be made twice.
+use super::Console;
+use crate::domain::{Money, OrderError, OrderId};
+use crate::ports::{Capability, ChargeLog, ChargeOutcome, ChargeRecord, PaymentGateway};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+pub const DEFAULT_ATTEMPTS: u32 = 3;
+
+pub struct RetryingPaymentGateway<G: PaymentGateway> {
+    inner: G,
+    attempts: u32,
+    retries: AtomicU32,
+    console: Console,
+}
+
+impl<G: PaymentGateway> RetryingPaymentGateway<G> {
+    pub fn new(inner: G) -> Self {
+        Self {
+            inner,
+            attempts: DEFAULT_ATTEMPTS,
+            retries: AtomicU32::new(0),
+            console: Console::stdout(),
+        }
+    }
+
+    // At least 1: the first attempt is not a retry.
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn inner(&self) -> &G {
+        &self.inner
+    }
+
+    // The attempts made past the first, over every charge so far.
+    pub fn retries(&self) -> u32 {
+        self.retries.load(Ordering::SeqCst)
+    }
+
+    fn retried<T>(
+        &self,
+        mut charge: impl FnMut() -> Result<T, OrderError>,
+    ) -> Result<T, OrderError> {
+        let mut attempt = 1;
+        loop {
+            match charge() {
+                Err(e) if attempt < self.attempts && worth_retrying(&e) => {
+                    attempt += 1;
+                    self.retries.fetch_add(1, Ordering::SeqCst);
+                    self.console.line(format_args!(
+                        "  [Retry] {e}, attempt {attempt} of {}",
+                        self.attempts
+                    ));
+                }
+                outcome => return outcome,
+            }
+        }
+    }
+}
+
+fn worth_retrying(error: &OrderError) -> bool {
+    match error {
+        OrderError::PaymentUnavailable => true,
+        OrderError::PaymentDeclined { reason } => reason.is_retryable(),
+        _ => false,
+    }
+}
+
+impl<G: PaymentGateway> PaymentGateway for RetryingPaymentGateway<G> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.retried(|| self.inner.charge(amount))
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.retried(|| self.inner.charge_for(order_id, amount))
+    }
+
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.retried(|| self.inner.charge_order(order_id, amount))
+    }
+
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.inner.refund_for(order_id, amount)
+    }
+}
+
+impl<G: PaymentGateway + ChargeLog> ChargeLog for RetryingPaymentGateway<G> {
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        self.inner.charges()
+    }
+}
+
+impl<G: PaymentGateway> Capability for RetryingPaymentGateway<G> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_err_variant;
+    use std::sync::Mutex;
+
+    // Answers with the next outcome in line, then Ok; counts the calls.
+    #[derive(Default)]
+    struct Scripted {
+        outcomes: Mutex<Vec<Result<(), OrderError>>>,
+        calls: AtomicU32,
+    }
+
+    impl Scripted {
+        fn failing(error: OrderError, times: usize) -> Self {
+            let scripted = Self::default();
+            scripted
+                .outcomes
+                .lock()
+                .unwrap()
+                .extend(std::iter::repeat_n(Err(error), times));
+            scripted
+        }
+
+        fn calls(&self) -> u32 {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl PaymentGateway for Scripted {
+        fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let mut outcomes = self.outcomes.lock().unwrap();
+            if outcomes.is_empty() {
+                return Ok(());
+            }
+            outcomes.remove(0)
+        }
+    }
+
+    fn retrying(inner: Scripted) -> RetryingPaymentGateway<Scripted> {
+        RetryingPaymentGateway::new(inner)
+            .with_attempts(3)
+            .with_console(Console::silent())
+    }
+
+    #[test]
+    fn an_unreachable_provider_is_tried_again_until_it_answers() {
+        let gateway = retrying(Scripted::failing(OrderError::PaymentUnavailable, 2));
+
+        assert!(gateway.charge(Money(100)).is_ok());
+        assert_eq!(gateway.inner().calls(), 3);
+        assert_eq!(gateway.retries(), 2);
+    }
+
+    #[test]
+    fn the_last_failure_comes_back_once_the_attempts_are_spent() {
+        let gateway = retrying(Scripted::failing(OrderError::PaymentUnavailable, 5));
+
+        assert_err_variant!(gateway.charge(Money(100)), OrderError::PaymentUnavailable);
+        assert_eq!(gateway.inner().calls(), 3);
+    }
+
+    #[test]
+    fn anything_else_fails_at_the_first_attempt() {
+        for error in [
+            OrderError::PaymentFailed,
+            OrderError::DailyCapExceeded {
+                remaining: Money(0),
+            },
+            OrderError::CircuitOpen {
+                retry_after_secs: 30,
+            },
+        ] {
+            let gateway = retrying(Scripted::failing(error.clone(), 1));
+            assert!(gateway.charge(Money(100)).is_err());
+            assert_eq!(gateway.inner().calls(), 1, "{error:?}");
+        }
+    }
+}
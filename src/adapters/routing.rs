@@ -0,0 +1,610 @@
+// --- Adaptive payment router ---
+// Two payment providers, one of them preferred. Every charge goes to the
+// primary while it answers well: once the slowest of its recent calls (the
+// 95th percentile of the last `window`) takes longer than `max_p95`, or too
+// many of them find it unreachable, charges go to the secondary instead.
+//
+// From then on, one charge every `probe_interval` goes to the primary
+// again, to see whether it has recovered. A probe that succeeds within
+// max_p95 brings every charge back to it, with its statistics starting
+// afresh; a probe that finds it unreachable is charged on the secondary,
+// so no customer pays for the probe. A slow but successful probe is a
+// charge like any other, and the secondary keeps the traffic.
+//
+// The router times calls with the Clock port, in whole seconds like the
+// circuit breaker; tests drive it with a SteppingClock. A statistic needs
+// MIN_SAMPLES calls before it switches anything. Only unreachable
+// providers count as errors: a declined card is an answer.
+//
+// A refund goes to the provider that made the charge, whichever one is
+// preferred at the time; the primary when the router never saw the charge,
+// or has forgotten it: only the last `remembered` charged orders are kept.
+// Refunds are not timed. The statistics are the charges', and a slow or
+// failing refund switches nothing.
+use crate::domain::{Money, OrderError, OrderId, Percent, Timestamp};
+use crate::ports::{Capability, ChargeLog, ChargeOutcome, ChargeRecord, Clock, PaymentGateway};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+pub const DEFAULT_WINDOW: usize = 20;
+pub const MIN_SAMPLES: usize = 5;
+pub const DEFAULT_MAX_P95: Duration = Duration::from_secs(2);
+pub const DEFAULT_MAX_ERROR_PERCENT: u8 = 20;
+pub const DEFAULT_PROBE_INTERVAL: Duration = Duration::from_secs(30);
+pub const DEFAULT_REMEMBERED_CHARGES: usize = 10_000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PaymentTarget {
+    Primary,
+    Secondary,
+}
+
+// A provider's last calls, as the health report shows them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct RouteStats {
+    pub calls: usize,
+    pub errors: usize,
+    pub p95_secs: u64,
+}
+
+impl RouteStats {
+    // 0 without calls.
+    pub fn error_percent(&self) -> usize {
+        (self.errors * 100).checked_div(self.calls).unwrap_or(0)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    secs: u64,
+    failed: bool,
+}
+
+struct Routing {
+    target: PaymentTarget,
+    // While on the secondary: when the primary is tried again.
+    next_probe: Option<Timestamp>,
+    primary: VecDeque<Sample>,
+    secondary: VecDeque<Sample>,
+    charged_by: HashMap<OrderId, PaymentTarget>,
+    // charged_by's orders, oldest first, to forget them in that order.
+    charged_order: VecDeque<OrderId>,
+}
+
+impl Routing {
+    fn samples(&mut self, target: PaymentTarget) -> &mut VecDeque<Sample> {
+        match target {
+            PaymentTarget::Primary => &mut self.primary,
+            PaymentTarget::Secondary => &mut self.secondary,
+        }
+    }
+
+    fn remember(&mut self, order_id: OrderId, target: PaymentTarget, remembered: usize) {
+        if self.charged_by.insert(order_id, target).is_none() {
+            self.charged_order.push_back(order_id);
+        }
+        while self.charged_order.len() > remembered {
+            if let Some(oldest) = self.charged_order.pop_front() {
+                self.charged_by.remove(&oldest);
+            }
+        }
+    }
+}
+
+pub struct AdaptivePaymentRouter<'a, P, S> {
+    primary: P,
+    secondary: S,
+    clock: &'a (dyn Clock + Sync),
+    window: usize,
+    max_p95_secs: u64,
+    max_error_percent: usize,
+    probe_secs: u64,
+    remembered: usize,
+    routing: Mutex<Routing>,
+}
+
+impl<'a, P: PaymentGateway, S: PaymentGateway> AdaptivePaymentRouter<'a, P, S> {
+    pub fn new(primary: P, secondary: S, clock: &'a (dyn Clock + Sync)) -> Self {
+        Self {
+            primary,
+            secondary,
+            clock,
+            window: DEFAULT_WINDOW,
+            max_p95_secs: DEFAULT_MAX_P95.as_secs(),
+            max_error_percent: usize::from(DEFAULT_MAX_ERROR_PERCENT),
+            probe_secs: DEFAULT_PROBE_INTERVAL.as_secs(),
+            remembered: DEFAULT_REMEMBERED_CHARGES,
+            routing: Mutex::new(Routing {
+                target: PaymentTarget::Primary,
+                next_probe: None,
+                primary: VecDeque::new(),
+                secondary: VecDeque::new(),
+                charged_by: HashMap::new(),
+                charged_order: VecDeque::new(),
+            }),
+        }
+    }
+
+    // How many of the last calls the statistics cover. At least
+    // MIN_SAMPLES, or nothing would ever switch.
+    pub fn with_window(mut self, calls: usize) -> Self {
+        self.window = calls.max(MIN_SAMPLES);
+        self
+    }
+
+    // Whole seconds, like the Clock.
+    pub fn with_max_p95(mut self, latency: Duration) -> Self {
+        self.max_p95_secs = latency.as_secs();
+        self
+    }
+
+    pub fn with_max_error_rate(mut self, rate: Percent) -> Self {
+        self.max_error_percent = usize::from(rate.value());
+        self
+    }
+
+    pub fn with_probe_interval(mut self, interval: Duration) -> Self {
+        self.probe_secs = interval.as_secs();
+        self
+    }
+
+    // How many charged orders the router remembers the provider of, for
+    // their refunds. At least one.
+    pub fn with_remembered_charges(mut self, orders: usize) -> Self {
+        self.remembered = orders.max(1);
+        self
+    }
+
+    pub fn primary(&self) -> &P {
+        &self.primary
+    }
+
+    pub fn secondary(&self) -> &S {
+        &self.secondary
+    }
+
+    // Where the next charge goes, a probe aside.
+    pub fn current_target(&self) -> PaymentTarget {
+        self.lock().target
+    }
+
+    // While on the secondary, when the next probe of the primary is due.
+    pub fn next_probe(&self) -> Option<Timestamp> {
+        self.lock().next_probe
+    }
+
+    pub fn stats(&self, target: PaymentTarget) -> RouteStats {
+        stats(self.lock().samples(target))
+    }
+
+    // The one place charges go through: `call` may run twice when a probe
+    // finds the primary unreachable, once on each provider.
+    fn route<T>(
+        &self,
+        order_id: Option<OrderId>,
+        call: impl Fn(&dyn PaymentGateway) -> Result<T, OrderError>,
+    ) -> Result<T, OrderError> {
+        let now = self.clock.now();
+        let (target, probe) = {
+            let mut routing = self.lock();
+            match (routing.target, routing.next_probe) {
+                // Pushed back before the call, so that one probe runs at a
+                // time.
+                (PaymentTarget::Secondary, Some(at)) if at <= now => {
+                    routing.next_probe = Some(now.plus_secs(self.probe_secs));
+                    (PaymentTarget::Primary, true)
+                }
+                (target, _) => (target, false),
+            }
+        };
+        let result = match self.timed(target, probe, &call) {
+            Err(error) if probe && unreachable(&error) => self
+                .timed(PaymentTarget::Secondary, false, &call)
+                .map(|value| (PaymentTarget::Secondary, value)),
+            other => other.map(|value| (target, value)),
+        };
+        let (charged_on, value) = result?;
+        if let Some(order_id) = order_id {
+            self.lock().remember(order_id, charged_on, self.remembered);
+        }
+        Ok(value)
+    }
+
+    fn timed<T>(
+        &self,
+        target: PaymentTarget,
+        probe: bool,
+        call: &impl Fn(&dyn PaymentGateway) -> Result<T, OrderError>,
+    ) -> Result<T, OrderError> {
+        let started = self.clock.now();
+        let result = match target {
+            PaymentTarget::Primary => call(&self.primary),
+            PaymentTarget::Secondary => call(&self.secondary),
+        };
+        let sample = Sample {
+            secs: self.clock.now().0.saturating_sub(started.0),
+            failed: result.as_ref().is_err_and(unreachable),
+        };
+        self.record(target, probe, sample);
+        result
+    }
+
+    fn record(&self, target: PaymentTarget, probe: bool, sample: Sample) {
+        let mut routing = self.lock();
+        if probe && !sample.failed && sample.secs <= self.max_p95_secs {
+            routing.target = PaymentTarget::Primary;
+            routing.next_probe = None;
+            routing.primary.clear();
+        }
+        let window = self.window;
+        let samples = routing.samples(target);
+        samples.push_back(sample);
+        while samples.len() > window {
+            samples.pop_front();
+        }
+        if routing.target == PaymentTarget::Primary
+            && target == PaymentTarget::Primary
+            && self.unhealthy(stats(&routing.primary))
+        {
+            routing.target = PaymentTarget::Secondary;
+            routing.next_probe = Some(self.clock.now().plus_secs(self.probe_secs));
+        }
+    }
+
+    fn unhealthy(&self, stats: RouteStats) -> bool {
+        stats.calls >= MIN_SAMPLES
+            && (stats.p95_secs > self.max_p95_secs
+                || stats.error_percent() > self.max_error_percent)
+    }
+
+    // A panic elsewhere while holding the lock leaves the routing as it was.
+    fn lock(&self) -> MutexGuard<'_, Routing> {
+        self.routing.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn stats(samples: &VecDeque<Sample>) -> RouteStats {
+    let mut latencies: Vec<u64> = samples.iter().map(|sample| sample.secs).collect();
+    latencies.sort_unstable();
+    // The smallest latency at least 95 % of the calls did not exceed.
+    let p95_secs = match latencies.len() {
+        0 => 0,
+        n => latencies[(n * 95).div_ceil(100) - 1],
+    };
+    RouteStats {
+        calls: samples.len(),
+        errors: samples.iter().filter(|sample| sample.failed).count(),
+        p95_secs,
+    }
+}
+
+fn unreachable(error: &OrderError) -> bool {
+    matches!(
+        error,
+        OrderError::PaymentUnavailable | OrderError::CircuitOpen { .. }
+    )
+}
+
+impl<P: PaymentGateway, S: PaymentGateway> PaymentGateway for AdaptivePaymentRouter<'_, P, S> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.route(None, |gateway| gateway.charge(amount))
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.route(Some(order_id), |gateway| {
+            gateway.charge_for(order_id, amount)
+        })
+    }
+
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.route(Some(order_id), |gateway| {
+            gateway.charge_order(order_id, amount)
+        })
+    }
+
+    // Never routed elsewhere, and left out of the statistics.
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        let target = self
+            .lock()
+            .charged_by
+            .get(&order_id)
+            .copied()
+            .unwrap_or(PaymentTarget::Primary);
+        match target {
+            PaymentTarget::Primary => self.primary.refund_for(order_id, amount),
+            PaymentTarget::Secondary => self.secondary.refund_for(order_id, amount),
+        }
+    }
+}
+
+// Both providers' records: the primary's first.
+impl<P, S> ChargeLog for AdaptivePaymentRouter<'_, P, S>
+where
+    P: PaymentGateway + ChargeLog,
+    S: PaymentGateway + ChargeLog,
+{
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        let mut charges = self.primary.charges()?;
+        charges.extend(self.secondary.charges()?);
+        Ok(charges)
+    }
+}
+
+impl<P, S> Capability for AdaptivePaymentRouter<'_, P, S> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::SteppingClock;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    // Takes the next step's seconds on the shared clock, then answers with
+    // its outcome; quick and fine once the steps run out.
+    struct Scripted<'c> {
+        clock: &'c SteppingClock,
+        steps: Mutex<VecDeque<(u64, Result<(), OrderError>)>>,
+        calls: AtomicU32,
+        refunds: AtomicU32,
+    }
+
+    impl<'c> Scripted<'c> {
+        fn on(clock: &'c SteppingClock) -> Self {
+            Self {
+                clock,
+                steps: Mutex::new(VecDeque::new()),
+                calls: AtomicU32::new(0),
+                refunds: AtomicU32::new(0),
+            }
+        }
+
+        fn then(self, secs: u64, outcome: Result<(), OrderError>, times: usize) -> Self {
+            self.steps
+                .lock()
+                .unwrap()
+                .extend(std::iter::repeat_n((secs, outcome), times));
+            self
+        }
+
+        fn calls(&self) -> u32 {
+            self.calls.load(Ordering::SeqCst)
+        }
+    }
+
+    impl PaymentGateway for Scripted<'_> {
+        fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+            self.calls.fetch_add(1, Ordering::SeqCst);
+            let (secs, outcome) = self
+                .steps
+                .lock()
+                .unwrap()
+                .pop_front()
+                .unwrap_or((0, Ok(())));
+            self.clock.advance_secs(secs);
+            outcome
+        }
+
+        fn refund_for(&self, _order_id: OrderId, _amount: Money) -> Result<(), OrderError> {
+            self.refunds.fetch_add(1, Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    type Router<'c> = AdaptivePaymentRouter<'c, Scripted<'c>, Scripted<'c>>;
+
+    fn router<'c>(clock: &'c SteppingClock, primary: Scripted<'c>) -> Router<'c> {
+        AdaptivePaymentRouter::new(primary, Scripted::on(clock), clock)
+    }
+
+    const DOWN: Result<(), OrderError> = Err(OrderError::PaymentUnavailable);
+
+    // Charges until the router leaves the primary, whatever the answers;
+    // how many it took.
+    fn charge_until_switched(router: &Router<'_>) -> u32 {
+        let mut charges = 0;
+        while router.current_target() == PaymentTarget::Primary {
+            let _ = router.charge(Money(100));
+            charges += 1;
+        }
+        charges
+    }
+
+    #[test]
+    fn a_slow_primary_loses_the_traffic_once_its_p95_is_over() {
+        let clock = SteppingClock::starting_at(Timestamp(1_000));
+        let router = router(&clock, Scripted::on(&clock).then(3, Ok(()), 10));
+
+        // Too few calls to judge: they all stay on the primary.
+        for _ in 0..MIN_SAMPLES - 1 {
+            router.charge(Money(100)).unwrap();
+            assert_eq!(router.current_target(), PaymentTarget::Primary);
+        }
+        router.charge(Money(100)).unwrap();
+
+        assert_eq!(router.current_target(), PaymentTarget::Secondary);
+        assert_eq!(router.primary().calls(), 5);
+        let stats = router.stats(PaymentTarget::Primary);
+        assert_eq!((stats.calls, stats.errors, stats.p95_secs), (5, 0, 3));
+        assert_eq!(router.next_probe(), Some(Timestamp(1_015 + 30)));
+        router.charge(Money(100)).unwrap();
+        assert_eq!(router.primary().calls(), 5);
+        assert_eq!(router.secondary().calls(), 1);
+        assert_eq!(router.stats(PaymentTarget::Secondary).calls, 1);
+    }
+
+    #[test]
+    fn one_slow_call_in_twenty_stays_under_the_p95() {
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let primary = Scripted::on(&clock).then(0, Ok(()), 19).then(9, Ok(()), 1);
+        let router = router(&clock, primary);
+
+        for _ in 0..DEFAULT_WINDOW {
+            router.charge(Money(100)).unwrap();
+        }
+
+        assert_eq!(router.current_target(), PaymentTarget::Primary);
+        assert_eq!(router.stats(PaymentTarget::Primary).p95_secs, 0);
+    }
+
+    #[test]
+    fn too_many_unreachable_calls_switch_but_declined_cards_do_not() {
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let primary = Scripted::on(&clock)
+            .then(0, Err(OrderError::PaymentFailed), 5)
+            // One in five is 20 %: not over the default.
+            .then(0, DOWN, 1)
+            .then(0, Ok(()), 4)
+            .then(0, DOWN, 1);
+        let router = router(&clock, primary);
+
+        for _ in 0..5 {
+            assert!(matches!(
+                router.charge(Money(100)),
+                Err(OrderError::PaymentFailed)
+            ));
+        }
+        assert_eq!(router.current_target(), PaymentTarget::Primary);
+        for _ in 0..5 {
+            let _ = router.charge(Money(100));
+        }
+        assert_eq!(router.current_target(), PaymentTarget::Primary);
+        assert_eq!(router.stats(PaymentTarget::Primary).error_percent(), 10);
+
+        assert!(matches!(
+            router.charge(Money(100)),
+            Err(OrderError::PaymentUnavailable)
+        ));
+        // 2 of the last 11: 18 %, still not over.
+        assert_eq!(router.current_target(), PaymentTarget::Primary);
+
+        let strict = AdaptivePaymentRouter::new(
+            Scripted::on(&clock).then(0, DOWN, 2),
+            Scripted::on(&clock),
+            &clock,
+        )
+        .with_window(5)
+        .with_max_error_rate(Percent::try_from(30).unwrap());
+        assert_eq!(charge_until_switched(&strict), 5);
+        assert_eq!(strict.stats(PaymentTarget::Primary).error_percent(), 40);
+    }
+
+    #[test]
+    fn the_primary_is_probed_once_per_interval_until_it_answers_quickly() {
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let primary = Scripted::on(&clock)
+            .then(5, Ok(()), 5)
+            // The first probe: still slow.
+            .then(5, Ok(()), 1);
+        let router = router(&clock, primary);
+        assert_eq!(charge_until_switched(&router), 5);
+        assert_eq!(router.next_probe(), Some(Timestamp(25 + 30)));
+
+        // Up to the probe, the secondary has it all.
+        clock.set(Timestamp(54));
+        for _ in 0..3 {
+            router.charge(Money(100)).unwrap();
+        }
+        assert_eq!(
+            (router.primary().calls(), router.secondary().calls()),
+            (5, 3)
+        );
+
+        // Due: one charge, and one only, tries the primary.
+        clock.set(Timestamp(55));
+        router.charge(Money(100)).unwrap();
+        assert_eq!(router.primary().calls(), 6);
+        assert_eq!(router.current_target(), PaymentTarget::Secondary);
+        assert_eq!(router.next_probe(), Some(Timestamp(55 + 30)));
+        router.charge(Money(100)).unwrap();
+        assert_eq!(
+            (router.primary().calls(), router.secondary().calls()),
+            (6, 4)
+        );
+
+        // Recovered: the next probe is quick, and the primary is back.
+        clock.set(Timestamp(85));
+        router.charge(Money(100)).unwrap();
+        assert_eq!(router.primary().calls(), 7);
+        assert_eq!(router.current_target(), PaymentTarget::Primary);
+        assert_eq!(router.next_probe(), None);
+        // Its slow past forgotten.
+        let stats = router.stats(PaymentTarget::Primary);
+        assert_eq!((stats.calls, stats.p95_secs), (1, 0));
+        router.charge(Money(100)).unwrap();
+        assert_eq!(
+            (router.primary().calls(), router.secondary().calls()),
+            (8, 4)
+        );
+    }
+
+    #[test]
+    fn a_probe_finding_the_primary_down_is_charged_on_the_secondary() {
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let router = router(&clock, Scripted::on(&clock).then(0, DOWN, 6))
+            .with_probe_interval(Duration::from_secs(10));
+        assert_eq!(charge_until_switched(&router), 5);
+
+        clock.advance_secs(10);
+        assert!(router.charge(Money(100)).is_ok());
+
+        assert_eq!(
+            (router.primary().calls(), router.secondary().calls()),
+            (6, 1)
+        );
+        assert_eq!(router.current_target(), PaymentTarget::Secondary);
+        assert_eq!(router.next_probe(), Some(Timestamp(20)));
+    }
+
+    #[test]
+    fn refunds_go_to_the_gateway_that_charged() {
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let router = router(&clock, Scripted::on(&clock).then(3, Ok(()), 5));
+
+        router.charge_for(OrderId(1), Money(100)).unwrap();
+        charge_until_switched(&router);
+        router.charge_for(OrderId(2), Money(100)).unwrap();
+        router.refund_for(OrderId(1), Money(100)).unwrap();
+        router.refund_for(OrderId(2), Money(100)).unwrap();
+        // Never charged here: the primary's.
+        router.refund_for(OrderId(3), Money(100)).unwrap();
+
+        assert_eq!(router.primary().refunds.load(Ordering::SeqCst), 2);
+        assert_eq!(router.secondary().refunds.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn refunds_are_not_counted_with_the_charges() {
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let router = router(&clock, Scripted::on(&clock));
+
+        router.charge_for(OrderId(1), Money(100)).unwrap();
+        for _ in 0..DEFAULT_WINDOW {
+            router.refund_for(OrderId(1), Money(1)).unwrap();
+        }
+
+        assert_eq!(router.stats(PaymentTarget::Primary).calls, 1);
+        assert_eq!(router.primary().refunds.load(Ordering::SeqCst), 20);
+    }
+
+    #[test]
+    fn only_the_last_charged_orders_are_remembered() {
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let router =
+            router(&clock, Scripted::on(&clock).then(3, Ok(()), 5)).with_remembered_charges(2);
+        charge_until_switched(&router);
+
+        for id in 1..=3 {
+            router.charge_for(OrderId(id), Money(100)).unwrap();
+        }
+        // The same order charged again is still one to remember.
+        router.charge_for(OrderId(3), Money(100)).unwrap();
+        assert_eq!(router.lock().charged_by.len(), 2);
+        for id in 1..=3 {
+            router.refund_for(OrderId(id), Money(100)).unwrap();
+        }
+
+        // Order 1 is forgotten: its refund goes to the primary.
+        assert_eq!(router.primary().refunds.load(Ordering::SeqCst), 1);
+        assert_eq!(router.secondary().refunds.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,195 @@
+// --- Single-threaded sharing: Rc<RefCell<...>> ---
+// A GUI or a single-threaded server often wants one repository used by
+// several services: one that writes, others that only read. Arc<Mutex<..>>
+// works but is overkill without threads; Rc<RefCell<..>> is the usual answer.
+// Services that can borrow the repository need neither: they all take it
+// by &. A handle is for those that must own what they hold, with no
+// lifetime tying them to the repository's owner.
+//
+// Its pitfall is the runtime borrow check: holding a borrow() while someone
+// calls borrow_mut() panics. SharedRepository avoids both halves of that:
+// - borrows never escape: with_repo() lends the repository to a closure only,
+//   so there is no guard to keep alive across a write
+// - a conflicting borrow is not a panic: it becomes OrderError::StorageFailed
+use crate::domain::{Money, Order, OrderError, OrderId, OrderKey, OrderReference};
+use crate::ports::{CancelToken, Capability, OrderReader, OrderWriter};
+use std::cell::RefCell;
+use std::rc::Rc;
+
+/// A cloneable handle on one repository, for single-threaded sharing.
+///
+/// Borrows cannot outlive the closure given to `with_repo`, so this does not compile:
+///
+/// ```compile_fail
+/// use hexa_lite::adapters::shared::SharedRepository;
+/// use hexa_lite::prelude::*;
+///
+/// let shared = SharedRepository::new(InMemoryOrderRepository::new());
+/// let leaked: &InMemoryOrderRepository = shared.with_repo(|repo| repo).unwrap();
+/// ```
+pub struct SharedRepository<R: OrderWriter> {
+    inner: Rc<RefCell<R>>,
+}
+
+impl<R: OrderWriter> SharedRepository<R> {
+    pub fn new(repository: R) -> Self {
+        Self {
+            inner: Rc::new(RefCell::new(repository)),
+        }
+    }
+
+    // Read access for the duration of `f`, the only way to reach the
+    // concrete repository. Fails instead of panicking if a write is in progress.
+    pub fn with_repo<T>(&self, f: impl FnOnce(&R) -> T) -> Result<T, OrderError> {
+        let repository = self
+            .inner
+            .try_borrow()
+            .map_err(|_| OrderError::StorageFailed)?;
+        Ok(f(&repository))
+    }
+
+    pub fn handles(&self) -> usize {
+        Rc::strong_count(&self.inner)
+    }
+}
+
+// Derived Clone would require R: Clone. Cloning a handle never clones the repository.
+impl<R: OrderWriter> Clone for SharedRepository<R> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: Rc::clone(&self.inner),
+        }
+    }
+}
+
+// Each port call borrows for the call only. Writes come through &self
+// but still borrow mutably: a write from inside a read is refused, instead
+// of re-entering the inner repository in the middle of its read.
+impl<R: OrderWriter> OrderReader for SharedRepository<R> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.with_repo(|repository| repository.find(id))?
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.with_repo(|repository| repository.list())?
+    }
+
+    // Forwarded, so the inner adapter's cheap versions are used.
+    fn exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        self.with_repo(|repository| repository.exists(id))?
+    }
+
+    fn total_of(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        self.with_repo(|repository| repository.total_of(id))?
+    }
+
+    // The visitor runs while the repository is borrowed: it must not reach
+    // for another handle of the same repository.
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        self.with_repo(|repository| repository.for_each(visit))?
+    }
+
+    fn for_each_cancellable(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        self.with_repo(|repository| repository.for_each_cancellable(cancel, visit))?
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        self.with_repo(|repository| repository.list_deleted())?
+    }
+
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.with_repo(|repository| repository.find_by_key(key))?
+    }
+
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        self.with_repo(|repository| repository.find_by_reference(reference))?
+    }
+}
+
+impl<R: OrderWriter> OrderWriter for SharedRepository<R> {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.inner
+            .try_borrow_mut()
+            .map_err(|_| OrderError::StorageFailed)?
+            .save(order)
+    }
+
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        self.inner
+            .try_borrow_mut()
+            .map_err(|_| OrderError::StorageFailed)?
+            .update(order)
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.inner
+            .try_borrow_mut()
+            .map_err(|_| OrderError::StorageFailed)?
+            .soft_delete(id)
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.inner
+            .try_borrow_mut()
+            .map_err(|_| OrderError::StorageFailed)?
+            .restore(id)
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.inner
+            .try_borrow_mut()
+            .map_err(|_| OrderError::StorageFailed)?
+            .purge(id)
+    }
+}
+
+impl<R: OrderWriter> Capability for SharedRepository<R> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::in_memory::InMemoryOrderRepository;
+    use crate::assert_err_variant;
+    use crate::domain::{LineItem, Money};
+
+    fn order(id: u32) -> Order {
+        Order::new(
+            OrderId(id),
+            vec![LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            }],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn clones_share_one_repository() {
+        let reader = SharedRepository::new(InMemoryOrderRepository::new());
+        let writer = reader.clone();
+
+        writer.save(&order(1)).unwrap();
+
+        assert!(reader.find(OrderId(1)).unwrap().is_some());
+        assert_eq!(reader.handles(), 2);
+    }
+
+    #[test]
+    fn write_during_a_read_is_an_error_not_a_panic() {
+        let reader = SharedRepository::new(InMemoryOrderRepository::new());
+        let writer = reader.clone();
+
+        let attempt = reader
+            .with_repo(|_repository| writer.save(&order(1)))
+            .unwrap();
+
+        assert_err_variant!(attempt, OrderError::StorageFailed);
+        // Once the closure returned, writing works again.
+        writer.save(&order(1)).unwrap();
+        assert_eq!(reader.list().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,1192 @@
+// --- SQL order repository ---
+// Orders in one table of a SQL database, through real, parameterized SQL:
+//
+//     orders (id, status, total, placed_at, uuid, version,
+//             approval, items, shipments, shipping_address, gift_note,
+//             currency, notes, attachments, reference, deleted)
+//
+// The scalar fields have columns of their own, so a database can index and
+// query them. Items, shipments, the approval, the shipping address, the
+// notes and the attachments are JSON text, the way a jsonb column would
+// hold them. A soft-deleted order keeps its row, with
+// deleted = 1.
+//
+// The repository only builds statements and reads rows back; running them
+// is a SqlExecutor's job. FakeExecutor, below, keeps the crate free of
+// dependencies: it records every statement, and holds the table in memory
+// so the repository works in tests and demos. A driver-backed executor
+// (rusqlite, postgres...) is the same two methods around a connection. The
+// placeholders are ?1, ?2... as SQLite spells them.
+//
+// In context (see adapters::tracing), every call sends the same statements with the call's traceparent in a comment at the end, the
+// way sqlcommenter writes it; the slow query log then says which trace a
+// statement belonged to:
+//
+//     SELECT ... WHERE id = ?1 AND deleted = 0 /*traceparent='00-...-01'*/
+use super::json::{self, Value};
+use super::{Console, NetworkConditions};
+use crate::domain::{
+    Address, Approval, ApproverId, Attachment, BlobId, Currency, LineItem, Money, Order,
+    OrderError, OrderId, OrderKey, OrderNote, OrderReference, OrderStatus, Shipment, Timestamp,
+    TrackingId, Uuid128,
+};
+use crate::ports::{
+    Capability, Context, ContextualRepository, OrderReader, OrderWriter, UnitOfWork,
+};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SqlValue {
+    Null,
+    Integer(i64),
+    Text(String),
+}
+
+// The values of one row, in the order of the SELECT's columns.
+pub type Row = Vec<SqlValue>;
+pub type Rows = Vec<Row>;
+
+// What a connection to the database does for this repository. A driver's
+// own errors stay behind it: the application only ever sees StorageFailed.
+// Both take &self, as the repository's writes do: a pool hands out a
+// connection per call, a single connection sits behind a Mutex.
+pub trait SqlExecutor {
+    // Rows changed.
+    fn execute(&self, sql: &str, params: &[SqlValue]) -> Result<u64, OrderError>;
+    fn query(&self, sql: &str, params: &[SqlValue]) -> Result<Rows, OrderError>;
+}
+
+const CREATE_TABLE: &str = "CREATE TABLE IF NOT EXISTS orders (\
+    id INTEGER PRIMARY KEY, \
+    status TEXT NOT NULL, \
+    total INTEGER NOT NULL, \
+    placed_at INTEGER, \
+    uuid TEXT UNIQUE, \
+    version INTEGER NOT NULL, \
+    approval TEXT, \
+    items TEXT NOT NULL, \
+    shipments TEXT NOT NULL, \
+    shipping_address TEXT, \
+    gift_note TEXT, \
+    currency TEXT NOT NULL DEFAULT 'USD', \
+    notes TEXT NOT NULL DEFAULT '[]', \
+    attachments TEXT NOT NULL DEFAULT '[]', \
+    reference TEXT UNIQUE, \
+    deleted INTEGER NOT NULL DEFAULT 0)";
+// Saving a deleted order updates it and leaves it deleted.
+const UPSERT: &str = "INSERT INTO orders \
+    (id, status, total, placed_at, uuid, version, approval, items, shipments, \
+    shipping_address, gift_note, currency, notes, attachments, reference) \
+    VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15) \
+    ON CONFLICT (id) DO UPDATE SET \
+    status = excluded.status, total = excluded.total, placed_at = excluded.placed_at, \
+    uuid = excluded.uuid, version = excluded.version, approval = excluded.approval, \
+    items = excluded.items, shipments = excluded.shipments, \
+    shipping_address = excluded.shipping_address, gift_note = excluded.gift_note, \
+    currency = excluded.currency, notes = excluded.notes, attachments = excluded.attachments, \
+    reference = excluded.reference";
+// Optimistic concurrency in the statement itself: the row only changes if
+// it still has the version the order was read at, ?16. Zero rows changed
+// is a conflict, or no such order.
+const UPDATE: &str = "UPDATE orders SET \
+    status = ?2, total = ?3, placed_at = ?4, uuid = ?5, version = ?6, approval = ?7, \
+    items = ?8, shipments = ?9, shipping_address = ?10, gift_note = ?11, currency = ?12, \
+    notes = ?13, attachments = ?14, reference = ?15 \
+    WHERE id = ?1 AND version = ?16 AND deleted = 0";
+const SELECT_BY_ID: &str = "SELECT id, status, total, placed_at, uuid, version, approval, \
+    items, shipments, shipping_address, gift_note, currency, notes, attachments, reference FROM orders WHERE id = ?1 AND deleted = 0";
+const SELECT_BY_UUID: &str = "SELECT id, status, total, placed_at, uuid, version, approval, \
+    items, shipments, shipping_address, gift_note, currency, notes, attachments, reference FROM orders WHERE uuid = ?1 AND deleted = 0";
+const SELECT_BY_REFERENCE: &str = "SELECT id, status, total, placed_at, uuid, version, approval, \
+    items, shipments, shipping_address, gift_note, currency, notes, attachments, reference FROM orders WHERE reference = ?1 AND deleted = 0";
+const SELECT_ALL: &str = "SELECT id, status, total, placed_at, uuid, version, approval, \
+    items, shipments, shipping_address, gift_note, currency, notes, attachments, reference FROM orders WHERE deleted = 0 ORDER BY id";
+const SELECT_DELETED: &str = "SELECT id, status, total, placed_at, uuid, version, approval, \
+    items, shipments, shipping_address, gift_note, currency, notes, attachments, reference FROM orders WHERE deleted = 1 ORDER BY id";
+const EXISTS: &str = "SELECT 1 FROM orders WHERE id = ?1 AND deleted = 0";
+const SELECT_TOTAL: &str = "SELECT total FROM orders WHERE id = ?1 AND deleted = 0";
+const SOFT_DELETE: &str = "UPDATE orders SET deleted = 1 WHERE id = ?1";
+const RESTORE: &str = "UPDATE orders SET deleted = 0 WHERE id = ?1 AND deleted = 1";
+const PURGE: &str = "DELETE FROM orders WHERE id = ?1";
+const BEGIN: &str = "BEGIN";
+const COMMIT: &str = "COMMIT";
+const ROLLBACK: &str = "ROLLBACK";
+
+pub struct SqlOrderRepository<E: SqlExecutor> {
+    executor: E,
+    // BEGIN failed: commit must not pretend the writes since then are one
+    // transaction.
+    broken_transaction: bool,
+    console: Console,
+}
+
+impl<E: SqlExecutor> SqlOrderRepository<E> {
+    // Creates the table when the database does not have it yet.
+    pub fn new(executor: E) -> Result<Self, OrderError> {
+        executor.execute(CREATE_TABLE, &[])?;
+        Ok(Self {
+            executor,
+            broken_transaction: false,
+            console: Console::default(),
+        })
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn executor(&self) -> &E {
+        &self.executor
+    }
+
+    fn orders(&self, sql: &str, params: &[SqlValue]) -> Result<Vec<Order>, OrderError> {
+        self.executor
+            .query(sql, params)?
+            .iter()
+            .map(|row| order_from_row(row))
+            .collect()
+    }
+
+    fn one(&self, sql: &str, params: &[SqlValue]) -> Result<Option<Order>, OrderError> {
+        Ok(self.orders(sql, params)?.into_iter().next())
+    }
+
+    // The writes below take their statements, as sent: with a comment or
+    // without. `select` finds the order again when no row changed, to tell
+    // a stale version from an order that is not there.
+    fn update_with(&self, update: &str, select: &str, order: &Order) -> Result<(), OrderError> {
+        let id = order.id;
+        self.console
+            .line(format_args!("  [SQL] Updating order {id:?}"));
+        let mut next = order.clone();
+        next.version += 1;
+        let mut params = order_to_params(&next)?;
+        params.push(SqlValue::Integer(i64::from(order.version)));
+        if self.executor.execute(update, &params)? > 0 {
+            return Ok(());
+        }
+        match self.one(select, &[id_param(id)])? {
+            None => Err(OrderError::NotFound { id }),
+            Some(stored) => Err(OrderError::VersionConflict {
+                id,
+                expected: order.version,
+                found: stored.version,
+            }),
+        }
+    }
+
+    fn soft_delete_with(&self, soft_delete: &str, id: OrderId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [SQL] Deleting order {id:?}"));
+        match self.executor.execute(soft_delete, &[id_param(id)])? {
+            0 => Err(OrderError::NotFound { id }),
+            _ => Ok(()),
+        }
+    }
+
+    fn restore_with(&self, restore: &str, exists: &str, id: OrderId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [SQL] Restoring order {id:?}"));
+        if self.executor.execute(restore, &[id_param(id)])? > 0 {
+            return Ok(());
+        }
+        match self.executor.query(exists, &[id_param(id)])?.is_empty() {
+            true => Err(OrderError::NotFound { id }),
+            false => Err(OrderError::NotDeleted { id }),
+        }
+    }
+
+    fn purge_with(&self, purge: &str, id: OrderId) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [SQL] Purging order {id:?}"));
+        match self.executor.execute(purge, &[id_param(id)])? {
+            0 => Err(OrderError::NotFound { id }),
+            _ => Ok(()),
+        }
+    }
+
+    fn find_by_key_with(
+        &self,
+        by_id: &str,
+        by_uuid: &str,
+        key: OrderKey,
+    ) -> Result<Option<Order>, OrderError> {
+        self.console
+            .line(format_args!("  [SQL] Finding order {key}"));
+        match key {
+            OrderKey::Sequential(id) => self.one(by_id, &[id_param(id)]),
+            OrderKey::Random(uuid) => self.one(by_uuid, &[SqlValue::Text(uuid.to_string())]),
+        }
+    }
+
+    fn find_by_reference_with(
+        &self,
+        by_reference: &str,
+        reference: &OrderReference,
+    ) -> Result<Option<Order>, OrderError> {
+        self.console
+            .line(format_args!("  [SQL] Finding order {reference}"));
+        self.one(by_reference, &[SqlValue::Text(reference.0.clone())])
+    }
+}
+
+impl<E: SqlExecutor> OrderReader for SqlOrderRepository<E> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.console
+            .line(format_args!("  [SQL] Finding order {id:?}"));
+        self.one(SELECT_BY_ID, &[id_param(id)])
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.console.line(format_args!("  [SQL] Listing orders"));
+        self.orders(SELECT_ALL, &[])
+    }
+
+    fn exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        self.console
+            .line(format_args!("  [SQL] Checking order {id:?}"));
+        Ok(!self.executor.query(EXISTS, &[id_param(id)])?.is_empty())
+    }
+
+    fn total_of(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        self.console
+            .line(format_args!("  [SQL] Reading total of order {id:?}"));
+        match self.executor.query(SELECT_TOTAL, &[id_param(id)])?.first() {
+            None => Ok(None),
+            Some(row) => Ok(Some(Money(column(row, 0).and_then(u32_of)?))),
+        }
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        self.orders(SELECT_DELETED, &[])
+    }
+
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.find_by_key_with(SELECT_BY_ID, SELECT_BY_UUID, key)
+    }
+
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        self.find_by_reference_with(SELECT_BY_REFERENCE, reference)
+    }
+}
+
+impl<E: SqlExecutor> OrderWriter for SqlOrderRepository<E> {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [SQL] Saving order {:?}", order.id));
+        self.executor.execute(UPSERT, &order_to_params(order)?)?;
+        Ok(())
+    }
+
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        self.update_with(UPDATE, SELECT_BY_ID, order)
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.soft_delete_with(SOFT_DELETE, id)
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.restore_with(RESTORE, EXISTS, id)
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.purge_with(PURGE, id)
+    }
+}
+
+impl<E: SqlExecutor> ContextualRepository for SqlOrderRepository<E> {
+    fn find_in(&self, context: &Context, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.console
+            .line(format_args!("  [SQL] Finding order {id:?}"));
+        self.one(&commented(SELECT_BY_ID, context), &[id_param(id)])
+    }
+
+    fn list_in(&self, context: &Context) -> Result<Vec<Order>, OrderError> {
+        self.console.line(format_args!("  [SQL] Listing orders"));
+        self.orders(&commented(SELECT_ALL, context), &[])
+    }
+
+    fn save_in(&self, context: &Context, order: &Order) -> Result<(), OrderError> {
+        self.console
+            .line(format_args!("  [SQL] Saving order {:?}", order.id));
+        let upsert = commented(UPSERT, context);
+        self.executor.execute(&upsert, &order_to_params(order)?)?;
+        Ok(())
+    }
+
+    fn update_in(&self, context: &Context, order: &Order) -> Result<(), OrderError> {
+        self.update_with(
+            &commented(UPDATE, context),
+            &commented(SELECT_BY_ID, context),
+            order,
+        )
+    }
+
+    fn soft_delete_in(&self, context: &Context, id: OrderId) -> Result<(), OrderError> {
+        self.soft_delete_with(&commented(SOFT_DELETE, context), id)
+    }
+
+    fn restore_in(&self, context: &Context, id: OrderId) -> Result<(), OrderError> {
+        self.restore_with(
+            &commented(RESTORE, context),
+            &commented(EXISTS, context),
+            id,
+        )
+    }
+
+    fn purge_in(&self, context: &Context, id: OrderId) -> Result<(), OrderError> {
+        self.purge_with(&commented(PURGE, context), id)
+    }
+
+    fn list_deleted_in(&self, context: &Context) -> Result<Vec<Order>, OrderError> {
+        self.orders(&commented(SELECT_DELETED, context), &[])
+    }
+
+    fn find_by_key_in(
+        &self,
+        context: &Context,
+        key: OrderKey,
+    ) -> Result<Option<Order>, OrderError> {
+        self.find_by_key_with(
+            &commented(SELECT_BY_ID, context),
+            &commented(SELECT_BY_UUID, context),
+            key,
+        )
+    }
+
+    fn find_by_reference_in(
+        &self,
+        context: &Context,
+        reference: &OrderReference,
+    ) -> Result<Option<Order>, OrderError> {
+        self.find_by_reference_with(&commented(SELECT_BY_REFERENCE, context), reference)
+    }
+}
+
+fn commented(sql: &str, context: &Context) -> String {
+    format!("{sql} /*traceparent='{}'*/", context.trace.traceparent())
+}
+
+impl<E: SqlExecutor> UnitOfWork for SqlOrderRepository<E> {
+    fn begin(&mut self) {
+        self.console.line(format_args!("  [SQL] BEGIN"));
+        self.broken_transaction = self.executor.execute(BEGIN, &[]).is_err();
+    }
+
+    fn commit(&mut self) -> Result<(), OrderError> {
+        self.console.line(format_args!("  [SQL] COMMIT"));
+        if std::mem::take(&mut self.broken_transaction) {
+            return Err(OrderError::StorageFailed);
+        }
+        self.executor.execute(COMMIT, &[]).map(drop)
+    }
+
+    fn rollback(&mut self) {
+        self.console.line(format_args!("  [SQL] ROLLBACK"));
+        // Nothing to undo when BEGIN failed; and a failed rollback leaves
+        // nothing to retry: the database drops the transaction itself.
+        if !std::mem::take(&mut self.broken_transaction) {
+            let _ = self.executor.execute(ROLLBACK, &[]);
+        }
+    }
+}
+
+impl<E: SqlExecutor> Capability for SqlOrderRepository<E> {
+    fn as_transactional(&mut self) -> Option<&mut dyn UnitOfWork> {
+        Some(self)
+    }
+}
+
+fn id_param(id: OrderId) -> SqlValue {
+    SqlValue::Integer(i64::from(id.0))
+}
+
+// The UPSERT's ?1..?15.
+fn order_to_params(order: &Order) -> Result<Vec<SqlValue>, OrderError> {
+    let text = |text: String| SqlValue::Text(text);
+    let placed_at = match order.placed_at {
+        None => SqlValue::Null,
+        Some(at) => SqlValue::Integer(i64::try_from(at.0).map_err(|_| OrderError::StorageFailed)?),
+    };
+    Ok(vec![
+        id_param(order.id),
+        text(order.status.to_string()),
+        SqlValue::Integer(i64::from(order.total.0)),
+        placed_at,
+        order
+            .uuid
+            .map_or(SqlValue::Null, |uuid| text(uuid.to_string())),
+        SqlValue::Integer(i64::from(order.version)),
+        order
+            .approval
+            .as_ref()
+            .map_or(SqlValue::Null, |approval| text(approval_json(approval))),
+        text(items_json(&order.items)),
+        text(shipments_json(&order.shipments)),
+        order
+            .shipping_address
+            .as_ref()
+            .map_or(SqlValue::Null, |address| text(address_json(address))),
+        order
+            .gift_note
+            .as_ref()
+            .map_or(SqlValue::Null, |note| text(note.clone())),
+        text(order.currency.to_string()),
+        text(notes_json(&order.notes)),
+        text(attachments_json(&order.attachments)),
+        order
+            .reference
+            .as_ref()
+            .map_or(SqlValue::Null, |reference| text(reference.0.clone())),
+    ])
+}
+
+fn items_json(items: &[LineItem]) -> String {
+    let items: Vec<String> = items
+        .iter()
+        .map(|item| {
+            format!(
+                r#"{{"name":"{}","price":{}}}"#,
+                json::escape(&item.name),
+                item.price.0
+            )
+        })
+        .collect();
+    format!("[{}]", items.join(","))
+}
+
+fn shipments_json(shipments: &[Shipment]) -> String {
+    let shipments: Vec<String> = shipments
+        .iter()
+        .map(|shipment| {
+            let items: Vec<String> = shipment.item_indices.iter().map(usize::to_string).collect();
+            format!(
+                r#"{{"tracking":"{}","items":[{}],"shipped_at":{}}}"#,
+                json::escape(&shipment.tracking.0),
+                items.join(","),
+                shipment.shipped_at.0
+            )
+        })
+        .collect();
+    format!("[{}]", shipments.join(","))
+}
+
+fn notes_json(notes: &[OrderNote]) -> String {
+    let notes: Vec<String> = notes
+        .iter()
+        .map(|note| {
+            format!(
+                r#"{{"author":"{}","text":"{}","at":{}}}"#,
+                json::escape(&note.author),
+                json::escape(&note.text),
+                note.at.0
+            )
+        })
+        .collect();
+    format!("[{}]", notes.join(","))
+}
+
+fn attachments_json(attachments: &[Attachment]) -> String {
+    let attachments: Vec<String> = attachments
+        .iter()
+        .map(|attachment| {
+            format!(
+                r#"{{"blob":{},"filename":"{}","bytes":{}}}"#,
+                attachment.blob.0,
+                json::escape(&attachment.filename),
+                attachment.bytes
+            )
+        })
+        .collect();
+    format!("[{}]", attachments.join(","))
+}
+
+fn address_json(address: &Address) -> String {
+    format!(
+        r#"{{"street":"{}","city":"{}","postal_code":"{}","country":"{}"}}"#,
+        json::escape(&address.street),
+        json::escape(&address.city),
+        json::escape(&address.postal_code),
+        json::escape(&address.country)
+    )
+}
+
+fn approval_json(approval: &Approval) -> String {
+    match approval {
+        Approval::Approved { by } => format!(r#"{{"approved_by":"{}"}}"#, json::escape(&by.0)),
+        Approval::Rejected { reason } => format!(r#"{{"rejected":"{}"}}"#, json::escape(reason)),
+    }
+}
+
+// A row the repository did not write, or a newer schema: StorageFailed
+// rather than an order with made up fields.
+fn order_from_row(row: &[SqlValue]) -> Result<Order, OrderError> {
+    let nullable = |at| match column(row, at)? {
+        SqlValue::Null => Ok(None),
+        value => Ok(Some(value)),
+    };
+    let placed_at = nullable(3)?
+        .map(|at| u64_of(at).map(Timestamp))
+        .transpose()?;
+    let uuid = nullable(4)?
+        .map(|uuid| {
+            text_of(uuid)?
+                .parse::<Uuid128>()
+                .map_err(|_| OrderError::StorageFailed)
+        })
+        .transpose()?;
+    let approval = nullable(6)?
+        .map(|approval| approval_from_json(text_of(approval)?))
+        .transpose()?;
+    let shipping_address = nullable(9)?
+        .map(|address| address_from_json(text_of(address)?))
+        .transpose()?;
+    let gift_note = nullable(10)?
+        .map(|note| text_of(note).map(str::to_string))
+        .transpose()?;
+    let currency =
+        Currency::from_code(column(row, 11).and_then(text_of)?).ok_or(OrderError::StorageFailed)?;
+    let notes = notes_from_json(column(row, 12).and_then(text_of)?)?;
+    let attachments = attachments_from_json(column(row, 13).and_then(text_of)?)?;
+    let reference = nullable(14)?
+        .map(|reference| text_of(reference).map(|text| OrderReference(text.to_string())))
+        .transpose()?;
+    Ok(Order {
+        id: OrderId(column(row, 0).and_then(u32_of)?),
+        items: items_from_json(column(row, 7).and_then(text_of)?)?,
+        total: Money(column(row, 2).and_then(u32_of)?),
+        currency,
+        status: status(column(row, 1).and_then(text_of)?)?,
+        shipments: shipments_from_json(column(row, 8).and_then(text_of)?)?,
+        placed_at,
+        uuid,
+        reference,
+        version: column(row, 5).and_then(u32_of)?,
+        approval,
+        shipping_address,
+        gift_note,
+        notes,
+        attachments,
+    })
+}
+
+fn column(row: &[SqlValue], at: usize) -> Result<&SqlValue, OrderError> {
+    row.get(at).ok_or(OrderError::StorageFailed)
+}
+
+fn u64_of(value: &SqlValue) -> Result<u64, OrderError> {
+    match value {
+        SqlValue::Integer(n) => u64::try_from(*n).map_err(|_| OrderError::StorageFailed),
+        _ => Err(OrderError::StorageFailed),
+    }
+}
+
+fn u32_of(value: &SqlValue) -> Result<u32, OrderError> {
+    u32::try_from(u64_of(value)?).map_err(|_| OrderError::StorageFailed)
+}
+
+fn text_of(value: &SqlValue) -> Result<&str, OrderError> {
+    match value {
+        SqlValue::Text(text) => Ok(text),
+        _ => Err(OrderError::StorageFailed),
+    }
+}
+
+fn status(name: &str) -> Result<OrderStatus, OrderError> {
+    [
+        OrderStatus::Placed,
+        OrderStatus::PendingApproval,
+        OrderStatus::UnderReview,
+        OrderStatus::PaymentPending,
+        OrderStatus::Paid,
+        OrderStatus::Shipped,
+        OrderStatus::Cancelled,
+    ]
+    .into_iter()
+    .find(|status| status.to_string() == name)
+    .ok_or(OrderError::StorageFailed)
+}
+
+fn items_from_json(text: &str) -> Result<Vec<LineItem>, OrderError> {
+    let Some(Value::Array(items)) = json::parse(text) else {
+        return Err(OrderError::StorageFailed);
+    };
+    items
+        .iter()
+        .map(|item| match fields(item)? {
+            [
+                (name_key, Value::String(name)),
+                (price_key, Value::Number(price)),
+            ] if name_key == "name" && price_key == "price" => Some(LineItem {
+                name: name.clone(),
+                price: Money(u32::try_from(*price).ok()?),
+            }),
+            _ => None,
+        })
+        .collect::<Option<_>>()
+        .ok_or(OrderError::StorageFailed)
+}
+
+fn shipments_from_json(text: &str) -> Result<Vec<Shipment>, OrderError> {
+    let Some(Value::Array(shipments)) = json::parse(text) else {
+        return Err(OrderError::StorageFailed);
+    };
+    shipments
+        .iter()
+        .map(|shipment| match fields(shipment)? {
+            [
+                (tracking_key, Value::String(tracking)),
+                (items_key, Value::Array(items)),
+                (at_key, Value::Number(at)),
+            ] if tracking_key == "tracking" && items_key == "items" && at_key == "shipped_at" => {
+                let item_indices = items
+                    .iter()
+                    .map(|index| match index {
+                        Value::Number(index) => usize::try_from(*index).ok(),
+                        _ => None,
+                    })
+                    .collect::<Option<_>>()?;
+                Some(Shipment {
+                    tracking: TrackingId(tracking.clone()),
+                    item_indices,
+                    shipped_at: Timestamp(*at),
+                })
+            }
+            _ => None,
+        })
+        .collect::<Option<_>>()
+        .ok_or(OrderError::StorageFailed)
+}
+
+fn notes_from_json(text: &str) -> Result<Vec<OrderNote>, OrderError> {
+    let Some(Value::Array(notes)) = json::parse(text) else {
+        return Err(OrderError::StorageFailed);
+    };
+    notes
+        .iter()
+        .map(|note| match fields(note)? {
+            [
+                (author_key, Value::String(author)),
+                (text_key, Value::String(text)),
+                (at_key, Value::Number(at)),
+            ] if author_key == "author" && text_key == "text" && at_key == "at" => {
+                Some(OrderNote {
+                    author: author.clone(),
+                    text: text.clone(),
+                    at: Timestamp(*at),
+                })
+            }
+            _ => None,
+        })
+        .collect::<Option<_>>()
+        .ok_or(OrderError::StorageFailed)
+}
+
+fn attachments_from_json(text: &str) -> Result<Vec<Attachment>, OrderError> {
+    let Some(Value::Array(attachments)) = json::parse(text) else {
+        return Err(OrderError::StorageFailed);
+    };
+    attachments
+        .iter()
+        .map(|attachment| match fields(attachment)? {
+            [
+                (blob_key, Value::Number(blob)),
+                (name_key, Value::String(filename)),
+                (bytes_key, Value::Number(bytes)),
+            ] if blob_key == "blob" && name_key == "filename" && bytes_key == "bytes" => {
+                Some(Attachment {
+                    blob: BlobId(*blob),
+                    filename: filename.clone(),
+                    bytes: *bytes,
+                })
+            }
+            _ => None,
+        })
+        .collect::<Option<_>>()
+        .ok_or(OrderError::StorageFailed)
+}
+
+fn approval_from_json(text: &str) -> Result<Approval, OrderError> {
+    match json::parse(text).as_ref().and_then(fields) {
+        Some([(kind, Value::String(by))]) if kind == "approved_by" => Ok(Approval::Approved {
+            by: ApproverId(by.clone()),
+        }),
+        Some([(kind, Value::String(reason))]) if kind == "rejected" => Ok(Approval::Rejected {
+            reason: reason.clone(),
+        }),
+        _ => Err(OrderError::StorageFailed),
+    }
+}
+
+fn address_from_json(text: &str) -> Result<Address, OrderError> {
+    match json::parse(text).as_ref().and_then(fields) {
+        Some(
+            [
+                (street_key, Value::String(street)),
+                (city_key, Value::String(city)),
+                (postal_key, Value::String(postal_code)),
+                (country_key, Value::String(country)),
+            ],
+        ) if street_key == "street"
+            && city_key == "city"
+            && postal_key == "postal_code"
+            && country_key == "country" =>
+        {
+            Ok(Address {
+                street: street.clone(),
+                city: city.clone(),
+                postal_code: postal_code.clone(),
+                country: country.clone(),
+            })
+        }
+        _ => Err(OrderError::StorageFailed),
+    }
+}
+
+fn fields(value: &Value) -> Option<&[(String, Value)]> {
+    match value {
+        Value::Object(fields) => Some(fields),
+        _ => None,
+    }
+}
+
+// One statement as the executor received it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Statement {
+    pub sql: String,
+    pub params: Vec<SqlValue>,
+}
+
+// The SqlExecutor for tests and demos, without a database: every
+// statement is recorded, and the orders table lives in memory.
+//
+// It runs the statements of SqlOrderRepository, and only those: anything
+// else is StorageFailed. That keeps it honest about what it is, a stand-in
+// checking what the repository asks for, not a SQL engine.
+//
+// On a simulated network (see adapters::network), a statement that does
+// not get through is StorageFailed: it is neither run nor recorded.
+//
+// A comment at the end of a statement is recorded with it, and otherwise
+// ignored.
+#[derive(Debug, Default)]
+pub struct FakeExecutor {
+    statements: Mutex<Vec<Statement>>,
+    tables: Mutex<Tables>,
+    network: Option<Arc<NetworkConditions>>,
+}
+
+// By id: the UPSERT's fifteen columns, and whether the order is deleted.
+type Table = BTreeMap<i64, (Row, bool)>;
+
+#[derive(Debug, Default)]
+struct Tables {
+    orders: Table,
+    // The table as it was at BEGIN, put back by ROLLBACK.
+    snapshot: Option<Table>,
+}
+
+impl FakeExecutor {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_network(mut self, network: Arc<NetworkConditions>) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    // Everything executed or queried so far, oldest first.
+    pub fn statements(&self) -> Vec<Statement> {
+        self.statements
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    // Sends the statement to the "database", which records it.
+    fn record(&self, sql: &str, params: &[SqlValue]) -> Result<(), OrderError> {
+        if let Some(network) = &self.network {
+            network.call().map_err(|_| OrderError::StorageFailed)?;
+        }
+        self.statements
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(Statement {
+                sql: sql.to_string(),
+                params: params.to_vec(),
+            });
+        Ok(())
+    }
+
+    fn tables(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn rows(&self, keep: impl Fn(i64, &Row, bool) -> bool) -> Vec<Row> {
+        self.tables()
+            .orders
+            .iter()
+            .filter(|(id, (row, deleted))| keep(**id, row, *deleted))
+            .map(|(_, (row, _))| row.clone())
+            .collect()
+    }
+}
+
+// The first parameter, which the statements below all bind to the id (or
+// the uuid, or the reference).
+fn first(params: &[SqlValue]) -> Result<&SqlValue, OrderError> {
+    params.first().ok_or(OrderError::StorageFailed)
+}
+
+fn id_of(params: &[SqlValue]) -> Result<i64, OrderError> {
+    match first(params)? {
+        SqlValue::Integer(id) => Ok(*id),
+        _ => Err(OrderError::StorageFailed),
+    }
+}
+
+fn without_comment(sql: &str) -> &str {
+    sql.strip_suffix("*/")
+        .and_then(|rest| rest.rsplit_once(" /*"))
+        .map_or(sql, |(statement, _)| statement)
+}
+
+impl SqlExecutor for FakeExecutor {
+    fn execute(&self, sql: &str, params: &[SqlValue]) -> Result<u64, OrderError> {
+        self.record(sql, params)?;
+        let mut tables = self.tables();
+        let tables = &mut *tables;
+        match without_comment(sql) {
+            CREATE_TABLE => Ok(0),
+            UPSERT if params.len() == 15 => {
+                let id = id_of(params)?;
+                let table = &mut tables.orders;
+                let deleted = table.get(&id).is_some_and(|(_, deleted)| *deleted);
+                table.insert(id, (params.to_vec(), deleted));
+                Ok(1)
+            }
+            UPDATE if params.len() == 16 => match tables.orders.get_mut(&id_of(params)?) {
+                Some((row, false)) if row[5] == params[15] => {
+                    *row = params[..15].to_vec();
+                    Ok(1)
+                }
+                _ => Ok(0),
+            },
+            SOFT_DELETE => match tables.orders.get_mut(&id_of(params)?) {
+                Some((_, deleted)) => {
+                    *deleted = true;
+                    Ok(1)
+                }
+                None => Ok(0),
+            },
+            RESTORE => match tables.orders.get_mut(&id_of(params)?) {
+                Some((_, deleted)) if *deleted => {
+                    *deleted = false;
+                    Ok(1)
+                }
+                _ => Ok(0),
+            },
+            PURGE => Ok(tables.orders.remove(&id_of(params)?).map_or(0, |_| 1)),
+            BEGIN => {
+                tables.snapshot = Some(tables.orders.clone());
+                Ok(0)
+            }
+            COMMIT => {
+                tables.snapshot = None;
+                Ok(0)
+            }
+            ROLLBACK => {
+                if let Some(snapshot) = tables.snapshot.take() {
+                    tables.orders = snapshot;
+                }
+                Ok(0)
+            }
+            _ => Err(OrderError::StorageFailed),
+        }
+    }
+
+    fn query(&self, sql: &str, params: &[SqlValue]) -> Result<Rows, OrderError> {
+        self.record(sql, params)?;
+        let rows = match without_comment(sql) {
+            SELECT_BY_ID => {
+                let id = id_of(params)?;
+                self.rows(|at, _, deleted| at == id && !deleted)
+            }
+            SELECT_BY_UUID => {
+                let uuid = first(params)?;
+                self.rows(|_, row, deleted| &row[4] == uuid && !deleted)
+            }
+            SELECT_BY_REFERENCE => {
+                let reference = first(params)?;
+                self.rows(|_, row, deleted| &row[14] == reference && !deleted)
+            }
+            SELECT_ALL => self.rows(|_, _, deleted| !deleted),
+            SELECT_DELETED => self.rows(|_, _, deleted| deleted),
+            EXISTS => {
+                let id = id_of(params)?;
+                self.rows(|at, _, deleted| at == id && !deleted)
+                    .into_iter()
+                    .map(|_| vec![SqlValue::Integer(1)])
+                    .collect()
+            }
+            SELECT_TOTAL => {
+                let id = id_of(params)?;
+                self.rows(|at, _, deleted| at == id && !deleted)
+                    .into_iter()
+                    .map(|row| vec![row[2].clone()])
+                    .collect()
+            }
+            _ => return Err(OrderError::StorageFailed),
+        };
+        Ok(rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ports::TraceContext;
+
+    fn repository() -> SqlOrderRepository<FakeExecutor> {
+        SqlOrderRepository::new(FakeExecutor::new()).unwrap()
+    }
+
+    fn pen(id: u32) -> Order {
+        Order::new(OrderId(id), vec![LineItem::new("Pen \"Bic\"", Money(150))]).unwrap()
+    }
+
+    fn last(repo: &SqlOrderRepository<FakeExecutor>) -> Statement {
+        repo.executor().statements().pop().unwrap()
+    }
+
+    #[test]
+    fn statements_in_context_end_with_their_traceparent() {
+        let repo = repository();
+        let context = Context {
+            trace: TraceContext {
+                trace_id: 0x4bf9_2f35_77b3_4da6_a3ce_929d_0e0e_4736,
+                span_id: 0x00f0_67aa_0ba9_02b7,
+                parent: None,
+            },
+        };
+
+        repo.save_in(&context, &pen(7)).unwrap();
+        let saved = last(&repo);
+        let found = repo.find_in(&context, OrderId(7)).unwrap();
+
+        let comment = " /*traceparent='00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01'*/";
+        assert_eq!(saved.sql, format!("{UPSERT}{comment}"));
+        assert_eq!(last(&repo).sql, format!("{SELECT_BY_ID}{comment}"));
+        assert_eq!(found, Some(pen(7)));
+    }
+
+    #[test]
+    fn save_binds_every_column_in_order() {
+        let repo = repository();
+        repo.save(&pen(7)).unwrap();
+
+        let statement = last(&repo);
+        assert!(statement.sql.starts_with(
+            "INSERT INTO orders (id, status, total, placed_at, uuid, version, approval, items, \
+             shipments, shipping_address, gift_note, currency, notes, attachments, reference) \
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15) ON \
+             CONFLICT (id) DO UPDATE SET"
+        ));
+        assert_eq!(
+            statement.params,
+            [
+                SqlValue::Integer(7),
+                SqlValue::Text("Placed".to_string()),
+                SqlValue::Integer(150),
+                SqlValue::Null,
+                SqlValue::Null,
+                SqlValue::Integer(0),
+                SqlValue::Null,
+                SqlValue::Text(r#"[{"name":"Pen \"Bic\"","price":150}]"#.to_string()),
+                SqlValue::Text("[]".to_string()),
+                SqlValue::Null,
+                SqlValue::Null,
+                SqlValue::Text("USD".to_string()),
+                SqlValue::Text("[]".to_string()),
+                SqlValue::Text("[]".to_string()),
+                SqlValue::Null,
+            ]
+        );
+    }
+
+    #[test]
+    fn reads_bind_the_id_and_never_inline_it() {
+        let repo = repository();
+        repo.save(&pen(7)).unwrap();
+
+        assert!(repo.find(OrderId(7)).unwrap().is_some());
+        assert_eq!(
+            last(&repo),
+            Statement {
+                sql: "SELECT id, status, total, placed_at, uuid, version, approval, items, \
+                      shipments, shipping_address, gift_note, currency, notes, attachments, \
+                      reference FROM orders WHERE id = ?1 AND deleted = 0"
+                    .to_string(),
+                params: vec![SqlValue::Integer(7)],
+            }
+        );
+        assert_eq!(repo.total_of(OrderId(7)).unwrap(), Some(Money(150)));
+        assert_eq!(
+            last(&repo).sql,
+            "SELECT total FROM orders WHERE id = ?1 AND deleted = 0"
+        );
+        repo.soft_delete(OrderId(7)).unwrap();
+        assert_eq!(
+            last(&repo),
+            Statement {
+                sql: "UPDATE orders SET deleted = 1 WHERE id = ?1".to_string(),
+                params: vec![SqlValue::Integer(7)],
+            }
+        );
+    }
+
+    #[test]
+    fn every_field_survives_the_row() {
+        let repo = repository();
+        let mut order = Order::new(
+            OrderId(3),
+            vec![
+                LineItem::new("Mug", Money(900)),
+                LineItem::new("Lid", Money(100)),
+            ],
+        )
+        .unwrap();
+        order.status = OrderStatus::Shipped;
+        order.placed_at = Some(Timestamp(1_700_000_000));
+        order.uuid = Some(Uuid128::v4(1, 2));
+        order.reference = Some(OrderReference("2024-000003".to_string()));
+        order.version = 4;
+        order.approval = Some(Approval::Rejected {
+            reason: "over \"budget\"".to_string(),
+        });
+        order.shipments = vec![Shipment {
+            tracking: TrackingId("PARCEL-3".to_string()),
+            item_indices: vec![0, 1],
+            shipped_at: Timestamp(1_700_000_600),
+        }];
+        order.shipping_address = Some(Address {
+            street: "1 \"Main\" St".to_string(),
+            city: "Springfield".to_string(),
+            postal_code: "12345".to_string(),
+            country: "US".to_string(),
+        });
+        order.gift_note = Some("Enjoy!".to_string());
+        order.currency = Currency::Jpy;
+        order
+            .add_note("ann", "Ring \"twice\"", Timestamp(1_700_000_700))
+            .unwrap();
+        order.attachments.push(Attachment {
+            blob: BlobId(4),
+            filename: "proof.png".to_string(),
+            bytes: 512,
+        });
+
+        repo.save(&order).unwrap();
+
+        assert_eq!(repo.find(OrderId(3)).unwrap(), Some(order.clone()));
+        assert_eq!(
+            repo.find_by_key(OrderKey::Random(Uuid128::v4(1, 2)))
+                .unwrap(),
+            Some(order.clone())
+        );
+        assert_eq!(
+            repo.find_by_reference(&OrderReference("2024-000003".to_string()))
+                .unwrap(),
+            Some(order)
+        );
+    }
+
+    #[test]
+    fn a_row_it_did_not_write_is_refused() {
+        let mut row = order_to_params(&pen(1)).unwrap();
+        assert!(order_from_row(&row).is_ok());
+
+        row[1] = SqlValue::Text("Lost".to_string());
+        assert!(matches!(
+            order_from_row(&row),
+            Err(OrderError::StorageFailed)
+        ));
+        assert!(matches!(
+            order_from_row(&row[..5]),
+            Err(OrderError::StorageFailed)
+        ));
+    }
+
+    #[test]
+    fn rollback_puts_the_table_back() {
+        let mut repo = repository();
+        repo.save(&pen(1)).unwrap();
+
+        repo.begin();
+        repo.save(&pen(2)).unwrap();
+        assert!(repo.exists(OrderId(2)).unwrap(), "visible inside");
+        repo.rollback();
+
+        assert!(!repo.exists(OrderId(2)).unwrap());
+        assert!(repo.exists(OrderId(1)).unwrap());
+    }
+
+    #[test]
+    fn an_update_only_changes_the_row_at_the_version_it_was_read_at() {
+        let repo = repository();
+        repo.save(&pen(7)).unwrap();
+        let stale = pen(7);
+
+        repo.update(&stale).unwrap();
+        let statement = last(&repo);
+        assert_eq!(statement.sql, UPDATE);
+        assert_eq!(
+            (&statement.params[5], &statement.params[15]),
+            (&SqlValue::Integer(1), &SqlValue::Integer(0))
+        );
+        assert!(matches!(
+            repo.update(&stale),
+            Err(OrderError::VersionConflict {
+                expected: 0,
+                found: 1,
+                ..
+            })
+        ));
+        assert_eq!(repo.find(OrderId(7)).unwrap().unwrap().version, 1);
+        assert!(matches!(
+            repo.update(&pen(8)),
+            Err(OrderError::NotFound { id: OrderId(8) })
+        ));
+    }
+
+    #[test]
+    fn purge_deletes_the_row_deleted_or_not() {
+        let repo = repository();
+        repo.save(&pen(1)).unwrap();
+        repo.save(&pen(2)).unwrap();
+        repo.soft_delete(OrderId(2)).unwrap();
+
+        repo.purge(OrderId(2)).unwrap();
+        assert_eq!(last(&repo).sql, PURGE);
+        repo.purge(OrderId(1)).unwrap();
+
+        assert!(repo.list().unwrap().is_empty());
+        assert!(repo.list_deleted().unwrap().is_empty());
+        assert!(matches!(
+            repo.purge(OrderId(1)),
+            Err(OrderError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn the_fake_refuses_sql_it_does_not_know() {
+        let executor = FakeExecutor::new();
+        assert!(executor.execute("DROP TABLE orders", &[]).is_err());
+        assert!(executor.query("SELECT * FROM orders", &[]).is_err());
+        assert_eq!(executor.statements().len(), 2, "refused, but recorded");
+    }
+}
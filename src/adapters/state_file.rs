@@ -0,0 +1,119 @@
+// --- File state store ---
+// ServiceState as one small JSON document:
+//
+//     {"next_id":4,"open_drafts":[2,3]}
+//
+// Written to a temporary file first, then renamed over the previous one,
+// so a crash while saving leaves the old state, never half of the new.
+use super::atomic_file;
+use super::json::{self, Value};
+use crate::domain::{OrderError, OrderId};
+use crate::ports::{Capability, ServiceState, StateStore};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+pub struct FileStateStore {
+    path: PathBuf,
+}
+
+impl FileStateStore {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl StateStore for FileStateStore {
+    fn save_state(&self, state: &ServiceState) -> Result<(), OrderError> {
+        atomic_file::replace(&self.path, state_json(state).as_bytes())
+            .map_err(|_| OrderError::StorageFailed)
+    }
+
+    // A file that cannot be understood is a failure, not a fresh start:
+    // starting from id 1 again would overwrite existing orders.
+    fn load_state(&self) -> Result<Option<ServiceState>, OrderError> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => state_from_json(&text)
+                .map(Some)
+                .ok_or(OrderError::StorageFailed),
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(_) => Err(OrderError::StorageFailed),
+        }
+    }
+}
+
+impl Capability for FileStateStore {}
+
+fn state_json(state: &ServiceState) -> String {
+    let drafts: Vec<String> = state
+        .open_drafts
+        .iter()
+        .map(|id| id.0.to_string())
+        .collect();
+    format!(
+        r#"{{"next_id":{},"open_drafts":[{}]}}"#,
+        state.next_id,
+        drafts.join(",")
+    )
+}
+
+fn state_from_json(text: &str) -> Option<ServiceState> {
+    let id = |value: &Value| match value {
+        Value::Number(n) => u32::try_from(*n).ok(),
+        _ => None,
+    };
+    let Value::Object(fields) = json::parse(text)? else {
+        return None;
+    };
+    let field = |name: &str| {
+        fields
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    };
+    let Value::Array(drafts) = field("open_drafts")? else {
+        return None;
+    };
+    Some(ServiceState {
+        next_id: id(field("next_id")?)?,
+        open_drafts: drafts
+            .iter()
+            .map(|draft| id(draft).map(OrderId))
+            .collect::<Option<_>>()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn state_survives_the_round_trip() {
+        for state in [
+            ServiceState {
+                next_id: 4,
+                open_drafts: vec![OrderId(2), OrderId(3)],
+            },
+            ServiceState {
+                next_id: 1,
+                open_drafts: vec![],
+            },
+        ] {
+            assert_eq!(state_from_json(&state_json(&state)), Some(state));
+        }
+    }
+
+    #[test]
+    fn malformed_state_is_refused() {
+        assert_eq!(state_from_json(r#"{"next_id":4}"#), None);
+        assert_eq!(state_from_json(r#"{"next_id":-4,"open_drafts":[]}"#), None);
+        assert_eq!(
+            state_from_json(r#"{"next_id":4,"open_drafts":["2"]}"#),
+            None
+        );
+    }
+}
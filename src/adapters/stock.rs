@@ -0,0 +1,248 @@
+// --- Low-stock alerts (decorator) ---
+// Watches what reservations leave on the shelf. The reservation taking an
+// item under `threshold` publishes InventoryEvent::LowStock; the ones after
+// it, already under, publish nothing until a restock or a release brings
+// the item back up. Every restock publishes Restocked.
+//
+// The levels before and after a reservation are read under one lock, so
+// of two reservations racing past the threshold exactly one sees it
+// crossed. Stock itself is only as safe as the inner inventory: an
+// InMemoryInventory reserves under its own lock, and never oversells.
+//
+// --- Ops notifier (subscriber) ---
+// Where the LowStock events end up: a Notice to the ops contact, through
+// whichever Sender reaches them.
+use crate::domain::{
+    InventoryEvent, LineItem, Notice, OrderError, OrderEvent, Timestamp, WarehouseId,
+};
+use crate::ports::{Capability, EventPublisher, Inventory, ReservationId, Sender};
+use std::collections::BTreeSet;
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+pub struct LowStockAlerts<'a, I: Inventory> {
+    inner: I,
+    events: &'a (dyn EventPublisher + Sync),
+    threshold: u32,
+    watch: Mutex<()>,
+}
+
+impl<'a, I: Inventory> LowStockAlerts<'a, I> {
+    pub fn new(inner: I, events: &'a (dyn EventPublisher + Sync), threshold: u32) -> Self {
+        Self {
+            inner,
+            events,
+            threshold,
+            watch: Mutex::new(()),
+        }
+    }
+
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    pub fn threshold(&self) -> u32 {
+        self.threshold
+    }
+
+    // The LowStock events of a reservation, taken under the watch lock.
+    fn reserve_watched(
+        &self,
+        reservation: ReservationId,
+        items: &[LineItem],
+    ) -> Result<Vec<InventoryEvent>, OrderError> {
+        let _watch = self.watch.lock().unwrap_or_else(PoisonError::into_inner);
+        let names: BTreeSet<&str> = items.iter().map(|item| item.name.as_str()).collect();
+        let mut before = Vec::with_capacity(names.len());
+        for name in names {
+            before.push((name, self.inner.stock_level(name)?));
+        }
+        self.inner.reserve(reservation, items)?;
+        let mut crossed = Vec::new();
+        for (name, was) in before {
+            let remaining = self.inner.stock_level(name)?;
+            if was >= self.threshold && remaining < self.threshold {
+                crossed.push(InventoryEvent::LowStock {
+                    item: name.to_string(),
+                    remaining,
+                });
+            }
+        }
+        Ok(crossed)
+    }
+}
+
+impl<I: Inventory> Inventory for LowStockAlerts<'_, I> {
+    // The units stay reserved when publishing fails; reserving again under
+    // the same id does nothing, as usual.
+    fn reserve(&self, reservation: ReservationId, items: &[LineItem]) -> Result<(), OrderError> {
+        for event in self.reserve_watched(reservation, items)? {
+            self.events.publish_inventory(&event)?;
+        }
+        Ok(())
+    }
+
+    fn confirm(&self, reservation: ReservationId) -> Result<(), OrderError> {
+        self.inner.confirm(reservation)
+    }
+
+    fn release(&self, reservation: ReservationId) -> Result<(), OrderError> {
+        let _watch = self.watch.lock().unwrap_or_else(PoisonError::into_inner);
+        self.inner.release(reservation)
+    }
+
+    fn restock(&self, item: &str, units: u32) -> Result<(), OrderError> {
+        let level = {
+            let _watch = self.watch.lock().unwrap_or_else(PoisonError::into_inner);
+            self.inner.restock(item, units)?;
+            self.inner.stock_level(item)?
+        };
+        self.events.publish_inventory(&InventoryEvent::Restocked {
+            item: item.to_string(),
+            added: units,
+            level,
+        })
+    }
+
+    fn stock_level(&self, item: &str) -> Result<u32, OrderError> {
+        self.inner.stock_level(item)
+    }
+
+    fn sources(&self, reservation: ReservationId) -> Result<Option<Vec<WarehouseId>>, OrderError> {
+        self.inner.sources(reservation)
+    }
+
+    fn release_expired(
+        &self,
+        now: Timestamp,
+        ttl: Duration,
+    ) -> Result<Vec<ReservationId>, OrderError> {
+        let _watch = self.watch.lock().unwrap_or_else(PoisonError::into_inner);
+        self.inner.release_expired(now, ttl)
+    }
+}
+
+impl<I: Inventory> Capability for LowStockAlerts<'_, I> {}
+
+pub struct OpsNotifier<'a> {
+    sender: &'a (dyn Sender + Sync),
+    contact: String,
+}
+
+impl<'a> OpsNotifier<'a> {
+    pub fn new(sender: &'a (dyn Sender + Sync), contact: impl Into<String>) -> Self {
+        Self {
+            sender,
+            contact: contact.into(),
+        }
+    }
+
+    pub fn notice(&self, item: &str, remaining: u32) -> Notice {
+        Notice {
+            to: self.contact.clone(),
+            subject: format!("Low stock: {item}"),
+            lines: vec![
+                format!("{remaining} {item} left on the shelf."),
+                format!("Restock {item} before it runs out."),
+            ],
+        }
+    }
+}
+
+impl EventPublisher for OpsNotifier<'_> {
+    // Orders are the customers' business, not ops'.
+    fn publish(&self, _event: &OrderEvent) -> Result<(), OrderError> {
+        Ok(())
+    }
+
+    fn publish_inventory(&self, event: &InventoryEvent) -> Result<(), OrderError> {
+        match event {
+            InventoryEvent::LowStock { item, remaining } => {
+                self.sender.send_notice(&self.notice(item, *remaining))
+            }
+            InventoryEvent::Restocked { .. } | InventoryEvent::ReservationExpired { .. } => Ok(()),
+        }
+    }
+}
+
+impl Capability for OpsNotifier<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::Console;
+    use crate::adapters::in_memory::InMemoryInventory;
+    use crate::domain::Money;
+
+    #[derive(Default)]
+    struct Journal(Mutex<Vec<InventoryEvent>>);
+
+    impl EventPublisher for Journal {
+        fn publish(&self, _event: &OrderEvent) -> Result<(), OrderError> {
+            Ok(())
+        }
+
+        fn publish_inventory(&self, event: &InventoryEvent) -> Result<(), OrderError> {
+            self.0.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    fn mugs(count: usize) -> Vec<LineItem> {
+        vec![LineItem::new("Mug", Money(900)); count]
+    }
+
+    #[test]
+    fn low_stock_is_published_once_on_the_way_under_the_threshold() {
+        let journal = Journal::default();
+        let shelf = InMemoryInventory::new()
+            .with_stock("Mug", 5)
+            .with_console(Console::silent());
+        let inventory = LowStockAlerts::new(shelf, &journal, 3);
+
+        // Down to the threshold itself: not under it yet.
+        inventory.reserve(ReservationId(1), &mugs(2)).unwrap();
+        assert!(journal.0.lock().unwrap().is_empty());
+
+        inventory.reserve(ReservationId(2), &mugs(1)).unwrap();
+        inventory.reserve(ReservationId(3), &mugs(1)).unwrap();
+        assert_eq!(
+            journal.0.lock().unwrap()[..],
+            [InventoryEvent::LowStock {
+                item: "Mug".to_string(),
+                remaining: 2,
+            }]
+        );
+
+        // Back up, then under again: a second alert.
+        inventory.restock("Mug", 4).unwrap();
+        inventory.reserve(ReservationId(4), &mugs(3)).unwrap();
+        assert_eq!(
+            journal.0.lock().unwrap()[1..],
+            [
+                InventoryEvent::Restocked {
+                    item: "Mug".to_string(),
+                    added: 4,
+                    level: 5,
+                },
+                InventoryEvent::LowStock {
+                    item: "Mug".to_string(),
+                    remaining: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_refused_reservation_publishes_nothing() {
+        let journal = Journal::default();
+        let shelf = InMemoryInventory::new()
+            .with_stock("Mug", 3)
+            .with_console(Console::silent());
+        let inventory = LowStockAlerts::new(shelf, &journal, 3);
+
+        assert!(inventory.reserve(ReservationId(1), &mugs(4)).is_err());
+        assert_eq!(inventory.stock_level("Mug").unwrap(), 3);
+        assert!(journal.0.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+// --- Timed (decorator) ---
+// Records how long every call to the port it wraps takes, success or not,
+// as an observation of the histogram `name` in the Metrics port. Which
+// port call was slow, and how slow the slowest one in a hundred is: the
+// numbers behind a latency story, where a counter only says how many.
+//
+// The time comes from `elapsed`, anything that only goes forward: an
+// Instant's elapsed() in production, the delay a simulated network has
+// added up (NetworkConditions::stats) in a demo, where nothing sleeps.
+use crate::domain::{
+    Money, Notice, Order, OrderConfirmation, OrderError, OrderId, OrderKey, OrderReference,
+};
+use crate::ports::{
+    CancelToken, Capability, ChargeLog, ChargeOutcome, ChargeRecord, Metrics, OrderReader,
+    OrderWriter, PaymentGateway, Sender,
+};
+use std::time::Duration;
+
+pub struct Timed<'a, I, E: Fn() -> Duration> {
+    inner: I,
+    metrics: &'a (dyn Metrics + Sync),
+    name: &'static str,
+    elapsed: E,
+}
+
+impl<'a, I, E: Fn() -> Duration> Timed<'a, I, E> {
+    pub fn new(
+        inner: I,
+        metrics: &'a (dyn Metrics + Sync),
+        name: &'static str,
+        elapsed: E,
+    ) -> Self {
+        Self {
+            inner,
+            metrics,
+            name,
+            elapsed,
+        }
+    }
+
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn invoke<T>(&self, call: impl FnOnce(&I) -> Result<T, OrderError>) -> Result<T, OrderError> {
+        let started = (self.elapsed)();
+        let result = call(&self.inner);
+        let took = (self.elapsed)().saturating_sub(started);
+        let millis = u64::try_from(took.as_millis()).unwrap_or(u64::MAX);
+        self.metrics.observe_histogram(self.name, millis);
+        result
+    }
+}
+
+impl<G: PaymentGateway, E: Fn() -> Duration> PaymentGateway for Timed<'_, G, E> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.charge(amount))
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.charge_for(order_id, amount))
+    }
+
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.invoke(|inner| inner.charge_order(order_id, amount))
+    }
+
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.refund_for(order_id, amount))
+    }
+}
+
+impl<G: PaymentGateway + ChargeLog, E: Fn() -> Duration> ChargeLog for Timed<'_, G, E> {
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        self.invoke(|inner| inner.charges())
+    }
+}
+
+impl<S: Sender, E: Fn() -> Duration> Sender for Timed<'_, S, E> {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.send(confirmation))
+    }
+
+    fn send_notice(&self, notice: &Notice) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.send_notice(notice))
+    }
+}
+
+// Forwarded one by one, so the inner adapter's cheap versions are used.
+impl<R: OrderReader, E: Fn() -> Duration> OrderReader for Timed<'_, R, E> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.invoke(|inner| inner.find(id))
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.invoke(|inner| inner.list())
+    }
+
+    fn exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        self.invoke(|inner| inner.exists(id))
+    }
+
+    fn total_of(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        self.invoke(|inner| inner.total_of(id))
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.for_each(visit))
+    }
+
+    fn for_each_cancellable(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.for_each_cancellable(cancel, visit))
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        self.invoke(|inner| inner.list_deleted())
+    }
+
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.invoke(|inner| inner.find_by_key(key))
+    }
+
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        self.invoke(|inner| inner.find_by_reference(reference))
+    }
+}
+
+impl<R: OrderWriter, E: Fn() -> Duration> OrderWriter for Timed<'_, R, E> {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.save(order))
+    }
+
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.update(order))
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.soft_delete(id))
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.restore(id))
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.invoke(|inner| inner.purge(id))
+    }
+}
+
+impl<I, E: Fn() -> Duration> Capability for Timed<'_, I, E> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::Console;
+    use crate::adapters::in_memory::MockPaymentGateway;
+    use crate::adapters::metrics::InMemoryMetrics;
+    use crate::domain::Currency;
+    use std::cell::Cell;
+
+    // Each call takes as long as the next entry in `delays`.
+    struct Slow<'a> {
+        delays: Cell<&'a [u64]>,
+        now: &'a Cell<u64>,
+    }
+
+    impl Sender for Slow<'_> {
+        fn send(&self, _confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+            let (first, rest) = self.delays.get().split_first().unwrap();
+            self.now.set(self.now.get() + first);
+            self.delays.set(rest);
+            if *first > 100 {
+                return Err(OrderError::NotificationFailed);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn every_call_is_observed_failures_included() {
+        let metrics = InMemoryMetrics::new();
+        let now = Cell::new(0);
+        let slow = Slow {
+            delays: Cell::new(&[3, 30, 300]),
+            now: &now,
+        };
+        let sender = Timed::new(slow, &metrics, "sender.send", || {
+            Duration::from_millis(now.get())
+        });
+        let confirmation = OrderConfirmation {
+            order_id: OrderId(1),
+            total: Money(100),
+            items: Vec::new(),
+            tax: Money(0),
+            currency: Currency::Usd,
+            customer_email: None,
+            reference: None,
+        };
+
+        sender.send(&confirmation).unwrap();
+        sender.send(&confirmation).unwrap();
+        assert!(sender.send(&confirmation).is_err());
+
+        let send = metrics.histogram("sender.send");
+        assert_eq!(send.buckets, [0, 1, 0, 1, 0, 1, 0, 0]);
+        assert_eq!(send.sum, 333);
+    }
+
+    #[test]
+    fn charges_go_through_untouched() {
+        let metrics = InMemoryMetrics::new();
+        let inner = MockPaymentGateway::new().with_console(Console::silent());
+        let payment = Timed::new(inner, &metrics, "payment", || Duration::ZERO);
+
+        payment.charge_for(OrderId(1), Money(4999)).unwrap();
+
+        assert_eq!(payment.charges().unwrap()[0].amount, Money(4999));
+        assert_eq!(metrics.histogram("payment").count, 2);
+    }
+}
@@ -0,0 +1,287 @@
+// --- Tracing (decorators) ---
+// Trace context across the ports, so that a collector can put one use
+// case's calls together with whatever they caused downstream:
+//
+//     InContext    a Sender or a repository again, for the service, each
+//                  call with a span of its own from the Tracer
+//     Traced       logs every call with its trace and span ids
+//     Contextless  any Sender or repository as a contextual one, which
+//                  passes nothing on
+//
+//     InContext::new(Traced::new(webhook_sender), &tracer)
+//     InContext::new(Traced::new(Contextless(in_memory_repository)), &tracer)
+//
+// The webhook sender and the SQL repository are contextual themselves: the
+// first sends a traceparent header, the second puts it in a comment on
+// each statement, where the database's own logs show it.
+//
+// In context, every method of the repository is the inner one's, with a
+// span of its own: an update keeps the inner repository's version check,
+// a soft delete its place for deleted orders. exists, total_of and the
+// scans go through find and list, in context like them.
+use super::Console;
+use crate::domain::{
+    Notice, Order, OrderConfirmation, OrderError, OrderId, OrderKey, OrderReference,
+};
+use crate::ports::{
+    Capability, Context, ContextualRepository, ContextualSender, OrderReader, OrderWriter, Sender,
+    Tracer,
+};
+
+pub struct InContext<'a, I> {
+    inner: I,
+    tracer: &'a Tracer,
+}
+
+impl<'a, I> InContext<'a, I> {
+    pub fn new(inner: I, tracer: &'a Tracer) -> Self {
+        Self { inner, tracer }
+    }
+
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    fn context(&self) -> Context {
+        Context {
+            trace: self.tracer.span(),
+        }
+    }
+}
+
+impl<S: ContextualSender> Sender for InContext<'_, S> {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.inner.send_in(&self.context(), confirmation)
+    }
+
+    fn send_notice(&self, notice: &Notice) -> Result<(), OrderError> {
+        self.inner.send_notice_in(&self.context(), notice)
+    }
+}
+
+impl<R: ContextualRepository> OrderReader for InContext<'_, R> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.inner.find_in(&self.context(), id)
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.inner.list_in(&self.context())
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        self.inner.list_deleted_in(&self.context())
+    }
+
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.inner.find_by_key_in(&self.context(), key)
+    }
+
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        self.inner.find_by_reference_in(&self.context(), reference)
+    }
+}
+
+impl<R: ContextualRepository> OrderWriter for InContext<'_, R> {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.inner.save_in(&self.context(), order)
+    }
+
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        self.inner.update_in(&self.context(), order)
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.inner.soft_delete_in(&self.context(), id)
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.inner.restore_in(&self.context(), id)
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.inner.purge_in(&self.context(), id)
+    }
+}
+
+impl<I> Capability for InContext<'_, I> {}
+
+pub struct Traced<I> {
+    inner: I,
+    console: Console,
+}
+
+impl<I> Traced<I> {
+    pub fn new(inner: I) -> Self {
+        Self {
+            inner,
+            console: Console::default(),
+        }
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    //   [Trace] Sender.send trace=4bf92f3577b34da6a3ce929d0e0e4736 span=00f067aa0ba902b7 parent=-
+    fn log(&self, operation: &str, context: &Context) {
+        let trace = &context.trace;
+        let parent = trace
+            .parent
+            .map_or_else(|| "-".to_string(), |parent| format!("{parent:016x}"));
+        self.console.line(format_args!(
+            "  [Trace] {operation} trace={:032x} span={:016x} parent={parent}",
+            trace.trace_id, trace.span_id
+        ));
+    }
+}
+
+impl<S: ContextualSender> ContextualSender for Traced<S> {
+    fn send_in(
+        &self,
+        context: &Context,
+        confirmation: &OrderConfirmation,
+    ) -> Result<(), OrderError> {
+        self.log("Sender.send", context);
+        self.inner.send_in(context, confirmation)
+    }
+
+    fn send_notice_in(&self, context: &Context, notice: &Notice) -> Result<(), OrderError> {
+        self.log("Sender.send_notice", context);
+        self.inner.send_notice_in(context, notice)
+    }
+}
+
+impl<R: ContextualRepository> ContextualRepository for Traced<R> {
+    fn find_in(&self, context: &Context, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.log("OrderReader.find", context);
+        self.inner.find_in(context, id)
+    }
+
+    fn list_in(&self, context: &Context) -> Result<Vec<Order>, OrderError> {
+        self.log("OrderReader.list", context);
+        self.inner.list_in(context)
+    }
+
+    fn save_in(&self, context: &Context, order: &Order) -> Result<(), OrderError> {
+        self.log("OrderWriter.save", context);
+        self.inner.save_in(context, order)
+    }
+
+    fn update_in(&self, context: &Context, order: &Order) -> Result<(), OrderError> {
+        self.log("OrderWriter.update", context);
+        self.inner.update_in(context, order)
+    }
+
+    fn soft_delete_in(&self, context: &Context, id: OrderId) -> Result<(), OrderError> {
+        self.log("OrderWriter.soft_delete", context);
+        self.inner.soft_delete_in(context, id)
+    }
+
+    fn restore_in(&self, context: &Context, id: OrderId) -> Result<(), OrderError> {
+        self.log("OrderWriter.restore", context);
+        self.inner.restore_in(context, id)
+    }
+
+    fn purge_in(&self, context: &Context, id: OrderId) -> Result<(), OrderError> {
+        self.log("OrderWriter.purge", context);
+        self.inner.purge_in(context, id)
+    }
+
+    fn list_deleted_in(&self, context: &Context) -> Result<Vec<Order>, OrderError> {
+        self.log("OrderReader.list_deleted", context);
+        self.inner.list_deleted_in(context)
+    }
+
+    fn find_by_key_in(
+        &self,
+        context: &Context,
+        key: OrderKey,
+    ) -> Result<Option<Order>, OrderError> {
+        self.log("OrderReader.find_by_key", context);
+        self.inner.find_by_key_in(context, key)
+    }
+
+    fn find_by_reference_in(
+        &self,
+        context: &Context,
+        reference: &OrderReference,
+    ) -> Result<Option<Order>, OrderError> {
+        self.log("OrderReader.find_by_reference", context);
+        self.inner.find_by_reference_in(context, reference)
+    }
+}
+
+impl<I> Capability for Traced<I> {}
+
+pub struct Contextless<I>(pub I);
+
+impl<S: Sender> ContextualSender for Contextless<S> {
+    fn send_in(
+        &self,
+        _context: &Context,
+        confirmation: &OrderConfirmation,
+    ) -> Result<(), OrderError> {
+        self.0.send(confirmation)
+    }
+
+    fn send_notice_in(&self, _context: &Context, notice: &Notice) -> Result<(), OrderError> {
+        self.0.send_notice(notice)
+    }
+}
+
+impl<R: OrderWriter> ContextualRepository for Contextless<R> {
+    fn find_in(&self, _context: &Context, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.0.find(id)
+    }
+
+    fn list_in(&self, _context: &Context) -> Result<Vec<Order>, OrderError> {
+        self.0.list()
+    }
+
+    fn save_in(&self, _context: &Context, order: &Order) -> Result<(), OrderError> {
+        self.0.save(order)
+    }
+
+    fn update_in(&self, _context: &Context, order: &Order) -> Result<(), OrderError> {
+        self.0.update(order)
+    }
+
+    fn soft_delete_in(&self, _context: &Context, id: OrderId) -> Result<(), OrderError> {
+        self.0.soft_delete(id)
+    }
+
+    fn restore_in(&self, _context: &Context, id: OrderId) -> Result<(), OrderError> {
+        self.0.restore(id)
+    }
+
+    fn purge_in(&self, _context: &Context, id: OrderId) -> Result<(), OrderError> {
+        self.0.purge(id)
+    }
+
+    fn list_deleted_in(&self, _context: &Context) -> Result<Vec<Order>, OrderError> {
+        self.0.list_deleted()
+    }
+
+    fn find_by_key_in(
+        &self,
+        _context: &Context,
+        key: OrderKey,
+    ) -> Result<Option<Order>, OrderError> {
+        self.0.find_by_key(key)
+    }
+
+    fn find_by_reference_in(
+        &self,
+        _context: &Context,
+        reference: &OrderReference,
+    ) -> Result<Option<Order>, OrderError> {
+        self.0.find_by_reference(reference)
+    }
+}
+
+impl<I> Capability for Contextless<I> {}
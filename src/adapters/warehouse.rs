@@ -0,0 +1,312 @@
+// --- Warehouse pickers ---
+// Two ways of choosing where a line item comes from, both answering from
+// their arguments alone: the same cart on the same shelves always splits
+// the same way.
+// - NearestFirst: a fixed priority list, the nearest warehouse first. A
+//   warehouse missing from the list is too far, and never picked.
+// - MostStockFirst: wherever the most is left, the lowest id on a tie.
+//   Asked line by line, it spreads a cart over warehouses rather than
+//   emptying one.
+//
+// --- Multi-warehouse inventory ---
+// Stock by warehouse and item name, one unit per line item. Each line of a
+// reservation is taken from the warehouse the picker names, and sources()
+// remembers which. Every line is picked before anything is taken off a
+// shelf: one refused line and the whole reservation holds nothing.
+// Restocking through the port fills the receiving warehouse; restock_at
+// fills any other.
+use super::Console;
+use crate::domain::{LineItem, OrderError, WarehouseId, WarehouseStock};
+use crate::ports::{Capability, Inventory, ReservationId, WarehousePicker};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, PoisonError};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NearestFirst(pub Vec<WarehouseId>);
+
+impl WarehousePicker for NearestFirst {
+    fn pick(&self, _item: &LineItem, stock: &[WarehouseStock]) -> Option<WarehouseId> {
+        self.0.iter().copied().find(|&warehouse| {
+            stock
+                .iter()
+                .any(|s| s.warehouse == warehouse && s.available > 0)
+        })
+    }
+}
+
+impl Capability for NearestFirst {}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MostStockFirst;
+
+impl WarehousePicker for MostStockFirst {
+    fn pick(&self, _item: &LineItem, stock: &[WarehouseStock]) -> Option<WarehouseId> {
+        stock
+            .iter()
+            .filter(|s| s.available > 0)
+            .min_by_key(|s| (std::cmp::Reverse(s.available), s.warehouse))
+            .map(|s| s.warehouse)
+    }
+}
+
+impl Capability for MostStockFirst {}
+
+pub struct MultiWarehouseInventory<'a> {
+    picker: &'a (dyn WarehousePicker + Sync),
+    receiving: WarehouseId,
+    stock: Mutex<Shelves>,
+    console: Console,
+}
+
+type Shelf = BTreeMap<WarehouseId, HashMap<String, u32>>;
+
+#[derive(Default)]
+struct Shelves {
+    on_shelf: Shelf,
+    // The warehouse of each line held by a reservation, and whether it was
+    // confirmed.
+    reservations: BTreeMap<ReservationId, (Vec<(WarehouseId, String)>, bool)>,
+}
+
+impl<'a> MultiWarehouseInventory<'a> {
+    pub fn new(picker: &'a (dyn WarehousePicker + Sync), receiving: WarehouseId) -> Self {
+        Self {
+            picker,
+            receiving,
+            stock: Mutex::new(Shelves::default()),
+            console: Console::default(),
+        }
+    }
+
+    pub fn with_stock(self, warehouse: WarehouseId, name: impl Into<String>, units: u32) -> Self {
+        self.stock
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .on_shelf
+            .entry(warehouse)
+            .or_default()
+            .insert(name.into(), units);
+        self
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    pub fn receiving(&self) -> WarehouseId {
+        self.receiving
+    }
+
+    // `units` more of `item` in `warehouse`, a new one or not.
+    pub fn restock_at(&self, warehouse: WarehouseId, item: &str, units: u32) {
+        let mut stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        let level = stock
+            .on_shelf
+            .entry(warehouse)
+            .or_default()
+            .entry(item.to_string())
+            .or_default();
+        *level += units;
+        self.console.line(format_args!(
+            "  [Warehouse] Restocked {units} {item} in {warehouse:?}, {level} available there"
+        ));
+    }
+
+    // Units of `name` neither reserved nor sold in one warehouse.
+    pub fn available_in(&self, warehouse: WarehouseId, name: &str) -> u32 {
+        let stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        stock
+            .on_shelf
+            .get(&warehouse)
+            .and_then(|shelf| shelf.get(name))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    // The warehouses still holding some of `name`, in warehouse order.
+    fn stock_of(shelves: &Shelf, name: &str) -> Vec<WarehouseStock> {
+        shelves
+            .iter()
+            .filter_map(|(&warehouse, shelf)| {
+                let available = shelf.get(name).copied().unwrap_or(0);
+                (available > 0).then_some(WarehouseStock {
+                    warehouse,
+                    available,
+                })
+            })
+            .collect()
+    }
+}
+
+impl Inventory for MultiWarehouseInventory<'_> {
+    fn reserve(&self, reservation: ReservationId, items: &[LineItem]) -> Result<(), OrderError> {
+        let mut stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        if stock.reservations.contains_key(&reservation) {
+            return Ok(());
+        }
+        // Picked against what earlier lines of the cart left behind.
+        let mut left = stock.on_shelf.clone();
+        let mut taken = Vec::with_capacity(items.len());
+        for item in items {
+            let out_of_stock = || OrderError::OutOfStock {
+                item: item.name.clone(),
+            };
+            let candidates = Self::stock_of(&left, &item.name);
+            let warehouse = self
+                .picker
+                .pick(item, &candidates)
+                .filter(|picked| candidates.iter().any(|s| s.warehouse == *picked))
+                .ok_or_else(out_of_stock)?;
+            let units = left
+                .get_mut(&warehouse)
+                .and_then(|shelf| shelf.get_mut(&item.name))
+                .ok_or_else(out_of_stock)?;
+            *units -= 1;
+            taken.push((warehouse, item.name.clone()));
+        }
+        stock.on_shelf = left;
+        let mut warehouses: Vec<WarehouseId> = taken.iter().map(|&(w, _)| w).collect();
+        warehouses.sort();
+        warehouses.dedup();
+        self.console.line(format_args!(
+            "  [Warehouse] Reserved {} item(s) under {reservation:?} from {warehouses:?}",
+            items.len()
+        ));
+        stock.reservations.insert(reservation, (taken, false));
+        Ok(())
+    }
+
+    fn confirm(&self, reservation: ReservationId) -> Result<(), OrderError> {
+        let mut stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        let (_, confirmed) = stock
+            .reservations
+            .get_mut(&reservation)
+            .ok_or(OrderError::InvalidOrder)?;
+        *confirmed = true;
+        self.console
+            .line(format_args!("  [Warehouse] Confirmed {reservation:?}"));
+        Ok(())
+    }
+
+    // Every unit goes back to the warehouse it was taken from.
+    fn release(&self, reservation: ReservationId) -> Result<(), OrderError> {
+        let mut stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        let Some((held, false)) = stock.reservations.get(&reservation).cloned() else {
+            return Ok(());
+        };
+        stock.reservations.remove(&reservation);
+        for (warehouse, name) in held {
+            *stock
+                .on_shelf
+                .entry(warehouse)
+                .or_default()
+                .entry(name)
+                .or_default() += 1;
+        }
+        self.console
+            .line(format_args!("  [Warehouse] Released {reservation:?}"));
+        Ok(())
+    }
+
+    fn restock(&self, item: &str, units: u32) -> Result<(), OrderError> {
+        self.restock_at(self.receiving, item, units);
+        Ok(())
+    }
+
+    // Every warehouse together.
+    fn stock_level(&self, item: &str) -> Result<u32, OrderError> {
+        let stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(Self::stock_of(&stock.on_shelf, item)
+            .iter()
+            .map(|s| s.available)
+            .sum())
+    }
+
+    fn sources(&self, reservation: ReservationId) -> Result<Option<Vec<WarehouseId>>, OrderError> {
+        let stock = self.stock.lock().unwrap_or_else(PoisonError::into_inner);
+        Ok(stock
+            .reservations
+            .get(&reservation)
+            .map(|(held, _)| held.iter().map(|&(warehouse, _)| warehouse).collect()))
+    }
+}
+
+impl Capability for MultiWarehouseInventory<'_> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Money;
+
+    const NORTH: WarehouseId = WarehouseId(1);
+    const SOUTH: WarehouseId = WarehouseId(2);
+
+    fn stock(levels: &[(WarehouseId, u32)]) -> Vec<WarehouseStock> {
+        levels
+            .iter()
+            .map(|&(warehouse, available)| WarehouseStock {
+                warehouse,
+                available,
+            })
+            .collect()
+    }
+
+    fn mug() -> LineItem {
+        LineItem::new("Mug", Money(900))
+    }
+
+    #[test]
+    fn nearest_first_follows_the_list_and_skips_what_is_missing() {
+        let picker = NearestFirst(vec![SOUTH, NORTH]);
+
+        assert_eq!(
+            picker.pick(&mug(), &stock(&[(NORTH, 9), (SOUTH, 1)])),
+            Some(SOUTH)
+        );
+        assert_eq!(picker.pick(&mug(), &stock(&[(NORTH, 9)])), Some(NORTH));
+        // Off the list, however much it has.
+        assert_eq!(picker.pick(&mug(), &stock(&[(WarehouseId(3), 50)])), None);
+    }
+
+    #[test]
+    fn most_stock_first_breaks_ties_on_the_lowest_id() {
+        let levels = stock(&[(SOUTH, 4), (NORTH, 4), (WarehouseId(3), 2)]);
+
+        assert_eq!(MostStockFirst.pick(&mug(), &levels), Some(NORTH));
+        assert_eq!(MostStockFirst.pick(&mug(), &stock(&[(NORTH, 0)])), None);
+    }
+
+    #[test]
+    fn a_release_puts_each_unit_back_where_it_came_from() {
+        let inventory = MultiWarehouseInventory::new(&MostStockFirst, NORTH)
+            .with_stock(NORTH, "Mug", 2)
+            .with_stock(SOUTH, "Mug", 2)
+            .with_console(Console::silent());
+
+        inventory
+            .reserve(ReservationId(1), &[mug(), mug()])
+            .unwrap();
+        assert_eq!(
+            inventory.sources(ReservationId(1)).unwrap(),
+            Some(vec![NORTH, SOUTH])
+        );
+        inventory.release(ReservationId(1)).unwrap();
+
+        assert_eq!(inventory.available_in(NORTH, "Mug"), 2);
+        assert_eq!(inventory.available_in(SOUTH, "Mug"), 2);
+        assert_eq!(inventory.sources(ReservationId(1)).unwrap(), None);
+    }
+
+    #[test]
+    fn a_restock_lands_in_the_receiving_warehouse() {
+        let inventory =
+            MultiWarehouseInventory::new(&MostStockFirst, SOUTH).with_console(Console::silent());
+
+        inventory.restock("Mug", 3).unwrap();
+
+        assert_eq!(inventory.available_in(SOUTH, "Mug"), 3);
+        assert_eq!(inventory.available_in(NORTH, "Mug"), 0);
+        assert_eq!(inventory.stock_level("Mug").unwrap(), 3);
+    }
+}
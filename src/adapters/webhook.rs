@@ -0,0 +1,341 @@
+// A "simulated" webhook adapter.
+// In real life, this would POST the body with reqwest or ureq. Here the
+// requests are only recorded, which is exactly what a test server would see.
+//
+// Signing is an adapter-side concern: the domain never hears about HMACs.
+// With a signing key, every request carries:
+// - X-Timestamp : seconds since the Unix epoch when the request was built
+// - X-Signature : hex HMAC-SHA256 over "<timestamp>.<body>"
+// Including the timestamp in the signed message is what gives replay
+// protection: the receiver rejects signatures that are too old.
+//
+// The body is the confirmation's versioned envelope: see
+// confirmation_envelope.
+//
+// On a simulated network (see adapters::network), a request that does not
+// get through is NotificationFailed, and is not in delivered().
+//
+// Sent in context (see adapters::tracing), a request also carries its
+// traceparent header, outside of what is signed.
+use super::clock::SystemClock;
+use super::hmac::{constant_time_eq, hmac_sha256, to_hex};
+use super::{ConfigError, Console, NetworkConditions, SecretString, confirmation_envelope};
+use crate::domain::{OrderConfirmation, OrderError};
+use crate::ports::{Capability, Clock, Context, ContextualSender, Sender, TRACEPARENT_HEADER};
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+pub const SIGNATURE_HEADER: &str = "X-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Timestamp";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct WebhookRequest {
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl WebhookRequest {
+    // Header names are case-insensitive in HTTP.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        find_header(&self.headers, name)
+    }
+}
+
+pub struct WebhookSender {
+    url: String,
+    signing_key: Option<SecretString>,
+    clock: Box<dyn Clock + Send + Sync>,
+    network: Option<Arc<NetworkConditions>>,
+    console: Console,
+    delivered: Mutex<Vec<WebhookRequest>>,
+}
+
+impl WebhookSender {
+    // The URL must be http:// or https:// with a host.
+    pub fn new(url: impl Into<String>) -> Result<Self, ConfigError> {
+        let url = url.into();
+        if let Err(reason) = check_url(&url) {
+            return Err(ConfigError::InvalidUrl { url, reason });
+        }
+        Ok(Self {
+            url,
+            signing_key: None,
+            clock: Box::new(SystemClock),
+            network: None,
+            console: Console::stdout(),
+            delivered: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn with_signing_key(mut self, key: SecretString) -> Self {
+        self.signing_key = Some(key);
+        self
+    }
+
+    // Where X-Timestamp comes from. The system clock by default.
+    pub fn with_clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    pub fn with_network(mut self, network: Arc<NetworkConditions>) -> Self {
+        self.network = Some(network);
+        self
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    // Everything "posted" so far, oldest first.
+    pub fn delivered(&self) -> Vec<WebhookRequest> {
+        self.delivered
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    fn post(
+        &self,
+        confirmation: &OrderConfirmation,
+        context: Option<&Context>,
+    ) -> Result<(), OrderError> {
+        let body = confirmation_envelope(confirmation);
+        let mut headers = vec![("Content-Type".to_string(), "application/json".to_string())];
+        if let Some(context) = context {
+            headers.push((TRACEPARENT_HEADER.to_string(), context.trace.traceparent()));
+        }
+
+        if let Some(key) = &self.signing_key {
+            let timestamp = self.clock.now().0;
+            headers.push((TIMESTAMP_HEADER.to_string(), timestamp.to_string()));
+            headers.push((SIGNATURE_HEADER.to_string(), sign(key, timestamp, &body)));
+        }
+
+        if let Some(network) = &self.network
+            && let Err(fault) = network.call()
+        {
+            self.console.line(format_args!(
+                "  [Webhook] POST {} for order {:?} failed ({fault:?})",
+                self.url, confirmation.order_id
+            ));
+            return Err(OrderError::NotificationFailed);
+        }
+        self.console.line(format_args!(
+            "  [Webhook] POST {} for order {:?}{}",
+            self.url,
+            confirmation.order_id,
+            if self.signing_key.is_some() {
+                " (signed)"
+            } else {
+                ""
+            }
+        ));
+        self.delivered
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(WebhookRequest {
+                url: self.url.clone(),
+                headers,
+                body,
+            });
+        Ok(())
+    }
+}
+
+impl Sender for WebhookSender {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.post(confirmation, None)
+    }
+}
+
+// The same request, with the call's traceparent header.
+impl ContextualSender for WebhookSender {
+    fn send_in(
+        &self,
+        context: &Context,
+        confirmation: &OrderConfirmation,
+    ) -> Result<(), OrderError> {
+        self.post(confirmation, Some(context))
+    }
+}
+
+impl Capability for WebhookSender {}
+
+// Hex HMAC-SHA256 over "<timestamp>.<body>".
+pub fn sign(key: &SecretString, timestamp: u64, body: &str) -> String {
+    let message = format!("{timestamp}.{body}");
+    to_hex(&hmac_sha256(key.expose().as_bytes(), message.as_bytes()))
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureError {
+    MissingHeader(&'static str),
+    MalformedTimestamp,
+    // The timestamp is further from "now" than the allowed skew, in either direction.
+    OutsideSkew { delta_secs: u64 },
+    Mismatch,
+}
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+// Receiving side helper, for the test server or for users writing one.
+pub fn verify_signature(
+    body: &str,
+    headers: &[(String, String)],
+    key: &SecretString,
+    max_skew: Duration,
+) -> Result<(), SignatureError> {
+    verify_signature_at(body, headers, key, max_skew, SystemClock.now().0)
+}
+
+// Same as verify_signature with an explicit "now", so tests do not depend on the wall clock.
+pub fn verify_signature_at(
+    body: &str,
+    headers: &[(String, String)],
+    key: &SecretString,
+    max_skew: Duration,
+    now_secs: u64,
+) -> Result<(), SignatureError> {
+    let timestamp = find_header(headers, TIMESTAMP_HEADER)
+        .ok_or(SignatureError::MissingHeader(TIMESTAMP_HEADER))?;
+    let signature = find_header(headers, SIGNATURE_HEADER)
+        .ok_or(SignatureError::MissingHeader(SIGNATURE_HEADER))?;
+    let timestamp: u64 = timestamp
+        .parse()
+        .map_err(|_| SignatureError::MalformedTimestamp)?;
+
+    // Check the signature first: an attacker must not learn anything
+    // about the skew window from an unsigned request.
+    let expected = sign(key, timestamp, body);
+    if !constant_time_eq(expected.as_bytes(), signature.as_bytes()) {
+        return Err(SignatureError::Mismatch);
+    }
+
+    let delta_secs = now_secs.abs_diff(timestamp);
+    if delta_secs > max_skew.as_secs() {
+        return Err(SignatureError::OutsideSkew { delta_secs });
+    }
+    Ok(())
+}
+
+fn find_header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case(name))
+        .map(|(_, value)| value.as_str())
+}
+
+// Only what a webhook needs: an http(s) scheme and a host. EnvConfig checks
+// its URL variable with it too, to name the variable at fault.
+pub(crate) fn check_url(value: &str) -> Result<(), &'static str> {
+    let rest = value
+        .strip_prefix("https://")
+        .or_else(|| value.strip_prefix("http://"))
+        .ok_or("expected an http:// or https:// URL")?;
+    let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+    if host.is_empty() || value.chars().any(char::is_whitespace) {
+        return Err("expected a host and no whitespace");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, Money, Order, OrderId};
+
+    #[test]
+    fn url_check_accepts_http_and_https_only() {
+        assert!(check_url("https://example.test/hook").is_ok());
+        assert!(check_url("http://localhost:8080").is_ok());
+        assert!(check_url("ftp://example.test").is_err());
+        assert!(check_url("https://").is_err());
+        assert!(check_url("https://exa mple.test").is_err());
+    }
+
+    #[test]
+    fn malformed_url_is_refused_at_construction() {
+        assert_eq!(
+            WebhookSender::new("example.test/hook").err(),
+            Some(ConfigError::InvalidUrl {
+                url: "example.test/hook".to_string(),
+                reason: "expected an http:// or https:// URL",
+            })
+        );
+    }
+
+    #[test]
+    fn body_is_json_with_escaped_names() {
+        let order = Order::new(
+            OrderId(3),
+            vec![LineItem {
+                name: "12\" \"Ruler\"".to_string(),
+                price: Money(199),
+            }],
+        )
+        .unwrap();
+
+        let sender = WebhookSender::new("https://example.test/hook")
+            .unwrap()
+            .with_console(Console::silent());
+        sender.send(&order.confirmation()).unwrap();
+
+        let body = &sender.delivered()[0].body;
+        assert_eq!(
+            body,
+            r#"{"v":2,"order_id":3,"total_cents":199,"items":[{"name":"12\" \"Ruler\"","price_cents":199}],"tax_cents":0,"currency":"USD","customer_email":null}"#
+        );
+        assert_eq!(
+            crate::adapters::parse_any_confirmation(body.as_bytes()),
+            Ok(order.confirmation())
+        );
+    }
+
+    #[test]
+    fn unsigned_sender_adds_no_signature_headers() {
+        let sender = WebhookSender::new("https://example.test/hook").unwrap();
+        let order = Order::new(
+            OrderId(1),
+            vec![LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            }],
+        )
+        .unwrap();
+
+        sender.send(&order.confirmation()).unwrap();
+
+        let request = &sender.delivered()[0];
+        assert_eq!(request.header(SIGNATURE_HEADER), None);
+        assert_eq!(request.header(TIMESTAMP_HEADER), None);
+    }
+
+    #[test]
+    fn timestamp_comes_from_the_clock() {
+        let clock = crate::testing::SteppingClock::starting_at(crate::domain::Timestamp(1_700));
+        let sender = WebhookSender::new("https://example.test/hook")
+            .unwrap()
+            .with_signing_key("whsec".into())
+            .with_clock(clock);
+        let order = Order::new(
+            OrderId(1),
+            vec![LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            }],
+        )
+        .unwrap();
+
+        sender.send(&order.confirmation()).unwrap();
+
+        assert_eq!(sender.delivered()[0].header(TIMESTAMP_HEADER), Some("1700"));
+    }
+}
@@ -0,0 +1,2090 @@
+// =============================================================================
+// APPLICATION Layer - Use Cases and Orchestration
+// =============================================================================
+// The application layer coordinates the business flow.
+// It does NOT implement business rules and does NOT know adapters.
+use crate::domain::{
+    Address, Approval, ApproverId, ConfirmPolicy, ConfirmedOrder, ConvertedLine, ConvertedOrder,
+    Currency, CurrencyTotals, Customer, Discount, ForeignLineItem, LineItem, Money, Order,
+    OrderDraft, OrderError, OrderEvent, OrderId, OrderKey, OrderStatus, Price, ReviewDecision,
+    RoundingStrategy, Shipment, StoredOrder, Timestamp,
+};
+use crate::ports::{
+    AmendOrder, AmendOrderUseCase, ApprovalPolicy, BlobStore, Budget, CancelOrderUseCase,
+    CancelToken, ChargeConfirmed, ChargeLog, ChargeOutcome, Clock, ConflictResolver,
+    DraftRepository, ErrorContext, ErrorReporter, EventPublisher, ExchangeRates, FraudScreen,
+    IdGenerator, Inventory, NotificationPolicy, OrderQueries, OrderReader, OrderWriter,
+    PaymentGateway, PendingCharges, PlaceOrder, PlaceOrderUseCase, Port, ReferenceGenerator,
+    ReservationId, Resolution, RetentionPolicy, RiskVerdict, SendConfirmed, Sender, ServiceState,
+    ShippingProvider, Tracer,
+};
+use std::collections::{BTreeMap, BTreeSet};
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+
+mod attachments;
+mod bulk;
+mod checked;
+mod expiry;
+mod hooks;
+mod idempotency;
+mod maintenance;
+mod parallel;
+mod read_model;
+mod reconciliation;
+mod retention;
+mod saga;
+mod settlement;
+
+use checked::CheckedReads;
+
+pub use bulk::{BULK_PROGRESS_EVERY, BulkProgress, BulkReport};
+pub use expiry::{DEFAULT_ORDER_TTL, ExpiryReport};
+pub use hooks::{CompositeHooks, Hooks};
+pub use maintenance::MaintenanceReport;
+pub use read_model::{OrderReadModel, OrderSummary};
+pub use reconciliation::{AmountMismatch, Reconciliation, ReconciliationReport};
+pub use retention::PurgeReport;
+pub use saga::CheckoutSaga;
+pub use settlement::{DECLINED_AT_SETTLEMENT, SettlementReport};
+
+// OrderService is generic over its ports,
+// and it holds *references* to implementations.
+//
+// This means:
+// - adapters live elsewhere
+// - the service only temporarily borrows capabilities
+// - the borrows are all shared, the repository's included
+// - multiple services could share the same adapters
+// - the ports may be trait objects too, when the adapters are only known
+//   at run time: see DynOrderService
+pub struct OrderService<'a, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    repository: &'a R,
+    payment: &'a P,
+    sender: &'a N,
+    next_id: u32,
+    // Optional ports are trait objects: the service works without them,
+    // and adding one does not change the type every caller already names.
+    telemetry: Option<Telemetry<'a>>,
+    events: Option<&'a (dyn EventPublisher + Sync)>,
+    exchange_rates: Option<&'a (dyn ExchangeRates + Sync)>,
+    clock: Option<&'a (dyn Clock + Sync)>,
+    approvals: Option<&'a (dyn ApprovalPolicy + Sync)>,
+    notifications: Option<&'a (dyn NotificationPolicy + Sync)>,
+    fraud: Option<&'a (dyn FraudScreen + Sync)>,
+    conflicts: Option<&'a (dyn ConflictResolver + Sync)>,
+    pending_charges: Option<&'a (dyn PendingCharges + Sync)>,
+    inventory: Option<&'a (dyn Inventory + Sync)>,
+    ids: Option<&'a (dyn IdGenerator + Sync)>,
+    references: Option<&'a (dyn ReferenceGenerator + Sync)>,
+    hooks: Option<&'a (dyn Hooks + Sync)>,
+    budget: Option<&'a Budget<'a>>,
+    tracer: Option<&'a Tracer>,
+    idempotency: Option<idempotency::Idempotency<'a>>,
+    retention: Option<&'a (dyn RetentionPolicy + Sync)>,
+    charge_log: Option<&'a (dyn ChargeLog + Sync)>,
+    blobs: Option<&'a (dyn BlobStore + Sync)>,
+    clock_tolerance: ClockTolerance,
+    order_ttl: Duration,
+    rounding: RoundingStrategy,
+}
+
+// How far a placed_at given by a client may be from the service's clock.
+// Ahead of it by more than max_future_secs, the order is refused with
+// ClockSkew: nothing is placed in the future. Behind by more than
+// max_past_secs, it is clamped to that bound: a client whose clock runs
+// late still gets its order taken.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ClockTolerance {
+    pub max_past_secs: u64,
+    pub max_future_secs: u64,
+}
+
+// An hour behind, five minutes ahead.
+impl Default for ClockTolerance {
+    fn default() -> Self {
+        Self {
+            max_past_secs: 60 * 60,
+            max_future_secs: 5 * 60,
+        }
+    }
+}
+
+// An OrderService over trait objects: one type, whatever adapters it is
+// given, each call dispatched at run time. Adapters chosen from a config
+// file, or kept in a Box, end up here.
+pub type DynOrderService<'a> = OrderService<
+    'a,
+    dyn OrderWriter + Sync + 'a,
+    dyn PaymentGateway + Sync + 'a,
+    dyn Sender + Sync + 'a,
+>;
+
+struct Telemetry<'a> {
+    reporter: &'a (dyn ErrorReporter + Sync),
+    clock: &'a (dyn Clock + Sync),
+}
+
+// The budget of the use case running, stopped however it returns. None
+// when it was already running: the use case that started it stops it.
+struct BudgetRun<'a>(Option<&'a Budget<'a>>);
+
+impl Drop for BudgetRun<'_> {
+    fn drop(&mut self) {
+        if let Some(budget) = self.0 {
+            budget.stop();
+        }
+    }
+}
+
+// The same for the use case's trace.
+struct TraceRun<'a>(Option<&'a Tracer>);
+
+impl Drop for TraceRun<'_> {
+    fn drop(&mut self) {
+        if let Some(tracer) = self.0 {
+            tracer.stop();
+        }
+    }
+}
+
+impl<'a, R, P, N> OrderService<'a, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    // Dependency injection via references.
+    // The application does not decide *what* implementations are used.
+    // It only states *what it needs*.
+    pub fn new(repository: &'a R, payment: &'a P, sender: &'a N) -> Self {
+        Self {
+            repository,
+            payment,
+            sender,
+            next_id: 1,
+            telemetry: None,
+            events: None,
+            exchange_rates: None,
+            clock: None,
+            approvals: None,
+            notifications: None,
+            fraud: None,
+            conflicts: None,
+            pending_charges: None,
+            inventory: None,
+            ids: None,
+            references: None,
+            hooks: None,
+            budget: None,
+            tracer: None,
+            idempotency: None,
+            retention: None,
+            charge_log: None,
+            blobs: None,
+            clock_tolerance: ClockTolerance::default(),
+            order_ttl: DEFAULT_ORDER_TTL,
+            rounding: RoundingStrategy::default(),
+        }
+    }
+
+    // A service picking up where a previous process stopped.
+    // `state` may be older than the repository, if orders were placed after
+    // it was saved: ids already in use are skipped rather than overwritten.
+    pub fn restore(
+        repository: &'a R,
+        payment: &'a P,
+        sender: &'a N,
+        state: &ServiceState,
+    ) -> Result<Self, OrderError> {
+        let mut highest = 0;
+        repository.for_each_checked(&mut |order| highest = highest.max(order.id.0))?;
+        let mut service = Self::new(repository, payment, sender);
+        service.next_id = state.next_id.max(highest.saturating_add(1));
+        Ok(service)
+    }
+
+    // What restore needs. OrderService never holds drafts.
+    pub fn state(&self) -> ServiceState {
+        ServiceState {
+            next_id: self.next_id,
+            open_drafts: Vec::new(),
+        }
+    }
+
+    // Every error returned from now on is also described to `reporter`,
+    // time-stamped with `clock`.
+    // Both must be Sync so the service can still be moved to a consumer thread.
+    pub fn with_error_reporter(
+        mut self,
+        reporter: &'a (dyn ErrorReporter + Sync),
+        clock: &'a (dyn Clock + Sync),
+    ) -> Self {
+        self.telemetry = Some(Telemetry { reporter, clock });
+        self
+    }
+
+    // OrderPlaced and OrderPaid are published once the order is saved.
+    pub fn with_event_publisher(mut self, events: &'a (dyn EventPublisher + Sync)) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    // New orders get their placed_at from this clock.
+    pub fn with_clock(mut self, clock: &'a (dyn Clock + Sync)) -> Self {
+        self.clock = Some(clock);
+        self
+    }
+
+    // What place_order accepts as a client's placed_at. See ClockTolerance.
+    pub fn with_clock_tolerance(mut self, tolerance: ClockTolerance) -> Self {
+        self.clock_tolerance = tolerance;
+        self
+    }
+
+    // New orders also get a UUID, the key to hand out instead of their
+    // number. Without a generator, orders only have their number.
+    pub fn with_id_generator(mut self, ids: &'a (dyn IdGenerator + Sync)) -> Self {
+        self.ids = Some(ids);
+        self
+    }
+
+    // New orders also get a reference for people to quote, shown on their
+    // receipt and looked up with OrderReader::find_by_reference.
+    pub fn with_reference_generator(
+        mut self,
+        references: &'a (dyn ReferenceGenerator + Sync),
+    ) -> Self {
+        self.references = Some(references);
+        self
+    }
+
+    // Orders the policy picks are parked by place_order, uncharged, until
+    // approve_order or reject_order is called for them.
+    pub fn with_approval_policy(mut self, policy: &'a (dyn ApprovalPolicy + Sync)) -> Self {
+        self.approvals = Some(policy);
+        self
+    }
+
+    // Orders the policy turns down are placed, charged and stored as usual,
+    // without a confirmation.
+    pub fn with_notification_policy(mut self, policy: &'a (dyn NotificationPolicy + Sync)) -> Self {
+        self.notifications = Some(policy);
+        self
+    }
+
+    // Every order is screened before it is charged: refused, parked
+    // UnderReview until resolve_review, or let through.
+    pub fn with_fraud_screen(mut self, screen: &'a (dyn FraudScreen + Sync)) -> Self {
+        self.fraud = Some(screen);
+        self
+    }
+
+    // How update_draft_with_resolution settles a lost race. Without one,
+    // the conflict is returned as it is.
+    pub fn with_conflict_resolver(mut self, resolver: &'a (dyn ConflictResolver + Sync)) -> Self {
+        self.conflicts = Some(resolver);
+        self
+    }
+
+    // Needed by place_order_in as soon as a cart mixes currencies.
+    // Where settle_pending finds the charges deferred by an offline-capable
+    // gateway: the same store the gateway records them in.
+    pub fn with_pending_charges(mut self, pending: &'a (dyn PendingCharges + Sync)) -> Self {
+        self.pending_charges = Some(pending);
+        self
+    }
+
+    // Where expire_stale releases the stock of the orders it cancels, and
+    // maintenance the reservations held too long.
+    pub fn with_inventory(mut self, inventory: &'a (dyn Inventory + Sync)) -> Self {
+        self.inventory = Some(inventory);
+        self
+    }
+
+    // Which orders purge forgets for good. Without one, it forgets none.
+    pub fn with_retention_policy(mut self, policy: &'a (dyn RetentionPolicy + Sync)) -> Self {
+        self.retention = Some(policy);
+        self
+    }
+
+    // What the payment provider still holds: purge keeps a cancelled order
+    // it shows a charge for, the refund not having gone through.
+    pub fn with_charge_log(mut self, log: &'a (dyn ChargeLog + Sync)) -> Self {
+        self.charge_log = Some(log);
+        self
+    }
+
+    // Where attach_file keeps the files, and purge deletes them from.
+    pub fn with_blob_store(mut self, blobs: &'a (dyn BlobStore + Sync)) -> Self {
+        self.blobs = Some(blobs);
+        self
+    }
+
+    // How long expire_stale leaves an order unpaid, and maintenance a
+    // reservation unconfirmed. DEFAULT_ORDER_TTL otherwise.
+    pub fn with_order_ttl(mut self, ttl: Duration) -> Self {
+        self.order_ttl = ttl;
+        self
+    }
+
+    // How the amounts the service works out itself are rounded: what a
+    // promotion takes off. HalfUp otherwise. Converted prices are the
+    // exchange rates' own: see FixedRates::with_rounding.
+    pub fn with_rounding(mut self, rounding: RoundingStrategy) -> Self {
+        self.rounding = rounding;
+        self
+    }
+
+    // Called along place_order, and with every error returned. They cannot
+    // change the outcome: see Hooks.
+    pub fn with_hooks(mut self, hooks: &'a (dyn Hooks + Sync)) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    // One budget for each use case, from its start to its return, shared
+    // with the decorators checking it around the ports: see Budget.
+    pub fn with_budget(mut self, budget: &'a Budget<'a>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    // One trace for each use case, the same way: every port call in it is
+    // a span of that trace, for the decorators passing it on. See Tracer.
+    pub fn with_tracer(mut self, tracer: &'a Tracer) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+
+    pub fn with_exchange_rates(mut self, rates: &'a (dyn ExchangeRates + Sync)) -> Self {
+        self.exchange_rates = Some(rates);
+        self
+    }
+
+    // "A customer places an order with prices in several currencies"
+    //
+    // Every line is converted, and rounded to the minor unit, on its own; the
+    // total is the sum of the converted lines. Rounding the total once would
+    // be off by a cent here and there, and the receipt would not add up.
+    //
+    // Without exchange rates only lines already in `currency` are accepted.
+    pub fn place_order_in(
+        &mut self,
+        currency: Currency,
+        items: Vec<ForeignLineItem>,
+    ) -> Result<ConvertedOrder, OrderError> {
+        let _budget = self.start_budget();
+        let _trace = self.start_trace();
+        let mut lines = Vec::with_capacity(items.len());
+        for item in items {
+            let amount = self.convert(item.price, currency).map_err(|e| {
+                self.report(
+                    "place_order_in",
+                    Some(Port::ExchangeRates),
+                    "convert",
+                    None,
+                    e,
+                )
+            })?;
+            lines.push(ConvertedLine {
+                original: item.price,
+                item: LineItem {
+                    name: item.name,
+                    price: amount,
+                },
+            });
+        }
+
+        let items = lines.iter().map(|line| line.item.clone()).collect();
+        let order = self.place(items, currency, None, None, &Customer::guest())?;
+        Ok(ConvertedOrder {
+            order,
+            currency,
+            lines,
+        })
+    }
+
+    fn convert(&self, price: Price, to: Currency) -> Result<Money, OrderError> {
+        if price.currency == to {
+            return Ok(price.amount);
+        }
+        match self.exchange_rates {
+            Some(rates) => rates.convert(price, to),
+            None => Err(OrderError::NoExchangeRate {
+                from: price.currency,
+                to,
+            }),
+        }
+    }
+
+    // This is the main use case:
+    // "A customer places an order"
+    pub fn place_order(&mut self, items: Vec<LineItem>) -> Result<Order, OrderError> {
+        self.place(items, Currency::default(), None, None, &Customer::guest())
+    }
+
+    // The same, for a customer the fraud screen can weigh up too.
+    pub fn place_order_for(
+        &mut self,
+        customer: &Customer,
+        items: Vec<LineItem>,
+    ) -> Result<Order, OrderError> {
+        self.place(items, Currency::default(), None, None, customer)
+    }
+
+    // "A customer places an order, with a promotion or a gift card"
+    // The order is charged what the discount leaves. When it leaves
+    // nothing, the gateway is not called at all: ChargeSkipped is published
+    // in place of the charge, and the order is Paid all the same.
+    pub fn place_discounted_order(
+        &mut self,
+        items: Vec<LineItem>,
+        discount: Discount,
+    ) -> Result<Order, OrderError> {
+        self.place(
+            items,
+            Currency::default(),
+            None,
+            Some(discount),
+            &Customer::guest(),
+        )
+    }
+
+    // `claimed_at` is when the client says the order was placed, if it
+    // says: see ClockTolerance.
+    fn place(
+        &mut self,
+        items: Vec<LineItem>,
+        currency: Currency,
+        claimed_at: Option<Timestamp>,
+        discount: Option<Discount>,
+        customer: &Customer,
+    ) -> Result<Order, OrderError> {
+        let _budget = self.start_budget();
+        let _trace = self.start_trace();
+        let order_id = self.take_id()?;
+        self.place_as(order_id, items, currency, claimed_at, discount, customer)
+    }
+
+    // The id the next order gets, even if it is refused.
+    fn take_id(&mut self) -> Result<OrderId, OrderError> {
+        let order_id = OrderId(self.next_id);
+        self.next_id =
+            next_id(order_id).map_err(|e| self.report("place_order", None, "next_id", None, e))?;
+        Ok(order_id)
+    }
+
+    // The rest of place, once the id is taken: nothing in it needs more than
+    // &self, so that place_orders_parallel can run it on several threads.
+    fn place_as(
+        &self,
+        order_id: OrderId,
+        items: Vec<LineItem>,
+        currency: Currency,
+        claimed_at: Option<Timestamp>,
+        discount: Option<Discount>,
+        customer: &Customer,
+    ) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "place_order";
+
+        // Step 1: pure business logic
+        // A rejected cart never became an order, so there is no id to report.
+        let mut order = match discount {
+            Some(discount) => Order::discounted(order_id, items, discount, self.rounding),
+            None => Order::new(order_id, items),
+        }
+        .map_err(|e| self.report(USE_CASE, None, "validate", None, e))?;
+        order.currency = currency;
+        order.placed_at = self
+            .placed_at(claimed_at)
+            .map_err(|e| self.report(USE_CASE, None, "check_placed_at", None, e))?;
+        order.uuid = self.ids.map(|ids| ids.next_uuid());
+        order.reference = self
+            .references
+            .map(|references| references.next_reference());
+        let id = Some(order_id);
+        match self.screen(USE_CASE, &order, customer)? {
+            RiskVerdict::Accept => {}
+            RiskVerdict::Review => {
+                let requested = OrderEvent::ReviewRequested {
+                    order_id,
+                    total: order.total,
+                };
+                return self.park(order, OrderStatus::UnderReview, requested);
+            }
+            RiskVerdict::Reject { reason } => {
+                let e = OrderError::FraudSuspected { reason };
+                return Err(self.report(USE_CASE, Some(Port::FraudScreen), "assess", id, e));
+            }
+        }
+        if self
+            .approvals
+            .is_some_and(|policy| policy.requires_approval(&order))
+        {
+            let requested = OrderEvent::ApprovalRequested {
+                order_id,
+                total: order.total,
+            };
+            return self.park(order, OrderStatus::PendingApproval, requested);
+        }
+
+        // Step 2: orchestrate external interactions
+        // Notice how everything goes through ports.
+        let free = order.total == Money(0);
+        if !free {
+            self.hook(USE_CASE, "before_charge", id, |hooks| {
+                hooks.before_charge(&order)
+            });
+            let outcome = self
+                .payment
+                .charge_order(order_id, order.total)
+                .map_err(|e| self.report(USE_CASE, Some(Port::Payment), "charge", id, e))?;
+            if outcome == ChargeOutcome::Deferred {
+                return self.defer(order);
+            }
+        }
+        order
+            .mark_paid()
+            .map_err(|e| self.report(USE_CASE, None, "mark_paid", id, e))?;
+        self.repository
+            .save(&order)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "save", id, e))?;
+        self.hook(USE_CASE, "after_save", id, |hooks| hooks.after_save(&order));
+        let mut events = vec![OrderEvent::OrderPlaced {
+            order_id,
+            item_count: order.items.len(),
+            total: order.total,
+        }];
+        if free {
+            events.push(OrderEvent::ChargeSkipped {
+                order_id,
+                covered: order.discount(),
+            });
+        }
+        events.push(OrderEvent::OrderPaid {
+            order_id,
+            amount: order.total,
+        });
+        self.publish(USE_CASE, order_id, &events)?;
+        self.confirm(USE_CASE, &order)?;
+
+        Ok(order)
+    }
+
+    // The clock's time, or the client's within tolerance. Without a clock
+    // there is nothing to check against: the client's time is kept as is.
+    fn placed_at(&self, claimed_at: Option<Timestamp>) -> Result<Option<Timestamp>, OrderError> {
+        let now = self.clock.map(|clock| clock.now());
+        let (Some(at), Some(now)) = (claimed_at, now) else {
+            return Ok(claimed_at.or(now));
+        };
+        let ClockTolerance {
+            max_past_secs,
+            max_future_secs,
+        } = self.clock_tolerance;
+        match at.validate_against(now, max_past_secs, max_future_secs) {
+            Err(OrderError::ClockSkew { delta_secs }) if delta_secs < 0 => {
+                Ok(Some(now.minus_secs(max_past_secs)))
+            }
+            checked => checked.map(|()| Some(at)),
+        }
+    }
+
+    // Accept without a screen. The screen's own failure fails the order.
+    fn screen(
+        &self,
+        use_case: &'static str,
+        order: &Order,
+        customer: &Customer,
+    ) -> Result<RiskVerdict, OrderError> {
+        let Some(screen) = self.fraud else {
+            return Ok(RiskVerdict::Accept);
+        };
+        screen.assess(order, customer).map_err(|e| {
+            self.report(
+                use_case,
+                Some(Port::FraudScreen),
+                "assess",
+                Some(order.id),
+                e,
+            )
+        })
+    }
+
+    // Stored, announced, but neither charged nor confirmed to the customer:
+    // waiting for an approval, or a review. `requested` says which.
+    fn park(
+        &self,
+        mut order: Order,
+        status: OrderStatus,
+        requested: OrderEvent,
+    ) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "place_order";
+        let id = Some(order.id);
+        order
+            .transition_to(status)
+            .map_err(|e| self.report(USE_CASE, None, "park", id, e))?;
+        self.repository
+            .save(&order)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "save", id, e))?;
+        self.hook(USE_CASE, "after_save", id, |hooks| hooks.after_save(&order));
+        self.publish(
+            USE_CASE,
+            order.id,
+            &[
+                OrderEvent::OrderPlaced {
+                    order_id: order.id,
+                    item_count: order.items.len(),
+                    total: order.total,
+                },
+                requested,
+            ],
+        )?;
+        Ok(order)
+    }
+
+    // Stored and announced like a paid order, but the customer hears from
+    // us only once settle_pending has actually charged it.
+    fn defer(&self, mut order: Order) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "place_order";
+        let id = Some(order.id);
+        order
+            .transition_to(OrderStatus::PaymentPending)
+            .map_err(|e| self.report(USE_CASE, None, "defer", id, e))?;
+        self.repository
+            .save(&order)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "save", id, e))?;
+        self.hook(USE_CASE, "after_save", id, |hooks| hooks.after_save(&order));
+        self.publish(
+            USE_CASE,
+            order.id,
+            &[
+                OrderEvent::OrderPlaced {
+                    order_id: order.id,
+                    item_count: order.items.len(),
+                    total: order.total,
+                },
+                OrderEvent::PaymentDeferred {
+                    order_id: order.id,
+                    amount: order.total,
+                },
+            ],
+        )?;
+        Ok(order)
+    }
+
+    // "Someone approves a large order"
+    // What place_order skipped happens now: charge, store, notify.
+    pub fn approve_order(
+        &mut self,
+        id: OrderId,
+        approver: ApproverId,
+    ) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "approve_order";
+        let _budget = self.start_budget();
+        let _trace = self.start_trace();
+        let mut order = self.pending_approval(USE_CASE, id)?;
+
+        self.payment
+            .charge_for(id, order.total)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Payment), "charge", Some(id), e))?;
+        order
+            .transition_to(OrderStatus::Paid)
+            .map_err(|e| self.report(USE_CASE, None, "mark_paid", Some(id), e))?;
+        order.approval = Some(Approval::Approved { by: approver });
+        self.repository
+            .update(&order)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "update", Some(id), e))?;
+        order.version += 1;
+        self.publish(
+            USE_CASE,
+            id,
+            &[OrderEvent::OrderPaid {
+                order_id: id,
+                amount: order.total,
+            }],
+        )?;
+        self.confirm(USE_CASE, &order)?;
+
+        Ok(order)
+    }
+
+    // "Someone resolves a fraud review"
+    // Accepted, the order is charged, stored and confirmed, as place_order
+    // would have done. Rejected, it is cancelled, never charged. Either way
+    // someone looked at it: the approval policy is not asked again.
+    pub fn resolve_review(
+        &mut self,
+        id: OrderId,
+        decision: ReviewDecision,
+    ) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "resolve_review";
+        let _budget = self.start_budget();
+        let _trace = self.start_trace();
+        let mut order = self
+            .repository
+            .find_checked(id)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "find", Some(id), e))?
+            .ok_or(OrderError::NotFound { id })
+            .map_err(|e| self.report(USE_CASE, None, "find", Some(id), e))?;
+        if order.status != OrderStatus::UnderReview {
+            let e = OrderError::NotUnderReview {
+                id,
+                status: order.status,
+            };
+            return Err(self.report(USE_CASE, None, "check_under_review", Some(id), e));
+        }
+
+        let event = match decision {
+            ReviewDecision::Accept => {
+                if order.total != Money(0) {
+                    self.payment.charge_for(id, order.total).map_err(|e| {
+                        self.report(USE_CASE, Some(Port::Payment), "charge", Some(id), e)
+                    })?;
+                }
+                order
+                    .transition_to(OrderStatus::Paid)
+                    .map_err(|e| self.report(USE_CASE, None, "mark_paid", Some(id), e))?;
+                OrderEvent::OrderPaid {
+                    order_id: id,
+                    amount: order.total,
+                }
+            }
+            ReviewDecision::Reject { reason } => {
+                order
+                    .transition_to(OrderStatus::Cancelled)
+                    .map_err(|e| self.report(USE_CASE, None, "cancel", Some(id), e))?;
+                OrderEvent::OrderRejected {
+                    order_id: id,
+                    reason,
+                }
+            }
+        };
+        self.repository
+            .update(&order)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "update", Some(id), e))?;
+        order.version += 1;
+        self.publish(USE_CASE, id, &[event])?;
+        if order.status == OrderStatus::Paid {
+            self.confirm(USE_CASE, &order)?;
+        }
+
+        Ok(order)
+    }
+
+    // "Someone rejects a large order": it is cancelled, nothing is charged.
+    pub fn reject_order(
+        &mut self,
+        id: OrderId,
+        reason: impl Into<String>,
+    ) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "reject_order";
+        let _budget = self.start_budget();
+        let _trace = self.start_trace();
+        let mut order = self.pending_approval(USE_CASE, id)?;
+        let reason = reason.into();
+
+        order
+            .transition_to(OrderStatus::Cancelled)
+            .map_err(|e| self.report(USE_CASE, None, "cancel", Some(id), e))?;
+        order.approval = Some(Approval::Rejected {
+            reason: reason.clone(),
+        });
+        self.repository
+            .update(&order)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "update", Some(id), e))?;
+        order.version += 1;
+        self.publish(
+            USE_CASE,
+            id,
+            &[OrderEvent::OrderRejected {
+                order_id: id,
+                reason,
+            }],
+        )?;
+
+        Ok(order)
+    }
+
+    // "The customer changes their mind before it ships"
+    // A paid order is refunded what its unshipped items were charged (all
+    // of it when nothing has shipped), then cancelled; one still waiting
+    // for approval was never charged, and is only cancelled. An order
+    // already cancelled is returned as it is, so cancelling can be retried.
+    // A PaymentPending order is refused: the charge it owes is settled, or
+    // declined, by settle_pending.
+    pub fn cancel_order(&mut self, id: OrderId) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "cancel_order";
+        let _budget = self.start_budget();
+        let _trace = self.start_trace();
+        let mut order = self
+            .repository
+            .find_checked(id)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "find", Some(id), e))?
+            .ok_or(OrderError::NotFound { id })
+            .map_err(|e| self.report(USE_CASE, None, "find", Some(id), e))?;
+        let refunded = match order.status {
+            OrderStatus::Cancelled => return Ok(order),
+            OrderStatus::Paid => order
+                .unshipped_charge()
+                .map_err(|e| self.report(USE_CASE, None, "refund_share", Some(id), e))?,
+            OrderStatus::PendingApproval => Money(0),
+            from => {
+                let e = OrderError::InvalidTransition {
+                    from,
+                    to: OrderStatus::Cancelled,
+                };
+                return Err(self.report(USE_CASE, None, "check_status", Some(id), e));
+            }
+        };
+
+        if refunded.0 > 0 {
+            self.payment
+                .refund_for(id, refunded)
+                .map_err(|e| self.report(USE_CASE, Some(Port::Payment), "refund", Some(id), e))?;
+        }
+        order
+            .transition_to(OrderStatus::Cancelled)
+            .map_err(|e| self.report(USE_CASE, None, "cancel", Some(id), e))?;
+        self.repository
+            .update(&order)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "update", Some(id), e))?;
+        order.version += 1;
+        self.publish(
+            USE_CASE,
+            id,
+            &[OrderEvent::OrderCancelled {
+                order_id: id,
+                refunded,
+            }],
+        )?;
+
+        Ok(order)
+    }
+
+    // A new address, a gift note, or both, for an order not shipped yet.
+    // Nothing is charged, refunded or sent: the order is only updated.
+    pub fn amend_order(&mut self, command: AmendOrder) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "amend_order";
+        let _budget = self.start_budget();
+        let _trace = self.start_trace();
+        let id = command.order_id;
+        let mut order = self
+            .repository
+            .find_checked(id)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "find", Some(id), e))?
+            .ok_or(OrderError::NotFound { id })
+            .map_err(|e| self.report(USE_CASE, None, "find", Some(id), e))?;
+        order
+            .amend(command.new_address.as_ref(), command.gift_note.as_deref())
+            .map_err(|e| self.report(USE_CASE, None, "amend", Some(id), e))?;
+        self.repository
+            .update(&order)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "update", Some(id), e))?;
+        order.version += 1;
+        Ok(order)
+    }
+
+    // The order, if it is still waiting for a decision.
+    fn pending_approval(&self, use_case: &'static str, id: OrderId) -> Result<Order, OrderError> {
+        let order = self
+            .repository
+            .find_checked(id)
+            .map_err(|e| self.report(use_case, Some(Port::Repository), "find", Some(id), e))?
+            .ok_or(OrderError::NotFound { id })
+            .map_err(|e| self.report(use_case, None, "find", Some(id), e))?;
+        let refused = match &order.approval {
+            Some(Approval::Approved { by }) => {
+                Some(OrderError::AlreadyApproved { id, by: by.clone() })
+            }
+            _ if order.status != OrderStatus::PendingApproval => {
+                Some(OrderError::NotPendingApproval {
+                    id,
+                    status: order.status,
+                })
+            }
+            _ => None,
+        };
+        match refused {
+            Some(e) => Err(self.report(use_case, None, "check_pending", Some(id), e)),
+            None => Ok(order),
+        }
+    }
+
+    // No-op without a publisher.
+    // The confirmation of a paid order, unless the notification policy
+    // says the customer does not hear about it.
+    fn confirm(&self, use_case: &'static str, order: &Order) -> Result<(), OrderError> {
+        if self
+            .notifications
+            .is_some_and(|policy| !policy.should_confirm(order))
+        {
+            return Ok(());
+        }
+        self.sender
+            .send(&order.confirmation())
+            .map_err(|e| self.report(use_case, Some(Port::Sender), "send", Some(order.id), e))
+    }
+
+    fn publish(
+        &self,
+        use_case: &'static str,
+        order_id: OrderId,
+        events: &[OrderEvent],
+    ) -> Result<(), OrderError> {
+        if let Some(publisher) = self.events {
+            for event in events {
+                publisher.publish(event).map_err(|e| {
+                    self.report(use_case, Some(Port::Events), "publish", Some(order_id), e)
+                })?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn get_order(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.repository
+            .find_checked(id)
+            .map_err(|e| self.report("get_order", Some(Port::Repository), "find", Some(id), e))
+    }
+
+    // The same, from what a customer quotes: a number or a UUID.
+    pub fn get_order_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        let id = match key {
+            OrderKey::Sequential(id) => Some(id),
+            OrderKey::Random(_) => None,
+        };
+        self.repository.find_by_key_checked(key).map_err(|e| {
+            self.report(
+                "get_order_by_key",
+                Some(Port::Repository),
+                "find_by_key",
+                id,
+                e,
+            )
+        })
+    }
+
+    // "Compliance deletes a cancelled order", reversibly.
+    // Anything still going through, or already delivered, has to stay.
+    // Deleting twice is not an error: the order is deleted either way.
+    pub fn delete_order(&mut self, id: OrderId) -> Result<(), OrderError> {
+        const USE_CASE: &str = "delete_order";
+        let order = self
+            .repository
+            .find_checked(id)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "find", Some(id), e))?;
+        if let Some(order) = order
+            && order.status != OrderStatus::Cancelled
+        {
+            let e = OrderError::CannotDelete {
+                id,
+                status: order.status,
+            };
+            return Err(self.report(USE_CASE, None, "check_status", Some(id), e));
+        }
+        // Not found: already deleted (fine) or unknown (NotFound), the
+        // repository tells which.
+        self.repository
+            .soft_delete(id)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "soft_delete", Some(id), e))
+    }
+
+    pub fn restore_order(&mut self, id: OrderId) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "restore_order";
+        self.repository
+            .restore(id)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "restore", Some(id), e))?;
+        self.repository
+            .find_checked(id)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "find", Some(id), e))?
+            .ok_or(OrderError::NotFound { id })
+            .map_err(|e| self.report(USE_CASE, None, "find", Some(id), e))
+    }
+
+    // "A shopper comes back to two abandoned carts"
+    // `from`'s items move into `into` (see Order::merge), then `from` is
+    // deleted, reversibly. It is not cancelled first: nothing was bought.
+    // `into` is written before `from` is deleted, so a failure in between
+    // shows the items twice rather than losing them.
+    pub fn merge_drafts(&mut self, into: OrderId, from: OrderId) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "merge_drafts";
+        let mut found = Vec::with_capacity(2);
+        for id in [into, from] {
+            let order = self
+                .repository
+                .find_checked(id)
+                .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "find", Some(id), e))?
+                .ok_or(OrderError::NotFound { id })
+                .map_err(|e| self.report(USE_CASE, None, "find", Some(id), e))?;
+            found.push(order);
+        }
+        let (mut target, source) = (found.remove(0), found.remove(0));
+
+        target
+            .merge(&source)
+            .map_err(|e| self.report(USE_CASE, None, "merge", Some(into), e))?;
+        self.repository
+            .update(&target)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "update", Some(into), e))?;
+        target.version += 1;
+        self.repository.soft_delete(from).map_err(|e| {
+            self.report(
+                USE_CASE,
+                Some(Port::Repository),
+                "soft_delete",
+                Some(from),
+                e,
+            )
+        })?;
+        self.publish(
+            USE_CASE,
+            into,
+            &[OrderEvent::OrdersMerged {
+                order_id: into,
+                from,
+                item_count: target.items.len(),
+                total: target.total,
+            }],
+        )?;
+
+        Ok(target)
+    }
+
+    // "A shopper edits a cart open in two tabs"
+    // `ours` is `base`, the draft as it was read, edited. When someone
+    // else updated the draft in the meantime, it is read again and the
+    // conflict resolver decides; what it settles on is written once more,
+    // and only once: a second conflict is returned, for the caller to start
+    // again from the draft as it is then. Returns the draft as stored.
+    pub fn update_draft_with_resolution(
+        &mut self,
+        base: &Order,
+        ours: Order,
+    ) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "update_draft";
+        let id = ours.id;
+        if ours.status != OrderStatus::Placed {
+            return Err(self.report(
+                USE_CASE,
+                None,
+                "update",
+                Some(id),
+                OrderError::NotADraft { id },
+            ));
+        }
+        if base.id != id || base.version != ours.version {
+            return Err(self.report(USE_CASE, None, "update", Some(id), OrderError::InvalidOrder));
+        }
+        let conflict = match self.repository.update(&ours) {
+            Ok(()) => {
+                return Ok(Order {
+                    version: ours.version + 1,
+                    ..ours
+                });
+            }
+            Err(conflict @ OrderError::VersionConflict { .. }) => conflict,
+            Err(e) => {
+                return Err(self.report(USE_CASE, Some(Port::Repository), "update", Some(id), e));
+            }
+        };
+
+        let theirs = self
+            .repository
+            .find_checked(id)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "find", Some(id), e))?
+            .ok_or(OrderError::NotFound { id })
+            .map_err(|e| self.report(USE_CASE, None, "find", Some(id), e))?;
+        if theirs.status != OrderStatus::Placed {
+            return Err(self.report(
+                USE_CASE,
+                None,
+                "resolve",
+                Some(id),
+                OrderError::NotADraft { id },
+            ));
+        }
+        let resolution = match self.conflicts {
+            Some(resolver) => resolver.resolve(base, &ours, &theirs),
+            None => Resolution::Fail,
+        };
+        let items = match resolution {
+            Resolution::TakeOurs => ours.items,
+            Resolution::TakeTheirs => return Ok(theirs),
+            Resolution::Merged(merged) => merged.items,
+            Resolution::Fail => {
+                return Err(self.report(USE_CASE, None, "resolve", Some(id), conflict));
+            }
+        };
+        // Summed again: a merge is checked like any new cart. The rest,
+        // version included, is theirs.
+        let checked = Order::new(id, items)
+            .map_err(|e| self.report(USE_CASE, None, "resolve", Some(id), e))?;
+        let mut settled = Order {
+            items: checked.items,
+            total: checked.total,
+            ..theirs
+        };
+        self.repository
+            .update(&settled)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "update", Some(id), e))?;
+        settled.version += 1;
+        Ok(settled)
+    }
+
+    // For reporting paths that do not need the items: no Order is cloned.
+    pub fn order_exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        self.repository.exists(id).map_err(|e| {
+            self.report(
+                "order_exists",
+                Some(Port::Repository),
+                "exists",
+                Some(id),
+                e,
+            )
+        })
+    }
+
+    pub fn order_total(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        self.repository.total_of(id).map_err(|e| {
+            self.report(
+                "order_total",
+                Some(Port::Repository),
+                "total_of",
+                Some(id),
+                e,
+            )
+        })
+    }
+
+    // Best effort: a reporter that panics must not replace the error the
+    // caller is about to receive, so the call is isolated with catch_unwind.
+    fn report(
+        &self,
+        use_case: &'static str,
+        port: Option<Port>,
+        operation: &'static str,
+        order_id: Option<OrderId>,
+        error: OrderError,
+    ) -> OrderError {
+        if let Some(telemetry) = &self.telemetry {
+            let context = ErrorContext {
+                use_case,
+                port,
+                operation,
+                order_id,
+                at: telemetry.clock.now(),
+                error: error.clone(),
+            };
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| telemetry.reporter.report(context)));
+        }
+        self.hook(use_case, "on_failure", order_id, |hooks| {
+            hooks.on_failure(&error)
+        });
+        error
+    }
+
+    fn start_budget(&self) -> BudgetRun<'a> {
+        BudgetRun(self.budget.filter(|budget| budget.start()))
+    }
+
+    fn start_trace(&self) -> TraceRun<'a> {
+        TraceRun(self.tracer.filter(|tracer| tracer.start()))
+    }
+
+    // A hook's error is reported, its panic caught, and either way the use
+    // case carries on: see Hooks.
+    fn hook(
+        &self,
+        use_case: &'static str,
+        operation: &'static str,
+        order_id: Option<OrderId>,
+        call: impl Fn(&dyn Hooks) -> Result<(), OrderError>,
+    ) {
+        let Some(hooks) = self.hooks else {
+            return;
+        };
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| call(hooks)));
+        if let (Ok(Err(error)), Some(telemetry)) = (outcome, &self.telemetry) {
+            let context = ErrorContext {
+                use_case,
+                port: Some(Port::Hooks),
+                operation,
+                order_id,
+                at: telemetry.clock.now(),
+                error,
+            };
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| telemetry.reporter.report(context)));
+        }
+    }
+}
+
+// OrderService is what driving adapters reach through the input port.
+impl<R, P, N> PlaceOrderUseCase for OrderService<'_, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    fn place_order(&mut self, command: PlaceOrder) -> Result<Order, OrderError> {
+        self.place(
+            command.items,
+            Currency::default(),
+            command.placed_at,
+            None,
+            &Customer::guest(),
+        )
+    }
+}
+
+impl<R, P, N> CancelOrderUseCase for OrderService<'_, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    fn cancel_order(&mut self, id: OrderId) -> Result<Order, OrderError> {
+        OrderService::cancel_order(self, id)
+    }
+}
+
+impl<R, P, N> AmendOrderUseCase for OrderService<'_, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    fn amend_order(&mut self, command: AmendOrder) -> Result<Order, OrderError> {
+        OrderService::amend_order(self, command)
+    }
+}
+
+// A read-only application service.
+// Browsing orders (a GUI list, a search box) needs no payment and no
+// notification, only the repository, and only for reading: it asks for an
+// OrderReader, so it could not save an order if it tried. It holds a
+// shared reference, like OrderService does, so both can hold the same
+// repository at once.
+pub struct OrderBrowser<'a, R: OrderReader> {
+    repository: &'a R,
+}
+
+impl<'a, R: OrderReader> OrderBrowser<'a, R> {
+    pub fn new(repository: &'a R) -> Self {
+        Self { repository }
+    }
+
+    pub fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.repository.list_checked()
+    }
+
+    pub fn get(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.repository.find_checked(id)
+    }
+
+    pub fn get_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.repository.find_by_key_checked(key)
+    }
+
+    // What delete_order put aside, for whoever may restore it.
+    pub fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        self.repository.list_deleted_checked()
+    }
+
+    // One scan for every revenue query, without cloning the orders.
+    fn revenue_by_currency_cancellable(
+        &self,
+        cancel: &CancelToken,
+    ) -> Result<CurrencyTotals, OrderError> {
+        let mut totals = CurrencyTotals::new();
+        self.repository
+            .for_each_cancellable_checked(cancel, &mut |order| {
+                if matches!(order.status, OrderStatus::Paid | OrderStatus::Shipped) {
+                    totals.add(order.currency, order.total);
+                }
+            })?;
+        Ok(totals)
+    }
+
+    // Orders containing an item whose name contains `text`, ignoring case.
+    pub fn search(&self, text: &str) -> Result<Vec<Order>, OrderError> {
+        let needle = text.to_lowercase();
+        let mut orders = self.repository.list_checked()?;
+        orders.retain(|order| {
+            order
+                .items
+                .iter()
+                .any(|item| item.name.to_lowercase().contains(&needle))
+        });
+        Ok(orders)
+    }
+}
+
+// The query side of the input port, for driving adapters.
+impl<R: OrderReader> OrderQueries for OrderBrowser<'_, R> {
+    fn list_orders(&self) -> Result<Vec<Order>, OrderError> {
+        self.list()
+    }
+
+    fn revenue(&self) -> Result<Money, OrderError> {
+        self.revenue_cancellable(&CancelToken::new())
+    }
+
+    // Cancelled counts the orders added up so far.
+    fn revenue_cancellable(&self, cancel: &CancelToken) -> Result<Money, OrderError> {
+        self.revenue_by_currency_cancellable(cancel)?
+            .require_single(Currency::default())
+    }
+
+    fn revenue_by_currency(&self) -> Result<CurrencyTotals, OrderError> {
+        self.revenue_by_currency_cancellable(&CancelToken::new())
+    }
+
+    // Looking up an order no one has is enough to reach the storage.
+    fn health(&self) -> Result<(), OrderError> {
+        self.repository.exists(OrderId(0)).map(|_| ())
+    }
+}
+
+// Shipping is a use case of its own: it needs the repository, a carrier and
+// a clock, but no payment and no notification. A separate service keeps
+// OrderService's list of ports, and its constructor, unchanged.
+pub struct ShippingService<'a, R, S, C>
+where
+    R: OrderWriter,
+    S: ShippingProvider,
+    C: Clock,
+{
+    repository: &'a R,
+    shipping: &'a S,
+    clock: &'a C,
+    events: Option<&'a (dyn EventPublisher + Sync)>,
+    inventory: Option<&'a (dyn Inventory + Sync)>,
+}
+
+impl<'a, R, S, C> ShippingService<'a, R, S, C>
+where
+    R: OrderWriter,
+    S: ShippingProvider,
+    C: Clock,
+{
+    pub fn new(repository: &'a R, shipping: &'a S, clock: &'a C) -> Self {
+        Self {
+            repository,
+            shipping,
+            clock,
+            events: None,
+            inventory: None,
+        }
+    }
+
+    // ItemsShipped is published once the shipment is saved.
+    pub fn with_event_publisher(mut self, events: &'a (dyn EventPublisher + Sync)) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    // Where ship_by_warehouse learns which warehouse holds each line: the
+    // stock reserved under the order's id.
+    pub fn with_inventory(mut self, inventory: &'a (dyn Inventory + Sync)) -> Self {
+        self.inventory = Some(inventory);
+        self
+    }
+
+    // "The warehouse sends some items of an order"
+    // The domain validates the indices before the carrier is called, so a
+    // rejected request never produces a parcel.
+    pub fn ship_items(
+        &mut self,
+        id: OrderId,
+        item_indices: &[usize],
+        address: &Address,
+    ) -> Result<Order, OrderError> {
+        let mut order = self
+            .repository
+            .find_checked(id)?
+            .ok_or(OrderError::NotFound { id })?;
+        order.check_shippable(item_indices)?;
+
+        let items: Vec<LineItem> = item_indices
+            .iter()
+            .map(|&index| order.items[index].clone())
+            .collect();
+        let tracking = self.shipping.ship(id, &items, address)?;
+
+        order.record_shipment(Shipment {
+            tracking: tracking.clone(),
+            item_indices: item_indices.to_vec(),
+            shipped_at: self.clock.now(),
+        })?;
+        self.repository.save(&order)?;
+        if let Some(events) = self.events {
+            events.publish(&OrderEvent::ItemsShipped {
+                order_id: id,
+                tracking,
+                item_count: item_indices.len(),
+            })?;
+        }
+        Ok(order)
+    }
+
+    // "Send whatever is left, one parcel per warehouse"
+    // The unshipped items are grouped by the warehouse their line was
+    // reserved from, and each group shipped as ship_items would, in
+    // warehouse order. Without an inventory, or from one with a single
+    // shelf, everything left goes in one parcel. A reservation not covering
+    // the order line for line is InvalidOrder, and ships nothing.
+    //
+    // A carrier failing part way leaves the parcels already sent recorded:
+    // calling again ships the rest.
+    pub fn ship_by_warehouse(
+        &mut self,
+        id: OrderId,
+        address: &Address,
+    ) -> Result<Order, OrderError> {
+        let order = self
+            .repository
+            .find_checked(id)?
+            .ok_or(OrderError::NotFound { id })?;
+        let unshipped: Vec<usize> = (0..order.items.len())
+            .filter(|&index| !order.is_item_shipped(index))
+            .collect();
+        order.check_shippable(&unshipped)?;
+
+        let sources = match self.inventory {
+            Some(inventory) => inventory.sources(ReservationId::of_order(id))?,
+            None => None,
+        };
+        let parcels: Vec<Vec<usize>> = match sources {
+            None => vec![unshipped],
+            Some(sources) if sources.len() != order.items.len() => {
+                return Err(OrderError::InvalidOrder);
+            }
+            Some(sources) => {
+                let mut by_warehouse = BTreeMap::new();
+                for index in unshipped {
+                    by_warehouse
+                        .entry(sources[index])
+                        .or_insert_with(Vec::new)
+                        .push(index);
+                }
+                by_warehouse.into_values().collect()
+            }
+        };
+
+        let mut shipped = order;
+        for item_indices in parcels {
+            shipped = self.ship_items(id, &item_indices, address)?;
+        }
+        Ok(shipped)
+    }
+}
+
+// The id after `id`. The last one is never handed out: there would be no
+// next one, and a repository already holding it leaves none to give.
+fn next_id(id: OrderId) -> Result<u32, OrderError> {
+    id.0.checked_add(1).ok_or(OrderError::StorageFailed)
+}
+
+// The two-phase flow, on top of the typestate of domain::OrderDraft:
+// phase 1 fills a draft, phase 2 confirms it then charges and notifies.
+// The payment and notification ports take a &ConfirmedOrder, so the
+// compiler checks that phase 2 never runs on a draft.
+pub struct CheckoutService<'a, R, P, N>
+where
+    R: DraftRepository,
+    P: ChargeConfirmed,
+    N: SendConfirmed,
+{
+    repository: &'a mut R,
+    payment: &'a P,
+    sender: &'a N,
+    next_id: u32,
+    open_drafts: BTreeSet<OrderId>,
+}
+
+impl<'a, R, P, N> CheckoutService<'a, R, P, N>
+where
+    R: DraftRepository,
+    P: ChargeConfirmed,
+    N: SendConfirmed,
+{
+    pub fn new(repository: &'a mut R, payment: &'a P, sender: &'a N) -> Self {
+        Self {
+            repository,
+            payment,
+            sender,
+            next_id: 1,
+            open_drafts: BTreeSet::new(),
+        }
+    }
+
+    // DraftRepository cannot be listed, so unlike OrderService::restore
+    // the state is taken as it is.
+    pub fn restore(
+        repository: &'a mut R,
+        payment: &'a P,
+        sender: &'a N,
+        state: &ServiceState,
+    ) -> Self {
+        let mut service = Self::new(repository, payment, sender);
+        service.next_id = state.next_id;
+        service.open_drafts = state.open_drafts.iter().copied().collect();
+        service
+    }
+
+    pub fn state(&self) -> ServiceState {
+        ServiceState {
+            next_id: self.next_id,
+            open_drafts: self.open_drafts.iter().copied().collect(),
+        }
+    }
+
+    // Drafts started and not confirmed yet, by ascending id.
+    pub fn open_drafts(&self) -> Vec<OrderId> {
+        self.open_drafts.iter().copied().collect()
+    }
+
+    // Phase 1: "A customer opens a cart"
+    pub fn start_draft(&mut self) -> Result<OrderId, OrderError> {
+        let id = OrderId(self.next_id);
+        self.next_id = next_id(id)?;
+        self.repository
+            .store(StoredOrder::Draft(OrderDraft::new(id)))?;
+        self.open_drafts.insert(id);
+        Ok(id)
+    }
+
+    pub fn add_item(&mut self, id: OrderId, item: LineItem) -> Result<(), OrderError> {
+        let mut draft = self.load_draft(id)?;
+        draft.add_item(item);
+        self.repository.store(StoredOrder::Draft(draft))
+    }
+
+    pub fn remove_item(&mut self, id: OrderId, index: usize) -> Result<LineItem, OrderError> {
+        let mut draft = self.load_draft(id)?;
+        let removed = draft.remove_item(index)?;
+        self.repository.store(StoredOrder::Draft(draft))?;
+        Ok(removed)
+    }
+
+    // Phase 2: "The customer checks out"
+    // A rejected draft stays stored as it was, ready to be fixed.
+    pub fn confirm(
+        &mut self,
+        id: OrderId,
+        policy: &ConfirmPolicy,
+    ) -> Result<ConfirmedOrder, OrderError> {
+        let draft = self.load_draft(id)?;
+        let mut order = draft.confirm(policy).map_err(|(_draft, error)| error)?;
+
+        self.payment.charge_confirmed(&order)?;
+        order.mark_paid()?;
+        self.repository
+            .store(StoredOrder::Confirmed(Box::new(order.clone())))?;
+        self.open_drafts.remove(&id);
+        self.sender.send_confirmed(&order)?;
+
+        Ok(order)
+    }
+
+    pub fn get(&self, id: OrderId) -> Result<Option<StoredOrder>, OrderError> {
+        checked::checked_load(id, self.repository.load(id)?)
+    }
+
+    fn load_draft(&self, id: OrderId) -> Result<OrderDraft, OrderError> {
+        match checked::checked_load(id, self.repository.load(id)?)? {
+            Some(StoredOrder::Draft(draft)) => Ok(draft),
+            Some(StoredOrder::Confirmed(_)) => Err(OrderError::NotADraft { id }),
+            None => Err(OrderError::NotFound { id }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::approval::ThresholdApproval;
+    use crate::adapters::error_reporting::InMemoryErrorReporter;
+    use crate::adapters::in_memory::{
+        ConsoleSender, InMemoryDraftRepository, InMemoryOrderRepository, MockPaymentGateway,
+        MockShippingProvider,
+    };
+    use crate::adapters::shared::SharedRepository;
+    use crate::assert_err_variant;
+    use crate::domain::{Money, OrderStatus, Timestamp, TrackingId};
+    use crate::ports::ChargeLog;
+    use crate::testing::{SteppingClock, assert_order};
+
+    struct DecliningPaymentGateway;
+
+    impl PaymentGateway for DecliningPaymentGateway {
+        fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+            Err(OrderError::PaymentFailed)
+        }
+    }
+
+    fn cart() -> Vec<LineItem> {
+        vec![
+            LineItem {
+                name: "Rust Book".to_string(),
+                price: Money(4999),
+            },
+            LineItem {
+                name: "Keyboard".to_string(),
+                price: Money(12999),
+            },
+        ]
+    }
+
+    #[test]
+    fn place_order_successfully() {
+        let repo = InMemoryOrderRepository::new();
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &payment, &sender);
+
+        let order = service.place_order(cart()).unwrap();
+
+        assert_order(&order)
+            .has_id(1)
+            .has_total_cents(17998)
+            .has_status(OrderStatus::Paid)
+            .has_item_named("Rust Book")
+            .has_item_named("Keyboard");
+        let stored = service.get_order(order.id).unwrap().unwrap();
+        assert_order(&stored).has_id(1).has_total_cents(17998);
+    }
+
+    #[test]
+    fn ids_are_sequential() {
+        let repo = InMemoryOrderRepository::new();
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &payment, &sender);
+
+        service.place_order(cart()).unwrap();
+        let second = service.place_order(cart()).unwrap();
+
+        assert_order(&second).has_id(2);
+    }
+
+    #[test]
+    fn declined_payment_stores_nothing() {
+        let repo = InMemoryOrderRepository::new();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &DecliningPaymentGateway, &sender);
+
+        assert_err_variant!(service.place_order(cart()), OrderError::PaymentFailed);
+        assert!(service.get_order(OrderId(1)).unwrap().is_none());
+    }
+
+    #[test]
+    fn cancelling_a_paid_order_refunds_it_once() {
+        let repo = InMemoryOrderRepository::new();
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &payment, &sender);
+        let order = service.place_order(cart()).unwrap();
+
+        let cancelled = service.cancel_order(order.id).unwrap();
+        let again = service.cancel_order(order.id).unwrap();
+
+        assert_order(&cancelled).has_status(OrderStatus::Cancelled);
+        assert_eq!(again.version, cancelled.version);
+        assert!(payment.charges().unwrap().is_empty());
+        assert_err_variant!(
+            service.cancel_order(OrderId(9)),
+            OrderError::NotFound { id: OrderId(9) }
+        );
+    }
+
+    #[test]
+    fn cancelling_a_partly_shipped_order_refunds_only_what_is_left() {
+        let repo = InMemoryOrderRepository::new();
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &payment, &sender);
+        // $179.98 less a $9.98 gift card: $170.00, shared by the two items
+        // as $47.22 and $122.78.
+        let order = service
+            .place_discounted_order(cart(), Discount::GiftCard(Money(998)))
+            .unwrap();
+        let carrier = MockShippingProvider::new();
+        let clock = SteppingClock::starting_at(Timestamp(1_000));
+        ShippingService::new(&repo, &carrier, &clock)
+            .ship_items(order.id, &[1], &address())
+            .unwrap();
+
+        service.cancel_order(order.id).unwrap();
+
+        // The keyboard has shipped: its share is still charged.
+        let charged = payment.charges().unwrap();
+        assert_eq!(charged.len(), 1);
+        assert_eq!(charged[0].amount, Money(12_278));
+    }
+
+    #[test]
+    fn payment_failure_is_reported() {
+        let repo = InMemoryOrderRepository::new();
+        let reporter = InMemoryErrorReporter::new();
+        let clock = SteppingClock::starting_at(Timestamp(1_234));
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &DecliningPaymentGateway, &sender)
+            .with_error_reporter(&reporter, &clock);
+
+        assert_err_variant!(service.place_order(cart()), OrderError::PaymentFailed);
+
+        let reports = reporter.reports();
+        assert_eq!(reports.len(), 1);
+        assert_eq!(reports[0].use_case, "place_order");
+        assert_eq!(reports[0].port, Some(Port::Payment));
+        assert_eq!(reports[0].operation, "charge");
+        assert_eq!(reports[0].order_id, Some(OrderId(1)));
+        assert_eq!(reports[0].at, Timestamp(1_234));
+        assert_err_variant!(
+            Err::<(), _>(reports[0].error.clone()),
+            OrderError::PaymentFailed
+        );
+    }
+
+    #[test]
+    fn rejected_cart_is_reported_without_order_id() {
+        let repo = InMemoryOrderRepository::new();
+        let reporter = InMemoryErrorReporter::new();
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service =
+            OrderService::new(&repo, &payment, &sender).with_error_reporter(&reporter, &clock);
+
+        assert_err_variant!(service.place_order(vec![]), OrderError::InvalidOrder);
+
+        let reports = reporter.reports();
+        assert_eq!(reports[0].port, None);
+        assert_eq!(reports[0].order_id, None);
+    }
+
+    struct PanickingReporter;
+
+    impl ErrorReporter for PanickingReporter {
+        fn report(&self, _context: ErrorContext) {
+            panic!("telemetry backend is down");
+        }
+    }
+
+    #[test]
+    fn panicking_reporter_does_not_mask_the_error() {
+        let repo = InMemoryOrderRepository::new();
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &DecliningPaymentGateway, &sender)
+            .with_error_reporter(&PanickingReporter, &clock);
+
+        assert_err_variant!(service.place_order(cart()), OrderError::PaymentFailed);
+        // The service is still usable afterwards.
+        assert_err_variant!(service.place_order(cart()), OrderError::PaymentFailed);
+    }
+
+    #[test]
+    fn browser_reads_while_service_writes() {
+        let shared = SharedRepository::new(InMemoryOrderRepository::new());
+        let writer = shared.clone();
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&writer, &payment, &sender);
+        let browser = OrderBrowser::new(&shared);
+
+        assert!(browser.list().unwrap().is_empty());
+        service.place_order(cart()).unwrap();
+        assert_eq!(browser.list().unwrap().len(), 1);
+        service
+            .place_order(vec![LineItem {
+                name: "USB cable".to_string(),
+                price: Money(999),
+            }])
+            .unwrap();
+
+        let ids: Vec<OrderId> = browser.list().unwrap().iter().map(|o| o.id).collect();
+        assert_eq!(ids, vec![OrderId(1), OrderId(2)]);
+        assert_order(&browser.get(OrderId(2)).unwrap().unwrap()).has_total_cents(999);
+        assert_eq!(browser.search("keyboard").unwrap().len(), 1);
+        assert_eq!(browser.search("CABLE").unwrap()[0].id, OrderId(2));
+        assert!(browser.search("monitor").unwrap().is_empty());
+    }
+
+    fn address() -> Address {
+        Address {
+            street: "1 Rue de Rivoli".to_string(),
+            city: "Paris".to_string(),
+            postal_code: "75001".to_string(),
+            country: "FR".to_string(),
+        }
+    }
+
+    // Places one paid order with three items, id 1.
+    fn seeded_repository() -> InMemoryOrderRepository {
+        let repo = InMemoryOrderRepository::new();
+        let mut items = cart();
+        items.push(LineItem {
+            name: "Mouse".to_string(),
+            price: Money(2500),
+        });
+        OrderService::new(&repo, &MockPaymentGateway::new(), &ConsoleSender::new())
+            .place_order(items)
+            .unwrap();
+        repo
+    }
+
+    #[test]
+    fn last_shipment_flips_the_status() {
+        let repo = seeded_repository();
+        let carrier = MockShippingProvider::new();
+        let clock = SteppingClock::starting_at(Timestamp(1_000));
+        let mut shipping = ShippingService::new(&repo, &carrier, &clock);
+
+        let order = shipping
+            .ship_items(OrderId(1), &[0, 2], &address())
+            .unwrap();
+        assert_order(&order)
+            .has_status(OrderStatus::Paid)
+            .has_shipment_count(1);
+
+        clock.advance_secs(3_600);
+        let order = shipping.ship_items(OrderId(1), &[1], &address()).unwrap();
+        assert_order(&order)
+            .has_status(OrderStatus::Shipped)
+            .has_shipment_count(2);
+        assert_eq!(order.shipments[1].shipped_at, Timestamp(4_600));
+        assert_eq!(
+            order.shipments[1].tracking,
+            TrackingId("TRK000002".to_string())
+        );
+
+        // One carrier call per shipment, with exactly its items.
+        let parcels: Vec<usize> = carrier.shipped().iter().map(|(_, _, n)| *n).collect();
+        assert_eq!(parcels, vec![2, 1]);
+        let stored = repo.find(OrderId(1)).unwrap().unwrap();
+        assert_order(&stored).has_status(OrderStatus::Shipped);
+    }
+
+    // Publishers take &self and must be Sync: a Mutex, not a RefCell.
+    #[derive(Default)]
+    struct RecordingPublisher {
+        events: std::sync::Mutex<Vec<OrderEvent>>,
+    }
+
+    impl EventPublisher for RecordingPublisher {
+        fn publish(&self, event: &OrderEvent) -> Result<(), OrderError> {
+            self.events.lock().unwrap().push(event.clone());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn events_follow_the_stored_facts() {
+        let repo = InMemoryOrderRepository::new();
+        let publisher = RecordingPublisher::default();
+        OrderService::new(&repo, &MockPaymentGateway::new(), &ConsoleSender::new())
+            .with_event_publisher(&publisher)
+            .place_order(cart())
+            .unwrap();
+        let carrier = MockShippingProvider::new();
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        ShippingService::new(&repo, &carrier, &clock)
+            .with_event_publisher(&publisher)
+            .ship_items(OrderId(1), &[1], &address())
+            .unwrap();
+
+        assert_eq!(
+            *publisher.events.lock().unwrap(),
+            vec![
+                OrderEvent::OrderPlaced {
+                    order_id: OrderId(1),
+                    item_count: 2,
+                    total: Money(17998),
+                },
+                OrderEvent::OrderPaid {
+                    order_id: OrderId(1),
+                    amount: Money(17998),
+                },
+                OrderEvent::ItemsShipped {
+                    order_id: OrderId(1),
+                    tracking: TrackingId("TRK000001".to_string()),
+                    item_count: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn parked_order_announces_the_approval_then_the_payment() {
+        let repo = InMemoryOrderRepository::new();
+        let publisher = RecordingPublisher::default();
+        let policy = ThresholdApproval::new(Money(10_000));
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &payment, &sender)
+            .with_event_publisher(&publisher)
+            .with_approval_policy(&policy);
+
+        service.place_order(cart()).unwrap();
+        service
+            .approve_order(OrderId(1), ApproverId("alice".to_string()))
+            .unwrap();
+
+        assert_eq!(
+            *publisher.events.lock().unwrap(),
+            vec![
+                OrderEvent::OrderPlaced {
+                    order_id: OrderId(1),
+                    item_count: 2,
+                    total: Money(17998),
+                },
+                OrderEvent::ApprovalRequested {
+                    order_id: OrderId(1),
+                    total: Money(17998),
+                },
+                OrderEvent::OrderPaid {
+                    order_id: OrderId(1),
+                    amount: Money(17998),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn declined_payment_publishes_nothing() {
+        let repo = InMemoryOrderRepository::new();
+        let publisher = RecordingPublisher::default();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &DecliningPaymentGateway, &sender)
+            .with_event_publisher(&publisher);
+
+        assert!(service.place_order(cart()).is_err());
+        assert!(publisher.events.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn overlapping_shipment_is_rejected_before_the_carrier_is_called() {
+        let repo = seeded_repository();
+        let carrier = MockShippingProvider::new();
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let mut shipping = ShippingService::new(&repo, &carrier, &clock);
+
+        shipping
+            .ship_items(OrderId(1), &[0, 1], &address())
+            .unwrap();
+
+        assert_err_variant!(
+            shipping.ship_items(OrderId(1), &[2, 1], &address()),
+            OrderError::AlreadyShipped { index: 1 }
+        );
+        assert_eq!(carrier.shipped().len(), 1);
+        let stored = repo.find(OrderId(1)).unwrap().unwrap();
+        assert_order(&stored).has_shipment_count(1);
+    }
+
+    #[test]
+    fn out_of_range_index_is_rejected() {
+        let repo = seeded_repository();
+        let carrier = MockShippingProvider::new();
+        let clock = SteppingClock::starting_at(Timestamp(0));
+        let mut shipping = ShippingService::new(&repo, &carrier, &clock);
+
+        assert_err_variant!(
+            shipping.ship_items(OrderId(1), &[3], &address()),
+            OrderError::InvalidItemIndex { index: 3 }
+        );
+        assert_err_variant!(
+            shipping.ship_items(OrderId(9), &[0], &address()),
+            OrderError::NotFound { id: OrderId(9) }
+        );
+        assert!(carrier.shipped().is_empty());
+    }
+
+    #[test]
+    fn checkout_confirms_charges_and_stores_the_order() {
+        let mut repo = InMemoryDraftRepository::new();
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut checkout = CheckoutService::new(&mut repo, &payment, &sender);
+
+        let id = checkout.start_draft().unwrap();
+        for item in cart() {
+            checkout.add_item(id, item).unwrap();
+        }
+        let order = checkout.confirm(id, &ConfirmPolicy::default()).unwrap();
+
+        assert_eq!(order.status(), OrderStatus::Paid);
+        assert_order(order.as_order()).has_total_cents(17998);
+        assert_eq!(
+            checkout.get(id).unwrap(),
+            Some(StoredOrder::Confirmed(Box::new(order)))
+        );
+    }
+
+    #[test]
+    fn rejected_draft_stays_editable() {
+        let mut repo = InMemoryDraftRepository::new();
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut checkout = CheckoutService::new(&mut repo, &payment, &sender);
+        let id = checkout.start_draft().unwrap();
+
+        assert_err_variant!(
+            checkout.confirm(id, &ConfirmPolicy::default()),
+            OrderError::InvalidOrder
+        );
+        assert!(matches!(
+            checkout.get(id).unwrap(),
+            Some(StoredOrder::Draft(_))
+        ));
+        checkout.add_item(id, cart().remove(0)).unwrap();
+        assert!(checkout.confirm(id, &ConfirmPolicy::default()).is_ok());
+    }
+
+    #[test]
+    fn confirmed_order_no_longer_accepts_items() {
+        let mut repo = InMemoryDraftRepository::new();
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut checkout = CheckoutService::new(&mut repo, &payment, &sender);
+        let id = checkout.start_draft().unwrap();
+        checkout.add_item(id, cart().remove(0)).unwrap();
+        checkout.confirm(id, &ConfirmPolicy::default()).unwrap();
+
+        assert_err_variant!(
+            checkout.add_item(id, cart().remove(1)),
+            OrderError::NotADraft { id: OrderId(1) }
+        );
+        assert_err_variant!(
+            checkout.confirm(OrderId(9), &ConfirmPolicy::default()),
+            OrderError::NotFound { id: OrderId(9) }
+        );
+    }
+
+    #[test]
+    fn declined_payment_leaves_the_draft_in_place() {
+        let mut repo = InMemoryDraftRepository::new();
+        let sender = ConsoleSender::new();
+        let mut checkout = CheckoutService::new(&mut repo, &DecliningPaymentGateway, &sender);
+        let id = checkout.start_draft().unwrap();
+        checkout.add_item(id, cart().remove(0)).unwrap();
+
+        assert_err_variant!(
+            checkout.confirm(id, &ConfirmPolicy::default()),
+            OrderError::PaymentFailed
+        );
+        assert!(matches!(
+            checkout.get(id).unwrap(),
+            Some(StoredOrder::Draft(_))
+        ));
+    }
+}
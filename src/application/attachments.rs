@@ -0,0 +1,94 @@
+// Notes and files added to an order already placed.
+//
+// attach_file keeps the bytes in the BlobStore first, then the order with
+// what it needs to find them again. When saving the order fails, the blob
+// just put is deleted again, as far as the store lets it: a blob no order
+// names is only wasted space, never shown to anyone.
+//
+// Without a BlobStore, nothing can be attached.
+use super::OrderService;
+use super::checked::CheckedReads;
+use crate::domain::{
+    Attachment, MAX_ATTACHMENT_BYTES, Order, OrderError, OrderId, Timestamp, sanitize_filename,
+};
+use crate::ports::{OrderWriter, PaymentGateway, Port, Sender};
+
+impl<R, P, N> OrderService<'_, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    pub fn add_note(
+        &mut self,
+        id: OrderId,
+        author: &str,
+        text: &str,
+        at: Timestamp,
+    ) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "add_note";
+        let _budget = self.start_budget();
+        let _trace = self.start_trace();
+        let mut order = self.annotated(USE_CASE, id)?;
+        order
+            .add_note(author, text, at)
+            .map_err(|e| self.report(USE_CASE, None, "add_note", Some(id), e))?;
+        self.repository
+            .update(&order)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "update", Some(id), e))?;
+        order.version += 1;
+        Ok(order)
+    }
+
+    // At most MAX_ATTACHMENT_BYTES, under the name sanitize_filename makes
+    // of `filename`.
+    pub fn attach_file(
+        &mut self,
+        id: OrderId,
+        bytes: &[u8],
+        filename: &str,
+    ) -> Result<Attachment, OrderError> {
+        const USE_CASE: &str = "attach_file";
+        let _budget = self.start_budget();
+        let _trace = self.start_trace();
+        if bytes.len() > MAX_ATTACHMENT_BYTES {
+            let error = OrderError::AttachmentTooLarge {
+                bytes: bytes.len(),
+                limit: MAX_ATTACHMENT_BYTES,
+            };
+            return Err(self.report(USE_CASE, None, "attach_file", Some(id), error));
+        }
+        let mut order = self.annotated(USE_CASE, id)?;
+        let Some(blobs) = self.blobs else {
+            return Err(self.report(
+                USE_CASE,
+                Some(Port::Blobs),
+                "put",
+                Some(id),
+                OrderError::StorageFailed,
+            ));
+        };
+        let blob = blobs
+            .put(bytes)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Blobs), "put", Some(id), e))?;
+        let attachment = Attachment {
+            blob,
+            filename: sanitize_filename(filename),
+            bytes: bytes.len() as u64,
+        };
+        order.attachments.push(attachment.clone());
+        if let Err(e) = self.repository.update(&order) {
+            let _ = blobs.delete(blob);
+            return Err(self.report(USE_CASE, Some(Port::Repository), "update", Some(id), e));
+        }
+        Ok(attachment)
+    }
+
+    fn annotated(&self, use_case: &'static str, id: OrderId) -> Result<Order, OrderError> {
+        self.repository
+            .find_checked(id)
+            .map_err(|e| self.report(use_case, Some(Port::Repository), "find", Some(id), e))?
+            .ok_or(OrderError::NotFound { id })
+            .map_err(|e| self.report(use_case, None, "find", Some(id), e))
+    }
+}
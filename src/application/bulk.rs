@@ -0,0 +1,296 @@
+// Back-office tooling: "mark all Paid orders older than T as Shipped".
+//
+// Matching orders are collected by id while scanning the repository, then
+// handled one by one: re-read, domain transition, optimistic update. Another
+// writer may have changed an order in between; such a conflict is retried
+// once from a fresh read before the order is reported as failed.
+//
+// Both bulk use cases take a CancelToken and look at it between two orders
+// (or carts). Cancelled during the scan, nothing has been written yet.
+use super::OrderService;
+use super::checked::CheckedReads;
+use crate::domain::{LineItem, Order, OrderCriteria, OrderError, OrderId, OrderStatus};
+use crate::ports::{CancelToken, OrderWriter, PaymentGateway, Port, Sender};
+
+// How often the progress callback fires, in processed orders.
+// It also fires once at the end, whatever the count.
+pub const BULK_PROGRESS_EVERY: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BulkProgress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct BulkReport {
+    pub transitioned: Vec<OrderId>,
+    // Matched, but the domain refused the transition from their status.
+    pub skipped: Vec<OrderId>,
+    // Could not be written (repeated conflicts, storage errors...).
+    pub failed: Vec<(OrderId, OrderError)>,
+}
+
+enum Outcome {
+    Transitioned,
+    Skipped,
+}
+
+impl<R, P, N> OrderService<'_, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    // Only a failure to scan the repository, or a cancellation, aborts the
+    // whole operation; per-order problems end up in the report. Cancelled
+    // counts the orders handled, none when it came during the scan.
+    pub fn bulk_transition(
+        &mut self,
+        criteria: &OrderCriteria,
+        to: OrderStatus,
+        cancel: &CancelToken,
+        progress: &mut dyn FnMut(BulkProgress),
+    ) -> Result<BulkReport, OrderError> {
+        let mut ids = Vec::new();
+        let scanned = self
+            .repository
+            .for_each_cancellable_checked(cancel, &mut |order| {
+                if criteria.matches(order) {
+                    ids.push(order.id);
+                }
+            });
+        match scanned {
+            Err(OrderError::Cancelled { .. }) => {
+                return Err(OrderError::Cancelled { processed: 0 });
+            }
+            Err(e) => {
+                return Err(self.report(
+                    "bulk_transition",
+                    Some(Port::Repository),
+                    "scan",
+                    None,
+                    e,
+                ));
+            }
+            Ok(()) => {}
+        }
+
+        let total = ids.len();
+        let mut report = BulkReport::default();
+        for (done, id) in ids.into_iter().enumerate() {
+            cancel.check(done)?;
+            let outcome = match self.transition_one(id, criteria, to) {
+                Err(OrderError::VersionConflict { .. }) => self.transition_one(id, criteria, to),
+                outcome => outcome,
+            };
+            match outcome {
+                Ok(Outcome::Transitioned) => report.transitioned.push(id),
+                Ok(Outcome::Skipped) => report.skipped.push(id),
+                Err(error) => report.failed.push((id, error)),
+            }
+
+            let processed = done + 1;
+            if processed % BULK_PROGRESS_EVERY == 0 || processed == total {
+                progress(BulkProgress { processed, total });
+            }
+        }
+        Ok(report)
+    }
+
+    // Places the carts one after the other, as place_order would, each with
+    // its own outcome, in the same order. Cancelled counts the carts tried:
+    // the orders placed before it are kept.
+    pub fn place_batch(
+        &mut self,
+        carts: Vec<Vec<LineItem>>,
+        cancel: &CancelToken,
+    ) -> Result<Vec<Result<Order, OrderError>>, OrderError> {
+        let mut outcomes = Vec::with_capacity(carts.len());
+        for cart in carts {
+            cancel.check(outcomes.len())?;
+            outcomes.push(self.place_order(cart));
+        }
+        Ok(outcomes)
+    }
+
+    // An order that no longer matches after a re-read (someone else moved
+    // it) is skipped, like one whose status does not allow the transition.
+    fn transition_one(
+        &mut self,
+        id: OrderId,
+        criteria: &OrderCriteria,
+        to: OrderStatus,
+    ) -> Result<Outcome, OrderError> {
+        let mut order = self
+            .repository
+            .find_checked(id)?
+            .ok_or(OrderError::NotFound { id })?;
+        if !criteria.matches(&order) || order.transition_to(to).is_err() {
+            return Ok(Outcome::Skipped);
+        }
+        self.repository.update(&order)?;
+        Ok(Outcome::Transitioned)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::in_memory::{ConsoleSender, InMemoryOrderRepository, MockPaymentGateway};
+    use crate::domain::{LineItem, Money, Order, Timestamp};
+    use crate::ports::OrderReader;
+
+    // 500 orders, one per second from t=0. Every 50th one was never paid.
+    fn seeded() -> InMemoryOrderRepository {
+        let repo = InMemoryOrderRepository::new();
+        for n in 1..=500 {
+            let mut order = Order::new(
+                OrderId(n),
+                vec![LineItem {
+                    name: format!("Item {n}"),
+                    price: Money(100),
+                }],
+            )
+            .unwrap();
+            order.placed_at = Some(Timestamp(u64::from(n)));
+            if n % 50 != 0 {
+                order.mark_paid().unwrap();
+            }
+            repo.save(&order).unwrap();
+        }
+        repo
+    }
+
+    #[test]
+    fn old_paid_orders_are_shipped_and_unpaid_ones_skipped() {
+        let repo = seeded();
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &payment, &sender);
+        let mut ticks = Vec::new();
+
+        let report = service
+            .bulk_transition(
+                &OrderCriteria::any().placed_before(Timestamp(451)),
+                OrderStatus::Shipped,
+                &CancelToken::new(),
+                &mut |progress| ticks.push(progress),
+            )
+            .unwrap();
+
+        // Orders 1..=450 match; 50, 100, ..., 450 are still Placed.
+        let skipped: Vec<OrderId> = (1..=9).map(|k| OrderId(k * 50)).collect();
+        assert_eq!(report.skipped, skipped);
+        assert_eq!(report.transitioned.len(), 441);
+        assert!(report.failed.is_empty());
+        assert_eq!(
+            ticks.iter().map(|p| p.processed).collect::<Vec<_>>(),
+            vec![100, 200, 300, 400, 450]
+        );
+        assert!(ticks.iter().all(|p| p.total == 450));
+
+        let status = |n| repo.find(OrderId(n)).unwrap().unwrap().status;
+        assert_eq!(status(1), OrderStatus::Shipped);
+        assert_eq!(status(50), OrderStatus::Placed);
+        assert_eq!(status(451), OrderStatus::Paid);
+        assert_eq!(repo.find(OrderId(1)).unwrap().unwrap().version, 1);
+    }
+
+    // Someone else writes order 1 right after each of our reads, `conflicts`
+    // times, so our update always arrives with a stale version.
+    struct RacingRepository {
+        inner: InMemoryOrderRepository,
+        conflicts: std::cell::Cell<u32>,
+    }
+
+    impl OrderReader for RacingRepository {
+        fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+            self.inner.find(id)
+        }
+
+        fn list(&self) -> Result<Vec<Order>, OrderError> {
+            self.inner.list()
+        }
+    }
+
+    impl OrderWriter for RacingRepository {
+        fn save(&self, order: &Order) -> Result<(), OrderError> {
+            self.inner.save(order)
+        }
+
+        fn update(&self, order: &Order) -> Result<(), OrderError> {
+            if self.conflicts.get() > 0 {
+                self.conflicts.set(self.conflicts.get() - 1);
+                let mut theirs = self.inner.find(order.id)?.unwrap();
+                theirs.version += 1;
+                self.inner.save(&theirs)?;
+            }
+            self.inner.update(order)
+        }
+    }
+
+    fn racing(conflicts: u32) -> RacingRepository {
+        let inner = InMemoryOrderRepository::new();
+        let mut order = Order::new(
+            OrderId(1),
+            vec![LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            }],
+        )
+        .unwrap();
+        order.mark_paid().unwrap();
+        inner.save(&order).unwrap();
+        RacingRepository {
+            inner,
+            conflicts: std::cell::Cell::new(conflicts),
+        }
+    }
+
+    #[test]
+    fn a_conflict_is_retried_once() {
+        let repo = racing(1);
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &payment, &sender);
+
+        let report = service
+            .bulk_transition(
+                &OrderCriteria::any(),
+                OrderStatus::Shipped,
+                &CancelToken::new(),
+                &mut |_| {},
+            )
+            .unwrap();
+
+        assert_eq!(report.transitioned, vec![OrderId(1)]);
+    }
+
+    #[test]
+    fn a_second_conflict_fails_the_order() {
+        let repo = racing(2);
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &payment, &sender);
+
+        let report = service
+            .bulk_transition(
+                &OrderCriteria::any(),
+                OrderStatus::Shipped,
+                &CancelToken::new(),
+                &mut |_| {},
+            )
+            .unwrap();
+
+        assert!(report.transitioned.is_empty());
+        assert!(matches!(
+            report.failed[..],
+            [(OrderId(1), OrderError::VersionConflict { .. })]
+        ));
+        assert_eq!(
+            repo.find(OrderId(1)).unwrap().unwrap().status,
+            OrderStatus::Paid
+        );
+    }
+}
@@ -0,0 +1,236 @@
+// The repository as the use cases read it: every order that comes back is
+// checked with Order::validate_consistency before anything is done with
+// it. On top of what one order may get wrong, a repository may get the
+// rest wrong: an order found under an id that is not its own, a listing
+// with an id twice or out of ascending order. All of it is CorruptData,
+// and the use case stops with that error instead of working on, or
+// panicking over, what the adapter made up.
+//
+// A scan stops handing out orders at the first corrupt one: whatever the
+// use case does with its orders, it does it to none past that point.
+use crate::domain::{Order, OrderError, OrderId, OrderKey, StoredOrder};
+use crate::ports::{CancelToken, OrderReader};
+
+pub(crate) trait CheckedReads: OrderReader {
+    fn find_checked(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        let found = self.find(id)?;
+        if let Some(order) = &found {
+            if order.id != id {
+                return Err(found_under(id, order));
+            }
+            order.validate_consistency()?;
+        }
+        Ok(found)
+    }
+
+    fn find_by_key_checked(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        let found = self.find_by_key(key)?;
+        if let Some(order) = &found {
+            let matches = match key {
+                OrderKey::Sequential(id) => order.id == id,
+                OrderKey::Random(uuid) => order.uuid == Some(uuid),
+            };
+            if !matches {
+                return Err(OrderError::CorruptData {
+                    order_id: order.id,
+                    reason: format!("found under {key}"),
+                });
+            }
+            order.validate_consistency()?;
+        }
+        Ok(found)
+    }
+
+    fn list_checked(&self) -> Result<Vec<Order>, OrderError> {
+        let orders = self.list()?;
+        check_listing(&orders)?;
+        Ok(orders)
+    }
+
+    fn list_deleted_checked(&self) -> Result<Vec<Order>, OrderError> {
+        let orders = self.list_deleted()?;
+        check_listing(&orders)?;
+        Ok(orders)
+    }
+
+    fn for_each_checked(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        let mut listing = Listing::default();
+        self.for_each(&mut |order| {
+            if listing.admit(order) {
+                visit(order);
+            }
+        })?;
+        listing.finish()
+    }
+
+    fn for_each_cancellable_checked(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        let mut listing = Listing::default();
+        self.for_each_cancellable(cancel, &mut |order| {
+            if listing.admit(order) {
+                visit(order);
+            }
+        })?;
+        listing.finish()
+    }
+}
+
+impl<R: OrderReader + ?Sized> CheckedReads for R {}
+
+// What a DraftRepository loaded under `id`: a draft is only checked for its
+// id, an order already confirmed for everything.
+pub(crate) fn checked_load(
+    id: OrderId,
+    found: Option<StoredOrder>,
+) -> Result<Option<StoredOrder>, OrderError> {
+    match &found {
+        Some(stored) if stored.id() != id => Err(OrderError::CorruptData {
+            order_id: stored.id(),
+            reason: format!("found under {id}"),
+        }),
+        Some(StoredOrder::Confirmed(order)) => {
+            order.as_order().validate_consistency()?;
+            Ok(found)
+        }
+        _ => Ok(found),
+    }
+}
+
+fn found_under(id: OrderId, order: &Order) -> OrderError {
+    OrderError::CorruptData {
+        order_id: order.id,
+        reason: format!("found under {id}"),
+    }
+}
+
+fn check_listing(orders: &[Order]) -> Result<(), OrderError> {
+    let mut listing = Listing::default();
+    for order in orders {
+        listing.admit(order);
+    }
+    listing.finish()
+}
+
+// Orders as a listing hands them out: each consistent, by strictly
+// ascending id. The first that is not is kept, and nothing is admitted
+// after it.
+#[derive(Default)]
+struct Listing {
+    last: Option<OrderId>,
+    corrupt: Option<OrderError>,
+}
+
+impl Listing {
+    fn admit(&mut self, order: &Order) -> bool {
+        if self.corrupt.is_some() {
+            return false;
+        }
+        let checked = match self.last {
+            Some(last) if order.id <= last => Err(OrderError::CorruptData {
+                order_id: order.id,
+                reason: format!("listed after {last}"),
+            }),
+            _ => order.validate_consistency(),
+        };
+        match checked {
+            Ok(()) => {
+                self.last = Some(order.id);
+                true
+            }
+            Err(e) => {
+                self.corrupt = Some(e);
+                false
+            }
+        }
+    }
+
+    fn finish(self) -> Result<(), OrderError> {
+        self.corrupt.map_or(Ok(()), Err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, Money};
+
+    fn order(id: u32) -> Order {
+        Order::new(OrderId(id), vec![LineItem::new("Pen", Money(150))]).unwrap()
+    }
+
+    struct Listed(Vec<Order>);
+
+    impl OrderReader for Listed {
+        fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+            Ok(self.0.iter().find(|order| order.id == id).cloned())
+        }
+
+        fn list(&self) -> Result<Vec<Order>, OrderError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    #[test]
+    fn a_scan_stops_at_the_first_id_out_of_order() {
+        let repo = Listed(vec![order(1), order(3), order(3), order(4)]);
+        let mut visited = Vec::new();
+
+        let scanned = repo.for_each_checked(&mut |order| visited.push(order.id));
+
+        assert!(matches!(
+            scanned,
+            Err(OrderError::CorruptData { order_id: OrderId(3), reason })
+                if reason == "listed after #000003"
+        ));
+        assert_eq!(visited, [OrderId(1), OrderId(3)]);
+        assert!(repo.list_checked().is_err());
+        assert!(Listed(vec![order(1), order(4)]).list_checked().is_ok());
+    }
+
+    // Whatever it is asked for, order 1.
+    struct Always(Order);
+
+    impl OrderReader for Always {
+        fn find(&self, _id: OrderId) -> Result<Option<Order>, OrderError> {
+            Ok(Some(self.0.clone()))
+        }
+
+        fn list(&self) -> Result<Vec<Order>, OrderError> {
+            Ok(vec![self.0.clone()])
+        }
+    }
+
+    #[test]
+    fn an_order_found_under_another_id_is_corrupt() {
+        let repo = Always(order(1));
+        let corrupt = |result: Result<Option<Order>, OrderError>| match result {
+            Err(OrderError::CorruptData { order_id, reason }) => (order_id, reason),
+            other => panic!("expected CorruptData, got {other:?}"),
+        };
+
+        assert!(repo.find_checked(OrderId(1)).unwrap().is_some());
+        assert_eq!(
+            corrupt(repo.find_checked(OrderId(2))),
+            (OrderId(1), "found under #000002".to_string())
+        );
+        assert_eq!(
+            corrupt(repo.find_by_key_checked(OrderKey::Sequential(OrderId(2)))),
+            (OrderId(1), "found under #000002".to_string())
+        );
+        assert!(matches!(
+            checked_load(
+                OrderId(2),
+                Some(StoredOrder::Draft(crate::domain::OrderDraft::new(OrderId(
+                    1
+                ))))
+            ),
+            Err(OrderError::CorruptData {
+                order_id: OrderId(1),
+                ..
+            })
+        ));
+    }
+}
@@ -0,0 +1,98 @@
+// "Carts nobody paid for do not pile up forever"
+//
+// Orders still Placed (an open cart) or PaymentPending (taken offline, never
+// settled) once their placed_at is older than the service's TTL are
+// cancelled, OrderExpired published, and their stock released. An order
+// without placed_at has no age, and is left alone.
+//
+// Each one is re-read before it is cancelled: paid meanwhile, it is no
+// longer stale and is skipped. The order is updated before its stock is
+// released; if the release fails, the order stays expired and the report
+// says so, for someone to release by hand (releasing twice is harmless).
+// A PaymentPending order's deferred charge is not removed here:
+// settle_pending drops charges whose order is no longer pending.
+use super::OrderService;
+use super::checked::CheckedReads;
+use crate::domain::{OrderCriteria, OrderError, OrderEvent, OrderId, OrderStatus, Timestamp};
+use crate::ports::{OrderWriter, PaymentGateway, Port, ReservationId, Sender};
+use std::time::Duration;
+
+// A day.
+pub const DEFAULT_ORDER_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+const STALE: [OrderStatus; 2] = [OrderStatus::Placed, OrderStatus::PaymentPending];
+
+#[derive(Debug, Clone, Default)]
+pub struct ExpiryReport {
+    pub expired: Vec<OrderId>,
+    pub failed: Vec<(OrderId, OrderError)>,
+}
+
+impl<R, P, N> OrderService<'_, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    // The stock held for order `id`, released when it expires.
+    pub fn reservation(id: OrderId) -> ReservationId {
+        ReservationId::of_order(id)
+    }
+
+    // Only a failure to scan the repository aborts the whole run; per-order
+    // problems end up in the report.
+    pub fn expire_stale(&mut self, now: Timestamp) -> Result<ExpiryReport, OrderError> {
+        const USE_CASE: &str = "expire_stale";
+        let placed_before = now.minus_secs(self.order_ttl.as_secs());
+        let criteria = STALE.map(|status| {
+            OrderCriteria::any()
+                .with_status(status)
+                .placed_before(placed_before)
+        });
+        let mut ids = Vec::new();
+        self.repository
+            .for_each_checked(&mut |order| {
+                if criteria.iter().any(|criteria| criteria.matches(order)) {
+                    ids.push(order.id);
+                }
+            })
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "for_each", None, e))?;
+
+        let mut report = ExpiryReport::default();
+        for id in ids {
+            match self.expire_one(id, &criteria) {
+                Ok(true) => report.expired.push(id),
+                Ok(false) => {}
+                Err(e) => report.failed.push((id, e)),
+            }
+        }
+        Ok(report)
+    }
+
+    // false when the order no longer matches after a re-read.
+    fn expire_one(&mut self, id: OrderId, criteria: &[OrderCriteria]) -> Result<bool, OrderError> {
+        const USE_CASE: &str = "expire_stale";
+        let mut order = self
+            .repository
+            .find_checked(id)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "find", Some(id), e))?
+            .ok_or(OrderError::NotFound { id })
+            .map_err(|e| self.report(USE_CASE, None, "find", Some(id), e))?;
+        if !criteria.iter().any(|criteria| criteria.matches(&order)) {
+            return Ok(false);
+        }
+        order
+            .transition_to(OrderStatus::Cancelled)
+            .map_err(|e| self.report(USE_CASE, None, "expire", Some(id), e))?;
+        self.repository
+            .update(&order)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "update", Some(id), e))?;
+        self.publish(USE_CASE, id, &[OrderEvent::OrderExpired { order_id: id }])?;
+        if let Some(inventory) = self.inventory {
+            inventory.release(Self::reservation(id)).map_err(|e| {
+                self.report(USE_CASE, Some(Port::Inventory), "release", Some(id), e)
+            })?;
+        }
+        Ok(true)
+    }
+}
@@ -0,0 +1,122 @@
+// Extension without modification: code that wants to add to place_order
+// (flag suspicious orders, time the steps, write an audit line) plugs a
+// Hooks into the service instead of editing it.
+//
+// Hooks watch; they do not steer. Deliberately, an error returned by a
+// hook is described to the ErrorReporter, with Port::Hooks, and otherwise
+// ignored, and a panicking hook is caught: either way the order goes
+// through exactly the steps it would have gone through without the hook.
+// A broken audit trail must not cost a sale. Code that must be able to stop
+// an order is a policy (ApprovalPolicy), not a hook.
+use crate::domain::{Order, OrderError};
+
+// Every method does nothing by default: implement only those you need.
+pub trait Hooks {
+    // The order is valid and about to be charged. Not called for an order
+    // a discount made free, nor for one parked for approval.
+    fn before_charge(&self, _order: &Order) -> Result<(), OrderError> {
+        Ok(())
+    }
+
+    // place_order saved the order: paid, parked or deferred.
+    fn after_save(&self, _order: &Order) -> Result<(), OrderError> {
+        Ok(())
+    }
+
+    // With every error the service returns, whichever the use case, once
+    // it was reported. Not called for a hook's own errors.
+    fn on_failure(&self, _error: &OrderError) -> Result<(), OrderError> {
+        Ok(())
+    }
+}
+
+// Several hooks as one, called in the order they were added.
+// Each one is called even when one before it returned an error; the first
+// error is the one returned. A panic stops the hooks after it.
+#[derive(Default)]
+pub struct CompositeHooks<'a> {
+    hooks: Vec<&'a (dyn Hooks + Sync)>,
+}
+
+impl<'a> CompositeHooks<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, hooks: &'a (dyn Hooks + Sync)) -> Self {
+        self.hooks.push(hooks);
+        self
+    }
+
+    fn each(&self, call: impl Fn(&dyn Hooks) -> Result<(), OrderError>) -> Result<(), OrderError> {
+        let mut first = Ok(());
+        for hooks in &self.hooks {
+            first = first.and(call(*hooks));
+        }
+        first
+    }
+}
+
+impl Hooks for CompositeHooks<'_> {
+    fn before_charge(&self, order: &Order) -> Result<(), OrderError> {
+        self.each(|hooks| hooks.before_charge(order))
+    }
+
+    fn after_save(&self, order: &Order) -> Result<(), OrderError> {
+        self.each(|hooks| hooks.after_save(order))
+    }
+
+    fn on_failure(&self, error: &OrderError) -> Result<(), OrderError> {
+        self.each(|hooks| hooks.on_failure(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, Money, OrderId};
+    use std::sync::Mutex;
+
+    struct Named<'a> {
+        name: &'static str,
+        called: &'a Mutex<Vec<&'static str>>,
+        fails_with: Option<OrderError>,
+    }
+
+    impl Hooks for Named<'_> {
+        fn after_save(&self, _order: &Order) -> Result<(), OrderError> {
+            self.called.lock().unwrap().push(self.name);
+            self.fails_with.clone().map_or(Ok(()), Err)
+        }
+    }
+
+    #[test]
+    fn every_hook_runs_and_the_first_error_wins() {
+        let order = Order::new(OrderId(1), vec![LineItem::new("Pen", Money(150))]).unwrap();
+        let called = Mutex::default();
+        let named = |name, fails_with| Named {
+            name,
+            called: &called,
+            fails_with,
+        };
+        let (first, second, third) = (
+            named("first", None),
+            named("second", Some(OrderError::StorageFailed)),
+            named("third", Some(OrderError::NotificationFailed)),
+        );
+
+        let hooks = CompositeHooks::new()
+            .with(&first)
+            .with(&second)
+            .with(&third);
+
+        assert!(matches!(
+            hooks.after_save(&order),
+            Err(OrderError::StorageFailed)
+        ));
+        assert_eq!(*called.lock().unwrap(), ["first", "second", "third"]);
+        // The defaults do nothing.
+        assert!(hooks.before_charge(&order).is_ok());
+        assert!(CompositeHooks::new().after_save(&order).is_ok());
+    }
+}
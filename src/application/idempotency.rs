@@ -0,0 +1,78 @@
+// "The client did not hear back, and sends the same order again"
+//
+// place_order_once takes the idempotency key the client chose for its
+// request. The first time, the order is placed and the key remembered; every
+// retry with that key gets the same order back, charged once.
+//
+// Keys are only worth keeping as long as a client may still retry:
+// maintenance, run from time to time, forgets those older than what
+// with_idempotency_store was given. The store itself may be a file (see
+// adapters::idempotency) so that retries across a restart are caught too.
+use super::OrderService;
+use super::checked::CheckedReads;
+use crate::domain::{LineItem, Order, OrderError, Timestamp};
+use crate::ports::{IdempotencyStore, OrderWriter, PaymentGateway, Port, Sender};
+use std::time::Duration;
+
+#[derive(Clone, Copy)]
+pub(super) struct Idempotency<'a> {
+    pub(super) store: &'a (dyn IdempotencyStore + Sync),
+    pub(super) keep_for: Duration,
+}
+
+impl<'a, R, P, N> OrderService<'a, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    // Keys are remembered with the clock's time, so keep_for only counts
+    // with a clock: without one, maintenance forgets nothing.
+    pub fn with_idempotency_store(
+        mut self,
+        store: &'a (dyn IdempotencyStore + Sync),
+        keep_for: Duration,
+    ) -> Self {
+        self.idempotency = Some(Idempotency { store, keep_for });
+        self
+    }
+
+    // Without a store, every call places a new order, as place_order does.
+    pub fn place_order_once(
+        &mut self,
+        key: &str,
+        items: Vec<LineItem>,
+    ) -> Result<Order, OrderError> {
+        const USE_CASE: &str = "place_order_once";
+        let Some(Idempotency { store, .. }) = self.idempotency else {
+            return self.place_order(items);
+        };
+        let known = store
+            .find(key)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Idempotency), "find", None, e))?;
+        if let Some(id) = known {
+            return self
+                .repository
+                .find_checked(id)
+                .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "find", Some(id), e))?
+                .ok_or(OrderError::NotFound { id })
+                .map_err(|e| self.report(USE_CASE, None, "find", Some(id), e));
+        }
+
+        let order = self.place_order(items)?;
+        // The order is placed and charged: failing now would only make the
+        // client retry, and place it a second time. The failure is reported,
+        // the order returned.
+        let at = self.clock.map_or(Timestamp(0), |clock| clock.now());
+        let _ = store.remember(key, order.id, at).map_err(|e| {
+            self.report(
+                USE_CASE,
+                Some(Port::Idempotency),
+                "remember",
+                Some(order.id),
+                e,
+            )
+        });
+        Ok(order)
+    }
+}
@@ -0,0 +1,74 @@
+// Housekeeping, for a timer or a cron job. One pass over what only piles
+// up unless someone clears it:
+//
+// - idempotency keys older than keep_for are forgotten
+// - open carts and offline orders older than the order TTL expire, and
+//   their stock goes back on the shelf (see expire_stale)
+// - reservations nobody confirmed within that same TTL are released, and
+//   InventoryEvent::ReservationExpired published for each: the stock a
+//   checkout reserved and never came back for
+//
+// Times are the clock's: without one, maintenance does nothing. What it
+// cleared is gone, so a second run straight after finds nothing to do.
+use super::OrderService;
+use super::idempotency::Idempotency;
+use crate::domain::{InventoryEvent, OrderError};
+use crate::ports::{OrderWriter, PaymentGateway, Port, Sender};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MaintenanceReport {
+    pub idempotency_keys_purged: usize,
+    pub orders_expired: usize,
+    pub reservations_released: usize,
+}
+
+impl<R, P, N> OrderService<'_, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    // An order that could not be expired is reported, and counted out;
+    // any other failure stops the pass there.
+    pub fn maintenance(&mut self) -> Result<MaintenanceReport, OrderError> {
+        const USE_CASE: &str = "maintenance";
+        let mut report = MaintenanceReport::default();
+        let Some(clock) = self.clock else {
+            return Ok(report);
+        };
+        let now = clock.now();
+        if let Some(Idempotency { store, keep_for }) = self.idempotency {
+            report.idempotency_keys_purged =
+                store.purge_older_than(keep_for, now).map_err(|e| {
+                    self.report(
+                        USE_CASE,
+                        Some(Port::Idempotency),
+                        "purge_older_than",
+                        None,
+                        e,
+                    )
+                })?;
+        }
+        report.orders_expired = self.expire_stale(now)?.expired.len();
+        let Some(inventory) = self.inventory else {
+            return Ok(report);
+        };
+        let released = inventory
+            .release_expired(now, self.order_ttl)
+            .map_err(|e| {
+                self.report(USE_CASE, Some(Port::Inventory), "release_expired", None, e)
+            })?;
+        report.reservations_released = released.len();
+        if let Some(events) = self.events {
+            for reservation in released {
+                let event = InventoryEvent::ReservationExpired {
+                    reservation: reservation.0,
+                };
+                events.publish_inventory(&event).map_err(|e| {
+                    self.report(USE_CASE, Some(Port::Events), "publish_inventory", None, e)
+                })?;
+            }
+        }
+        Ok(report)
+    }
+}
@@ -0,0 +1,116 @@
+// Placing many carts at once, on several threads.
+//
+// place_orders_parallel gives what place_batch would: the same ids, cart i
+// getting the i-th id whether it is placed or refused, and the outcomes in
+// the order of the carts. The ids are all taken before any thread starts,
+// on this one, so no two threads ever race for the next id. Only the rest,
+// screening, charging, saving, telling the customer, runs on the workers,
+// and it needs nothing more of the service than a shared reference.
+//
+// The adapters are shared as they are: they must be Sync, and each keeps
+// its own invariants under concurrent calls, the way a repository's lock or
+// a ConcurrencyLimited gateway does. None of them is called with a lock of
+// the service held, because the service holds none.
+//
+// A Budget covers the whole batch, like one use case.
+use super::OrderService;
+use crate::domain::{Currency, Customer, LineItem, Order, OrderError};
+use crate::ports::{OrderWriter, PaymentGateway, Sender};
+use std::panic;
+use std::sync::{Mutex, PoisonError};
+use std::thread;
+
+impl<R, P, N> OrderService<'_, R, P, N>
+where
+    R: OrderWriter + Sync + ?Sized,
+    P: PaymentGateway + Sync + ?Sized,
+    N: Sender + Sync + ?Sized,
+{
+    // At least one worker; no more than there are carts. With one, the
+    // carts are placed on this thread.
+    pub fn place_orders_parallel(
+        &mut self,
+        carts: Vec<Vec<LineItem>>,
+        workers: usize,
+    ) -> Vec<Result<Order, OrderError>> {
+        let _budget = self.start_budget();
+        let _trace = self.start_trace();
+        let jobs: Vec<_> = carts
+            .into_iter()
+            .map(|cart| (self.take_id(), cart))
+            .collect();
+        let service = &*self;
+        run_in_order(jobs, workers, |(id, cart)| {
+            let guest = Customer::guest();
+            id.and_then(|id| service.place_as(id, cart, Currency::default(), None, None, &guest))
+        })
+    }
+}
+
+// A pool for one batch: `workers` threads, each taking the next item
+// nobody has taken yet until none is left. The results come back in the
+// order of the items, whichever thread ran them. A job that panics panics
+// the caller, once the other threads are done.
+fn run_in_order<I: Send, T: Send>(
+    items: Vec<I>,
+    workers: usize,
+    job: impl Fn(I) -> T + Sync,
+) -> Vec<T> {
+    let count = items.len();
+    let workers = workers.clamp(1, count.max(1));
+    if workers == 1 {
+        return items.into_iter().map(job).collect();
+    }
+    let queue = Mutex::new(items.into_iter().enumerate());
+    let done: Vec<Vec<(usize, T)>> = thread::scope(|scope| {
+        let threads: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let next = queue.lock().unwrap_or_else(PoisonError::into_inner).next();
+                        let Some((at, item)) = next else {
+                            return done;
+                        };
+                        done.push((at, job(item)));
+                    }
+                })
+            })
+            .collect();
+        threads
+            .into_iter()
+            .map(|thread| {
+                thread
+                    .join()
+                    .unwrap_or_else(|panic| panic::resume_unwind(panic))
+            })
+            .collect()
+    });
+    let mut slots: Vec<Option<T>> = (0..count).map(|_| None).collect();
+    for (at, result) in done.into_iter().flatten() {
+        slots[at] = Some(result);
+    }
+    slots
+        .into_iter()
+        .map(|slot| slot.expect("every item is taken once"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn results_come_back_in_index_order_whatever_thread_ran_them() {
+        let squares = run_in_order((0..100).collect(), 8, |at: usize| {
+            if at.is_multiple_of(7) {
+                thread::yield_now();
+            }
+            at * at
+        });
+
+        assert_eq!(squares, (0..100).map(|at| at * at).collect::<Vec<_>>());
+        assert!(run_in_order(Vec::<usize>::new(), 8, |at| at).is_empty());
+        assert_eq!(run_in_order(vec![0, 1, 2], 0, |at| at), [0, 1, 2]);
+    }
+}
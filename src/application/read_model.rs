@@ -0,0 +1,179 @@
+// A read model: the order list as the back office wants to see it, built
+// from events only. It never reads the repository, so it can live in
+// another process, be rebuilt from an event log, or lag behind for a while.
+use crate::domain::{Money, OrderEvent, OrderId, OrderStatus};
+use crate::ports::EventSubscriber;
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderSummary {
+    pub id: OrderId,
+    pub item_count: usize,
+    pub total: Money,
+    pub status: OrderStatus,
+    pub shipped_items: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct OrderReadModel {
+    orders: BTreeMap<OrderId, OrderSummary>,
+    events_applied: usize,
+}
+
+impl OrderReadModel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn get(&self, id: OrderId) -> Option<&OrderSummary> {
+        self.orders.get(&id)
+    }
+
+    // By ascending id.
+    pub fn orders(&self) -> Vec<&OrderSummary> {
+        self.orders.values().collect()
+    }
+
+    pub fn events_applied(&self) -> usize {
+        self.events_applied
+    }
+
+    // What has actually been charged, not what was placed.
+    pub fn revenue(&self) -> Money {
+        Money(
+            self.orders
+                .values()
+                .filter(|order| matches!(order.status, OrderStatus::Paid | OrderStatus::Shipped))
+                .map(|order| order.total.0)
+                .sum(),
+        )
+    }
+}
+
+impl EventSubscriber for OrderReadModel {
+    // Events about an order the model never saw placed are counted but
+    // otherwise ignored: the log they come from may have lost its start.
+    fn on_event(&mut self, event: &OrderEvent) {
+        self.events_applied += 1;
+        match event {
+            OrderEvent::OrderPlaced {
+                order_id,
+                item_count,
+                total,
+            } => {
+                self.orders.insert(
+                    *order_id,
+                    OrderSummary {
+                        id: *order_id,
+                        item_count: *item_count,
+                        total: *total,
+                        status: OrderStatus::Placed,
+                        shipped_items: 0,
+                    },
+                );
+            }
+            OrderEvent::ApprovalRequested { order_id, .. } => {
+                if let Some(order) = self.orders.get_mut(order_id) {
+                    order.status = OrderStatus::PendingApproval;
+                }
+            }
+            OrderEvent::ReviewRequested { order_id, .. } => {
+                if let Some(order) = self.orders.get_mut(order_id) {
+                    order.status = OrderStatus::UnderReview;
+                }
+            }
+            OrderEvent::OrderRejected { order_id, .. }
+            | OrderEvent::OrderCancelled { order_id, .. }
+            | OrderEvent::OrderExpired { order_id } => {
+                if let Some(order) = self.orders.get_mut(order_id) {
+                    order.status = OrderStatus::Cancelled;
+                }
+            }
+            // OrderPaid follows, which is what the model keeps.
+            OrderEvent::ChargeSkipped { .. } => {}
+            OrderEvent::PaymentDeferred { order_id, .. } => {
+                if let Some(order) = self.orders.get_mut(order_id) {
+                    order.status = OrderStatus::PaymentPending;
+                }
+            }
+            OrderEvent::OrderPaid { order_id, .. } => {
+                if let Some(order) = self.orders.get_mut(order_id) {
+                    order.status = OrderStatus::Paid;
+                }
+            }
+            OrderEvent::ItemsShipped {
+                order_id,
+                item_count,
+                ..
+            } => {
+                if let Some(order) = self.orders.get_mut(order_id) {
+                    order.shipped_items += item_count;
+                    if order.shipped_items >= order.item_count {
+                        order.status = OrderStatus::Shipped;
+                    }
+                }
+            }
+            OrderEvent::OrdersMerged {
+                order_id,
+                from,
+                item_count,
+                total,
+            } => {
+                self.orders.remove(from);
+                if let Some(order) = self.orders.get_mut(order_id) {
+                    order.item_count = *item_count;
+                    order.total = *total;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::TrackingId;
+
+    #[test]
+    fn model_follows_an_order_through_its_life() {
+        let mut model = OrderReadModel::new();
+        let id = OrderId(1);
+
+        model.on_event(&OrderEvent::OrderPlaced {
+            order_id: id,
+            item_count: 2,
+            total: Money(7499),
+        });
+        assert_eq!(model.revenue(), Money(0));
+
+        model.on_event(&OrderEvent::OrderPaid {
+            order_id: id,
+            amount: Money(7499),
+        });
+        assert_eq!(model.revenue(), Money(7499));
+
+        for tracking in ["TRK1", "TRK2"] {
+            model.on_event(&OrderEvent::ItemsShipped {
+                order_id: id,
+                tracking: TrackingId(tracking.to_string()),
+                item_count: 1,
+            });
+        }
+        let summary = model.get(id).unwrap();
+        assert_eq!(summary.status, OrderStatus::Shipped);
+        assert_eq!(summary.shipped_items, 2);
+        assert_eq!(model.events_applied(), 4);
+    }
+
+    #[test]
+    fn events_for_unknown_orders_are_ignored() {
+        let mut model = OrderReadModel::new();
+        model.on_event(&OrderEvent::OrderPaid {
+            order_id: OrderId(9),
+            amount: Money(100),
+        });
+
+        assert!(model.orders().is_empty());
+        assert_eq!(model.events_applied(), 1);
+    }
+}
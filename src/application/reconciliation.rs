@@ -0,0 +1,98 @@
+// "Do our orders and the provider's charges agree?"
+//
+// A read-only use case, run after the fact: it looks at what the repository
+// holds and what the ChargeLog says was charged, and lists every
+// disagreement. It fixes nothing; each entry needs a human, or a refund.
+//
+// Paid and Shipped orders must have been charged their total, exactly. An
+// order at any other status must not have been charged at all. Charges for
+// the same order add up, so a double charge shows as a mismatch.
+use super::checked::CheckedReads;
+use crate::domain::{Money, Order, OrderError, OrderId, OrderStatus};
+use crate::ports::{ChargeLog, ChargeRecord, OrderReader};
+use std::collections::BTreeMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AmountMismatch {
+    pub order_id: OrderId,
+    pub expected: Money,
+    pub charged: Money,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ReconciliationReport {
+    // Paid or shipped, but never charged.
+    pub missing_charges: Vec<OrderId>,
+    // Charged for an order the repository does not know.
+    pub orphan_charges: Vec<ChargeRecord>,
+    pub amount_mismatches: Vec<AmountMismatch>,
+}
+
+impl ReconciliationReport {
+    pub fn is_consistent(&self) -> bool {
+        self.missing_charges.is_empty()
+            && self.orphan_charges.is_empty()
+            && self.amount_mismatches.is_empty()
+    }
+}
+
+// Holds shared references only, like OrderBrowser: it can run next to an
+// OrderService writing to the same repository.
+pub struct Reconciliation<'a, R: OrderReader> {
+    repository: &'a R,
+    charges: &'a dyn ChargeLog,
+}
+
+impl<'a, R: OrderReader> Reconciliation<'a, R> {
+    pub fn new(repository: &'a R, charges: &'a dyn ChargeLog) -> Self {
+        Self {
+            repository,
+            charges,
+        }
+    }
+
+    // Each list by ascending order id, orphans in the log's order.
+    // Soft-deleted orders still count as known: their charges are not
+    // orphans.
+    pub fn reconcile(&self) -> Result<ReconciliationReport, OrderError> {
+        let mut orders: BTreeMap<OrderId, Order> = BTreeMap::new();
+        for order in self
+            .repository
+            .list_checked()?
+            .into_iter()
+            .chain(self.repository.list_deleted_checked()?)
+        {
+            orders.insert(order.id, order);
+        }
+
+        let mut report = ReconciliationReport::default();
+        // Summed in u64: a double charge of a large order must not wrap. A
+        // sum past Money's range is shown as the largest amount.
+        let mut charged: BTreeMap<OrderId, u64> = BTreeMap::new();
+        for record in self.charges.charges()? {
+            if orders.contains_key(&record.order_id) {
+                *charged.entry(record.order_id).or_default() += u64::from(record.amount.0);
+            } else {
+                report.orphan_charges.push(record);
+            }
+        }
+
+        for (id, order) in &orders {
+            let expected = match order.status {
+                OrderStatus::Paid | OrderStatus::Shipped => order.total.0,
+                _ => 0,
+            };
+            match charged.get(id) {
+                None if expected > 0 => report.missing_charges.push(*id),
+                None => {}
+                Some(&sum) if sum == u64::from(expected) => {}
+                Some(&sum) => report.amount_mismatches.push(AmountMismatch {
+                    order_id: *id,
+                    expected: Money(expected),
+                    charged: Money(u32::try_from(sum).unwrap_or(u32::MAX)),
+                }),
+            }
+        }
+        Ok(report)
+    }
+}
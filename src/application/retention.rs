@@ -0,0 +1,153 @@
+// "Orders are not kept forever"
+//
+// purge asks the retention policy about every order the repository holds,
+// deleted or not, and forgets for good the ones it picks: the order, and
+// what else the service keeps about it, its idempotency keys, a charge
+// still deferred for it and the files attached to it. Nothing restores
+// them. Without a policy, nothing
+// is purged.
+//
+// An order picked while something about it is unsettled is kept, and the
+// report says why: a cancelled order the ChargeLog still shows a charge
+// for (its refund never went through), a paid order still to ship, an
+// order whose checkout has not finished. Without a ChargeLog, a cancelled
+// order is taken to be refunded.
+//
+// What surrounds the order goes first, the order last: a failure midway
+// leaves it in place, picked again by the next run.
+use super::OrderService;
+use super::checked::CheckedReads;
+use crate::domain::{Order, OrderError, OrderId, OrderStatus, Timestamp};
+use crate::ports::{OrderWriter, PaymentGateway, Port, Sender};
+use std::collections::BTreeSet;
+
+#[derive(Debug, Clone, Default)]
+pub struct PurgeReport {
+    pub purged: Vec<OrderId>,
+    // Idempotency keys forgotten along with the purged orders.
+    pub keys_forgotten: usize,
+    // Picked by the policy and kept all the same, each with its
+    // RetentionBlocked.
+    pub blocked: Vec<(OrderId, OrderError)>,
+    pub failed: Vec<(OrderId, OrderError)>,
+}
+
+impl<R, P, N> OrderService<'_, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    // Only a failure to scan the repository or the charges aborts the
+    // whole run; per-order problems end up in the report.
+    pub fn purge(&mut self, now: Timestamp) -> Result<PurgeReport, OrderError> {
+        const USE_CASE: &str = "purge";
+        let mut report = PurgeReport::default();
+        let Some(policy) = self.retention else {
+            return Ok(report);
+        };
+        let mut picked = Vec::new();
+        self.repository
+            .for_each_checked(&mut |order| {
+                if policy.should_purge(order, now) {
+                    picked.push(order.clone());
+                }
+            })
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "for_each", None, e))?;
+        let deleted = self
+            .repository
+            .list_deleted_checked()
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "list_deleted", None, e))?;
+        picked.extend(
+            deleted
+                .into_iter()
+                .filter(|order| policy.should_purge(order, now)),
+        );
+
+        let charged: BTreeSet<OrderId> = match self.charge_log {
+            Some(log) => log
+                .charges()
+                .map_err(|e| self.report(USE_CASE, Some(Port::Payment), "charges", None, e))?
+                .iter()
+                .map(|charge| charge.order_id)
+                .collect(),
+            None => BTreeSet::new(),
+        };
+        let deferred: BTreeSet<OrderId> = match self.pending_charges {
+            Some(store) => store
+                .pending()
+                .map_err(|e| self.report(USE_CASE, Some(Port::PendingCharges), "pending", None, e))?
+                .iter()
+                .map(|charge| charge.order_id)
+                .collect(),
+            None => BTreeSet::new(),
+        };
+
+        for order in picked {
+            let id = order.id;
+            if let Some(reason) = unsettled(&order, &charged) {
+                report
+                    .blocked
+                    .push((id, OrderError::RetentionBlocked { reason }));
+                continue;
+            }
+            match self.purge_one(&order, deferred.contains(&id)) {
+                Ok(keys) => {
+                    report.purged.push(id);
+                    report.keys_forgotten += keys;
+                }
+                Err(e) => report.failed.push((id, e)),
+            }
+        }
+        Ok(report)
+    }
+
+    // The number of idempotency keys forgotten.
+    fn purge_one(&mut self, order: &Order, deferred: bool) -> Result<usize, OrderError> {
+        const USE_CASE: &str = "purge";
+        let id = order.id;
+        // Deleting a blob already gone succeeds: a run that stopped halfway
+        // is picked up again.
+        if let Some(blobs) = self.blobs {
+            for attachment in &order.attachments {
+                blobs
+                    .delete(attachment.blob)
+                    .map_err(|e| self.report(USE_CASE, Some(Port::Blobs), "delete", Some(id), e))?;
+            }
+        }
+        if let (true, Some(store)) = (deferred, self.pending_charges) {
+            store.remove(id).map_err(|e| {
+                self.report(USE_CASE, Some(Port::PendingCharges), "remove", Some(id), e)
+            })?;
+        }
+        let keys = match self.idempotency {
+            Some(idempotency) => idempotency.store.forget_order(id).map_err(|e| {
+                self.report(
+                    USE_CASE,
+                    Some(Port::Idempotency),
+                    "forget_order",
+                    Some(id),
+                    e,
+                )
+            })?,
+            None => 0,
+        };
+        self.repository
+            .purge(id)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "purge", Some(id), e))?;
+        Ok(keys)
+    }
+}
+
+// Why `order` may not be forgotten yet, if anything stops it.
+fn unsettled(order: &Order, charged: &BTreeSet<OrderId>) -> Option<String> {
+    match order.status {
+        OrderStatus::Shipped => None,
+        OrderStatus::Cancelled if charged.contains(&order.id) => {
+            Some("refund not settled: the charge is still on record".to_string())
+        }
+        OrderStatus::Cancelled => None,
+        OrderStatus::Paid => Some("shipment pending: paid, not shipped yet".to_string()),
+        status => Some(format!("checkout not finished: still {status}")),
+    }
+}
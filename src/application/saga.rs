@@ -0,0 +1,223 @@
+// "Check this cart out": reserve the stock, place the order, confirm the
+// stock. Three calls to two services, none of which can roll the others
+// back; so each step that went through has a compensation, run in reverse
+// when a later one fails:
+//
+//     reserve inventory     <- release the reservation
+//     place the order       <- cancel it (refunded if it was paid)
+//     confirm reservation
+//
+// Every outcome is written to the SagaLog before the saga moves on. A saga
+// interrupted half way, by a crash or a log that stopped answering, is
+// resumed from there: done steps are skipped, a failure that was being
+// compensated keeps being compensated. Steps are written so that running
+// one again is harmless, since a crash may come after a step went through
+// and before its entry was written: the reservation is keyed by the saga's
+// id, the order placed with the saga's idempotency key (give the
+// OrderService an IdempotencyStore, or a retried place is a second order),
+// and cancelling a cancelled order only returns it.
+//
+// Only confirming may have to do its step twice over: a reservation left
+// unconfirmed long enough expires (see OrderService::maintenance), and is
+// made again, OutOfStock if the stock went to someone else meanwhile.
+use super::OrderService;
+use crate::domain::{LineItem, Order, OrderError, OrderId, SagaId};
+use crate::ports::{
+    Inventory, OrderWriter, PaymentGateway, ReservationId, SagaEntry, SagaLog, SagaStep, Sender,
+};
+
+pub struct CheckoutSaga<'a, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    orders: OrderService<'a, R, P, N>,
+    inventory: &'a (dyn Inventory + Sync),
+    log: &'a (dyn SagaLog + Sync),
+}
+
+// Where a saga stands, read from its log.
+struct Progress {
+    items: Vec<LineItem>,
+    reserved: bool,
+    order_id: Option<OrderId>,
+    confirmed: bool,
+    failure: Option<OrderError>,
+    compensated: Vec<SagaStep>,
+}
+
+impl Progress {
+    fn of(entries: Vec<SagaEntry>) -> Option<Self> {
+        let mut entries = entries.into_iter();
+        let Some(SagaEntry::Started { items }) = entries.next() else {
+            return None;
+        };
+        let mut progress = Progress {
+            items,
+            reserved: false,
+            order_id: None,
+            confirmed: false,
+            failure: None,
+            compensated: Vec::new(),
+        };
+        for entry in entries {
+            match entry {
+                SagaEntry::Started { .. } => {}
+                SagaEntry::Reserved { .. } => progress.reserved = true,
+                SagaEntry::OrderPlaced { order_id } => progress.order_id = Some(order_id),
+                SagaEntry::Confirmed => progress.confirmed = true,
+                SagaEntry::Failed { error, .. } => progress.failure = Some(error),
+                SagaEntry::Compensated { step } => progress.compensated.push(step),
+            }
+        }
+        Some(progress)
+    }
+}
+
+impl<'a, R, P, N> CheckoutSaga<'a, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    pub fn new(
+        orders: OrderService<'a, R, P, N>,
+        inventory: &'a (dyn Inventory + Sync),
+        log: &'a (dyn SagaLog + Sync),
+    ) -> Self {
+        Self {
+            orders,
+            inventory,
+            log,
+        }
+    }
+
+    pub fn orders(&self) -> &OrderService<'a, R, P, N> {
+        &self.orders
+    }
+
+    // The stock held for saga `saga`.
+    pub fn reservation(saga: SagaId) -> ReservationId {
+        ReservationId(saga.0)
+    }
+
+    // Runs saga `saga` for `items`. A saga id already in the log is not
+    // started again: it is resumed, and `items` ignored. A saga that
+    // completed answers with its order, one that was compensated with the
+    // error that stopped it.
+    pub fn execute(&mut self, saga: SagaId, items: Vec<LineItem>) -> Result<Order, OrderError> {
+        if self.log.entries(saga)?.is_empty() {
+            self.log.append(saga, SagaEntry::Started { items })?;
+        }
+        self.resume(saga)
+    }
+
+    // Picks saga `saga` up where its log says it stopped.
+    pub fn resume(&mut self, saga: SagaId) -> Result<Order, OrderError> {
+        let mut progress =
+            Progress::of(self.log.entries(saga)?).ok_or(OrderError::UnknownSaga { id: saga })?;
+        if let Some(error) = progress.failure.take() {
+            return self.compensate(saga, &progress, error);
+        }
+        let reservation = Self::reservation(saga);
+
+        if !progress.reserved {
+            if let Err(error) = self.inventory.reserve(reservation, &progress.items) {
+                return self.fail(saga, &progress, SagaStep::ReserveInventory, error);
+            }
+            self.log.append(saga, SagaEntry::Reserved { reservation })?;
+            progress.reserved = true;
+        }
+
+        let order_id = match progress.order_id {
+            Some(order_id) => order_id,
+            None => {
+                let key = format!("saga-{}", saga.0);
+                match self.orders.place_order_once(&key, progress.items.clone()) {
+                    Ok(order) => {
+                        self.log
+                            .append(saga, SagaEntry::OrderPlaced { order_id: order.id })?;
+                        progress.order_id = Some(order.id);
+                        order.id
+                    }
+                    Err(error) => return self.fail(saga, &progress, SagaStep::PlaceOrder, error),
+                }
+            }
+        };
+
+        if !progress.confirmed {
+            if let Err(error) = self.confirm(reservation, &progress.items) {
+                return self.fail(saga, &progress, SagaStep::ConfirmReservation, error);
+            }
+            self.log.append(saga, SagaEntry::Confirmed)?;
+        }
+
+        self.orders
+            .get_order(order_id)?
+            .ok_or(OrderError::NotFound { id: order_id })
+    }
+
+    // A saga resumed long after it reserved may find its reservation gone,
+    // expired meanwhile: the stock is reserved again, if there still is
+    // some, then confirmed.
+    fn confirm(&self, reservation: ReservationId, items: &[LineItem]) -> Result<(), OrderError> {
+        match self.inventory.confirm(reservation) {
+            Err(OrderError::InvalidOrder) => {
+                self.inventory.reserve(reservation, items)?;
+                self.inventory.confirm(reservation)
+            }
+            outcome => outcome,
+        }
+    }
+
+    fn fail(
+        &mut self,
+        saga: SagaId,
+        progress: &Progress,
+        step: SagaStep,
+        error: OrderError,
+    ) -> Result<Order, OrderError> {
+        self.log.append(
+            saga,
+            SagaEntry::Failed {
+                step,
+                error: error.clone(),
+            },
+        )?;
+        self.compensate(saga, progress, error)
+    }
+
+    // Undoes the steps done, last first, and answers with the error that
+    // stopped the saga. A compensation that fails stops here with its own
+    // error; resuming tries it again.
+    fn compensate(
+        &mut self,
+        saga: SagaId,
+        progress: &Progress,
+        error: OrderError,
+    ) -> Result<Order, OrderError> {
+        let done = |step| progress.compensated.contains(&step);
+        if let Some(order_id) = progress.order_id
+            && !done(SagaStep::PlaceOrder)
+        {
+            self.orders.cancel_order(order_id)?;
+            self.log.append(
+                saga,
+                SagaEntry::Compensated {
+                    step: SagaStep::PlaceOrder,
+                },
+            )?;
+        }
+        if progress.reserved && !done(SagaStep::ReserveInventory) {
+            self.inventory.release(Self::reservation(saga))?;
+            self.log.append(
+                saga,
+                SagaEntry::Compensated {
+                    step: SagaStep::ReserveInventory,
+                },
+            )?;
+        }
+        Err(error)
+    }
+}
@@ -0,0 +1,160 @@
+// "The payment provider is back: charge what was sold offline"
+//
+// Every charge in the PendingCharges store is tried again through
+// PaymentGateway::charge_for. Charged, the order becomes Paid and the customer
+// gets the confirmation place_order held back; declined, it is cancelled.
+// A provider still unreachable, or an issuer answering TryAgainLater, leaves
+// the charge where it is, for the next run.
+//
+// The charge is removed from the store right after the provider answered,
+// before the order is updated: if the update then fails, the order needs a
+// look, but the next run will not charge the customer a second time.
+use super::OrderService;
+use super::checked::CheckedReads;
+use crate::domain::{OrderError, OrderEvent, OrderId, OrderStatus};
+use crate::ports::{OrderWriter, PaymentGateway, PendingCharge, PendingCharges, Port, Sender};
+
+pub const DECLINED_AT_SETTLEMENT: &str = "payment declined at settlement";
+
+#[derive(Debug, Clone, Default)]
+pub struct SettlementReport {
+    pub paid: Vec<OrderId>,
+    // Declined by the provider: cancelled.
+    pub declined: Vec<OrderId>,
+    // The provider is still unreachable, or asked to try again later.
+    pub still_pending: Vec<OrderId>,
+    pub failed: Vec<(OrderId, OrderError)>,
+}
+
+enum Settled {
+    Paid,
+    Declined,
+    StillPending,
+}
+
+impl<R, P, N> OrderService<'_, R, P, N>
+where
+    R: OrderWriter + ?Sized,
+    P: PaymentGateway + ?Sized,
+    N: Sender + ?Sized,
+{
+    // Oldest charge first. Without a PendingCharges store there is nothing
+    // to settle. Only a failure to read the store aborts the whole run;
+    // per-order problems end up in the report.
+    pub fn settle_pending(&mut self) -> Result<SettlementReport, OrderError> {
+        let mut report = SettlementReport::default();
+        let Some(store) = self.pending_charges else {
+            return Ok(report);
+        };
+        let charges = store.pending().map_err(|e| {
+            self.report(
+                "settle_pending",
+                Some(Port::PendingCharges),
+                "pending",
+                None,
+                e,
+            )
+        })?;
+
+        for charge in charges {
+            let id = charge.order_id;
+            match self.settle_one(store, charge) {
+                Ok(Settled::Paid) => report.paid.push(id),
+                Ok(Settled::Declined) => report.declined.push(id),
+                Ok(Settled::StillPending) => report.still_pending.push(id),
+                Err(e) => report.failed.push((id, e)),
+            }
+        }
+        Ok(report)
+    }
+
+    fn settle_one(
+        &mut self,
+        store: &(dyn PendingCharges + Sync),
+        charge: PendingCharge,
+    ) -> Result<Settled, OrderError> {
+        const USE_CASE: &str = "settle_pending";
+        let id = charge.order_id;
+        // A charge whose order was never saved, or was settled some other
+        // way, is dropped without charging anything.
+        let mut order = match self.repository.find_checked(id) {
+            Ok(Some(order)) if order.status == OrderStatus::PaymentPending => order,
+            Ok(found) => {
+                let e = match found {
+                    Some(order) => OrderError::InvalidTransition {
+                        from: order.status,
+                        to: OrderStatus::Paid,
+                    },
+                    None => OrderError::NotFound { id },
+                };
+                self.remove_charge(store, id)?;
+                return Err(self.report(USE_CASE, None, "check_pending", Some(id), e));
+            }
+            Err(e) => {
+                return Err(self.report(USE_CASE, Some(Port::Repository), "find", Some(id), e));
+            }
+        };
+
+        let (to, settled) = match self.payment.charge_for(id, charge.amount) {
+            Ok(()) => (OrderStatus::Paid, Settled::Paid),
+            Err(OrderError::PaymentDeclined { reason }) if reason.is_retryable() => {
+                return Ok(Settled::StillPending);
+            }
+            Err(OrderError::PaymentFailed | OrderError::PaymentDeclined { .. }) => {
+                (OrderStatus::Cancelled, Settled::Declined)
+            }
+            Err(OrderError::PaymentUnavailable) => return Ok(Settled::StillPending),
+            Err(e) => {
+                return Err(self.report(USE_CASE, Some(Port::Payment), "charge", Some(id), e));
+            }
+        };
+        self.remove_charge(store, id)?;
+
+        order
+            .transition_to(to)
+            .map_err(|e| self.report(USE_CASE, None, "settle", Some(id), e))?;
+        self.repository
+            .update(&order)
+            .map_err(|e| self.report(USE_CASE, Some(Port::Repository), "update", Some(id), e))?;
+        order.version += 1;
+
+        match settled {
+            Settled::Paid => {
+                self.publish(
+                    USE_CASE,
+                    id,
+                    &[OrderEvent::OrderPaid {
+                        order_id: id,
+                        amount: order.total,
+                    }],
+                )?;
+                self.confirm(USE_CASE, &order)?;
+            }
+            _ => self.publish(
+                USE_CASE,
+                id,
+                &[OrderEvent::OrderRejected {
+                    order_id: id,
+                    reason: DECLINED_AT_SETTLEMENT.to_string(),
+                }],
+            )?,
+        }
+        Ok(settled)
+    }
+
+    fn remove_charge(
+        &self,
+        store: &(dyn PendingCharges + Sync),
+        id: OrderId,
+    ) -> Result<(), OrderError> {
+        store.remove(id).map_err(|e| {
+            self.report(
+                "settle_pending",
+                Some(Port::PendingCharges),
+                "remove",
+                Some(id),
+                e,
+            )
+        })
+    }
+}
@@ -0,0 +1,123 @@
+// cargo run --bin hexlite -- list
+// cargo run --bin hexlite -- run <name>
+
+// The demos of hexa_lite::scenarios, by name. `list` says what there is;
+// `run` plays one, prints everything it printed, then its summary, and
+// exits with 1 when a checkpoint failed.
+use hexa_lite::scenarios::ScenarioRegistry;
+use std::io::{self, Write};
+use std::process::ExitCode;
+
+const USAGE: &str = "usage: hexlite list | hexlite run <name>";
+
+// What main does, writing to `out` and returning the exit status: 0 when
+// all went well, 1 for a failed scenario, 2 for a command it cannot run.
+fn run(registry: &ScenarioRegistry, args: &[String], out: &mut impl Write) -> io::Result<u8> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    match args.as_slice() {
+        ["list"] => {
+            writeln!(out, "{}", registry.list())?;
+            Ok(0)
+        }
+        ["run", name] => match registry.run(name) {
+            Some(report) => {
+                write!(out, "{}", report.output)?;
+                writeln!(out, "\n{}", report.summary)?;
+                Ok(if report.passed { 0 } else { 1 })
+            }
+            None => {
+                writeln!(out, "no scenario named {name:?}; `hexlite list` shows them")?;
+                Ok(2)
+            }
+        },
+        _ => {
+            writeln!(out, "{USAGE}")?;
+            Ok(2)
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let registry = ScenarioRegistry::standard();
+    match run(&registry, &args, &mut io::stdout().lock()) {
+        Ok(status) => ExitCode::from(status),
+        Err(error) => {
+            eprintln!("hexlite: {error}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hexlite(args: &[&str]) -> (u8, String) {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        let mut out = Vec::new();
+        let status = run(&ScenarioRegistry::standard(), &args, &mut out).unwrap();
+        (status, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn list_names_every_scenario() {
+        assert_eq!(
+            hexlite(&["list"]),
+            (
+                0,
+                "\
+quickstart      Orders placed, charged and confirmed in memory
+approval        A large order waits uncharged until someone approves it
+fraud-review    The fraud screen refuses one customer and sends a large order to review
+draft-conflict  Two tabs edit one cart; their additions are merged
+"
+                .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn run_prints_the_scenario_then_its_summary() {
+        let (status, out) = hexlite(&["run", "quickstart"]);
+
+        assert_eq!(status, 0);
+        assert!(out.starts_with("\n=== Step 1: A book, then a keyboard and a mouse ===\n"));
+        assert!(out.contains("  [MockPayment] Charging $49.99"), "{out}");
+        assert!(out.ends_with(
+            "\
+step  checkpoint                            result
+1     both orders are paid                  PASS
+1     each was charged its total            PASS
+2     it is refused, and nothing is stored  PASS
+3/3 checkpoints passed
+PASS quickstart
+"
+        ));
+    }
+
+    #[test]
+    fn run_of_another_scenario() {
+        let (status, out) = hexlite(&["run", "approval"]);
+
+        assert_eq!(status, 0);
+        assert!(out.ends_with(
+            "\
+step  checkpoint                  result
+1     the order waits, uncharged  PASS
+2     it is charged and paid      PASS
+2/2 checkpoints passed
+PASS approval
+"
+        ));
+    }
+
+    #[test]
+    fn unknown_commands_and_names_are_refused() {
+        assert_eq!(hexlite(&[]), (2, format!("{USAGE}\n")));
+        assert_eq!(hexlite(&["run"]).0, 2);
+        let (status, out) = hexlite(&["run", "nope"]);
+        assert_eq!(status, 2);
+        assert!(out.starts_with("no scenario named \"nope\""));
+    }
+}
@@ -0,0 +1,1285 @@
+// =============================================================================
+// COMPOSITION ROOT - Choosing the Adapters
+// =============================================================================
+// The one place that knows which concrete adapters run in production.
+// Instead of raw strings scattered in main(), the choice comes from
+// environment variables, read once into a typed EnvConfig:
+//
+//     HEXLITE_SENDER          console (default) | sendgrid | webhook
+//     HEXLITE_SENDGRID_KEY    required with sendgrid, non-empty
+//     HEXLITE_WEBHOOK_URL     required with webhook, http:// or https://
+//     HEXLITE_WEBHOOK_SECRET  optional; when set, webhooks are signed
+//     HEXLITE_EVENT_LOG       optional path of a FileEventLog
+//     HEXLITE_REPOSITORY      memory (default) | a registered factory
+//     HEXLITE_PAYMENT         mock (default) | a registered factory
+//     HEXLITE_<PORT>_OPTIONS  key=value,... for the factory named above
+//
+// A factory is how another crate's adapters get in: see register_factory.
+//
+// Every problem is collected before anything is reported: a deployment
+// with three bad variables learns about the three at once. The same goes
+// for the adapters themselves, which refuse to be built with settings that
+// cannot work (an event log in a missing directory...).
+//
+// What was chosen is recorded in a Wiring, one line per port, so a
+// deployment can print which adapters it actually runs with. The adapters
+// themselves are put in a Registry, once per port, before the service
+// takes them out. Once built, self_test runs a synthetic order through
+// them, and cleans up after it.
+use crate::adapters::event_log::FileEventLog;
+use crate::adapters::external::SendGridSender;
+use crate::adapters::in_memory::{ConsoleSender, InMemoryOrderRepository, MockPaymentGateway};
+use crate::adapters::webhook::{WebhookSender, check_url};
+use crate::adapters::{self, SecretString};
+use crate::application::{OrderBrowser, OrderService};
+use crate::domain::{
+    Money, Notice, Order, OrderConfirmation, OrderError, OrderId, OrderKey, OrderReference,
+};
+use crate::facade::DefaultFacade;
+use crate::ports::{
+    CancelToken, Capability, ChargeLog, ChargeOutcome, ChargeRecord, Direction, EventPublisher,
+    OrderFacade, OrderReader, OrderWriter, PaymentGateway, PortInfo, PortSpec, Sender, all_ports,
+};
+use std::any::{Any, TypeId};
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+mod plugins;
+mod self_test;
+mod shutdown;
+
+pub use plugins::{AdapterConfig, AdapterFactory, is_registered, register_factory};
+pub use self_test::{CheckOutcome, PortCheck, SelfTestOptions, SelfTestReport, self_test};
+pub use shutdown::{ShutdownCoordinator, ShutdownPriority, ShutdownReport, ShutdownStep};
+
+pub const SENDER_VAR: &str = "HEXLITE_SENDER";
+pub const SENDGRID_KEY_VAR: &str = "HEXLITE_SENDGRID_KEY";
+pub const WEBHOOK_URL_VAR: &str = "HEXLITE_WEBHOOK_URL";
+pub const WEBHOOK_SECRET_VAR: &str = "HEXLITE_WEBHOOK_SECRET";
+pub const EVENT_LOG_VAR: &str = "HEXLITE_EVENT_LOG";
+pub const REPOSITORY_VAR: &str = "HEXLITE_REPOSITORY";
+pub const PAYMENT_VAR: &str = "HEXLITE_PAYMENT";
+pub const REPOSITORY_OPTIONS_VAR: &str = "HEXLITE_REPOSITORY_OPTIONS";
+pub const PAYMENT_OPTIONS_VAR: &str = "HEXLITE_PAYMENT_OPTIONS";
+pub const SENDER_OPTIONS_VAR: &str = "HEXLITE_SENDER_OPTIONS";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SenderConfig {
+    Console,
+    SendGrid {
+        api_key: SecretString,
+    },
+    Webhook {
+        url: String,
+        signing_key: Option<SecretString>,
+    },
+    // Made by a registered factory.
+    Plugin(AdapterConfig),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvConfig {
+    pub sender: SenderConfig,
+    pub event_log: Option<PathBuf>,
+    // None: the built-in in-memory repository and mock gateway.
+    pub repository: Option<AdapterConfig>,
+    pub payment: Option<AdapterConfig>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConfigError {
+    Missing { var: &'static str },
+    Invalid { var: &'static str, reason: String },
+    // Configured, but the adapter refused to be built with it.
+    Adapter(adapters::ConfigError),
+    // More than one of the above, in the order the variables are read,
+    // then the order the adapters are built.
+    Multiple(Vec<ConfigError>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Missing { var } => write!(f, "{var} is not set"),
+            ConfigError::Invalid { var, reason } => write!(f, "{var} is invalid: {reason}"),
+            ConfigError::Adapter(error) => write!(f, "{error}"),
+            ConfigError::Multiple(errors) => {
+                write!(f, "{} configuration errors:", errors.len())?;
+                for error in errors {
+                    write!(f, "\n  - {error}")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl EnvConfig {
+    pub fn load() -> Result<Self, ConfigError> {
+        Self::from_lookup(|var| std::env::var(var).ok())
+    }
+
+    // Same as load(), reading from any source. Tests use a HashMap.
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let mut errors = Vec::new();
+
+        let sender_options = options(&lookup, SENDER_OPTIONS_VAR, &mut errors);
+        let sender = match lookup(SENDER_VAR).as_deref() {
+            Some(name) if plugins::is_registered(name) => sender_options.map(|options| {
+                SenderConfig::Plugin(AdapterConfig {
+                    var: SENDER_VAR,
+                    name: name.to_string(),
+                    options,
+                })
+            }),
+            _ if sender_options
+                .as_ref()
+                .is_some_and(|options| !options.is_empty()) =>
+            {
+                errors.push(only_for_factories(SENDER_OPTIONS_VAR));
+                None
+            }
+            None | Some("console") => Some(SenderConfig::Console),
+            Some("sendgrid") => secret(&lookup, SENDGRID_KEY_VAR, &mut errors)
+                .map(|api_key| SenderConfig::SendGrid { api_key }),
+            Some("webhook") => {
+                let url = url(&lookup, WEBHOOK_URL_VAR, &mut errors);
+                let signing_key = match lookup(WEBHOOK_SECRET_VAR) {
+                    None => Some(None),
+                    Some(_) => secret(&lookup, WEBHOOK_SECRET_VAR, &mut errors).map(Some),
+                };
+                url.zip(signing_key)
+                    .map(|(url, signing_key)| SenderConfig::Webhook { url, signing_key })
+            }
+            Some(other) => {
+                errors.push(ConfigError::Invalid {
+                    var: SENDER_VAR,
+                    reason: format!(
+                        "unknown sender {other:?}, expected console, sendgrid, webhook \
+                         or a registered factory"
+                    ),
+                });
+                None
+            }
+        };
+
+        let event_log = match lookup(EVENT_LOG_VAR) {
+            Some(path) if path.trim().is_empty() => {
+                errors.push(ConfigError::Invalid {
+                    var: EVENT_LOG_VAR,
+                    reason: "empty path".to_string(),
+                });
+                None
+            }
+            path => path.map(PathBuf::from),
+        };
+
+        let repository = plugin(
+            &lookup,
+            (REPOSITORY_VAR, REPOSITORY_OPTIONS_VAR),
+            "memory",
+            &mut errors,
+        );
+        let payment = plugin(
+            &lookup,
+            (PAYMENT_VAR, PAYMENT_OPTIONS_VAR),
+            "mock",
+            &mut errors,
+        );
+
+        match (sender, repository, payment) {
+            (Some(sender), Some(repository), Some(payment)) if errors.is_empty() => Ok(EnvConfig {
+                sender,
+                event_log,
+                repository,
+                payment,
+            }),
+            _ => Err(one_or_many(errors)),
+        }
+    }
+}
+
+// Some(None) for the built-in, Some(Some(_)) for a registered factory,
+// None when refused: a name that is neither, or bad options.
+fn plugin(
+    lookup: &impl Fn(&str) -> Option<String>,
+    (var, options_var): (&'static str, &'static str),
+    built_in: &str,
+    errors: &mut Vec<ConfigError>,
+) -> Option<Option<AdapterConfig>> {
+    let options = options(lookup, options_var, errors)?;
+    match lookup(var) {
+        Some(name) if plugins::is_registered(&name) => {
+            Some(Some(AdapterConfig { var, name, options }))
+        }
+        Some(name) if name != built_in => {
+            errors.push(ConfigError::Invalid {
+                var,
+                reason: format!(
+                    "unknown adapter {name:?}, expected {built_in} or a registered factory"
+                ),
+            });
+            None
+        }
+        _ if !options.is_empty() => {
+            errors.push(only_for_factories(options_var));
+            None
+        }
+        _ => Some(None),
+    }
+}
+
+fn options(
+    lookup: &impl Fn(&str) -> Option<String>,
+    var: &'static str,
+    errors: &mut Vec<ConfigError>,
+) -> Option<Vec<(String, String)>> {
+    match plugins::parse_options(&lookup(var).unwrap_or_default()) {
+        Ok(options) => Some(options),
+        Err(reason) => {
+            errors.push(ConfigError::Invalid { var, reason });
+            None
+        }
+    }
+}
+
+fn only_for_factories(var: &'static str) -> ConfigError {
+    ConfigError::Invalid {
+        var,
+        reason: "only read by a registered factory, and none was chosen".to_string(),
+    }
+}
+
+fn one_or_many(mut errors: Vec<ConfigError>) -> ConfigError {
+    match errors.len() {
+        1 => errors.remove(0),
+        _ => ConfigError::Multiple(errors),
+    }
+}
+
+fn secret(
+    lookup: &impl Fn(&str) -> Option<String>,
+    var: &'static str,
+    errors: &mut Vec<ConfigError>,
+) -> Option<SecretString> {
+    match lookup(var) {
+        None => {
+            errors.push(ConfigError::Missing { var });
+            None
+        }
+        Some(value) if value.trim().is_empty() => {
+            errors.push(ConfigError::Invalid {
+                var,
+                reason: "empty secret".to_string(),
+            });
+            None
+        }
+        Some(value) => Some(SecretString::new(value)),
+    }
+}
+
+fn url(
+    lookup: &impl Fn(&str) -> Option<String>,
+    var: &'static str,
+    errors: &mut Vec<ConfigError>,
+) -> Option<String> {
+    let value = match lookup(var) {
+        None => {
+            errors.push(ConfigError::Missing { var });
+            return None;
+        }
+        Some(value) => value,
+    };
+    match check_url(&value) {
+        Ok(()) => Some(value),
+        Err(reason) => {
+            errors.push(ConfigError::Invalid {
+                var,
+                reason: reason.to_string(),
+            });
+            None
+        }
+    }
+}
+
+// Which adapter fulfils which port. Only the composition root fills it:
+//
+//     wiring.bind::<dyn PaymentGateway>("StripePaymentGateway")?;
+//
+// A port is bound at most once; a second binding is a conflict, not an
+// override, since two places disagreeing on an adapter is a wiring bug.
+#[derive(Debug, Clone, Default)]
+pub struct Wiring {
+    bindings: Vec<(TypeId, PortSpec, Binding)>,
+}
+
+// One row of Wiring::describe(). `config` is what is worth knowing about
+// that adapter, secrets excluded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Binding {
+    pub port: &'static str,
+    pub adapter: String,
+    pub config: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WiringError {
+    Conflict {
+        port: &'static str,
+        bound: String,
+        rejected: String,
+    },
+}
+
+impl fmt::Display for WiringError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WiringError::Conflict {
+                port,
+                bound,
+                rejected,
+            } => write!(
+                f,
+                "{port} is already bound to {bound}, cannot bind {rejected}"
+            ),
+        }
+    }
+}
+
+impl Wiring {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn bind<P: ?Sized + PortInfo + 'static>(
+        &mut self,
+        adapter: impl Into<String>,
+    ) -> Result<&mut Self, WiringError> {
+        self.insert::<P>(adapter.into(), None)
+    }
+
+    pub fn bind_with<P: ?Sized + PortInfo + 'static>(
+        &mut self,
+        adapter: impl Into<String>,
+        config: impl Into<String>,
+    ) -> Result<&mut Self, WiringError> {
+        self.insert::<P>(adapter.into(), Some(config.into()))
+    }
+
+    fn insert<P: ?Sized + PortInfo + 'static>(
+        &mut self,
+        adapter: String,
+        config: Option<String>,
+    ) -> Result<&mut Self, WiringError> {
+        let port = P::port_name();
+        if let Some((_, _, bound)) = self
+            .bindings
+            .iter()
+            .find(|(id, _, _)| *id == TypeId::of::<P>())
+        {
+            return Err(WiringError::Conflict {
+                port,
+                bound: bound.adapter.clone(),
+                rejected: adapter,
+            });
+        }
+        self.bindings.push((
+            TypeId::of::<P>(),
+            PortSpec::of::<P>(),
+            Binding {
+                port,
+                adapter,
+                config,
+            },
+        ));
+        Ok(self)
+    }
+
+    // In binding order.
+    pub fn describe(&self) -> Vec<Binding> {
+        self.bindings
+            .iter()
+            .map(|(_, _, binding)| binding.clone())
+            .collect()
+    }
+}
+
+// A table, columns padded to their widest cell:
+//
+//     port         adapter                  config
+//     OrderReader  InMemoryOrderRepository
+//     OrderWriter  InMemoryOrderRepository
+//     Sender       WebhookSender            https://example.test/hook, signed
+impl fmt::Display for Wiring {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let rows = self.describe();
+        let port_width = rows.iter().map(|b| b.port.len()).fold(4, usize::max);
+        let adapter_width = rows.iter().map(|b| b.adapter.len()).fold(7, usize::max);
+        write!(
+            f,
+            "{:<port_width$}  {:<adapter_width$}  config",
+            "port", "adapter"
+        )?;
+        for row in &rows {
+            let line = format!(
+                "{:<port_width$}  {:<adapter_width$}  {}",
+                row.port,
+                row.adapter,
+                row.config.as_deref().unwrap_or("")
+            );
+            write!(f, "\n{}", line.trim_end())?;
+        }
+        Ok(())
+    }
+}
+
+// The hexagon as a Mermaid flowchart: every port of ports::all_ports(), and
+// any other bound one, on its side of the domain, with the adapter bound to
+// it outside. Ports nobody is bound to are still drawn: the hexagon has
+// them, this deployment just does not plug anything in.
+//
+//     flowchart LR
+//         subgraph hexagon [Hexagon]
+//             subgraph inbound [Inbound ports]
+//                 port_PlaceOrderUseCase["PlaceOrderUseCase<br/>place_order"]
+//             end
+//             domain{{"Domain"}}
+//             ...
+//         port_OrderRepository --> adapter_InMemoryOrderRepository
+//
+// Paste it in a ```mermaid block of a README, or give it to mmdc.
+pub fn hexagon_diagram(wiring: &Wiring) -> String {
+    let mut ports = all_ports();
+    for (_, spec, _) in &wiring.bindings {
+        if !ports.iter().any(|port| port.name == spec.name) {
+            ports.push(spec.clone());
+        }
+    }
+    let adapter_of = |port: &PortSpec| {
+        wiring
+            .bindings
+            .iter()
+            .find(|(_, spec, _)| spec.name == port.name)
+            .map(|(_, _, binding)| binding.adapter.as_str())
+    };
+    let (inbound, outbound): (Vec<&PortSpec>, Vec<&PortSpec>) = ports
+        .iter()
+        .partition(|port| port.direction == Direction::Inbound);
+
+    let mut lines = vec!["flowchart LR".to_string()];
+    adapter_group(
+        &mut lines,
+        "driving",
+        "Driving adapters",
+        inbound.iter().filter_map(|port| adapter_of(port)),
+    );
+    lines.push("    subgraph hexagon [Hexagon]".to_string());
+    port_group(&mut lines, "inbound", "Inbound ports", &inbound);
+    lines.push(r#"        domain{{"Domain"}}"#.to_string());
+    port_group(&mut lines, "outbound", "Outbound ports", &outbound);
+    lines.push("    end".to_string());
+    adapter_group(
+        &mut lines,
+        "driven",
+        "Driven adapters",
+        outbound.iter().filter_map(|port| adapter_of(port)),
+    );
+    // The arrows follow the calls: adapter -> port -> domain on the driving
+    // side, domain -> port -> adapter on the driven side.
+    for port in &inbound {
+        let id = node_id("port", port.name);
+        if let Some(adapter) = adapter_of(port) {
+            lines.push(format!("    {} --> {id}", node_id("adapter", adapter)));
+        }
+        lines.push(format!("    {id} --> domain"));
+    }
+    for port in &outbound {
+        let id = node_id("port", port.name);
+        lines.push(format!("    domain --> {id}"));
+        if let Some(adapter) = adapter_of(port) {
+            lines.push(format!("    {id} --> {}", node_id("adapter", adapter)));
+        }
+    }
+    lines.join("\n")
+}
+
+fn port_group(lines: &mut Vec<String>, id: &str, title: &str, ports: &[&PortSpec]) {
+    lines.push(format!("        subgraph {id} [{title}]"));
+    for port in ports {
+        lines.push(format!(
+            r#"            {}["{}<br/>{}"]"#,
+            node_id("port", port.name),
+            port.name,
+            port.methods.join(", ")
+        ));
+    }
+    lines.push("        end".to_string());
+}
+
+// Nothing is drawn for a side without adapters. An adapter bound to two
+// ports is drawn once.
+fn adapter_group<'a>(
+    lines: &mut Vec<String>,
+    id: &str,
+    title: &str,
+    adapters: impl Iterator<Item = &'a str>,
+) {
+    let mut names: Vec<&str> = Vec::new();
+    for adapter in adapters {
+        if !names.contains(&adapter) {
+            names.push(adapter);
+        }
+    }
+    if names.is_empty() {
+        return;
+    }
+    lines.push(format!("    subgraph {id} [{title}]"));
+    for name in names {
+        lines.push(format!(
+            r#"        {}(["{}"])"#,
+            node_id("adapter", name),
+            name.replace('"', "#quot;")
+        ));
+    }
+    lines.push("    end".to_string());
+}
+
+// Mermaid ids are single words: "Capped<Mock>" -> "adapter_Capped_Mock_"
+fn node_id(kind: &str, name: &str) -> String {
+    let name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("{kind}_{name}")
+}
+
+// The adapters of a composition root, each owned behind the port it is
+// plugged into:
+//
+//     registry.put::<dyn PaymentGateway>(MockPaymentGateway::new())?;
+//
+// Only an adapter implementing the port goes in, and only one per port: a
+// second one, or the same one wrapped twice, is refused rather than
+// silently replacing the first. verify_complete says which ports are still
+// missing, all of them at once; take hands each adapter over to whoever
+// builds the service, as its own type.
+/// # Examples
+///
+/// ```compile_fail
+/// use hexa_lite::composition::Registry;
+/// use hexa_lite::prelude::*;
+/// use hexa_lite::ports::PaymentGateway;
+///
+/// // A sender is not a payment gateway.
+/// Registry::new().put::<dyn PaymentGateway>(ConsoleSender::new());
+/// ```
+#[derive(Default)]
+pub struct Registry {
+    entries: Vec<Entry>,
+}
+
+struct Entry {
+    port: TypeId,
+    port_name: &'static str,
+    adapter_name: String,
+    adapter: Box<dyn Any>,
+}
+
+// What may be plugged into port P: implemented for every adapter of the
+// ports a Registry knows, so put::<dyn P> refuses anything else. Sealed:
+// another crate implementing it would put a non-adapter in a port.
+pub trait PlugsInto<P: ?Sized>: sealed::PlugsInto<P> {}
+
+impl<A: OrderWriter> PlugsInto<dyn OrderWriter> for A {}
+impl<A: PaymentGateway> PlugsInto<dyn PaymentGateway> for A {}
+impl<A: Sender> PlugsInto<dyn Sender> for A {}
+impl<A: EventPublisher> PlugsInto<dyn EventPublisher> for A {}
+
+mod sealed {
+    use crate::ports::{EventPublisher, OrderWriter, PaymentGateway, Sender};
+
+    pub trait PlugsInto<P: ?Sized> {}
+
+    impl<A: OrderWriter> PlugsInto<dyn OrderWriter> for A {}
+    impl<A: PaymentGateway> PlugsInto<dyn PaymentGateway> for A {}
+    impl<A: Sender> PlugsInto<dyn Sender> for A {}
+    impl<A: EventPublisher> PlugsInto<dyn EventPublisher> for A {}
+}
+
+// A list of ports, for Registry::verify_complete.
+pub trait PortSet {
+    fn ports() -> Vec<(TypeId, &'static str)>;
+}
+
+// What OrderService cannot be built without.
+pub struct RequiredPorts;
+
+impl PortSet for RequiredPorts {
+    fn ports() -> Vec<(TypeId, &'static str)> {
+        vec![
+            port_key::<dyn OrderWriter>(),
+            port_key::<dyn PaymentGateway>(),
+            port_key::<dyn Sender>(),
+        ]
+    }
+}
+
+fn port_key<P: ?Sized + PortInfo + 'static>() -> (TypeId, &'static str) {
+    (TypeId::of::<P>(), P::port_name())
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RegistryError {
+    Duplicate {
+        port: &'static str,
+        bound: String,
+        rejected: String,
+    },
+    // Every missing port, in the order the PortSet lists them.
+    Incomplete {
+        missing: Vec<&'static str>,
+    },
+    // take asked for an adapter type other than the one put in.
+    WrongAdapter {
+        port: &'static str,
+        bound: String,
+        requested: String,
+    },
+}
+
+impl fmt::Display for RegistryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RegistryError::Duplicate {
+                port,
+                bound,
+                rejected,
+            } => write!(
+                f,
+                "{port} already has an adapter, {bound}; {rejected} was put in twice"
+            ),
+            RegistryError::Incomplete { missing } => {
+                write!(f, "no adapter for {}", missing.join(", "))
+            }
+            RegistryError::WrongAdapter {
+                port,
+                bound,
+                requested,
+            } => write!(f, "{port} has a {bound}, not a {requested}"),
+        }
+    }
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn put<P: ?Sized + PortInfo + 'static>(
+        &mut self,
+        adapter: impl PlugsInto<P> + 'static,
+    ) -> Result<&mut Self, RegistryError> {
+        let adapter_name = short_type_name(type_name_of(&adapter));
+        if let Some(bound) = self.entry(TypeId::of::<P>()) {
+            return Err(RegistryError::Duplicate {
+                port: P::port_name(),
+                bound: bound.adapter_name.clone(),
+                rejected: adapter_name,
+            });
+        }
+        self.entries.push(Entry {
+            port: TypeId::of::<P>(),
+            port_name: P::port_name(),
+            adapter_name,
+            adapter: Box::new(adapter),
+        });
+        Ok(self)
+    }
+
+    // None when the port has nothing, or something other than an A.
+    pub fn get<P: ?Sized + 'static, A: PlugsInto<P> + 'static>(&self) -> Option<&A> {
+        self.entry(TypeId::of::<P>())?.adapter.downcast_ref()
+    }
+
+    pub fn take<P: ?Sized + PortInfo + 'static, A: PlugsInto<P> + 'static>(
+        &mut self,
+    ) -> Result<A, RegistryError> {
+        let at = self
+            .entries
+            .iter()
+            .position(|entry| entry.port == TypeId::of::<P>())
+            .ok_or(RegistryError::Incomplete {
+                missing: vec![P::port_name()],
+            })?;
+        if !self.entries[at].adapter.is::<A>() {
+            return Err(RegistryError::WrongAdapter {
+                port: P::port_name(),
+                bound: self.entries[at].adapter_name.clone(),
+                requested: short_type_name(std::any::type_name::<A>()),
+            });
+        }
+        let entry = self.entries.remove(at);
+        Ok(*entry
+            .adapter
+            .downcast()
+            .expect("the type was checked just above"))
+    }
+
+    pub fn verify_complete<S: PortSet>(&self) -> Result<(), RegistryError> {
+        let missing: Vec<&'static str> = S::ports()
+            .into_iter()
+            .filter(|(port, _)| self.entry(*port).is_none())
+            .map(|(_, name)| name)
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(RegistryError::Incomplete { missing })
+        }
+    }
+
+    // (port, adapter) for every port with something in it, in put order.
+    pub fn describe(&self) -> Vec<(&'static str, &str)> {
+        self.entries
+            .iter()
+            .map(|entry| (entry.port_name, entry.adapter_name.as_str()))
+            .collect()
+    }
+
+    fn entry(&self, port: TypeId) -> Option<&Entry> {
+        self.entries.iter().find(|entry| entry.port == port)
+    }
+}
+
+fn type_name_of<T>(_: &T) -> &'static str {
+    std::any::type_name::<T>()
+}
+
+// "hexa_lite::adapters::capped::CappedPaymentGateway<hexa_lite::...::MockPaymentGateway>"
+// -> "CappedPaymentGateway<MockPaymentGateway>"
+fn short_type_name(full: &str) -> String {
+    let mut short = String::new();
+    let mut segment = String::new();
+    let mut chars = full.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == ':' && chars.peek() == Some(&':') {
+            chars.next();
+            segment.clear();
+        } else if c.is_alphanumeric() || c == '_' {
+            segment.push(c);
+        } else {
+            short.push_str(&segment);
+            segment.clear();
+            short.push(c);
+        }
+    }
+    short + &segment
+}
+
+// The senders EnvConfig can choose from, behind one type so the service
+// keeps a single, static Sender parameter.
+pub enum ConfiguredSender {
+    Console(ConsoleSender),
+    SendGrid(SendGridSender),
+    Webhook(WebhookSender),
+    Plugin(Box<dyn Sender + Sync>),
+}
+
+impl Sender for ConfiguredSender {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        match self {
+            ConfiguredSender::Console(sender) => sender.send(confirmation),
+            ConfiguredSender::SendGrid(sender) => sender.send(confirmation),
+            ConfiguredSender::Webhook(sender) => sender.send(confirmation),
+            ConfiguredSender::Plugin(sender) => sender.send(confirmation),
+        }
+    }
+
+    fn send_notice(&self, notice: &Notice) -> Result<(), OrderError> {
+        match self {
+            ConfiguredSender::Console(sender) => sender.send_notice(notice),
+            ConfiguredSender::SendGrid(sender) => sender.send_notice(notice),
+            ConfiguredSender::Webhook(sender) => sender.send_notice(notice),
+            ConfiguredSender::Plugin(sender) => sender.send_notice(notice),
+        }
+    }
+}
+
+impl Capability for ConfiguredSender {}
+
+// The same for the repository: the built-in one, or a factory's.
+pub enum ConfiguredRepository {
+    InMemory(InMemoryOrderRepository),
+    Plugin(Box<dyn OrderWriter + Sync>),
+}
+
+impl ConfiguredRepository {
+    fn chosen(&self) -> &(dyn OrderWriter + Sync) {
+        match self {
+            ConfiguredRepository::InMemory(repository) => repository,
+            ConfiguredRepository::Plugin(repository) => repository.as_ref(),
+        }
+    }
+}
+
+// Every method, defaults included: a factory's adapter may override any.
+impl OrderReader for ConfiguredRepository {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.chosen().find(id)
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.chosen().list()
+    }
+
+    fn exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        self.chosen().exists(id)
+    }
+
+    fn total_of(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        self.chosen().total_of(id)
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        self.chosen().for_each(visit)
+    }
+
+    fn for_each_cancellable(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        self.chosen().for_each_cancellable(cancel, visit)
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        self.chosen().list_deleted()
+    }
+
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.chosen().find_by_key(key)
+    }
+
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        self.chosen().find_by_reference(reference)
+    }
+}
+
+impl OrderWriter for ConfiguredRepository {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.chosen().save(order)
+    }
+
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        self.chosen().update(order)
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.chosen().soft_delete(id)
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.chosen().restore(id)
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.chosen().purge(id)
+    }
+}
+
+impl Capability for ConfiguredRepository {}
+
+// And for the payment gateway.
+pub enum ConfiguredPayment {
+    Mock(MockPaymentGateway),
+    Plugin(Box<dyn PaymentGateway + Sync>),
+}
+
+impl ConfiguredPayment {
+    fn chosen(&self) -> &(dyn PaymentGateway + Sync) {
+        match self {
+            ConfiguredPayment::Mock(payment) => payment,
+            ConfiguredPayment::Plugin(payment) => payment.as_ref(),
+        }
+    }
+}
+
+impl PaymentGateway for ConfiguredPayment {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.chosen().charge(amount)
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.chosen().charge_for(order_id, amount)
+    }
+
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.chosen().charge_order(order_id, amount)
+    }
+
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.chosen().refund_for(order_id, amount)
+    }
+}
+
+// A factory's gateway is only known as a PaymentGateway: what it charged
+// is its own business.
+impl ChargeLog for ConfiguredPayment {
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        match self {
+            ConfiguredPayment::Mock(payment) => payment.charges(),
+            ConfiguredPayment::Plugin(_) => Err(OrderError::custom(
+                "a payment gateway from a factory keeps no charge log here",
+            )),
+        }
+    }
+}
+
+impl Capability for ConfiguredPayment {}
+
+// Everything the service borrows, owned in one place.
+pub struct Adapters {
+    pub repository: ConfiguredRepository,
+    pub payment: ConfiguredPayment,
+    pub sender: ConfiguredSender,
+    pub event_log: Option<FileEventLog>,
+    // What the fields above are, for printing.
+    pub wiring: Wiring,
+}
+
+impl Adapters {
+    // The adapters here with something to do before the process exits,
+    // registered; the caller adds its own before running it. Of the
+    // configured ones only the event log holds anything on to the end.
+    pub fn shutdown_coordinator(&mut self) -> ShutdownCoordinator<'_> {
+        let coordinator = ShutdownCoordinator::new();
+        match &mut self.event_log {
+            Some(log) => coordinator.register("event log", ShutdownPriority::CloseLogs, log),
+            None => coordinator,
+        }
+    }
+}
+
+// Builds every adapter before giving up, so all those refusing their
+// settings are reported together.
+pub fn build_adapters(config: &EnvConfig) -> Result<Adapters, ConfigError> {
+    const ONCE: &str = "each port is bound once";
+    let mut errors = Vec::new();
+    let mut wiring = Wiring::new();
+    let mut registry = Registry::new();
+    let repository = match &config.repository {
+        None => {
+            wiring
+                .bind::<dyn OrderReader>("InMemoryOrderRepository")
+                .expect(ONCE)
+                .bind::<dyn OrderWriter>("InMemoryOrderRepository")
+                .expect(ONCE);
+            Some(ConfiguredRepository::InMemory(
+                InMemoryOrderRepository::new(),
+            ))
+        }
+        Some(plugin) => {
+            wiring
+                .bind_with::<dyn OrderReader>(&plugin.name, FROM_FACTORY)
+                .expect(ONCE)
+                .bind_with::<dyn OrderWriter>(&plugin.name, FROM_FACTORY)
+                .expect(ONCE);
+            made(
+                plugin,
+                |factory| factory.make_repository(plugin),
+                &mut errors,
+            )
+            .map(ConfiguredRepository::Plugin)
+        }
+    };
+    let payment = match &config.payment {
+        None => {
+            wiring
+                .bind::<dyn PaymentGateway>("MockPaymentGateway")
+                .expect(ONCE);
+            Some(ConfiguredPayment::Mock(MockPaymentGateway::new()))
+        }
+        Some(plugin) => {
+            wiring
+                .bind_with::<dyn PaymentGateway>(&plugin.name, FROM_FACTORY)
+                .expect(ONCE);
+            made(plugin, |factory| factory.make_payment(plugin), &mut errors)
+                .map(ConfiguredPayment::Plugin)
+        }
+    };
+    let sender = match &config.sender {
+        SenderConfig::Console => {
+            wiring.bind::<dyn Sender>("ConsoleSender").expect(ONCE);
+            Some(ConfiguredSender::Console(ConsoleSender::new()))
+        }
+        SenderConfig::SendGrid { api_key } => {
+            wiring.bind::<dyn Sender>("SendGridSender").expect(ONCE);
+            built(SendGridSender::new(api_key.clone()), &mut errors).map(ConfiguredSender::SendGrid)
+        }
+        SenderConfig::Webhook { url, signing_key } => {
+            let signed = if signing_key.is_some() {
+                "signed"
+            } else {
+                "unsigned"
+            };
+            wiring
+                .bind_with::<dyn Sender>("WebhookSender", format!("{url}, {signed}"))
+                .expect(ONCE);
+            built(WebhookSender::new(url.clone()), &mut errors).map(|sender| {
+                ConfiguredSender::Webhook(match signing_key {
+                    Some(key) => sender.with_signing_key(key.clone()),
+                    None => sender,
+                })
+            })
+        }
+        SenderConfig::Plugin(plugin) => {
+            wiring
+                .bind_with::<dyn Sender>(&plugin.name, FROM_FACTORY)
+                .expect(ONCE);
+            made(plugin, |factory| factory.make_sender(plugin), &mut errors)
+                .map(ConfiguredSender::Plugin)
+        }
+    };
+    if let Some(path) = &config.event_log {
+        wiring
+            .bind_with::<dyn EventPublisher>("FileEventLog", path.display().to_string())
+            .expect(ONCE);
+    }
+    if let Some(repository) = repository {
+        registry.put::<dyn OrderWriter>(repository).expect(ONCE);
+    }
+    if let Some(payment) = payment {
+        registry.put::<dyn PaymentGateway>(payment).expect(ONCE);
+    }
+    if let Some(sender) = sender {
+        registry.put::<dyn Sender>(sender).expect(ONCE);
+    }
+    if let Some(log) = config
+        .event_log
+        .as_ref()
+        .and_then(|path| built(FileEventLog::new(path), &mut errors))
+    {
+        registry.put::<dyn EventPublisher>(log).expect(ONCE);
+    }
+
+    if !errors.is_empty() {
+        return Err(one_or_many(errors));
+    }
+    Ok(Adapters::from_registry(registry, wiring)
+        .expect("every required port was put in the registry above"))
+}
+
+impl Adapters {
+    // Takes every adapter the service needs out of `registry`, and the
+    // event log when there is one. Incomplete names every missing port.
+    // The repository and gateway may be put in as they are, or already
+    // configured.
+    pub fn from_registry(mut registry: Registry, wiring: Wiring) -> Result<Self, RegistryError> {
+        registry.verify_complete::<RequiredPorts>()?;
+        let event_log = match registry.get::<dyn EventPublisher, FileEventLog>() {
+            Some(_) => Some(registry.take::<dyn EventPublisher, FileEventLog>()?),
+            None => None,
+        };
+        let repository = match registry.get::<dyn OrderWriter, InMemoryOrderRepository>() {
+            Some(_) => ConfiguredRepository::InMemory(registry.take::<dyn OrderWriter, _>()?),
+            None => registry.take::<dyn OrderWriter, _>()?,
+        };
+        let payment = match registry.get::<dyn PaymentGateway, MockPaymentGateway>() {
+            Some(_) => ConfiguredPayment::Mock(registry.take::<dyn PaymentGateway, _>()?),
+            None => registry.take::<dyn PaymentGateway, _>()?,
+        };
+        Ok(Adapters {
+            repository,
+            payment,
+            sender: registry.take::<dyn Sender, _>()?,
+            event_log,
+            wiring,
+        })
+    }
+}
+
+// What the wiring says of an adapter a factory made.
+const FROM_FACTORY: &str = "from a registered factory";
+
+// The factory may have been chosen by hand, in an EnvConfig built without
+// from_lookup: its name is checked again here.
+fn made<A>(
+    plugin: &AdapterConfig,
+    make: impl FnOnce(&dyn AdapterFactory) -> Result<A, ConfigError>,
+    errors: &mut Vec<ConfigError>,
+) -> Option<A> {
+    let Some(factory) = plugins::factory(&plugin.name) else {
+        errors.push(ConfigError::Invalid {
+            var: plugin.var,
+            reason: format!("no factory registered as {:?}", plugin.name),
+        });
+        return None;
+    };
+    make(factory.as_ref()).map_err(|e| errors.push(e)).ok()
+}
+
+fn built<A>(adapter: Result<A, adapters::ConfigError>, errors: &mut Vec<ConfigError>) -> Option<A> {
+    adapter
+        .map_err(|e| errors.push(ConfigError::Adapter(e)))
+        .ok()
+}
+
+pub fn build_service(
+    adapters: &Adapters,
+) -> OrderService<'_, ConfiguredRepository, ConfiguredPayment, ConfiguredSender> {
+    let service = OrderService::new(&adapters.repository, &adapters.payment, &adapters.sender);
+    match &adapters.event_log {
+        Some(log) => service.with_event_publisher(log),
+        None => service,
+    }
+}
+
+// The service build_service makes, and the queries on its repository, as
+// one object for a GUI's callbacks to share.
+pub fn build_facade(adapters: &Adapters) -> Arc<dyn OrderFacade + Send + Sync + '_> {
+    let queries = OrderBrowser::new(&adapters.repository);
+    DefaultFacade::new(build_service(adapters), queries).shared()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn port_names_are_short() {
+        let mut wiring = Wiring::new();
+        wiring
+            .bind::<dyn PaymentGateway>("MockPaymentGateway")
+            .unwrap()
+            .bind::<dyn EventPublisher>("FileEventLog")
+            .unwrap();
+        let ports: Vec<&str> = wiring.describe().iter().map(|b| b.port).collect();
+        assert_eq!(ports, vec!["PaymentGateway", "EventPublisher"]);
+    }
+
+    #[test]
+    fn wiring_prints_as_a_table() {
+        let mut wiring = Wiring::new();
+        wiring
+            .bind::<dyn OrderReader>("InMemoryOrderRepository")
+            .unwrap()
+            .bind::<dyn OrderWriter>("InMemoryOrderRepository")
+            .unwrap()
+            .bind_with::<dyn Sender>("WebhookSender", "https://example.test/hook, signed")
+            .unwrap();
+
+        assert_eq!(
+            wiring.to_string(),
+            "port         adapter                  config\n\
+             OrderReader  InMemoryOrderRepository\n\
+             OrderWriter  InMemoryOrderRepository\n\
+             Sender       WebhookSender            https://example.test/hook, signed"
+        );
+    }
+
+    #[test]
+    fn a_port_takes_one_adapter() {
+        let mut registry = Registry::new();
+        registry
+            .put::<dyn PaymentGateway>(MockPaymentGateway::new())
+            .unwrap();
+
+        let error = registry
+            .put::<dyn PaymentGateway>(MockPaymentGateway::new())
+            .err()
+            .unwrap();
+        assert_eq!(
+            error,
+            RegistryError::Duplicate {
+                port: "PaymentGateway",
+                bound: "MockPaymentGateway".to_string(),
+                rejected: "MockPaymentGateway".to_string(),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "PaymentGateway already has an adapter, MockPaymentGateway; MockPaymentGateway was put in twice"
+        );
+        assert_eq!(
+            registry.describe(),
+            vec![("PaymentGateway", "MockPaymentGateway")]
+        );
+    }
+
+    #[test]
+    fn every_missing_port_is_reported_at_once() {
+        let mut registry = Registry::new();
+        registry
+            .put::<dyn PaymentGateway>(MockPaymentGateway::new())
+            .unwrap();
+
+        let error = registry.verify_complete::<RequiredPorts>().unwrap_err();
+        assert_eq!(
+            error,
+            RegistryError::Incomplete {
+                missing: vec!["OrderWriter", "Sender"],
+            }
+        );
+        assert_eq!(error.to_string(), "no adapter for OrderWriter, Sender");
+
+        registry
+            .put::<dyn OrderWriter>(InMemoryOrderRepository::new())
+            .unwrap()
+            .put::<dyn Sender>(ConsoleSender::new())
+            .unwrap();
+        assert_eq!(registry.verify_complete::<RequiredPorts>(), Ok(()));
+    }
+
+    #[test]
+    fn adapters_come_out_as_their_own_type() {
+        let mut registry = Registry::new();
+        registry.put::<dyn Sender>(ConsoleSender::new()).unwrap();
+
+        assert!(registry.get::<dyn Sender, ConsoleSender>().is_some());
+        assert!(registry.get::<dyn Sender, ConfiguredSender>().is_none());
+        assert_eq!(
+            registry.take::<dyn Sender, ConfiguredSender>().err(),
+            Some(RegistryError::WrongAdapter {
+                port: "Sender",
+                bound: "ConsoleSender".to_string(),
+                requested: "ConfiguredSender".to_string(),
+            })
+        );
+        assert!(registry.take::<dyn Sender, ConsoleSender>().is_ok());
+        // Taken, the port is free again.
+        assert_eq!(
+            registry.verify_complete::<RequiredPorts>().unwrap_err(),
+            RegistryError::Incomplete {
+                missing: vec!["OrderWriter", "PaymentGateway", "Sender"],
+            }
+        );
+    }
+
+    #[test]
+    fn adapter_names_lose_their_paths() {
+        assert_eq!(
+            short_type_name(
+                "hexa_lite::adapters::capped::CappedPaymentGateway<hexa_lite::adapters::in_memory::MockPaymentGateway, &hexa_lite::ports::SystemClock>"
+            ),
+            "CappedPaymentGateway<MockPaymentGateway, &SystemClock>"
+        );
+    }
+
+    #[test]
+    fn errors_are_listed_one_per_line() {
+        let error = ConfigError::Multiple(vec![
+            ConfigError::Missing {
+                var: SENDGRID_KEY_VAR,
+            },
+            ConfigError::Invalid {
+                var: EVENT_LOG_VAR,
+                reason: "empty path".to_string(),
+            },
+        ]);
+        assert_eq!(
+            error.to_string(),
+            "2 configuration errors:\n  - HEXLITE_SENDGRID_KEY is not set\n  - HEXLITE_EVENT_LOG is invalid: empty path"
+        );
+    }
+}
@@ -0,0 +1,131 @@
+// Adapters from other crates, chosen by name like the built-in ones.
+// A crate with its own adapters registers a factory once, before the
+// configuration is read:
+//
+//     register_factory("sqlite", Box::new(SqliteFactory));
+//
+// then HEXLITE_REPOSITORY=sqlite builds its repository. A name given in
+// the environment is looked up here first, so a factory registered as
+// "console" or "mock" replaces that built-in; an unset variable is always
+// the built-in default. A factory only makes the ports it knows of: the
+// others are refused with a reason, like any other bad setting.
+//
+// Settings for a factory come from the variable next to the name, as
+// comma-separated key=value pairs:
+//
+//     HEXLITE_REPOSITORY_OPTIONS=path=/var/lib/shop.db,timeout=5
+use super::ConfigError;
+use crate::ports::{OrderWriter, PaymentGateway, Sender};
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+
+// What a factory is handed: the name it was chosen by, under which
+// variable, and that variable's options.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdapterConfig {
+    pub var: &'static str,
+    pub name: String,
+    pub options: Vec<(String, String)>,
+}
+
+impl AdapterConfig {
+    pub fn option(&self, key: &str) -> Option<&str> {
+        self.options
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    // For a factory refusing what it was given.
+    pub fn invalid(&self, reason: impl Into<String>) -> ConfigError {
+        ConfigError::Invalid {
+            var: self.var,
+            reason: format!("{}: {}", self.name, reason.into()),
+        }
+    }
+}
+
+pub trait AdapterFactory: Send + Sync {
+    fn make_repository(
+        &self,
+        config: &AdapterConfig,
+    ) -> Result<Box<dyn OrderWriter + Sync>, ConfigError> {
+        Err(config.invalid("makes no OrderWriter"))
+    }
+
+    fn make_payment(
+        &self,
+        config: &AdapterConfig,
+    ) -> Result<Box<dyn PaymentGateway + Sync>, ConfigError> {
+        Err(config.invalid("makes no PaymentGateway"))
+    }
+
+    fn make_sender(&self, config: &AdapterConfig) -> Result<Box<dyn Sender + Sync>, ConfigError> {
+        Err(config.invalid("makes no Sender"))
+    }
+}
+
+type Factories = Mutex<Vec<(String, Arc<dyn AdapterFactory>)>>;
+
+static FACTORIES: OnceLock<Factories> = OnceLock::new();
+
+fn factories() -> &'static Factories {
+    FACTORIES.get_or_init(Factories::default)
+}
+
+// For the whole process. A second factory under the same name replaces
+// the first.
+pub fn register_factory(name: impl Into<String>, factory: Box<dyn AdapterFactory>) {
+    let name = name.into();
+    let mut factories = factories().lock().unwrap_or_else(PoisonError::into_inner);
+    factories.retain(|(registered, _)| *registered != name);
+    factories.push((name, Arc::from(factory)));
+}
+
+pub fn is_registered(name: &str) -> bool {
+    factory(name).is_some()
+}
+
+// Cloned out, so a factory runs without the registry locked.
+pub(super) fn factory(name: &str) -> Option<Arc<dyn AdapterFactory>> {
+    factories()
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .iter()
+        .find(|(registered, _)| registered == name)
+        .map(|(_, factory)| Arc::clone(factory))
+}
+
+// "path=/tmp/a.db, timeout=5" -> [("path", "/tmp/a.db"), ("timeout", "5")]
+pub(super) fn parse_options(text: &str) -> Result<Vec<(String, String)>, String> {
+    text.split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| match pair.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                Ok((key.trim().to_string(), value.trim().to_string()))
+            }
+            _ => Err(format!("expected key=value, got {pair:?}")),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn options_are_comma_separated_pairs() {
+        assert_eq!(
+            parse_options("path=/tmp/a.db, timeout=5,"),
+            Ok(vec![
+                ("path".to_string(), "/tmp/a.db".to_string()),
+                ("timeout".to_string(), "5".to_string()),
+            ])
+        );
+        assert_eq!(parse_options(""), Ok(Vec::new()));
+        assert_eq!(
+            parse_options("path"),
+            Err(r#"expected key=value, got "path""#.to_string())
+        );
+    }
+}
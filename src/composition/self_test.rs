@@ -0,0 +1,270 @@
+// "Does this deployment work, end to end, before customers find out?"
+//
+// A health check asks each adapter whether it is up; the self-test uses
+// them. A synthetic order, with an id from OrderId::SELF_TEST_IDS so that
+// any adapter can tell it apart, is charged, saved and read back through
+// the adapters the composition root built, then cleaned up: refunded and
+// soft-deleted, in that order, whatever failed on the way.
+//
+// The confirmation a customer would get is never sent: the customer is not
+// real. The configured sender is exercised with a Notice to the operator
+// instead, when there is one. What cannot be taken back (a
+// notice delivered, an event appended to a log) is reported as such:
+// Irreversible says what was left behind. The event log is only written
+// to when asked for.
+use super::Adapters;
+use crate::domain::{LineItem, Money, Notice, Order, OrderError, OrderEvent, OrderId, OrderStatus};
+use crate::ports::{EventPublisher, OrderReader, OrderWriter, PaymentGateway, Sender};
+use std::fmt;
+use std::time::Duration;
+
+pub struct SelfTestOptions<'a> {
+    pub order_id: OrderId,
+    pub amount: Money,
+    // Who gets the Notice through the configured sender. None: the sender
+    // is not exercised.
+    pub operator: Option<String>,
+    // Whether the event log, if any, gets a synthetic OrderPlaced.
+    pub publish_events: bool,
+    // Anything that only goes forward, as for Timed.
+    pub elapsed: &'a dyn Fn() -> Duration,
+}
+
+impl<'a> SelfTestOptions<'a> {
+    // The first self-test id, a one-dollar order, no operator, no event.
+    pub fn new(elapsed: &'a dyn Fn() -> Duration) -> Self {
+        Self {
+            order_id: OrderId(*OrderId::SELF_TEST_IDS.start()),
+            amount: Money(100),
+            operator: None,
+            publish_events: false,
+            elapsed,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum CheckOutcome {
+    Passed,
+    Failed(OrderError),
+    // Not tried, and why.
+    Skipped(&'static str),
+    // Done, and staying done: what is left behind.
+    Irreversible(&'static str),
+}
+
+// One call to one adapter.
+#[derive(Debug, Clone)]
+pub struct PortCheck {
+    pub port: &'static str,
+    pub adapter: String,
+    pub step: &'static str,
+    pub outcome: CheckOutcome,
+    pub took: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct SelfTestReport {
+    pub order_id: OrderId,
+    // In the order they ran: the order's own steps, then the cleanup.
+    pub checks: Vec<PortCheck>,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        !self
+            .checks
+            .iter()
+            .any(|check| matches!(check.outcome, CheckOutcome::Failed(_)))
+    }
+
+    pub fn left_behind(&self) -> impl Iterator<Item = &PortCheck> {
+        self.checks
+            .iter()
+            .filter(|check| matches!(check.outcome, CheckOutcome::Irreversible(_)))
+    }
+}
+
+// One line per check:
+//
+//     PaymentGateway  MockPaymentGateway  charge_for  passed  0ms
+impl fmt::Display for SelfTestReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "self-test, order {}", self.order_id)?;
+        for check in &self.checks {
+            let outcome = match &check.outcome {
+                CheckOutcome::Passed => "passed".to_string(),
+                CheckOutcome::Failed(e) => format!("FAILED: {e}"),
+                CheckOutcome::Skipped(why) => format!("skipped: {why}"),
+                CheckOutcome::Irreversible(what) => format!("left behind: {what}"),
+            };
+            writeln!(
+                f,
+                "  {:<15} {:<24} {:<11} {outcome}  {}ms",
+                check.port,
+                check.adapter,
+                check.step,
+                check.took.as_millis()
+            )?;
+        }
+        Ok(())
+    }
+}
+
+struct Run<'a, 'o> {
+    adapters: &'a Adapters,
+    options: &'a SelfTestOptions<'o>,
+    checks: Vec<PortCheck>,
+}
+
+impl Run<'_, '_> {
+    fn adapter(&self, port: &str) -> String {
+        self.adapters
+            .wiring
+            .describe()
+            .into_iter()
+            .find(|binding| binding.port == port)
+            .map_or_else(|| "-".to_string(), |binding| binding.adapter)
+    }
+
+    // Times `call` and records what came of it; true when it passed.
+    fn check(
+        &mut self,
+        port: &'static str,
+        step: &'static str,
+        call: impl FnOnce() -> Result<(), OrderError>,
+    ) -> bool {
+        let started = (self.options.elapsed)();
+        let result = call();
+        let took = (self.options.elapsed)().saturating_sub(started);
+        let passed = result.is_ok();
+        let outcome = result.map_or_else(CheckOutcome::Failed, |()| CheckOutcome::Passed);
+        self.record_timed(port, step, outcome, took);
+        passed
+    }
+
+    fn record(&mut self, port: &'static str, step: &'static str, outcome: CheckOutcome) {
+        self.record_timed(port, step, outcome, Duration::ZERO);
+    }
+
+    fn record_timed(
+        &mut self,
+        port: &'static str,
+        step: &'static str,
+        outcome: CheckOutcome,
+        took: Duration,
+    ) {
+        let adapter = self.adapter(port);
+        self.checks.push(PortCheck {
+            port,
+            adapter,
+            step,
+            outcome,
+            took,
+        });
+    }
+}
+
+pub fn self_test(adapters: &Adapters, options: &SelfTestOptions<'_>) -> SelfTestReport {
+    const EARLIER: &str = "an earlier step failed";
+    let id = options.order_id;
+    let mut run = Run {
+        adapters,
+        options,
+        checks: Vec::new(),
+    };
+    let mut order = match Order::new(id, vec![LineItem::new("Self-test", options.amount)]) {
+        Ok(order) => order,
+        Err(e) => {
+            run.record("Domain", "validate", CheckOutcome::Failed(e));
+            return SelfTestReport {
+                order_id: id,
+                checks: run.checks,
+            };
+        }
+    };
+    let (repository, payment) = (&adapters.repository, &adapters.payment);
+
+    let charged = run.check("PaymentGateway", "charge_for", || {
+        payment.charge_for(id, order.total)
+    });
+    let saved = charged
+        && run.check("OrderWriter", "save", || {
+            order.transition_to(OrderStatus::Paid)?;
+            repository.save(&order)
+        });
+    if saved {
+        run.check("OrderReader", "find", || match repository.find(id)? {
+            Some(stored) if stored.status == OrderStatus::Paid => Ok(()),
+            _ => Err(OrderError::NotFound { id }),
+        });
+    } else {
+        run.record("OrderReader", "find", CheckOutcome::Skipped(EARLIER));
+    }
+
+    run.record(
+        "Sender",
+        "send",
+        CheckOutcome::Skipped("the confirmation of a synthetic order goes to no customer"),
+    );
+    match &options.operator {
+        None => run.record(
+            "Sender",
+            "send_notice",
+            CheckOutcome::Skipped("no operator"),
+        ),
+        Some(operator) => {
+            let notice = Notice {
+                to: operator.clone(),
+                subject: format!("Self-test of order {id}"),
+                lines: vec!["The adapters of this deployment are being tested.".to_string()],
+            };
+            if run.check("Sender", "send_notice", || {
+                adapters.sender.send_notice(&notice)
+            }) {
+                run.record(
+                    "Sender",
+                    "cleanup",
+                    CheckOutcome::Irreversible("the notice stays with the operator"),
+                );
+            }
+        }
+    }
+
+    match &adapters.event_log {
+        None => {}
+        Some(_) if !options.publish_events => run.record(
+            "EventPublisher",
+            "publish",
+            CheckOutcome::Skipped("the event log keeps whatever it is given"),
+        ),
+        Some(log) => {
+            let placed = OrderEvent::OrderPlaced {
+                order_id: id,
+                item_count: order.items.len(),
+                total: order.total,
+            };
+            if run.check("EventPublisher", "publish", || log.publish(&placed)) {
+                run.record(
+                    "EventPublisher",
+                    "cleanup",
+                    CheckOutcome::Irreversible("the event stays in the log"),
+                );
+            }
+        }
+    }
+
+    // Cleanup: whatever was done is undone, even after a failure.
+    if saved {
+        run.check("OrderWriter", "soft_delete", || repository.soft_delete(id));
+    }
+    if charged {
+        run.check("PaymentGateway", "refund_for", || {
+            payment.refund_for(id, order.total)
+        });
+    }
+    SelfTestReport {
+        order_id: id,
+        checks: run.checks,
+    }
+}
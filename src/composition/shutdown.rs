@@ -0,0 +1,108 @@
+// Shutting the adapters down, in the one order that loses nothing: the
+// senders first, since sending what they held back may still write to a
+// repository or a log; then the repositories; the logs last, so they
+// record everything before them.
+//
+// Each adapter is registered with its priority class; within a class they
+// run in the order they were registered. One that fails does not stop the
+// others: run goes on to the end, and its report says which failed.
+use crate::ports::{Shutdown, ShutdownError};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ShutdownPriority {
+    FlushSenders,
+    CloseRepositories,
+    CloseLogs,
+}
+
+struct Registered<'a> {
+    name: &'static str,
+    priority: ShutdownPriority,
+    adapter: &'a mut dyn Shutdown,
+}
+
+#[derive(Default)]
+pub struct ShutdownCoordinator<'a> {
+    registered: Vec<Registered<'a>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ShutdownStep {
+    pub name: &'static str,
+    pub priority: ShutdownPriority,
+    pub outcome: Result<(), ShutdownError>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ShutdownReport {
+    // In the order they ran.
+    pub steps: Vec<ShutdownStep>,
+}
+
+impl<'a> ShutdownCoordinator<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(
+        mut self,
+        name: &'static str,
+        priority: ShutdownPriority,
+        adapter: &'a mut dyn Shutdown,
+    ) -> Self {
+        self.registered.push(Registered {
+            name,
+            priority,
+            adapter,
+        });
+        self
+    }
+
+    pub fn run(mut self) -> ShutdownReport {
+        // Stable: registration order is kept within a class.
+        self.registered
+            .sort_by_key(|registered| registered.priority);
+        let steps = self
+            .registered
+            .into_iter()
+            .map(|registered| ShutdownStep {
+                name: registered.name,
+                priority: registered.priority,
+                outcome: registered.adapter.shutdown(),
+            })
+            .collect();
+        ShutdownReport { steps }
+    }
+}
+
+impl ShutdownReport {
+    pub fn is_clean(&self) -> bool {
+        self.steps.iter().all(|step| step.outcome.is_ok())
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &ShutdownStep> {
+        self.steps.iter().filter(|step| step.outcome.is_err())
+    }
+}
+
+// One line per adapter:
+//
+//     CloseLogs          event log  FAILED: events.log: permission denied
+impl fmt::Display for ShutdownReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "shutdown")?;
+        if self.steps.is_empty() {
+            writeln!(f, "  nothing registered")?;
+        }
+        for step in &self.steps {
+            let outcome = match &step.outcome {
+                Ok(()) => "done".to_string(),
+                Err(e) => format!("FAILED: {e}"),
+            };
+            let priority = format!("{:?}", step.priority);
+            writeln!(f, "  {priority:<18} {:<10} {outcome}", step.name)?;
+        }
+        Ok(())
+    }
+}
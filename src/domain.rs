@@ -0,0 +1,778 @@
+// =============================================================================
+// DOMAIN Layer - Pure Business Concepts
+// =============================================================================
+// The domain is the heart of the application.
+// It contains business vocabulary and business rules.
+// No traits. No infrastructure. No frameworks.
+use std::fmt;
+use std::ops::RangeInclusive;
+
+mod allocation;
+mod amendment;
+mod approval;
+mod confirmation;
+mod consistency;
+mod criteria;
+mod currency;
+mod decline;
+mod delta;
+mod discount;
+mod draft;
+mod error_codes;
+mod events;
+mod merge;
+mod notes;
+mod order_key;
+mod rate;
+mod redact;
+mod screening;
+mod shipping;
+mod transitions;
+mod warehouse;
+
+pub use amendment::{
+    FieldError, FieldErrors, MAX_ADDRESS_LINE_CHARS, MAX_GIFT_NOTE_CHARS, MAX_POSTAL_CODE_CHARS,
+};
+pub use approval::{Approval, ApproverId};
+pub use confirmation::{Notice, OrderConfirmation};
+pub use criteria::OrderCriteria;
+pub use currency::{
+    ConvertedLine, ConvertedOrder, Currency, CurrencyTotals, ExchangeRate, ForeignLineItem, Price,
+};
+pub use decline::DeclineReason;
+pub use delta::OrderDelta;
+pub use discount::Discount;
+pub use draft::{ConfirmPolicy, ConfirmedOrder, OrderDraft, StoredOrder};
+pub use error_codes::{CatalogueEntry, catalogue};
+pub use events::{InventoryEvent, OrderEvent};
+pub use notes::{
+    Attachment, BlobId, MAX_ATTACHMENT_BYTES, MAX_NOTE_BYTES, MAX_NOTES, OrderNote,
+    sanitize_filename,
+};
+pub use order_key::{OrderKey, ParseKeyError, Uuid128};
+pub use rate::{BasisPoints, Percent, RateOutOfRange, RoundingStrategy};
+pub use redact::Redact;
+pub use screening::{Contact, Customer, ReviewDecision};
+pub use shipping::{Address, Shipment, TrackingId};
+pub use transitions::{ORDER_TRANSITIONS, TransitionTable};
+pub use warehouse::{WarehouseId, WarehouseStock};
+
+// Strongly-typed identifiers make illegal states harder to represent.
+// These are "Value Objects": they represent business concepts.
+// OrderId isn't just a u32, it's a meaningful business identifier.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OrderId(pub u32);
+
+// What customers see on receipts and emails: #000042
+impl OrderId {
+    // Not to be handed out to customers: the synthetic orders of
+    // composition::self_test, which any adapter may tell apart.
+    pub const SELF_TEST_IDS: RangeInclusive<u32> = 4_000_000_000..=u32::MAX;
+
+    pub fn is_self_test(self) -> bool {
+        Self::SELF_TEST_IDS.contains(&self.0)
+    }
+}
+
+impl fmt::Display for OrderId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!("#{:06}", self.0))
+    }
+}
+
+// What a customer quotes on the phone or reads on a receipt: 2024-000042,
+// ORD-7F3K9Q. Handed out at placement by a ReferenceGenerator, when there
+// is one; OrderId stays the key the application works with.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct OrderReference(pub String);
+
+impl fmt::Display for OrderReference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.0)
+    }
+}
+
+// A checkout saga: reserve stock, place the order, confirm the stock.
+// Chosen by whoever starts it, so that starting it again is recognised.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SagaId(pub u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Money(pub u32); // stored in minor units: cents, for dollars
+
+// $129.99
+// Money alone does not know its currency, so it is shown in dollars; an
+// amount in another one is shown with Money::in_currency.
+// Uses pad() so width and alignment flags ({:>10}) work in receipts.
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!("${}.{:02}", self.0 / 100, self.0 % 100))
+    }
+}
+
+// A point in time, in seconds since the Unix epoch.
+// The domain never reads the system clock: timestamps come in through the
+// Clock port, which is what makes time-dependent rules testable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(pub u64);
+
+impl Timestamp {
+    pub const fn from_secs(secs: u64) -> Self {
+        Timestamp(secs)
+    }
+
+    pub fn plus_secs(self, secs: u64) -> Self {
+        Timestamp(self.0.saturating_add(secs))
+    }
+
+    pub fn minus_secs(self, secs: u64) -> Self {
+        Timestamp(self.0.saturating_sub(secs))
+    }
+
+    // A timestamp from outside (a client, an import) against our own clock:
+    // no more than `max_past` seconds before `now`, no more than
+    // `max_future` after. Exactly on a bound is still fine.
+    pub fn validate_against(
+        self,
+        now: Timestamp,
+        max_past: u64,
+        max_future: u64,
+    ) -> Result<(), OrderError> {
+        let delta = i128::from(self.0) - i128::from(now.0);
+        if delta > i128::from(max_future) || -delta > i128::from(max_past) {
+            return Err(OrderError::ClockSkew {
+                delta_secs: delta.clamp(i64::MIN.into(), i64::MAX.into()) as i64,
+            });
+        }
+        Ok(())
+    }
+}
+
+// Where an order is in its life.
+// Placed: validated, not charged yet. Paid: charged and stored.
+// Shipped: every item has left the warehouse.
+// PendingApproval: too large to be charged without a second look.
+// UnderReview: doubtful to the fraud screen, not charged until resolved.
+// Cancelled: rejected during approval or review, or declined at
+// settlement, never charged; or cancelled after payment, and refunded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderStatus {
+    Placed,
+    PendingApproval,
+    UnderReview,
+    // Accepted while the payment provider was unreachable, not charged yet.
+    PaymentPending,
+    Paid,
+    Shipped,
+    Cancelled,
+}
+
+impl fmt::Display for OrderStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            OrderStatus::Placed => "Placed",
+            OrderStatus::PendingApproval => "PendingApproval",
+            OrderStatus::UnderReview => "UnderReview",
+            OrderStatus::PaymentPending => "PaymentPending",
+            OrderStatus::Paid => "Paid",
+            OrderStatus::Shipped => "Shipped",
+            OrderStatus::Cancelled => "Cancelled",
+        };
+        f.pad(name)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LineItem {
+    pub name: String,
+    pub price: Money,
+}
+
+impl LineItem {
+    // LineItem::new("Pen", Money(150)): the struct literal, without the
+    // to_string().
+    pub fn new(name: impl Into<String>, price: Money) -> Self {
+        Self {
+            name: name.into(),
+            price,
+        }
+    }
+}
+
+// The Order entity is pure business data + invariants.
+// Notice: no database stuff, no HTTP, no external dependencies.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Order {
+    pub id: OrderId,
+    pub items: Vec<LineItem>,
+    pub total: Money,
+    // What the total and every price are in. Usd unless placed with
+    // place_order_in.
+    pub currency: Currency,
+    pub status: OrderStatus,
+    pub shipments: Vec<Shipment>,
+    // None when the order was created without a clock at hand.
+    pub placed_at: Option<Timestamp>,
+    // The id to show outside, when orders are given one: see OrderKey.
+    pub uuid: Option<Uuid128>,
+    // The one for people, when orders are given one.
+    pub reference: Option<OrderReference>,
+    // Bumped by every OrderWriter::update, for optimistic concurrency:
+    // an update based on a stale read is refused instead of overwriting.
+    pub version: u32,
+    // Only set on orders that had to wait for an approval.
+    pub approval: Option<Approval>,
+    // Where it ships, and what the parcel says: set by amending the order.
+    pub shipping_address: Option<Address>,
+    pub gift_note: Option<String>,
+    // Oldest first: see notes.rs.
+    pub notes: Vec<OrderNote>,
+    pub attachments: Vec<Attachment>,
+}
+
+// Domain-level errors describe business failures,
+// not technical ones (no SQL errors, no HTTP codes).
+#[derive(Debug, Clone)]
+pub enum OrderError {
+    InvalidOrder,
+    // The card was declined, and the provider did not say why: retrying
+    // will not help.
+    PaymentFailed,
+    // The payment provider could not be reached: the same charge may go
+    // through later.
+    PaymentUnavailable,
+    // The charge would take the last 24 hours' spend over the cap.
+    DailyCapExceeded {
+        remaining: Money,
+    },
+    StorageFailed,
+    NotificationFailed,
+    NotFound {
+        id: OrderId,
+    },
+    InvalidTransition {
+        from: OrderStatus,
+        to: OrderStatus,
+    },
+    InvalidItemIndex {
+        index: usize,
+    },
+    AlreadyShipped {
+        index: usize,
+    },
+    ShippingFailed,
+    // The order was already confirmed, the draft operation no longer applies.
+    NotADraft {
+        id: OrderId,
+    },
+    NoExchangeRate {
+        from: Currency,
+        to: Currency,
+    },
+    // The stored order changed since it was read.
+    VersionConflict {
+        id: OrderId,
+        expected: u32,
+        found: u32,
+    },
+    AlreadyApproved {
+        id: OrderId,
+        by: ApproverId,
+    },
+    // Approving or rejecting an order that is not waiting for it.
+    NotPendingApproval {
+        id: OrderId,
+        status: OrderStatus,
+    },
+    // Only cancelled orders may be deleted.
+    CannotDelete {
+        id: OrderId,
+        status: OrderStatus,
+    },
+    // Restoring an order that is not deleted.
+    NotDeleted {
+        id: OrderId,
+    },
+    // A timestamp given from outside is too far from our clock: positive
+    // when ahead of it, negative when behind.
+    ClockSkew {
+        delta_secs: i64,
+    },
+    // Splitting an amount by weights that are missing or all zero.
+    InvalidWeights,
+    // Not enough of `item` left to reserve.
+    OutOfStock {
+        item: String,
+    },
+    // Resuming a saga its log has never heard of.
+    UnknownSaga {
+        id: SagaId,
+    },
+    // Nothing to charge, and no discount to say why.
+    ZeroTotalNotAllowed,
+    // The fraud screen refused the order, for `reason`.
+    FraudSuspected {
+        reason: String,
+    },
+    // Resolving the review of an order that is not waiting for one.
+    NotUnderReview {
+        id: OrderId,
+        status: OrderStatus,
+    },
+    // A circuit breaker kept the call from a failing service: try again in
+    // `retry_after_secs`, 0 when a probe is already on its way.
+    CircuitOpen {
+        retry_after_secs: u64,
+    },
+    // Every one of the `limit` calls a ConcurrencyLimited port allows at a
+    // time is under way, and it was told not to wait.
+    TooManyConcurrentCalls {
+        limit: usize,
+    },
+    // The use case ran out of its time Budget before this call: `spent_ms`
+    // had gone since it started.
+    BudgetExhausted {
+        spent_ms: u64,
+    },
+    // Someone cancelled the CancelToken of a long-running use case. It
+    // stopped between two items, `processed` of them fully handled.
+    Cancelled {
+        processed: usize,
+    },
+    // Amending an order that has gone too far: shipped, cancelled, or
+    // still waiting for a decision.
+    NotAmendable {
+        status: OrderStatus,
+    },
+    // Fields of a request that are not right, each with what is wrong.
+    InvalidFields {
+        errors: FieldErrors,
+    },
+    // The retention policy picked an order that still has something
+    // unsettled, and it is kept for now, for `reason`.
+    RetentionBlocked {
+        reason: String,
+    },
+    // What a repository handed back is not an order this crate could have
+    // saved, for `reason`: see Order::validate_consistency.
+    CorruptData {
+        order_id: OrderId,
+        reason: String,
+    },
+    // A single amount was asked for, in `expected`, but there is revenue in
+    // `others` too: see CurrencyTotals::require_single.
+    MixedCurrencies {
+        expected: Currency,
+        others: Vec<Currency>,
+    },
+    // A note of `bytes`, more than the `limit` one may have.
+    NoteTooLong {
+        bytes: usize,
+        limit: usize,
+    },
+    // The order already has the `limit` notes it may have.
+    TooManyNotes {
+        limit: usize,
+    },
+    AttachmentTooLarge {
+        bytes: usize,
+        limit: usize,
+    },
+    // The card was declined, for `reason`. Only TryAgainLater may go
+    // through if the charge is made again.
+    PaymentDeclined {
+        reason: DeclineReason,
+    },
+    // What an adapter of another crate has to say that no variant above
+    // does. See OrderError::custom.
+    Custom {
+        message: String,
+    },
+}
+
+impl OrderError {
+    // For adapters outside this crate: their own failure, in their words.
+    pub fn custom(message: impl Into<String>) -> Self {
+        OrderError::Custom {
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for OrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{self:?}")
+    }
+}
+
+// Business rule:
+// An order must contain at least one item, and its total must fit in Money.
+// It must come to something, too: only Order::discounted may take it down
+// to zero.
+impl Order {
+    pub fn new(id: OrderId, items: Vec<LineItem>) -> Result<Self, OrderError> {
+        if items.is_empty() {
+            return Err(OrderError::InvalidOrder);
+        }
+
+        let total = items
+            .iter()
+            .try_fold(0u32, |sum, item| sum.checked_add(item.price.0))
+            .map(Money)
+            .ok_or(OrderError::InvalidOrder)?;
+        if total == Money(0) {
+            return Err(OrderError::ZeroTotalNotAllowed);
+        }
+
+        Ok(Order {
+            id,
+            items,
+            total,
+            currency: Currency::default(),
+            status: OrderStatus::Placed,
+            shipments: Vec::new(),
+            placed_at: None,
+            uuid: None,
+            reference: None,
+            version: 0,
+            approval: None,
+            shipping_address: None,
+            gift_note: None,
+            notes: Vec::new(),
+            attachments: Vec::new(),
+        })
+    }
+
+    // Its UUID when it has one, its number otherwise.
+    pub fn key(&self) -> OrderKey {
+        match self.uuid {
+            Some(uuid) => OrderKey::Random(uuid),
+            None => OrderKey::Sequential(self.id),
+        }
+    }
+
+    // Business rule: only a placed order can be marked as paid.
+    pub fn mark_paid(&mut self) -> Result<(), OrderError> {
+        self.transition_to(OrderStatus::Paid)
+    }
+
+    // Business rule: an order only moves forward, one step at a time, as
+    // ORDER_TRANSITIONS lays out (see transitions.rs).
+    pub fn transition_to(&mut self, to: OrderStatus) -> Result<(), OrderError> {
+        if !self.status.can_become(to) {
+            return Err(OrderError::InvalidTransition {
+                from: self.status,
+                to,
+            });
+        }
+        self.status = to;
+        Ok(())
+    }
+
+    // The receipt body: the reference when there is one, one line per item
+    // then the total.
+    // Names are truncated so prices stay aligned whatever the catalogue contains.
+    pub fn receipt_lines(&self) -> Vec<String> {
+        receipt_lines(self.reference.as_ref(), &self.items, self.total)
+    }
+}
+
+const RECEIPT_NAME_WIDTH: usize = 40;
+const RECEIPT_PRICE_WIDTH: usize = 10;
+
+fn receipt_lines(
+    reference: Option<&OrderReference>,
+    items: &[LineItem],
+    total: Money,
+) -> Vec<String> {
+    let mut lines: Vec<String> = reference
+        .map(|reference| format!("Reference {}", printable(&reference.0)))
+        .into_iter()
+        .collect();
+    lines.extend(
+        items
+            .iter()
+            .map(|item| receipt_line(&item.name, item.price)),
+    );
+    lines.push("-".repeat(RECEIPT_NAME_WIDTH + 1 + RECEIPT_PRICE_WIDTH));
+    lines.push(receipt_line("Total", total));
+    lines
+}
+
+// A name is printed on one line, and whatever direction its script runs
+// in, the price stays where it is: control characters and bidi controls in
+// it are shown as '\u{FFFD}', and a name with right-to-left letters is set
+// between FSI and PDI.
+fn receipt_line(name: &str, price: Money) -> String {
+    let shown = truncate_with_ellipsis(&printable(name), RECEIPT_NAME_WIDTH);
+    let padding = RECEIPT_NAME_WIDTH.saturating_sub(shown.chars().count());
+    let shown = if shown.chars().any(is_right_to_left) {
+        format!("\u{2068}{shown}\u{2069}")
+    } else {
+        shown
+    };
+    format!(
+        "{shown}{} {:>price_width$}",
+        " ".repeat(padding),
+        price,
+        price_width = RECEIPT_PRICE_WIDTH
+    )
+}
+
+fn printable(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            '\u{200E}'
+            | '\u{200F}'
+            | '\u{061C}'
+            | '\u{202A}'..='\u{202E}'
+            | '\u{2066}'..='\u{2069}' => '\u{FFFD}',
+            c if c.is_control() => '\u{FFFD}',
+            c => c,
+        })
+        .collect()
+}
+
+// Hebrew, Arabic, Syriac, Thaana, N'Ko and their presentation forms.
+fn is_right_to_left(c: char) -> bool {
+    matches!(c, '\u{0590}'..='\u{08FF}' | '\u{FB1D}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFC}')
+}
+
+// Counts chars, not bytes, so multi-byte names are never cut in half.
+fn truncate_with_ellipsis(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+    let mut truncated: String = text.chars().take(max_chars - 1).collect();
+    truncated.push('…');
+    truncated
+}
+
+// A human-readable receipt:
+//
+//     Order #000001
+//     Rust Book                                    $49.99
+//     ...
+impl fmt::Display for Order {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Order {}", self.id)?;
+        for line in self.receipt_lines() {
+            write!(f, "\n{line}")?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_err_variant;
+    use crate::testing::assert_order;
+
+    #[test]
+    fn overflowing_total_is_invalid() {
+        let items = vec![
+            LineItem {
+                name: "Gold bar".to_string(),
+                price: Money(u32::MAX),
+            },
+            LineItem {
+                name: "Gift wrap".to_string(),
+                price: Money(1),
+            },
+        ];
+        assert_err_variant!(Order::new(OrderId(1), items), OrderError::InvalidOrder);
+    }
+
+    #[test]
+    fn new_order_sums_item_prices() {
+        let items = vec![
+            LineItem {
+                name: "Rust Book".to_string(),
+                price: Money(4999),
+            },
+            LineItem {
+                name: "Keyboard".to_string(),
+                price: Money(12999),
+            },
+        ];
+
+        let order = Order::new(OrderId(1), items).unwrap();
+
+        assert_order(&order)
+            .has_id(1)
+            .has_total_cents(17998)
+            .has_item_count(2)
+            .has_item_named("Keyboard");
+    }
+
+    #[test]
+    fn status_only_moves_one_step_forward() {
+        let mut order = Order::new(
+            OrderId(1),
+            vec![LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            }],
+        )
+        .unwrap();
+
+        assert_err_variant!(
+            order.transition_to(OrderStatus::Shipped),
+            OrderError::InvalidTransition {
+                from: OrderStatus::Placed,
+                to: OrderStatus::Shipped
+            }
+        );
+        order.transition_to(OrderStatus::Paid).unwrap();
+        order.transition_to(OrderStatus::Shipped).unwrap();
+        assert_err_variant!(
+            order.transition_to(OrderStatus::Paid),
+            OrderError::InvalidTransition { .. }
+        );
+    }
+
+    #[test]
+    fn payment_pending_order_is_settled_or_cancelled() {
+        let mouse = || {
+            Order::new(
+                OrderId(1),
+                vec![LineItem {
+                    name: "Mouse".to_string(),
+                    price: Money(2500),
+                }],
+            )
+            .unwrap()
+        };
+
+        let mut order = mouse();
+        order.transition_to(OrderStatus::PaymentPending).unwrap();
+        assert_err_variant!(
+            order.transition_to(OrderStatus::Shipped),
+            OrderError::InvalidTransition {
+                from: OrderStatus::PaymentPending,
+                ..
+            }
+        );
+        order.transition_to(OrderStatus::Paid).unwrap();
+
+        let mut order = mouse();
+        order.transition_to(OrderStatus::PaymentPending).unwrap();
+        order.transition_to(OrderStatus::Cancelled).unwrap();
+    }
+
+    #[test]
+    fn open_cart_is_cancelled_for_good() {
+        let mut order = Order::new(OrderId(1), vec![LineItem::new("Mouse", Money(2500))]).unwrap();
+
+        order.transition_to(OrderStatus::Cancelled).unwrap();
+        assert_err_variant!(
+            order.transition_to(OrderStatus::Paid),
+            OrderError::InvalidTransition {
+                from: OrderStatus::Cancelled,
+                ..
+            }
+        );
+    }
+
+    #[test]
+    fn paid_order_is_cancelled_until_it_ships() {
+        let mut order = Order::new(OrderId(1), vec![LineItem::new("Mouse", Money(2500))]).unwrap();
+        order.mark_paid().unwrap();
+        let mut shipped = order.clone();
+
+        order.transition_to(OrderStatus::Cancelled).unwrap();
+        shipped.transition_to(OrderStatus::Shipped).unwrap();
+        assert_err_variant!(
+            shipped.transition_to(OrderStatus::Cancelled),
+            OrderError::InvalidTransition {
+                from: OrderStatus::Shipped,
+                ..
+            }
+        );
+    }
+
+    #[test]
+    fn order_id_and_money_display() {
+        assert_eq!(OrderId(42).to_string(), "#000042");
+        assert_eq!(Money(12999).to_string(), "$129.99");
+        assert_eq!(Money(5).to_string(), "$0.05");
+        assert_eq!(format!("[{:>8}]", Money(4999)), "[  $49.99]");
+    }
+
+    #[test]
+    fn receipt_text_is_pinned() {
+        let order = Order::new(
+            OrderId(42),
+            vec![
+                LineItem {
+                    name: "Rust Book".to_string(),
+                    price: Money(4999),
+                },
+                LineItem {
+                    name: "Ergonomic split mechanical keyboard with hot-swap switches".to_string(),
+                    price: Money(12999),
+                },
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(
+            order.to_string(),
+            "Order #000042\n\
+             Rust Book                                    $49.99\n\
+             Ergonomic split mechanical keyboard wit…    $129.99\n\
+             ---------------------------------------------------\n\
+             Total                                       $179.98"
+        );
+    }
+
+    #[test]
+    fn truncation_counts_chars() {
+        let name = "é".repeat(41);
+
+        let truncated = truncate_with_ellipsis(&name, 40);
+
+        assert_eq!(truncated.chars().count(), 40);
+        assert!(truncated.ends_with('…'));
+    }
+
+    #[test]
+    fn a_name_cannot_break_its_receipt_line() {
+        assert_eq!(
+            receipt_line("ruler\nsecond\u{202E}line", Money(199)),
+            format!(
+                "ruler\u{FFFD}second\u{FFFD}line{} {:>10}",
+                " ".repeat(23),
+                "$1.99"
+            )
+        );
+        assert_eq!(
+            receipt_line("ספר", Money(199)),
+            format!("\u{2068}ספר\u{2069}{} {:>10}", " ".repeat(37), "$1.99")
+        );
+    }
+
+    #[test]
+    fn empty_order_is_invalid() {
+        assert_err_variant!(Order::new(OrderId(1), vec![]), OrderError::InvalidOrder);
+    }
+
+    #[test]
+    fn timestamps_exactly_on_the_tolerance_are_valid() {
+        let now = Timestamp(1_700_000_000);
+        let check = |at: Timestamp| at.validate_against(now, 3600, 300);
+
+        assert!(check(now).is_ok());
+        assert!(check(now.minus_secs(3600)).is_ok());
+        assert!(check(now.plus_secs(300)).is_ok());
+        assert_err_variant!(
+            check(now.minus_secs(3601)),
+            OrderError::ClockSkew { delta_secs: -3601 }
+        );
+        assert_err_variant!(
+            check(now.plus_secs(301)),
+            OrderError::ClockSkew { delta_secs: 301 }
+        );
+    }
+}
@@ -0,0 +1,140 @@
+// Splitting an amount without losing a cent.
+// $10.03 in three is not three times $3.343...: it is $3.35, $3.34 and
+// $3.34. Every share is rounded down, then the cents left over go one by
+// one to the shares that lost the most in the rounding (largest remainder).
+// The parts always add up to the amount, exactly.
+use super::{Money, OrderError};
+
+impl Money {
+    // `parts` equal shares, the first ones a cent larger when the amount
+    // does not divide evenly. Splitting into zero parts is a bug: it panics.
+    pub fn allocate(&self, parts: usize) -> Vec<Money> {
+        assert!(parts > 0, "cannot allocate {self} into zero parts");
+        let parts = parts as u64;
+        let total = u64::from(self.0);
+        let (share, extra) = (total / parts, total % parts);
+        // Every share is at most the amount, so it fits back into Money.
+        (0..parts)
+            .map(|i| Money((share + u64::from(i < extra)) as u32))
+            .collect()
+    }
+
+    // Shares in proportion to `weights`: [1, 3] gives a quarter and three
+    // quarters. A zero weight gets nothing. Ties for a leftover cent go to
+    // the earlier share. No weights, or only zeros, is InvalidWeights.
+    pub fn allocate_weighted(&self, weights: &[u32]) -> Result<Vec<Money>, OrderError> {
+        let sum: u64 = weights.iter().copied().map(u64::from).sum();
+        if sum == 0 {
+            return Err(OrderError::InvalidWeights);
+        }
+        let total = u64::from(self.0);
+        // total * weight fits in u64: both are below 2^32.
+        let mut shares: Vec<u64> = Vec::with_capacity(weights.len());
+        let mut remainders: Vec<(u64, usize)> = Vec::with_capacity(weights.len());
+        for (i, &weight) in weights.iter().enumerate() {
+            let exact = total * u64::from(weight);
+            shares.push(exact / sum);
+            remainders.push((exact % sum, i));
+        }
+        let left_over = total - shares.iter().sum::<u64>();
+        // Largest remainder first, then earliest share.
+        remainders.sort_by(|a, b| b.0.cmp(&a.0).then(a.1.cmp(&b.1)));
+        for &(_, i) in remainders.iter().take(left_over as usize) {
+            shares[i] += 1;
+        }
+        Ok(shares
+            .into_iter()
+            .map(|share| Money(share as u32))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift64*: the same inputs on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 >> 12;
+            self.0 ^= self.0 << 25;
+            self.0 ^= self.0 >> 27;
+            self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+        }
+    }
+
+    fn sum(parts: &[Money]) -> u64 {
+        parts.iter().map(|part| u64::from(part.0)).sum()
+    }
+
+    #[test]
+    fn ten_dollars_three_cents_in_three() {
+        assert_eq!(
+            Money(1003).allocate(3),
+            vec![Money(335), Money(334), Money(334)]
+        );
+        assert_eq!(Money(2).allocate(3), vec![Money(1), Money(1), Money(0)]);
+    }
+
+    #[test]
+    fn weighted_shares_follow_the_weights() {
+        assert_eq!(
+            Money(1000).allocate_weighted(&[1, 3]).unwrap(),
+            vec![Money(250), Money(750)]
+        );
+        // 100 * 1/3 = 33.33, 100 * 2/3 = 66.67: the cent goes to the second.
+        assert_eq!(
+            Money(100).allocate_weighted(&[1, 2]).unwrap(),
+            vec![Money(33), Money(67)]
+        );
+        assert_eq!(
+            Money(100).allocate_weighted(&[0, 1, 0]).unwrap(),
+            vec![Money(0), Money(100), Money(0)]
+        );
+    }
+
+    #[test]
+    fn weights_must_not_all_be_zero() {
+        assert!(matches!(
+            Money(100).allocate_weighted(&[]),
+            Err(OrderError::InvalidWeights)
+        ));
+        assert!(matches!(
+            Money(100).allocate_weighted(&[0, 0]),
+            Err(OrderError::InvalidWeights)
+        ));
+    }
+
+    #[test]
+    fn no_cent_is_lost_or_invented() {
+        let mut rng = Rng(0x00a1_10c8);
+        for _ in 0..2_000 {
+            let amount = Money(rng.next() as u32);
+            let parts = 1 + (rng.next() % 50) as usize;
+            let equal = amount.allocate(parts);
+            assert_eq!(equal.len(), parts);
+            assert_eq!(sum(&equal), u64::from(amount.0), "{amount:?} in {parts}");
+            // Equal shares differ by a cent at most.
+            let (min, max) = (
+                equal.iter().min_by_key(|m| m.0),
+                equal.iter().max_by_key(|m| m.0),
+            );
+            assert!(max.unwrap().0 - min.unwrap().0 <= 1);
+
+            let weights: Vec<u32> = (0..parts)
+                .map(|_| rng.next() as u32 >> (rng.next() % 32))
+                .collect();
+            if weights.iter().all(|&w| w == 0) {
+                continue;
+            }
+            let weighted = amount.allocate_weighted(&weights).unwrap();
+            assert_eq!(
+                sum(&weighted),
+                u64::from(amount.0),
+                "{amount:?} by {weights:?}"
+            );
+        }
+    }
+}
@@ -0,0 +1,303 @@
+// Amendments: what a customer may still change about a placed order, as
+// long as it has not shipped: where it goes, and the note that goes with it.
+//
+// Business rules:
+// - only a Placed or Paid order is amendable
+// - a new address has every part filled in, and a two-letter country code
+// - a gift note is at most MAX_GIFT_NOTE_CHARS characters, printable but
+//   for line breaks; an empty one removes the note
+//
+// Every field is checked before anything changes, and every problem is
+// reported, each under the name of its field: a customer fixes them all in
+// one go rather than one per attempt.
+use super::{Address, Order, OrderError, OrderStatus};
+use std::fmt;
+
+pub const MAX_GIFT_NOTE_CHARS: usize = 200;
+pub const MAX_ADDRESS_LINE_CHARS: usize = 100;
+pub const MAX_POSTAL_CODE_CHARS: usize = 10;
+
+const AMENDABLE: [OrderStatus; 2] = [OrderStatus::Placed, OrderStatus::Paid];
+
+// What is wrong with one field, in words meant for whoever filled it in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    // As the request names it: "gift_note", "new_address.city"...
+    pub field: String,
+    pub message: String,
+}
+
+// Every FieldError of a request, in the order the fields were checked.
+// Shown as "gift_note: too long (312/200); new_address.country: ...".
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FieldErrors(Vec<FieldError>);
+
+impl FieldErrors {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, field: impl Into<String>, message: impl Into<String>) {
+        self.0.push(FieldError {
+            field: field.into(),
+            message: message.into(),
+        });
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &FieldError> {
+        self.0.iter()
+    }
+
+    // The message for `field`, if it has one.
+    pub fn get(&self, field: &str) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|error| error.field == field)
+            .map(|error| error.message.as_str())
+    }
+
+    // Ok when there is nothing to report.
+    pub fn into_result(self) -> Result<(), OrderError> {
+        match self.is_empty() {
+            true => Ok(()),
+            false => Err(OrderError::InvalidFields { errors: self }),
+        }
+    }
+}
+
+impl fmt::Display for FieldErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (at, error) in self.0.iter().enumerate() {
+            if at > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{}: {}", error.field, error.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl Address {
+    // The problems of each part, named `prefix`.street, `prefix`.city...
+    pub fn check(&self, prefix: &str, errors: &mut FieldErrors) {
+        let parts = [
+            ("street", &self.street, MAX_ADDRESS_LINE_CHARS),
+            ("city", &self.city, MAX_ADDRESS_LINE_CHARS),
+            ("postal_code", &self.postal_code, MAX_POSTAL_CODE_CHARS),
+        ];
+        for (name, value, max) in parts {
+            let field = format!("{prefix}.{name}");
+            let chars = value.trim().chars().count();
+            if chars == 0 {
+                errors.push(field, "required");
+            } else if chars > max {
+                errors.push(field, format!("too long ({chars}/{max})"));
+            } else if value.chars().any(char::is_control) {
+                errors.push(field, "must be a single line of text");
+            }
+        }
+        if !self
+            .postal_code
+            .trim()
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == ' ' || c == '-')
+        {
+            errors.push(
+                format!("{prefix}.postal_code"),
+                "only letters, digits, spaces and dashes",
+            );
+        }
+        let country = self.country.as_bytes();
+        if country.len() != 2 || !country.iter().all(u8::is_ascii_uppercase) {
+            errors.push(
+                format!("{prefix}.country"),
+                "must be a two-letter country code, like US",
+            );
+        }
+    }
+}
+
+impl Order {
+    pub fn is_amendable(&self) -> bool {
+        AMENDABLE.contains(&self.status)
+    }
+
+    // Changes nothing unless everything given is valid. Given nothing, it
+    // has nothing to change, and says so.
+    pub fn amend(
+        &mut self,
+        new_address: Option<&Address>,
+        gift_note: Option<&str>,
+    ) -> Result<(), OrderError> {
+        if !self.is_amendable() {
+            return Err(OrderError::NotAmendable {
+                status: self.status,
+            });
+        }
+        let mut errors = FieldErrors::new();
+        if new_address.is_none() && gift_note.is_none() {
+            errors.push("amendment", "nothing to change");
+        }
+        if let Some(address) = new_address {
+            address.check("new_address", &mut errors);
+        }
+        if let Some(note) = gift_note {
+            check_gift_note(note, &mut errors);
+        }
+        errors.into_result()?;
+
+        if let Some(address) = new_address {
+            self.shipping_address = Some(address.clone());
+        }
+        if let Some(note) = gift_note {
+            let note = note.trim();
+            self.gift_note = (!note.is_empty()).then(|| note.to_string());
+        }
+        Ok(())
+    }
+}
+
+fn check_gift_note(note: &str, errors: &mut FieldErrors) {
+    let chars = note.trim().chars().count();
+    if chars > MAX_GIFT_NOTE_CHARS {
+        errors.push(
+            "gift_note",
+            format!("too long ({chars}/{MAX_GIFT_NOTE_CHARS})"),
+        );
+    } else if note.chars().any(|c| c.is_control() && c != '\n') {
+        errors.push("gift_note", "only printable characters and line breaks");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, Money, OrderId};
+
+    fn order(status: OrderStatus) -> Order {
+        let mut order = Order::new(OrderId(1), vec![LineItem::new("Pen", Money(150))]).unwrap();
+        order.status = status;
+        order
+    }
+
+    fn address() -> Address {
+        Address {
+            street: "1 Main St".to_string(),
+            city: "Springfield".to_string(),
+            postal_code: "12345".to_string(),
+            country: "US".to_string(),
+        }
+    }
+
+    fn field_errors(result: Result<(), OrderError>) -> FieldErrors {
+        match result {
+            Err(OrderError::InvalidFields { errors }) => errors,
+            other => panic!("expected InvalidFields, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn placed_and_paid_orders_take_a_new_address_and_a_note() {
+        for status in AMENDABLE {
+            let mut order = order(status);
+
+            order
+                .amend(Some(&address()), Some("  Happy birthday!\n"))
+                .unwrap();
+
+            assert_eq!(order.shipping_address, Some(address()));
+            assert_eq!(order.gift_note.as_deref(), Some("Happy birthday!"));
+        }
+    }
+
+    #[test]
+    fn an_empty_note_removes_the_note_and_keeps_the_address() {
+        let mut order = order(OrderStatus::Paid);
+        order.amend(Some(&address()), Some("Hi")).unwrap();
+
+        order.amend(None, Some("")).unwrap();
+
+        assert_eq!(order.gift_note, None);
+        assert_eq!(order.shipping_address, Some(address()));
+    }
+
+    #[test]
+    fn any_other_status_is_not_amendable() {
+        for status in [
+            OrderStatus::PendingApproval,
+            OrderStatus::UnderReview,
+            OrderStatus::PaymentPending,
+            OrderStatus::Shipped,
+            OrderStatus::Cancelled,
+        ] {
+            assert!(matches!(
+                order(status).amend(None, Some("Hi")),
+                Err(OrderError::NotAmendable { status: found }) if found == status
+            ));
+        }
+    }
+
+    #[test]
+    fn a_note_over_the_limit_says_by_how_much() {
+        let mut order = order(OrderStatus::Placed);
+
+        let errors = field_errors(order.amend(None, Some(&"x".repeat(312))));
+
+        assert_eq!(errors.to_string(), "gift_note: too long (312/200)");
+        assert!(order.amend(None, Some(&"é".repeat(200))).is_ok());
+        let errors = field_errors(order.amend(None, Some("bell\u{7}")));
+        assert!(errors.get("gift_note").is_some());
+    }
+
+    #[test]
+    fn every_bad_part_of_an_address_is_reported_and_nothing_changes() {
+        let mut order = order(OrderStatus::Placed);
+        let bad = Address {
+            street: " ".to_string(),
+            city: "c".repeat(101),
+            postal_code: "12#45".to_string(),
+            country: "usa".to_string(),
+        };
+
+        let errors = field_errors(order.amend(Some(&bad), Some(&"x".repeat(201))));
+
+        assert_eq!(errors.len(), 5);
+        assert_eq!(errors.get("new_address.street"), Some("required"));
+        assert_eq!(errors.get("new_address.city"), Some("too long (101/100)"));
+        assert_eq!(
+            errors.get("new_address.postal_code"),
+            Some("only letters, digits, spaces and dashes")
+        );
+        assert!(errors.get("new_address.country").is_some());
+        assert_eq!(errors.get("gift_note"), Some("too long (201/200)"));
+        assert_eq!(order.shipping_address, None);
+        assert_eq!(order.gift_note, None);
+    }
+
+    #[test]
+    fn an_amendment_of_nothing_is_refused() {
+        let errors = field_errors(order(OrderStatus::Placed).amend(None, None));
+        assert_eq!(errors.to_string(), "amendment: nothing to change");
+    }
+
+    #[test]
+    fn field_errors_read_one_after_the_other() {
+        let mut errors = FieldErrors::new();
+        assert!(errors.clone().into_result().is_ok());
+        errors.push("gift_note", "too long (312/200)");
+        errors.push("new_address.country", "required");
+
+        assert_eq!(
+            errors.to_string(),
+            "gift_note: too long (312/200); new_address.country: required"
+        );
+    }
+}
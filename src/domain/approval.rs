@@ -0,0 +1,22 @@
+// Approvals.
+// Above a certain amount an order is not charged on the spot: it waits,
+// PendingApproval, until someone approves it (it is charged then) or
+// rejects it (it is cancelled). The decision stays on the order, which is
+// how a second approval is told apart from approving an order that never
+// needed one.
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ApproverId(pub String);
+
+impl fmt::Display for ApproverId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&self.0)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Approval {
+    Approved { by: ApproverId },
+    Rejected { reason: String },
+}
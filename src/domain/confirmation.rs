@@ -0,0 +1,75 @@
+// What the customer is told about an order.
+// Senders get this rather than the Order entity: they need the id, the lines
+// and the total, not the status, the shipments or whatever Order grows next.
+// Order can change without touching a single notification adapter.
+//
+// What goes over the wire is versioned: see adapters::confirmation. A field
+// added here is a new version there.
+//
+// A Notice is the other kind of message: for the people running the shop,
+// about the shop, such as stock running out.
+use super::{Currency, LineItem, Money, Order, OrderId, OrderReference};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderConfirmation {
+    pub order_id: OrderId,
+    pub items: Vec<LineItem>,
+    pub total: Money,
+    // Included in the total. Nothing the shop sells is taxed yet: zero.
+    pub tax: Money,
+    pub currency: Currency,
+    // Where a consumer may write to the customer, when the order says.
+    pub customer_email: Option<String>,
+    // What the customer quotes back, when the order was given one.
+    pub reference: Option<OrderReference>,
+}
+
+impl OrderConfirmation {
+    // Same layout as Order::receipt_lines.
+    pub fn receipt_lines(&self) -> Vec<String> {
+        super::receipt_lines(self.reference.as_ref(), &self.items, self.total)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notice {
+    pub to: String,
+    pub subject: String,
+    pub lines: Vec<String>,
+}
+
+impl Order {
+    pub fn confirmation(&self) -> OrderConfirmation {
+        OrderConfirmation {
+            order_id: self.id,
+            items: self.items.clone(),
+            total: self.total,
+            tax: Money(0),
+            currency: Currency::default(),
+            customer_email: None,
+            reference: self.reference.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn confirmation_renders_the_same_receipt_as_the_order() {
+        let order = Order::new(
+            OrderId(8),
+            vec![LineItem {
+                name: "Rust Book".to_string(),
+                price: Money(4999),
+            }],
+        )
+        .unwrap();
+
+        let confirmation = order.confirmation();
+        assert_eq!(confirmation.order_id, OrderId(8));
+        assert_eq!(confirmation.total, Money(4999));
+        assert_eq!(confirmation.receipt_lines(), order.receipt_lines());
+    }
+}
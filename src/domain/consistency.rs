@@ -0,0 +1,135 @@
+// What every Order coming back from storage must still be: the order this
+// crate built and saved. An adapter may hand back anything, a total that
+// no longer matches its items, a shipment naming an item that is not
+// there; the application layer checks it here, on the way in, and a
+// broken record is CorruptData rather than a panic further down.
+//
+// Business rules:
+// - at least one item, and items adding up to what Money holds
+// - a total no higher than its items: a discount only ever lowers it
+// - shipments naming items the order has, each item at most once
+// - a version that can still be bumped
+use super::{Order, OrderError};
+
+impl Order {
+    pub fn validate_consistency(&self) -> Result<(), OrderError> {
+        let corrupt = |reason: String| {
+            Err(OrderError::CorruptData {
+                order_id: self.id,
+                reason,
+            })
+        };
+        if self.items.is_empty() {
+            return corrupt("no items".to_string());
+        }
+        let Some(sum) = self
+            .items
+            .iter()
+            .try_fold(0u32, |sum, item| sum.checked_add(item.price.0))
+        else {
+            return corrupt("items add up to more than Money holds".to_string());
+        };
+        if self.total.0 > sum {
+            return corrupt(format!(
+                "total of {} cents above its items' {sum}",
+                self.total.0
+            ));
+        }
+        let mut shipped = vec![false; self.items.len()];
+        for (number, shipment) in self.shipments.iter().enumerate() {
+            for &index in &shipment.item_indices {
+                match shipped.get_mut(index) {
+                    None => {
+                        return corrupt(format!(
+                            "shipment {number} names item {index} of {}",
+                            self.items.len()
+                        ));
+                    }
+                    Some(true) => return corrupt(format!("item {index} shipped twice")),
+                    Some(seen) => *seen = true,
+                }
+            }
+        }
+        if self.version == u32::MAX {
+            return corrupt("version can go no higher".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, Money, OrderId, Shipment, Timestamp, TrackingId};
+
+    fn order() -> Order {
+        Order::new(
+            OrderId(1),
+            vec![
+                LineItem::new("Pen", Money(150)),
+                LineItem::new("Ink", Money(50)),
+            ],
+        )
+        .unwrap()
+    }
+
+    fn shipment(item_indices: Vec<usize>) -> Shipment {
+        Shipment {
+            tracking: TrackingId("T1".to_string()),
+            item_indices,
+            shipped_at: Timestamp(0),
+        }
+    }
+
+    fn reason(order: &Order) -> String {
+        match order.validate_consistency() {
+            Err(OrderError::CorruptData { order_id, reason }) => {
+                assert_eq!(order_id, order.id);
+                reason
+            }
+            other => panic!("expected CorruptData, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn an_order_as_built_is_consistent() {
+        let mut order = order();
+        assert!(order.validate_consistency().is_ok());
+
+        // A discount took the total down, and one item has shipped.
+        order.total = Money(0);
+        order.shipments.push(shipment(vec![1]));
+        assert!(order.validate_consistency().is_ok());
+    }
+
+    #[test]
+    fn totals_and_items_must_agree() {
+        let mut order = order();
+        order.total = Money(201);
+        assert_eq!(reason(&order), "total of 201 cents above its items' 200");
+
+        order.items[0].price = Money(u32::MAX);
+        assert_eq!(reason(&order), "items add up to more than Money holds");
+
+        order.items.clear();
+        order.total = Money(0);
+        assert_eq!(reason(&order), "no items");
+    }
+
+    #[test]
+    fn shipments_name_each_item_of_the_order_once() {
+        let mut order = order();
+        order.shipments = vec![shipment(vec![0]), shipment(vec![2])];
+        assert_eq!(reason(&order), "shipment 1 names item 2 of 2");
+
+        order.shipments = vec![shipment(vec![0]), shipment(vec![1, 0])];
+        assert_eq!(reason(&order), "item 0 shipped twice");
+    }
+
+    #[test]
+    fn the_last_version_cannot_be_bumped() {
+        let mut order = order();
+        order.version = u32::MAX;
+        assert_eq!(reason(&order), "version can go no higher");
+    }
+}
@@ -0,0 +1,79 @@
+// Which orders a query or a bulk operation is about.
+// Every condition left to None matches everything; the ones set must all hold.
+use super::{Order, OrderStatus, Timestamp};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct OrderCriteria {
+    pub status: Option<OrderStatus>,
+    // Strictly before. An order without placed_at never matches.
+    pub placed_before: Option<Timestamp>,
+}
+
+impl OrderCriteria {
+    pub fn any() -> Self {
+        Self::default()
+    }
+
+    pub fn with_status(mut self, status: OrderStatus) -> Self {
+        self.status = Some(status);
+        self
+    }
+
+    pub fn placed_before(mut self, at: Timestamp) -> Self {
+        self.placed_before = Some(at);
+        self
+    }
+
+    pub fn matches(&self, order: &Order) -> bool {
+        let status_ok = self.status.is_none_or(|status| order.status == status);
+        let age_ok = self
+            .placed_before
+            .is_none_or(|limit| order.placed_at.is_some_and(|at| at < limit));
+        status_ok && age_ok
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, Money, OrderId};
+
+    fn order_placed_at(at: Option<u64>) -> Order {
+        let mut order = Order::new(
+            OrderId(1),
+            vec![LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            }],
+        )
+        .unwrap();
+        order.placed_at = at.map(Timestamp);
+        order
+    }
+
+    #[test]
+    fn empty_criteria_match_everything() {
+        assert!(OrderCriteria::any().matches(&order_placed_at(None)));
+    }
+
+    #[test]
+    fn placed_before_is_strict_and_needs_a_timestamp() {
+        let criteria = OrderCriteria::any().placed_before(Timestamp(100));
+
+        assert!(criteria.matches(&order_placed_at(Some(99))));
+        assert!(!criteria.matches(&order_placed_at(Some(100))));
+        assert!(!criteria.matches(&order_placed_at(None)));
+    }
+
+    #[test]
+    fn every_condition_must_hold() {
+        let criteria = OrderCriteria::any()
+            .with_status(OrderStatus::Paid)
+            .placed_before(Timestamp(100));
+        let mut order = order_placed_at(Some(50));
+
+        assert!(!criteria.matches(&order));
+        order.mark_paid().unwrap();
+        assert!(criteria.matches(&order));
+    }
+}
@@ -0,0 +1,438 @@
+// Currencies.
+// Money stays a plain amount of minor units in the order's currency: every
+// total, charge and receipt is in one currency. Only the cart may mix them,
+// and it is converted line by line before it becomes an order.
+//
+// A minor unit is not always a cent: a yen has no subdivision, and a
+// Kuwaiti dinar has a thousand fils. Money(500) is $5.00, but ¥500 and
+// KD 0.500; only the currency says where the decimal point goes.
+use super::{LineItem, Money, Order, OrderError, RoundingStrategy};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Currency {
+    Eur,
+    #[default]
+    Usd,
+    Gbp,
+    Jpy,
+    Kwd,
+}
+
+impl Currency {
+    pub const ALL: [Currency; 5] = [
+        Currency::Eur,
+        Currency::Usd,
+        Currency::Gbp,
+        Currency::Jpy,
+        Currency::Kwd,
+    ];
+
+    // "EUR" -> Currency::Eur, as Display writes it.
+    pub fn from_code(code: &str) -> Option<Currency> {
+        Self::ALL
+            .into_iter()
+            .find(|currency| currency.to_string() == code)
+    }
+
+    // How many digits after the decimal point: 2 for EUR, 0 for JPY, 3 for KWD.
+    pub fn minor_unit_digits(self) -> u32 {
+        match self {
+            Currency::Eur | Currency::Usd | Currency::Gbp => 2,
+            Currency::Jpy => 0,
+            Currency::Kwd => 3,
+        }
+    }
+
+    // Minor units in one major unit: 100 cents in a euro, 1 yen in a yen.
+    pub fn minor_units_per_unit(self) -> u32 {
+        10u32.pow(self.minor_unit_digits())
+    }
+
+    // What goes in front of the amount on a receipt.
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Currency::Eur => "€",
+            Currency::Usd => "$",
+            Currency::Gbp => "£",
+            Currency::Jpy => "¥",
+            Currency::Kwd => "KD ",
+        }
+    }
+
+    // "1.25" in KWD is Money(1250), in JPY it is refused: a yen has no
+    // fraction. Fewer decimals than the currency has are fine, more are not;
+    // a price is never rounded on its way in.
+    pub fn parse_amount(self, text: &str) -> Result<Money, OrderError> {
+        let digits = self.minor_unit_digits() as usize;
+        let (whole, fraction) = text.split_once('.').unwrap_or((text, ""));
+        let is_number = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+        if whole.is_empty()
+            || !is_number(whole)
+            || !is_number(fraction)
+            || fraction.len() > digits
+            || (text.contains('.') && fraction.is_empty())
+        {
+            return Err(OrderError::InvalidOrder);
+        }
+        let minor = format!("{whole}{fraction:0<digits$}");
+        minor
+            .parse::<u32>()
+            .map(Money)
+            .map_err(|_| OrderError::InvalidOrder)
+    }
+}
+
+impl fmt::Display for Currency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let code = match self {
+            Currency::Eur => "EUR",
+            Currency::Usd => "USD",
+            Currency::Gbp => "GBP",
+            Currency::Jpy => "JPY",
+            Currency::Kwd => "KWD",
+        };
+        f.pad(code)
+    }
+}
+
+// An amount with its currency: €49.99, ¥500, KD 1.250.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Price {
+    pub amount: Money,
+    pub currency: Currency,
+}
+
+impl Money {
+    // The same amount, shown in `currency`'s own way.
+    pub fn in_currency(self, currency: Currency) -> Price {
+        Price {
+            amount: self,
+            currency,
+        }
+    }
+}
+
+// Uses pad() like Money, so receipts can align prices in any currency.
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let digits = self.currency.minor_unit_digits() as usize;
+        let per_unit = self.currency.minor_units_per_unit();
+        let (whole, minor) = (self.amount.0 / per_unit, self.amount.0 % per_unit);
+        let symbol = self.currency.symbol();
+        if digits == 0 {
+            f.pad(&format!("{symbol}{whole}"))
+        } else {
+            f.pad(&format!("{symbol}{whole}.{minor:0digits$}"))
+        }
+    }
+}
+
+// How many target units one source unit is worth, in hundred-millionths:
+// EUR -> USD at 1.0850 is ExchangeRate(108_500_000), JPY -> USD at
+// 0.00667234 is ExchangeRate(667_234). Eight decimals, as rate feeds quote
+// them: at four, a yen rate keeps one or two significant digits.
+// Unlike BasisPoints, a rate may exceed 100 %.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExchangeRate(pub u64);
+
+const RATE_SCALE: u128 = 100_000_000;
+
+impl ExchangeRate {
+    pub const IDENTITY: ExchangeRate = ExchangeRate(100_000_000);
+
+    // Rounded to the minor unit with `rounding`. Both sides are taken to
+    // have the same minor unit; see convert when they do not.
+    // None when the converted amount does not fit in Money.
+    pub fn apply(&self, amount: Money, rounding: RoundingStrategy) -> Option<Money> {
+        let scaled = u128::from(amount.0) * u128::from(self.0);
+        u32::try_from(rounding.divide(scaled, RATE_SCALE))
+            .ok()
+            .map(Money)
+    }
+
+    // `amount` of `from`'s minor units, in `to`'s. The rate is between whole
+    // units, so going from cents to yen also divides by 100, and from cents
+    // to fils multiplies by 10, before rounding once.
+    pub fn convert(
+        &self,
+        amount: Money,
+        from: Currency,
+        to: Currency,
+        rounding: RoundingStrategy,
+    ) -> Option<Money> {
+        let numerator =
+            u128::from(amount.0) * u128::from(self.0) * u128::from(to.minor_units_per_unit());
+        let denominator = RATE_SCALE * u128::from(from.minor_units_per_unit());
+        u32::try_from(rounding.divide(numerator, denominator))
+            .ok()
+            .map(Money)
+    }
+}
+
+// A cart line priced in any currency, before conversion.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ForeignLineItem {
+    pub name: String,
+    pub price: Price,
+}
+
+// A cart line after conversion: what the customer saw, and what the order
+// actually contains.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertedLine {
+    pub original: Price,
+    pub item: LineItem,
+}
+
+// What place_order_in returns: the order, in `currency`, and how each of
+// its lines was converted, in the same order as Order::items.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConvertedOrder {
+    pub order: Order,
+    pub currency: Currency,
+    pub lines: Vec<ConvertedLine>,
+}
+
+// Amounts kept apart by currency, as a report adds them up: 12 euros and
+// 5 dollars are not 17 of anything. Listed in Currency::ALL order, and only
+// the currencies something was added in.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CurrencyTotals {
+    totals: Vec<(Currency, Money)>,
+}
+
+impl CurrencyTotals {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Saturates at Money's limit, like the single-currency sum did.
+    pub fn add(&mut self, currency: Currency, amount: Money) {
+        match self.totals.iter_mut().find(|(held, _)| *held == currency) {
+            Some((_, total)) => *total = Money(total.0.saturating_add(amount.0)),
+            None => {
+                self.totals.push((currency, amount));
+                self.totals
+                    .sort_by_key(|(held, _)| Currency::ALL.iter().position(|c| c == held));
+            }
+        }
+    }
+
+    // Money(0) for a currency nothing was added in.
+    pub fn get(&self, currency: Currency) -> Money {
+        self.totals
+            .iter()
+            .find(|(held, _)| *held == currency)
+            .map_or(Money(0), |&(_, total)| total)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Currency, Money)> + '_ {
+        self.totals.iter().copied()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.totals.is_empty()
+    }
+
+    // The one amount, for callers that can only show one: refused with
+    // MixedCurrencies when any other currency has something in it. Listed
+    // with nothing, as a free order puts it, it has nothing to mix in.
+    pub fn require_single(&self, currency: Currency) -> Result<Money, OrderError> {
+        let others: Vec<Currency> = self
+            .iter()
+            .filter(|&(held, total)| held != currency && total.0 > 0)
+            .map(|(held, _)| held)
+            .collect();
+        if others.is_empty() {
+            Ok(self.get(currency))
+        } else {
+            Err(OrderError::MixedCurrencies {
+                expected: currency,
+                others,
+            })
+        }
+    }
+}
+
+// "€12.00, $5.00", and "nothing" when empty.
+impl fmt::Display for CurrencyTotals {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("nothing");
+        }
+        let shown: Vec<String> = self
+            .iter()
+            .map(|(currency, total)| total.in_currency(currency).to_string())
+            .collect();
+        f.write_str(&shown.join(", "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RoundingStrategy::HalfUp;
+
+    #[test]
+    fn rate_rounds_half_up_to_the_cent() {
+        let rate = ExchangeRate(150_000_000); // 1.5
+        assert_eq!(rate.apply(Money(1), HalfUp), Some(Money(2))); // 1.5 -> 2
+        assert_eq!(rate.apply(Money(3), HalfUp), Some(Money(5))); // 4.5 -> 5
+        assert_eq!(
+            ExchangeRate::IDENTITY.apply(Money(4999), HalfUp),
+            Some(Money(4999))
+        );
+    }
+
+    #[test]
+    fn rate_rounds_the_way_it_is_told() {
+        let rate = ExchangeRate(150_000_000);
+        let even = RoundingStrategy::HalfEven;
+        assert_eq!(rate.apply(Money(1), even), Some(Money(2)));
+        assert_eq!(rate.apply(Money(3), even), Some(Money(4)));
+        // 0.01 EUR is 1.6 yen, rounded down: 1.
+        assert_eq!(
+            ExchangeRate(16_000_000_000).convert(
+                Money(1),
+                Currency::Eur,
+                Currency::Jpy,
+                RoundingStrategy::Down
+            ),
+            Some(Money(1))
+        );
+    }
+
+    #[test]
+    fn codes_read_back_what_display_writes() {
+        for currency in Currency::ALL {
+            assert_eq!(Currency::from_code(&currency.to_string()), Some(currency));
+        }
+        assert_eq!(Currency::from_code("eur"), None);
+    }
+
+    #[test]
+    fn totals_keep_currencies_apart() {
+        let mut totals = CurrencyTotals::new();
+        totals.add(Currency::Usd, Money(500));
+        totals.add(Currency::Eur, Money(1_200));
+        totals.add(Currency::Usd, Money(u32::MAX));
+
+        assert_eq!(
+            totals.iter().collect::<Vec<_>>(),
+            [
+                (Currency::Eur, Money(1_200)),
+                (Currency::Usd, Money(u32::MAX))
+            ]
+        );
+        assert_eq!(totals.get(Currency::Jpy), Money(0));
+        assert!(matches!(
+            totals.require_single(Currency::Usd),
+            Err(OrderError::MixedCurrencies { expected: Currency::Usd, others })
+                if others == [Currency::Eur]
+        ));
+        assert_eq!(CurrencyTotals::new().to_string(), "nothing");
+        // Nothing at all is a single currency too.
+        assert!(matches!(
+            CurrencyTotals::new().require_single(Currency::Eur),
+            Ok(Money(0))
+        ));
+    }
+
+    #[test]
+    fn nothing_in_another_currency_is_not_a_mix() {
+        let mut totals = CurrencyTotals::new();
+        totals.add(Currency::Usd, Money(500));
+        // A line a discount paid for, in euros.
+        totals.add(Currency::Eur, Money(0));
+
+        assert!(matches!(
+            totals.require_single(Currency::Usd),
+            Ok(Money(500))
+        ));
+        assert!(matches!(
+            totals.require_single(Currency::Eur),
+            Err(OrderError::MixedCurrencies { expected: Currency::Eur, others })
+                if others == [Currency::Usd]
+        ));
+    }
+
+    #[test]
+    fn overflowing_conversion_is_refused() {
+        assert_eq!(
+            ExchangeRate(200_000_000).apply(Money(u32::MAX), HalfUp),
+            None
+        );
+    }
+
+    #[test]
+    fn conversion_moves_the_decimal_point_between_minor_units() {
+        // 1.00 EUR at 160.00 is 160 yen, not 16 000.
+        let eur_jpy = ExchangeRate(16_000_000_000);
+        assert_eq!(
+            eur_jpy.convert(Money(100), Currency::Eur, Currency::Jpy, HalfUp),
+            Some(Money(160))
+        );
+        // 0.01 EUR is 1.6 yen: rounded once, at the end.
+        assert_eq!(
+            eur_jpy.convert(Money(1), Currency::Eur, Currency::Jpy, HalfUp),
+            Some(Money(2))
+        );
+        // ¥500 at 0.0062 is 3.10 EUR.
+        assert_eq!(
+            ExchangeRate(620_000).convert(Money(500), Currency::Jpy, Currency::Eur, HalfUp),
+            Some(Money(310))
+        );
+        // 10.00 EUR at 0.3345 is KD 3.345: three decimals, none lost.
+        assert_eq!(
+            ExchangeRate(33_450_000).convert(Money(1_000), Currency::Eur, Currency::Kwd, HalfUp),
+            Some(Money(3_345))
+        );
+        // KD 1.250 at 3.2600 is 4.075 USD, so 4.08.
+        assert_eq!(
+            ExchangeRate(326_000_000).convert(Money(1_250), Currency::Kwd, Currency::Usd, HalfUp),
+            Some(Money(408))
+        );
+        // Between two cent currencies it is apply.
+        assert_eq!(
+            ExchangeRate(108_500_000).convert(Money(4999), Currency::Eur, Currency::Usd, HalfUp),
+            ExchangeRate(108_500_000).apply(Money(4999), HalfUp)
+        );
+    }
+
+    #[test]
+    fn price_shows_its_currency() {
+        assert_eq!(Money(4999).in_currency(Currency::Eur).to_string(), "€49.99");
+        assert_eq!(Money(4999).in_currency(Currency::Usd).to_string(), "$49.99");
+        assert_eq!(Money(500).in_currency(Currency::Jpy).to_string(), "¥500");
+        assert_eq!(
+            Money(1_250).in_currency(Currency::Kwd).to_string(),
+            "KD 1.250"
+        );
+        assert_eq!(Money(5).in_currency(Currency::Kwd).to_string(), "KD 0.005");
+        assert_eq!(
+            format!("{:>8}", Money(500).in_currency(Currency::Jpy)),
+            "    ¥500"
+        );
+    }
+
+    #[test]
+    fn amounts_are_parsed_in_the_minor_units_of_their_currency() {
+        assert_eq!(Currency::Eur.parse_amount("49.99").unwrap(), Money(4999));
+        assert_eq!(Currency::Eur.parse_amount("49.9").unwrap(), Money(4990));
+        assert_eq!(Currency::Jpy.parse_amount("500").unwrap(), Money(500));
+        assert_eq!(Currency::Kwd.parse_amount("1.25").unwrap(), Money(1_250));
+        assert_eq!(Currency::Kwd.parse_amount("1").unwrap(), Money(1_000));
+        for (currency, text) in [
+            (Currency::Jpy, "500.0"),
+            (Currency::Eur, "1.999"),
+            (Currency::Kwd, "1.2505"),
+            (Currency::Eur, "1."),
+            (Currency::Eur, ".50"),
+            (Currency::Eur, "-1"),
+            (Currency::Kwd, "4294968"),
+        ] {
+            assert!(currency.parse_amount(text).is_err(), "{text} in {currency}");
+        }
+    }
+}
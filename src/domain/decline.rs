@@ -0,0 +1,53 @@
+// Why a card was declined.
+// A payment provider says more than "no": the customer has no funds left,
+// the card has expired, the issuer thinks it is stolen, or the issuer
+// could not answer just now. Only the last is worth asking again; for the
+// others, the customer has to pay some other way.
+//
+// The codes are the ones a client matches on, next to PAY-039: the names
+// the providers use, and never changed once given out.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DeclineReason {
+    InsufficientFunds,
+    CardExpired,
+    SuspectedFraud,
+    TryAgainLater,
+}
+
+impl DeclineReason {
+    pub const ALL: [DeclineReason; 4] = [
+        DeclineReason::InsufficientFunds,
+        DeclineReason::CardExpired,
+        DeclineReason::SuspectedFraud,
+        DeclineReason::TryAgainLater,
+    ];
+
+    pub fn code(self) -> &'static str {
+        match self {
+            DeclineReason::InsufficientFunds => "insufficient_funds",
+            DeclineReason::CardExpired => "expired_card",
+            DeclineReason::SuspectedFraud => "fraudulent",
+            DeclineReason::TryAgainLater => "try_again_later",
+        }
+    }
+
+    // The same charge may go through if it is made again.
+    pub fn is_retryable(self) -> bool {
+        self == DeclineReason::TryAgainLater
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_try_again_later_is_worth_retrying() {
+        let retryable: Vec<DeclineReason> = DeclineReason::ALL
+            .into_iter()
+            .filter(|reason| reason.is_retryable())
+            .collect();
+        assert_eq!(retryable, [DeclineReason::TryAgainLater]);
+    }
+}
@@ -0,0 +1,311 @@
+// Deltas: what changed between two versions of one order, field by field.
+// A store that keeps every version of an order can keep the first one
+// whole and each later one as the deltas from the one before.
+//
+// Order::diff gives the fewest it can: one delta per field that differs,
+// and for the items one per line added, removed or changed, the lines both
+// versions share in the same order left alone. Applying them, in order, to
+// the older version gives the newer one exactly: `old.apply(&old.diff(&new))`
+// is `new`, whatever the two orders hold. The id is the one field never
+// compared: both are versions of the same order.
+//
+// Applying is bookkeeping, not a business rule: a status goes where the
+// delta says, allowed transition or not. It only fails on an item index
+// the order does not have.
+use super::{
+    Address, Approval, Attachment, Currency, LineItem, Money, Order, OrderError, OrderNote,
+    OrderReference, OrderStatus, Shipment, Timestamp, Uuid128,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderDelta {
+    // Inserted at `index`, the items from there on moving up one.
+    ItemAdded { index: usize, item: LineItem },
+    ItemRemoved { index: usize },
+    // The line at `index`, replaced.
+    ItemChanged { index: usize, item: LineItem },
+    TotalChanged { total: Money },
+    CurrencyChanged { currency: Currency },
+    StatusChanged { from: OrderStatus, to: OrderStatus },
+    // Shipments only ever come after the ones before.
+    ShipmentAdded { shipment: Shipment },
+    // For anything else done to the shipments.
+    ShipmentsReplaced { shipments: Vec<Shipment> },
+    PlacedAtChanged { placed_at: Option<Timestamp> },
+    UuidChanged { uuid: Option<Uuid128> },
+    ReferenceChanged { reference: Option<OrderReference> },
+    VersionChanged { version: u32 },
+    ApprovalChanged { approval: Option<Approval> },
+    AddressChanged { address: Option<Address> },
+    GiftNoteChanged { note: Option<String> },
+    // Like shipments, notes and attachments come after the ones before.
+    NoteAdded { note: OrderNote },
+    NotesReplaced { notes: Vec<OrderNote> },
+    AttachmentAdded { attachment: Attachment },
+    AttachmentsReplaced { attachments: Vec<Attachment> },
+}
+
+impl Order {
+    // What takes this order to `newer`, oldest change first.
+    pub fn diff(&self, newer: &Order) -> Vec<OrderDelta> {
+        let mut deltas = item_deltas(&self.items, &newer.items);
+        if self.total != newer.total {
+            deltas.push(OrderDelta::TotalChanged { total: newer.total });
+        }
+        if self.currency != newer.currency {
+            deltas.push(OrderDelta::CurrencyChanged {
+                currency: newer.currency,
+            });
+        }
+        if self.status != newer.status {
+            deltas.push(OrderDelta::StatusChanged {
+                from: self.status,
+                to: newer.status,
+            });
+        }
+        match newer.shipments.strip_prefix(&self.shipments[..]) {
+            Some(added) => deltas.extend(added.iter().map(|shipment| OrderDelta::ShipmentAdded {
+                shipment: shipment.clone(),
+            })),
+            None => deltas.push(OrderDelta::ShipmentsReplaced {
+                shipments: newer.shipments.clone(),
+            }),
+        }
+        if self.placed_at != newer.placed_at {
+            deltas.push(OrderDelta::PlacedAtChanged {
+                placed_at: newer.placed_at,
+            });
+        }
+        if self.uuid != newer.uuid {
+            deltas.push(OrderDelta::UuidChanged { uuid: newer.uuid });
+        }
+        if self.reference != newer.reference {
+            deltas.push(OrderDelta::ReferenceChanged {
+                reference: newer.reference.clone(),
+            });
+        }
+        if self.version != newer.version {
+            deltas.push(OrderDelta::VersionChanged {
+                version: newer.version,
+            });
+        }
+        if self.approval != newer.approval {
+            deltas.push(OrderDelta::ApprovalChanged {
+                approval: newer.approval.clone(),
+            });
+        }
+        if self.shipping_address != newer.shipping_address {
+            deltas.push(OrderDelta::AddressChanged {
+                address: newer.shipping_address.clone(),
+            });
+        }
+        if self.gift_note != newer.gift_note {
+            deltas.push(OrderDelta::GiftNoteChanged {
+                note: newer.gift_note.clone(),
+            });
+        }
+        match newer.notes.strip_prefix(&self.notes[..]) {
+            Some(added) => deltas.extend(
+                added
+                    .iter()
+                    .map(|note| OrderDelta::NoteAdded { note: note.clone() }),
+            ),
+            None => deltas.push(OrderDelta::NotesReplaced {
+                notes: newer.notes.clone(),
+            }),
+        }
+        match newer.attachments.strip_prefix(&self.attachments[..]) {
+            Some(added) => {
+                deltas.extend(added.iter().map(|attachment| OrderDelta::AttachmentAdded {
+                    attachment: attachment.clone(),
+                }))
+            }
+            None => deltas.push(OrderDelta::AttachmentsReplaced {
+                attachments: newer.attachments.clone(),
+            }),
+        }
+        deltas
+    }
+
+    // All or nothing: on an index out of range the order is left as it was.
+    pub fn apply(&mut self, deltas: &[OrderDelta]) -> Result<(), OrderError> {
+        let mut order = self.clone();
+        for delta in deltas {
+            order.apply_one(delta)?;
+        }
+        *self = order;
+        Ok(())
+    }
+
+    fn apply_one(&mut self, delta: &OrderDelta) -> Result<(), OrderError> {
+        let out_of_range = |index: usize| OrderError::InvalidItemIndex { index };
+        match delta {
+            OrderDelta::ItemAdded { index, item } => {
+                if *index > self.items.len() {
+                    return Err(out_of_range(*index));
+                }
+                self.items.insert(*index, item.clone());
+            }
+            OrderDelta::ItemRemoved { index } => {
+                if *index >= self.items.len() {
+                    return Err(out_of_range(*index));
+                }
+                self.items.remove(*index);
+            }
+            OrderDelta::ItemChanged { index, item } => {
+                *self.items.get_mut(*index).ok_or(out_of_range(*index))? = item.clone();
+            }
+            OrderDelta::TotalChanged { total } => self.total = *total,
+            OrderDelta::CurrencyChanged { currency } => self.currency = *currency,
+            OrderDelta::StatusChanged { to, .. } => self.status = *to,
+            OrderDelta::ShipmentAdded { shipment } => self.shipments.push(shipment.clone()),
+            OrderDelta::ShipmentsReplaced { shipments } => self.shipments = shipments.clone(),
+            OrderDelta::PlacedAtChanged { placed_at } => self.placed_at = *placed_at,
+            OrderDelta::UuidChanged { uuid } => self.uuid = *uuid,
+            OrderDelta::ReferenceChanged { reference } => self.reference = reference.clone(),
+            OrderDelta::VersionChanged { version } => self.version = *version,
+            OrderDelta::ApprovalChanged { approval } => self.approval = approval.clone(),
+            OrderDelta::AddressChanged { address } => self.shipping_address = address.clone(),
+            OrderDelta::GiftNoteChanged { note } => self.gift_note = note.clone(),
+            OrderDelta::NoteAdded { note } => self.notes.push(note.clone()),
+            OrderDelta::NotesReplaced { notes } => self.notes = notes.clone(),
+            OrderDelta::AttachmentAdded { attachment } => self.attachments.push(attachment.clone()),
+            OrderDelta::AttachmentsReplaced { attachments } => {
+                self.attachments = attachments.clone()
+            }
+        }
+        Ok(())
+    }
+}
+
+// The longest run of lines both share, in order, is kept; around it, a line
+// removed where one is added becomes one change. Carts are small: the
+// table is old × new.
+fn item_deltas(old: &[LineItem], new: &[LineItem]) -> Vec<OrderDelta> {
+    // kept[i][j]: how many lines old[i..] and new[j..] have in common.
+    let mut kept = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            kept[i][j] = if old[i] == new[j] {
+                kept[i + 1][j + 1] + 1
+            } else {
+                kept[i + 1][j].max(kept[i][j + 1])
+            };
+        }
+    }
+    // `at` is where the next line is in the order being edited.
+    let (mut deltas, mut at, mut i, mut j) = (Vec::new(), 0, 0, 0);
+    while i < old.len() || j < new.len() {
+        let keep = i < old.len() && j < new.len() && old[i] == new[j];
+        let change = i < old.len() && j < new.len() && !keep && kept[i + 1][j + 1] == kept[i][j];
+        if keep || change {
+            if change {
+                deltas.push(OrderDelta::ItemChanged {
+                    index: at,
+                    item: new[j].clone(),
+                });
+            }
+            (i, j, at) = (i + 1, j + 1, at + 1);
+        } else if j < new.len() && (i == old.len() || kept[i][j + 1] >= kept[i + 1][j]) {
+            deltas.push(OrderDelta::ItemAdded {
+                index: at,
+                item: new[j].clone(),
+            });
+            (j, at) = (j + 1, at + 1);
+        } else {
+            deltas.push(OrderDelta::ItemRemoved { index: at });
+            i += 1;
+        }
+    }
+    deltas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::OrderId;
+
+    fn order(items: &[(&str, u32)]) -> Order {
+        let items = items
+            .iter()
+            .map(|(name, cents)| LineItem::new(*name, Money(*cents)))
+            .collect();
+        Order::new(OrderId(1), items).unwrap()
+    }
+
+    fn round_trip(old: &Order, new: &Order) -> Vec<OrderDelta> {
+        let deltas = old.diff(new);
+        let mut applied = old.clone();
+        applied.apply(&deltas).unwrap();
+        assert_eq!(&applied, new);
+        deltas
+    }
+
+    #[test]
+    fn one_field_changed_is_one_delta() {
+        let old = order(&[("Pen", 150), ("Ink", 250)]);
+
+        let mut paid = old.clone();
+        paid.status = OrderStatus::Paid;
+        assert_eq!(
+            round_trip(&old, &paid),
+            [OrderDelta::StatusChanged {
+                from: OrderStatus::Placed,
+                to: OrderStatus::Paid
+            }]
+        );
+
+        let mut noted = old.clone();
+        noted.gift_note = Some("Happy birthday".to_string());
+        assert_eq!(round_trip(&old, &noted).len(), 1);
+
+        let mut repriced = old.clone();
+        repriced.items[1].price = Money(300);
+        assert_eq!(
+            round_trip(&old, &repriced),
+            [OrderDelta::ItemChanged {
+                index: 1,
+                item: LineItem::new("Ink", Money(300))
+            }]
+        );
+    }
+
+    #[test]
+    fn lines_both_share_are_left_alone() {
+        let old = order(&[("Pen", 150), ("Ink", 250), ("Pad", 400)]);
+        let mut new = order(&[("Cap", 50), ("Pen", 150), ("Pad", 400), ("Nib", 90)]);
+        new.total = old.total;
+
+        assert_eq!(
+            round_trip(&old, &new),
+            [
+                OrderDelta::ItemAdded {
+                    index: 0,
+                    item: LineItem::new("Cap", Money(50))
+                },
+                OrderDelta::ItemRemoved { index: 2 },
+                OrderDelta::ItemAdded {
+                    index: 3,
+                    item: LineItem::new("Nib", Money(90))
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn a_delta_the_order_cannot_take_changes_nothing() {
+        let mut order = order(&[("Pen", 150)]);
+        let before = order.clone();
+
+        let result = order.apply(&[
+            OrderDelta::TotalChanged { total: Money(1) },
+            OrderDelta::ItemRemoved { index: 1 },
+        ]);
+
+        assert!(matches!(
+            result,
+            Err(OrderError::InvalidItemIndex { index: 1 })
+        ));
+        assert_eq!(order, before);
+    }
+}
@@ -0,0 +1,156 @@
+// What brings a cart's price down before it is charged: a promotion taking
+// a share off, or a gift card paying part or all of it.
+//
+// The order keeps its items at their full prices and only what is left to
+// charge in its total, so what the discount covered is always the sum of
+// the items minus the total.
+use super::{BasisPoints, LineItem, Money, Order, OrderError, OrderId, Percent, RoundingStrategy};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Discount {
+    // Off the whole cart: 100 % makes it free.
+    Promotion(Percent),
+    // A balance spent up to the cart's price; whatever is left stays on
+    // the card.
+    GiftCard(Money),
+}
+
+impl Discount {
+    // How much of `subtotal` this covers: never more than all of it. A
+    // promotion's share is rounded with `rounding`; whatever it rounds away
+    // is charged.
+    pub fn covered(&self, subtotal: Money, rounding: RoundingStrategy) -> Money {
+        match self {
+            Discount::Promotion(percent) => subtotal.scale(BasisPoints::from(*percent), rounding),
+            Discount::GiftCard(balance) => Money(balance.0.min(subtotal.0)),
+        }
+    }
+}
+
+// Business rule:
+// an order may come to nothing only when a discount paid for it. A cart
+// whose items are all free is still refused: nothing was sold.
+impl Order {
+    pub fn discounted(
+        id: OrderId,
+        items: Vec<LineItem>,
+        discount: Discount,
+        rounding: RoundingStrategy,
+    ) -> Result<Self, OrderError> {
+        let mut order = Order::new(id, items)?;
+        order.total = Money(order.total.0 - discount.covered(order.total, rounding).0);
+        Ok(order)
+    }
+
+    // What a discount covered: nothing for an order charged in full.
+    pub fn discount(&self) -> Money {
+        let subtotal = self
+            .items
+            .iter()
+            .map(|item| u64::from(item.price.0))
+            .sum::<u64>();
+        Money(u32::try_from(subtotal.saturating_sub(u64::from(self.total.0))).unwrap_or(u32::MAX))
+    }
+
+    // What each item was charged, in the order of Order::items: the total
+    // split in proportion to their prices, so a discount is shared by all
+    // of them and the shares still add up to the total, to the cent.
+    pub fn charged_per_item(&self) -> Result<Vec<Money>, OrderError> {
+        let prices: Vec<u32> = self.items.iter().map(|item| item.price.0).collect();
+        self.total.allocate_weighted(&prices)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use RoundingStrategy::{Down, HalfEven, HalfUp};
+
+    fn cart() -> Vec<LineItem> {
+        vec![
+            LineItem::new("Mug", Money(1200)),
+            LineItem::new("Tea", Money(800)),
+        ]
+    }
+
+    fn percent(value: u32) -> Percent {
+        Percent::try_from(value).unwrap()
+    }
+
+    #[test]
+    fn a_full_promotion_or_a_large_gift_card_makes_the_order_free() {
+        for discount in [
+            Discount::Promotion(percent(100)),
+            Discount::GiftCard(Money(5000)),
+        ] {
+            let order = Order::discounted(OrderId(1), cart(), discount, HalfUp).unwrap();
+            assert_eq!((order.total, order.discount()), (Money(0), Money(2000)));
+        }
+    }
+
+    #[test]
+    fn a_partial_discount_leaves_the_rest_to_charge() {
+        let promoted =
+            Order::discounted(OrderId(1), cart(), Discount::Promotion(percent(25)), HalfUp);
+        assert_eq!(promoted.unwrap().total, Money(1500));
+
+        let carded =
+            Order::discounted(OrderId(2), cart(), Discount::GiftCard(Money(500)), HalfUp).unwrap();
+        assert_eq!((carded.total, carded.discount()), (Money(1500), Money(500)));
+    }
+
+    #[test]
+    fn every_item_carries_its_share_of_the_discount() {
+        let promoted =
+            Order::discounted(OrderId(1), cart(), Discount::Promotion(percent(25)), HalfUp);
+        assert_eq!(
+            promoted.unwrap().charged_per_item().unwrap(),
+            vec![Money(900), Money(600)]
+        );
+
+        // A cent off three equal items: one of them is a cent cheaper.
+        let three = vec![LineItem::new("Cup", Money(1000)); 3];
+        let carded =
+            Order::discounted(OrderId(2), three, Discount::GiftCard(Money(1)), HalfUp).unwrap();
+        let shares = carded.charged_per_item().unwrap();
+        assert_eq!(shares, vec![Money(1000), Money(1000), Money(999)]);
+        assert_eq!(
+            shares.iter().map(|share| share.0).sum::<u32>(),
+            carded.total.0
+        );
+    }
+
+    #[test]
+    fn the_promotion_is_rounded_the_way_the_order_is_told() {
+        // 15 % off $0.30 is 4.5 cents off.
+        let cart = vec![LineItem::new("Stamp", Money(30))];
+        let total = |rounding| {
+            Order::discounted(
+                OrderId(1),
+                cart.clone(),
+                Discount::Promotion(percent(15)),
+                rounding,
+            )
+            .unwrap()
+            .total
+        };
+
+        assert_eq!(total(HalfUp), Money(25));
+        assert_eq!(total(HalfEven), Money(26));
+        assert_eq!(total(Down), Money(26));
+    }
+
+    #[test]
+    fn free_items_are_refused_discount_or_not() {
+        let free = vec![LineItem::new("Sticker", Money(0))];
+
+        assert!(matches!(
+            Order::new(OrderId(1), free.clone()),
+            Err(OrderError::ZeroTotalNotAllowed)
+        ));
+        assert!(matches!(
+            Order::discounted(OrderId(1), free, Discount::Promotion(percent(100)), HalfUp),
+            Err(OrderError::ZeroTotalNotAllowed)
+        ));
+    }
+}
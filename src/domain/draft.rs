@@ -0,0 +1,210 @@
+// Typestate: a draft and a confirmed order are two different types.
+//
+// An OrderDraft is a cart being filled: items come and go, nothing is
+// guaranteed, not even that it has an item. Confirming it checks the
+// business rules once and, only if they hold, produces a ConfirmedOrder.
+// A ConfirmedOrder cannot be edited: its accessors only read.
+//
+// The payoff is in the ports: charging and notifying take a &ConfirmedOrder,
+// so "charge a cart nobody confirmed" is not a bug to test for, it does not
+// compile.
+use super::{LineItem, Money, Order, OrderError, OrderId, OrderStatus};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderDraft {
+    id: OrderId,
+    items: Vec<LineItem>,
+}
+
+impl OrderDraft {
+    pub fn new(id: OrderId) -> Self {
+        Self {
+            id,
+            items: Vec::new(),
+        }
+    }
+
+    pub fn id(&self) -> OrderId {
+        self.id
+    }
+
+    pub fn items(&self) -> &[LineItem] {
+        &self.items
+    }
+
+    pub fn add_item(&mut self, item: LineItem) {
+        self.items.push(item);
+    }
+
+    pub fn remove_item(&mut self, index: usize) -> Result<LineItem, OrderError> {
+        if index >= self.items.len() {
+            return Err(OrderError::InvalidItemIndex { index });
+        }
+        Ok(self.items.remove(index))
+    }
+
+    // On failure the draft comes back with the error, untouched, so the
+    // customer can fix the cart instead of starting over.
+    pub fn confirm(
+        self,
+        policy: &ConfirmPolicy,
+    ) -> Result<ConfirmedOrder, (OrderDraft, OrderError)> {
+        let order = match Order::new(self.id, self.items.clone()) {
+            Ok(order) => order,
+            Err(error) => return Err((self, error)),
+        };
+        if let Err(error) = policy.check(&order) {
+            return Err((self, error));
+        }
+        Ok(ConfirmedOrder { order })
+    }
+}
+
+// The rules a draft must satisfy to be confirmed, on top of "not empty".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ConfirmPolicy {
+    pub max_items: usize,
+    pub min_total: Money,
+}
+
+impl Default for ConfirmPolicy {
+    // No limit beyond the one every order has.
+    fn default() -> Self {
+        Self {
+            max_items: usize::MAX,
+            min_total: Money(0),
+        }
+    }
+}
+
+impl ConfirmPolicy {
+    fn check(&self, order: &Order) -> Result<(), OrderError> {
+        if order.items.len() > self.max_items || order.total.0 < self.min_total.0 {
+            return Err(OrderError::InvalidOrder);
+        }
+        Ok(())
+    }
+}
+
+// A confirmed order: validated, read-only from the outside.
+// Only the application layer may move it forward (Placed -> Paid).
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfirmedOrder {
+    order: Order,
+}
+
+impl ConfirmedOrder {
+    pub fn id(&self) -> OrderId {
+        self.order.id
+    }
+
+    pub fn items(&self) -> &[LineItem] {
+        &self.order.items
+    }
+
+    pub fn total(&self) -> Money {
+        self.order.total
+    }
+
+    pub fn status(&self) -> OrderStatus {
+        self.order.status
+    }
+
+    // A shared borrow of the underlying order, for code written against
+    // Order (receipts, senders). Nothing can be changed through it.
+    pub fn as_order(&self) -> &Order {
+        &self.order
+    }
+
+    pub(crate) fn mark_paid(&mut self) -> Result<(), OrderError> {
+        self.order.mark_paid()
+    }
+}
+
+// What a draft-aware repository keeps: either state, under the same id.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StoredOrder {
+    Draft(OrderDraft),
+    Confirmed(Box<ConfirmedOrder>),
+}
+
+impl StoredOrder {
+    pub fn id(&self) -> OrderId {
+        match self {
+            StoredOrder::Draft(draft) => draft.id(),
+            StoredOrder::Confirmed(order) => order.id(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_err_variant;
+
+    fn item(name: &str, cents: u32) -> LineItem {
+        LineItem {
+            name: name.to_string(),
+            price: Money(cents),
+        }
+    }
+
+    #[test]
+    fn confirming_a_valid_draft_yields_a_placed_order() {
+        let mut draft = OrderDraft::new(OrderId(1));
+        draft.add_item(item("Keyboard", 12999));
+        draft.add_item(item("Mouse", 2500));
+
+        let confirmed = draft.confirm(&ConfirmPolicy::default()).unwrap();
+        assert_eq!(confirmed.id(), OrderId(1));
+        assert_eq!(confirmed.total(), Money(15499));
+        assert_eq!(confirmed.status(), OrderStatus::Placed);
+    }
+
+    #[test]
+    fn failed_confirmation_gives_the_draft_back() {
+        let mut draft = OrderDraft::new(OrderId(2));
+        draft.add_item(item("Sticker", 150));
+        let policy = ConfirmPolicy {
+            min_total: Money(500),
+            ..ConfirmPolicy::default()
+        };
+
+        let (draft, error) = draft.confirm(&policy).unwrap_err();
+        assert_err_variant!(Err::<(), _>(error), OrderError::InvalidOrder);
+        assert_eq!(draft.items(), &[item("Sticker", 150)]);
+
+        // Fix the cart and try again.
+        let mut draft = draft;
+        draft.add_item(item("Rust Book", 4999));
+        assert!(draft.confirm(&policy).is_ok());
+    }
+
+    #[test]
+    fn empty_draft_and_too_many_items_are_rejected() {
+        let draft = OrderDraft::new(OrderId(3));
+        assert!(draft.confirm(&ConfirmPolicy::default()).is_err());
+
+        let mut draft = OrderDraft::new(OrderId(4));
+        draft.add_item(item("A", 100));
+        draft.add_item(item("B", 100));
+        let policy = ConfirmPolicy {
+            max_items: 1,
+            ..ConfirmPolicy::default()
+        };
+        assert!(draft.confirm(&policy).is_err());
+    }
+
+    #[test]
+    fn remove_item_checks_the_index() {
+        let mut draft = OrderDraft::new(OrderId(5));
+        draft.add_item(item("Mouse", 2500));
+
+        assert_err_variant!(
+            draft.remove_item(1),
+            OrderError::InvalidItemIndex { index: 1 }
+        );
+        assert_eq!(draft.remove_item(0).unwrap(), item("Mouse", 2500));
+        assert!(draft.items().is_empty());
+    }
+}
@@ -0,0 +1,224 @@
+// Error codes.
+// What a program on the other side of the HTTP or CLI adapter can rely on,
+// whatever becomes of the variant names: every OrderError has a code, and
+// a code once given out is never changed nor given to another variant.
+// A new variant takes the next number; a removed one takes its number with
+// it.
+//
+// The letters say what the error is about:
+//
+//     ORD  the order itself        PAY  charging and refunding
+//     STO  storing it              NTF  telling the customer
+//     SHP  shipping it             CUR  currencies
+//     APR  approval                FRD  the fraud screen
+//     STK  stock                   REQ  what the client sent
+//     SYS  the service's own state EXT  an adapter of another crate
+//
+// A message template names the variant's fields in braces, for a front
+// end to fill in and translate.
+use super::OrderError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CatalogueEntry {
+    pub code: &'static str,
+    pub variant: &'static str,
+    pub message: &'static str,
+}
+
+// One line per variant, in the order the codes were given out: the
+// catalogue and OrderError::catalogue_entry are both written from it.
+macro_rules! catalogue {
+    ($($code:literal $variant:ident $message:literal,)*) => {
+        const CATALOGUE: &[CatalogueEntry] = &[$(CatalogueEntry {
+            code: $code,
+            variant: stringify!($variant),
+            message: $message,
+        }),*];
+
+        impl OrderError {
+            // Its line in the catalogue. No wildcard: a new variant does
+            // not compile until it has a line.
+            pub fn catalogue_entry(&self) -> &'static CatalogueEntry {
+                match self {
+                    $(OrderError::$variant { .. } => &CatalogueEntry {
+                        code: $code,
+                        variant: stringify!($variant),
+                        message: $message,
+                    },)*
+                }
+            }
+        }
+    };
+}
+
+catalogue! {
+    "ORD-001" InvalidOrder "The order needs at least one item, and a total that fits.",
+    "PAY-002" PaymentFailed "The payment was declined.",
+    "PAY-003" PaymentUnavailable "The payment provider cannot be reached. Try again later.",
+    "PAY-004" DailyCapExceeded "This charge would go over today's cap: {remaining} left.",
+    "STO-005" StorageFailed "The order could not be stored.",
+    "NTF-006" NotificationFailed "The confirmation could not be sent.",
+    "ORD-007" NotFound "There is no order {id}.",
+    "ORD-008" InvalidTransition "An order cannot go from {from} to {to}.",
+    "ORD-009" InvalidItemIndex "The order has no item {index}.",
+    "SHP-010" AlreadyShipped "Item {index} has already shipped.",
+    "SHP-011" ShippingFailed "The shipment could not be booked.",
+    "ORD-012" NotADraft "Order {id} is no longer a draft.",
+    "CUR-013" NoExchangeRate "There is no exchange rate from {from} to {to}.",
+    "ORD-014" VersionConflict "Order {id} changed meanwhile: it is at version {found}, not {expected}.",
+    "APR-015" AlreadyApproved "Order {id} was already approved by {by}.",
+    "APR-016" NotPendingApproval "Order {id} is {status}, not waiting for approval.",
+    "ORD-017" CannotDelete "Order {id} is {status}: only cancelled orders can be deleted.",
+    "ORD-018" NotDeleted "Order {id} is not deleted.",
+    "REQ-019" ClockSkew "The time given is {delta_secs} seconds away from the service's.",
+    "ORD-020" InvalidWeights "The amount cannot be split by these weights.",
+    "STK-021" OutOfStock "There is not enough {item} left.",
+    "SYS-022" UnknownSaga "There is no saga {id} to resume.",
+    "ORD-023" ZeroTotalNotAllowed "The order comes to nothing.",
+    "FRD-024" FraudSuspected "The order was refused: {reason}.",
+    "FRD-025" NotUnderReview "Order {id} is {status}, not under review.",
+    "SYS-026" CircuitOpen "The service is down. Try again in {retry_after_secs} seconds.",
+    "SYS-027" TooManyConcurrentCalls "The service is busy. Try again shortly.",
+    "SYS-028" BudgetExhausted "The request ran out of time after {spent_ms} ms.",
+    "SYS-029" Cancelled "Cancelled, after {processed} orders.",
+    "ORD-030" NotAmendable "A {status} order can no longer be changed.",
+    "REQ-031" InvalidFields "Some fields are not right: {errors}.",
+    "ORD-032" RetentionBlocked "The order is kept for now: {reason}.",
+    "STO-033" CorruptData "Order {order_id} as stored is not valid: {reason}.",
+    "CUR-034" MixedCurrencies "There are amounts in {others} as well as in {expected}.",
+    "EXT-035" Custom "{message}",
+    "ORD-036" NoteTooLong "A note may have {limit} bytes, not {bytes}.",
+    "ORD-037" TooManyNotes "The order already has its {limit} notes.",
+    "ORD-038" AttachmentTooLarge "A file may have {limit} bytes, not {bytes}.",
+    "PAY-039" PaymentDeclined "The payment was declined: {reason}.",
+}
+
+// Every code, in the order they were given out.
+pub fn catalogue() -> &'static [CatalogueEntry] {
+    CATALOGUE
+}
+
+impl OrderError {
+    pub fn code(&self) -> &'static str {
+        self.catalogue_entry().code
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        ApproverId, Currency, DeclineReason, FieldErrors, Money, OrderId, OrderStatus, SagaId,
+    };
+    use std::collections::HashSet;
+
+    // One of each variant, in the catalogue's order.
+    fn one_of_each() -> Vec<OrderError> {
+        let id = OrderId(1);
+        let status = OrderStatus::Paid;
+        let reason = String::from("why");
+        vec![
+            OrderError::InvalidOrder,
+            OrderError::PaymentFailed,
+            OrderError::PaymentUnavailable,
+            OrderError::DailyCapExceeded {
+                remaining: Money(0),
+            },
+            OrderError::StorageFailed,
+            OrderError::NotificationFailed,
+            OrderError::NotFound { id },
+            OrderError::InvalidTransition {
+                from: status,
+                to: status,
+            },
+            OrderError::InvalidItemIndex { index: 0 },
+            OrderError::AlreadyShipped { index: 0 },
+            OrderError::ShippingFailed,
+            OrderError::NotADraft { id },
+            OrderError::NoExchangeRate {
+                from: Currency::Eur,
+                to: Currency::Usd,
+            },
+            OrderError::VersionConflict {
+                id,
+                expected: 1,
+                found: 2,
+            },
+            OrderError::AlreadyApproved {
+                id,
+                by: ApproverId(String::from("ann")),
+            },
+            OrderError::NotPendingApproval { id, status },
+            OrderError::CannotDelete { id, status },
+            OrderError::NotDeleted { id },
+            OrderError::ClockSkew { delta_secs: 0 },
+            OrderError::InvalidWeights,
+            OrderError::OutOfStock {
+                item: reason.clone(),
+            },
+            OrderError::UnknownSaga { id: SagaId(1) },
+            OrderError::ZeroTotalNotAllowed,
+            OrderError::FraudSuspected {
+                reason: reason.clone(),
+            },
+            OrderError::NotUnderReview { id, status },
+            OrderError::CircuitOpen {
+                retry_after_secs: 0,
+            },
+            OrderError::TooManyConcurrentCalls { limit: 1 },
+            OrderError::BudgetExhausted { spent_ms: 0 },
+            OrderError::Cancelled { processed: 0 },
+            OrderError::NotAmendable { status },
+            OrderError::InvalidFields {
+                errors: FieldErrors::default(),
+            },
+            OrderError::RetentionBlocked {
+                reason: reason.clone(),
+            },
+            OrderError::CorruptData {
+                order_id: id,
+                reason,
+            },
+            OrderError::MixedCurrencies {
+                expected: Currency::Usd,
+                others: Vec::new(),
+            },
+            OrderError::custom("theirs"),
+            OrderError::NoteTooLong { bytes: 2, limit: 1 },
+            OrderError::TooManyNotes { limit: 1 },
+            OrderError::AttachmentTooLarge { bytes: 2, limit: 1 },
+            OrderError::PaymentDeclined {
+                reason: DeclineReason::TryAgainLater,
+            },
+        ]
+    }
+
+    // Unique and well formed; not necessarily one after the other, as a
+    // removed variant takes its number with it.
+    #[test]
+    fn codes_are_unique_and_well_formed() {
+        let codes: HashSet<_> = catalogue().iter().map(|entry| entry.code).collect();
+        assert_eq!(codes.len(), catalogue().len());
+        let areas = [
+            "ORD", "PAY", "STO", "NTF", "SHP", "CUR", "APR", "FRD", "STK", "REQ", "SYS", "EXT",
+        ];
+        for entry in catalogue() {
+            let (area, number) = entry.code.split_once('-').unwrap();
+            assert!(areas.contains(&area), "{entry:?}");
+            assert_eq!(number.len(), 3, "{entry:?}");
+            assert!(number.bytes().all(|b| b.is_ascii_digit()), "{entry:?}");
+        }
+    }
+
+    #[test]
+    fn every_variant_has_its_own_line_in_the_catalogue() {
+        let errors = one_of_each();
+        assert_eq!(errors.len(), catalogue().len());
+        for (error, entry) in errors.iter().zip(catalogue()) {
+            assert_eq!(error.catalogue_entry(), entry);
+            // The name is the variant's, as Debug shows it.
+            let debug = format!("{error:?}");
+            assert_eq!(debug.split([' ', '{']).next(), Some(entry.variant));
+        }
+    }
+}
@@ -0,0 +1,108 @@
+// Domain events: facts about orders, in the past tense.
+// The service publishes them once the fact is stored; whoever listens
+// (a read model, an audit log, another bounded context) builds its own view.
+use super::{Money, OrderId, TrackingId};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum OrderEvent {
+    OrderPlaced {
+        order_id: OrderId,
+        item_count: usize,
+        total: Money,
+    },
+    // Placed, but parked until approved or rejected.
+    ApprovalRequested {
+        order_id: OrderId,
+        total: Money,
+    },
+    // Placed, but parked until its fraud review is resolved.
+    ReviewRequested {
+        order_id: OrderId,
+        total: Money,
+    },
+    OrderRejected {
+        order_id: OrderId,
+        reason: String,
+    },
+    // Placed while the payment provider was unreachable: the charge is
+    // owed, and follows as OrderPaid, or OrderRejected if declined.
+    PaymentDeferred {
+        order_id: OrderId,
+        amount: Money,
+    },
+    // Nothing to charge: a discount, `covered`, paid for all of it. The
+    // gateway was never called; OrderPaid follows, for nothing.
+    ChargeSkipped {
+        order_id: OrderId,
+        covered: Money,
+    },
+    OrderPaid {
+        order_id: OrderId,
+        amount: Money,
+    },
+    // Paid, then cancelled before it shipped: `refunded` went back.
+    OrderCancelled {
+        order_id: OrderId,
+        refunded: Money,
+    },
+    ItemsShipped {
+        order_id: OrderId,
+        tracking: TrackingId,
+        item_count: usize,
+    },
+    // Left unpaid, Placed or PaymentPending, for longer than the service
+    // keeps such orders: cancelled, nothing charged or refunded.
+    OrderExpired {
+        order_id: OrderId,
+    },
+    // `from`, an open cart, was merged into `order_id`, which now has
+    // `item_count` items for `total`. `from` is deleted.
+    OrdersMerged {
+        order_id: OrderId,
+        from: OrderId,
+        item_count: usize,
+        total: Money,
+    },
+}
+
+// Facts about the stock rather than any one order. They come from the
+// inventory, not the order service.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InventoryEvent {
+    // A reservation took `item` under the low-stock threshold: `remaining`
+    // are left. Published once on the way down, not for every reservation
+    // after it.
+    LowStock {
+        item: String,
+        remaining: u32,
+    },
+    // `added` units came in; `level` are now available.
+    Restocked {
+        item: String,
+        added: u32,
+        level: u32,
+    },
+    // Reservation number `reservation` was held too long, and its stock
+    // put back on the shelf.
+    ReservationExpired {
+        reservation: u32,
+    },
+}
+
+impl OrderEvent {
+    pub fn order_id(&self) -> OrderId {
+        match self {
+            OrderEvent::OrderPlaced { order_id, .. }
+            | OrderEvent::ApprovalRequested { order_id, .. }
+            | OrderEvent::ReviewRequested { order_id, .. }
+            | OrderEvent::OrderRejected { order_id, .. }
+            | OrderEvent::PaymentDeferred { order_id, .. }
+            | OrderEvent::ChargeSkipped { order_id, .. }
+            | OrderEvent::OrderPaid { order_id, .. }
+            | OrderEvent::OrderCancelled { order_id, .. }
+            | OrderEvent::ItemsShipped { order_id, .. }
+            | OrderEvent::OrderExpired { order_id }
+            | OrderEvent::OrdersMerged { order_id, .. } => *order_id,
+        }
+    }
+}
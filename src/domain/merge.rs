@@ -0,0 +1,113 @@
+// Two carts left open by the same shopper become one.
+//
+// Only orders still Placed, nothing charged nor parked, can be merged: once
+// money or an approver is involved, the order is no longer a cart. A line
+// is one unit here, so "consolidating" identical lines (same name, same
+// price) means keeping them together: the merged order lists each of the
+// absorbed lines right after its twins, and the quantity of an item is the
+// number of its lines. The total is summed again, checked.
+use super::{Order, OrderError, OrderStatus};
+
+impl Order {
+    // `other`'s items move into this order; `other` itself is left as is,
+    // for the caller to retire. On any error, this order is unchanged.
+    pub fn merge(&mut self, other: &Order) -> Result<(), OrderError> {
+        for order in [&*self, other] {
+            if order.status != OrderStatus::Placed {
+                return Err(OrderError::NotADraft { id: order.id });
+            }
+        }
+        if self.id == other.id {
+            return Err(OrderError::InvalidOrder);
+        }
+
+        let mut items = self.items.clone();
+        for item in &other.items {
+            match items.iter().rposition(|line| line == item) {
+                Some(last_twin) => items.insert(last_twin + 1, item.clone()),
+                None => items.push(item.clone()),
+            }
+        }
+        let total = Order::new(self.id, items.clone())?.total;
+
+        self.items = items;
+        self.total = total;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_err_variant;
+    use crate::domain::{LineItem, Money, OrderId};
+
+    fn item(name: &str, cents: u32) -> LineItem {
+        LineItem {
+            name: name.to_string(),
+            price: Money(cents),
+        }
+    }
+
+    fn order(id: u32, items: &[(&str, u32)]) -> Order {
+        let items = items
+            .iter()
+            .map(|&(name, cents)| item(name, cents))
+            .collect();
+        Order::new(OrderId(id), items).unwrap()
+    }
+
+    #[test]
+    fn identical_lines_end_up_together() {
+        let mut into = order(1, &[("Cable", 500), ("Mouse", 2500), ("Cable", 500)]);
+        let from = order(2, &[("Cable", 500), ("Keyboard", 12999), ("Mouse", 2400)]);
+
+        into.merge(&from).unwrap();
+
+        let names: Vec<(&str, u32)> = into
+            .items
+            .iter()
+            .map(|item| (item.name.as_str(), item.price.0))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("Cable", 500),
+                ("Mouse", 2500),
+                ("Cable", 500),
+                ("Cable", 500),
+                // Same name, other price: another item.
+                ("Keyboard", 12999),
+                ("Mouse", 2400),
+            ]
+        );
+        assert_eq!(into.total, Money(500 * 3 + 2500 + 12999 + 2400));
+        assert_eq!(into.id, OrderId(1));
+    }
+
+    #[test]
+    fn a_total_past_money_leaves_the_order_unchanged() {
+        let mut into = order(1, &[("Yacht", u32::MAX - 10)]);
+        let before = into.clone();
+
+        assert_err_variant!(
+            into.merge(&order(2, &[("Dinghy", 11)])),
+            OrderError::InvalidOrder
+        );
+        assert_eq!(into, before);
+        // One cent less still fits.
+        into.merge(&order(3, &[("Dinghy", 10)])).unwrap();
+        assert_eq!(into.total, Money(u32::MAX));
+    }
+
+    #[test]
+    fn only_placed_orders_merge() {
+        let mut paid = order(1, &[("Cable", 500)]);
+        paid.mark_paid().unwrap();
+        let mut into = order(2, &[("Cable", 500)]);
+
+        assert_err_variant!(into.merge(&paid), OrderError::NotADraft { id: OrderId(1) });
+        assert_err_variant!(paid.merge(&into), OrderError::NotADraft { id: OrderId(1) });
+        assert_err_variant!(into.clone().merge(&into), OrderError::InvalidOrder);
+    }
+}
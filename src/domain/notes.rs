@@ -0,0 +1,150 @@
+// Notes and attachments.
+// Whoever handles an order may annotate it: a note says who wrote what,
+// and when. Notes are only ever added, at most MAX_NOTES of them, each at
+// most MAX_NOTE_BYTES of UTF-8.
+//
+// A file attached to an order is not kept in it: a BlobStore keeps the
+// bytes, and the order only what to show of it, its BlobId, name and size.
+// Purging the order deletes its blobs.
+use super::{Order, OrderError, Timestamp};
+
+pub const MAX_NOTE_BYTES: usize = 1024;
+pub const MAX_NOTES: usize = 20;
+pub const MAX_ATTACHMENT_BYTES: usize = 1024 * 1024;
+// Longer names are cut, keeping their extension.
+const MAX_FILENAME_CHARS: usize = 100;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderNote {
+    pub author: String,
+    pub text: String,
+    pub at: Timestamp,
+}
+
+// What a BlobStore gave back for the bytes it keeps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct BlobId(pub u64);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attachment {
+    pub blob: BlobId,
+    // Sanitized: see sanitize_filename.
+    pub filename: String,
+    pub bytes: u64,
+}
+
+impl Order {
+    pub fn add_note(
+        &mut self,
+        author: impl Into<String>,
+        text: impl Into<String>,
+        at: Timestamp,
+    ) -> Result<(), OrderError> {
+        let text = text.into();
+        if text.len() > MAX_NOTE_BYTES {
+            return Err(OrderError::NoteTooLong {
+                bytes: text.len(),
+                limit: MAX_NOTE_BYTES,
+            });
+        }
+        if self.notes.len() >= MAX_NOTES {
+            return Err(OrderError::TooManyNotes { limit: MAX_NOTES });
+        }
+        self.notes.push(OrderNote {
+            author: author.into(),
+            text,
+            at,
+        });
+        Ok(())
+    }
+}
+
+// A name safe to show and to save a copy under: no directory, nothing
+// hidden, letters, digits, '.', '-' and '_' only, the rest made '_'.
+// "../../etc/passwd" is "passwd", "My invoice (2).pdf" is
+// "My_invoice__2_.pdf". A name with nothing left is "attachment".
+pub fn sanitize_filename(name: &str) -> String {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let safe: String = base
+        .trim()
+        .trim_start_matches('.')
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '.' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect();
+    if safe.is_empty() {
+        return "attachment".to_string();
+    }
+    if safe.len() <= MAX_FILENAME_CHARS {
+        return safe;
+    }
+    // All ASCII by now: bytes are characters.
+    match safe.rfind('.').filter(|&dot| safe.len() - dot <= 10) {
+        Some(dot) => {
+            let extension = &safe[dot..];
+            format!(
+                "{}{extension}",
+                &safe[..MAX_FILENAME_CHARS - extension.len()]
+            )
+        }
+        None => safe[..MAX_FILENAME_CHARS].to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, Money, OrderId};
+
+    fn order() -> Order {
+        Order::new(OrderId(1), vec![LineItem::new("Pen", Money(150))]).unwrap()
+    }
+
+    #[test]
+    fn notes_are_capped_in_size_and_number() {
+        let mut order = order();
+        let at = Timestamp(1_700_000_000);
+
+        assert!(
+            order
+                .add_note("ann", "x".repeat(MAX_NOTE_BYTES), at)
+                .is_ok()
+        );
+        assert!(matches!(
+            order.add_note("ann", "é".repeat(513), at),
+            Err(OrderError::NoteTooLong {
+                bytes: 1026,
+                limit: MAX_NOTE_BYTES
+            })
+        ));
+        for n in 1..MAX_NOTES {
+            order.add_note("bob", format!("note {n}"), at).unwrap();
+        }
+        assert!(matches!(
+            order.add_note("bob", "one more", at),
+            Err(OrderError::TooManyNotes { limit: MAX_NOTES })
+        ));
+        assert_eq!(order.notes.len(), MAX_NOTES);
+        assert_eq!(order.notes[1].text, "note 1");
+    }
+
+    #[test]
+    fn filenames_lose_their_directories_and_odd_characters() {
+        assert_eq!(sanitize_filename("invoice.pdf"), "invoice.pdf");
+        assert_eq!(sanitize_filename("../../etc/passwd"), "passwd");
+        assert_eq!(sanitize_filename(r"C:\Users\ann\scan 1.png"), "scan_1.png");
+        assert_eq!(
+            sanitize_filename("My invoice (2).pdf"),
+            "My_invoice__2_.pdf"
+        );
+        assert_eq!(sanitize_filename(".htaccess"), "htaccess");
+        assert_eq!(sanitize_filename("../"), "attachment");
+        assert_eq!(sanitize_filename("reçu.txt"), "re_u.txt");
+
+        let long = sanitize_filename(&format!("{}.jpeg", "a".repeat(200)));
+        assert_eq!(long.len(), 100);
+        assert!(long.ends_with("a.jpeg"));
+    }
+}
@@ -0,0 +1,171 @@
+// Ids that say nothing.
+// OrderId counts: #000042 tells a competitor how many orders were taken,
+// and two processes numbering on their own hand out the same ids. An order
+// may also carry a random UUID (version 4), safe to show outside and unique
+// without coordination. OrderId stays the key the application works with.
+use super::OrderId;
+use std::fmt;
+use std::str::FromStr;
+
+// 128 bits as two u64s, most significant first. Printed as the usual
+// 36 characters: 8-4-4-4-12 lowercase hex digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Uuid128 {
+    pub hi: u64,
+    pub lo: u64,
+}
+
+impl Uuid128 {
+    // 122 random bits, with the version (4) and variant (RFC 4122) bits set.
+    pub fn v4(random_hi: u64, random_lo: u64) -> Self {
+        Self {
+            hi: (random_hi & !0xf000) | 0x4000,
+            lo: (random_lo & !(0b11 << 62)) | (0b10 << 62),
+        }
+    }
+
+    pub fn version(&self) -> u8 {
+        ((self.hi >> 12) & 0xf) as u8
+    }
+}
+
+impl fmt::Display for Uuid128 {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(&format!(
+            "{:08x}-{:04x}-{:04x}-{:04x}-{:012x}",
+            self.hi >> 32,
+            (self.hi >> 16) & 0xffff,
+            self.hi & 0xffff,
+            self.lo >> 48,
+            self.lo & 0xffff_ffff_ffff
+        ))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseKeyError {
+    pub input: String,
+}
+
+impl fmt::Display for ParseKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} is neither an order number nor a UUID (8-4-4-4-12 hex digits)",
+            self.input
+        )
+    }
+}
+
+// Either case is accepted; braces, URNs and missing hyphens are not.
+impl FromStr for Uuid128 {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = || ParseKeyError {
+            input: s.to_string(),
+        };
+        let groups: Vec<&str> = s.split('-').collect();
+        let lengths: Vec<usize> = groups.iter().map(|group| group.len()).collect();
+        if lengths != [8, 4, 4, 4, 12] {
+            return Err(error());
+        }
+        let mut bits: u128 = 0;
+        for digit in groups.concat().chars() {
+            bits = bits << 4 | u128::from(digit.to_digit(16).ok_or_else(error)?);
+        }
+        Ok(Self {
+            hi: (bits >> 64) as u64,
+            lo: bits as u64,
+        })
+    }
+}
+
+// How an order is looked up from outside: by its number, or by its UUID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OrderKey {
+    Sequential(OrderId),
+    Random(Uuid128),
+}
+
+// #000042, or the 36-character UUID.
+impl fmt::Display for OrderKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            OrderKey::Sequential(id) => id.fmt(f),
+            OrderKey::Random(uuid) => uuid.fmt(f),
+        }
+    }
+}
+
+// The reverse of Display: "#000042" (or "42") and UUIDs.
+impl FromStr for OrderKey {
+    type Err = ParseKeyError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let digits = s.strip_prefix('#').unwrap_or(s);
+        if !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+            return digits
+                .parse()
+                .map(|n| OrderKey::Sequential(OrderId(n)))
+                .map_err(|_| ParseKeyError {
+                    input: s.to_string(),
+                });
+        }
+        s.parse().map(OrderKey::Random)
+    }
+}
+
+impl From<OrderId> for OrderKey {
+    fn from(id: OrderId) -> Self {
+        OrderKey::Sequential(id)
+    }
+}
+
+impl From<Uuid128> for OrderKey {
+    fn from(uuid: Uuid128) -> Self {
+        OrderKey::Random(uuid)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_and_from_str_round_trip() {
+        let uuid = Uuid128::v4(0x0123_4567_89ab_cdef, 0xfedc_ba98_7654_3210);
+        let text = uuid.to_string();
+
+        assert_eq!(text, "01234567-89ab-4def-bedc-ba9876543210");
+        assert_eq!(text.len(), 36);
+        assert_eq!(text.parse::<Uuid128>(), Ok(uuid));
+        assert_eq!(text.to_uppercase().parse::<Uuid128>(), Ok(uuid));
+    }
+
+    #[test]
+    fn malformed_uuids_are_refused() {
+        for bad in [
+            "",
+            "0123456789ab4def bedcba9876543210",
+            "0123456789ab4defbedcba9876543210",
+            "01234567-89ab-4def-bedc-ba987654321",
+            "01234567-89ab-4def-bedc-ba98765432100",
+            "0123456g-89ab-4def-bedc-ba9876543210",
+            "+1234567-89ab-4def-bedc-ba9876543210",
+        ] {
+            assert!(bad.parse::<Uuid128>().is_err(), "{bad:?}");
+        }
+    }
+
+    #[test]
+    fn keys_parse_from_what_they_display() {
+        let uuid = Uuid128::v4(1, 2);
+        for key in [OrderKey::Sequential(OrderId(42)), OrderKey::Random(uuid)] {
+            assert_eq!(key.to_string().parse::<OrderKey>(), Ok(key));
+        }
+        assert_eq!("42".parse(), Ok(OrderKey::Sequential(OrderId(42))));
+        assert!("#".parse::<OrderKey>().is_err());
+        assert!("#99999999999".parse::<OrderKey>().is_err());
+    }
+}
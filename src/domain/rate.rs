@@ -0,0 +1,313 @@
+// Rates as value objects.
+// A discount of "15" means nothing until you know whether it is 15 %, 15
+// basis points or 15 cents. These types make the unit part of the type and
+// keep the rounding rule in a single place: RoundingStrategy, to the minor
+// unit (the cent, the yen, the fils) of whatever currency the Money is in.
+// Half up unless the service, or the adapter, is told otherwise.
+use super::Money;
+use std::fmt;
+
+// Where an amount between two minor units goes.
+// HalfUp: 0.5 cent to 1, as on most receipts.
+// HalfEven: 0.5 cent to the even neighbour, so that over many ties as many
+// go down as up (banker's rounding).
+// Down: towards zero; never more than the exact amount.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum RoundingStrategy {
+    #[default]
+    HalfUp,
+    HalfEven,
+    Down,
+}
+
+impl RoundingStrategy {
+    // numerator / denominator, to a whole number. Every Money-scaling
+    // operation ends here, in u128 so that none of them can overflow on
+    // the way. A zero denominator is a bug: it panics.
+    pub fn divide(self, numerator: u128, denominator: u128) -> u128 {
+        let (quotient, remainder) = (numerator / denominator, numerator % denominator);
+        let up = match self {
+            RoundingStrategy::HalfUp => 2 * remainder >= denominator,
+            RoundingStrategy::HalfEven => {
+                2 * remainder > denominator || (2 * remainder == denominator && quotient % 2 == 1)
+            }
+            RoundingStrategy::Down => false,
+        };
+        quotient + u128::from(up)
+    }
+}
+
+impl Money {
+    // The share `rate` is of this amount, rounded with `strategy`.
+    // The rate is at most 100 %, so the result always fits back into Money.
+    pub fn scale(&self, rate: BasisPoints, strategy: RoundingStrategy) -> Money {
+        let scaled = u128::from(self.0) * u128::from(rate.0);
+        Money(strategy.divide(scaled, u128::from(BasisPoints::MAX)) as u32)
+    }
+}
+
+// 1 basis point = 0.01 %, so 10_000 basis points = 100 %.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct BasisPoints(u16);
+
+// A whole percentage, 0 to 100.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Percent(u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateOutOfRange {
+    pub value: u32,
+    pub max: u32,
+}
+
+impl fmt::Display for RateOutOfRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "rate {} is out of range 0..={}", self.value, self.max)
+    }
+}
+
+impl BasisPoints {
+    pub const MAX: u16 = 10_000;
+    pub const ZERO: BasisPoints = BasisPoints(0);
+    pub const FULL: BasisPoints = BasisPoints(Self::MAX);
+
+    pub fn value(self) -> u16 {
+        self.0
+    }
+
+    // The share of `amount` this rate represents, rounded half up to the
+    // minor unit: see Money::scale for the other strategies.
+    pub fn of(&self, amount: Money) -> Money {
+        amount.scale(*self, RoundingStrategy::HalfUp)
+    }
+}
+
+impl TryFrom<u32> for BasisPoints {
+    type Error = RateOutOfRange;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        if value > u32::from(Self::MAX) {
+            return Err(RateOutOfRange {
+                value,
+                max: u32::from(Self::MAX),
+            });
+        }
+        Ok(BasisPoints(value as u16))
+    }
+}
+
+impl fmt::Display for BasisPoints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{:02}%", self.0 / 100, self.0 % 100)
+    }
+}
+
+impl Percent {
+    pub const MAX: u8 = 100;
+
+    pub fn value(self) -> u8 {
+        self.0
+    }
+
+    pub fn of(&self, amount: Money) -> Money {
+        BasisPoints::from(*self).of(amount)
+    }
+}
+
+impl TryFrom<u32> for Percent {
+    type Error = RateOutOfRange;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        if value > u32::from(Self::MAX) {
+            return Err(RateOutOfRange {
+                value,
+                max: u32::from(Self::MAX),
+            });
+        }
+        Ok(Percent(value as u8))
+    }
+}
+
+// Every percentage is exactly representable in basis points.
+impl From<Percent> for BasisPoints {
+    fn from(percent: Percent) -> Self {
+        BasisPoints(u16::from(percent.0) * 100)
+    }
+}
+
+impl fmt::Display for Percent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}%", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bp(value: u32) -> BasisPoints {
+        BasisPoints::try_from(value).unwrap()
+    }
+
+    // A tiny xorshift so the "property" tests cover many inputs without a dependency.
+    fn pseudo_random(count: usize) -> impl Iterator<Item = u32> {
+        let mut state: u32 = 0x9e37_79b9;
+        std::iter::repeat_with(move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state
+        })
+        .take(count)
+    }
+
+    #[test]
+    fn construction_is_validated() {
+        assert_eq!(bp(10_000), BasisPoints::FULL);
+        assert_eq!(
+            BasisPoints::try_from(10_001),
+            Err(RateOutOfRange {
+                value: 10_001,
+                max: 10_000
+            })
+        );
+        assert!(Percent::try_from(100).is_ok());
+        assert_eq!(
+            Percent::try_from(101),
+            Err(RateOutOfRange {
+                value: 101,
+                max: 100
+            })
+        );
+    }
+
+    #[test]
+    fn rounding_is_half_up() {
+        // 15 % of 0.15 = 0.0225 -> 0.02
+        assert_eq!(bp(1500).of(Money(15)), Money(2));
+        // 50 % of 0.01 = 0.005 -> 0.01
+        assert_eq!(bp(5000).of(Money(1)), Money(1));
+        // 49.99 % of 0.01 = 0.004999 -> 0.00
+        assert_eq!(bp(4999).of(Money(1)), Money(0));
+        // 20 % of 49.99 = 9.998 -> 10.00
+        assert_eq!(Percent::try_from(20).unwrap().of(Money(4999)), Money(1000));
+    }
+
+    #[test]
+    fn rounding_is_to_the_minor_unit_whatever_it_is() {
+        // 15 % of ¥155 = ¥23.25 -> ¥23: no fraction of a yen is kept.
+        assert_eq!(bp(1500).of(Money(155)), Money(23));
+        // 10 % of KD 1.255 = KD 0.1255 -> KD 0.126, not 0.13.
+        assert_eq!(bp(1000).of(Money(1_255)), Money(126));
+    }
+
+    #[test]
+    fn share_never_exceeds_the_amount() {
+        for (amount, rate) in pseudo_random(10_000).zip(pseudo_random(10_000).skip(1)) {
+            let rate = bp(rate % 10_001);
+            assert!(rate.of(Money(amount)).0 <= amount, "{rate} of {amount}");
+        }
+        // The largest amount does not overflow the intermediate product.
+        assert_eq!(bp(5_000).of(Money(u32::MAX)), Money(u32::MAX / 2 + 1));
+    }
+
+    #[test]
+    fn full_rate_is_identity() {
+        for amount in pseudo_random(10_000).chain([0, 1, 3, 99, 12_345, u32::MAX]) {
+            assert_eq!(BasisPoints::FULL.of(Money(amount)), Money(amount));
+        }
+        assert_eq!(Percent::try_from(100).unwrap().of(Money(4999)), Money(4999));
+    }
+
+    #[test]
+    fn percent_and_basis_points_agree() {
+        for p in 0..=100 {
+            let percent = Percent::try_from(p).unwrap();
+            let as_bp = BasisPoints::from(percent);
+            assert_eq!(u32::from(as_bp.value()), p * 100);
+            for amount in [1, 15, 4999, 12_999] {
+                assert_eq!(percent.of(Money(amount)), as_bp.of(Money(amount)));
+            }
+        }
+    }
+
+    // Every strategy lands within one minor unit of the exact share.
+    // 2 * MAX * result against 2 * amount * rate keeps halves whole.
+    fn doubled_error(amount: u32, rate: BasisPoints, strategy: RoundingStrategy) -> i128 {
+        let max = i128::from(BasisPoints::MAX);
+        let exact = i128::from(amount) * i128::from(rate.value());
+        2 * (i128::from(Money(amount).scale(rate, strategy).0) * max - exact)
+    }
+
+    #[test]
+    fn down_never_exceeds_the_exact_share() {
+        let max = i128::from(BasisPoints::MAX);
+        for (amount, rate) in pseudo_random(10_000).zip(pseudo_random(10_000).skip(1)) {
+            let rate = bp(rate % 10_001);
+            let error = doubled_error(amount, rate, RoundingStrategy::Down);
+            assert!((-2 * max..=0).contains(&error), "{rate} of {amount}");
+        }
+    }
+
+    #[test]
+    fn half_up_and_half_even_stay_within_half_a_unit() {
+        let max = i128::from(BasisPoints::MAX);
+        for (amount, rate) in pseudo_random(10_000).zip(pseudo_random(10_000).skip(1)) {
+            let rate = bp(rate % 10_001);
+            for strategy in [RoundingStrategy::HalfUp, RoundingStrategy::HalfEven] {
+                let error = doubled_error(amount, rate, strategy);
+                assert!(error.abs() <= max, "{strategy:?}: {rate} of {amount}");
+            }
+            // Only a tie can tell them apart.
+            let (up, even) = (
+                Money(amount).scale(rate, RoundingStrategy::HalfUp),
+                Money(amount).scale(rate, RoundingStrategy::HalfEven),
+            );
+            if up != even {
+                assert_eq!(doubled_error(amount, rate, RoundingStrategy::HalfUp), max);
+            }
+        }
+    }
+
+    #[test]
+    fn half_even_is_unbiased_over_symmetric_ties() {
+        // Half of 0, 1, 2... 1999 cents: a tie for every odd amount.
+        let total_error = |strategy| {
+            (0..2_000)
+                .map(|amount| doubled_error(amount, bp(5_000), strategy))
+                .sum::<i128>()
+        };
+        let half_a_unit = i128::from(BasisPoints::MAX);
+
+        assert_eq!(total_error(RoundingStrategy::HalfEven), 0);
+        // HalfUp rounds every one of the 1000 ties up.
+        assert_eq!(total_error(RoundingStrategy::HalfUp), 1_000 * half_a_unit);
+        assert_eq!(total_error(RoundingStrategy::Down), -1_000 * half_a_unit);
+    }
+
+    #[test]
+    fn ties_go_to_the_even_neighbour() {
+        let half = bp(5_000);
+        for (amount, even) in [(1, 0), (3, 2), (5, 2), (7, 4)] {
+            assert_eq!(
+                Money(amount).scale(half, RoundingStrategy::HalfEven),
+                Money(even)
+            );
+        }
+        // Not a tie: 15 % of 0.15 = 0.0225 -> 0.02 either way.
+        assert_eq!(
+            Money(15).scale(bp(1500), RoundingStrategy::HalfEven),
+            Money(2)
+        );
+        assert_eq!(
+            Money(u32::MAX).scale(BasisPoints::FULL, RoundingStrategy::Down),
+            Money(u32::MAX)
+        );
+    }
+
+    #[test]
+    fn display() {
+        assert_eq!(bp(1234).to_string(), "12.34%");
+        assert_eq!(Percent::try_from(7).unwrap().to_string(), "7%");
+    }
+}
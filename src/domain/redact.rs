@@ -0,0 +1,186 @@
+// Redaction.
+// A customer's email, phone number and address are personal data, and
+// Debug output goes everywhere: error reports, panics, test failures,
+// transcripts. So the Debug of Customer, Contact and Address shows only
+// what is needed to tell them apart:
+//
+//     an email     j***@example.com   the first letter and the domain
+//     a phone      ***42              the last two digits
+//     an address   Lyon               the city
+//
+// Redact gives the same, as one line for a message. Built with the
+// full-debug feature, debug_full() shows everything, for local debugging.
+use super::{Address, Contact, Customer};
+use std::fmt;
+
+pub trait Redact {
+    fn redacted(&self) -> String;
+}
+
+// Nothing of the mailbox but its first character.
+fn email(email: &str) -> String {
+    match email.split_once('@') {
+        Some((mailbox, domain)) => {
+            let first: String = mailbox.chars().take(1).collect();
+            format!("{first}***@{domain}")
+        }
+        None => "***".to_string(),
+    }
+}
+
+// The last two digits, when there are more than two.
+fn phone(phone: &str) -> String {
+    let digits: Vec<char> = phone.chars().filter(char::is_ascii_digit).collect();
+    match digits.len() {
+        0..=2 => "***".to_string(),
+        n => format!("***{}", digits[n - 2..].iter().collect::<String>()),
+    }
+}
+
+impl Redact for Contact {
+    // "j***@example.com, ***42", as much of it as there is.
+    fn redacted(&self) -> String {
+        let email = self.email.as_deref().map(email);
+        let phone = self.phone.as_deref().map(phone);
+        email
+            .into_iter()
+            .chain(phone)
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+}
+
+impl Redact for Address {
+    fn redacted(&self) -> String {
+        self.city.clone()
+    }
+}
+
+impl Redact for Customer {
+    // "ann (j***@example.com, ***42, Lyon)", or "ann" alone.
+    fn redacted(&self) -> String {
+        let details: Vec<String> = [self.contact.redacted()]
+            .into_iter()
+            .chain(self.address.as_ref().map(Redact::redacted))
+            .filter(|detail| !detail.is_empty())
+            .collect();
+        if details.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} ({})", self.name, details.join(", "))
+        }
+    }
+}
+
+impl fmt::Debug for Contact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Contact")
+            .field("email", &self.email.as_deref().map(email))
+            .field("phone", &self.phone.as_deref().map(phone))
+            .finish()
+    }
+}
+
+impl fmt::Debug for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Address")
+            .field("city", &self.city)
+            .finish_non_exhaustive()
+    }
+}
+
+impl fmt::Debug for Customer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Customer")
+            .field("name", &self.name)
+            .field("contact", &self.contact)
+            .field("address", &self.address)
+            .finish()
+    }
+}
+
+// What a derived Debug would show.
+#[cfg(feature = "full-debug")]
+impl Contact {
+    pub fn debug_full(&self) -> String {
+        format!(
+            "Contact {{ email: {:?}, phone: {:?} }}",
+            self.email, self.phone
+        )
+    }
+}
+
+#[cfg(feature = "full-debug")]
+impl Address {
+    pub fn debug_full(&self) -> String {
+        format!(
+            "Address {{ street: {:?}, city: {:?}, postal_code: {:?}, country: {:?} }}",
+            self.street, self.city, self.postal_code, self.country
+        )
+    }
+}
+
+#[cfg(feature = "full-debug")]
+impl Customer {
+    pub fn debug_full(&self) -> String {
+        let address = match &self.address {
+            Some(address) => format!("Some({})", address.debug_full()),
+            None => "None".to_string(),
+        };
+        format!(
+            "Customer {{ name: {:?}, contact: {}, address: {address} }}",
+            self.name,
+            self.contact.debug_full()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn jane() -> Customer {
+        Customer::new("Jane")
+            .with_email("jane.doe@example.com")
+            .with_phone("+33 6 12 34 56 78")
+            .with_address(Address {
+                street: "12 rue de la Paix".to_string(),
+                city: "Lyon".to_string(),
+                postal_code: "69001".to_string(),
+                country: "FR".to_string(),
+            })
+    }
+
+    #[test]
+    fn emails_and_phones_keep_just_enough() {
+        assert_eq!(email("jane.doe@example.com"), "j***@example.com");
+        assert_eq!(email("@example.com"), "***@example.com");
+        assert_eq!(email("not an email"), "***");
+        assert_eq!(phone("+33 6 12 34 56 78"), "***78");
+        assert_eq!(phone("12"), "***");
+    }
+
+    #[test]
+    fn debug_and_redacted_show_no_personal_data() {
+        let jane = jane();
+
+        assert_eq!(
+            format!("{jane:?}"),
+            "Customer { name: \"Jane\", contact: Contact { email: Some(\"j***@example.com\"), \
+             phone: Some(\"***78\") }, address: Some(Address { city: \"Lyon\", .. }) }"
+        );
+        assert_eq!(jane.redacted(), "Jane (j***@example.com, ***78, Lyon)");
+        assert_eq!(Customer::guest().redacted(), "guest");
+        let pretty = format!("{jane:#?}");
+        assert!(!pretty.contains("jane.doe") && !pretty.contains("rue de la Paix"));
+    }
+
+    #[cfg(feature = "full-debug")]
+    #[test]
+    fn debug_full_shows_everything() {
+        let full = jane().debug_full();
+
+        assert!(full.contains("\"jane.doe@example.com\""));
+        assert!(full.contains("street: \"12 rue de la Paix\""));
+    }
+}
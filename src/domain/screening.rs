@@ -0,0 +1,59 @@
+// Fraud screening.
+// Before an order is charged, a FraudScreen may look at it and at who is
+// buying. A suspicious order is refused outright; a doubtful one waits,
+// UnderReview and uncharged, until someone resolves the review: accepted,
+// it is charged then; rejected, it is cancelled.
+//
+// The contact details and the address are personal data: their Debug
+// leaves most of them out (see redact.rs).
+use super::Address;
+
+// Who places an order, as far as screening is concerned.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Customer {
+    pub name: String,
+    pub contact: Contact,
+    pub address: Option<Address>,
+}
+
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Contact {
+    pub email: Option<String>,
+    pub phone: Option<String>,
+}
+
+impl Customer {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            contact: Contact::default(),
+            address: None,
+        }
+    }
+
+    // Orders placed without saying who for.
+    pub fn guest() -> Self {
+        Self::new("guest")
+    }
+
+    pub fn with_email(mut self, email: impl Into<String>) -> Self {
+        self.contact.email = Some(email.into());
+        self
+    }
+
+    pub fn with_phone(mut self, phone: impl Into<String>) -> Self {
+        self.contact.phone = Some(phone.into());
+        self
+    }
+
+    pub fn with_address(mut self, address: Address) -> Self {
+        self.address = Some(address);
+        self
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ReviewDecision {
+    Accept,
+    Reject { reason: String },
+}
@@ -0,0 +1,165 @@
+// Shipping: an order may leave the warehouse in several parcels.
+// Each Shipment covers some of the order's items, identified by their index
+// in Order::items. The order is Shipped once every item is covered.
+use super::{Money, Order, OrderError, OrderStatus, Timestamp};
+
+// The carrier's reference for one parcel.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct TrackingId(pub String);
+
+// Debug shows the city alone (see redact.rs).
+#[derive(Clone, PartialEq, Eq)]
+pub struct Address {
+    pub street: String,
+    pub city: String,
+    pub postal_code: String,
+    pub country: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shipment {
+    pub tracking: TrackingId,
+    pub item_indices: Vec<usize>,
+    pub shipped_at: Timestamp,
+}
+
+impl Order {
+    // Business rules for a new shipment, checked before anything is sent:
+    // - only a paid order ships
+    // - at least one item, every index in range
+    // - no item ships twice, neither across shipments nor within this one
+    pub fn check_shippable(&self, item_indices: &[usize]) -> Result<(), OrderError> {
+        if !self.status.can_become(OrderStatus::Shipped) {
+            return Err(OrderError::InvalidTransition {
+                from: self.status,
+                to: OrderStatus::Shipped,
+            });
+        }
+        if item_indices.is_empty() {
+            return Err(OrderError::InvalidOrder);
+        }
+        for (position, &index) in item_indices.iter().enumerate() {
+            if index >= self.items.len() {
+                return Err(OrderError::InvalidItemIndex { index });
+            }
+            if self.is_item_shipped(index) || item_indices[..position].contains(&index) {
+                return Err(OrderError::AlreadyShipped { index });
+            }
+        }
+        Ok(())
+    }
+
+    pub fn record_shipment(&mut self, shipment: Shipment) -> Result<(), OrderError> {
+        self.check_shippable(&shipment.item_indices)?;
+        self.shipments.push(shipment);
+        if (0..self.items.len()).all(|index| self.is_item_shipped(index)) {
+            self.transition_to(OrderStatus::Shipped)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_item_shipped(&self, index: usize) -> bool {
+        self.shipments
+            .iter()
+            .any(|shipment| shipment.item_indices.contains(&index))
+    }
+
+    // What the items not shipped yet were charged: the whole total before
+    // the first shipment, nothing once the last one is out.
+    pub fn unshipped_charge(&self) -> Result<Money, OrderError> {
+        let charged = self.charged_per_item()?;
+        Ok(Money(
+            charged
+                .iter()
+                .enumerate()
+                .filter(|&(index, _)| !self.is_item_shipped(index))
+                .map(|(_, share)| share.0)
+                .sum(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_err_variant;
+    use crate::domain::{LineItem, Money, OrderId};
+
+    fn paid_order(item_count: usize) -> Order {
+        let items = (0..item_count)
+            .map(|i| LineItem {
+                name: format!("Item {i}"),
+                price: Money(100),
+            })
+            .collect();
+        let mut order = Order::new(OrderId(1), items).unwrap();
+        order.mark_paid().unwrap();
+        order
+    }
+
+    fn shipment(indices: &[usize]) -> Shipment {
+        Shipment {
+            tracking: TrackingId("TRK".to_string()),
+            item_indices: indices.to_vec(),
+            shipped_at: Timestamp(0),
+        }
+    }
+
+    #[test]
+    fn duplicate_index_within_one_shipment_is_rejected() {
+        let order = paid_order(3);
+
+        assert_err_variant!(
+            order.check_shippable(&[2, 0, 2]),
+            OrderError::AlreadyShipped { index: 2 }
+        );
+    }
+
+    #[test]
+    fn unpaid_order_cannot_ship() {
+        let order = Order::new(
+            OrderId(1),
+            vec![LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            }],
+        )
+        .unwrap();
+
+        assert_err_variant!(
+            order.check_shippable(&[0]),
+            OrderError::InvalidTransition {
+                from: OrderStatus::Placed,
+                to: OrderStatus::Shipped
+            }
+        );
+    }
+
+    #[test]
+    fn empty_shipment_is_rejected() {
+        assert_err_variant!(paid_order(1).check_shippable(&[]), OrderError::InvalidOrder);
+    }
+
+    #[test]
+    fn only_what_has_not_shipped_is_still_charged_for() {
+        let mut order = paid_order(3);
+        assert_eq!(order.unshipped_charge().unwrap(), Money(300));
+
+        order.record_shipment(shipment(&[1])).unwrap();
+        assert_eq!(order.unshipped_charge().unwrap(), Money(200));
+        order.record_shipment(shipment(&[0, 2])).unwrap();
+        assert_eq!(order.unshipped_charge().unwrap(), Money(0));
+    }
+
+    #[test]
+    fn shipped_order_is_closed() {
+        let mut order = paid_order(1);
+        order.record_shipment(shipment(&[0])).unwrap();
+
+        assert_eq!(order.status, OrderStatus::Shipped);
+        assert_err_variant!(
+            order.record_shipment(shipment(&[0])),
+            OrderError::InvalidTransition { .. }
+        );
+    }
+}
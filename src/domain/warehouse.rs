@@ -0,0 +1,14 @@
+// Warehouses: the same item may sit on several shelves, in several places.
+// Which one a line item is taken from is a WarehousePicker's call; what
+// the domain knows is only the warehouse's id and how much of the item it
+// still has.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WarehouseId(pub u32);
+
+// Units of one item a warehouse can still give, neither reserved nor sold.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WarehouseStock {
+    pub warehouse: WarehouseId,
+    pub available: u32,
+}
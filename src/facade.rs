@@ -0,0 +1,116 @@
+// =============================================================================
+// FACADE - The whole service behind one object
+// =============================================================================
+// A GUI or a TUI wants one thing to hold, and to call from a button's
+// callback: ports::OrderFacade, every command and query through &self.
+// DefaultFacade puts it in front of the services the crate already has,
+// a command side and a query side:
+//
+//     let service = OrderService::new(&repo, &payment, &sender);
+//     let facade = DefaultFacade::new(service, OrderBrowser::new(&repo)).shared();
+//
+// The use cases take &mut: the command side is kept behind a Mutex, and
+// commands run one at a time, in the order they come. Queries do not wait
+// for it: the query side only reads, through its own &.
+//
+// composition::build_facade does the same for the adapters a composition
+// root built, in one call.
+use crate::domain::{CurrencyTotals, Money, Order, OrderError, OrderId};
+use crate::ports::{
+    AmendOrder, AmendOrderUseCase, CancelOrderUseCase, CancelToken, Freshness, OrderFacade,
+    OrderPreview, OrderQueries, PlaceOrder, PlaceOrderUseCase,
+};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+pub struct DefaultFacade<C, Q> {
+    commands: Mutex<C>,
+    queries: Q,
+}
+
+impl<C, Q> DefaultFacade<C, Q>
+where
+    C: PlaceOrderUseCase + AmendOrderUseCase + CancelOrderUseCase,
+    Q: OrderQueries,
+{
+    pub fn new(commands: C, queries: Q) -> Self {
+        Self {
+            commands: Mutex::new(commands),
+            queries,
+        }
+    }
+
+    // For every callback that needs it, from any thread.
+    pub fn shared<'a>(self) -> Arc<dyn OrderFacade + Send + Sync + 'a>
+    where
+        C: Send + 'a,
+        Q: Send + Sync + 'a,
+    {
+        Arc::new(self)
+    }
+
+    pub fn into_inner(self) -> (C, Q) {
+        let commands = self
+            .commands
+            .into_inner()
+            .unwrap_or_else(PoisonError::into_inner);
+        (commands, self.queries)
+    }
+
+    // A command that panicked does not take the facade down with it: the
+    // next one runs on the service as that one left it.
+    fn commands(&self) -> MutexGuard<'_, C> {
+        self.commands.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<C, Q> OrderFacade for DefaultFacade<C, Q>
+where
+    C: PlaceOrderUseCase + AmendOrderUseCase + CancelOrderUseCase,
+    Q: OrderQueries,
+{
+    fn place_order(&self, command: PlaceOrder) -> Result<Order, OrderError> {
+        self.commands().place_order(command)
+    }
+
+    fn amend_order(&self, command: AmendOrder) -> Result<Order, OrderError> {
+        self.commands().amend_order(command)
+    }
+
+    fn cancel_order(&self, id: OrderId) -> Result<Order, OrderError> {
+        self.commands().cancel_order(id)
+    }
+
+    // The order the domain would make of the cart, under an id nobody has.
+    fn preview_order(&self, command: &PlaceOrder) -> Result<OrderPreview, OrderError> {
+        let order = Order::new(OrderId(0), command.items.clone())?;
+        Ok(OrderPreview {
+            item_count: order.items.len(),
+            total: order.total,
+            currency: order.currency,
+        })
+    }
+
+    fn list_orders(&self) -> Result<Vec<Order>, OrderError> {
+        self.queries.list_orders()
+    }
+
+    fn revenue(&self) -> Result<Money, OrderError> {
+        self.queries.revenue()
+    }
+
+    fn revenue_cancellable(&self, cancel: &CancelToken) -> Result<Money, OrderError> {
+        self.queries.revenue_cancellable(cancel)
+    }
+
+    fn revenue_by_currency(&self) -> Result<CurrencyTotals, OrderError> {
+        self.queries.revenue_by_currency()
+    }
+
+    fn freshness(&self) -> Result<Freshness, OrderError> {
+        self.queries.freshness()
+    }
+
+    fn health(&self) -> Result<(), OrderError> {
+        self.queries.health()
+    }
+}
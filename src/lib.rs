@@ -0,0 +1,30 @@
+// hexa_lite - the hexagon of ex07 as a library
+//
+// The examples in examples/ are self-contained on purpose: each one can be read
+// top to bottom next to the article. Once the hexagon grows beyond a single
+// file it needs a home of its own, and this crate is that home.
+//
+// The layering is the same as in ex07:
+// - domain      : business vocabulary and rules, no traits, no infrastructure
+// - ports       : what the application needs from the outside world
+// - application : use cases orchestrating the domain through the ports
+// - adapters    : concrete implementations living at the edge
+// - composition : picks the adapters from the environment (EnvConfig)
+// - facade      : the whole service as one object, for a GUI or a TUI
+// - testing     : helpers for the tests of this crate and of its users
+// - tutorial    : checkpoints for examples/ex15, which assembles the hexagon
+// - scenarios   : the demos by name, listed and run by the hexlite binary
+//
+// Most users only need `use hexa_lite::prelude::*;`
+
+pub mod adapters;
+pub mod application;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod composition;
+pub mod domain;
+pub mod facade;
+pub mod ports;
+pub mod prelude;
+pub mod scenarios;
+pub mod testing;
+pub mod tutorial;
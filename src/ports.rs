@@ -0,0 +1,2739 @@
+// =============================================================================
+// PORTS - What the Domain Needs From the Outside World
+// =============================================================================
+// Ports are abstractions defined by the application/domain.
+// They describe required capabilities, not implementations.
+//
+// Every trait here may be implemented in another crate, with nothing but
+// the public API: tests/external_impl.rs does it for each of them. The
+// exceptions are sealed, and say so in their declaration: ChargeConfirmed
+// and SendConfirmed come with every PaymentGateway and Sender, and there
+// is nothing else to implement.
+//
+// Some of them are also described in ports.toml, from which the build
+// generates `generated`: traits of the same shape, with test doubles.
+use crate::domain::{
+    Address, BlobId, ConfirmedOrder, Currency, CurrencyTotals, Customer, InventoryEvent, LineItem,
+    Money, Notice, Order, OrderConfirmation, OrderError, OrderEvent, OrderId, OrderKey,
+    OrderReference, OrderStatus, Price, SagaId, StoredOrder, Timestamp, TrackingId, Uuid128,
+    WarehouseId, WarehouseStock,
+};
+use std::fmt;
+use std::hash::{BuildHasher, RandomState};
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+pub mod generated;
+
+// Every port describes itself: its name, which side of the hexagon it is
+// on, and what can be called through it. composition::hexagon_diagram
+// draws the hexagon from these, so the picture follows the code.
+//
+// The method lists are written by hand, next to each trait: keep them in
+// step when a port changes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    // Driving side: the outside world calls the application through it.
+    Inbound,
+    // Driven side: the application calls the outside world through it.
+    Outbound,
+}
+
+pub trait PortInfo {
+    fn port_name() -> &'static str;
+    fn direction() -> Direction;
+    fn methods() -> &'static [&'static str];
+}
+
+// PortInfo, read once into plain data.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortSpec {
+    pub name: &'static str,
+    pub direction: Direction,
+    pub methods: &'static [&'static str],
+}
+
+impl PortSpec {
+    pub fn of<P: ?Sized + PortInfo>() -> Self {
+        PortSpec {
+            name: P::port_name(),
+            direction: P::direction(),
+            methods: P::methods(),
+        }
+    }
+}
+
+//     port_info!(Clock, Outbound, [now]);
+macro_rules! port_info {
+    ($port:ident, $direction:ident, [$($method:ident),* $(,)?]) => {
+        impl PortInfo for dyn $port {
+            fn port_name() -> &'static str {
+                stringify!($port)
+            }
+
+            fn direction() -> Direction {
+                Direction::$direction
+            }
+
+            fn methods() -> &'static [&'static str] {
+                &[$(stringify!($method)),*]
+            }
+        }
+    };
+}
+
+// Every port of the hexagon, inbound first.
+pub fn all_ports() -> Vec<PortSpec> {
+    vec![
+        PortSpec::of::<dyn PlaceOrderUseCase>(),
+        PortSpec::of::<dyn CancelOrderUseCase>(),
+        PortSpec::of::<dyn AmendOrderUseCase>(),
+        PortSpec::of::<dyn OrderQueries>(),
+        PortSpec::of::<dyn OrderFacade>(),
+        PortSpec::of::<dyn OrderReader>(),
+        PortSpec::of::<dyn OrderWriter>(),
+        PortSpec::of::<dyn PaymentGateway>(),
+        PortSpec::of::<dyn ChargeLog>(),
+        PortSpec::of::<dyn ExchangeRates>(),
+        PortSpec::of::<dyn Sender>(),
+        PortSpec::of::<dyn DraftRepository>(),
+        PortSpec::of::<dyn ShippingProvider>(),
+        PortSpec::of::<dyn Inventory>(),
+        PortSpec::of::<dyn WarehousePicker>(),
+        PortSpec::of::<dyn ApprovalPolicy>(),
+        PortSpec::of::<dyn NotificationPolicy>(),
+        PortSpec::of::<dyn FraudScreen>(),
+        PortSpec::of::<dyn ConflictResolver>(),
+        PortSpec::of::<dyn RetentionPolicy>(),
+        PortSpec::of::<dyn Clock>(),
+        PortSpec::of::<dyn IdGenerator>(),
+        PortSpec::of::<dyn ReferenceGenerator>(),
+        PortSpec::of::<dyn EventPublisher>(),
+        PortSpec::of::<dyn EventSubscriber>(),
+        PortSpec::of::<dyn StateStore>(),
+        PortSpec::of::<dyn PendingCharges>(),
+        PortSpec::of::<dyn DeadLetterSink>(),
+        PortSpec::of::<dyn SagaLog>(),
+        PortSpec::of::<dyn IdempotencyStore>(),
+        PortSpec::of::<dyn BlobStore>(),
+        PortSpec::of::<dyn Metrics>(),
+        PortSpec::of::<dyn ErrorReporter>(),
+        PortSpec::of::<dyn Logger>(),
+    ]
+}
+
+// Output port: persistence because "I need to store orders somewhere"
+// Could be PostgreSQL, MongoDB, a file, Redis... domain doesn't care.
+//
+// Split in two. OrderReader is everything that only looks; OrderWriter
+// adds the writes on top of it. The query side (OrderBrowser,
+// Reconciliation...) asks for a reader, so a report cannot save an order
+// even by mistake: it does not compile. OrderService asks for a writer.
+// Every repository adapter implements both.
+//
+// Every method takes &self, writes included. A connection pool or an HTTP
+// client already writes through a shared reference; a map in memory has
+// to hide behind a Mutex to do the same. That lock is the price: in return
+// the services borrow their repository with a plain &, so two of them, or
+// a service and a reader, can hold the same one at once. The writes are
+// then only as atomic as the adapter makes them: the default update is a
+// find then a save, and an adapter shared between threads should override
+// it with one step.
+/// # Examples
+///
+/// A query only gets to look:
+///
+/// ```compile_fail
+/// use hexa_lite::ports::OrderReader;
+/// use hexa_lite::prelude::*;
+///
+/// fn mark_all_shipped(orders: &impl OrderReader) -> Result<(), OrderError> {
+///     for mut order in orders.list()? {
+///         order.status = OrderStatus::Shipped;
+///         orders.save(&order)?; // no `save` on a reader
+///     }
+///     Ok(())
+/// }
+/// ```
+pub trait OrderReader {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError>;
+    // Every stored order, by ascending id.
+    // The ordering is part of the contract, whatever order the orders were
+    // saved in and however the adapter stores them: exports, reports and
+    // golden transcripts print what comes out of here, and must not change
+    // from one run to the next.
+    fn list(&self) -> Result<Vec<Order>, OrderError>;
+
+    // Lightweight lookups for callers that do not need the items.
+    // The defaults go through find(), so every adapter has them; adapters
+    // that can answer without materializing the order should override them.
+    fn exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        Ok(self.find(id)?.is_some())
+    }
+
+    fn total_of(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        Ok(self.find(id)?.map(|order| order.total))
+    }
+
+    // Visits every stored order, by ascending id like list(), without
+    // handing out copies: callers keep only what they need.
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        for order in self.list()? {
+            visit(&order);
+        }
+        Ok(())
+    }
+
+    // The same visit, looking at `cancel` before each order: once it is
+    // cancelled, the scan stops with Cancelled and how many were visited.
+    fn for_each_cancellable(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        for (processed, order) in self.list()?.iter().enumerate() {
+            cancel.check(processed)?;
+            visit(order);
+        }
+        Ok(())
+    }
+
+    // The deleted orders, by ascending id like list(). None for adapters
+    // that cannot keep deleted orders aside.
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        Ok(Vec::new())
+    }
+
+    // By number, or by the UUID the order was given. The default scans
+    // every order for a UUID; adapters keeping an index should override it.
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        match key {
+            OrderKey::Sequential(id) => self.find(id),
+            OrderKey::Random(uuid) => Ok(self
+                .list()?
+                .into_iter()
+                .find(|order| order.uuid == Some(uuid))),
+        }
+    }
+
+    // By the reference it was given when placed: see ReferenceGenerator.
+    // The default scans every order, like find_by_key for a UUID.
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        Ok(self
+            .list()?
+            .into_iter()
+            .find(|order| order.reference.as_ref() == Some(reference)))
+    }
+}
+
+port_info!(
+    OrderReader,
+    Outbound,
+    [
+        find,
+        list,
+        exists,
+        total_of,
+        for_each,
+        for_each_cancellable,
+        list_deleted,
+        find_by_key,
+        find_by_reference
+    ]
+);
+
+// The writes, on top of everything a reader can do.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::ports::{OrderReader, OrderWriter};
+/// use hexa_lite::prelude::*;
+/// use std::collections::BTreeMap;
+/// use std::sync::Mutex;
+///
+/// // A BTreeMap lists by ascending id, as the contract asks.
+/// #[derive(Default)]
+/// struct MapRepository(Mutex<BTreeMap<OrderId, Order>>);
+///
+/// impl OrderReader for MapRepository {
+///     fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+///         Ok(self.0.lock().unwrap().get(&id).cloned())
+///     }
+///
+///     fn list(&self) -> Result<Vec<Order>, OrderError> {
+///         Ok(self.0.lock().unwrap().values().cloned().collect())
+///     }
+/// }
+///
+/// impl OrderWriter for MapRepository {
+///     fn save(&self, order: &Order) -> Result<(), OrderError> {
+///         self.0.lock().unwrap().insert(order.id, order.clone());
+///         Ok(())
+///     }
+/// }
+///
+/// let repo = MapRepository::default();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service = OrderService::new(&repo, &payment, &sender);
+///
+/// let order = service.place_order(vec![LineItem::new("Pen", Money(150))])?;
+/// assert_eq!(repo.find(order.id)?, Some(order));
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait OrderWriter: OrderReader {
+    fn save(&self, order: &Order) -> Result<(), OrderError>;
+
+    // Optimistic concurrency: `order.version` is the version it was read
+    // at. The write only happens if the stored order still has it, and
+    // stores the order with the next version.
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        let id = order.id;
+        let stored = self.find(id)?.ok_or(OrderError::NotFound { id })?;
+        if stored.version != order.version {
+            return Err(OrderError::VersionConflict {
+                id,
+                expected: order.version,
+                found: stored.version,
+            });
+        }
+        let mut next = order.clone();
+        next.version += 1;
+        self.save(&next)
+    }
+
+    // Reversible deletion. A deleted order is gone from find, list and the
+    // lookups of OrderReader, until restore brings it back unchanged.
+    // Deleting an order twice is fine; deleting an unknown one is NotFound.
+    // The defaults are for adapters that cannot keep deleted orders aside:
+    // they refuse.
+    fn soft_delete(&self, _id: OrderId) -> Result<(), OrderError> {
+        Err(OrderError::StorageFailed)
+    }
+
+    // NotDeleted for an order that was never deleted, NotFound for an id
+    // the repository does not hold at all.
+    fn restore(&self, _id: OrderId) -> Result<(), OrderError> {
+        Err(OrderError::StorageFailed)
+    }
+
+    // Deletion for good, of an order deleted or not: nothing restores it.
+    // NotFound for an id the repository does not hold at all. Refused by
+    // default too, like soft_delete.
+    fn purge(&self, _id: OrderId) -> Result<(), OrderError> {
+        Err(OrderError::StorageFailed)
+    }
+}
+
+port_info!(
+    OrderWriter,
+    Outbound,
+    [save, update, soft_delete, restore, purge]
+);
+
+// Output port: payment processing because "I need to charge customers"
+// Could be Stripe, PayPal, a mock for testing... domain doesn't care.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::prelude::*;
+///
+/// // Declines anything above $100.
+/// struct PrepaidCard;
+///
+/// impl PaymentGateway for PrepaidCard {
+///     fn charge(&self, amount: Money) -> Result<(), OrderError> {
+///         if amount.0 > 10_000 {
+///             return Err(OrderError::PaymentFailed);
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let mut repo = InMemoryOrderRepository::new();
+/// let sender = ConsoleSender::new();
+/// let mut service = OrderService::new(&repo, &PrepaidCard, &sender);
+///
+/// assert!(service.place_order(vec![LineItem::new("Pen", Money(150))]).is_ok());
+/// let laptop = service.place_order(vec![LineItem::new("Laptop", Money(129_999))]);
+/// assert!(matches!(laptop, Err(OrderError::PaymentFailed)));
+/// ```
+pub trait PaymentGateway {
+    fn charge(&self, amount: Money) -> Result<(), OrderError>;
+
+    // Charging now, on behalf of a given order: what the services call.
+    // Gateways keeping a ChargeLog override it to note the order id.
+    fn charge_for(&self, _order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.charge(amount)
+    }
+
+    // What place_order calls. A gateway able to work offline may accept the
+    // charge without making it yet, and answer Deferred; the order then
+    // waits for OrderService::settle_pending. Every other gateway just
+    // charges.
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.charge_for(order_id, amount)
+            .map(|()| ChargeOutcome::Charged)
+    }
+
+    // Giving back what charge_for took for an order, all of it or a part:
+    // cancelling a partly shipped order refunds only the items left. The
+    // default is for gateways that cannot refund: they refuse.
+    fn refund_for(&self, _order_id: OrderId, _amount: Money) -> Result<(), OrderError> {
+        Err(OrderError::PaymentFailed)
+    }
+}
+
+port_info!(
+    PaymentGateway,
+    Outbound,
+    [charge, charge_for, charge_order, refund_for]
+);
+
+// `|amount| Ok(())`: a gateway taking whatever it is asked. Refunds are
+// refused, as by default.
+impl<F> PaymentGateway for F
+where
+    F: Fn(Money) -> Result<(), OrderError>,
+{
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self(amount)
+    }
+}
+
+// One charge the payment provider made, as its records show it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChargeRecord {
+    pub order_id: OrderId,
+    pub amount: Money,
+}
+
+// Output port: "what did the provider actually charge?"
+// The other side of reconciliation: a payment provider's report, a
+// settlement file... Only charges made through charge_for (or
+// charge_order) are known by order.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::application::Reconciliation;
+/// use hexa_lite::ports::{ChargeLog, ChargeRecord};
+/// use hexa_lite::prelude::*;
+///
+/// // The provider's monthly statement.
+/// struct Statement(Vec<ChargeRecord>);
+///
+/// impl ChargeLog for Statement {
+///     fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+///         Ok(self.0.clone())
+///     }
+/// }
+///
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let order = OrderService::new(&repo, &payment, &sender)
+///     .place_order(vec![LineItem::new("Pen", Money(150))])?;
+///
+/// let statement = Statement(vec![ChargeRecord {
+///     order_id: order.id,
+///     amount: order.total,
+/// }]);
+/// assert!(Reconciliation::new(&repo, &statement).reconcile()?.is_consistent());
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait ChargeLog {
+    // Oldest first.
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError>;
+}
+
+port_info!(ChargeLog, Outbound, [charges]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChargeOutcome {
+    Charged,
+    Deferred,
+}
+
+// Output port: "how much is this in that currency?"
+// Could be a bank feed, an FX API, a table in the config...
+/// # Examples
+///
+/// ```
+/// use hexa_lite::domain::{Currency, ForeignLineItem, Price};
+/// use hexa_lite::ports::ExchangeRates;
+/// use hexa_lite::prelude::*;
+///
+/// // One euro is always 1.10 dollars.
+/// struct FixedRate;
+///
+/// impl ExchangeRates for FixedRate {
+///     fn convert(&self, from: Price, to: Currency) -> Result<Money, OrderError> {
+///         match (from.currency, to) {
+///             (Currency::Eur, Currency::Usd) => Ok(Money(from.amount.0 * 110 / 100)),
+///             (from, to) => Err(OrderError::NoExchangeRate { from, to }),
+///         }
+///     }
+/// }
+///
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service = OrderService::new(&repo, &payment, &sender).with_exchange_rates(&FixedRate);
+///
+/// let mug = ForeignLineItem {
+///     name: "Mug".to_string(),
+///     price: Price {
+///         amount: Money(1000),
+///         currency: Currency::Eur,
+///     },
+/// };
+/// let converted = service.place_order_in(Currency::Usd, vec![mug])?;
+/// assert_eq!(converted.order.total, Money(1100));
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait ExchangeRates {
+    fn convert(&self, from: Price, to: Currency) -> Result<Money, OrderError>;
+}
+
+port_info!(ExchangeRates, Outbound, [convert]);
+
+// Output port: notifications
+// Senders receive what the customer is told, not the whole Order entity.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::prelude::*;
+/// use std::sync::Mutex;
+///
+/// // Keeps the receipts instead of mailing them.
+/// #[derive(Default)]
+/// struct Mailbox(Mutex<Vec<String>>);
+///
+/// impl Sender for Mailbox {
+///     fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+///         let receipt = confirmation.receipt_lines().join("\n");
+///         self.0.lock().unwrap().push(receipt);
+///         Ok(())
+///     }
+/// }
+///
+/// let mailbox = Mailbox::default();
+/// let mut repo = InMemoryOrderRepository::new();
+/// let payment = MockPaymentGateway::new();
+/// OrderService::new(&repo, &payment, &mailbox)
+///     .place_order(vec![LineItem::new("Pen", Money(150))])?;
+///
+/// assert!(mailbox.0.lock().unwrap()[0].contains("$1.50"));
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait Sender {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError>;
+
+    // A message for the shop's own people, to `notice.to`. A sender that
+    // only reaches customers refuses it.
+    fn send_notice(&self, _notice: &Notice) -> Result<(), OrderError> {
+        Err(OrderError::NotificationFailed)
+    }
+}
+
+port_info!(Sender, Outbound, [send, send_notice]);
+
+// A closure is a Sender too, for a test or an example that only needs to
+// see what is sent, without a struct of its own. The same goes for the
+// PaymentGateway, the Clock and the EventPublisher: ports whose one
+// required method is all most callers need. A repository has too many for
+// one closure to stand for it:
+/// ```compile_fail
+/// use hexa_lite::prelude::*;
+///
+/// let repo = |_order: &Order| Ok::<(), OrderError>(());
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// OrderService::new(&repo, &payment, &sender); // a closure is no OrderWriter
+/// ```
+///
+/// A closure counting what it was given:
+///
+/// ```
+/// use hexa_lite::domain::OrderConfirmation;
+/// use hexa_lite::prelude::*;
+/// use std::cell::Cell;
+///
+/// let sent = Cell::new(0);
+/// let sender = |_: &OrderConfirmation| {
+///     sent.set(sent.get() + 1);
+///     Ok(())
+/// };
+/// let (repo, payment) = (InMemoryOrderRepository::new(), MockPaymentGateway::new());
+/// OrderService::new(&repo, &payment, &sender).place_order(vec![LineItem::new("Pen", Money(150))])?;
+///
+/// assert_eq!(sent.get(), 1);
+/// # Ok::<(), OrderError>(())
+/// ```
+impl<F> Sender for F
+where
+    F: Fn(&OrderConfirmation) -> Result<(), OrderError>,
+{
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self(confirmation)
+    }
+}
+
+// The first version of the Sender port, which took the whole Order.
+// Changing a port signature breaks every adapter at once, so the old trait
+// stays for a while: adapters written against it keep working once wrapped
+// in adapters::compat::V1Compat, and the deprecation warning tells their
+// authors where to go.
+///
+/// ```compile_fail
+/// #![deny(deprecated)]
+/// use hexa_lite::domain::{Order, OrderError};
+/// use hexa_lite::ports::SenderV1;
+///
+/// struct LegacySender;
+///
+/// impl SenderV1 for LegacySender {
+///     fn send(&self, _order: &Order) -> Result<(), OrderError> {
+///         Ok(())
+///     }
+/// }
+/// ```
+#[deprecated(
+    note = "implement Sender, which takes an OrderConfirmation; wrap old adapters in adapters::compat::V1Compat meanwhile"
+)]
+pub trait SenderV1 {
+    fn send(&self, order: &Order) -> Result<(), OrderError>;
+}
+
+// --- Ports of the draft/confirm flow (see domain::OrderDraft) ---
+
+// Output port: persistence for orders that may still be drafts.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::application::CheckoutService;
+/// use hexa_lite::domain::{ConfirmPolicy, StoredOrder};
+/// use hexa_lite::ports::DraftRepository;
+/// use hexa_lite::prelude::*;
+/// use std::collections::HashMap;
+///
+/// #[derive(Default)]
+/// struct Drafts(HashMap<OrderId, StoredOrder>);
+///
+/// impl DraftRepository for Drafts {
+///     fn store(&mut self, order: StoredOrder) -> Result<(), OrderError> {
+///         self.0.insert(order.id(), order);
+///         Ok(())
+///     }
+///
+///     fn load(&self, id: OrderId) -> Result<Option<StoredOrder>, OrderError> {
+///         Ok(self.0.get(&id).cloned())
+///     }
+/// }
+///
+/// let mut drafts = Drafts::default();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut checkout = CheckoutService::new(&mut drafts, &payment, &sender);
+///
+/// let id = checkout.start_draft()?;
+/// checkout.add_item(id, LineItem::new("Pen", Money(150)))?;
+/// let order = checkout.confirm(id, &ConfirmPolicy::default())?;
+/// assert_eq!(order.id(), id);
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait DraftRepository {
+    fn store(&mut self, order: StoredOrder) -> Result<(), OrderError>;
+    fn load(&self, id: OrderId) -> Result<Option<StoredOrder>, OrderError>;
+}
+
+port_info!(DraftRepository, Outbound, [store, load]);
+
+// Output port: charging a confirmed order.
+// Every PaymentGateway is one, so no adapter has to change. What changes is
+// the argument: a draft is not accepted.
+///
+/// ```compile_fail
+/// use hexa_lite::adapters::in_memory::MockPaymentGateway;
+/// use hexa_lite::domain::{OrderDraft, OrderId};
+/// use hexa_lite::ports::ChargeConfirmed;
+///
+/// let draft = OrderDraft::new(OrderId(1));
+/// // error[E0308]: expected `&ConfirmedOrder`, found `&OrderDraft`
+/// MockPaymentGateway::new().charge_confirmed(&draft).unwrap();
+/// ```
+pub trait ChargeConfirmed: sealed::ChargeConfirmed {
+    fn charge_confirmed(&self, order: &ConfirmedOrder) -> Result<(), OrderError>;
+}
+
+impl<P: PaymentGateway + ?Sized> sealed::ChargeConfirmed for P {}
+
+impl<P: PaymentGateway + ?Sized> ChargeConfirmed for P {
+    fn charge_confirmed(&self, order: &ConfirmedOrder) -> Result<(), OrderError> {
+        self.charge_for(order.id(), order.total())
+    }
+}
+
+// Output port: notifying about a confirmed order. Same idea as above.
+///
+/// ```compile_fail
+/// use hexa_lite::adapters::in_memory::ConsoleSender;
+/// use hexa_lite::domain::{OrderDraft, OrderId};
+/// use hexa_lite::ports::SendConfirmed;
+///
+/// let draft = OrderDraft::new(OrderId(1));
+/// ConsoleSender::new().send_confirmed(&draft).unwrap();
+/// ```
+pub trait SendConfirmed: sealed::SendConfirmed {
+    fn send_confirmed(&self, order: &ConfirmedOrder) -> Result<(), OrderError>;
+}
+
+impl<N: Sender + ?Sized> sealed::SendConfirmed for N {}
+
+impl<N: Sender + ?Sized> SendConfirmed for N {
+    fn send_confirmed(&self, order: &ConfirmedOrder) -> Result<(), OrderError> {
+        self.send(&order.as_order().confirmation())
+    }
+}
+
+// Output port: handing parcels to a carrier.
+// Called once per shipment with the items it contains.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::application::ShippingService;
+/// use hexa_lite::domain::{Address, TrackingId};
+/// use hexa_lite::ports::ShippingProvider;
+/// use hexa_lite::prelude::testing::SteppingClock;
+/// use hexa_lite::prelude::*;
+///
+/// // One parcel per order, tracked by the order number.
+/// struct Courier;
+///
+/// impl ShippingProvider for Courier {
+///     fn ship(
+///         &self,
+///         order_id: OrderId,
+///         _items: &[LineItem],
+///         _address: &Address,
+///     ) -> Result<TrackingId, OrderError> {
+///         Ok(TrackingId(format!("PARCEL-{}", order_id.0)))
+///     }
+/// }
+///
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let order = OrderService::new(&repo, &payment, &sender)
+///     .place_order(vec![LineItem::new("Pen", Money(150))])?;
+///
+/// let address = Address {
+///     street: "1 Main St".to_string(),
+///     city: "Springfield".to_string(),
+///     postal_code: "12345".to_string(),
+///     country: "US".to_string(),
+/// };
+/// let clock = SteppingClock::starting_at(Timestamp(1_700_000_000));
+/// let shipped = ShippingService::new(&repo, &Courier, &clock).ship_items(order.id, &[0], &address)?;
+/// assert_eq!(shipped.status, OrderStatus::Shipped);
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait ShippingProvider {
+    fn ship(
+        &self,
+        order_id: OrderId,
+        items: &[LineItem],
+        address: &Address,
+    ) -> Result<TrackingId, OrderError>;
+}
+
+port_info!(ShippingProvider, Outbound, [ship]);
+
+// Stock held for one checkout, under an id its caller chooses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ReservationId(pub u32);
+
+impl ReservationId {
+    // The stock held for one order, under the order's own number.
+    pub fn of_order(id: OrderId) -> Self {
+        Self(id.0)
+    }
+}
+
+// Output port: "keep these aside for me".
+// Stock is reserved, then either confirmed (it is sold) or released (back
+// on the shelf). Every call may be repeated with the same id and does
+// nothing the second time: a checkout resumed after a crash cannot know
+// whether its last call went through.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::adapters::in_memory::InMemorySagaLog;
+/// use hexa_lite::application::CheckoutSaga;
+/// use hexa_lite::domain::SagaId;
+/// use hexa_lite::ports::{Inventory, ReservationId};
+/// use hexa_lite::prelude::*;
+/// use std::collections::BTreeMap;
+/// use std::sync::Mutex;
+///
+/// // Ten of everything; a reservation is just its item count.
+/// #[derive(Default)]
+/// struct Shelf(Mutex<BTreeMap<ReservationId, usize>>);
+///
+/// impl Inventory for Shelf {
+///     fn reserve(&self, reservation: ReservationId, items: &[LineItem]) -> Result<(), OrderError> {
+///         if items.len() > 10 {
+///             return Err(OrderError::OutOfStock { item: items[0].name.clone() });
+///         }
+///         self.0.lock().unwrap().insert(reservation, items.len());
+///         Ok(())
+///     }
+///
+///     fn confirm(&self, reservation: ReservationId) -> Result<(), OrderError> {
+///         match self.0.lock().unwrap().contains_key(&reservation) {
+///             true => Ok(()),
+///             false => Err(OrderError::InvalidOrder),
+///         }
+///     }
+///
+///     fn release(&self, reservation: ReservationId) -> Result<(), OrderError> {
+///         self.0.lock().unwrap().remove(&reservation);
+///         Ok(())
+///     }
+///
+///     // Ten, whatever comes in.
+///     fn restock(&self, _item: &str, _units: u32) -> Result<(), OrderError> {
+///         Ok(())
+///     }
+///
+///     fn stock_level(&self, _item: &str) -> Result<u32, OrderError> {
+///         Ok(10)
+///     }
+/// }
+///
+/// let (shelf, log) = (Shelf::default(), InMemorySagaLog::new());
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let orders = OrderService::new(&repo, &payment, &sender);
+///
+/// let order = CheckoutSaga::new(orders, &shelf, &log)
+///     .execute(SagaId(1), vec![LineItem::new("Pen", Money(150))])?;
+/// assert_eq!(order.status, OrderStatus::Paid);
+/// assert_eq!(shelf.0.lock().unwrap()[&ReservationId(1)], 1);
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait Inventory {
+    // OutOfStock, holding nothing, when any item is short.
+    fn reserve(&self, reservation: ReservationId, items: &[LineItem]) -> Result<(), OrderError>;
+    // InvalidOrder for a reservation never made, or no longer held: released,
+    // or expired. Reserving it again takes the stock anew.
+    fn confirm(&self, reservation: ReservationId) -> Result<(), OrderError>;
+    // Releasing an unknown or confirmed reservation is fine, and does nothing.
+    fn release(&self, reservation: ReservationId) -> Result<(), OrderError>;
+    // `units` more of `item` on the shelf.
+    fn restock(&self, item: &str, units: u32) -> Result<(), OrderError>;
+    // Units of `item` neither reserved nor sold; 0 for an item never stocked.
+    fn stock_level(&self, item: &str) -> Result<u32, OrderError>;
+    // The warehouse each line of a reservation is taken from, in the order
+    // the items were reserved. None from an inventory with a single shelf,
+    // and for a reservation never made.
+    fn sources(&self, _reservation: ReservationId) -> Result<Option<Vec<WarehouseId>>, OrderError> {
+        Ok(None)
+    }
+    // Releases every reservation still held, neither confirmed nor released,
+    // that was made `ttl` or longer before `now`, and says which, in id
+    // order. An inventory that does not date its reservations has none.
+    fn release_expired(
+        &self,
+        _now: Timestamp,
+        _ttl: Duration,
+    ) -> Result<Vec<ReservationId>, OrderError> {
+        Ok(Vec::new())
+    }
+}
+
+port_info!(
+    Inventory,
+    Outbound,
+    [
+        reserve,
+        confirm,
+        release,
+        restock,
+        stock_level,
+        sources,
+        release_expired
+    ]
+);
+
+// Output port: "which warehouse does this line come from?"
+// Asked once per line item, with every warehouse still holding some of
+// it, in warehouse order. None when none of them will do: the reservation
+// is then refused. The answer must depend on its arguments only, so the
+// same cart on the same shelves always splits the same way.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::adapters::Console;
+/// use hexa_lite::adapters::warehouse::MultiWarehouseInventory;
+/// use hexa_lite::domain::{WarehouseId, WarehouseStock};
+/// use hexa_lite::ports::{Inventory, ReservationId, WarehousePicker};
+/// use hexa_lite::prelude::*;
+///
+/// // Anything but the flagship store, unless nobody else has it.
+/// struct SpareTheStore;
+///
+/// impl WarehousePicker for SpareTheStore {
+///     fn pick(&self, _item: &LineItem, stock: &[WarehouseStock]) -> Option<WarehouseId> {
+///         let elsewhere = stock.iter().find(|s| s.warehouse != WarehouseId(1));
+///         elsewhere.or(stock.first()).map(|s| s.warehouse)
+///     }
+/// }
+///
+/// let inventory = MultiWarehouseInventory::new(&SpareTheStore, WarehouseId(1))
+///     .with_stock(WarehouseId(1), "Pen", 5)
+///     .with_stock(WarehouseId(2), "Pen", 1)
+///     .with_console(Console::silent());
+///
+/// let pens = vec![LineItem::new("Pen", Money(150)); 2];
+/// inventory.reserve(ReservationId(1), &pens)?;
+/// assert_eq!(
+///     inventory.sources(ReservationId(1))?,
+///     Some(vec![WarehouseId(2), WarehouseId(1)])
+/// );
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait WarehousePicker {
+    fn pick(&self, item: &LineItem, stock: &[WarehouseStock]) -> Option<WarehouseId>;
+}
+
+port_info!(WarehousePicker, Outbound, [pick]);
+
+// Output port: "does this order need someone to approve it?"
+// A business decision that changes more often than the code around it:
+// a threshold today, a per-customer limit or a fraud score tomorrow.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::domain::ApproverId;
+/// use hexa_lite::ports::ApprovalPolicy;
+/// use hexa_lite::prelude::*;
+///
+/// // Someone looks at anything above $500.
+/// struct AboveFiveHundred;
+///
+/// impl ApprovalPolicy for AboveFiveHundred {
+///     fn requires_approval(&self, order: &Order) -> bool {
+///         order.total.0 > 50_000
+///     }
+/// }
+///
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service =
+///     OrderService::new(&repo, &payment, &sender).with_approval_policy(&AboveFiveHundred);
+///
+/// let laptop = service.place_order(vec![LineItem::new("Laptop", Money(129_999))])?;
+/// assert_eq!(laptop.status, OrderStatus::PendingApproval);
+/// let approved = service.approve_order(laptop.id, ApproverId("alice".to_string()))?;
+/// assert_eq!(approved.status, OrderStatus::Paid);
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait ApprovalPolicy {
+    fn requires_approval(&self, order: &Order) -> bool;
+}
+
+port_info!(ApprovalPolicy, Outbound, [requires_approval]);
+
+// Output port: "does the customer hear about this order?"
+// Asked before every confirmation the service sends. Without a policy,
+// every order is confirmed.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::domain::Discount;
+/// use hexa_lite::ports::NotificationPolicy;
+/// use hexa_lite::prelude::*;
+///
+/// // Free orders go through without an email.
+/// struct PaidForOnly;
+///
+/// impl NotificationPolicy for PaidForOnly {
+///     fn should_confirm(&self, order: &Order) -> bool {
+///         order.total != Money(0)
+///     }
+/// }
+///
+/// let repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service =
+///     OrderService::new(&repo, &payment, &sender).with_notification_policy(&PaidForOnly);
+///
+/// let free = Discount::Promotion(Percent::try_from(100).unwrap());
+/// let order = service.place_discounted_order(vec![LineItem::new("Pen", Money(150))], free)?;
+/// assert_eq!((order.status, order.total), (OrderStatus::Paid, Money(0)));
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait NotificationPolicy {
+    fn should_confirm(&self, order: &Order) -> bool;
+}
+
+port_info!(NotificationPolicy, Outbound, [should_confirm]);
+
+// Output port: "is this order what it claims to be?"
+// Asked once the order is valid, before anything is charged. A screen that
+// cannot answer fails the order: nothing unscreened is charged.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::domain::{Customer, ReviewDecision};
+/// use hexa_lite::ports::{ChargeLog, FraudScreen, RiskVerdict};
+/// use hexa_lite::prelude::*;
+///
+/// // Someone looks at every order of ten items or more.
+/// struct BulkBuyers;
+///
+/// impl FraudScreen for BulkBuyers {
+///     fn assess(&self, order: &Order, _customer: &Customer) -> Result<RiskVerdict, OrderError> {
+///         Ok(if order.items.len() >= 10 {
+///             RiskVerdict::Review
+///         } else {
+///             RiskVerdict::Accept
+///         })
+///     }
+/// }
+///
+/// let repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service = OrderService::new(&repo, &payment, &sender).with_fraud_screen(&BulkBuyers);
+///
+/// let cards = vec![LineItem::new("Gift card", Money(5_000)); 10];
+/// let order = service.place_order_for(&Customer::new("Mallory"), cards)?;
+/// assert_eq!(order.status, OrderStatus::UnderReview);
+/// assert!(payment.charges()?.is_empty());
+/// let resolved = service.resolve_review(order.id, ReviewDecision::Accept)?;
+/// assert_eq!(resolved.status, OrderStatus::Paid);
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait FraudScreen {
+    fn assess(&self, order: &Order, customer: &Customer) -> Result<RiskVerdict, OrderError>;
+}
+
+port_info!(FraudScreen, Outbound, [assess]);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RiskVerdict {
+    Accept,
+    // Parked, uncharged, until someone resolves the review.
+    Review,
+    // Refused with FraudSuspected, carrying `reason`.
+    Reject { reason: String },
+}
+
+// Output port: "two clients edited the same draft: what now?"
+// Asked by OrderService::update_draft_with_resolution when an update loses
+// the race: `base` is the draft both started from, `ours` the edit that
+// lost, `theirs` what the repository holds now. Resolving is a decision,
+// not a call: it cannot fail, Fail is one of its answers.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::ports::{ConflictResolver, Resolution};
+/// use hexa_lite::prelude::*;
+///
+/// // The larger cart wins: the customer can still remove items.
+/// struct LargerCart;
+///
+/// impl ConflictResolver for LargerCart {
+///     fn resolve(&self, _base: &Order, ours: &Order, theirs: &Order) -> Resolution {
+///         if ours.items.len() > theirs.items.len() {
+///             Resolution::TakeOurs
+///         } else {
+///             Resolution::TakeTheirs
+///         }
+///     }
+/// }
+///
+/// let base = Order::new(OrderId(1), vec![LineItem::new("Pen", Money(150))])?;
+/// let mut ours = base.clone();
+/// ours.items.push(LineItem::new("Ink", Money(300)));
+/// assert_eq!(LargerCart.resolve(&base, &ours, &base), Resolution::TakeOurs);
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait ConflictResolver {
+    fn resolve(&self, base: &Order, ours: &Order, theirs: &Order) -> Resolution;
+}
+
+port_info!(ConflictResolver, Outbound, [resolve]);
+
+// Output port: "may this order be forgotten, now?"
+// How long orders are kept is a legal question as much as a technical one,
+// and differs from one country, or one kind of order, to the next.
+// OrderService::purge asks it about every order, deleted or not; an order
+// it picks that still has something unsettled is kept all the same.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::ports::RetentionPolicy;
+/// use hexa_lite::prelude::*;
+///
+/// // Cancelled orders go at the end of the year after they were placed.
+/// struct EndOfNextYear;
+///
+/// const YEAR_SECS: u64 = 365 * 24 * 60 * 60;
+///
+/// impl RetentionPolicy for EndOfNextYear {
+///     fn should_purge(&self, order: &Order, now: Timestamp) -> bool {
+///         let year = |at: Timestamp| at.0 / YEAR_SECS;
+///         order.status == OrderStatus::Cancelled
+///             && order.placed_at.is_some_and(|at| year(now) > year(at) + 1)
+///     }
+/// }
+///
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service = OrderService::new(&repo, &payment, &sender)
+///     .with_retention_policy(&EndOfNextYear);
+///
+/// let mut order = Order::new(OrderId(1), vec![LineItem::new("Pen", Money(150))])?;
+/// order.placed_at = Some(Timestamp(YEAR_SECS));
+/// order.transition_to(OrderStatus::Cancelled)?;
+/// repo.save(&order)?;
+///
+/// assert!(service.purge(Timestamp(2 * YEAR_SECS))?.purged.is_empty());
+/// assert_eq!(service.purge(Timestamp(3 * YEAR_SECS))?.purged, [OrderId(1)]);
+/// assert_eq!(repo.list_deleted()?.len() + repo.list()?.len(), 0);
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait RetentionPolicy {
+    fn should_purge(&self, order: &Order, now: Timestamp) -> bool;
+}
+
+port_info!(RetentionPolicy, Outbound, [should_purge]);
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Resolution {
+    // Written over theirs.
+    TakeOurs,
+    // Kept as it is; ours is dropped.
+    TakeTheirs,
+    // Written over theirs, its total summed again.
+    Merged(Box<Order>),
+    // Ours is refused with the VersionConflict.
+    Fail,
+}
+
+// Output port: "what time is it?"
+// Asking a port instead of SystemTime::now() lets tests decide.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::prelude::*;
+///
+/// // Always the same second: what a test wants.
+/// struct Frozen;
+///
+/// impl Clock for Frozen {
+///     fn now(&self) -> Timestamp {
+///         Timestamp(1_700_000_000)
+///     }
+/// }
+///
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service = OrderService::new(&repo, &payment, &sender).with_clock(&Frozen);
+///
+/// let order = service.place_order(vec![LineItem::new("Pen", Money(150))])?;
+/// assert_eq!(order.placed_at, Some(Timestamp(1_700_000_000)));
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait Clock {
+    fn now(&self) -> Timestamp;
+}
+
+port_info!(Clock, Outbound, [now]);
+
+// `|| Timestamp(1_700_000_000)`: a clock stopped where the test wants it.
+impl<F> Clock for F
+where
+    F: Fn() -> Timestamp,
+{
+    fn now(&self) -> Timestamp {
+        self()
+    }
+}
+
+// The time a whole use case may take, whichever ports it calls on the way:
+// "a checkout answers within 2 seconds", not "each call gets 2 seconds".
+//
+// The service starts it when a use case begins and stops it when the use
+// case returns (see OrderService::with_budget). Decorators around the ports
+// (adapters::budget) check it before each call, and refuse with
+// BudgetExhausted once nothing is left; the service reports the refusal
+// with the port and the operation it was about to run. A budget that is not
+// running refuses nothing, so the same decorated ports serve outside any
+// use case.
+//
+// Measured with the Clock port, in whole seconds.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::ports::Budget;
+/// use hexa_lite::prelude::testing::SteppingClock;
+/// use hexa_lite::prelude::*;
+/// use std::time::Duration;
+///
+/// let clock = SteppingClock::starting_at(Timestamp(0));
+/// let budget = Budget::new(&clock, Duration::from_secs(2));
+///
+/// budget.start();
+/// clock.advance_secs(1);
+/// assert_eq!(budget.remaining(), Duration::from_secs(1));
+/// assert!(budget.check().is_ok());
+///
+/// clock.advance_secs(1);
+/// assert!(matches!(
+///     budget.check(),
+///     Err(OrderError::BudgetExhausted { spent_ms: 2_000 })
+/// ));
+/// ```
+pub struct Budget<'a> {
+    clock: &'a (dyn Clock + Sync),
+    limit: Duration,
+    started: Mutex<Option<Timestamp>>,
+}
+
+impl<'a> Budget<'a> {
+    pub fn new(clock: &'a (dyn Clock + Sync), limit: Duration) -> Self {
+        Self {
+            clock,
+            limit,
+            started: Mutex::new(None),
+        }
+    }
+
+    pub fn limit(&self) -> Duration {
+        self.limit
+    }
+
+    // From now, with the whole limit. False when it was already running:
+    // a use case called by another one spends the caller's budget, and only
+    // the caller stops it.
+    pub fn start(&self) -> bool {
+        let mut started = self.lock();
+        if started.is_some() {
+            return false;
+        }
+        *started = Some(self.clock.now());
+        true
+    }
+
+    pub fn stop(&self) {
+        *self.lock() = None;
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.lock().is_some()
+    }
+
+    // Zero when not running.
+    pub fn spent(&self) -> Duration {
+        match *self.lock() {
+            Some(started) => Duration::from_secs(self.clock.now().0.saturating_sub(started.0)),
+            None => Duration::ZERO,
+        }
+    }
+
+    // The whole limit when not running, zero once spent.
+    pub fn remaining(&self) -> Duration {
+        self.limit.saturating_sub(self.spent())
+    }
+
+    // What a decorator asks before starting a call.
+    pub fn check(&self) -> Result<(), OrderError> {
+        if self.is_running() && self.remaining().is_zero() {
+            let spent_ms = u64::try_from(self.spent().as_millis()).unwrap_or(u64::MAX);
+            return Err(OrderError::BudgetExhausted { spent_ms });
+        }
+        Ok(())
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Option<Timestamp>> {
+        self.started.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+// A way to stop a long-running use case (a bulk transition, a report, a
+// batch of carts) once started. Whoever started it keeps a clone and calls
+// cancel(), from any thread; the use case looks at it between two items
+// and stops with Cancelled, saying how far it got. Nothing is cut short in
+// the middle of an item.
+//
+// child() scopes a token to one part of the work: cancelling the child
+// leaves the parent alone, cancelling the parent cancels every child.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::ports::CancelToken;
+///
+/// let session = CancelToken::new();
+/// let command = session.child();
+///
+/// command.cancel();
+/// assert!(command.is_cancelled());
+/// assert!(!session.is_cancelled());
+///
+/// let next = session.child();
+/// session.cancel();
+/// assert!(next.is_cancelled());
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken(Arc<CancelNode>);
+
+#[derive(Debug, Default)]
+struct CancelNode {
+    cancelled: AtomicBool,
+    parent: Option<Arc<CancelNode>>,
+}
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    // Cancelled itself, or any of its parents.
+    pub fn is_cancelled(&self) -> bool {
+        let mut node = Some(&self.0);
+        while let Some(current) = node {
+            if current.cancelled.load(Ordering::SeqCst) {
+                return true;
+            }
+            node = current.parent.as_ref();
+        }
+        false
+    }
+
+    pub fn child(&self) -> CancelToken {
+        CancelToken(Arc::new(CancelNode {
+            cancelled: AtomicBool::new(false),
+            parent: Some(self.0.clone()),
+        }))
+    }
+
+    // What a use case asks between two items, `processed` of them done.
+    pub fn check(&self, processed: usize) -> Result<(), OrderError> {
+        if self.is_cancelled() {
+            return Err(OrderError::Cancelled { processed });
+        }
+        Ok(())
+    }
+}
+
+// Where a call stands in a distributed trace: the trace it belongs to, its
+// own span, and the span that made the call. On the wire, the W3C
+// traceparent header: "00-<trace id>-<span id>-01", in hex.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::ports::TraceContext;
+///
+/// let caller =
+///     TraceContext::parse_traceparent("00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01")
+///         .unwrap();
+/// let call = caller.child(42);
+///
+/// assert_eq!(call.trace_id, caller.trace_id);
+/// assert_eq!(call.parent, Some(0x00f0_67aa_0ba9_02b7));
+/// assert_eq!(
+///     call.traceparent(),
+///     "00-4bf92f3577b34da6a3ce929d0e0e4736-000000000000002a-01"
+/// );
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct TraceContext {
+    pub trace_id: u128,
+    pub span_id: u64,
+    pub parent: Option<u64>,
+}
+
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+
+impl TraceContext {
+    // A span of the same trace, started by this one.
+    pub fn child(&self, span_id: u64) -> TraceContext {
+        TraceContext {
+            trace_id: self.trace_id,
+            span_id,
+            parent: Some(self.span_id),
+        }
+    }
+
+    // Always sampled: which traces to keep is the collector's business.
+    pub fn traceparent(&self) -> String {
+        format!("00-{:032x}-{:016x}-01", self.trace_id, self.span_id)
+    }
+
+    // The caller's span, as its header gives it; its own parent is not in
+    // there. None for any version but 00, and for ids of only zeros: the
+    // trace then starts again here, as if there had been no header.
+    pub fn parse_traceparent(header: &str) -> Option<TraceContext> {
+        let parts: Vec<&str> = header.trim().split('-').collect();
+        let [version, trace_id, span_id, flags] = parts[..] else {
+            return None;
+        };
+        if version != "00" || !is_hex(flags, 2) || !is_hex(trace_id, 32) || !is_hex(span_id, 16) {
+            return None;
+        }
+        let trace_id = u128::from_str_radix(trace_id, 16).ok()?;
+        let span_id = u64::from_str_radix(span_id, 16).ok()?;
+        if trace_id == 0 || span_id == 0 {
+            return None;
+        }
+        Some(TraceContext {
+            trace_id,
+            span_id,
+            parent: None,
+        })
+    }
+}
+
+fn is_hex(text: &str, digits: usize) -> bool {
+    text.len() == digits && text.bytes().all(|byte| byte.is_ascii_hexdigit())
+}
+
+// What a contextual port is given with each call: the span of that call.
+// A struct of its own rather than the bare TraceContext, so that the next
+// thing every call carries does not change every contextual port again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Context {
+    pub trace: TraceContext,
+}
+
+// The spans of a service's use cases, handed out the way a Budget keeps
+// their time. The service starts a trace when a use case begins and ends
+// it when the use case returns (see OrderService::with_tracer); every call
+// through a port then gets a span of its own, a child of the use case's
+// (adapters::tracing::InContext asks for it). A call made outside of any
+// use case is a trace of its own.
+//
+// An inbound adapter handed the caller's traceparent passes it on with
+// continue_from: the next use case joins that trace rather than starting
+// one.
+//
+// A handle, cheap to clone, like a CancelToken. The ids are a counter
+// hashed with std's RandomState keys: different enough to tell traces
+// apart, nothing more.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::ports::Tracer;
+///
+/// let tracer = Tracer::new();
+/// assert!(tracer.start());
+/// let use_case = tracer.current().unwrap();
+/// let call = tracer.span();
+/// assert_eq!(call.trace_id, use_case.trace_id);
+/// assert_eq!(call.parent, Some(use_case.span_id));
+///
+/// tracer.stop();
+/// assert_ne!(tracer.span().trace_id, use_case.trace_id);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Tracer(Arc<TracerState>);
+
+#[derive(Debug, Default)]
+struct TracerState {
+    keys: RandomState,
+    counter: AtomicU64,
+    // The running use case's span.
+    current: Mutex<Option<TraceContext>>,
+    // The caller's span, for the next use case to join.
+    caller: Mutex<Option<TraceContext>>,
+}
+
+impl Tracer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn continue_from(&self, caller: TraceContext) {
+        *locked(&self.0.caller) = Some(caller);
+    }
+
+    // False when a use case is already running, as for Budget::start: a use
+    // case called by another one stays in the caller's trace.
+    pub fn start(&self) -> bool {
+        let mut current = locked(&self.0.current);
+        if current.is_some() {
+            return false;
+        }
+        let caller = locked(&self.0.caller).take();
+        *current = Some(match caller {
+            Some(caller) => caller.child(self.next_id()),
+            None => self.root(),
+        });
+        true
+    }
+
+    pub fn stop(&self) {
+        *locked(&self.0.current) = None;
+    }
+
+    // The running use case's span.
+    pub fn current(&self) -> Option<TraceContext> {
+        *locked(&self.0.current)
+    }
+
+    // A new span, for one call.
+    pub fn span(&self) -> TraceContext {
+        match self.current() {
+            Some(use_case) => use_case.child(self.next_id()),
+            None => self.root(),
+        }
+    }
+
+    fn root(&self) -> TraceContext {
+        TraceContext {
+            trace_id: u128::from(self.next_id()) << 64 | u128::from(self.next_id()),
+            span_id: self.next_id(),
+            parent: None,
+        }
+    }
+
+    // Never zero, which a traceparent reads as no id at all.
+    fn next_id(&self) -> u64 {
+        let n = self.0.counter.fetch_add(1, Ordering::Relaxed);
+        self.0.keys.hash_one(n).max(1)
+    }
+}
+
+fn locked<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+// Sender, for the adapters that pass the trace on downstream, as a header
+// say: the same calls, each with its Context. Sender itself is left as it
+// is. The wrappers between the two are in adapters::tracing: InContext
+// makes one of these a Sender again, for the service; Contextless makes
+// any Sender one of these, passing nothing on.
+pub trait ContextualSender {
+    fn send_in(
+        &self,
+        context: &Context,
+        confirmation: &OrderConfirmation,
+    ) -> Result<(), OrderError>;
+
+    fn send_notice_in(&self, _context: &Context, _notice: &Notice) -> Result<(), OrderError> {
+        Err(OrderError::NotificationFailed)
+    }
+}
+
+// The same for a repository: OrderReader and OrderWriter, each call with
+// its Context. The defaults are theirs, over the three required methods:
+// an adapter that overrides update, soft_delete and the others overrides
+// them here too, or loses them once it is in context.
+pub trait ContextualRepository {
+    fn find_in(&self, context: &Context, id: OrderId) -> Result<Option<Order>, OrderError>;
+    fn list_in(&self, context: &Context) -> Result<Vec<Order>, OrderError>;
+    fn save_in(&self, context: &Context, order: &Order) -> Result<(), OrderError>;
+
+    fn update_in(&self, context: &Context, order: &Order) -> Result<(), OrderError> {
+        let id = order.id;
+        let stored = self
+            .find_in(context, id)?
+            .ok_or(OrderError::NotFound { id })?;
+        if stored.version != order.version {
+            return Err(OrderError::VersionConflict {
+                id,
+                expected: order.version,
+                found: stored.version,
+            });
+        }
+        let mut next = order.clone();
+        next.version += 1;
+        self.save_in(context, &next)
+    }
+
+    fn soft_delete_in(&self, _context: &Context, _id: OrderId) -> Result<(), OrderError> {
+        Err(OrderError::StorageFailed)
+    }
+
+    fn restore_in(&self, _context: &Context, _id: OrderId) -> Result<(), OrderError> {
+        Err(OrderError::StorageFailed)
+    }
+
+    fn purge_in(&self, _context: &Context, _id: OrderId) -> Result<(), OrderError> {
+        Err(OrderError::StorageFailed)
+    }
+
+    fn list_deleted_in(&self, _context: &Context) -> Result<Vec<Order>, OrderError> {
+        Ok(Vec::new())
+    }
+
+    fn find_by_key_in(
+        &self,
+        context: &Context,
+        key: OrderKey,
+    ) -> Result<Option<Order>, OrderError> {
+        match key {
+            OrderKey::Sequential(id) => self.find_in(context, id),
+            OrderKey::Random(uuid) => Ok(self
+                .list_in(context)?
+                .into_iter()
+                .find(|order| order.uuid == Some(uuid))),
+        }
+    }
+
+    fn find_by_reference_in(
+        &self,
+        context: &Context,
+        reference: &OrderReference,
+    ) -> Result<Option<Order>, OrderError> {
+        Ok(self
+            .list_in(context)?
+            .into_iter()
+            .find(|order| order.reference.as_ref() == Some(reference)))
+    }
+}
+
+// Output port: "a fresh id nobody else has"
+// Random, so that an id says nothing about how many came before it, and two
+// processes never hand out the same one.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::domain::Uuid128;
+/// use hexa_lite::ports::IdGenerator;
+/// use hexa_lite::prelude::*;
+///
+/// // Not random at all, which is what a test wants.
+/// struct Fixed;
+///
+/// impl IdGenerator for Fixed {
+///     fn next_uuid(&self) -> Uuid128 {
+///         Uuid128::v4(1, 2)
+///     }
+/// }
+///
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service = OrderService::new(&repo, &payment, &sender).with_id_generator(&Fixed);
+///
+/// let order = service.place_order(vec![LineItem::new("Pen", Money(150))])?;
+/// assert_eq!(order.uuid, Some(Uuid128::v4(1, 2)));
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait IdGenerator {
+    fn next_uuid(&self) -> Uuid128;
+}
+
+port_info!(IdGenerator, Outbound, [next_uuid]);
+
+// The reference to give a new order, for the people who will quote it:
+// short, and easy to read out. Unlike a UUID it may say something, such as
+// the year the order was placed in; see adapters::references.
+//
+// Unique for as long as the generator lives. Processes sharing a repository
+// each need a generator of their own kind: a prefix, a sequence.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::domain::OrderReference;
+/// use hexa_lite::ports::{OrderReader, ReferenceGenerator};
+/// use hexa_lite::prelude::*;
+/// use std::sync::atomic::{AtomicU32, Ordering};
+///
+/// // Raffle tickets, numbered from 100.
+/// struct Tickets(AtomicU32);
+///
+/// impl ReferenceGenerator for Tickets {
+///     fn next_reference(&self) -> OrderReference {
+///         OrderReference(format!("T{}", self.0.fetch_add(1, Ordering::Relaxed)))
+///     }
+/// }
+///
+/// let tickets = Tickets(AtomicU32::new(100));
+/// let repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service =
+///     OrderService::new(&repo, &payment, &sender).with_reference_generator(&tickets);
+///
+/// let order = service.place_order(vec![LineItem::new("Pen", Money(150))])?;
+/// let reference = OrderReference("T100".to_string());
+/// assert_eq!(order.reference.as_ref(), Some(&reference));
+/// assert_eq!(repo.find_by_reference(&reference)?, Some(order));
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait ReferenceGenerator {
+    fn next_reference(&self) -> OrderReference;
+}
+
+port_info!(ReferenceGenerator, Outbound, [next_reference]);
+
+// Output port: "tell the world what happened".
+// Called after the fact is stored, so a listener never hears about an
+// order the repository does not have.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::domain::OrderEvent;
+/// use hexa_lite::ports::EventPublisher;
+/// use hexa_lite::prelude::*;
+/// use std::sync::Mutex;
+///
+/// #[derive(Default)]
+/// struct Journal(Mutex<Vec<OrderEvent>>);
+///
+/// impl EventPublisher for Journal {
+///     fn publish(&self, event: &OrderEvent) -> Result<(), OrderError> {
+///         self.0.lock().unwrap().push(event.clone());
+///         Ok(())
+///     }
+/// }
+///
+/// let journal = Journal::default();
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// OrderService::new(&repo, &payment, &sender)
+///     .with_event_publisher(&journal)
+///     .place_order(vec![LineItem::new("Pen", Money(150))])?;
+///
+/// assert!(matches!(
+///     journal.0.lock().unwrap()[..],
+///     [OrderEvent::OrderPlaced { .. }, OrderEvent::OrderPaid { .. }]
+/// ));
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait EventPublisher {
+    fn publish(&self, event: &OrderEvent) -> Result<(), OrderError>;
+
+    // What the inventory has to say. A publisher only interested in orders
+    // may ignore it.
+    fn publish_inventory(&self, _event: &InventoryEvent) -> Result<(), OrderError> {
+        Ok(())
+    }
+}
+
+port_info!(EventPublisher, Outbound, [publish, publish_inventory]);
+
+// The order events only: inventory events are ignored, as by default.
+impl<F> EventPublisher for F
+where
+    F: Fn(&OrderEvent) -> Result<(), OrderError>,
+{
+    fn publish(&self, event: &OrderEvent) -> Result<(), OrderError> {
+        self(event)
+    }
+}
+
+// The other side: something that consumes events one by one, in order.
+// Read models are subscribers; so is whatever replays an event log.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::domain::OrderEvent;
+/// use hexa_lite::ports::EventSubscriber;
+/// use hexa_lite::prelude::*;
+///
+/// // The money that came in, in cents.
+/// #[derive(Default)]
+/// struct Revenue(u64);
+///
+/// impl EventSubscriber for Revenue {
+///     fn on_event(&mut self, event: &OrderEvent) {
+///         if let OrderEvent::OrderPaid { amount, .. } = event {
+///             self.0 += u64::from(amount.0);
+///         }
+///     }
+/// }
+///
+/// // Events as an event log would replay them, oldest first.
+/// let mut revenue = Revenue::default();
+/// for (id, cents) in [(1, 150), (2, 250)] {
+///     revenue.on_event(&OrderEvent::OrderPaid {
+///         order_id: OrderId(id),
+///         amount: Money(cents),
+///     });
+/// }
+/// assert_eq!(revenue.0, 400);
+/// ```
+pub trait EventSubscriber {
+    fn on_event(&mut self, event: &OrderEvent);
+}
+
+port_info!(EventSubscriber, Outbound, [on_event]);
+
+// What a service holds in memory between calls and must get back after a
+// restart. Orders, drafts and pending approvals already live in their
+// repositories; this is what only the service knows: the next id to hand
+// out, and which drafts are still open.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceState {
+    pub next_id: u32,
+    pub open_drafts: Vec<OrderId>,
+}
+
+// Output port: "keep this until the next start".
+/// # Examples
+///
+/// ```
+/// use hexa_lite::ports::{ServiceState, StateStore};
+/// use hexa_lite::prelude::*;
+/// use std::sync::Mutex;
+///
+/// #[derive(Default)]
+/// struct Slot(Mutex<Option<ServiceState>>);
+///
+/// impl StateStore for Slot {
+///     fn save_state(&self, state: &ServiceState) -> Result<(), OrderError> {
+///         *self.0.lock().unwrap() = Some(state.clone());
+///         Ok(())
+///     }
+///
+///     fn load_state(&self) -> Result<Option<ServiceState>, OrderError> {
+///         Ok(self.0.lock().unwrap().clone())
+///     }
+/// }
+///
+/// let slot = Slot::default();
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service = OrderService::new(&repo, &payment, &sender);
+/// service.place_order(vec![LineItem::new("Pen", Money(150))])?;
+/// slot.save_state(&service.state())?;
+///
+/// // After a restart, numbering goes on where it stopped.
+/// let state = slot.load_state()?.expect("saved before the restart");
+/// let mut service = OrderService::restore(&repo, &payment, &sender, &state)?;
+/// let next = service.place_order(vec![LineItem::new("Ink", Money(450))])?;
+/// assert_eq!(next.id, OrderId(2));
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait StateStore {
+    fn save_state(&self, state: &ServiceState) -> Result<(), OrderError>;
+    // None when nothing was ever saved: start from scratch.
+    fn load_state(&self) -> Result<Option<ServiceState>, OrderError>;
+}
+
+port_info!(StateStore, Outbound, [save_state, load_state]);
+
+// A charge owed by an order taken offline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PendingCharge {
+    pub order_id: OrderId,
+    pub amount: Money,
+}
+
+// Output port: charges accepted while the payment provider was unreachable,
+// kept until they are settled. Shared by the gateway that records them and
+// the service that settles them, hence &self everywhere.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::adapters::offline::OfflineCapablePaymentGateway;
+/// use hexa_lite::ports::{PendingCharge, PendingCharges};
+/// use hexa_lite::prelude::*;
+/// use std::sync::Mutex;
+///
+/// #[derive(Default)]
+/// struct Ledger(Mutex<Vec<PendingCharge>>);
+///
+/// impl PendingCharges for Ledger {
+///     fn record(&self, charge: PendingCharge) -> Result<(), OrderError> {
+///         self.0.lock().unwrap().push(charge);
+///         Ok(())
+///     }
+///
+///     fn pending(&self) -> Result<Vec<PendingCharge>, OrderError> {
+///         Ok(self.0.lock().unwrap().clone())
+///     }
+///
+///     fn remove(&self, order_id: OrderId) -> Result<(), OrderError> {
+///         self.0.lock().unwrap().retain(|charge| charge.order_id != order_id);
+///         Ok(())
+///     }
+/// }
+///
+/// // The payment provider is down.
+/// struct Unreachable;
+///
+/// impl PaymentGateway for Unreachable {
+///     fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+///         Err(OrderError::PaymentUnavailable)
+///     }
+/// }
+///
+/// let ledger = Ledger::default();
+/// let gateway = OfflineCapablePaymentGateway::new(Unreachable, &ledger);
+/// let mut repo = InMemoryOrderRepository::new();
+/// let sender = ConsoleSender::new();
+/// let order = OrderService::new(&repo, &gateway, &sender)
+///     .with_pending_charges(&ledger)
+///     .place_order(vec![LineItem::new("Pen", Money(150))])?;
+///
+/// assert_eq!(order.status, OrderStatus::PaymentPending);
+/// assert_eq!(
+///     ledger.pending()?,
+///     [PendingCharge {
+///         order_id: order.id,
+///         amount: Money(150),
+///     }]
+/// );
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait PendingCharges {
+    fn record(&self, charge: PendingCharge) -> Result<(), OrderError>;
+    // Oldest first.
+    fn pending(&self) -> Result<Vec<PendingCharge>, OrderError>;
+    fn remove(&self, order_id: OrderId) -> Result<(), OrderError>;
+}
+
+port_info!(PendingCharges, Outbound, [record, pending, remove]);
+
+// A notification given up on: `attempts` sends failed, the last one with
+// `last_error`, at `failed_at`.
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub confirmation: OrderConfirmation,
+    pub attempts: u32,
+    pub last_error: OrderError,
+    pub failed_at: Timestamp,
+}
+
+// Output port: where notifications end up once retrying them is pointless,
+// for someone to look at, fix the address, and send again by hand.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::adapters::outbox::{OutboxSender, RetryPolicy};
+/// use hexa_lite::ports::{DeadLetter, DeadLetterSink};
+/// use hexa_lite::prelude::testing::SteppingClock;
+/// use hexa_lite::prelude::*;
+/// use std::sync::Mutex;
+/// use std::time::Duration;
+///
+/// #[derive(Default)]
+/// struct Tray(Mutex<Vec<DeadLetter>>);
+///
+/// impl DeadLetterSink for Tray {
+///     fn dead_letter(&self, letter: DeadLetter) -> Result<(), OrderError> {
+///         self.0.lock().unwrap().push(letter);
+///         Ok(())
+///     }
+/// }
+///
+/// // Every mail bounces.
+/// struct Bounce;
+///
+/// impl Sender for Bounce {
+///     fn send(&self, _confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+///         Err(OrderError::NotificationFailed)
+///     }
+/// }
+///
+/// let tray = Tray::default();
+/// let clock = SteppingClock::starting_at(Timestamp(0));
+/// let once = RetryPolicy::new(Duration::from_secs(1), Duration::from_secs(1), 1);
+/// let outbox = OutboxSender::new(Bounce, clock, &tray).with_policy(once);
+/// let mut repo = InMemoryOrderRepository::new();
+/// let payment = MockPaymentGateway::new();
+/// let order = OrderService::new(&repo, &payment, &outbox)
+///     .place_order(vec![LineItem::new("Pen", Money(150))])?;
+///
+/// outbox.run_dispatcher(Timestamp(0))?;
+/// assert_eq!(tray.0.lock().unwrap()[0].confirmation.order_id, order.id);
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait DeadLetterSink {
+    fn dead_letter(&self, letter: DeadLetter) -> Result<(), OrderError>;
+}
+
+port_info!(DeadLetterSink, Outbound, [dead_letter]);
+
+// The steps of application::CheckoutSaga, in the order they run.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SagaStep {
+    ReserveInventory,
+    PlaceOrder,
+    ConfirmReservation,
+}
+
+// What a saga's log holds, one entry per outcome, oldest first:
+//
+//     Started, Reserved, OrderPlaced, Confirmed
+//
+// or, when a step fails, Failed then a Compensated entry for every step
+// already done, undone in reverse:
+//
+//     Started, Reserved, OrderPlaced, Failed(ConfirmReservation),
+//     Compensated(PlaceOrder), Compensated(ReserveInventory)
+#[derive(Debug, Clone)]
+pub enum SagaEntry {
+    Started { items: Vec<LineItem> },
+    Reserved { reservation: ReservationId },
+    OrderPlaced { order_id: OrderId },
+    Confirmed,
+    Failed { step: SagaStep, error: OrderError },
+    Compensated { step: SagaStep },
+}
+
+// Output port: "where was this checkout when it stopped?"
+// Written before the saga moves on, read back to resume it. A log that
+// keeps its entries across a restart is what makes a crash resumable.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::adapters::in_memory::InMemoryInventory;
+/// use hexa_lite::application::CheckoutSaga;
+/// use hexa_lite::domain::SagaId;
+/// use hexa_lite::ports::{SagaEntry, SagaLog};
+/// use hexa_lite::prelude::*;
+/// use std::sync::Mutex;
+///
+/// // Every saga's entries, interleaved, as a journal would hold them.
+/// #[derive(Default)]
+/// struct Journal(Mutex<Vec<(SagaId, SagaEntry)>>);
+///
+/// impl SagaLog for Journal {
+///     fn append(&self, saga: SagaId, entry: SagaEntry) -> Result<(), OrderError> {
+///         self.0.lock().unwrap().push((saga, entry));
+///         Ok(())
+///     }
+///
+///     fn entries(&self, saga: SagaId) -> Result<Vec<SagaEntry>, OrderError> {
+///         let journal = self.0.lock().unwrap();
+///         Ok(journal.iter().filter(|(id, _)| *id == saga).map(|(_, entry)| entry.clone()).collect())
+///     }
+/// }
+///
+/// let inventory = InMemoryInventory::new().with_stock("Pen", 1);
+/// let journal = Journal::default();
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let orders = OrderService::new(&repo, &payment, &sender);
+/// let mut saga = CheckoutSaga::new(orders, &inventory, &journal);
+///
+/// saga.execute(SagaId(1), vec![LineItem::new("Pen", Money(150))])?;
+/// let sold_out = saga.execute(SagaId(2), vec![LineItem::new("Pen", Money(150))]);
+/// assert!(matches!(sold_out, Err(OrderError::OutOfStock { .. })));
+/// assert!(matches!(journal.entries(SagaId(1))?.last(), Some(SagaEntry::Confirmed)));
+/// assert_eq!(journal.entries(SagaId(2))?.len(), 2);
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait SagaLog {
+    fn append(&self, saga: SagaId, entry: SagaEntry) -> Result<(), OrderError>;
+    // Oldest first; empty for a saga never started.
+    fn entries(&self, saga: SagaId) -> Result<Vec<SagaEntry>, OrderError>;
+}
+
+port_info!(SagaLog, Outbound, [append, entries]);
+
+// Output port: "was this request already served?"
+// A client retrying after a timeout sends the same idempotency key again:
+// what it gets back is the order placed the first time, not a second one.
+// Keys are kept for a while, not forever: purge_older_than forgets those
+// remembered before `now - max_age`, and says how many.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::ports::IdempotencyStore;
+/// use hexa_lite::prelude::*;
+/// use std::collections::HashMap;
+/// use std::sync::Mutex;
+/// use std::time::Duration;
+///
+/// // Remembers every key forever: fine for a demo, not for a server.
+/// #[derive(Default)]
+/// struct Keys(Mutex<HashMap<String, OrderId>>);
+///
+/// impl IdempotencyStore for Keys {
+///     fn find(&self, key: &str) -> Result<Option<OrderId>, OrderError> {
+///         Ok(self.0.lock().unwrap().get(key).copied())
+///     }
+///
+///     fn remember(&self, key: &str, id: OrderId, _at: Timestamp) -> Result<(), OrderError> {
+///         self.0.lock().unwrap().insert(key.to_string(), id);
+///         Ok(())
+///     }
+///
+///     fn purge_older_than(&self, _max_age: Duration, _now: Timestamp) -> Result<usize, OrderError> {
+///         Ok(0)
+///     }
+/// }
+///
+/// let keys = Keys::default();
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service = OrderService::new(&repo, &payment, &sender)
+///     .with_idempotency_store(&keys, Duration::from_secs(24 * 60 * 60));
+///
+/// let first = service.place_order_once("retry-1", vec![LineItem::new("Pen", Money(150))])?;
+/// let retried = service.place_order_once("retry-1", vec![LineItem::new("Pen", Money(150))])?;
+/// assert_eq!(retried.id, first.id);
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait IdempotencyStore {
+    fn find(&self, key: &str) -> Result<Option<OrderId>, OrderError>;
+    fn remember(&self, key: &str, id: OrderId, at: Timestamp) -> Result<(), OrderError>;
+    fn purge_older_than(&self, max_age: Duration, now: Timestamp) -> Result<usize, OrderError>;
+    // Forgets every key standing for order `id`, whatever its age, and says
+    // how many; for an order purged for good. Refused by default.
+    fn forget_order(&self, _id: OrderId) -> Result<usize, OrderError> {
+        Err(OrderError::StorageFailed)
+    }
+}
+
+port_info!(
+    IdempotencyStore,
+    Outbound,
+    [find, remember, purge_older_than, forget_order]
+);
+
+// Output port: "keep these bytes for me"
+// The files attached to orders: an object store, a directory, a database's
+// large objects. The store picks the BlobId; the order keeps it, with the
+// file's name. get is None for a blob never put, or deleted; deleting one
+// that is not there is fine.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::domain::BlobId;
+/// use hexa_lite::ports::BlobStore;
+/// use hexa_lite::prelude::*;
+/// use std::sync::Mutex;
+///
+/// #[derive(Default)]
+/// struct Shelf(Mutex<Vec<Option<Vec<u8>>>>);
+///
+/// impl BlobStore for Shelf {
+///     fn put(&self, bytes: &[u8]) -> Result<BlobId, OrderError> {
+///         let mut shelf = self.0.lock().unwrap();
+///         shelf.push(Some(bytes.to_vec()));
+///         Ok(BlobId(shelf.len() as u64))
+///     }
+///
+///     fn get(&self, id: BlobId) -> Result<Option<Vec<u8>>, OrderError> {
+///         let shelf = self.0.lock().unwrap();
+///         Ok(shelf.get(id.0 as usize - 1).cloned().flatten())
+///     }
+///
+///     fn delete(&self, id: BlobId) -> Result<(), OrderError> {
+///         if let Some(blob) = self.0.lock().unwrap().get_mut(id.0 as usize - 1) {
+///             *blob = None;
+///         }
+///         Ok(())
+///     }
+/// }
+///
+/// let shelf = Shelf::default();
+/// let repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service = OrderService::new(&repo, &payment, &sender).with_blob_store(&shelf);
+///
+/// let order = service.place_order(vec![LineItem::new("Pen", Money(150))])?;
+/// let receipt = service.attach_file(order.id, b"%PDF-1.7", "receipt.pdf")?;
+/// assert_eq!(shelf.get(receipt.blob)?.as_deref(), Some(&b"%PDF-1.7"[..]));
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait BlobStore {
+    fn put(&self, bytes: &[u8]) -> Result<BlobId, OrderError>;
+    fn get(&self, id: BlobId) -> Result<Option<Vec<u8>>, OrderError>;
+    fn delete(&self, id: BlobId) -> Result<(), OrderError>;
+}
+
+port_info!(BlobStore, Outbound, [put, get, delete]);
+
+// Output port: operational counters, and histograms.
+// Names are dotted paths ("sender.v1_compat.send"); what happens to the
+// numbers (Prometheus, StatsD, a log line) is the adapter's business.
+/// # Examples
+///
+/// ```
+/// #![allow(deprecated)] // SenderV1, on purpose
+/// use hexa_lite::adapters::compat::{V1Compat, V1_SEND_COUNTER};
+/// use hexa_lite::ports::{Metrics, SenderV1};
+/// use hexa_lite::prelude::*;
+/// use std::cell::RefCell;
+/// use std::collections::HashMap;
+///
+/// #[derive(Default)]
+/// struct Counters(RefCell<HashMap<String, u32>>);
+///
+/// impl Metrics for Counters {
+///     fn increment(&self, name: &str) {
+///         *self.0.borrow_mut().entry(name.to_string()).or_default() += 1;
+///     }
+/// }
+///
+/// // An adapter still written against the old Sender port.
+/// struct LegacyMailer;
+///
+/// impl SenderV1 for LegacyMailer {
+///     fn send(&self, _order: &Order) -> Result<(), OrderError> {
+///         Ok(())
+///     }
+/// }
+///
+/// let counters = Counters::default();
+/// let sender = V1Compat::new(LegacyMailer, &counters);
+/// let mut repo = InMemoryOrderRepository::new();
+/// let payment = MockPaymentGateway::new();
+/// OrderService::new(&repo, &payment, &sender)
+///     .place_order(vec![LineItem::new("Pen", Money(150))])?;
+///
+/// assert_eq!(counters.0.borrow()[V1_SEND_COUNTER], 1);
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait Metrics {
+    fn increment(&self, name: &str);
+
+    // One observation of a distribution, such as how long a call took.
+    // A backend that only counts may ignore them.
+    fn observe_histogram(&self, _name: &str, _value_ms: u64) {}
+}
+
+port_info!(Metrics, Outbound, [increment, observe_histogram]);
+
+// Output port: error telemetry.
+// The service describes every failure it returns: which use case, which
+// port was being called (None when a domain rule said no), for which order.
+// Reporting is best effort: it must never change what the caller gets.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::ports::{ErrorContext, ErrorReporter, Port};
+/// use hexa_lite::prelude::testing::SteppingClock;
+/// use hexa_lite::prelude::*;
+/// use std::sync::Mutex;
+///
+/// #[derive(Default)]
+/// struct Alerts(Mutex<Vec<ErrorContext>>);
+///
+/// impl ErrorReporter for Alerts {
+///     fn report(&self, context: ErrorContext) {
+///         self.0.lock().unwrap().push(context);
+///     }
+/// }
+///
+/// struct Declining;
+///
+/// impl PaymentGateway for Declining {
+///     fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+///         Err(OrderError::PaymentFailed)
+///     }
+/// }
+///
+/// let alerts = Alerts::default();
+/// let clock = SteppingClock::starting_at(Timestamp(1_700_000_000));
+/// let mut repo = InMemoryOrderRepository::new();
+/// let sender = ConsoleSender::new();
+/// let mut service =
+///     OrderService::new(&repo, &Declining, &sender).with_error_reporter(&alerts, &clock);
+///
+/// assert!(service.place_order(vec![LineItem::new("Pen", Money(150))]).is_err());
+/// let alerts = alerts.0.lock().unwrap();
+/// assert_eq!(alerts[0].use_case, "place_order");
+/// assert_eq!(alerts[0].port, Some(Port::Payment));
+/// ```
+pub trait ErrorReporter {
+    fn report(&self, context: ErrorContext);
+}
+
+port_info!(ErrorReporter, Outbound, [report]);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Port {
+    Repository,
+    Payment,
+    Sender,
+    Events,
+    ExchangeRates,
+    PendingCharges,
+    Idempotency,
+    FraudScreen,
+    Inventory,
+    Blobs,
+    // Not a port: an extension plugged into the service, see Hooks.
+    Hooks,
+}
+
+#[derive(Debug, Clone)]
+pub struct ErrorContext {
+    pub use_case: &'static str,
+    pub port: Option<Port>,
+    pub operation: &'static str,
+    pub order_id: Option<OrderId>,
+    pub at: Timestamp,
+    pub error: OrderError,
+}
+
+// Output port: where the adapters say what they are doing.
+// `target` names who speaks ("InMemory", "Stripe"...), so a logger can
+// filter or tag lines without reading them. Logging is best effort, like
+// error reporting: a logger never fails the call it describes.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::adapters::Console;
+/// use hexa_lite::ports::{Level, Logger};
+/// use hexa_lite::prelude::*;
+/// use std::fmt;
+/// use std::sync::{Arc, Mutex};
+///
+/// // Keeps the warnings only.
+/// #[derive(Clone, Default)]
+/// struct Warnings(Arc<Mutex<Vec<String>>>);
+///
+/// impl Logger for Warnings {
+///     fn log(&self, level: Level, target: &str, message: fmt::Arguments<'_>) {
+///         if level >= Level::Warn {
+///             self.0.lock().unwrap().push(format!("{target}: {message}"));
+///         }
+///     }
+/// }
+///
+/// let warnings = Warnings::default();
+/// let repo = InMemoryOrderRepository::new().with_console(Console::logger(warnings.clone()));
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// OrderService::new(&repo, &payment, &sender).place_order(vec![LineItem::new("Pen", Money(150))])?;
+///
+/// // Saving is an Info line: nothing kept.
+/// assert!(warnings.0.lock().unwrap().is_empty());
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait Logger {
+    fn log(&self, level: Level, target: &str, message: fmt::Arguments<'_>);
+}
+
+port_info!(Logger, Outbound, [log]);
+
+// Ordered: `level >= Level::Warn` keeps the warnings and the errors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Level {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+// Input port: what the outside world can ask the application to do.
+// Driving adapters (a CLI, an HTTP handler, a queue consumer...) depend on
+// this trait, not on OrderService, so they can be tested against a stub.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::prelude::*;
+///
+/// // A driving adapter only knows the port...
+/// fn order_one_pen(use_case: &mut dyn PlaceOrderUseCase) -> Result<Order, OrderError> {
+///     use_case.place_order(PlaceOrder {
+///         items: vec![LineItem::new("Pen", Money(150))],
+///         placed_at: None,
+///     })
+/// }
+///
+/// // ...so it runs against the real service,
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service = OrderService::new(&repo, &payment, &sender);
+/// assert_eq!(order_one_pen(&mut service)?.total, Money(150));
+///
+/// // and against a stub, in the adapter's own tests.
+/// struct Closed;
+///
+/// impl PlaceOrderUseCase for Closed {
+///     fn place_order(&mut self, _command: PlaceOrder) -> Result<Order, OrderError> {
+///         Err(OrderError::InvalidOrder)
+///     }
+/// }
+///
+/// assert!(order_one_pen(&mut Closed).is_err());
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait PlaceOrderUseCase {
+    fn place_order(&mut self, command: PlaceOrder) -> Result<Order, OrderError>;
+}
+
+port_info!(PlaceOrderUseCase, Inbound, [place_order]);
+
+// Input port: "the customer changes their mind". See
+// OrderService::cancel_order for what happens to the money.
+pub trait CancelOrderUseCase {
+    fn cancel_order(&mut self, id: OrderId) -> Result<Order, OrderError>;
+}
+
+port_info!(CancelOrderUseCase, Inbound, [cancel_order]);
+
+// Input port: "the customer changes where it goes, or what the card says".
+// Only until the order ships; what is wrong with the request comes back
+// field by field, in OrderError::InvalidFields, for the customer to fix.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::domain::Address;
+/// use hexa_lite::ports::{AmendOrder, AmendOrderUseCase};
+/// use hexa_lite::prelude::*;
+///
+/// let mut repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let mut service = OrderService::new(&repo, &payment, &sender);
+/// let order = service.place_order(vec![LineItem::new("Pen", Money(150))])?;
+///
+/// let use_case: &mut dyn AmendOrderUseCase = &mut service;
+/// let amended = use_case.amend_order(AmendOrder {
+///     order_id: order.id,
+///     new_address: None,
+///     gift_note: Some("Happy birthday!".to_string()),
+/// })?;
+/// assert_eq!(amended.gift_note.as_deref(), Some("Happy birthday!"));
+///
+/// let too_long = use_case.amend_order(AmendOrder {
+///     order_id: order.id,
+///     new_address: None,
+///     gift_note: Some("x".repeat(312)),
+/// });
+/// assert!(matches!(
+///     too_long,
+///     Err(OrderError::InvalidFields { errors })
+///         if errors.to_string() == "gift_note: too long (312/200)"
+/// ));
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait AmendOrderUseCase {
+    fn amend_order(&mut self, command: AmendOrder) -> Result<Order, OrderError>;
+}
+
+port_info!(AmendOrderUseCase, Inbound, [amend_order]);
+
+// Input port: what the outside world can ask about the orders, without
+// changing any. OrderBrowser answers it.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::application::OrderBrowser;
+/// use hexa_lite::ports::OrderQueries;
+/// use hexa_lite::prelude::*;
+///
+/// // A status page only knows the port.
+/// fn status(queries: &dyn OrderQueries) -> String {
+///     match (queries.health(), queries.revenue()) {
+///         (Ok(()), Ok(revenue)) => format!("up, {revenue} taken"),
+///         _ => "down".to_string(),
+///     }
+/// }
+///
+/// let repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// OrderService::new(&repo, &payment, &sender).place_order(vec![LineItem::new("Pen", Money(150))])?;
+///
+/// assert_eq!(status(&OrderBrowser::new(&repo)), "up, $1.50 taken");
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait OrderQueries {
+    // By ascending id.
+    fn list_orders(&self) -> Result<Vec<Order>, OrderError>;
+    // What was charged and kept: the totals of Paid and Shipped orders.
+    // One amount, so MixedCurrencies once any of them is not in dollars:
+    // see revenue_by_currency.
+    fn revenue(&self) -> Result<Money, OrderError>;
+    // Ok when the orders can be read at all.
+    fn health(&self) -> Result<(), OrderError>;
+    // revenue(), stopped with Cancelled once `cancel` is. A service that
+    // cannot stop halfway may ignore it.
+    fn revenue_cancellable(&self, cancel: &CancelToken) -> Result<Money, OrderError> {
+        cancel.check(0)?;
+        self.revenue()
+    }
+    // The same orders as revenue(), added up in each one's own currency.
+    fn revenue_by_currency(&self) -> Result<CurrencyTotals, OrderError> {
+        let mut totals = CurrencyTotals::new();
+        for order in self.list_orders()? {
+            if matches!(order.status, OrderStatus::Paid | OrderStatus::Shipped) {
+                totals.add(order.currency, order.total);
+            }
+        }
+        Ok(totals)
+    }
+    // How far the answers lag behind the orders as written. Answers read
+    // from the repository itself never do: the default.
+    fn freshness(&self) -> Result<Freshness, OrderError> {
+        Ok(Freshness::default())
+    }
+}
+
+port_info!(
+    OrderQueries,
+    Inbound,
+    [
+        list_orders,
+        revenue,
+        health,
+        revenue_cancellable,
+        revenue_by_currency,
+        freshness
+    ]
+);
+
+// What a read side has not caught up with yet. The default is up to date.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Freshness {
+    // Events received and not yet applied.
+    pub pending_events: usize,
+    // When the oldest of them was received.
+    pub oldest_pending: Option<Timestamp>,
+}
+
+impl Freshness {
+    pub fn is_current(&self) -> bool {
+        self.pending_events == 0
+    }
+}
+
+// The command carried by the input port.
+// Plain data: easy to build from a CLI line, a JSON body or a queue message.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaceOrder {
+    pub items: Vec<LineItem>,
+    // When the client says it placed the order, if it says. The service
+    // checks it against its own clock.
+    pub placed_at: Option<Timestamp>,
+}
+
+// The command of AmendOrderUseCase. What is None stays as it is; an empty
+// gift note removes the note.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AmendOrder {
+    pub order_id: OrderId,
+    pub new_address: Option<Address>,
+    pub gift_note: Option<String>,
+}
+
+// Input port: the whole service as one object, for a GUI or a TUI to hold.
+// The commands and queries above, and a preview of a cart, every one of
+// them through &self: a toolkit's callbacks only ever get a shared
+// reference, often on a thread of their own. facade::DefaultFacade puts it
+// in front of the services; the REPL and the HTTP adapter use nothing else.
+/// # Examples
+///
+/// ```
+/// use hexa_lite::application::OrderBrowser;
+/// use hexa_lite::facade::DefaultFacade;
+/// use hexa_lite::ports::OrderFacade;
+/// use hexa_lite::prelude::*;
+/// use std::sync::Arc;
+///
+/// let repo = InMemoryOrderRepository::new();
+/// let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+/// let service = OrderService::new(&repo, &payment, &sender);
+/// let facade: Arc<dyn OrderFacade + Send + Sync> =
+///     DefaultFacade::new(service, OrderBrowser::new(&repo)).shared();
+///
+/// // What a button's callback gets: a clone of the Arc, nothing mutable.
+/// let on_click = {
+///     let facade = Arc::clone(&facade);
+///     move || facade.place_order(PlaceOrder {
+///         items: vec![LineItem::new("Pen", Money(150))],
+///         placed_at: None,
+///     })
+/// };
+/// assert_eq!(on_click()?.status, OrderStatus::Paid);
+/// assert_eq!(facade.revenue()?, Money(150));
+/// # Ok::<(), OrderError>(())
+/// ```
+pub trait OrderFacade {
+    fn place_order(&self, command: PlaceOrder) -> Result<Order, OrderError>;
+    fn amend_order(&self, command: AmendOrder) -> Result<Order, OrderError>;
+    fn cancel_order(&self, id: OrderId) -> Result<Order, OrderError>;
+    // What placing `command` would come to, with nothing saved, charged or
+    // sent. Only the domain's rules are checked: payment, stock and fraud
+    // screening are for when it is placed.
+    fn preview_order(&self, command: &PlaceOrder) -> Result<OrderPreview, OrderError>;
+    // As OrderQueries answers them.
+    fn list_orders(&self) -> Result<Vec<Order>, OrderError>;
+    fn revenue(&self) -> Result<Money, OrderError>;
+    fn revenue_cancellable(&self, cancel: &CancelToken) -> Result<Money, OrderError>;
+    fn revenue_by_currency(&self) -> Result<CurrencyTotals, OrderError>;
+    fn freshness(&self) -> Result<Freshness, OrderError>;
+    fn health(&self) -> Result<(), OrderError>;
+}
+
+port_info!(
+    OrderFacade,
+    Inbound,
+    [
+        place_order,
+        amend_order,
+        cancel_order,
+        preview_order,
+        list_orders,
+        revenue,
+        revenue_cancellable,
+        revenue_by_currency,
+        freshness,
+        health
+    ]
+);
+
+// A facade borrowed or shared is one still: an Arc<dyn OrderFacade> goes
+// wherever an OrderFacade does.
+impl<F: OrderFacade + ?Sized> OrderFacade for &F {
+    fn place_order(&self, command: PlaceOrder) -> Result<Order, OrderError> {
+        (**self).place_order(command)
+    }
+
+    fn amend_order(&self, command: AmendOrder) -> Result<Order, OrderError> {
+        (**self).amend_order(command)
+    }
+
+    fn cancel_order(&self, id: OrderId) -> Result<Order, OrderError> {
+        (**self).cancel_order(id)
+    }
+
+    fn preview_order(&self, command: &PlaceOrder) -> Result<OrderPreview, OrderError> {
+        (**self).preview_order(command)
+    }
+
+    fn list_orders(&self) -> Result<Vec<Order>, OrderError> {
+        (**self).list_orders()
+    }
+
+    fn revenue(&self) -> Result<Money, OrderError> {
+        (**self).revenue()
+    }
+
+    fn revenue_cancellable(&self, cancel: &CancelToken) -> Result<Money, OrderError> {
+        (**self).revenue_cancellable(cancel)
+    }
+
+    fn revenue_by_currency(&self) -> Result<CurrencyTotals, OrderError> {
+        (**self).revenue_by_currency()
+    }
+
+    fn freshness(&self) -> Result<Freshness, OrderError> {
+        (**self).freshness()
+    }
+
+    fn health(&self) -> Result<(), OrderError> {
+        (**self).health()
+    }
+}
+
+impl<F: OrderFacade + ?Sized> OrderFacade for Arc<F> {
+    fn place_order(&self, command: PlaceOrder) -> Result<Order, OrderError> {
+        (**self).place_order(command)
+    }
+
+    fn amend_order(&self, command: AmendOrder) -> Result<Order, OrderError> {
+        (**self).amend_order(command)
+    }
+
+    fn cancel_order(&self, id: OrderId) -> Result<Order, OrderError> {
+        (**self).cancel_order(id)
+    }
+
+    fn preview_order(&self, command: &PlaceOrder) -> Result<OrderPreview, OrderError> {
+        (**self).preview_order(command)
+    }
+
+    fn list_orders(&self) -> Result<Vec<Order>, OrderError> {
+        (**self).list_orders()
+    }
+
+    fn revenue(&self) -> Result<Money, OrderError> {
+        (**self).revenue()
+    }
+
+    fn revenue_cancellable(&self, cancel: &CancelToken) -> Result<Money, OrderError> {
+        (**self).revenue_cancellable(cancel)
+    }
+
+    fn revenue_by_currency(&self) -> Result<CurrencyTotals, OrderError> {
+        (**self).revenue_by_currency()
+    }
+
+    fn freshness(&self) -> Result<Freshness, OrderError> {
+        (**self).freshness()
+    }
+
+    fn health(&self) -> Result<(), OrderError> {
+        (**self).health()
+    }
+}
+
+// What OrderFacade::preview_order says of a cart.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OrderPreview {
+    pub item_count: usize,
+    pub total: Money,
+    pub currency: Currency,
+}
+
+// Optional capabilities.
+// Some adapters can do more than their port promises: a database can group
+// writes in a transaction, a buffered sender can be flushed. The application
+// never needs these, but the composition root may want to use them on
+// whatever adapters support them, without downcasting to concrete types.
+//
+// Every adapter implements Capability; the defaults answer "not supported".
+pub trait Capability {
+    fn as_flushable(&mut self) -> Option<&mut dyn Flushable> {
+        None
+    }
+
+    fn as_transactional(&mut self) -> Option<&mut dyn UnitOfWork> {
+        None
+    }
+}
+
+// Pushes out anything held back by the adapter.
+pub trait Flushable {
+    fn flush(&mut self) -> Result<(), OrderError>;
+}
+
+// Groups writes so they become visible all at once, or not at all.
+pub trait UnitOfWork {
+    fn begin(&mut self);
+    fn commit(&mut self) -> Result<(), OrderError>;
+    fn rollback(&mut self);
+}
+
+// What an adapter holding state does before the process exits: send what
+// it held back, make sure what it wrote is on disk. The composition root
+// calls it once the service is done with the adapter, through a
+// composition::ShutdownCoordinator; nothing calls the adapter after it.
+pub trait Shutdown {
+    fn shutdown(&mut self) -> Result<(), ShutdownError>;
+}
+
+#[derive(Debug, Clone)]
+pub enum ShutdownError {
+    // What the adapter's port would have told the service.
+    Failed(OrderError),
+    Io { path: PathBuf, kind: io::ErrorKind },
+    // Work the adapter had to give up on, lost with the process.
+    Abandoned { count: usize, what: &'static str },
+}
+
+impl fmt::Display for ShutdownError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ShutdownError::Failed(error) => write!(f, "{error}"),
+            ShutdownError::Io { path, kind } => write!(f, "{}: {kind}", path.display()),
+            ShutdownError::Abandoned { count, what } => write!(f, "{count} {what} abandoned"),
+        }
+    }
+}
+
+impl From<OrderError> for ShutdownError {
+    fn from(error: OrderError) -> Self {
+        ShutdownError::Failed(error)
+    }
+}
+
+// Supertraits other crates cannot name, so cannot implement: the traits
+// requiring them are only implemented here.
+mod sealed {
+    pub trait ChargeConfirmed {}
+    pub trait SendConfirmed {}
+}
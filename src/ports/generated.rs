@@ -0,0 +1,6 @@
+// Ports generated from ports.toml by build.rs, with a Spy and a Noop
+// adapter for each: a team with many ports describes them once, and gets
+// the test doubles with them. The traits are the handwritten ones of this
+// module's parent, method for method, but without their defaults: every
+// method is for the adapter to write.
+include!(concat!(env!("OUT_DIR"), "/ports.rs"));
@@ -0,0 +1,36 @@
+// One import for the common case:
+//
+//     use hexa_lite::prelude::*;
+//
+// brings the domain vocabulary, the ports, the application service and the
+// in-memory adapters into scope. Anything more specialised (external
+// adapters, the queue consumer, the webhook sender...) is imported from its
+// own module, so reading the use lines still tells you what a file depends on.
+//
+// The test helpers live under prelude::testing so they never clash with
+// production names.
+
+#[doc(inline)]
+pub use crate::domain::{
+    BasisPoints, LineItem, Money, Order, OrderConfirmation, OrderError, OrderId, OrderStatus,
+    Percent, Timestamp,
+};
+
+#[doc(inline)]
+pub use crate::ports::{
+    Clock, OrderReader, OrderWriter, PaymentGateway, PlaceOrder, PlaceOrderUseCase, Sender,
+};
+
+#[doc(inline)]
+pub use crate::application::OrderService;
+
+#[doc(inline)]
+pub use crate::adapters::in_memory::{ConsoleSender, InMemoryOrderRepository, MockPaymentGateway};
+
+pub mod testing {
+    #[doc(inline)]
+    pub use crate::testing::{
+        GlobalRecorder, OrderAssert, Recorded, ScenarioTranscript, SteppingClock,
+        assert_err_variant, assert_order, assert_orders_eq, expect_sequence,
+    };
+}
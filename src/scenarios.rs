@@ -0,0 +1,374 @@
+// =============================================================================
+// SCENARIOS - The demos, by name
+// =============================================================================
+// Twenty examples are a lot to browse. The demos that need nothing from
+// the outside world are registered here under a name, with a line saying
+// what they show, and the hexlite binary lists and runs them:
+//
+//     cargo run --bin hexlite -- list
+//     cargo run --bin hexlite -- run approval
+//
+// A scenario wires its own adapters and drives them through a
+// TutorialRunner: its steps and notes are the story, its checkpoints what
+// must hold. Everything it prints, adapters included, is captured on the
+// runner's console, and the run ends with the runner's summary.
+//
+// More can be added next to the library's own:
+//
+//     let mut registry = ScenarioRegistry::standard();
+//     registry.register_scenario("mine", "what it shows", my_scenario)?;
+use crate::adapters::approval::ThresholdApproval;
+use crate::adapters::conflict::ItemUnionMerge;
+use crate::adapters::fraud::RuleBasedScreen;
+use crate::adapters::in_memory::{ConsoleSender, InMemoryOrderRepository, MockPaymentGateway};
+use crate::adapters::{Console, SharedBuffer};
+use crate::application::OrderService;
+use crate::domain::{ApproverId, Customer, LineItem, Money, Order, OrderId, OrderStatus};
+use crate::domain::{OrderError, ReviewDecision};
+use crate::ports::{ChargeLog, OrderReader, OrderWriter};
+use crate::tutorial::TutorialRunner;
+use std::fmt;
+use std::panic::{self, AssertUnwindSafe};
+
+pub type ScenarioFn = fn(&mut TutorialRunner);
+
+#[derive(Debug, Clone, Copy)]
+pub struct Scenario {
+    pub name: &'static str,
+    // One line, for `hexlite list`.
+    pub description: &'static str,
+    pub run: ScenarioFn,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScenarioError {
+    // Names are what `hexlite run` is given: one word, and unique.
+    InvalidName { name: String },
+    AlreadyRegistered { name: String },
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidName { name } => write!(f, "{name:?} is not a scenario name"),
+            Self::AlreadyRegistered { name } => write!(f, "{name} is already registered"),
+        }
+    }
+}
+
+impl std::error::Error for ScenarioError {}
+
+// In the order they were registered, which is the order they are listed.
+#[derive(Debug, Default)]
+pub struct ScenarioRegistry {
+    scenarios: Vec<Scenario>,
+}
+
+// What a run printed and how it went. `summary` is the runner's table,
+// then one line: `PASS <name>` or `FAIL <name>`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScenarioReport {
+    pub name: &'static str,
+    pub output: String,
+    pub summary: String,
+    pub passed: bool,
+}
+
+impl ScenarioRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // The library's demos.
+    pub fn standard() -> Self {
+        let mut registry = Self::new();
+        let standard: [(&'static str, &'static str, ScenarioFn); 4] = [
+            (
+                "quickstart",
+                "Orders placed, charged and confirmed in memory",
+                quickstart,
+            ),
+            (
+                "approval",
+                "A large order waits uncharged until someone approves it",
+                approval,
+            ),
+            (
+                "fraud-review",
+                "The fraud screen refuses one customer and sends a large order to review",
+                fraud_review,
+            ),
+            (
+                "draft-conflict",
+                "Two tabs edit one cart; their additions are merged",
+                draft_conflict,
+            ),
+        ];
+        for (name, description, run) in standard {
+            registry
+                .register_scenario(name, description, run)
+                .expect("the standard scenarios have distinct, valid names");
+        }
+        registry
+    }
+
+    pub fn register_scenario(
+        &mut self,
+        name: &'static str,
+        description: &'static str,
+        run: ScenarioFn,
+    ) -> Result<(), ScenarioError> {
+        if name.is_empty() || name.chars().any(char::is_whitespace) {
+            return Err(ScenarioError::InvalidName {
+                name: name.to_string(),
+            });
+        }
+        if self.find(name).is_some() {
+            return Err(ScenarioError::AlreadyRegistered {
+                name: name.to_string(),
+            });
+        }
+        self.scenarios.push(Scenario {
+            name,
+            description,
+            run,
+        });
+        Ok(())
+    }
+
+    pub fn scenarios(&self) -> &[Scenario] {
+        &self.scenarios
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Scenario> {
+        self.scenarios.iter().find(|scenario| scenario.name == name)
+    }
+
+    // One scenario a line, descriptions aligned:
+    //
+    //     quickstart    Orders placed, charged and confirmed in memory
+    //     approval      A large order waits uncharged until someone approves it
+    pub fn list(&self) -> String {
+        let width = self
+            .scenarios
+            .iter()
+            .map(|scenario| scenario.name.chars().count())
+            .max()
+            .unwrap_or(0);
+        self.scenarios
+            .iter()
+            .map(|scenario| format!("{:<width$}  {}", scenario.name, scenario.description))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    // None for a name nobody registered. A scenario that panics fails,
+    // with what it printed until then.
+    pub fn run(&self, name: &str) -> Option<ScenarioReport> {
+        let scenario = self.find(name)?;
+        let output = SharedBuffer::new();
+        let mut runner = TutorialRunner::new().with_console(Console::to(output.clone()));
+        let completed =
+            panic::catch_unwind(AssertUnwindSafe(|| (scenario.run)(&mut runner))).is_ok();
+        if !completed {
+            runner.note("the scenario panicked");
+        }
+        let passed = completed && runner.all_passed();
+        let verdict = if passed { "PASS" } else { "FAIL" };
+        Some(ScenarioReport {
+            name: scenario.name,
+            output: output.contents(),
+            summary: format!("{}\n{verdict} {}", runner.summary(), scenario.name),
+            passed,
+        })
+    }
+}
+
+fn item(name: &str, cents: u32) -> LineItem {
+    LineItem::new(name, Money(cents))
+}
+
+fn quickstart(runner: &mut TutorialRunner) {
+    let console = runner.console();
+    let repo = InMemoryOrderRepository::new().with_console(console.clone());
+    let payment = MockPaymentGateway::new().with_console(console.clone());
+    let sender = ConsoleSender::new().with_console(console);
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    runner.step("A book, then a keyboard and a mouse");
+    let book = service.place_order(vec![item("Rust Book", 4_999)]);
+    let desk = service.place_order(vec![item("Keyboard", 12_999), item("Mouse", 2_500)]);
+    runner.checkpoint("both orders are paid", || {
+        [&book, &desk]
+            .iter()
+            .all(|order| order.as_ref().is_ok_and(|o| o.status == OrderStatus::Paid))
+    });
+    runner.checkpoint("each was charged its total", || {
+        let charged: Vec<u32> = payment
+            .charges()
+            .unwrap()
+            .iter()
+            .map(|c| c.amount.0)
+            .collect();
+        charged == [4_999, 15_499]
+    });
+
+    runner.step("An empty cart");
+    let empty = service.place_order(vec![]);
+    runner.checkpoint("it is refused, and nothing is stored", || {
+        matches!(empty, Err(OrderError::InvalidOrder)) && repo.list().unwrap().len() == 2
+    });
+}
+
+fn approval(runner: &mut TutorialRunner) {
+    let console = runner.console();
+    let repo = InMemoryOrderRepository::new().with_console(console.clone());
+    let payment = MockPaymentGateway::new().with_console(console.clone());
+    let sender = ConsoleSender::new().with_console(console);
+    let policy = ThresholdApproval::new(Money(100_000));
+    let mut service = OrderService::new(&repo, &payment, &sender).with_approval_policy(&policy);
+
+    runner.step("A workstation, above the $1000.00 threshold");
+    runner.note("Held for approval: not charged, not confirmed.");
+    let held = service.place_order(vec![item("Workstation", 349_900)]);
+    runner.checkpoint("the order waits, uncharged", || {
+        held.as_ref()
+            .is_ok_and(|o| o.status == OrderStatus::PendingApproval)
+            && payment.charges().unwrap().is_empty()
+    });
+
+    runner.step("A manager approves it");
+    let approved = held.and_then(|order| {
+        service.approve_order(order.id, ApproverId("manager@example.test".to_string()))
+    });
+    runner.checkpoint("it is charged and paid", || {
+        approved
+            .as_ref()
+            .is_ok_and(|o| o.status == OrderStatus::Paid)
+            && payment.charges().unwrap().len() == 1
+    });
+}
+
+fn fraud_review(runner: &mut TutorialRunner) {
+    let console = runner.console();
+    let repo = InMemoryOrderRepository::new().with_console(console.clone());
+    let payment = MockPaymentGateway::new().with_console(console.clone());
+    let sender = ConsoleSender::new().with_console(console);
+    let screen = RuleBasedScreen::new()
+        .with_max_total(Money(50_000))
+        .with_denied_name("Mallory");
+    let mut service = OrderService::new(&repo, &payment, &sender).with_fraud_screen(&screen);
+
+    runner.step("Mallory, denylisted, orders a pen");
+    let refused = service.place_order_for(&Customer::new("Mallory"), vec![item("Pen", 150)]);
+    runner.checkpoint("the order is refused before any charge", || {
+        matches!(refused, Err(OrderError::FraudSuspected { .. }))
+            && payment.charges().unwrap().is_empty()
+    });
+
+    runner.step("Alice orders a $3499.00 workstation");
+    let alice = Customer::new("Alice");
+    let parked = service.place_order_for(&alice, vec![item("Workstation", 349_900)]);
+    runner.checkpoint("the order waits UnderReview", || {
+        parked
+            .as_ref()
+            .is_ok_and(|o| o.status == OrderStatus::UnderReview)
+    });
+    let resolved =
+        parked.and_then(|order| service.resolve_review(order.id, ReviewDecision::Accept));
+    runner.checkpoint("accepted, it is charged and paid", || {
+        resolved
+            .as_ref()
+            .is_ok_and(|o| o.status == OrderStatus::Paid)
+            && payment.charges().unwrap().len() == 1
+    });
+}
+
+fn draft_conflict(runner: &mut TutorialRunner) {
+    let console = runner.console();
+    let repo = InMemoryOrderRepository::new().with_console(console.clone());
+    let payment = MockPaymentGateway::new().with_console(console.clone());
+    let sender = ConsoleSender::new().with_console(console);
+    let mut tab_a = OrderService::new(&repo, &payment, &sender);
+    let mut tab_b =
+        OrderService::new(&repo, &payment, &sender).with_conflict_resolver(&ItemUnionMerge);
+
+    runner.step("One cart, open in two tabs");
+    let cart = Order::new(OrderId(1), vec![item("Keyboard", 12_999)]);
+    let base = cart.and_then(|cart| repo.save(&cart).map(|()| cart));
+    runner.checkpoint("the cart is stored", || base.is_ok());
+    let Ok(base) = base else {
+        return;
+    };
+
+    runner.step("Each tab adds an item");
+    let mut in_a = base.clone();
+    in_a.items.push(item("Mouse", 2_500));
+    let mut in_b = base.clone();
+    in_b.items.push(item("Cable", 500));
+    let first = tab_a.update_draft_with_resolution(&base, in_a);
+    runner.note("Tab B writes second, and meets a conflict.");
+    let merged = tab_b.update_draft_with_resolution(&base, in_b);
+    runner.checkpoint("the first write goes through", || first.is_ok());
+    runner.checkpoint("the cart holds both additions", || {
+        let names = |order: &Order| -> Vec<String> {
+            order.items.iter().map(|item| item.name.clone()).collect()
+        };
+        merged
+            .as_ref()
+            .is_ok_and(|o| names(o) == ["Keyboard", "Mouse", "Cable"] && o.total == Money(15_999))
+            && repo.find(OrderId(1)).unwrap().as_ref() == merged.as_ref().ok()
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nothing(_: &mut TutorialRunner) {}
+
+    #[test]
+    fn names_are_single_unique_words() {
+        let mut registry = ScenarioRegistry::standard();
+
+        assert_eq!(
+            registry.register_scenario("two words", "", nothing),
+            Err(ScenarioError::InvalidName {
+                name: "two words".to_string()
+            })
+        );
+        assert_eq!(
+            registry.register_scenario("approval", "", nothing),
+            Err(ScenarioError::AlreadyRegistered {
+                name: "approval".to_string()
+            })
+        );
+        registry.register_scenario("mine", "Mine", nothing).unwrap();
+        assert_eq!(registry.scenarios().last().unwrap().name, "mine");
+    }
+
+    #[test]
+    fn every_standard_scenario_passes() {
+        let registry = ScenarioRegistry::standard();
+
+        for scenario in registry.scenarios() {
+            let report = registry.run(scenario.name).unwrap();
+            assert!(report.passed, "{}\n{}", report.output, report.summary);
+        }
+        assert_eq!(registry.run("missing"), None);
+    }
+
+    #[test]
+    fn a_panicking_scenario_fails() {
+        let mut registry = ScenarioRegistry::new();
+        registry
+            .register_scenario("boom", "Panics", |_| panic!("boom"))
+            .unwrap();
+
+        let report = registry.run("boom").unwrap();
+
+        assert!(!report.passed);
+        assert!(report.output.contains("the scenario panicked"));
+        assert!(report.summary.ends_with("\nFAIL boom"));
+    }
+}
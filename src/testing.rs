@@ -0,0 +1,328 @@
+// =============================================================================
+// TESTING - Helpers for tests (ours and yours)
+// =============================================================================
+// Asserting on an Order field by field gets repetitive and, worse, a failing
+// `assert_eq!(order.total.0, 17998)` does not tell you which order it was
+// looking at. These helpers name the field that differed and dump the order.
+use crate::adapters::{Console, SharedBuffer};
+use crate::domain::{Order, OrderStatus, Timestamp};
+use crate::ports::{Capability, Clock};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+#[cfg(not(target_arch = "wasm32"))]
+mod bench;
+mod characterize;
+mod diff;
+#[cfg(not(target_arch = "wasm32"))]
+mod failpoint;
+mod generator;
+mod recorder;
+mod sender_contract;
+pub mod stubs;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use bench::{BenchProfile, BenchTiming, bench_profile};
+pub use characterize::characterize;
+pub use diff::{
+    CONTEXT_LINES, FieldChange, OrderChange, OrderDiff, assert_orders_eq, diff_orders, diff_text,
+};
+#[cfg(not(target_arch = "wasm32"))]
+pub use failpoint::FailpointFs;
+pub use generator::{DEFAULT_NAMES, OrderGenerator};
+pub use recorder::{Call, GlobalRecorder, Recorded, SequenceMismatch, check_sequence};
+pub use sender_contract::{SenderContract, sender_contract};
+
+// Fluent assertions on an Order:
+//
+//     assert_order(&order).has_id(1).has_total_cents(17998).has_item_named("Keyboard");
+//
+// Every method panics on mismatch and returns the asserter so calls can chain.
+pub fn assert_order(order: &Order) -> OrderAssert<'_> {
+    OrderAssert { order }
+}
+
+pub struct OrderAssert<'a> {
+    order: &'a Order,
+}
+
+impl OrderAssert<'_> {
+    #[track_caller]
+    pub fn has_id(self, expected: u32) -> Self {
+        if self.order.id.0 != expected {
+            self.fail("id", expected, self.order.id.0);
+        }
+        self
+    }
+
+    #[track_caller]
+    pub fn has_total_cents(self, expected: u32) -> Self {
+        if self.order.total.0 != expected {
+            self.fail("total", expected, self.order.total.0);
+        }
+        self
+    }
+
+    #[track_caller]
+    pub fn has_item_count(self, expected: usize) -> Self {
+        if self.order.items.len() != expected {
+            self.fail("items.len()", expected, self.order.items.len());
+        }
+        self
+    }
+
+    #[track_caller]
+    pub fn has_item_named(self, name: &str) -> Self {
+        if !self.order.items.iter().any(|item| item.name == name) {
+            let names: Vec<&str> = self.order.items.iter().map(|i| i.name.as_str()).collect();
+            self.fail(
+                "items",
+                format!("an item named {name:?}"),
+                format!("{names:?}"),
+            );
+        }
+        self
+    }
+
+    #[track_caller]
+    pub fn has_status(self, expected: OrderStatus) -> Self {
+        if self.order.status != expected {
+            self.fail("status", expected, self.order.status);
+        }
+        self
+    }
+
+    #[track_caller]
+    pub fn has_shipment_count(self, expected: usize) -> Self {
+        if self.order.shipments.len() != expected {
+            self.fail("shipments.len()", expected, self.order.shipments.len());
+        }
+        self
+    }
+
+    #[track_caller]
+    fn fail(&self, field: &str, expected: impl std::fmt::Debug, actual: impl std::fmt::Debug) -> ! {
+        panic!(
+            "order {field} mismatch: expected {expected:?}, got {actual:?}\n  order: {:#?}",
+            self.order
+        )
+    }
+}
+
+// OrderError does not implement PartialEq (errors rarely should), so
+// `assert_eq!(result.unwrap_err(), OrderError::PaymentFailed)` does not compile.
+// This macro matches on the variant instead:
+//
+//     assert_err_variant!(service.place_order(vec![]), OrderError::InvalidOrder);
+#[macro_export]
+macro_rules! assert_err_variant {
+    ($result:expr, $variant:pat $(,)?) => {
+        match $result {
+            Err($variant) => {}
+            Err(other) => panic!(
+                "expected Err({}), got Err({:?})",
+                stringify!($variant),
+                other
+            ),
+            Ok(value) => panic!(
+                "expected Err({}), got Ok({:?})",
+                stringify!($variant),
+                value
+            ),
+        }
+    };
+}
+
+pub use crate::assert_err_variant;
+pub use crate::expect_sequence;
+
+// A clock that only moves when told to.
+// Time-dependent rules can then be tested exactly at their boundaries.
+// It is Sync, so it can be handed to services running on another thread.
+pub struct SteppingClock {
+    now: Mutex<Timestamp>,
+}
+
+impl SteppingClock {
+    pub fn starting_at(start: Timestamp) -> Self {
+        Self {
+            now: Mutex::new(start),
+        }
+    }
+
+    pub fn advance_secs(&self, secs: u64) {
+        let mut now = self.now.lock().unwrap_or_else(PoisonError::into_inner);
+        *now = now.plus_secs(secs);
+    }
+
+    pub fn set(&self, now: Timestamp) {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner) = now;
+    }
+}
+
+impl Clock for SteppingClock {
+    fn now(&self) -> Timestamp {
+        *self.now.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl Capability for SteppingClock {}
+
+// Everything a scenario printed, in order, compared against a committed
+// golden file. Hand transcript.console() to every adapter of the scenario,
+// mark its steps with section(), then:
+//
+//     transcript.assert_matches_golden("tests/golden/demo.txt");
+//
+// When the output changes on purpose, rewrite the golden files with
+//
+//     UPDATE_GOLDEN=1 cargo test
+//
+// and review the diff like any other change.
+pub const UPDATE_GOLDEN_VAR: &str = "UPDATE_GOLDEN";
+
+#[derive(Default)]
+pub struct ScenarioTranscript {
+    buffer: SharedBuffer,
+}
+
+impl ScenarioTranscript {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn console(&self) -> Console {
+        Console::to(self.buffer.clone())
+    }
+
+    pub fn section(&self, title: &str) {
+        self.console().line(format_args!("--- {title} ---"));
+    }
+
+    // For what the scenario itself reports, outside of any adapter.
+    pub fn note(&self, text: &str) {
+        self.console().line(format_args!("{text}"));
+    }
+
+    pub fn text(&self) -> String {
+        self.buffer.contents()
+    }
+
+    // A relative path is taken from the directory cargo runs tests in, the
+    // crate root.
+    #[track_caller]
+    pub fn assert_matches_golden(&self, path: impl AsRef<Path>) {
+        let path = path.as_ref();
+        let actual = self.text();
+        if std::env::var(UPDATE_GOLDEN_VAR).is_ok_and(|value| value == "1") {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).unwrap();
+            }
+            fs::write(path, &actual).unwrap();
+            return;
+        }
+        let expected = fs::read_to_string(path).unwrap_or_else(|error| {
+            panic!(
+                "cannot read golden file {}: {error}\n  run with {UPDATE_GOLDEN_VAR}=1 to create it",
+                path.display()
+            )
+        });
+        if let Some(line) = first_difference(&expected, &actual) {
+            panic!(
+                "transcript differs from {} at line {}\n  run with {UPDATE_GOLDEN_VAR}=1 to accept the new output\n\n{}",
+                path.display(),
+                line + 1,
+                diff_text(&expected, &actual),
+            );
+        }
+    }
+}
+
+// Line by line, so golden files checked out with \r\n endings still match.
+fn first_difference(expected: &str, actual: &str) -> Option<usize> {
+    let mut expected = expected.lines();
+    let mut actual = actual.lines();
+    for line in 0.. {
+        match (expected.next(), actual.next()) {
+            (None, None) => return None,
+            (e, a) if e != a => return Some(line),
+            _ => {}
+        }
+    }
+    unreachable!()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{LineItem, Money, OrderError, OrderId};
+
+    fn order() -> Order {
+        Order::new(
+            OrderId(7),
+            vec![LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            }],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn matching_order_passes() {
+        assert_order(&order())
+            .has_id(7)
+            .has_total_cents(2500)
+            .has_item_count(1)
+            .has_item_named("Mouse");
+    }
+
+    #[test]
+    #[should_panic(expected = "order total mismatch: expected 100, got 2500")]
+    fn mismatch_names_the_field() {
+        assert_order(&order()).has_total_cents(100);
+    }
+
+    #[test]
+    #[should_panic(expected = "name: \"Mouse\"")]
+    fn mismatch_dumps_the_order() {
+        assert_order(&order()).has_id(8);
+    }
+
+    #[test]
+    #[should_panic(expected = "order items mismatch")]
+    fn missing_item_is_reported() {
+        assert_order(&order()).has_item_named("Keyboard");
+    }
+
+    #[test]
+    #[should_panic(expected = "order status mismatch: expected Paid, got Placed")]
+    fn status_mismatch_is_reported() {
+        assert_order(&order()).has_status(OrderStatus::Paid);
+    }
+
+    #[test]
+    fn stepping_clock_moves_only_when_told() {
+        let clock = SteppingClock::starting_at(Timestamp(1_000));
+
+        assert_eq!(clock.now(), Timestamp(1_000));
+        assert_eq!(clock.now(), Timestamp(1_000));
+        clock.advance_secs(60);
+        assert_eq!(clock.now(), Timestamp(1_060));
+    }
+
+    #[test]
+    #[should_panic(expected = "expected Err(OrderError::PaymentFailed), got Err(StorageFailed)")]
+    fn wrong_error_variant_is_reported() {
+        let result: Result<(), OrderError> = Err(OrderError::StorageFailed);
+        assert_err_variant!(result, OrderError::PaymentFailed);
+    }
+
+    #[test]
+    fn transcript_differences_are_found_by_line() {
+        assert_eq!(first_difference("a\nb\n", "a\r\nb\r\n"), None);
+        assert_eq!(first_difference("a\nb\n", "a\nc\n"), Some(1));
+        assert_eq!(first_difference("a\n", "a\nb\n"), Some(1));
+    }
+}
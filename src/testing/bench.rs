@@ -0,0 +1,119 @@
+// The scenario of benches/wiring, for anyone's adapters.
+//
+// The same seeded carts every run, a warm-up pass, then the best of a few
+// timed ones: what order_lookups and storage_formats already do, without
+// criterion (the crate has no dependencies). Time per order is what a
+// wiring costs; compare it with the numbers `cargo bench --bench wiring`
+// prints for the in-memory adapters.
+//
+//     let profile = bench_profile();
+//     let timing = profile.measure(|carts| {
+//         let repo = MyRepository::connect();
+//         let mut service = OrderService::new(&repo, &payment, &sender);
+//         for cart in carts {
+//             service.place_order(cart.clone()).unwrap();
+//         }
+//     });
+//     eprintln!("{:.0} ns/order", timing.ns_per_order());
+//
+// Adapters printing every call measure their printing: give them
+// Console::silent().
+use super::OrderGenerator;
+use crate::domain::LineItem;
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchProfile {
+    pub orders: usize,
+    pub items_per_cart: usize,
+    pub seed: u64,
+    // Timed runs, after the warm-up.
+    pub runs: usize,
+}
+
+// 10_000 orders of 10 items.
+pub fn bench_profile() -> BenchProfile {
+    BenchProfile {
+        orders: 10_000,
+        items_per_cart: 10,
+        seed: 2024,
+        runs: 5,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BenchTiming {
+    pub orders: usize,
+    // The fastest run.
+    pub best: Duration,
+}
+
+impl BenchTiming {
+    pub fn ns_per_order(&self) -> f64 {
+        self.best.as_nanos() as f64 / self.orders.max(1) as f64
+    }
+}
+
+impl BenchProfile {
+    // The same carts for the same profile, on every machine.
+    pub fn carts(&self) -> Vec<Vec<LineItem>> {
+        let mut generator = OrderGenerator::new(self.seed)
+            .items_per_cart(self.items_per_cart..=self.items_per_cart);
+        (0..self.orders).map(|_| generator.next_cart()).collect()
+    }
+
+    // `run` places every cart it is given, and wires its adapters afresh
+    // each time: an order placed in a run is not there for the next.
+    pub fn measure(&self, mut run: impl FnMut(&[Vec<LineItem>])) -> BenchTiming {
+        let carts = self.carts();
+        run(&carts); // warm-up
+        let best = (0..self.runs.max(1))
+            .map(|_| {
+                let start = Instant::now();
+                run(&carts);
+                start.elapsed()
+            })
+            .min()
+            .unwrap_or_default();
+        BenchTiming {
+            orders: carts.len(),
+            best,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn small() -> BenchProfile {
+        BenchProfile {
+            orders: 3,
+            items_per_cart: 2,
+            seed: 7,
+            runs: 2,
+        }
+    }
+
+    #[test]
+    fn the_carts_depend_on_the_profile_only() {
+        let carts = small().carts();
+
+        assert_eq!(carts.len(), 3);
+        assert!(carts.iter().all(|cart| cart.len() == 2));
+        assert_eq!(carts, small().carts());
+    }
+
+    #[test]
+    fn every_run_gets_every_cart_after_a_warm_up() {
+        let mut runs = 0;
+
+        let timing = small().measure(|carts| {
+            assert_eq!(carts.len(), 3);
+            runs += 1;
+        });
+
+        assert_eq!(runs, 3);
+        assert_eq!(timing.orders, 3);
+    }
+}
@@ -0,0 +1,145 @@
+// Characterization: old code and its replacement, side by side.
+//
+// Before rewriting a function nobody dares touch, pin down what it does.
+// Feed the same inputs to the old code and to the new, capture every byte
+// each one writes, and demand the same bytes. Then move one path at a time
+// into the hexagon (a "strangler fig" migration), rerunning this after
+// every step:
+//
+//     let transcript = characterize(
+//         |cart, out| legacy::process_order(cart, &db, out),
+//         |cart, out| migrated::process_order(&mut service, cart, out),
+//         carts,
+//     );
+//
+// The two sides take turns, one input at a time, and each keeps its own
+// state from one input to the next: ids handed out, orders stored. That is
+// how a caller would see either of them.
+use std::fmt::Debug;
+use std::io::{self, Write};
+
+// Panics at the first input the two sides answer differently, with the
+// first differing line. Otherwise returns what both wrote, each input under
+// a `--- input N: ... ---` header, ready to print or to compare with a
+// golden file.
+//
+// Bytes are compared exactly: a "\r\n" where the legacy code wrote "\n" is a
+// difference, since whoever reads the output may notice.
+#[track_caller]
+pub fn characterize<I, L, M>(
+    mut legacy: L,
+    mut migrated: M,
+    inputs: impl IntoIterator<Item = I>,
+) -> String
+where
+    I: Debug,
+    L: FnMut(&I, &mut dyn Write) -> io::Result<()>,
+    M: FnMut(&I, &mut dyn Write) -> io::Result<()>,
+{
+    let mut transcript = String::new();
+    for (n, input) in (1..).zip(inputs) {
+        let expected = run("legacy", &mut legacy, n, &input);
+        let actual = run("migrated", &mut migrated, n, &input);
+        if let Some(line) = first_difference(&expected, &actual) {
+            panic!(
+                "input {n} ({input:?}): migrated output differs at line {}\n  legacy:   {:?}\n  migrated: {:?}\n\n--- legacy ---\n{}\n--- migrated ---\n{}",
+                line + 1,
+                nth_line(&expected, line),
+                nth_line(&actual, line),
+                String::from_utf8_lossy(&expected),
+                String::from_utf8_lossy(&actual),
+            );
+        }
+        transcript.push_str(&format!("--- input {n}: {input:?} ---\n"));
+        transcript.push_str(&String::from_utf8_lossy(&expected));
+    }
+    transcript
+}
+
+// Writing to a Vec cannot fail: an error is the code's own.
+#[track_caller]
+fn run<I: Debug>(
+    side: &str,
+    code: &mut impl FnMut(&I, &mut dyn Write) -> io::Result<()>,
+    n: usize,
+    input: &I,
+) -> Vec<u8> {
+    let mut output = Vec::new();
+    if let Err(error) = code(input, &mut output) {
+        panic!("input {n} ({input:?}): {side} code failed: {error}");
+    }
+    output
+}
+
+// Lines keep their endings, so that they are compared too.
+fn lines(bytes: &[u8]) -> impl Iterator<Item = &[u8]> {
+    bytes.split_inclusive(|&byte| byte == b'\n')
+}
+
+fn first_difference(expected: &[u8], actual: &[u8]) -> Option<usize> {
+    let mut expected = lines(expected);
+    let mut actual = lines(actual);
+    for line in 0.. {
+        match (expected.next(), actual.next()) {
+            (None, None) => return None,
+            (e, a) if e != a => return Some(line),
+            _ => {}
+        }
+    }
+    unreachable!()
+}
+
+fn nth_line(bytes: &[u8], n: usize) -> String {
+    lines(bytes).nth(n).map_or_else(
+        || "<end of output>".to_string(),
+        |line| String::from_utf8_lossy(line).into_owned(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Hands out numbers, the way both sides of a real migration would.
+    fn counter(format: &'static str) -> impl FnMut(&&str, &mut dyn Write) -> io::Result<()> {
+        let mut next = 0;
+        move |name, out| {
+            next += 1;
+            match format {
+                "plain" => writeln!(out, "{name} is #{next}"),
+                _ => write!(out, "{name} is #{next}\r\n"),
+            }
+        }
+    }
+
+    #[test]
+    fn agreeing_sides_give_the_transcript() {
+        let transcript = characterize(counter("plain"), counter("plain"), ["Ada", "Alan"]);
+
+        assert_eq!(
+            transcript,
+            "--- input 1: \"Ada\" ---\nAda is #1\n--- input 2: \"Alan\" ---\nAlan is #2\n"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "input 1 (\"Ada\"): migrated output differs at line 1")]
+    fn line_endings_count() {
+        characterize(counter("plain"), counter("windows"), ["Ada"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "input 2 (\"Alan\"): migrated output differs at line 1\n  \
+        legacy:   \"Alan is #2\\n\"\n  migrated: \"Alan is #1\\n\"")]
+    fn a_side_forgetting_its_state_is_caught() {
+        let mut forgetful = |name: &&str, out: &mut dyn Write| writeln!(out, "{name} is #1");
+        characterize(counter("plain"), &mut forgetful, ["Ada", "Alan"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "input 1 (\"Ada\"): migrated code failed: nope")]
+    fn failing_code_is_reported() {
+        let failing = |_: &&str, _: &mut dyn Write| Err(io::Error::other("nope"));
+        characterize(counter("plain"), failing, ["Ada"]);
+    }
+}
@@ -0,0 +1,502 @@
+// Differences a person can read, for when two lists of orders or two
+// transcripts should be the same and are not. assert_eq! on a Vec<Order>
+// prints both vectors whole and leaves the reader to spot the change;
+// these say which orders are missing, which are extra, and which fields
+// of the others changed:
+//
+//     orders differ: 1 missing, 1 changed, 1 extra
+//       missing  #000002 Placed $0.70, 1 item
+//       changed  #000003
+//                  total: $49.99 -> $50.00
+//       extra    #000009 Paid $1.00, 1 item
+//
+// and, for text, which lines, with a little of what surrounds them:
+//
+//     @@ line 3 @@
+//       --- A book ---
+//     - [Stripe] Charged $49.99
+//     + [Stripe] Charged $50.00
+//       [Email] Sent receipt
+use crate::domain::{Address, LineItem, Order, OrderId};
+use std::collections::BTreeMap;
+use std::fmt;
+
+// Unchanged lines shown around each change.
+pub const CONTEXT_LINES: usize = 2;
+
+// Past this many line pairs left to align, a text diff stops looking for
+// the smallest change and shows the rest as replaced.
+const MAX_ALIGNED_PAIRS: usize = 1_000_000;
+
+// One field of an order, as shown: "total", "items[1]", "gift_note"...
+// None on the side that does not have it, like an item past the end.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    pub field: String,
+    pub expected: Option<String>,
+    pub actual: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OrderChange {
+    pub id: OrderId,
+    pub fields: Vec<FieldChange>,
+}
+
+// Orders are matched by id. Missing and extra ones are kept whole, in the
+// order they came; changed ones with each field that differs.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct OrderDiff {
+    pub missing: Vec<Order>,
+    pub changed: Vec<OrderChange>,
+    pub extra: Vec<Order>,
+    // (expected, actual) ids, when both hold the same orders in another
+    // order.
+    pub reordered: Option<(Vec<OrderId>, Vec<OrderId>)>,
+}
+
+impl OrderDiff {
+    pub fn is_empty(&self) -> bool {
+        self.missing.is_empty()
+            && self.changed.is_empty()
+            && self.extra.is_empty()
+            && self.reordered.is_none()
+    }
+}
+
+pub fn diff_orders(expected: &[Order], actual: &[Order]) -> OrderDiff {
+    let by_id = |orders: &[Order]| -> BTreeMap<OrderId, usize> {
+        let mut index = BTreeMap::new();
+        for (at, order) in orders.iter().enumerate() {
+            index.entry(order.id).or_insert(at);
+        }
+        index
+    };
+    let (expected_ids, actual_ids) = (by_id(expected), by_id(actual));
+    let mut diff = OrderDiff::default();
+    for order in expected {
+        match actual_ids.get(&order.id) {
+            None => diff.missing.push(order.clone()),
+            Some(&at) => {
+                let fields = diff_fields(order, &actual[at]);
+                if !fields.is_empty() {
+                    diff.changed.push(OrderChange {
+                        id: order.id,
+                        fields,
+                    });
+                }
+            }
+        }
+    }
+    diff.extra = actual
+        .iter()
+        .filter(|order| !expected_ids.contains_key(&order.id))
+        .cloned()
+        .collect();
+    let ids = |orders: &[Order]| orders.iter().map(|order| order.id).collect::<Vec<_>>();
+    if diff.is_empty() && ids(expected) != ids(actual) {
+        diff.reordered = Some((ids(expected), ids(actual)));
+    }
+    diff
+}
+
+// Panics with the diff when the two differ.
+#[track_caller]
+pub fn assert_orders_eq(expected: &[Order], actual: &[Order]) {
+    let diff = diff_orders(expected, actual);
+    if !diff.is_empty() {
+        panic!("{diff}");
+    }
+}
+
+impl fmt::Display for OrderDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("orders are the same");
+        }
+        let counts: Vec<String> = [
+            (self.missing.len(), "missing"),
+            (self.changed.len(), "changed"),
+            (self.extra.len(), "extra"),
+        ]
+        .into_iter()
+        .filter(|&(count, _)| count > 0)
+        .map(|(count, what)| format!("{count} {what}"))
+        .collect();
+        match counts.is_empty() {
+            true => f.write_str("orders differ: same orders, listed in another order")?,
+            false => write!(f, "orders differ: {}", counts.join(", "))?,
+        }
+        for order in &self.missing {
+            write!(f, "\n  missing  {}", summary(order))?;
+        }
+        for change in &self.changed {
+            write!(f, "\n  changed  {}", change.id)?;
+            for field in &change.fields {
+                let side = |value: &Option<String>| value.clone().unwrap_or("(none)".to_string());
+                write!(
+                    f,
+                    "\n             {}: {} -> {}",
+                    field.field,
+                    side(&field.expected),
+                    side(&field.actual)
+                )?;
+            }
+        }
+        for order in &self.extra {
+            write!(f, "\n  extra    {}", summary(order))?;
+        }
+        if let Some((expected, actual)) = &self.reordered {
+            let list = |ids: &[OrderId]| {
+                ids.iter()
+                    .map(OrderId::to_string)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            };
+            write!(
+                f,
+                "\n  expected {}\n  got      {}",
+                list(expected),
+                list(actual)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+// "#000002 Placed $0.70, 1 item"
+fn summary(order: &Order) -> String {
+    let items = match order.items.len() {
+        1 => "1 item".to_string(),
+        n => format!("{n} items"),
+    };
+    format!("{} {} {}, {items}", order.id, order.status, order.total)
+}
+
+fn item(item: &LineItem) -> String {
+    format!("{:?} {}", item.name, item.price)
+}
+
+// In full: Debug shows only the city.
+fn address(address: &Address) -> String {
+    format!(
+        "{:?}, {:?} {:?}, {:?}",
+        address.street, address.postal_code, address.city, address.country
+    )
+}
+
+// An order as named lines, one per field, one per item and shipment.
+fn fields(order: &Order) -> Vec<(String, String)> {
+    let mut fields = vec![
+        ("status".to_string(), order.status.to_string()),
+        ("total".to_string(), order.total.to_string()),
+        ("currency".to_string(), order.currency.to_string()),
+    ];
+    for (at, line) in order.items.iter().enumerate() {
+        fields.push((format!("items[{at}]"), item(line)));
+    }
+    for (at, shipment) in order.shipments.iter().enumerate() {
+        fields.push((format!("shipments[{at}]"), format!("{shipment:?}")));
+    }
+    fields.extend([
+        ("placed_at".to_string(), format!("{:?}", order.placed_at)),
+        ("uuid".to_string(), format!("{:?}", order.uuid)),
+        ("reference".to_string(), format!("{:?}", order.reference)),
+        ("version".to_string(), order.version.to_string()),
+        ("approval".to_string(), format!("{:?}", order.approval)),
+        (
+            "shipping_address".to_string(),
+            order
+                .shipping_address
+                .as_ref()
+                .map_or("None".to_string(), address),
+        ),
+        ("gift_note".to_string(), format!("{:?}", order.gift_note)),
+    ]);
+    for (at, note) in order.notes.iter().enumerate() {
+        fields.push((format!("notes[{at}]"), format!("{note:?}")));
+    }
+    for (at, attachment) in order.attachments.iter().enumerate() {
+        fields.push((format!("attachments[{at}]"), format!("{attachment:?}")));
+    }
+    fields
+}
+
+fn diff_fields(expected: &Order, actual: &Order) -> Vec<FieldChange> {
+    let (expected, actual) = (fields(expected), fields(actual));
+    let value = |fields: &[(String, String)], name: &str| {
+        fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.clone())
+    };
+    // Expected's fields first, then the ones only actual has.
+    let mut names: Vec<&String> = expected.iter().map(|(name, _)| name).collect();
+    names.extend(
+        actual
+            .iter()
+            .map(|(name, _)| name)
+            .filter(|name| !expected.iter().any(|(field, _)| field == *name)),
+    );
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (before, after) = (value(&expected, name), value(&actual, name));
+            (before != after).then(|| FieldChange {
+                field: name.clone(),
+                expected: before,
+                actual: after,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line {
+    Same,
+    Removed,
+    Added,
+}
+
+// Line by line, so text with \r\n endings matches its \n twin. Empty when
+// the lines are the same; otherwise one hunk per group of changes, each
+// change with CONTEXT_LINES unchanged lines around it.
+pub fn diff_text(expected: &str, actual: &str) -> String {
+    let expected: Vec<&str> = expected.lines().collect();
+    let actual: Vec<&str> = actual.lines().collect();
+    let script = edit_script(&expected, &actual);
+    if script.iter().all(|&line| line == Line::Same) {
+        return String::new();
+    }
+
+    // Each step with where it is in both texts.
+    let mut steps = Vec::with_capacity(script.len());
+    let (mut e, mut a) = (0, 0);
+    for line in script {
+        steps.push((line, e, a));
+        match line {
+            Line::Same => (e, a) = (e + 1, a + 1),
+            Line::Removed => e += 1,
+            Line::Added => a += 1,
+        }
+    }
+    let changed: Vec<usize> = (0..steps.len())
+        .filter(|&at| steps[at].0 != Line::Same)
+        .collect();
+
+    let mut out = String::new();
+    let mut at = 0;
+    while at < changed.len() {
+        let start = changed[at].saturating_sub(CONTEXT_LINES);
+        let mut end = changed[at];
+        while at < changed.len() && changed[at] <= end + 2 * CONTEXT_LINES + 1 {
+            end = changed[at];
+            at += 1;
+        }
+        let end = (end + CONTEXT_LINES).min(steps.len() - 1);
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        let (_, e, a) = steps[start];
+        match e == a {
+            true => out.push_str(&format!("@@ line {} @@\n", e + 1)),
+            false => out.push_str(&format!(
+                "@@ expected line {}, actual line {} @@\n",
+                e + 1,
+                a + 1
+            )),
+        }
+        for &(line, e, a) in &steps[start..=end] {
+            let (mark, text) = match line {
+                Line::Same => (' ', expected[e]),
+                Line::Removed => ('-', expected[e]),
+                Line::Added => ('+', actual[a]),
+            };
+            out.push_str(&format!("{mark} {text}\n"));
+        }
+    }
+    out
+}
+
+// The shortest way from `expected` to `actual`: common lines kept, the
+// rest removed then added. The common start and end are set aside first,
+// which leaves next to nothing to align when a few lines changed.
+fn edit_script(expected: &[&str], actual: &[&str]) -> Vec<Line> {
+    let prefix = expected
+        .iter()
+        .zip(actual)
+        .take_while(|(e, a)| e == a)
+        .count();
+    let suffix = expected[prefix..]
+        .iter()
+        .rev()
+        .zip(actual[prefix..].iter().rev())
+        .take_while(|(e, a)| e == a)
+        .count();
+    let e = &expected[prefix..expected.len() - suffix];
+    let a = &actual[prefix..actual.len() - suffix];
+
+    let mut script = vec![Line::Same; prefix];
+    if e.len().saturating_mul(a.len()) > MAX_ALIGNED_PAIRS {
+        script.extend(std::iter::repeat_n(Line::Removed, e.len()));
+        script.extend(std::iter::repeat_n(Line::Added, a.len()));
+    } else {
+        // common[i][j]: the longest common subsequence of e[i..] and a[j..].
+        let width = a.len() + 1;
+        let mut common = vec![0u32; (e.len() + 1) * width];
+        for i in (0..e.len()).rev() {
+            for j in (0..a.len()).rev() {
+                common[i * width + j] = match e[i] == a[j] {
+                    true => common[(i + 1) * width + j + 1] + 1,
+                    false => common[(i + 1) * width + j].max(common[i * width + j + 1]),
+                };
+            }
+        }
+        let (mut i, mut j) = (0, 0);
+        while i < e.len() || j < a.len() {
+            if i < e.len() && j < a.len() && e[i] == a[j] {
+                script.push(Line::Same);
+                (i, j) = (i + 1, j + 1);
+            } else if j == a.len()
+                || (i < e.len() && common[(i + 1) * width + j] >= common[i * width + j + 1])
+            {
+                script.push(Line::Removed);
+                i += 1;
+            } else {
+                script.push(Line::Added);
+                j += 1;
+            }
+        }
+    }
+    script.extend(std::iter::repeat_n(Line::Same, suffix));
+    script
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::Money;
+
+    fn order(id: u32, items: &[(&str, u32)]) -> Order {
+        let items = items
+            .iter()
+            .map(|&(name, cents)| LineItem::new(name, Money(cents)))
+            .collect();
+        Order::new(OrderId(id), items).unwrap()
+    }
+
+    #[test]
+    fn a_missing_a_changed_and_an_extra_order_are_each_named() {
+        let expected = [
+            order(1, &[("Pen", 150)]),
+            order(2, &[("Ink", 70)]),
+            order(3, &[("Book", 4999)]),
+        ];
+        let mut changed = expected[2].clone();
+        changed.total = Money(5000);
+        changed.mark_paid().unwrap();
+        let mut extra = order(9, &[("Bead", 100)]);
+        extra.mark_paid().unwrap();
+        let actual = [expected[0].clone(), changed, extra];
+
+        let diff = diff_orders(&expected, &actual);
+
+        assert_eq!(diff.missing, [expected[1].clone()]);
+        assert_eq!(diff.extra.len(), 1);
+        assert_eq!(
+            diff.to_string(),
+            "orders differ: 1 missing, 1 changed, 1 extra\n  \
+             missing  #000002 Placed $0.70, 1 item\n  \
+             changed  #000003\n             \
+             status: Placed -> Paid\n             \
+             total: $49.99 -> $50.00\n  \
+             extra    #000009 Paid $1.00, 1 item"
+        );
+    }
+
+    #[test]
+    fn items_are_compared_line_by_line() {
+        let expected = [order(1, &[("Pen", 150), ("Ink", 70)])];
+        let actual = [order(1, &[("Pen", 160)])];
+
+        assert_eq!(
+            diff_orders(&expected, &actual).changed[0].fields,
+            [
+                FieldChange {
+                    field: "total".to_string(),
+                    expected: Some("$2.20".to_string()),
+                    actual: Some("$1.60".to_string()),
+                },
+                FieldChange {
+                    field: "items[0]".to_string(),
+                    expected: Some("\"Pen\" $1.50".to_string()),
+                    actual: Some("\"Pen\" $1.60".to_string()),
+                },
+                FieldChange {
+                    field: "items[1]".to_string(),
+                    expected: Some("\"Ink\" $0.70".to_string()),
+                    actual: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn an_address_is_compared_in_full() {
+        let mut expected = order(1, &[("Pen", 150)]);
+        expected.shipping_address = Some(Address {
+            street: "1 Main St".to_string(),
+            city: "Springfield".to_string(),
+            postal_code: "12345".to_string(),
+            country: "US".to_string(),
+        });
+        let mut actual = expected.clone();
+        actual.shipping_address.as_mut().unwrap().street = "2 Main St".to_string();
+
+        let fields = &diff_orders(&[expected], &[actual]).changed[0].fields;
+        assert_eq!(fields.len(), 1);
+        assert_eq!(
+            fields[0].actual.as_deref(),
+            Some(r#""2 Main St", "12345" "Springfield", "US""#)
+        );
+    }
+
+    #[test]
+    fn the_same_orders_in_another_order_are_a_difference() {
+        let (one, two) = (order(1, &[("Pen", 150)]), order(2, &[("Ink", 70)]));
+
+        assert!(diff_orders(&[one.clone(), two.clone()], &[one.clone(), two.clone()]).is_empty());
+        assert_eq!(
+            diff_orders(&[one.clone(), two.clone()], &[two, one]).to_string(),
+            "orders differ: same orders, listed in another order\n  \
+             expected #000001, #000002\n  \
+             got      #000002, #000001"
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "orders differ: 1 missing")]
+    fn assert_orders_eq_panics_with_the_diff() {
+        assert_orders_eq(&[order(1, &[("Pen", 150)])], &[]);
+    }
+
+    #[test]
+    fn text_diffs_show_the_changed_lines_in_context() {
+        let expected = "a\nb\nc\nd\ne\nf\ng\nh\ni\nj\n";
+        let actual = "a\nb\nc\ne\nf\ng\nh\ni\nj\nk\n";
+
+        assert_eq!(
+            diff_text(expected, actual),
+            "@@ line 2 @@\n  b\n  c\n- d\n  e\n  f\n\n\
+             @@ expected line 9, actual line 8 @@\n  i\n  j\n+ k\n"
+        );
+        assert_eq!(diff_text("a\nb\n", "a\r\nb\r\n"), "");
+    }
+
+    #[test]
+    fn nearby_changes_share_a_hunk() {
+        let diff = diff_text("a\nb\nc\nd\ne\n", "A\nb\nc\nd\nE\n");
+
+        assert_eq!(diff, "@@ line 1 @@\n- a\n+ A\n  b\n  c\n  d\n- e\n+ E\n");
+    }
+}
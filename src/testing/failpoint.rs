@@ -0,0 +1,99 @@
+// A file system that dies partway: the first `survive` writes go through to
+// the inner Fs, the next one only halfway, and nothing after it. What is
+// left on disk is what a process killed at that moment leaves behind:
+// files replaced or not, and the last one written torn.
+//
+//     let fs = FailpointFs::new(RealFs, 3);
+//     let repository = JournaledRepository::open_on(&dir, JsonFormat, fs)?;
+//
+// Writes, appends and renames all count; a rename is never torn, it happens
+// or it does not. Reads go through until the crash, and fail after it like
+// everything else. With usize::MAX to survive, nothing fails, and writes()
+// tells how many failpoints a run has.
+use crate::adapters::file_repository::{Fs, RealFs};
+use std::io;
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub struct FailpointFs<F: Fs = RealFs> {
+    inner: F,
+    survive: usize,
+    writes: AtomicUsize,
+}
+
+impl<F: Fs> FailpointFs<F> {
+    pub fn new(inner: F, survive: usize) -> Self {
+        Self {
+            inner,
+            survive,
+            writes: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn inner(&self) -> &F {
+        &self.inner
+    }
+
+    // The writes tried so far, the one that crashed included.
+    pub fn writes(&self) -> usize {
+        self.writes.load(Ordering::SeqCst)
+    }
+
+    pub fn crashed(&self) -> bool {
+        self.writes() > self.survive
+    }
+
+    // `whole` while the writes last, `torn` for the one that crashes.
+    fn write_with(
+        &self,
+        whole: impl FnOnce() -> io::Result<()>,
+        torn: impl FnOnce() -> io::Result<()>,
+    ) -> io::Result<()> {
+        let n = self.writes.fetch_add(1, Ordering::SeqCst);
+        if n < self.survive {
+            return whole();
+        }
+        if n == self.survive {
+            let _ = torn();
+        }
+        Err(crash())
+    }
+}
+
+fn crash() -> io::Error {
+    io::Error::other("crashed at the failpoint")
+}
+
+impl<F: Fs> Fs for FailpointFs<F> {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        if self.crashed() {
+            return Err(crash());
+        }
+        self.inner.read(path)
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.write_with(
+            || self.inner.write(path, bytes),
+            || self.inner.write(path, &bytes[..bytes.len() / 2]),
+        )
+    }
+
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.write_with(
+            || self.inner.append(path, bytes),
+            || self.inner.append(path, &bytes[..bytes.len() / 2]),
+        )
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        self.write_with(|| self.inner.rename(from, to), || Ok(()))
+    }
+
+    fn create_dir_all(&self, path: &Path) -> io::Result<()> {
+        if self.crashed() {
+            return Err(crash());
+        }
+        self.inner.create_dir_all(path)
+    }
+}
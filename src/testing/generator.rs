@@ -0,0 +1,201 @@
+// Test data: carts and orders from a seed.
+//
+// Benchmarks and stress tests want many realistic carts, and they want the
+// same ones on every run, on every machine: a slowdown must come from the
+// code, not from the data. OrderGenerator draws from its own xorshift64*,
+// with no dependency and nothing platform-sized in the arithmetic, so a
+// seed always means the same carts.
+//
+//     let mut generator = OrderGenerator::new(42).items_per_cart(1..=3);
+//     let cart = generator.next_cart();
+use crate::application::OrderService;
+use crate::domain::{LineItem, Money, Order, OrderError};
+use crate::ports::{OrderWriter, PaymentGateway, Sender};
+use std::ops::RangeInclusive;
+
+pub const DEFAULT_NAMES: [&str; 8] = [
+    "Rust Book",
+    "Keyboard",
+    "Mouse",
+    "Monitor",
+    "USB cable",
+    "Headset",
+    "Webcam",
+    "Desk lamp",
+];
+
+pub struct OrderGenerator {
+    state: u64,
+    items_per_cart: RangeInclusive<usize>,
+    prices: RangeInclusive<u32>,
+    names: Vec<String>,
+}
+
+impl OrderGenerator {
+    // 1 to 5 items of 1.00 to 200.00, named from DEFAULT_NAMES.
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: splitmix64(seed),
+            items_per_cart: 1..=5,
+            prices: 100..=20_000,
+            names: DEFAULT_NAMES.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+
+    pub fn items_per_cart(mut self, range: RangeInclusive<usize>) -> Self {
+        assert!(!range.is_empty(), "empty items_per_cart range {range:?}");
+        self.items_per_cart = range;
+        self
+    }
+
+    // In cents.
+    pub fn price_range(mut self, range: RangeInclusive<u32>) -> Self {
+        assert!(!range.is_empty(), "empty price range {range:?}");
+        self.prices = range;
+        self
+    }
+
+    pub fn names(mut self, names: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.names = names.into_iter().map(Into::into).collect();
+        assert!(!self.names.is_empty(), "empty name pool");
+        self
+    }
+
+    pub fn next_cart(&mut self) -> Vec<LineItem> {
+        let count = self.pick(
+            *self.items_per_cart.start() as u64,
+            *self.items_per_cart.end() as u64,
+        );
+        (0..count)
+            .map(|_| {
+                let name = self.pick(0, self.names.len() as u64 - 1) as usize;
+                let cents = self.pick(
+                    u64::from(*self.prices.start()),
+                    u64::from(*self.prices.end()),
+                );
+                LineItem {
+                    name: self.names[name].clone(),
+                    // Within the u32 price range by construction.
+                    price: Money(cents as u32),
+                }
+            })
+            .collect()
+    }
+
+    // Places `count` generated carts through `service`, which stores them
+    // wherever its repository keeps orders. Stops at the first refusal.
+    pub fn generate_orders<R, P, N>(
+        &mut self,
+        service: &mut OrderService<'_, R, P, N>,
+        count: usize,
+    ) -> Result<Vec<Order>, OrderError>
+    where
+        R: OrderWriter + ?Sized,
+        P: PaymentGateway + ?Sized,
+        N: Sender + ?Sized,
+    {
+        (0..count)
+            .map(|_| service.place_order(self.next_cart()))
+            .collect()
+    }
+
+    // xorshift64*
+    fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // Uniform enough in low..=high; a slight modulo bias does not matter
+    // for test data.
+    fn pick(&mut self, low: u64, high: u64) -> u64 {
+        low + self.next_u64() % (high - low + 1)
+    }
+}
+
+// Spreads any seed, 0 included, into a valid xorshift state (never 0).
+fn splitmix64(seed: u64) -> u64 {
+    let mut z = seed.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    (z ^ (z >> 31)).max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ports::OrderReader;
+
+    // FNV-1a: std's hashers may change between releases, this one cannot.
+    fn fnv1a(carts: &[Vec<LineItem>]) -> u64 {
+        let mut hash = 0xcbf2_9ce4_8422_2325_u64;
+        let mut eat = |bytes: &[u8]| {
+            for &byte in bytes {
+                hash = (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+            }
+        };
+        for cart in carts {
+            eat(&(cart.len() as u64).to_le_bytes());
+            for item in cart {
+                eat(item.name.as_bytes());
+                eat(&item.price.0.to_le_bytes());
+            }
+        }
+        hash
+    }
+
+    #[test]
+    fn same_seed_same_carts_everywhere() {
+        let mut generator = OrderGenerator::new(42);
+        let carts: Vec<_> = (0..100).map(|_| generator.next_cart()).collect();
+
+        // Changing this value breaks every benchmark baseline: don't.
+        assert_eq!(fnv1a(&carts), 4_841_386_308_746_382_747);
+    }
+
+    #[test]
+    fn carts_follow_the_configured_distributions() {
+        let mut generator = OrderGenerator::new(7)
+            .items_per_cart(2..=3)
+            .price_range(500..=600)
+            .names(["Cable"]);
+
+        for _ in 0..200 {
+            let cart = generator.next_cart();
+            assert!((2..=3).contains(&cart.len()));
+            for item in cart {
+                assert!((500..=600).contains(&item.price.0));
+                assert_eq!(item.name, "Cable");
+            }
+        }
+    }
+
+    #[test]
+    fn generated_orders_land_in_the_repository() {
+        use crate::adapters::Console;
+        use crate::adapters::in_memory::{
+            ConsoleSender, InMemoryOrderRepository, MockPaymentGateway,
+        };
+
+        let quiet = Console::silent();
+        let repo = InMemoryOrderRepository::new().with_console(quiet.clone());
+        let payment = MockPaymentGateway::new().with_console(quiet.clone());
+        let sender = ConsoleSender::new().with_console(quiet);
+        let mut service = OrderService::new(&repo, &payment, &sender);
+
+        let orders = OrderGenerator::new(3)
+            .generate_orders(&mut service, 20)
+            .unwrap();
+
+        assert_eq!(orders.len(), 20);
+        assert_eq!(repo.list().unwrap(), orders);
+    }
+
+    #[test]
+    fn different_seeds_differ() {
+        let a = OrderGenerator::new(1).next_cart();
+        let b = OrderGenerator::new(2).next_cart();
+        assert_ne!(a, b);
+    }
+}
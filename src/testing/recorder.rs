@@ -0,0 +1,493 @@
+// Which port was called, in which order, across every port of a use case.
+//
+// A spy remembers its own calls; whether the charge came before the save is
+// a question about two of them. Wrap each adapter in a Recorded, all of them
+// reporting into one GlobalRecorder, and assert on the sequence:
+//
+//     let recorder = GlobalRecorder::new();
+//     let payment = Recorded::new(MockPaymentGateway::new(), &recorder, "payment");
+//     ...
+//     expect_sequence!(recorder, ["payment.charge_order", "repository.save", "sender.send"]);
+//
+// The expected calls must appear in that order, not back to back: calls in
+// between (a metrics counter, an audit event, a find) are allowed. A call
+// that is missing, or came too early, fails with both lists side by side.
+//
+// Only the call made to the wrapper is recorded: a default method of the
+// inner adapter calling another one stays a single call.
+use crate::application::Hooks;
+use crate::domain::{
+    Customer, InventoryEvent, LineItem, Money, Notice, Order, OrderConfirmation, OrderError,
+    OrderEvent, OrderId, OrderKey, OrderReference, SagaId, Timestamp, WarehouseId,
+};
+use crate::ports::{
+    CancelToken, Capability, ChargeLog, ChargeOutcome, ChargeRecord, EventPublisher, FraudScreen,
+    Inventory, Metrics, OrderReader, OrderWriter, PaymentGateway, ReservationId, RiskVerdict,
+    SagaEntry, SagaLog, Sender,
+};
+use std::fmt;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Call {
+    pub port: &'static str,
+    pub operation: &'static str,
+    // When the call names an order: an id, an order, a confirmation.
+    pub order_id: Option<OrderId>,
+    // Position among every call the recorder heard, from 0.
+    pub seq: usize,
+}
+
+impl Call {
+    // "port.operation", as written in expect_sequence!.
+    pub fn name(&self) -> String {
+        format!("{}.{}", self.port, self.operation)
+    }
+}
+
+impl fmt::Display for Call {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{} {}", self.seq, self.name())?;
+        match self.order_id {
+            Some(id) => write!(f, " (order {})", id.0),
+            None => Ok(()),
+        }
+    }
+}
+
+// Cloned into every adapter it listens to; the clones share one list.
+#[derive(Debug, Clone, Default)]
+pub struct GlobalRecorder {
+    calls: Arc<Mutex<Vec<Call>>>,
+}
+
+impl GlobalRecorder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&self, port: &'static str, operation: &'static str, order_id: Option<OrderId>) {
+        let mut calls = self.calls.lock().unwrap_or_else(PoisonError::into_inner);
+        let seq = calls.len();
+        calls.push(Call {
+            port,
+            operation,
+            order_id,
+            seq,
+        });
+    }
+
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    pub fn calls_to(&self, port: &str) -> Vec<Call> {
+        self.calls()
+            .into_iter()
+            .filter(|call| call.port == port)
+            .collect()
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.calls().iter().map(Call::name).collect()
+    }
+
+    // Forgets what was recorded so far, for a test with a setup phase.
+    // Sequence numbers start again from 0.
+    pub fn clear(&self) {
+        self.calls
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clear();
+    }
+
+    pub fn check_sequence(&self, expected: &[&str]) -> Result<(), SequenceMismatch> {
+        check_sequence(&self.calls(), expected)
+    }
+
+    // What expect_sequence! calls.
+    #[track_caller]
+    pub fn expect_sequence(&self, expected: &[&str]) {
+        if let Err(mismatch) = self.check_sequence(expected) {
+            panic!("{mismatch}");
+        }
+    }
+}
+
+// `expected` in order within `calls`, other calls allowed in between.
+// Each expected call is matched to the first one recorded after the
+// previous match: if any way of matching them exists, this one does.
+pub fn check_sequence(calls: &[Call], expected: &[&str]) -> Result<(), SequenceMismatch> {
+    let mut matched = Vec::with_capacity(expected.len());
+    let mut from = 0;
+    for &name in expected {
+        match calls[from..].iter().position(|call| call.name() == name) {
+            Some(offset) => {
+                matched.push(from + offset);
+                from += offset + 1;
+            }
+            None => {
+                return Err(SequenceMismatch {
+                    expected: expected.iter().map(|name| name.to_string()).collect(),
+                    matched,
+                    calls: calls.to_vec(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+// The first expected call not found, with every one before it matched.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SequenceMismatch {
+    pub expected: Vec<String>,
+    // Where each expected call before the missing one was recorded.
+    pub matched: Vec<usize>,
+    pub calls: Vec<Call>,
+}
+
+impl SequenceMismatch {
+    pub fn missing(&self) -> &str {
+        &self.expected[self.matched.len()]
+    }
+}
+
+impl fmt::Display for SequenceMismatch {
+    //   expected                   recorded
+    //   ok  repository.find   #0     #0 repository.find (order 1)
+    //   !!  payment.refund_for       #1 repository.update (order 1)
+    //       repository.update
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let missing = self.missing();
+        match self.matched.last() {
+            Some(&last) => writeln!(
+                f,
+                "call sequence mismatch: no {missing} after {}",
+                self.calls[last]
+            )?,
+            None => writeln!(f, "call sequence mismatch: no {missing} at all")?,
+        }
+        if self.calls.iter().any(|call| call.name() == missing) {
+            writeln!(f, "  ({missing} was called, but too early)")?;
+        }
+        let width = self.expected.iter().map(String::len).max().unwrap_or(0);
+        let rows = self.expected.len().max(self.calls.len());
+        writeln!(f, "  {:<w$}  recorded", "expected", w = width + 10)?;
+        for row in 0..rows {
+            let left = match self.expected.get(row) {
+                Some(name) if row < self.matched.len() => {
+                    format!("ok  {name:<width$} #{:<4}", self.matched[row])
+                }
+                Some(name) if row == self.matched.len() => format!("!!  {name:<width$}      "),
+                Some(name) => format!("    {name:<width$}      "),
+                None => " ".repeat(width + 10),
+            };
+            let right = self.calls.get(row).map(Call::to_string).unwrap_or_default();
+            writeln!(f, "  {left}  {right}")?;
+        }
+        Ok(())
+    }
+}
+
+// Checks the calls a GlobalRecorder heard against a list of
+// "port.operation", in order, others allowed in between:
+//
+//     expect_sequence!(recorder, ["inventory.reserve", "payment.charge_order"]);
+#[macro_export]
+macro_rules! expect_sequence {
+    ($recorder:expr, [$($call:expr),* $(,)?] $(,)?) => {
+        $recorder.expect_sequence(&[$($call),*])
+    };
+}
+
+// --- Recorded (decorator) ---
+// Any adapter, reporting every call made to it as `port`.operation before
+// passing it on. The call is recorded whether it succeeds or not.
+pub struct Recorded<I> {
+    inner: I,
+    recorder: GlobalRecorder,
+    port: &'static str,
+}
+
+impl<I> Recorded<I> {
+    pub fn new(inner: I, recorder: &GlobalRecorder, port: &'static str) -> Self {
+        Self {
+            inner,
+            recorder: recorder.clone(),
+            port,
+        }
+    }
+
+    pub fn inner(&self) -> &I {
+        &self.inner
+    }
+
+    fn note(&self, operation: &'static str, order_id: Option<OrderId>) -> &I {
+        self.recorder.record(self.port, operation, order_id);
+        &self.inner
+    }
+}
+
+impl<R: OrderReader> OrderReader for Recorded<R> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.note("find", Some(id)).find(id)
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.note("list", None).list()
+    }
+
+    fn exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        self.note("exists", Some(id)).exists(id)
+    }
+
+    fn total_of(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        self.note("total_of", Some(id)).total_of(id)
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        self.note("for_each", None).for_each(visit)
+    }
+
+    fn for_each_cancellable(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        self.note("for_each_cancellable", None)
+            .for_each_cancellable(cancel, visit)
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        self.note("list_deleted", None).list_deleted()
+    }
+
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.note("find_by_key", None).find_by_key(key)
+    }
+
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        self.note("find_by_reference", None)
+            .find_by_reference(reference)
+    }
+}
+
+impl<R: OrderWriter> OrderWriter for Recorded<R> {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.note("save", Some(order.id)).save(order)
+    }
+
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        self.note("update", Some(order.id)).update(order)
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.note("soft_delete", Some(id)).soft_delete(id)
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.note("restore", Some(id)).restore(id)
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.note("purge", Some(id)).purge(id)
+    }
+}
+
+impl<G: PaymentGateway> PaymentGateway for Recorded<G> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.note("charge", None).charge(amount)
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.note("charge_for", Some(order_id))
+            .charge_for(order_id, amount)
+    }
+
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.note("charge_order", Some(order_id))
+            .charge_order(order_id, amount)
+    }
+
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.note("refund_for", Some(order_id))
+            .refund_for(order_id, amount)
+    }
+}
+
+impl<G: PaymentGateway + ChargeLog> ChargeLog for Recorded<G> {
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        self.note("charges", None).charges()
+    }
+}
+
+impl<S: Sender> Sender for Recorded<S> {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.note("send", Some(confirmation.order_id))
+            .send(confirmation)
+    }
+
+    fn send_notice(&self, notice: &Notice) -> Result<(), OrderError> {
+        self.note("send_notice", None).send_notice(notice)
+    }
+}
+
+impl<I: Inventory> Inventory for Recorded<I> {
+    fn reserve(&self, reservation: ReservationId, items: &[LineItem]) -> Result<(), OrderError> {
+        self.note("reserve", None).reserve(reservation, items)
+    }
+
+    fn confirm(&self, reservation: ReservationId) -> Result<(), OrderError> {
+        self.note("confirm", None).confirm(reservation)
+    }
+
+    fn release(&self, reservation: ReservationId) -> Result<(), OrderError> {
+        self.note("release", None).release(reservation)
+    }
+
+    fn restock(&self, item: &str, units: u32) -> Result<(), OrderError> {
+        self.note("restock", None).restock(item, units)
+    }
+
+    fn stock_level(&self, item: &str) -> Result<u32, OrderError> {
+        self.note("stock_level", None).stock_level(item)
+    }
+
+    fn sources(&self, reservation: ReservationId) -> Result<Option<Vec<WarehouseId>>, OrderError> {
+        self.note("sources", None).sources(reservation)
+    }
+
+    fn release_expired(
+        &self,
+        now: Timestamp,
+        ttl: Duration,
+    ) -> Result<Vec<ReservationId>, OrderError> {
+        self.note("release_expired", None).release_expired(now, ttl)
+    }
+}
+
+impl<E: EventPublisher> EventPublisher for Recorded<E> {
+    fn publish(&self, event: &OrderEvent) -> Result<(), OrderError> {
+        self.note("publish", Some(event.order_id())).publish(event)
+    }
+
+    fn publish_inventory(&self, event: &InventoryEvent) -> Result<(), OrderError> {
+        self.note("publish_inventory", None)
+            .publish_inventory(event)
+    }
+}
+
+impl<F: FraudScreen> FraudScreen for Recorded<F> {
+    fn assess(&self, order: &Order, customer: &Customer) -> Result<RiskVerdict, OrderError> {
+        self.note("assess", Some(order.id)).assess(order, customer)
+    }
+}
+
+impl<L: SagaLog> SagaLog for Recorded<L> {
+    fn append(&self, saga: SagaId, entry: SagaEntry) -> Result<(), OrderError> {
+        self.note("append", None).append(saga, entry)
+    }
+
+    fn entries(&self, saga: SagaId) -> Result<Vec<SagaEntry>, OrderError> {
+        self.note("entries", None).entries(saga)
+    }
+}
+
+impl<M: Metrics> Metrics for Recorded<M> {
+    fn increment(&self, name: &str) {
+        self.note("increment", None).increment(name)
+    }
+
+    fn observe_histogram(&self, name: &str, value_ms: u64) {
+        self.note("observe_histogram", None)
+            .observe_histogram(name, value_ms)
+    }
+}
+
+// Not a port, but called between them: "hooks.before_charge" and the like
+// place the hooks in the sequence.
+impl<H: Hooks> Hooks for Recorded<H> {
+    fn before_charge(&self, order: &Order) -> Result<(), OrderError> {
+        self.note("before_charge", Some(order.id))
+            .before_charge(order)
+    }
+
+    fn after_save(&self, order: &Order) -> Result<(), OrderError> {
+        self.note("after_save", Some(order.id)).after_save(order)
+    }
+
+    fn on_failure(&self, error: &OrderError) -> Result<(), OrderError> {
+        self.note("on_failure", None).on_failure(error)
+    }
+}
+
+impl<I> Capability for Recorded<I> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn recorder(names: &[(&'static str, &'static str)]) -> GlobalRecorder {
+        let recorder = GlobalRecorder::new();
+        for &(port, operation) in names {
+            recorder.record(port, operation, Some(OrderId(1)));
+        }
+        recorder
+    }
+
+    #[test]
+    fn calls_in_between_are_allowed() {
+        let recorder = recorder(&[
+            ("payment", "charge_order"),
+            ("metrics", "increment"),
+            ("repository", "save"),
+            ("sender", "send"),
+        ]);
+
+        expect_sequence!(recorder, ["payment.charge_order", "sender.send"]);
+        expect_sequence!(recorder, []);
+        assert_eq!(recorder.calls()[3].seq, 3);
+    }
+
+    #[test]
+    fn a_call_too_early_is_reported_as_such() {
+        let recorder = recorder(&[("repository", "save"), ("payment", "charge_order")]);
+
+        let mismatch = recorder
+            .check_sequence(&["payment.charge_order", "repository.save"])
+            .unwrap_err();
+
+        assert_eq!(mismatch.missing(), "repository.save");
+        assert_eq!(mismatch.matched, [1]);
+        let text = mismatch.to_string();
+        assert!(text.starts_with(
+            "call sequence mismatch: no repository.save after #1 payment.charge_order (order 1)\n"
+        ));
+        assert!(text.contains("(repository.save was called, but too early)"));
+        assert!(text.contains("ok  payment.charge_order #1"));
+        assert!(text.contains("!!  repository.save"));
+    }
+
+    #[test]
+    #[should_panic(expected = "no sender.send at all")]
+    fn a_call_never_made_fails_the_expectation() {
+        let recorder = recorder(&[("repository", "save")]);
+
+        expect_sequence!(recorder, ["sender.send"]);
+    }
+
+    #[test]
+    fn cleared_recorders_count_from_zero_again() {
+        let recorder = recorder(&[("repository", "save")]);
+        recorder.clear();
+        recorder.record("sender", "send", None);
+
+        assert_eq!(recorder.names(), ["sender.send"]);
+        assert_eq!(recorder.calls_to("sender")[0].seq, 0);
+    }
+}
@@ -0,0 +1,234 @@
+// What every Sender owes the service, whatever the confirmation holds.
+//
+// Item names come from shoppers and from catalogues in every script: emoji,
+// right-to-left text, control characters pasted in by accident, a 10 KB
+// name, bidi overrides put there on purpose. Totals go from nothing to the
+// largest a Money holds. For each of these a sender must not panic, and
+// must either send or say NotificationFailed: any other error would send
+// the service looking for a problem the order does not have.
+//
+//     sender_contract(|| MySender::new());
+//
+// A sender whose output can be read back is held to what it wrote, too:
+//
+//     SenderContract::new(|| ConsoleSender::new().with_console(Console::to(buffer.clone())))
+//         .printing_to(&buffer)
+//         .run();
+//
+// - printing_to: the receipt lines of the confirmation, each whole on its
+//   own line of the console, none with a control character or a bidi
+//   override left open to spill onto the price
+// - posting_envelopes: the last envelope posted reads back as the
+//   confirmation sent, every character intact
+//
+// A sender that holds confirmations back, like BufferedSender, says how to
+// let them go with after_send.
+use crate::adapters::{SharedBuffer, parse_any_confirmation};
+use crate::domain::{
+    Currency, LineItem, Money, OrderConfirmation, OrderError, OrderId, OrderReference,
+};
+use crate::ports::Sender;
+use std::panic::{self, AssertUnwindSafe};
+
+type Envelopes<S> = Box<dyn Fn(&S) -> Vec<String>>;
+type AfterSend<S> = Box<dyn Fn(&mut S) -> Result<(), OrderError>>;
+
+#[track_caller]
+pub fn sender_contract<S: Sender>(make: impl Fn() -> S + 'static) {
+    SenderContract::new(make).run();
+}
+
+pub struct SenderContract<S> {
+    make: Box<dyn Fn() -> S>,
+    console: Option<SharedBuffer>,
+    envelopes: Option<Envelopes<S>>,
+    after_send: Option<AfterSend<S>>,
+}
+
+impl<S: Sender> SenderContract<S> {
+    // A fresh sender for every confirmation.
+    pub fn new(make: impl Fn() -> S + 'static) -> Self {
+        Self {
+            make: Box::new(make),
+            console: None,
+            envelopes: None,
+            after_send: None,
+        }
+    }
+
+    // Where the senders print their receipts.
+    pub fn printing_to(mut self, buffer: &SharedBuffer) -> Self {
+        self.console = Some(buffer.clone());
+        self
+    }
+
+    // Every envelope a sender posted, oldest first.
+    pub fn posting_envelopes(mut self, envelopes: impl Fn(&S) -> Vec<String> + 'static) -> Self {
+        self.envelopes = Some(Box::new(envelopes));
+        self
+    }
+
+    pub fn after_send(
+        mut self,
+        after_send: impl Fn(&mut S) -> Result<(), OrderError> + 'static,
+    ) -> Self {
+        self.after_send = Some(Box::new(after_send));
+        self
+    }
+
+    // Panics at the first confirmation a sender gets wrong, naming it.
+    #[track_caller]
+    pub fn run(&self) {
+        for (case, confirmation) in confirmations() {
+            let printed_before = self
+                .console
+                .as_ref()
+                .map_or(0, |buffer| buffer.contents().len());
+            let mut sender = (self.make)();
+            let outcome = panic::catch_unwind(AssertUnwindSafe(|| {
+                sender.send(&confirmation)?;
+                match &self.after_send {
+                    Some(after_send) => after_send(&mut sender),
+                    None => Ok(()),
+                }
+            }));
+            match outcome {
+                Err(_) => panic!("{case}: the sender panicked"),
+                Ok(Err(OrderError::NotificationFailed)) => continue,
+                Ok(Err(other)) => panic!("{case}: {other:?}, not NotificationFailed"),
+                Ok(Ok(())) => {}
+            }
+            if let Some(buffer) = &self.console {
+                check_receipt(case, &confirmation, &buffer.contents()[printed_before..]);
+            }
+            if let Some(envelopes) = &self.envelopes {
+                let envelopes = envelopes(&sender);
+                let Some(last) = envelopes.last() else {
+                    panic!("{case}: sent, and no envelope posted");
+                };
+                assert_eq!(
+                    parse_any_confirmation(last.as_bytes()).as_ref(),
+                    Ok(&confirmation),
+                    "{case}: the envelope does not read back as sent:\n{last}"
+                );
+            }
+        }
+    }
+}
+
+#[track_caller]
+fn check_receipt(case: &str, confirmation: &OrderConfirmation, printed: &str) {
+    let lines: Vec<&str> = printed.lines().collect();
+    for line in &lines {
+        assert!(
+            !line.chars().any(char::is_control),
+            "{case}: a control character was printed: {line:?}"
+        );
+        assert!(bidi_closed(line), "{case}: bidi left open: {line:?}");
+    }
+    for expected in confirmation.receipt_lines() {
+        assert!(
+            lines.iter().any(|line| line.ends_with(&expected)),
+            "{case}: the receipt line {expected:?} is not printed whole:\n{printed}"
+        );
+    }
+}
+
+// Every embedding, override and isolate started on the line is ended on it.
+fn bidi_closed(line: &str) -> bool {
+    let (mut embeddings, mut isolates) = (0i32, 0i32);
+    for c in line.chars() {
+        match c {
+            '\u{202A}'..='\u{202B}' | '\u{202D}'..='\u{202E}' => embeddings += 1,
+            '\u{202C}' => embeddings -= 1,
+            '\u{2066}'..='\u{2068}' => isolates += 1,
+            '\u{2069}' => isolates -= 1,
+            _ => {}
+        }
+    }
+    embeddings <= 0 && isolates <= 0
+}
+
+fn confirmation(id: u32, items: Vec<LineItem>) -> OrderConfirmation {
+    let total = items.iter().map(|item| item.price.0).sum();
+    OrderConfirmation {
+        order_id: OrderId(id),
+        items,
+        total: Money(total),
+        tax: Money(0),
+        currency: Currency::default(),
+        customer_email: None,
+        reference: None,
+    }
+}
+
+fn confirmations() -> Vec<(&'static str, OrderConfirmation)> {
+    let mut emoji = confirmation(
+        1,
+        vec![
+            LineItem::new("Coffee ☕", Money(350)),
+            LineItem::new("Party pack 🎉🎈", Money(1_299)),
+            LineItem::new("Sticker 👩🏽‍💻", Money(150)),
+            LineItem::new("Flag 🇫🇷", Money(500)),
+        ],
+    );
+    emoji.customer_email = Some("zoë@example.test".to_string());
+    let mut largest = confirmation(7, vec![LineItem::new("Everything", Money(u32::MAX))]);
+    largest.currency = Currency::Kwd;
+    largest.reference = Some(OrderReference("2024-000007".to_string()));
+    vec![
+        ("emoji", emoji),
+        (
+            "right-to-left text",
+            confirmation(
+                2,
+                vec![
+                    LineItem::new("كتاب الطبخ", Money(2_500)),
+                    LineItem::new("ספר 2 כרכים", Money(4_000)),
+                ],
+            ),
+        ),
+        (
+            "a bidi override",
+            confirmation(3, vec![LineItem::new("invoice\u{202E}fdp.exe", Money(100))]),
+        ),
+        (
+            "control characters",
+            confirmation(
+                4,
+                vec![LineItem::new(
+                    "12\" ruler\nsecond line\t\u{7}\\",
+                    Money(199),
+                )],
+            ),
+        ),
+        (
+            "a 10 KB name",
+            confirmation(
+                5,
+                vec![
+                    LineItem::new("é".repeat(5 * 1024), Money(1)),
+                    LineItem::new("x".repeat(10 * 1024), Money(1)),
+                ],
+            ),
+        ),
+        (
+            "a zero total",
+            confirmation(6, vec![LineItem::new("Gift", Money(0))]),
+        ),
+        ("the largest total", largest),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_what_is_opened_on_a_line_must_be_closed_on_it() {
+        assert!(bidi_closed("plain"));
+        assert!(bidi_closed("\u{2068}كتاب\u{2069} $1.00"));
+        assert!(!bidi_closed("invoice\u{202E}fdp.exe $1.00"));
+        assert!(!bidi_closed("\u{2067}ספר $1.00"));
+    }
+}
@@ -0,0 +1,175 @@
+// Stub adapters, shared so every test stubs the same way.
+//
+// The early examples each declare their own port (OrderNotifier,
+// StuffHandler...), which no crate outside them can implement. So every
+// stub carries its behavior in an inherent method generic over what it is
+// given and what error it returns; an example only writes the one-line
+// impl of its own trait:
+//
+//     impl OrderNotifier for OkNotifier {
+//         fn process(&self, order: &Order) -> Result<(), OrderError> {
+//             self.notify(order)
+//         }
+//     }
+//
+// The same stubs implement this crate's own ports directly.
+use crate::domain::{Money, OrderConfirmation, OrderError, OrderId};
+use crate::ports::{Capability, PaymentGateway, Sender};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+// Accepts every notification.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OkNotifier;
+
+impl OkNotifier {
+    pub fn notify<T: ?Sized, E>(&self, _subject: &T) -> Result<(), E> {
+        Ok(())
+    }
+}
+
+impl Sender for OkNotifier {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.notify(confirmation)
+    }
+}
+
+impl Capability for OkNotifier {}
+
+// Accepts everything it is asked to handle.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OkHandler;
+
+impl OkHandler {
+    pub fn handle<T: ?Sized, E>(&self, _subject: &T) -> Result<(), E> {
+        Ok(())
+    }
+}
+
+impl Capability for OkHandler {}
+
+// Sends nothing, successfully.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OkSender;
+
+impl Sender for OkSender {
+    fn send(&self, _confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        Ok(())
+    }
+}
+
+impl Capability for OkSender {}
+
+// Every charge, and every refund, goes through.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OkPaymentGateway;
+
+impl PaymentGateway for OkPaymentGateway {
+    fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+        Ok(())
+    }
+
+    fn refund_for(&self, _order_id: OrderId, _amount: Money) -> Result<(), OrderError> {
+        Ok(())
+    }
+}
+
+impl Capability for OkPaymentGateway {}
+
+// Accepts every notification and counts them. Sync, like the adapters.
+#[derive(Debug, Default)]
+pub struct CountingNotifier {
+    calls: AtomicUsize,
+}
+
+impl CountingNotifier {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn notify<T: ?Sized, E>(&self, _subject: &T) -> Result<(), E> {
+        self.calls.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    pub fn calls(&self) -> usize {
+        self.calls.load(Ordering::Relaxed)
+    }
+}
+
+impl Sender for CountingNotifier {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.notify(confirmation)
+    }
+}
+
+impl Capability for CountingNotifier {}
+
+// Refuses every notification with the same error. OrderError by default;
+// an example with an error type of its own names it: ErrNotifier<MyError>.
+#[derive(Debug, Clone)]
+pub struct ErrNotifier<E = OrderError>(pub E);
+
+impl<E: Clone> ErrNotifier<E> {
+    pub fn notify<T: ?Sized>(&self, _subject: &T) -> Result<(), E> {
+        Err(self.0.clone())
+    }
+}
+
+impl Sender for ErrNotifier {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.notify(confirmation)
+    }
+}
+
+impl<E> Capability for ErrNotifier<E> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::assert_err_variant;
+    use crate::domain::{LineItem, Order, OrderId};
+
+    fn confirmation() -> OrderConfirmation {
+        Order::new(
+            OrderId(1),
+            vec![LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            }],
+        )
+        .unwrap()
+        .confirmation()
+    }
+
+    #[test]
+    fn ok_stubs_accept_anything() {
+        assert!(OkNotifier.notify::<_, OrderError>(&42).is_ok());
+        assert!(OkHandler.handle::<_, ()>("stuff").is_ok());
+        assert!(OkNotifier.send(&confirmation()).is_ok());
+        assert!(OkSender.send(&confirmation()).is_ok());
+        assert!(OkPaymentGateway.charge(Money(u32::MAX)).is_ok());
+    }
+
+    #[test]
+    fn counting_notifier_counts_every_call() {
+        let notifier = CountingNotifier::new();
+        assert_eq!(notifier.calls(), 0);
+
+        notifier.notify::<_, OrderError>(&1).unwrap();
+        notifier.send(&confirmation()).unwrap();
+
+        assert_eq!(notifier.calls(), 2);
+    }
+
+    #[test]
+    fn err_notifier_always_returns_its_error() {
+        let notifier = ErrNotifier(OrderError::NotificationFailed);
+        for _ in 0..2 {
+            assert_err_variant!(
+                notifier.send(&confirmation()),
+                OrderError::NotificationFailed
+            );
+        }
+        assert_eq!(ErrNotifier("down").notify(&()), Err("down"));
+    }
+}
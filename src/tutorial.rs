@@ -0,0 +1,157 @@
+// =============================================================================
+// TUTORIAL - Building the hexagon one port at a time
+// =============================================================================
+// examples/ex15 assembles the hexagon at runtime, step by step, and checks
+// after each step that what was just attached works. TutorialRunner keeps
+// the score:
+//
+//     runner.step("Only the domain");
+//     runner.checkpoint("an empty cart is refused", || {
+//         Order::new(OrderId(1), vec![]).is_err()
+//     });
+//     ...
+//     println!("{}", runner.summary());
+//
+// Every checkpoint prints PASS or FAIL as it goes; the summary lists them
+// all at the end. An assertion that panics counts as a FAIL, so assert!
+// and assert_order work inside checkpoints too.
+use crate::adapters::Console;
+use std::fmt::Write;
+use std::panic::{self, AssertUnwindSafe};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checkpoint {
+    // 1 for the first step; 0 before any step.
+    pub step: usize,
+    pub name: String,
+    pub passed: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct TutorialRunner {
+    console: Console,
+    step: usize,
+    checkpoints: Vec<Checkpoint>,
+}
+
+impl TutorialRunner {
+    // Prints on stdout.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_console(mut self, console: Console) -> Self {
+        self.console = console;
+        self
+    }
+
+    // For the adapters of the tutorial, so their lines come out in order
+    // with the runner's.
+    pub fn console(&self) -> Console {
+        self.console.clone()
+    }
+
+    pub fn step(&mut self, title: &str) {
+        self.step += 1;
+        self.console
+            .line(format_args!("\n=== Step {}: {title} ===", self.step));
+    }
+
+    pub fn note(&self, text: &str) {
+        for line in text.lines() {
+            self.console.line(format_args!("  {line}"));
+        }
+    }
+
+    // Runs `assertion` and records whether it held.
+    pub fn checkpoint(&mut self, name: &str, assertion: impl FnOnce() -> bool) -> bool {
+        let passed = panic::catch_unwind(AssertUnwindSafe(assertion)).unwrap_or(false);
+        let verdict = if passed { "PASS" } else { "FAIL" };
+        self.console.line(format_args!("  [{verdict}] {name}"));
+        self.checkpoints.push(Checkpoint {
+            step: self.step,
+            name: name.to_string(),
+            passed,
+        });
+        passed
+    }
+
+    pub fn checkpoints(&self) -> &[Checkpoint] {
+        &self.checkpoints
+    }
+
+    // True for a tutorial with no checkpoint at all: nothing failed.
+    pub fn all_passed(&self) -> bool {
+        self.checkpoints.iter().all(|checkpoint| checkpoint.passed)
+    }
+
+    //     step  checkpoint                  result
+    //     1     an empty cart is refused    PASS
+    //     ...
+    //     7/7 checkpoints passed
+    pub fn summary(&self) -> String {
+        let width = self
+            .checkpoints
+            .iter()
+            .map(|checkpoint| checkpoint.name.chars().count())
+            .fold("checkpoint".len(), usize::max);
+        let mut table = format!("step  {:<width$}  result", "checkpoint");
+        for checkpoint in &self.checkpoints {
+            let verdict = if checkpoint.passed { "PASS" } else { "FAIL" };
+            let _ = write!(
+                table,
+                "\n{:<4}  {:<width$}  {verdict}",
+                checkpoint.step, checkpoint.name
+            );
+        }
+        let passed = self.checkpoints.iter().filter(|c| c.passed).count();
+        let _ = write!(
+            table,
+            "\n{passed}/{} checkpoints passed",
+            self.checkpoints.len()
+        );
+        table
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::adapters::SharedBuffer;
+
+    #[test]
+    fn every_checkpoint_is_printed_and_counted() {
+        let buffer = SharedBuffer::new();
+        let mut runner = TutorialRunner::new().with_console(Console::to(buffer.clone()));
+
+        runner.step("Arithmetic");
+        assert!(runner.checkpoint("one plus one", || 1 + 1 == 2));
+        assert!(!runner.checkpoint("one plus two", || 1 + 2 == 2));
+
+        assert_eq!(
+            buffer.contents(),
+            "\n=== Step 1: Arithmetic ===\n  [PASS] one plus one\n  [FAIL] one plus two\n"
+        );
+        assert!(!runner.all_passed());
+        assert_eq!(
+            runner.summary(),
+            "step  checkpoint    result\n\
+             1     one plus one  PASS\n\
+             1     one plus two  FAIL\n\
+             1/2 checkpoints passed"
+        );
+    }
+
+    #[test]
+    fn a_panicking_assertion_fails_the_checkpoint() {
+        let mut runner = TutorialRunner::new().with_console(Console::silent());
+
+        let passed = runner.checkpoint("finds an order in an empty list", || {
+            let orders: Vec<u32> = Vec::new();
+            orders[0] == 1
+        });
+
+        assert!(!passed);
+        assert!(!runner.checkpoints()[0].passed);
+    }
+}
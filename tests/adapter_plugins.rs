@@ -0,0 +1,246 @@
+// cargo test --test adapter_plugins
+// Adapters from another crate, picked by name from the environment like
+// the built-in ones. Everything here is what that crate would write, with
+// only the public API: a factory, registered once, then named in
+// HEXLITE_REPOSITORY, HEXLITE_PAYMENT or HEXLITE_SENDER.
+//
+// The factories are registered for the whole test binary, each test under
+// names of its own.
+use hexa_lite::composition::{
+    AdapterConfig, AdapterFactory, ConfigError, ConfiguredPayment, ConfiguredSender, EnvConfig,
+    PAYMENT_OPTIONS_VAR, PAYMENT_VAR, REPOSITORY_OPTIONS_VAR, REPOSITORY_VAR, SENDER_VAR,
+    SenderConfig, build_adapters, build_service, register_factory,
+};
+use hexa_lite::domain::OrderConfirmation;
+use hexa_lite::ports::{Capability, ChargeLog, OrderWriter, PaymentGateway, Sender};
+use hexa_lite::prelude::*;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+fn load(vars: &[(&str, &str)]) -> Result<EnvConfig, ConfigError> {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|&(var, value)| (var.to_string(), value.to_string()))
+        .collect();
+    EnvConfig::from_lookup(|var| vars.get(var).cloned())
+}
+
+// What the shop's adapters did, in order.
+type Journal = Arc<Mutex<Vec<String>>>;
+
+fn note(journal: &Journal, line: String) {
+    journal.lock().unwrap().push(line);
+}
+
+struct Till {
+    limit: Money,
+    journal: Journal,
+}
+
+impl PaymentGateway for Till {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        if amount.0 > self.limit.0 {
+            return Err(OrderError::PaymentFailed);
+        }
+        note(&self.journal, format!("charged {amount}"));
+        Ok(())
+    }
+}
+
+struct Postcards(Journal);
+
+impl Sender for Postcards {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        note(&self.0, format!("postcard for {}", confirmation.order_id));
+        Ok(())
+    }
+}
+
+impl Capability for Postcards {}
+
+// Makes all three ports. The till takes a limit=<cents> option.
+struct Shop(Journal);
+
+impl AdapterFactory for Shop {
+    fn make_repository(
+        &self,
+        _config: &AdapterConfig,
+    ) -> Result<Box<dyn OrderWriter + Sync>, ConfigError> {
+        Ok(Box::new(InMemoryOrderRepository::new()))
+    }
+
+    fn make_payment(
+        &self,
+        config: &AdapterConfig,
+    ) -> Result<Box<dyn PaymentGateway + Sync>, ConfigError> {
+        let limit = match config.option("limit") {
+            None => u32::MAX,
+            Some(limit) => limit
+                .parse()
+                .map_err(|_| config.invalid(format!("limit {limit:?} is not in cents")))?,
+        };
+        Ok(Box::new(Till {
+            limit: Money(limit),
+            journal: Arc::clone(&self.0),
+        }))
+    }
+
+    fn make_sender(&self, _config: &AdapterConfig) -> Result<Box<dyn Sender + Sync>, ConfigError> {
+        Ok(Box::new(Postcards(Arc::clone(&self.0))))
+    }
+}
+
+// Makes senders only.
+struct PostOffice(Journal);
+
+impl AdapterFactory for PostOffice {
+    fn make_sender(&self, _config: &AdapterConfig) -> Result<Box<dyn Sender + Sync>, ConfigError> {
+        Ok(Box::new(Postcards(Arc::clone(&self.0))))
+    }
+}
+
+fn cart() -> Vec<LineItem> {
+    vec![LineItem::new("Teapot", Money(2_400))]
+}
+
+#[test]
+fn a_registered_factory_makes_every_port() {
+    let journal = Journal::default();
+    register_factory("shop", Box::new(Shop(Arc::clone(&journal))));
+    let config = load(&[
+        (REPOSITORY_VAR, "shop"),
+        (PAYMENT_VAR, "shop"),
+        (PAYMENT_OPTIONS_VAR, "limit=5000"),
+        (SENDER_VAR, "shop"),
+    ])
+    .unwrap();
+    assert_eq!(
+        config.payment,
+        Some(AdapterConfig {
+            var: PAYMENT_VAR,
+            name: "shop".to_string(),
+            options: vec![("limit".to_string(), "5000".to_string())],
+        })
+    );
+
+    let adapters = build_adapters(&config).unwrap();
+    let mut service = build_service(&adapters);
+    let order = service.place_order(cart()).unwrap();
+
+    assert!(matches!(
+        service.place_order(vec![LineItem::new("Samovar", Money(9_000))]),
+        Err(OrderError::PaymentFailed)
+    ));
+    assert_eq!(
+        *journal.lock().unwrap(),
+        ["charged $24.00", "postcard for #000001"]
+    );
+    assert_eq!(adapters.repository.find(order.id).unwrap(), Some(order));
+    assert!(matches!(adapters.payment, ConfiguredPayment::Plugin(_)));
+    assert!(adapters.payment.charges().is_err());
+    let adapters_of = |port: &str| {
+        adapters
+            .wiring
+            .describe()
+            .into_iter()
+            .find(|binding| binding.port == port)
+            .map(|binding| (binding.adapter, binding.config))
+    };
+    assert_eq!(
+        adapters_of("PaymentGateway"),
+        Some((
+            "shop".to_string(),
+            Some("from a registered factory".to_string())
+        ))
+    );
+}
+
+#[test]
+fn names_nobody_registered_are_refused_together() {
+    let error = load(&[
+        (REPOSITORY_VAR, "postgres"),
+        (PAYMENT_VAR, "stripe"),
+        (REPOSITORY_OPTIONS_VAR, "url"),
+    ])
+    .unwrap_err();
+
+    assert_eq!(
+        error.to_string(),
+        "2 configuration errors:\n  \
+         - HEXLITE_REPOSITORY_OPTIONS is invalid: expected key=value, got \"url\"\n  \
+         - HEXLITE_PAYMENT is invalid: unknown adapter \"stripe\", \
+         expected mock or a registered factory"
+    );
+    // Options are only for a factory: with the built-in, they are a mistake.
+    assert!(matches!(
+        load(&[(PAYMENT_OPTIONS_VAR, "limit=5000")]),
+        Err(ConfigError::Invalid {
+            var: PAYMENT_OPTIONS_VAR,
+            ..
+        })
+    ));
+    // Built by hand, a config can still name a factory that is not there.
+    let mut config = load(&[]).unwrap();
+    config.repository = Some(AdapterConfig {
+        var: REPOSITORY_VAR,
+        name: "postgres".to_string(),
+        options: Vec::new(),
+    });
+    assert_eq!(
+        build_adapters(&config).err(),
+        Some(ConfigError::Invalid {
+            var: REPOSITORY_VAR,
+            reason: "no factory registered as \"postgres\"".to_string(),
+        })
+    );
+}
+
+#[test]
+fn what_a_factory_refuses_is_a_config_error() {
+    register_factory("post-office", Box::new(PostOffice(Journal::default())));
+    register_factory("strict-shop", Box::new(Shop(Journal::default())));
+    let config = load(&[
+        (PAYMENT_VAR, "post-office"),
+        (SENDER_VAR, "strict-shop"),
+        (REPOSITORY_VAR, "strict-shop"),
+    ])
+    .unwrap();
+    let mut picky = config.clone();
+    picky.payment = Some(AdapterConfig {
+        var: PAYMENT_VAR,
+        name: "strict-shop".to_string(),
+        options: vec![("limit".to_string(), "ten dollars".to_string())],
+    });
+
+    assert_eq!(
+        build_adapters(&config).err(),
+        Some(ConfigError::Invalid {
+            var: PAYMENT_VAR,
+            reason: "post-office: makes no PaymentGateway".to_string(),
+        })
+    );
+    assert_eq!(
+        build_adapters(&picky).err().map(|error| error.to_string()),
+        Some(
+            "HEXLITE_PAYMENT is invalid: strict-shop: limit \"ten dollars\" is not in cents"
+                .to_string()
+        )
+    );
+}
+
+#[test]
+fn a_factory_may_take_over_a_built_in_name() {
+    let journal = Journal::default();
+    register_factory("webhook", Box::new(PostOffice(Arc::clone(&journal))));
+
+    // The built-in webhook sender would want a URL: the factory does not.
+    let config = load(&[(SENDER_VAR, "webhook")]).unwrap();
+    assert!(matches!(&config.sender, SenderConfig::Plugin(plugin) if plugin.name == "webhook"));
+    let adapters = build_adapters(&config).unwrap();
+    build_service(&adapters).place_order(cart()).unwrap();
+
+    assert!(matches!(adapters.sender, ConfiguredSender::Plugin(_)));
+    assert_eq!(*journal.lock().unwrap(), ["postcard for #000001"]);
+    // Unset, a port keeps its built-in whatever is registered.
+    assert_eq!(load(&[]).unwrap().sender, SenderConfig::Console);
+}
@@ -0,0 +1,449 @@
+// cargo test --test adversarial_repository
+// The application layer never panics, whatever a repository hands back.
+// EvilRepository keeps honest orders and lies about them on the way out:
+// no items, a total its items do not add up to, shipments of items the
+// order does not have, a version that cannot be bumped, an order filed
+// under someone else's id, listings with an id twice or backwards. Every
+// public use case reading through it must stop with an error, CorruptData
+// where a lie is what it found, and none of them may panic.
+use hexa_lite::adapters::in_memory::MockShippingProvider;
+use hexa_lite::adapters::offline::InMemoryPendingCharges;
+use hexa_lite::adapters::retention::AgeAndStatusRetention;
+use hexa_lite::application::{OrderBrowser, Reconciliation, ShippingService};
+use hexa_lite::domain::{
+    Address, ApproverId, OrderCriteria, OrderKey, ReviewDecision, Shipment, TrackingId,
+};
+use hexa_lite::ports::{
+    AmendOrder, CancelToken, OrderQueries, OrderReader, OrderWriter, PendingCharge, PendingCharges,
+    ServiceState,
+};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::panic::{self, AssertUnwindSafe};
+use std::time::Duration;
+
+// Ways a repository can lie about the orders it holds.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Lie {
+    NoItems,
+    TotalAboveItems,
+    ItemsOverflow,
+    ShipsMissingItem,
+    ShipsItemTwice,
+    LastVersion,
+    // find() answers with another order than the one asked for.
+    SomeoneElses,
+    // list() repeats its first order, or goes backwards.
+    ListedTwice,
+    ListedBackwards,
+}
+
+const LIES: [Lie; 9] = [
+    Lie::NoItems,
+    Lie::TotalAboveItems,
+    Lie::ItemsOverflow,
+    Lie::ShipsMissingItem,
+    Lie::ShipsItemTwice,
+    Lie::LastVersion,
+    Lie::SomeoneElses,
+    Lie::ListedTwice,
+    Lie::ListedBackwards,
+];
+
+impl Lie {
+    fn about_one_order(self) -> bool {
+        !matches!(self, Lie::ListedTwice | Lie::ListedBackwards)
+    }
+
+    fn about_listings(self) -> bool {
+        self != Lie::SomeoneElses
+    }
+}
+
+// Writes are honest; reads are not.
+struct EvilRepository {
+    honest: InMemoryOrderRepository,
+    lie: Lie,
+}
+
+impl EvilRepository {
+    // Orders 1 and 2 paid, 3 and 4 cancelled then deleted.
+    fn new(lie: Lie) -> Self {
+        let honest = InMemoryOrderRepository::new();
+        {
+            let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+            let clock = SteppingClock::starting_at(Timestamp(1_000));
+            let mut service = OrderService::new(&honest, &payment, &sender).with_clock(&clock);
+            for _ in 0..4 {
+                service.place_order(cart()).unwrap();
+            }
+            for id in [OrderId(3), OrderId(4)] {
+                service.cancel_order(id).unwrap();
+                service.delete_order(id).unwrap();
+            }
+        }
+        Self { honest, lie }
+    }
+
+    fn tell(&self, mut order: Order) -> Order {
+        let shipment = |index| Shipment {
+            tracking: TrackingId("T1".to_string()),
+            item_indices: vec![index],
+            shipped_at: Timestamp(2_000),
+        };
+        match self.lie {
+            Lie::NoItems => order.items.clear(),
+            Lie::TotalAboveItems => order.total = Money(u32::MAX),
+            Lie::ItemsOverflow => order.items[0].price = Money(u32::MAX),
+            Lie::ShipsMissingItem => order.shipments.push(shipment(order.items.len())),
+            Lie::ShipsItemTwice => order.shipments = vec![shipment(0), shipment(0)],
+            Lie::LastVersion => order.version = u32::MAX,
+            Lie::SomeoneElses | Lie::ListedTwice | Lie::ListedBackwards => {}
+        }
+        order
+    }
+
+    fn tell_all(&self, orders: Vec<Order>) -> Vec<Order> {
+        let mut orders: Vec<Order> = orders.into_iter().map(|order| self.tell(order)).collect();
+        match self.lie {
+            Lie::ListedTwice if !orders.is_empty() => orders.push(orders[0].clone()),
+            Lie::ListedBackwards => orders.reverse(),
+            _ => {}
+        }
+        orders
+    }
+}
+
+impl OrderReader for EvilRepository {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        let asked = match self.lie {
+            Lie::SomeoneElses => OrderId(id.0 % 2 + 1),
+            _ => id,
+        };
+        Ok(self.honest.find(asked)?.map(|order| self.tell(order)))
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        Ok(self.tell_all(self.honest.list()?))
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        Ok(self.tell_all(self.honest.list_deleted()?))
+    }
+}
+
+impl OrderWriter for EvilRepository {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.honest.save(order)
+    }
+
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        self.honest.update(order)
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.honest.soft_delete(id)
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.honest.restore(id)
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.honest.purge(id)
+    }
+}
+
+fn cart() -> Vec<LineItem> {
+    vec![
+        LineItem::new("Teapot", Money(2_400)),
+        LineItem::new("Cup", Money(600)),
+    ]
+}
+
+fn address() -> Address {
+    Address {
+        street: "1 Main St".to_string(),
+        city: "Springfield".to_string(),
+        postal_code: "12345".to_string(),
+        country: "US".to_string(),
+    }
+}
+
+type Service<'a> = OrderService<'a, EvilRepository, MockPaymentGateway, ConsoleSender>;
+
+fn with_service(
+    repo: &EvilRepository,
+    use_case: impl FnOnce(&mut Service<'_>) -> Result<(), OrderError>,
+) -> Result<(), OrderError> {
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    use_case(&mut OrderService::new(repo, &payment, &sender))
+}
+
+type UseCase = fn(&EvilRepository) -> Result<(), OrderError>;
+
+// Use cases reading one order, order 1.
+fn one_order_use_cases() -> Vec<(&'static str, UseCase)> {
+    vec![
+        ("get_order", |repo| {
+            with_service(repo, |s| s.get_order(OrderId(1)).map(drop))
+        }),
+        ("get_order_by_key", |repo| {
+            with_service(repo, |s| {
+                s.get_order_by_key(OrderKey::Sequential(OrderId(1)))
+                    .map(drop)
+            })
+        }),
+        ("cancel_order", |repo| {
+            with_service(repo, |s| s.cancel_order(OrderId(1)).map(drop))
+        }),
+        ("amend_order", |repo| {
+            with_service(repo, |s| {
+                s.amend_order(AmendOrder {
+                    order_id: OrderId(1),
+                    new_address: Some(address()),
+                    gift_note: Some("Enjoy".to_string()),
+                })
+                .map(drop)
+            })
+        }),
+        ("approve_order", |repo| {
+            with_service(repo, |s| {
+                s.approve_order(OrderId(1), ApproverId("ana".to_string()))
+                    .map(drop)
+            })
+        }),
+        ("reject_order", |repo| {
+            with_service(repo, |s| s.reject_order(OrderId(1), "no").map(drop))
+        }),
+        ("resolve_review", |repo| {
+            with_service(repo, |s| {
+                s.resolve_review(OrderId(1), ReviewDecision::Accept)
+                    .map(drop)
+            })
+        }),
+        ("delete_order", |repo| {
+            with_service(repo, |s| s.delete_order(OrderId(1)))
+        }),
+        ("merge_drafts", |repo| {
+            with_service(repo, |s| s.merge_drafts(OrderId(1), OrderId(2)).map(drop))
+        }),
+        ("update_draft_with_resolution", |repo| {
+            // A stale version: the update conflicts, and theirs is read.
+            let mut base = Order::new(OrderId(1), cart())?;
+            base.version = 5;
+            with_service(repo, |s| {
+                s.update_draft_with_resolution(&base, base.clone())
+                    .map(drop)
+            })
+        }),
+        // Reports what went wrong with each charge rather than failing.
+        ("settle_pending", |repo| {
+            let waiting = InMemoryPendingCharges::new();
+            waiting.record(PendingCharge {
+                order_id: OrderId(1),
+                amount: Money(3_000),
+            })?;
+            let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+            let report = OrderService::new(repo, &payment, &sender)
+                .with_pending_charges(&waiting)
+                .settle_pending()?;
+            match report.failed.into_iter().next() {
+                Some((_, e)) => Err(e),
+                None => Ok(()),
+            }
+        }),
+        ("browser.get", |repo| {
+            OrderBrowser::new(repo).get(OrderId(1)).map(drop)
+        }),
+        ("ship_items", |repo| {
+            let (carrier, clock) = (
+                MockShippingProvider::new(),
+                SteppingClock::starting_at(Timestamp(3_000)),
+            );
+            ShippingService::new(repo, &carrier, &clock)
+                .ship_items(OrderId(1), &[0], &address())
+                .map(drop)
+        }),
+        ("ship_by_warehouse", |repo| {
+            let (carrier, clock) = (
+                MockShippingProvider::new(),
+                SteppingClock::starting_at(Timestamp(3_000)),
+            );
+            ShippingService::new(repo, &carrier, &clock)
+                .ship_by_warehouse(OrderId(1), &address())
+                .map(drop)
+        }),
+    ]
+}
+
+// Use cases reading every order.
+fn every_order_use_cases() -> Vec<(&'static str, UseCase)> {
+    vec![
+        ("restore", |repo| {
+            let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+            let state = ServiceState {
+                next_id: 1,
+                open_drafts: Vec::new(),
+            };
+            OrderService::restore(repo, &payment, &sender, &state)
+                .and_then(|mut service| service.place_order(cart()))
+                .map(drop)
+        }),
+        ("expire_stale", |repo| {
+            with_service(repo, |s| s.expire_stale(Timestamp(1_000_000)).map(drop))
+        }),
+        ("purge", |repo| {
+            let keep_nothing =
+                AgeAndStatusRetention::new(Duration::ZERO).with_status(OrderStatus::Paid);
+            let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+            OrderService::new(repo, &payment, &sender)
+                .with_retention_policy(&keep_nothing)
+                .purge(Timestamp(1_000_000))
+                .map(drop)
+        }),
+        ("bulk_transition", |repo| {
+            with_service(repo, |s| {
+                s.bulk_transition(
+                    &OrderCriteria::any(),
+                    OrderStatus::Cancelled,
+                    &CancelToken::new(),
+                    &mut |_| {},
+                )
+                .map(drop)
+            })
+        }),
+        ("browser.list", |repo| {
+            OrderBrowser::new(repo).list().map(drop)
+        }),
+        ("browser.list_deleted", |repo| {
+            OrderBrowser::new(repo).list_deleted().map(drop)
+        }),
+        ("browser.search", |repo| {
+            OrderBrowser::new(repo).search("tea").map(drop)
+        }),
+        ("browser.revenue", |repo| {
+            OrderBrowser::new(repo).revenue().map(drop)
+        }),
+        ("reconcile", |repo| {
+            let charges = MockPaymentGateway::new();
+            Reconciliation::new(repo, &charges).reconcile().map(drop)
+        }),
+    ]
+}
+
+// Runs every use case against every lie it applies to; lists the ones
+// that panicked, or did not see through the lie.
+fn failures(use_cases: Vec<(&'static str, UseCase)>, applies: fn(Lie) -> bool) -> Vec<String> {
+    let mut failures = Vec::new();
+    for lie in LIES.into_iter().filter(|&lie| applies(lie)) {
+        for (name, use_case) in &use_cases {
+            let repo = EvilRepository::new(lie);
+            match panic::catch_unwind(AssertUnwindSafe(|| use_case(&repo))) {
+                Err(_) => failures.push(format!("{name} panicked on {lie:?}")),
+                Ok(Err(OrderError::CorruptData { .. })) => {}
+                Ok(outcome) => failures.push(format!("{name} took {lie:?} for {outcome:?}")),
+            }
+        }
+    }
+    failures
+}
+
+#[test]
+fn no_use_case_reading_one_order_falls_for_a_lie() {
+    let failures = failures(one_order_use_cases(), Lie::about_one_order);
+    assert!(failures.is_empty(), "{failures:#?}");
+}
+
+#[test]
+fn no_use_case_reading_every_order_falls_for_a_lie() {
+    let failures = failures(every_order_use_cases(), Lie::about_listings);
+    assert!(failures.is_empty(), "{failures:#?}");
+}
+
+#[test]
+fn what_is_wrong_is_said_about_the_order_it_is_wrong_with() {
+    let reason = |lie| {
+        let repo = EvilRepository::new(lie);
+        match with_service(&repo, |s| s.cancel_order(OrderId(1)).map(drop)) {
+            Err(OrderError::CorruptData { order_id, reason }) => (order_id, reason),
+            other => panic!("{lie:?}: expected CorruptData, got {other:?}"),
+        }
+    };
+
+    assert_eq!(reason(Lie::NoItems), (OrderId(1), "no items".to_string()));
+    assert_eq!(
+        reason(Lie::ShipsMissingItem),
+        (OrderId(1), "shipment 0 names item 2 of 2".to_string())
+    );
+    assert_eq!(
+        reason(Lie::SomeoneElses),
+        (OrderId(2), "found under #000001".to_string())
+    );
+    let repo = EvilRepository::new(Lie::ListedTwice);
+    assert_err_variant!(
+        OrderBrowser::new(&repo).list(),
+        OrderError::CorruptData {
+            order_id: OrderId(1),
+            ..
+        }
+    );
+}
+
+#[test]
+fn a_lie_stops_a_scan_before_anything_is_changed() {
+    let repo = EvilRepository::new(Lie::ListedBackwards);
+
+    assert_err_variant!(
+        with_service(&repo, |s| {
+            s.bulk_transition(
+                &OrderCriteria::any(),
+                OrderStatus::Cancelled,
+                &CancelToken::new(),
+                &mut |_| {},
+            )
+            .map(drop)
+        }),
+        OrderError::CorruptData { .. }
+    );
+    let statuses: Vec<OrderStatus> = repo
+        .honest
+        .list()
+        .unwrap()
+        .iter()
+        .map(|order| order.status)
+        .collect();
+    assert_eq!(statuses, [OrderStatus::Paid, OrderStatus::Paid]);
+}
+
+#[test]
+fn oversized_and_last_id_orders_are_handled_without_a_panic() {
+    // A hundred thousand lines is a lot, not a lie: it is read and used.
+    let repo = InMemoryOrderRepository::new();
+    let huge = vec![LineItem::new("Bead", Money(1)); 100_000];
+    let mut order = Order::new(OrderId(1), huge).unwrap();
+    order.mark_paid().unwrap();
+    repo.save(&order).unwrap();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let amended = service
+        .amend_order(AmendOrder {
+            order_id: OrderId(1),
+            new_address: None,
+            gift_note: Some("Count them".to_string()),
+        })
+        .unwrap();
+    assert_eq!(amended.items.len(), 100_000);
+
+    // With the last id taken, there is none left to give.
+    let repo = InMemoryOrderRepository::new();
+    repo.save(&Order::new(OrderId(u32::MAX), cart()).unwrap())
+        .unwrap();
+    let state = ServiceState {
+        next_id: 1,
+        open_drafts: Vec::new(),
+    };
+    let mut service = OrderService::restore(&repo, &payment, &sender, &state).unwrap();
+    assert_err_variant!(service.place_order(cart()), OrderError::StorageFailed);
+    assert_eq!(repo.list().unwrap().len(), 1);
+}
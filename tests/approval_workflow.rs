@@ -0,0 +1,160 @@
+// cargo test --test approval_workflow
+// Orders above the threshold are parked by place_order and only charged
+// when approve_order is called; reject_order cancels them uncharged.
+use hexa_lite::adapters::approval::ThresholdApproval;
+use hexa_lite::domain::{Approval, ApproverId};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::sync::Mutex;
+
+// Records every amount charged, so a test can tell *when* it happened.
+#[derive(Default)]
+struct RecordingGateway {
+    charges: Mutex<Vec<Money>>,
+}
+
+impl RecordingGateway {
+    fn charges(&self) -> Vec<Money> {
+        self.charges.lock().unwrap().clone()
+    }
+}
+
+impl PaymentGateway for RecordingGateway {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.charges.lock().unwrap().push(amount);
+        Ok(())
+    }
+}
+
+fn monitor() -> Vec<LineItem> {
+    vec![LineItem {
+        name: "Monitor 32\"".to_string(),
+        price: Money(149_900),
+    }]
+}
+
+fn mouse() -> Vec<LineItem> {
+    vec![LineItem {
+        name: "Mouse".to_string(),
+        price: Money(2500),
+    }]
+}
+
+fn alice() -> ApproverId {
+    ApproverId("alice".to_string())
+}
+
+#[test]
+fn large_order_is_charged_only_when_approved() {
+    let repo = InMemoryOrderRepository::new();
+    let gateway = RecordingGateway::default();
+    let policy = ThresholdApproval::new(Money(100_000));
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &gateway, &sender).with_approval_policy(&policy);
+
+    let parked = service.place_order(monitor()).unwrap();
+    assert_order(&parked).has_status(OrderStatus::PendingApproval);
+    assert!(gateway.charges().is_empty());
+
+    let approved = service.approve_order(parked.id, alice()).unwrap();
+    assert_order(&approved).has_status(OrderStatus::Paid);
+    assert_eq!(approved.approval, Some(Approval::Approved { by: alice() }));
+    assert_eq!(gateway.charges(), vec![Money(149_900)]);
+
+    let stored = repo.find(parked.id).unwrap().unwrap();
+    assert_eq!(stored, approved);
+}
+
+#[test]
+fn small_order_is_charged_at_once() {
+    let repo = InMemoryOrderRepository::new();
+    let gateway = RecordingGateway::default();
+    let policy = ThresholdApproval::new(Money(100_000));
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &gateway, &sender).with_approval_policy(&policy);
+
+    let order = service.place_order(mouse()).unwrap();
+
+    assert_order(&order).has_status(OrderStatus::Paid);
+    assert_eq!(order.approval, None);
+    assert_eq!(gateway.charges(), vec![Money(2500)]);
+}
+
+#[test]
+fn rejected_order_is_cancelled_and_never_charged() {
+    let repo = InMemoryOrderRepository::new();
+    let gateway = RecordingGateway::default();
+    let policy = ThresholdApproval::new(Money(100_000));
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &gateway, &sender).with_approval_policy(&policy);
+
+    let parked = service.place_order(monitor()).unwrap();
+    let rejected = service.reject_order(parked.id, "over budget").unwrap();
+
+    assert_order(&rejected).has_status(OrderStatus::Cancelled);
+    assert_eq!(
+        rejected.approval,
+        Some(Approval::Rejected {
+            reason: "over budget".to_string()
+        })
+    );
+    // Too late to change one's mind.
+    assert_err_variant!(
+        service.approve_order(parked.id, alice()),
+        OrderError::NotPendingApproval {
+            status: OrderStatus::Cancelled,
+            ..
+        }
+    );
+    assert!(gateway.charges().is_empty());
+}
+
+#[test]
+fn second_approval_is_refused_and_charges_nothing() {
+    let repo = InMemoryOrderRepository::new();
+    let gateway = RecordingGateway::default();
+    let policy = ThresholdApproval::new(Money(100_000));
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &gateway, &sender).with_approval_policy(&policy);
+
+    let parked = service.place_order(monitor()).unwrap();
+    service.approve_order(parked.id, alice()).unwrap();
+
+    let bob = ApproverId("bob".to_string());
+    match service.approve_order(parked.id, bob) {
+        Err(OrderError::AlreadyApproved { id, by }) => {
+            assert_eq!(id, parked.id);
+            assert_eq!(by, alice());
+        }
+        other => panic!("expected AlreadyApproved, got {other:?}"),
+    }
+    assert_eq!(gateway.charges().len(), 1);
+}
+
+#[test]
+fn order_that_never_waited_cannot_be_approved() {
+    let repo = InMemoryOrderRepository::new();
+    let gateway = RecordingGateway::default();
+    let policy = ThresholdApproval::new(Money(100_000));
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &gateway, &sender).with_approval_policy(&policy);
+
+    let order = service.place_order(mouse()).unwrap();
+
+    assert_err_variant!(
+        service.approve_order(order.id, alice()),
+        OrderError::NotPendingApproval {
+            status: OrderStatus::Paid,
+            ..
+        }
+    );
+    assert_err_variant!(
+        service.reject_order(order.id, "changed my mind"),
+        OrderError::NotPendingApproval { .. }
+    );
+    assert_err_variant!(
+        service.approve_order(OrderId(99), alice()),
+        OrderError::NotFound { .. }
+    );
+    assert_eq!(gateway.charges().len(), 1);
+}
@@ -0,0 +1,235 @@
+// cargo test --test architecture
+// The layering rules of the hexagon, checked on the crate's own source:
+//
+// - domain depends on nothing else in the crate
+// - ports depend on the domain only
+// - application never names an adapter
+// - an adapter never reaches into another adapter; the private helpers
+//   of the adapters module (json, hmac...) are shared by all of them
+//
+// Only `crate::` and `super::` paths in non-test code are looked at:
+// `#[cfg(test)]` blocks may use whatever they need. A failure names the
+// file and the line. Deliberate exceptions go in ALLOWED, with a reason.
+//
+// No syn here, the crate has no dependencies: lines are scanned as text,
+// which is enough for code formatted by rustfmt. Copy this file to guard
+// your own hexagon, and adapt LAYERS to your module names.
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// (file, path it may reference, why)
+const ALLOWED: &[(&str, &str, &str)] = &[
+    (
+        "src/adapters/webhook.rs",
+        "adapters::clock",
+        "the system clock is the default source of X-Timestamp",
+    ),
+    (
+        "src/adapters/audit_log.rs",
+        "adapters::clock",
+        "the system clock is the default time of a published entry",
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Layer {
+    Domain,
+    Ports,
+    Application,
+    Adapter,
+    // lib.rs, prelude, testing, tutorial, composition: allowed to see
+    // everything.
+    Outside,
+}
+
+fn layer_of(file: &str) -> Layer {
+    let module = file
+        .trim_start_matches("src/")
+        .split(['/', '.'])
+        .next()
+        .unwrap_or_default();
+    match module {
+        "domain" => Layer::Domain,
+        "ports" => Layer::Ports,
+        "application" => Layer::Application,
+        "adapters" => Layer::Adapter,
+        _ => Layer::Outside,
+    }
+}
+
+// src/adapters/webhook.rs -> Some("webhook"), src/adapters/mod.rs -> None.
+// The files of a module split in several belong to it:
+// src/adapters/file_repository/ron.rs -> Some("file_repository").
+fn adapter_name(file: &str) -> Option<&str> {
+    let name = file.strip_prefix("src/adapters/")?.strip_suffix(".rs")?;
+    let name = name.split('/').next()?;
+    (name != "mod").then_some(name)
+}
+
+// The adapters: modules declared `pub mod` in src/adapters/mod.rs.
+// Private ones (`mod json;`) are the shared helpers.
+fn public_adapters(root: &Path) -> Vec<String> {
+    let text = fs::read_to_string(root.join("src/adapters/mod.rs")).unwrap();
+    text.lines()
+        .filter_map(|line| line.trim().strip_prefix("pub mod "))
+        .map(|name| name.trim_end_matches(';').to_string())
+        .collect()
+}
+
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            rust_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+// Every `crate::a::b` in the line, as "a::b" (or "a").
+// In a file under src/adapters/, `super::x` is `crate::adapters::x`.
+fn referenced_paths(line: &str, in_adapters: bool) -> Vec<String> {
+    let mut paths = Vec::new();
+    for (prefix, base) in [("crate::", ""), ("super::", "adapters::")] {
+        if prefix == "super::" && !in_adapters {
+            continue;
+        }
+        let mut rest = line;
+        while let Some(at) = rest.find(prefix) {
+            let after = &rest[at + prefix.len()..];
+            let segments: Vec<&str> = after
+                .split("::")
+                .take(2)
+                .map(|segment| {
+                    let end = segment
+                        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                        .unwrap_or(segment.len());
+                    &segment[..end]
+                })
+                .take_while(|segment| !segment.is_empty())
+                .collect();
+            if !segments.is_empty() {
+                paths.push(format!("{base}{}", segments.join("::")));
+            }
+            rest = after;
+        }
+    }
+    paths
+}
+
+fn violation(file: &str, path: &str, adapters: &[String]) -> Option<&'static str> {
+    let top = path.split("::").next().unwrap_or_default();
+    match layer_of(file) {
+        Layer::Domain if matches!(top, "ports" | "application" | "adapters" | "composition") => {
+            Some("the domain depends on nothing else in the crate")
+        }
+        Layer::Ports if matches!(top, "application" | "adapters" | "composition") => {
+            Some("ports only depend on the domain")
+        }
+        Layer::Application if matches!(top, "adapters" | "composition") => {
+            Some("the application never names an adapter")
+        }
+        Layer::Adapter => {
+            let other = path.strip_prefix("adapters::")?.split("::").next()?;
+            let this = adapter_name(file)?;
+            (other != this && adapters.iter().any(|adapter| adapter == other))
+                .then_some("an adapter never reaches into another adapter")
+        }
+        _ => None,
+    }
+}
+
+fn allowed(file: &str, path: &str) -> bool {
+    ALLOWED.iter().any(|(allowed_file, allowed_path, _)| {
+        *allowed_file == file && path.starts_with(allowed_path)
+    })
+}
+
+#[test]
+fn layers_only_depend_inwards() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let adapters = public_adapters(root);
+    let mut files = Vec::new();
+    rust_files(&root.join("src"), &mut files);
+    files.sort();
+
+    let mut violations = Vec::new();
+    for path in &files {
+        let file = path
+            .strip_prefix(root)
+            .unwrap()
+            .to_string_lossy()
+            .replace('\\', "/");
+        let text = fs::read_to_string(path).unwrap();
+        for (number, line) in text.lines().enumerate() {
+            let code = line.trim_start();
+            if code.starts_with("#[cfg(test)]") {
+                break; // by convention the test module closes the file
+            }
+            if code.starts_with("//") {
+                continue;
+            }
+            for referenced in referenced_paths(code, file.starts_with("src/adapters/")) {
+                if let Some(rule) = violation(&file, &referenced, &adapters)
+                    && !allowed(&file, &referenced)
+                {
+                    violations.push(format!(
+                        "{file}:{}: crate::{referenced}: {rule}",
+                        number + 1
+                    ));
+                }
+            }
+        }
+    }
+
+    assert!(
+        violations.is_empty(),
+        "layering violations:\n  {}",
+        violations.join("\n  ")
+    );
+}
+
+// Every exception must still be needed, or it silently widens the rules.
+#[test]
+fn every_exception_is_used() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    for (file, path, reason) in ALLOWED {
+        let text = fs::read_to_string(root.join(file)).unwrap();
+        let needed = text
+            .lines()
+            .take_while(|line| !line.trim_start().starts_with("#[cfg(test)]"))
+            .flat_map(|line| referenced_paths(line, file.starts_with("src/adapters/")))
+            .any(|referenced| referenced.starts_with(path));
+        assert!(needed, "{file} no longer uses {path} ({reason})");
+    }
+}
+
+#[test]
+fn the_checker_catches_what_it_should() {
+    let adapters = vec!["in_memory".to_string(), "webhook".to_string()];
+    let check = |file: &str, line: &str| {
+        referenced_paths(line, file.starts_with("src/adapters/"))
+            .iter()
+            .any(|path| violation(file, path, &adapters).is_some())
+    };
+
+    assert!(check("src/domain/draft.rs", "use crate::ports::Sender;"));
+    assert!(check(
+        "src/application.rs",
+        "    let repo = crate::adapters::in_memory::InMemoryOrderRepository::new();"
+    ));
+    assert!(check(
+        "src/adapters/webhook.rs",
+        "use super::in_memory::ConsoleSender;"
+    ));
+    assert!(!check("src/adapters/webhook.rs", "use super::json;"));
+    assert!(!check(
+        "src/application.rs",
+        "use crate::ports::{Clock, Sender};"
+    ));
+    assert!(!check(
+        "src/composition.rs",
+        "use crate::adapters::webhook::WebhookSender;"
+    ));
+}
@@ -0,0 +1,138 @@
+// cargo test --test audit_log
+// The audit trail outlives the process: entries go to files that rotate by
+// size, a query finds an order's entries in whichever file they ended up
+// in, and only the oldest files are given up once there are too many.
+use hexa_lite::adapters::audit_log::{ACTIVE_AUDIT_FILE, AuditEntry, FileAuditLog};
+use hexa_lite::domain::OrderEvent;
+use hexa_lite::ports::EventPublisher;
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+
+// One directory per test, removed when the test ends, even on failure.
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path = std::env::temp_dir().join(format!("hexa_lite_{}_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        TempDir(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn paid(order_id: u32, at: u64) -> AuditEntry {
+    AuditEntry {
+        at: Timestamp(at),
+        event: OrderEvent::OrderPaid {
+            order_id: OrderId(order_id),
+            amount: Money(1_000),
+        },
+    }
+}
+
+fn names(log: &FileAuditLog) -> Vec<String> {
+    log.files()
+        .iter()
+        .map(|path| path.file_name().unwrap().to_string_lossy().into_owned())
+        .collect()
+}
+
+// About 60 bytes a line: ten lines take a file past 600 bytes.
+const MAX_BYTES: u64 = 600;
+
+#[test]
+fn entries_are_found_across_rotated_files() {
+    let dir = TempDir::new("audit_rotation");
+    let log = FileAuditLog::open(&dir.0, MAX_BYTES).unwrap();
+
+    // Order 7 every third entry, the rest for order 1: 25 entries make two
+    // rotations, and five more lines in the active file.
+    for n in 0..25 {
+        let order = if n % 3 == 0 { 7 } else { 1 };
+        log.append(&[paid(order, n)]).unwrap();
+    }
+
+    assert_eq!(
+        names(&log),
+        ["audit.2.jsonl", "audit.1.jsonl", ACTIVE_AUDIT_FILE]
+    );
+    assert!(fs::metadata(log.active()).unwrap().len() <= MAX_BYTES);
+    let times: Vec<u64> = log
+        .query(OrderId(7))
+        .unwrap()
+        .iter()
+        .map(|entry| entry.at.0)
+        .collect();
+    assert_eq!(times, [0, 3, 6, 9, 12, 15, 18, 21, 24]);
+    assert!(log.query(OrderId(99)).unwrap().is_empty());
+}
+
+#[test]
+fn the_oldest_file_goes_once_there_are_more_than_kept() {
+    let dir = TempDir::new("audit_keep");
+    let log = FileAuditLog::open(&dir.0, MAX_BYTES).unwrap().with_keep(2);
+
+    // A batch of ten is one file's worth: four batches, four rotations.
+    for batch in 0..4 {
+        let entries: Vec<AuditEntry> = (0..10)
+            .map(|n| paid(batch, batch as u64 * 10 + n))
+            .collect();
+        log.append(&entries).unwrap();
+    }
+
+    assert_eq!(
+        names(&log),
+        ["audit.2.jsonl", "audit.1.jsonl", ACTIVE_AUDIT_FILE]
+    );
+    assert!(!log.rotated(3).exists());
+    assert!(log.query(OrderId(0)).unwrap().is_empty());
+    assert!(log.query(OrderId(1)).unwrap().is_empty());
+    assert_eq!(log.query(OrderId(2)).unwrap().len(), 10);
+    assert_eq!(log.query(OrderId(3)).unwrap()[0], paid(3, 30));
+}
+
+#[test]
+fn published_events_are_stamped_and_survive_a_reopen() {
+    let dir = TempDir::new("audit_publish");
+    {
+        let log = FileAuditLog::open(&dir.0, MAX_BYTES)
+            .unwrap()
+            .with_clock(SteppingClock::starting_at(Timestamp(1_700_000_000)));
+        log.publish(&OrderEvent::OrderExpired {
+            order_id: OrderId(4),
+        })
+        .unwrap();
+    }
+    // A torn line from a crash: skipped, not fatal.
+    let log = FileAuditLog::open(&dir.0, MAX_BYTES).unwrap();
+    fs::write(
+        log.active(),
+        fs::read_to_string(log.active()).unwrap() + r#"{"at":1,"type":"OrderExp"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        log.query(OrderId(4)).unwrap(),
+        [AuditEntry {
+            at: Timestamp(1_700_000_000),
+            event: OrderEvent::OrderExpired {
+                order_id: OrderId(4)
+            },
+        }]
+    );
+}
+
+#[test]
+fn a_directory_that_is_not_there_is_refused_at_open() {
+    let dir = std::env::temp_dir().join("hexa-lite-no-such-audit-dir");
+
+    assert!(FileAuditLog::open(dir, MAX_BYTES).is_err());
+}
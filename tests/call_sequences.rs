@@ -0,0 +1,135 @@
+// cargo test --test call_sequences
+// The order in which a use case calls its ports, across all of them: every
+// adapter wrapped in a Recorded, reporting into one GlobalRecorder, and
+// expect_sequence! checking the calls that matter while a metrics counter
+// or an audit event is free to come in between.
+use hexa_lite::adapters::metrics::InMemoryMetrics;
+use hexa_lite::adapters::timing::Timed;
+use hexa_lite::domain::OrderEvent;
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::time::Duration;
+
+// Publishes into the void, and lets the recorder tell when. A function is
+// an EventPublisher as it is.
+fn nowhere(_event: &OrderEvent) -> Result<(), OrderError> {
+    Ok(())
+}
+
+// Charges everything, refunds nothing, as any function made a gateway.
+fn no_refunds(_amount: Money) -> Result<(), OrderError> {
+    Ok(())
+}
+
+fn cart() -> Vec<LineItem> {
+    vec![LineItem::new("Keyboard", Money(4999))]
+}
+
+#[test]
+fn place_order_charges_then_saves_then_tells() {
+    let recorder = GlobalRecorder::new();
+    let metrics = Recorded::new(InMemoryMetrics::new(), &recorder, "metrics");
+    let repo = Recorded::new(InMemoryOrderRepository::new(), &recorder, "repository");
+    // Timed on the outside: every charge is followed by its histogram.
+    let payment = Timed::new(
+        Recorded::new(MockPaymentGateway::new(), &recorder, "payment"),
+        &metrics,
+        "payment_ms",
+        || Duration::ZERO,
+    );
+    let sender = Recorded::new(ConsoleSender::new(), &recorder, "sender");
+    let events = Recorded::new(nowhere, &recorder, "events");
+    let mut service = OrderService::new(&repo, &payment, &sender).with_event_publisher(&events);
+
+    let order = service.place_order(cart()).unwrap();
+
+    expect_sequence!(
+        recorder,
+        [
+            "payment.charge_order",
+            "repository.save",
+            "events.publish",
+            "sender.send",
+        ]
+    );
+    // The metrics call in between did not get in the way.
+    assert_eq!(recorder.names()[1], "metrics.observe_histogram");
+    assert!(
+        recorder
+            .calls()
+            .iter()
+            .filter(|call| call.port != "metrics")
+            .all(|call| call.order_id == Some(order.id))
+    );
+}
+
+#[test]
+fn cancel_order_refunds_before_recording_the_cancellation() {
+    let recorder = GlobalRecorder::new();
+    let repo = Recorded::new(InMemoryOrderRepository::new(), &recorder, "repository");
+    let payment = Recorded::new(MockPaymentGateway::new(), &recorder, "payment");
+    let sender = Recorded::new(ConsoleSender::new(), &recorder, "sender");
+    let events = Recorded::new(nowhere, &recorder, "events");
+    let mut service = OrderService::new(&repo, &payment, &sender).with_event_publisher(&events);
+    let order = service.place_order(cart()).unwrap();
+    recorder.clear();
+
+    service.cancel_order(order.id).unwrap();
+
+    expect_sequence!(
+        recorder,
+        [
+            "repository.find",
+            "payment.refund_for",
+            "repository.update",
+            "events.publish",
+        ]
+    );
+    assert!(recorder.calls_to("sender").is_empty());
+
+    // Already cancelled: looked up, and nothing else.
+    recorder.clear();
+    service.cancel_order(order.id).unwrap();
+    assert_eq!(recorder.names(), ["repository.find"]);
+}
+
+#[test]
+fn a_refused_refund_leaves_the_order_as_it_was() {
+    let recorder = GlobalRecorder::new();
+    let repo = Recorded::new(InMemoryOrderRepository::new(), &recorder, "repository");
+    let payment = Recorded::new(no_refunds, &recorder, "payment");
+    let sender = Recorded::new(ConsoleSender::new(), &recorder, "sender");
+    let events = Recorded::new(nowhere, &recorder, "events");
+    let mut service = OrderService::new(&repo, &payment, &sender).with_event_publisher(&events);
+    let order = service.place_order(cart()).unwrap();
+    recorder.clear();
+
+    assert_err_variant!(service.cancel_order(order.id), OrderError::PaymentFailed);
+
+    // The refund is the last call: no update, no event.
+    assert_eq!(recorder.names(), ["repository.find", "payment.refund_for"]);
+    assert_eq!(
+        repo.find(order.id).unwrap().unwrap().status,
+        OrderStatus::Paid
+    );
+}
+
+#[test]
+fn a_wrong_order_is_explained_with_both_lists() {
+    let recorder = GlobalRecorder::new();
+    let repo = Recorded::new(InMemoryOrderRepository::new(), &recorder, "repository");
+    let payment = Recorded::new(MockPaymentGateway::new(), &recorder, "payment");
+    let sender = Recorded::new(ConsoleSender::new(), &recorder, "sender");
+    OrderService::new(&repo, &payment, &sender)
+        .place_order(cart())
+        .unwrap();
+
+    let mismatch = recorder
+        .check_sequence(&["repository.save", "payment.charge_order"])
+        .unwrap_err();
+
+    assert_eq!(mismatch.missing(), "payment.charge_order");
+    let text = mismatch.to_string();
+    assert!(text.contains("(payment.charge_order was called, but too early)"));
+    assert!(text.contains("#0 payment.charge_order (order 1)"));
+}
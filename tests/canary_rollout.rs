@@ -0,0 +1,216 @@
+// cargo test --test canary_rollout
+// A replacement adapter rolled out through adapters::canary: a share of
+// the orders goes to the new side, always the same orders for a seed, and
+// in compare mode the reads of both sides are held against each other
+// without the caller ever seeing the new side's answer.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::canary::{Canary, Side};
+use hexa_lite::adapters::error_reporting::InMemoryErrorReporter;
+use hexa_lite::adapters::external::StripePaymentGateway;
+use hexa_lite::domain::{OrderReference, Percent, Timestamp};
+use hexa_lite::ports::{CancelToken, ChargeLog, Port};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::SteppingClock;
+
+const SEED: u64 = 20_240_601;
+
+fn percent(value: u32) -> Percent {
+    Percent::try_from(value).unwrap()
+}
+
+fn mock() -> MockPaymentGateway {
+    MockPaymentGateway::new().with_console(Console::silent())
+}
+
+fn pen(id: u32, cents: u32) -> Order {
+    Order::new(OrderId(id), vec![LineItem::new("Pen", Money(cents))]).unwrap()
+}
+
+#[test]
+fn the_new_side_gets_its_share_of_a_thousand_orders() {
+    for share in [5, 20, 50] {
+        let canary = Canary::new(mock(), mock(), percent(share)).with_seed(SEED);
+        let new = (1..=1000)
+            .filter(|&id| canary.side_for(OrderId(id)) == Side::New)
+            .count();
+        // Within 3 points of the share asked for.
+        let expected = share as usize * 10;
+        assert!(new.abs_diff(expected) <= 30, "{share}%: {new} of 1000");
+    }
+}
+
+#[test]
+fn every_order_stays_on_its_side_charge_and_refund_alike() {
+    let canary = Canary::new(mock(), mock(), percent(30)).with_seed(SEED);
+    let again = Canary::new(mock(), mock(), percent(30)).with_seed(SEED);
+
+    for id in 1..=200 {
+        canary.charge_for(OrderId(id), Money(500)).unwrap();
+        assert_eq!(canary.side_for(OrderId(id)), again.side_for(OrderId(id)));
+    }
+    let (old, new) = (
+        canary.old().charges().unwrap(),
+        canary.new_side().charges().unwrap(),
+    );
+    assert_eq!(old.len() + new.len(), 200);
+    assert!(
+        new.iter()
+            .all(|charge| canary.side_for(charge.order_id) == Side::New)
+    );
+
+    // Each side only refunds what it charged: none of these would go
+    // through on the other one.
+    for id in 1..=200 {
+        canary.refund_for(OrderId(id), Money(500)).unwrap();
+    }
+    assert!(canary.old().charges().unwrap().is_empty());
+    assert!(canary.new_side().charges().unwrap().is_empty());
+}
+
+#[test]
+fn the_sides_may_be_different_adapters_behind_one_service() {
+    let stripe = StripePaymentGateway::new().with_console(Console::silent());
+    let canary = Canary::new(mock(), stripe, percent(50)).with_seed(SEED);
+    let (repo, sender) = (InMemoryOrderRepository::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &canary, &sender);
+
+    for _ in 0..20 {
+        service
+            .place_order(vec![LineItem::new("Pen", Money(150))])
+            .unwrap();
+    }
+
+    let on_new = canary.new_side().charges().unwrap();
+    assert_eq!(canary.old().charges().unwrap().len() + on_new.len(), 20);
+    assert!(!on_new.is_empty());
+}
+
+#[test]
+fn compare_mode_reports_what_differs_and_answers_as_the_old_side() {
+    let (old, new) = (
+        InMemoryOrderRepository::new(),
+        InMemoryOrderRepository::new(),
+    );
+    old.save(&pen(1, 150)).unwrap();
+    new.save(&pen(1, 175)).unwrap();
+    old.save(&pen(2, 300)).unwrap();
+    new.save(&pen(2, 300)).unwrap();
+    // Not migrated yet.
+    old.save(&pen(3, 450)).unwrap();
+    let reporter = InMemoryErrorReporter::new();
+    let clock = SteppingClock::starting_at(Timestamp(1_700_000_000));
+    // Every order would be read from the new side, but for compare mode.
+    let canary = Canary::new(old, new, percent(100))
+        .with_compare_mode(true)
+        .with_error_reporter(&reporter, &clock);
+
+    assert_eq!(canary.find(OrderId(1)).unwrap(), Some(pen(1, 150)));
+    assert_eq!(canary.total_of(OrderId(2)).unwrap(), Some(Money(300)));
+    assert!(canary.exists(OrderId(3)).unwrap());
+    assert_eq!(canary.list().unwrap().len(), 3);
+
+    let reports = reporter.reports();
+    let reported: Vec<(&str, Option<OrderId>)> = reports
+        .iter()
+        .map(|report| (report.operation, report.order_id))
+        .collect();
+    assert_eq!(
+        reported,
+        [
+            ("find", Some(OrderId(1))),
+            ("exists", Some(OrderId(3))),
+            ("list", None)
+        ]
+    );
+    assert!(
+        reports
+            .iter()
+            .all(|report| report.use_case == "canary" && report.port == Some(Port::Repository))
+    );
+    assert_eq!(canary.mismatches(), 3);
+}
+
+#[test]
+fn without_compare_mode_a_read_goes_to_the_orders_side_only() {
+    let (old, new) = (
+        InMemoryOrderRepository::new(),
+        InMemoryOrderRepository::new(),
+    );
+    old.save(&pen(1, 150)).unwrap();
+    let reporter = InMemoryErrorReporter::new();
+    let clock = SteppingClock::starting_at(Timestamp(1_700_000_000));
+    let canary = Canary::new(old, new, percent(100)).with_error_reporter(&reporter, &clock);
+
+    // Order 1 is the new side's now, and was never copied over.
+    assert_eq!(canary.find(OrderId(1)).unwrap(), None);
+    assert_eq!(canary.list().unwrap(), vec![]);
+    assert!(reporter.reports().is_empty());
+    assert_eq!(canary.mismatches(), 0);
+}
+
+#[test]
+fn orders_on_the_new_side_are_listed_and_found_by_reference() {
+    let (old, new) = (
+        InMemoryOrderRepository::new(),
+        InMemoryOrderRepository::new(),
+    );
+    let split = Canary::new((), (), percent(50)).with_seed(SEED);
+    let placed: Vec<Order> = (1..=20)
+        .map(|id| {
+            let mut order = pen(id, 100 + id);
+            order.reference = Some(OrderReference(format!("2024-{id:06}")));
+            match split.side_for(order.id) {
+                Side::Old => old.save(&order).unwrap(),
+                Side::New => new.save(&order).unwrap(),
+            }
+            order
+        })
+        .collect();
+    let on_new: Vec<&Order> = placed
+        .iter()
+        .filter(|order| split.side_for(order.id) == Side::New)
+        .collect();
+    assert!(!on_new.is_empty() && on_new.len() < placed.len());
+    let canary = Canary::new(old, new, percent(50)).with_seed(SEED);
+
+    assert_eq!(canary.list().unwrap(), placed);
+    let mut visited = Vec::new();
+    canary
+        .for_each_cancellable(&CancelToken::new(), &mut |order| visited.push(order.id))
+        .unwrap();
+    assert_eq!(
+        visited,
+        placed.iter().map(|order| order.id).collect::<Vec<_>>()
+    );
+    for order in on_new {
+        let reference = order.reference.as_ref().unwrap();
+        assert_eq!(
+            canary.find_by_reference(reference).unwrap().as_ref(),
+            Some(order)
+        );
+        assert_eq!(canary.find(order.id).unwrap().as_ref(), Some(order));
+    }
+}
+
+#[test]
+fn a_copy_left_on_the_other_side_is_not_read() {
+    let (old, new) = (
+        InMemoryOrderRepository::new(),
+        InMemoryOrderRepository::new(),
+    );
+    let reference = OrderReference("2024-000001".to_string());
+    let with_reference = |mut order: Order| {
+        order.reference = Some(reference.clone());
+        order
+    };
+    // Moved to the new side, and changed there since.
+    old.save(&with_reference(pen(1, 150))).unwrap();
+    new.save(&with_reference(pen(1, 175))).unwrap();
+    let canary = Canary::new(old, new, percent(100));
+
+    assert_eq!(canary.list().unwrap(), vec![with_reference(pen(1, 175))]);
+    assert_eq!(
+        canary.find_by_reference(&reference).unwrap(),
+        Some(with_reference(pen(1, 175)))
+    );
+}
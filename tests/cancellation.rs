@@ -0,0 +1,274 @@
+// cargo test --test cancellation
+// Long-running use cases stop when their CancelToken is cancelled: between
+// two items, with Cancelled saying how many were handled. The report is
+// cancelled from another thread, a quarter of the way through 10 000
+// orders; the write use cases are cancelled before they write, or leave
+// what follows alone. The driving adapters, the shell and the command file,
+// pass a token on.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::cli::CliAdapter;
+use hexa_lite::adapters::repl::Repl;
+use hexa_lite::application::{Hooks, OrderBrowser};
+use hexa_lite::domain::OrderCriteria;
+use hexa_lite::facade::DefaultFacade;
+use hexa_lite::ports::{CancelToken, OrderQueries};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender as Channel};
+use std::thread;
+
+const ORDERS: u32 = 10_000;
+
+// 10 000 paid orders of $1.00.
+fn seeded() -> InMemoryOrderRepository {
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    for n in 1..=ORDERS {
+        let mut order = Order::new(OrderId(n), vec![LineItem::new("Pen", Money(100))]).unwrap();
+        order.mark_paid().unwrap();
+        repo.save(&order).unwrap();
+    }
+    repo
+}
+
+// Pauses a scan after its `after`-th order until the other side answers:
+// the other thread gets to cancel at a known point, and the scan goes on
+// only once it has.
+struct Pausing<'a> {
+    inner: &'a InMemoryOrderRepository,
+    after: usize,
+    reached: Channel<()>,
+    resume: Receiver<()>,
+}
+
+impl OrderReader for Pausing<'_> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.inner.find(id)
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.inner.list()
+    }
+
+    fn for_each_cancellable(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        let mut visited = 0;
+        self.inner.for_each_cancellable(cancel, &mut |order| {
+            visit(order);
+            visited += 1;
+            if visited == self.after {
+                self.reached.send(()).unwrap();
+                self.resume.recv().unwrap();
+            }
+        })
+    }
+}
+
+impl OrderWriter for Pausing<'_> {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.inner.save(order)
+    }
+
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        self.inner.update(order)
+    }
+}
+
+// Runs `scan` over a Pausing repository while another thread cancels its
+// token once `after` orders went by.
+fn cancelled_after<T>(
+    repo: &InMemoryOrderRepository,
+    after: usize,
+    scan: impl FnOnce(&Pausing, &CancelToken) -> T,
+) -> T {
+    let (reached, on_reached) = mpsc::channel();
+    let (resumed, resume) = mpsc::channel();
+    let pausing = Pausing {
+        inner: repo,
+        after,
+        reached,
+        resume,
+    };
+    let cancel = CancelToken::new();
+    thread::scope(|scope| {
+        let token = cancel.clone();
+        scope.spawn(move || {
+            on_reached.recv().unwrap();
+            token.cancel();
+            resumed.send(()).unwrap();
+        });
+        scan(&pausing, &cancel)
+    })
+}
+
+#[test]
+fn a_report_stops_where_it_was_cancelled() {
+    let repo = seeded();
+
+    let revenue = cancelled_after(&repo, 2_500, |pausing, cancel| {
+        OrderBrowser::new(pausing).revenue_cancellable(cancel)
+    });
+
+    assert_err_variant!(revenue, OrderError::Cancelled { processed: 2_500 });
+    // Uncancelled, the same report goes to the end.
+    let browser = OrderBrowser::new(&repo);
+    assert_eq!(
+        browser.revenue_cancellable(&CancelToken::new()).unwrap(),
+        Money(100 * ORDERS)
+    );
+}
+
+#[test]
+fn a_bulk_transition_cancelled_during_its_scan_writes_nothing() {
+    let repo = seeded();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+
+    let report = cancelled_after(&repo, 4_000, |pausing, cancel| {
+        OrderService::new(pausing, &payment, &sender).bulk_transition(
+            &OrderCriteria::any(),
+            OrderStatus::Shipped,
+            cancel,
+            &mut |_| {},
+        )
+    });
+
+    assert_err_variant!(report, OrderError::Cancelled { processed: 0 });
+    let untouched = repo.list().unwrap();
+    assert_eq!(untouched.len(), ORDERS as usize);
+    assert!(
+        untouched
+            .iter()
+            .all(|order| order.status == OrderStatus::Paid && order.version == 0)
+    );
+}
+
+#[test]
+fn a_bulk_transition_cancelled_halfway_leaves_the_rest_alone() {
+    let repo = seeded();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let cancel = CancelToken::new();
+
+    // The progress callback fires every 100 orders; cancel at the third.
+    let report = service.bulk_transition(
+        &OrderCriteria::any(),
+        OrderStatus::Shipped,
+        &cancel,
+        &mut |progress| {
+            if progress.processed == 300 {
+                cancel.cancel();
+            }
+        },
+    );
+
+    assert_err_variant!(report, OrderError::Cancelled { processed: 300 });
+    let status = |n| repo.find(OrderId(n)).unwrap().unwrap().status;
+    assert_eq!(status(300), OrderStatus::Shipped);
+    assert_eq!(status(301), OrderStatus::Paid);
+    assert_eq!(status(ORDERS), OrderStatus::Paid);
+}
+
+// Cancels `token` once `after` orders were saved.
+struct CancelAfterSaves {
+    token: CancelToken,
+    after: usize,
+    saved: AtomicUsize,
+}
+
+impl Hooks for CancelAfterSaves {
+    fn after_save(&self, _order: &Order) -> Result<(), OrderError> {
+        if self.saved.fetch_add(1, Ordering::SeqCst) + 1 == self.after {
+            self.token.cancel();
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn a_batch_keeps_the_orders_placed_before_it_was_cancelled() {
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let hooks = CancelAfterSaves {
+        token: CancelToken::new(),
+        after: 3,
+        saved: AtomicUsize::new(0),
+    };
+    let mut service = OrderService::new(&repo, &payment, &sender).with_hooks(&hooks);
+    let carts = vec![vec![LineItem::new("Pen", Money(150))]; 10];
+
+    assert_err_variant!(
+        service.place_batch(carts.clone(), &hooks.token),
+        OrderError::Cancelled { processed: 3 }
+    );
+    assert_eq!(repo.list().unwrap().len(), 3);
+
+    // Cancelled before it starts, a batch places nothing.
+    assert_err_variant!(
+        service.place_batch(carts, &hooks.token),
+        OrderError::Cancelled { processed: 0 }
+    );
+    assert_eq!(repo.list().unwrap().len(), 3);
+}
+
+// Cancels every command it is handed, as a Ctrl-C pressed at once would.
+fn interrupted(token: Option<&CancelToken>) {
+    if let Some(token) = token {
+        token.cancel();
+    }
+}
+
+#[test]
+fn the_shell_stops_the_command_it_was_running_and_goes_on() {
+    let repo = seeded();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let service = OrderService::new(&repo, &payment, &sender);
+    let mut repl = Repl::new(DefaultFacade::new(service, OrderBrowser::new(&repo)))
+        .with_interrupt(interrupted);
+
+    assert_eq!(
+        repl.eval("report revenue").unwrap(),
+        "error Cancelled { processed: 0 }"
+    );
+    let facade = repl.into_inner();
+
+    // A new command runs under a new token: only the session's stops them all.
+    let session = CancelToken::new();
+    let mut repl = Repl::new(facade).with_cancel_token(session.clone());
+    assert_eq!(repl.eval("report revenue").unwrap(), "revenue $10000.00");
+    session.cancel();
+    assert_eq!(
+        repl.eval("report revenue").unwrap(),
+        "error Cancelled { processed: 0 }"
+    );
+}
+
+#[test]
+fn a_cancelled_command_file_stops_before_its_next_line() {
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let hooks = CancelAfterSaves {
+        token: CancelToken::new(),
+        after: 2,
+        saved: AtomicUsize::new(0),
+    };
+    let service = OrderService::new(&repo, &payment, &sender).with_hooks(&hooks);
+    let mut cli = CliAdapter::new(service).with_cancel_token(hooks.token.clone());
+
+    let mut output = Vec::new();
+    cli.run(
+        &b"place Pen=150\nplace Ink=300\nplace Pad=400\n"[..],
+        &mut output,
+    )
+    .unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "ok #000001 $1.50\nok #000002 $3.00\nerror Cancelled { processed: 2 }\n"
+    );
+    assert_eq!(repo.list().unwrap().len(), 2);
+}
@@ -0,0 +1,109 @@
+// cargo test --test capabilities
+// The composition root owns a mixed set of adapters. After the use cases
+// ran, it flushes and commits whatever supports it, asking each adapter
+// through the Capability port instead of knowing their concrete types.
+use hexa_lite::adapters::buffered::BufferedSender;
+use hexa_lite::adapters::external::StripePaymentGateway;
+use hexa_lite::adapters::sql::{FakeExecutor, SqlOrderRepository};
+use hexa_lite::ports::{Capability, UnitOfWork};
+use hexa_lite::prelude::*;
+use std::cell::RefCell;
+
+#[derive(Default)]
+struct RecordingSender {
+    sent: RefCell<Vec<OrderId>>,
+}
+
+impl Sender for RecordingSender {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.sent.borrow_mut().push(confirmation.order_id);
+        Ok(())
+    }
+}
+
+// What a composition root would do on shutdown or at the end of a batch.
+// Returns how many adapters were flushed and committed.
+fn flush_and_commit(adapters: &mut [&mut dyn Capability]) -> (usize, usize) {
+    let (mut flushed, mut committed) = (0, 0);
+    for adapter in adapters.iter_mut() {
+        if let Some(flushable) = adapter.as_flushable() {
+            flushable.flush().unwrap();
+            flushed += 1;
+        }
+        if let Some(unit_of_work) = adapter.as_transactional() {
+            unit_of_work.commit().unwrap();
+            committed += 1;
+        }
+    }
+    (flushed, committed)
+}
+
+fn cart() -> Vec<LineItem> {
+    vec![LineItem {
+        name: "Keyboard".to_string(),
+        price: Money(12999),
+    }]
+}
+
+#[test]
+fn root_uses_exactly_the_capabilities_adapters_expose() {
+    let mut sql = SqlOrderRepository::new(FakeExecutor::new()).unwrap();
+    let mut in_memory = InMemoryOrderRepository::new();
+    let mut stripe = StripePaymentGateway::new();
+    let mut buffered = BufferedSender::new(RecordingSender::default());
+    let mut console = ConsoleSender::new();
+
+    sql.begin();
+    let mut staged = OrderService::new(&sql, &stripe, &buffered);
+    staged.place_order(cart()).unwrap();
+    staged.place_order(cart()).unwrap();
+    OrderService::new(&in_memory, &stripe, &console)
+        .place_order(cart())
+        .unwrap();
+    assert_eq!(buffered.pending(), 2);
+
+    let (flushed, committed) = flush_and_commit(&mut [
+        &mut sql,
+        &mut in_memory,
+        &mut stripe,
+        &mut buffered,
+        &mut console,
+    ]);
+
+    assert_eq!((flushed, committed), (1, 1));
+    assert_eq!(buffered.pending(), 0);
+    assert_eq!(
+        *buffered.inner().sent.borrow(),
+        vec![OrderId(1), OrderId(2)]
+    );
+}
+
+#[test]
+fn rolled_back_writes_are_gone_and_committed_ones_stay() {
+    let mut repo = SqlOrderRepository::new(FakeExecutor::new()).unwrap();
+    let order = Order::new(OrderId(1), cart()).unwrap();
+
+    repo.begin();
+    repo.save(&order).unwrap();
+    assert!(repo.find(OrderId(1)).unwrap().is_some()); // read your own writes
+    repo.rollback();
+    assert!(repo.find(OrderId(1)).unwrap().is_none());
+
+    repo.begin();
+    repo.save(&order).unwrap();
+    repo.as_transactional().unwrap().commit().unwrap();
+    assert!(repo.find(OrderId(1)).unwrap().is_some());
+}
+
+#[test]
+fn plain_adapters_expose_no_capability() {
+    assert!(InMemoryOrderRepository::new().as_transactional().is_none());
+    assert!(InMemoryOrderRepository::new().as_flushable().is_none());
+    assert!(ConsoleSender::new().as_flushable().is_none());
+    assert!(
+        SqlOrderRepository::new(FakeExecutor::new())
+            .unwrap()
+            .as_flushable()
+            .is_none()
+    );
+}
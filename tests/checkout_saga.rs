@@ -0,0 +1,454 @@
+// cargo test --test checkout_saga
+// Reserve, place, confirm: when a step fails, what was done before it is
+// undone, last first. A saga stopped half way is resumed from its log, and
+// no step that went through runs twice to any effect.
+use hexa_lite::adapters::in_memory::{
+    InMemoryIdempotencyStore, InMemoryInventory, InMemorySagaLog,
+};
+use hexa_lite::application::{CheckoutSaga, Reconciliation};
+use hexa_lite::domain::SagaId;
+use hexa_lite::ports::{ChargeLog, Inventory, ReservationId, SagaEntry, SagaLog};
+use hexa_lite::prelude::testing::{GlobalRecorder, Recorded, assert_err_variant, expect_sequence};
+use hexa_lite::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+const SAGA: SagaId = SagaId(7);
+const DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+fn cart() -> Vec<LineItem> {
+    vec![
+        LineItem::new("Mug", Money(900)),
+        LineItem::new("Mug", Money(900)),
+        LineItem::new("Lid", Money(100)),
+    ]
+}
+
+fn shelf() -> InMemoryInventory {
+    InMemoryInventory::new()
+        .with_stock("Mug", 5)
+        .with_stock("Lid", 5)
+}
+
+// The real inventory, except that confirm fails `failures` times.
+struct FlakyConfirm {
+    inner: InMemoryInventory,
+    failures: AtomicUsize,
+}
+
+impl Inventory for FlakyConfirm {
+    fn reserve(&self, reservation: ReservationId, items: &[LineItem]) -> Result<(), OrderError> {
+        self.inner.reserve(reservation, items)
+    }
+
+    fn confirm(&self, reservation: ReservationId) -> Result<(), OrderError> {
+        if self.failures.load(Ordering::SeqCst) > 0 {
+            self.failures.fetch_sub(1, Ordering::SeqCst);
+            return Err(OrderError::StorageFailed);
+        }
+        self.inner.confirm(reservation)
+    }
+
+    fn release(&self, reservation: ReservationId) -> Result<(), OrderError> {
+        self.inner.release(reservation)
+    }
+
+    fn restock(&self, item: &str, units: u32) -> Result<(), OrderError> {
+        self.inner.restock(item, units)
+    }
+
+    fn stock_level(&self, item: &str) -> Result<u32, OrderError> {
+        self.inner.stock_level(item)
+    }
+}
+
+// The real log, until it stops answering after `budget` entries: the saga
+// stops there, as it would if the process died.
+struct CrashingLog {
+    inner: InMemorySagaLog,
+    budget: AtomicUsize,
+}
+
+impl CrashingLog {
+    fn failing_after(budget: usize) -> Self {
+        CrashingLog {
+            inner: InMemorySagaLog::new(),
+            budget: AtomicUsize::new(budget),
+        }
+    }
+
+    fn recover(&self) {
+        self.budget.store(usize::MAX, Ordering::SeqCst);
+    }
+}
+
+impl SagaLog for CrashingLog {
+    fn append(&self, saga: SagaId, entry: SagaEntry) -> Result<(), OrderError> {
+        if self.budget.load(Ordering::SeqCst) == 0 {
+            return Err(OrderError::StorageFailed);
+        }
+        self.budget.fetch_sub(1, Ordering::SeqCst);
+        self.inner.append(saga, entry)
+    }
+
+    fn entries(&self, saga: SagaId) -> Result<Vec<SagaEntry>, OrderError> {
+        self.inner.entries(saga)
+    }
+}
+
+fn steps(log: &dyn SagaLog) -> Vec<String> {
+    log.entries(SAGA)
+        .unwrap()
+        .iter()
+        .map(|entry| match entry {
+            SagaEntry::Started { .. } => "Started".to_string(),
+            SagaEntry::Reserved { .. } => "Reserved".to_string(),
+            SagaEntry::OrderPlaced { .. } => "OrderPlaced".to_string(),
+            SagaEntry::Confirmed => "Confirmed".to_string(),
+            SagaEntry::Failed { step, .. } => format!("Failed({step:?})"),
+            SagaEntry::Compensated { step } => format!("Compensated({step:?})"),
+        })
+        .collect()
+}
+
+#[test]
+fn a_completed_saga_holds_the_stock_and_the_order() {
+    let (inventory, log) = (shelf(), InMemorySagaLog::new());
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let orders = OrderService::new(&repo, &payment, &sender);
+    let mut saga = CheckoutSaga::new(orders, &inventory, &log);
+
+    let order = saga.execute(SAGA, cart()).unwrap();
+
+    assert_eq!(order.status, OrderStatus::Paid);
+    assert_eq!(order.total, Money(1900));
+    assert_eq!(
+        steps(&log),
+        ["Started", "Reserved", "OrderPlaced", "Confirmed"]
+    );
+    assert_eq!(
+        (inventory.available("Mug"), inventory.available("Lid")),
+        (3, 4)
+    );
+    // Confirmed stock is sold: releasing it afterwards changes nothing.
+    inventory.release(ReservationId(SAGA.0)).unwrap();
+    assert_eq!(inventory.available("Mug"), 3);
+}
+
+#[test]
+fn out_of_stock_fails_before_anything_is_done() {
+    let recorder = GlobalRecorder::new();
+    let shelf = InMemoryInventory::new()
+        .with_stock("Mug", 1)
+        .with_stock("Lid", 5);
+    let inventory = Recorded::new(shelf, &recorder, "inventory");
+    let log = InMemorySagaLog::new();
+    let repo = Recorded::new(InMemoryOrderRepository::new(), &recorder, "repository");
+    let payment = Recorded::new(MockPaymentGateway::new(), &recorder, "payment");
+    let sender = ConsoleSender::new();
+    let orders = OrderService::new(&repo, &payment, &sender);
+    let mut saga = CheckoutSaga::new(orders, &inventory, &log);
+
+    match saga.execute(SAGA, cart()) {
+        Err(OrderError::OutOfStock { item }) => assert_eq!(item, "Mug"),
+        other => panic!("expected OutOfStock, got {other:?}"),
+    }
+
+    assert_eq!(steps(&log), ["Started", "Failed(ReserveInventory)"]);
+    assert_eq!(
+        (
+            inventory.inner().available("Mug"),
+            inventory.inner().available("Lid")
+        ),
+        (1, 5)
+    );
+    // Nothing to undo: no release, and neither payment nor repository heard
+    // of the cart.
+    assert_eq!(recorder.names(), ["inventory.reserve"]);
+}
+
+#[test]
+fn a_declined_payment_releases_the_stock() {
+    struct Declining;
+
+    impl PaymentGateway for Declining {
+        fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+            Err(OrderError::PaymentFailed)
+        }
+    }
+
+    let (inventory, log) = (shelf(), InMemorySagaLog::new());
+    let repo = InMemoryOrderRepository::new();
+    let sender = ConsoleSender::new();
+    let orders = OrderService::new(&repo, &Declining, &sender);
+    let mut saga = CheckoutSaga::new(orders, &inventory, &log);
+
+    assert_err_variant!(saga.execute(SAGA, cart()), OrderError::PaymentFailed);
+
+    assert_eq!(
+        steps(&log),
+        [
+            "Started",
+            "Reserved",
+            "Failed(PlaceOrder)",
+            "Compensated(ReserveInventory)"
+        ]
+    );
+    assert_eq!(
+        (inventory.available("Mug"), inventory.available("Lid")),
+        (5, 5)
+    );
+}
+
+#[test]
+fn a_failed_confirmation_cancels_and_refunds_the_order() {
+    let inventory = FlakyConfirm {
+        inner: shelf(),
+        failures: AtomicUsize::new(1),
+    };
+    let log = InMemorySagaLog::new();
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let orders = OrderService::new(&repo, &payment, &sender);
+    let mut saga = CheckoutSaga::new(orders, &inventory, &log);
+
+    assert_err_variant!(saga.execute(SAGA, cart()), OrderError::StorageFailed);
+
+    assert_eq!(
+        steps(&log),
+        [
+            "Started",
+            "Reserved",
+            "OrderPlaced",
+            "Failed(ConfirmReservation)",
+            "Compensated(PlaceOrder)",
+            "Compensated(ReserveInventory)"
+        ]
+    );
+    assert_eq!(inventory.inner.available("Mug"), 5);
+    let order = repo.find(OrderId(1)).unwrap().unwrap();
+    assert_eq!(order.status, OrderStatus::Cancelled);
+    assert!(payment.charges().unwrap().is_empty(), "refunded");
+    assert!(
+        Reconciliation::new(&repo, &payment)
+            .reconcile()
+            .unwrap()
+            .is_consistent()
+    );
+}
+
+// Every port of the saga reporting into one recorder.
+struct Wired<I> {
+    recorder: GlobalRecorder,
+    inventory: Recorded<I>,
+    repo: Recorded<InMemoryOrderRepository>,
+    payment: Recorded<MockPaymentGateway>,
+    sender: Recorded<ConsoleSender>,
+}
+
+fn wired<I: Inventory>(inventory: I) -> Wired<I> {
+    let recorder = GlobalRecorder::new();
+    Wired {
+        inventory: Recorded::new(inventory, &recorder, "inventory"),
+        repo: Recorded::new(InMemoryOrderRepository::new(), &recorder, "repository"),
+        payment: Recorded::new(MockPaymentGateway::new(), &recorder, "payment"),
+        sender: Recorded::new(ConsoleSender::new(), &recorder, "sender"),
+        recorder,
+    }
+}
+
+#[test]
+fn the_ports_are_called_step_by_step() {
+    let w = wired(shelf());
+    let log = InMemorySagaLog::new();
+    let orders = OrderService::new(&w.repo, &w.payment, &w.sender);
+
+    CheckoutSaga::new(orders, &w.inventory, &log)
+        .execute(SAGA, cart())
+        .unwrap();
+
+    expect_sequence!(
+        w.recorder,
+        [
+            "inventory.reserve",
+            "payment.charge_order",
+            "repository.save",
+            "sender.send",
+            "inventory.confirm",
+        ]
+    );
+    assert_eq!(
+        w.recorder.calls_to("inventory").len(),
+        2,
+        "reserved and confirmed, never released"
+    );
+}
+
+#[test]
+fn compensations_run_in_exactly_the_reverse_order() {
+    let w = wired(FlakyConfirm {
+        inner: shelf(),
+        failures: AtomicUsize::new(1),
+    });
+    let log = InMemorySagaLog::new();
+    let orders = OrderService::new(&w.repo, &w.payment, &w.sender);
+
+    assert_err_variant!(
+        CheckoutSaga::new(orders, &w.inventory, &log).execute(SAGA, cart()),
+        OrderError::StorageFailed
+    );
+
+    expect_sequence!(
+        w.recorder,
+        [
+            "inventory.reserve",
+            "payment.charge_order",
+            "repository.save",
+            "inventory.confirm",
+            "payment.refund_for",
+            "repository.update",
+            "inventory.release",
+        ]
+    );
+    // Each step that went through, and the call undoing it: the undoing
+    // calls come in the reverse order of the steps, none missing.
+    const UNDO: [(&str, &str); 2] = [
+        ("inventory.reserve", "inventory.release"),
+        ("payment.charge_order", "payment.refund_for"),
+    ];
+    let names = w.recorder.names();
+    let at = |name: &str| names.iter().position(|n| n == name).unwrap();
+    let mut done = UNDO.map(|(step, _)| step);
+    done.sort_by_key(|step| at(step));
+    let mut undone = UNDO;
+    undone.sort_by_key(|(_, undo)| at(undo));
+    let undone = undone.map(|(step, _)| step);
+    done.reverse();
+    assert_eq!(undone, done);
+    assert_eq!(
+        names.iter().filter(|n| *n == "inventory.release").count(),
+        1
+    );
+    assert_eq!(
+        names.iter().filter(|n| *n == "payment.refund_for").count(),
+        1
+    );
+}
+
+#[test]
+fn a_compensated_saga_is_not_run_again() {
+    let inventory = FlakyConfirm {
+        inner: shelf(),
+        failures: AtomicUsize::new(1),
+    };
+    let log = InMemorySagaLog::new();
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let orders = OrderService::new(&repo, &payment, &sender);
+    let mut saga = CheckoutSaga::new(orders, &inventory, &log);
+    assert!(saga.execute(SAGA, cart()).is_err());
+
+    // Confirm would work now, but the saga is over: same error, no step.
+    assert_err_variant!(saga.execute(SAGA, cart()), OrderError::StorageFailed);
+    assert_err_variant!(saga.resume(SAGA), OrderError::StorageFailed);
+
+    assert_eq!(steps(&log).len(), 6);
+    assert_eq!(inventory.inner.available("Mug"), 5);
+    assert_eq!(repo.list().unwrap().len(), 1);
+}
+
+// The log dies after each possible entry in turn; once it is back, the
+// saga resumes and ends exactly as if nothing had happened.
+#[test]
+fn resumes_after_a_crash_between_any_two_steps() {
+    for budget in 1..4 {
+        let (inventory, log) = (shelf(), CrashingLog::failing_after(budget));
+        let keys = InMemoryIdempotencyStore::new();
+        let repo = InMemoryOrderRepository::new();
+        let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+        let orders = OrderService::new(&repo, &payment, &sender).with_idempotency_store(&keys, DAY);
+        let mut saga = CheckoutSaga::new(orders, &inventory, &log);
+
+        assert_err_variant!(saga.execute(SAGA, cart()), OrderError::StorageFailed);
+        log.recover();
+        let order = saga.resume(SAGA).unwrap();
+
+        assert_eq!(order.status, OrderStatus::Paid, "crash after {budget}");
+        assert_eq!(
+            steps(&log),
+            ["Started", "Reserved", "OrderPlaced", "Confirmed"],
+            "crash after {budget}"
+        );
+        assert_eq!(inventory.available("Mug"), 3, "crash after {budget}");
+        assert_eq!(payment.charges().unwrap().len(), 1, "crash after {budget}");
+        assert_eq!(repo.list().unwrap().len(), 1, "crash after {budget}");
+    }
+}
+
+// The crash comes after the order was placed, before the log heard of it:
+// placing it again finds the same order through its idempotency key.
+#[test]
+fn a_step_done_but_not_logged_is_not_done_twice() {
+    let (inventory, log) = (shelf(), CrashingLog::failing_after(2));
+    let keys = InMemoryIdempotencyStore::new();
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let orders = OrderService::new(&repo, &payment, &sender).with_idempotency_store(&keys, DAY);
+    let mut saga = CheckoutSaga::new(orders, &inventory, &log);
+
+    assert!(saga.execute(SAGA, cart()).is_err());
+    assert_eq!(steps(&log), ["Started", "Reserved"]);
+    assert_eq!(payment.charges().unwrap().len(), 1, "placed, not logged");
+
+    log.recover();
+    let order = saga.execute(SAGA, cart()).unwrap();
+
+    assert_eq!(order.id, OrderId(1));
+    assert_eq!(payment.charges().unwrap().len(), 1);
+    // A completed saga answers with its order, and does nothing more.
+    assert_eq!(saga.resume(SAGA).unwrap(), order);
+    assert_eq!(steps(&log).len(), 4);
+}
+
+#[test]
+fn an_interrupted_compensation_is_finished_on_resume() {
+    let inventory = FlakyConfirm {
+        inner: shelf(),
+        failures: AtomicUsize::new(1),
+    };
+    // Started, Reserved, OrderPlaced, Failed: then no more.
+    let log = CrashingLog::failing_after(4);
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let orders = OrderService::new(&repo, &payment, &sender);
+    let mut saga = CheckoutSaga::new(orders, &inventory, &log);
+
+    assert!(saga.execute(SAGA, cart()).is_err());
+    assert_eq!(inventory.inner.available("Mug"), 3, "not released yet");
+
+    log.recover();
+    assert_err_variant!(saga.resume(SAGA), OrderError::StorageFailed);
+
+    assert_eq!(
+        steps(&log)[4..],
+        ["Compensated(PlaceOrder)", "Compensated(ReserveInventory)"]
+    );
+    assert_eq!(inventory.inner.available("Mug"), 5);
+    assert!(payment.charges().unwrap().is_empty(), "refunded once");
+}
+
+#[test]
+fn an_unknown_saga_cannot_be_resumed() {
+    let (inventory, log) = (shelf(), InMemorySagaLog::new());
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let orders = OrderService::new(&repo, &payment, &sender);
+    let mut saga = CheckoutSaga::new(orders, &inventory, &log);
+
+    assert_err_variant!(
+        saga.resume(SagaId(99)),
+        OrderError::UnknownSaga { id: SagaId(99) }
+    );
+    assert!(saga.orders().get_order(OrderId(1)).unwrap().is_none());
+}
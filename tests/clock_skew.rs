@@ -0,0 +1,107 @@
+// cargo test --test clock_skew
+// A client may say when it placed an order. Too far ahead of the service's
+// clock, the order is refused; behind it by more than the tolerance, the
+// time is clamped. Exactly on either bound, it is taken as given.
+use hexa_lite::adapters::http::{HttpAdapter, HttpRequest};
+use hexa_lite::application::{ClockTolerance, OrderBrowser};
+use hexa_lite::facade::DefaultFacade;
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+
+const NOW: u64 = 1_700_000_000;
+const TOLERANCE: ClockTolerance = ClockTolerance {
+    max_past_secs: 3600,
+    max_future_secs: 300,
+};
+
+fn command(placed_at: u64) -> PlaceOrder {
+    PlaceOrder {
+        items: vec![LineItem {
+            name: "Keyboard".to_string(),
+            price: Money(12999),
+        }],
+        placed_at: Some(Timestamp(placed_at)),
+    }
+}
+
+// placed_at of the order the service stored, or its error.
+fn place_at(placed_at: u64) -> Result<Option<Timestamp>, OrderError> {
+    let clock = SteppingClock::starting_at(Timestamp(NOW));
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let repo = InMemoryOrderRepository::new();
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_clock(&clock)
+        .with_clock_tolerance(TOLERANCE);
+    PlaceOrderUseCase::place_order(&mut service, command(placed_at)).map(|order| order.placed_at)
+}
+
+#[test]
+fn future_bound_is_inclusive() {
+    assert_eq!(place_at(NOW + 300).unwrap(), Some(Timestamp(NOW + 300)));
+    assert_err_variant!(
+        place_at(NOW + 301),
+        OrderError::ClockSkew { delta_secs: 301 }
+    );
+}
+
+#[test]
+fn past_bound_is_inclusive_and_older_times_are_clamped() {
+    assert_eq!(place_at(NOW - 3600).unwrap(), Some(Timestamp(NOW - 3600)));
+    assert_eq!(place_at(NOW - 3601).unwrap(), Some(Timestamp(NOW - 3600)));
+    assert_eq!(place_at(0).unwrap(), Some(Timestamp(NOW - 3600)));
+}
+
+#[test]
+fn without_a_client_time_the_clock_decides() {
+    let clock = SteppingClock::starting_at(Timestamp(NOW));
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let repo = InMemoryOrderRepository::new();
+    let mut service = OrderService::new(&repo, &payment, &sender).with_clock(&clock);
+
+    let order = PlaceOrderUseCase::place_order(
+        &mut service,
+        PlaceOrder {
+            placed_at: None,
+            ..command(0)
+        },
+    )
+    .unwrap();
+
+    assert_eq!(order.placed_at, Some(Timestamp(NOW)));
+}
+
+#[test]
+fn http_answers_422_with_the_skew() {
+    let clock = SteppingClock::starting_at(Timestamp(NOW));
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let repo = InMemoryOrderRepository::new();
+    let service = OrderService::new(&repo, &payment, &sender)
+        .with_clock(&clock)
+        .with_clock_tolerance(TOLERANCE);
+    let http = HttpAdapter::new(DefaultFacade::new(service, OrderBrowser::new(&repo)));
+    let post = |placed_at: u64| HttpRequest {
+        method: "POST".to_string(),
+        path: "/orders".to_string(),
+        headers: Vec::new(),
+        body: format!(
+            r#"{{"items":[{{"name":"Keyboard","price_cents":12999}}],"placed_at":{placed_at}}}"#
+        )
+        .into_bytes(),
+    };
+
+    let refused = http.handle(&post(NOW + 900));
+    assert_eq!(refused.status, 422);
+    assert_eq!(
+        refused.body,
+        r#"{"error":"ClockSkew","code":"REQ-019","delta_secs":900}"#
+    );
+
+    let accepted = http.handle(&post(NOW + 300));
+    assert_eq!(accepted.status, 201);
+    let (service, _) = http.into_inner().into_inner();
+    let stored = service.get_order(OrderId(2)).unwrap().unwrap();
+    assert_eq!(stored.placed_at, Some(Timestamp(NOW + 300)));
+}
@@ -0,0 +1,78 @@
+// cargo test --test closure_adapters
+// Closures plugged straight into the one-method ports: a Sender, a
+// PaymentGateway, a Clock, an EventPublisher, each keeping what it needs
+// in the variables it captures. A repository cannot be one; the doc test
+// on the closure Sender shows it does not compile.
+use hexa_lite::domain::{OrderConfirmation, OrderEvent};
+use hexa_lite::prelude::*;
+use std::cell::{Cell, RefCell};
+use std::sync::Mutex;
+
+fn pen() -> Vec<LineItem> {
+    vec![LineItem::new("Pen", Money(150))]
+}
+
+#[test]
+fn a_closure_sender_counts_what_it_is_given() {
+    let calls = Cell::new(0);
+    let told = RefCell::new(Vec::new());
+    let sender = |confirmation: &OrderConfirmation| {
+        calls.set(calls.get() + 1);
+        told.borrow_mut().push(confirmation.order_id);
+        Ok(())
+    };
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    service.place_order(pen()).unwrap();
+    service.place_order(pen()).unwrap();
+
+    assert_eq!(calls.get(), 2);
+    assert_eq!(told.borrow()[..], [OrderId(1), OrderId(2)]);
+}
+
+#[test]
+fn a_closure_gateway_declines_once_its_allowance_is_spent() {
+    let allowance = Cell::new(Money(300));
+    let payment = |amount: Money| {
+        let left = allowance.get().0.checked_sub(amount.0);
+        let left = left.ok_or(OrderError::PaymentFailed)?;
+        allowance.set(Money(left));
+        Ok(())
+    };
+    let repo = InMemoryOrderRepository::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    assert!(service.place_order(pen()).is_ok());
+    assert!(service.place_order(pen()).is_ok());
+    assert!(matches!(
+        service.place_order(pen()),
+        Err(OrderError::PaymentFailed)
+    ));
+    assert_eq!(allowance.get(), Money(0));
+    // Refunds are refused, as for any gateway that does not say otherwise.
+    assert!(payment.refund_for(OrderId(1), Money(150)).is_err());
+}
+
+#[test]
+fn a_closure_clock_and_a_closure_publisher_serve_the_service() {
+    let clock = || Timestamp(1_700_000_000);
+    let published = Mutex::new(Vec::new());
+    let events = |event: &OrderEvent| {
+        published.lock().unwrap().push(event.order_id());
+        Ok(())
+    };
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_clock(&clock)
+        .with_event_publisher(&events);
+
+    let order = service.place_order(pen()).unwrap();
+
+    assert_eq!(order.placed_at, Some(Timestamp(1_700_000_000)));
+    assert_eq!(published.lock().unwrap()[..], [OrderId(1), OrderId(1)]);
+}
@@ -0,0 +1,141 @@
+// cargo test --test concurrency_limits
+// More threads than the payment provider allows concurrent calls, all
+// sharing one gateway. Whether the extra callers wait or are turned down,
+// the provider behind ConcurrencyLimited never sees more than its permits
+// at once: a spy counts the calls inside it and keeps the highest count.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::limited::{ConcurrencyLimited, WhenFull};
+use hexa_lite::ports::ServiceState;
+use hexa_lite::prelude::*;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Barrier};
+use std::thread;
+use std::time::Duration;
+
+const PERMITS: usize = 4;
+const THREADS: usize = 16;
+
+// The first `held` calls stay inside until the test lets them go: they
+// meet the test at `entered`, then again at `leave`. The others take
+// `pause`.
+struct Spy {
+    inside: AtomicUsize,
+    high_water: AtomicUsize,
+    calls: AtomicUsize,
+    held: usize,
+    entered: Barrier,
+    leave: Barrier,
+    pause: Duration,
+}
+
+impl Spy {
+    fn pausing(pause: Duration) -> Self {
+        Self::holding(0, pause)
+    }
+
+    fn holding(held: usize, pause: Duration) -> Self {
+        Spy {
+            inside: AtomicUsize::new(0),
+            high_water: AtomicUsize::new(0),
+            calls: AtomicUsize::new(0),
+            held,
+            entered: Barrier::new(held + 1),
+            leave: Barrier::new(held + 1),
+            pause,
+        }
+    }
+
+    fn high_water(&self) -> usize {
+        self.high_water.load(Ordering::SeqCst)
+    }
+}
+
+impl PaymentGateway for Spy {
+    fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+        let inside = self.inside.fetch_add(1, Ordering::SeqCst) + 1;
+        self.high_water.fetch_max(inside, Ordering::SeqCst);
+        if self.calls.fetch_add(1, Ordering::SeqCst) < self.held {
+            self.entered.wait();
+            self.leave.wait();
+        } else {
+            thread::sleep(self.pause);
+        }
+        self.inside.fetch_sub(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+#[test]
+fn blocked_callers_wait_their_turn_and_every_order_is_paid() {
+    let gateway = ConcurrencyLimited::new(Spy::pausing(Duration::from_millis(2)), PERMITS);
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let adapters = Arc::new((repo, gateway, sender));
+    let ready = Arc::new(Barrier::new(THREADS));
+
+    // Thread t places three orders, under ids of its own.
+    let workers: Vec<_> = (0..THREADS)
+        .map(|t| {
+            let (adapters, ready) = (Arc::clone(&adapters), Arc::clone(&ready));
+            thread::spawn(move || {
+                let (repo, gateway, sender) = &*adapters;
+                let state = ServiceState {
+                    next_id: (t * 3 + 1) as u32,
+                    open_drafts: Vec::new(),
+                };
+                let mut service = OrderService::restore(repo, gateway, sender, &state).unwrap();
+                ready.wait();
+                for _ in 0..3 {
+                    service
+                        .place_order(vec![LineItem::new("Pen", Money(150))])
+                        .unwrap();
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+
+    let (repo, gateway, _) = &*adapters;
+    assert!(gateway.inner().high_water() <= PERMITS);
+    assert_eq!(gateway.inner().calls.load(Ordering::SeqCst), THREADS * 3);
+    assert_eq!(gateway.in_use(), 0);
+    let orders = repo.list().unwrap();
+    assert_eq!(orders.len(), THREADS * 3);
+    assert!(orders.iter().all(|order| order.status == OrderStatus::Paid));
+}
+
+#[test]
+fn failing_callers_are_turned_down_while_the_permits_are_out() {
+    let gateway = Arc::new(
+        ConcurrencyLimited::new(Spy::holding(PERMITS, Duration::ZERO), PERMITS)
+            .with_when_full(WhenFull::Fail),
+    );
+    let charge = |gateway: &Arc<ConcurrencyLimited<Spy>>| {
+        let gateway = Arc::clone(gateway);
+        thread::spawn(move || gateway.charge(Money(150)))
+    };
+
+    // Every permit taken, by calls that stay inside the provider.
+    let holders: Vec<_> = (0..PERMITS).map(|_| charge(&gateway)).collect();
+    gateway.inner().entered.wait();
+    let refused: Vec<_> = (PERMITS..THREADS).map(|_| charge(&gateway)).collect();
+    for caller in refused {
+        assert!(matches!(
+            caller.join().unwrap(),
+            Err(OrderError::TooManyConcurrentCalls { limit: PERMITS })
+        ));
+    }
+    gateway.inner().leave.wait();
+    for holder in holders {
+        assert!(holder.join().unwrap().is_ok());
+    }
+
+    assert_eq!(gateway.inner().high_water(), PERMITS);
+    // The refused calls never reached the provider.
+    assert_eq!(gateway.inner().calls.load(Ordering::SeqCst), PERMITS);
+    // With the permits back, the next call goes through.
+    assert!(gateway.charge(Money(150)).is_ok());
+    assert_eq!(gateway.inner().high_water(), PERMITS);
+}
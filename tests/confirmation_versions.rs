@@ -0,0 +1,172 @@
+// cargo test --test confirmation_versions
+// Confirmations over the wire, version by version. The payloads under
+// tests/fixtures/ are committed as they were first written, and must keep
+// parsing: a test reading them differently is a broken consumer somewhere.
+use hexa_lite::adapters::outbox::{InMemoryDeadLetters, OutboxEntry, OutboxSender};
+use hexa_lite::adapters::webhook::WebhookSender;
+use hexa_lite::adapters::{
+    Console, OrderConfirmationV1, WireError, confirmation_envelope, parse_any_confirmation,
+};
+use hexa_lite::domain::{Currency, OrderReference};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::sync::Mutex;
+
+const V1: &str = include_str!("fixtures/confirmation_v1.json");
+const V2: &str = include_str!("fixtures/confirmation_v2.json");
+const V3: &str = include_str!("fixtures/confirmation_v3.json");
+// A v2 payload for an order with a reference, added to v2 after it shipped.
+const V2_REFERENCE: &str = include_str!("fixtures/confirmation_v2_reference.json");
+
+fn books() -> Vec<LineItem> {
+    vec![
+        LineItem::new("Rust Book", Money(4999)),
+        LineItem::new("Bookmark", Money(499)),
+    ]
+}
+
+// What the v2 fixture says.
+fn v2_confirmation() -> OrderConfirmation {
+    OrderConfirmation {
+        order_id: OrderId(42),
+        items: books(),
+        total: Money(5498),
+        tax: Money(916),
+        currency: Currency::Gbp,
+        customer_email: Some("ada@example.test".to_string()),
+        reference: None,
+    }
+}
+
+#[test]
+fn a_v1_payload_is_upgraded_with_the_defaults() {
+    let confirmation = parse_any_confirmation(V1.as_bytes()).unwrap();
+
+    assert_eq!(
+        confirmation,
+        OrderConfirmation {
+            order_id: OrderId(41),
+            items: books(),
+            total: Money(5498),
+            tax: Money(0),
+            currency: Currency::Usd,
+            customer_email: None,
+            reference: None,
+        }
+    );
+}
+
+#[test]
+fn a_v2_payload_reads_whole_and_is_what_the_envelope_writes() {
+    assert_eq!(parse_any_confirmation(V2.as_bytes()), Ok(v2_confirmation()));
+    assert_eq!(confirmation_envelope(&v2_confirmation()), V2.trim_end());
+}
+
+#[test]
+fn a_v1_consumer_reads_what_a_v2_producer_posts() {
+    let webhook = WebhookSender::new("https://example.test/hook")
+        .unwrap()
+        .with_console(Console::silent());
+    webhook.send(&v2_confirmation()).unwrap();
+    let body = &webhook.delivered()[0].body;
+
+    for payload in [body.as_str(), V2] {
+        assert_eq!(
+            OrderConfirmationV1::from_json(payload),
+            Ok(OrderConfirmationV1 {
+                order_id: OrderId(42),
+                items: books(),
+                total: Money(5498),
+            })
+        );
+    }
+}
+
+#[test]
+fn a_reference_is_one_more_v2_field_that_older_readers_skip() {
+    let referenced = OrderConfirmation {
+        reference: Some(OrderReference("2024-000042".to_string())),
+        ..v2_confirmation()
+    };
+
+    assert_eq!(
+        parse_any_confirmation(V2_REFERENCE.as_bytes()),
+        Ok(referenced.clone())
+    );
+    assert_eq!(confirmation_envelope(&referenced), V2_REFERENCE.trim_end());
+    assert_eq!(
+        OrderConfirmationV1::from_json(V2_REFERENCE),
+        OrderConfirmationV1::from_json(V2)
+    );
+}
+
+#[test]
+fn a_payload_from_a_future_version_is_refused() {
+    assert_eq!(
+        parse_any_confirmation(V3.as_bytes()),
+        Err(WireError::UnknownVersion {
+            version: 3,
+            newest: 2
+        })
+    );
+    // A v1 consumer still gets what it knows out of it.
+    assert_eq!(
+        OrderConfirmationV1::from_json(V3).unwrap().order_id,
+        OrderId(43)
+    );
+}
+
+#[derive(Default)]
+struct Inbox(Mutex<Vec<OrderConfirmation>>);
+
+impl Sender for Inbox {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.0.lock().unwrap().push(confirmation.clone());
+        Ok(())
+    }
+}
+
+#[test]
+fn the_outbox_keeps_envelopes_and_upgrades_old_ones_on_the_way_out() {
+    let now = Timestamp(1_700_000_000);
+    let dead_letters = InMemoryDeadLetters::new();
+    let left_over = |payload: &str| OutboxEntry {
+        envelope: payload.trim_end().to_string(),
+        ..OutboxEntry::new(&v2_confirmation(), now)
+    };
+    let outbox = OutboxSender::new(
+        Inbox::default(),
+        SteppingClock::starting_at(now),
+        &dead_letters,
+    )
+    .with_entries(vec![left_over(V1), left_over(V2)])
+    .with_console(Console::silent());
+    outbox.send(&v2_confirmation()).unwrap();
+    assert_eq!(outbox.entries()[2].envelope, V2.trim_end());
+
+    assert_eq!(outbox.run_dispatcher(now).unwrap().sent, 3);
+    let sent = outbox.inner().0.lock().unwrap();
+    assert_eq!(sent[0], parse_any_confirmation(V1.as_bytes()).unwrap());
+    assert_eq!(sent[1..], [v2_confirmation(), v2_confirmation()]);
+}
+
+#[test]
+fn an_entry_from_a_newer_release_stays_in_the_outbox() {
+    let now = Timestamp(1_700_000_000);
+    let dead_letters = InMemoryDeadLetters::new();
+    let outbox = OutboxSender::new(
+        Inbox::default(),
+        SteppingClock::starting_at(now),
+        &dead_letters,
+    )
+    .with_entries(vec![OutboxEntry {
+        envelope: V3.trim_end().to_string(),
+        ..OutboxEntry::new(&v2_confirmation(), now)
+    }])
+    .with_console(Console::silent());
+
+    assert_err_variant!(outbox.run_dispatcher(now), OrderError::Custom { .. });
+    assert_eq!(outbox.entries().len(), 1);
+    assert!(outbox.inner().0.lock().unwrap().is_empty());
+    assert!(dead_letters.letters().is_empty());
+}
@@ -0,0 +1,160 @@
+// cargo test --test data_retention
+// Orders forgotten for good once the retention policy lets them go, with
+// everything kept about them: their idempotency keys, a charge still
+// deferred for them. Those with something unsettled stay, and the report
+// says why.
+use hexa_lite::adapters::in_memory::InMemoryIdempotencyStore;
+use hexa_lite::adapters::offline::InMemoryPendingCharges;
+use hexa_lite::adapters::retention::AgeAndStatusRetention;
+use hexa_lite::application::PurgeReport;
+use hexa_lite::ports::{IdempotencyStore, PendingCharge, PendingCharges};
+use hexa_lite::prelude::testing::SteppingClock;
+use hexa_lite::prelude::*;
+use std::time::Duration;
+
+const DAY: u64 = 24 * 60 * 60;
+
+fn cart() -> Vec<LineItem> {
+    vec![LineItem::new("Lamp", Money(3_500))]
+}
+
+fn reasons(report: &PurgeReport) -> Vec<(OrderId, String)> {
+    report
+        .blocked
+        .iter()
+        .map(|(id, e)| match e {
+            OrderError::RetentionBlocked { reason } => (*id, reason.clone()),
+            other => panic!("order {id:?} blocked with {other:?}"),
+        })
+        .collect()
+}
+
+// Moves a stored order on, as another use case would have.
+fn move_to(repo: &InMemoryOrderRepository, id: OrderId, status: OrderStatus) {
+    let mut order = repo.find(id).unwrap().unwrap();
+    order.status = status;
+    repo.update(&order).unwrap();
+}
+
+#[test]
+fn old_settled_orders_go_with_their_keys_and_the_rest_stay() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let (keys, clock) = (
+        InMemoryIdempotencyStore::new(),
+        SteppingClock::starting_at(Timestamp(DAY)),
+    );
+    let policy = AgeAndStatusRetention::new(Duration::from_secs(30 * DAY))
+        .with_status(OrderStatus::Shipped)
+        .with_status(OrderStatus::Paid);
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_clock(&clock)
+        .with_idempotency_store(&keys, Duration::from_secs(365 * DAY))
+        .with_retention_policy(&policy)
+        .with_charge_log(&payment);
+
+    // Cancelled and deleted, cancelled only, shipped: all settled.
+    let deleted = service.place_order_once("deleted", cart()).unwrap().id;
+    service.cancel_order(deleted).unwrap();
+    service.delete_order(deleted).unwrap();
+    let cancelled = service.place_order_once("cancelled", cart()).unwrap().id;
+    service.cancel_order(cancelled).unwrap();
+    let shipped = service.place_order_once("shipped", cart()).unwrap().id;
+    move_to(&repo, shipped, OrderStatus::Shipped);
+    // Paid, not shipped; cancelled behind the provider's back, still charged.
+    let paid = service.place_order_once("paid", cart()).unwrap().id;
+    let unrefunded = service.place_order(cart()).unwrap().id;
+    move_to(&repo, unrefunded, OrderStatus::Cancelled);
+    // Cancelled, but too recent.
+    clock.set(Timestamp(90 * DAY));
+    let recent = service.place_order_once("recent", cart()).unwrap().id;
+    service.cancel_order(recent).unwrap();
+
+    let report = service.purge(Timestamp(100 * DAY)).unwrap();
+
+    assert_eq!(report.purged, [cancelled, shipped, deleted]);
+    assert_eq!(report.keys_forgotten, 3);
+    assert!(report.failed.is_empty());
+    for &id in &report.purged {
+        assert_eq!(repo.find(id).unwrap(), None);
+    }
+    assert!(repo.list_deleted().unwrap().is_empty());
+    for key in ["deleted", "cancelled", "shipped"] {
+        assert_eq!(keys.find(key).unwrap(), None);
+    }
+
+    assert_eq!(
+        reasons(&report),
+        [
+            (paid, "shipment pending: paid, not shipped yet".to_string()),
+            (
+                unrefunded,
+                "refund not settled: the charge is still on record".to_string()
+            ),
+        ]
+    );
+    let left: Vec<OrderId> = repo.list().unwrap().iter().map(|o| o.id).collect();
+    assert_eq!(left, [paid, unrefunded, recent]);
+    assert_eq!(keys.find("paid").unwrap(), Some(paid));
+    assert_eq!(keys.find("recent").unwrap(), Some(recent));
+
+    // Nothing left to do: the same run again changes nothing.
+    let again = service.purge(Timestamp(100 * DAY)).unwrap();
+    assert!(again.purged.is_empty());
+    assert_eq!(again.blocked.len(), 2);
+}
+
+#[test]
+fn a_charge_deferred_for_a_purged_order_goes_with_it() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let (pending, clock) = (
+        InMemoryPendingCharges::new(),
+        SteppingClock::starting_at(Timestamp(DAY)),
+    );
+    let policy = AgeAndStatusRetention::new(Duration::from_secs(DAY));
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_clock(&clock)
+        .with_pending_charges(&pending)
+        .with_retention_policy(&policy);
+    let order = service.place_order(cart()).unwrap();
+    service.cancel_order(order.id).unwrap();
+    let waiting = service.place_order(cart()).unwrap();
+    move_to(&repo, waiting.id, OrderStatus::PaymentPending);
+    for order in [&order, &waiting] {
+        pending
+            .record(PendingCharge {
+                order_id: order.id,
+                amount: order.total,
+            })
+            .unwrap();
+    }
+
+    let report = service.purge(Timestamp(10 * DAY)).unwrap();
+
+    // The policy only lets Cancelled orders go: the pending one stays, and
+    // its charge with it.
+    assert_eq!(report.purged, [order.id]);
+    assert!(report.blocked.is_empty());
+    let left: Vec<OrderId> = pending
+        .pending()
+        .unwrap()
+        .iter()
+        .map(|c| c.order_id)
+        .collect();
+    assert_eq!(left, [waiting.id]);
+}
+
+#[test]
+fn without_a_policy_nothing_is_forgotten() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let order = service.place_order(cart()).unwrap();
+    service.cancel_order(order.id).unwrap();
+
+    let report = service.purge(Timestamp(u64::MAX)).unwrap();
+
+    assert!(report.purged.is_empty());
+    assert_eq!(repo.list().unwrap().len(), 1);
+}
@@ -0,0 +1,144 @@
+// cargo test --test discounted_orders
+// A discount that covers the whole cart leaves nothing to charge: the order
+// is Paid without the gateway ever being called, and ChargeSkipped says so
+// in the events. A cart that costs nothing by itself is refused.
+use hexa_lite::adapters::notification::ConfirmChargedOnly;
+use hexa_lite::domain::{Discount, OrderEvent};
+use hexa_lite::ports::{ChargeLog, EventPublisher};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct Journal(Mutex<Vec<OrderEvent>>);
+
+impl EventPublisher for Journal {
+    fn publish(&self, event: &OrderEvent) -> Result<(), OrderError> {
+        self.0.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Outbox(Mutex<Vec<OrderId>>);
+
+impl Sender for Outbox {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.0.lock().unwrap().push(confirmation.order_id);
+        Ok(())
+    }
+}
+
+fn cart() -> Vec<LineItem> {
+    vec![
+        LineItem::new("Mug", Money(1200)),
+        LineItem::new("Tea", Money(800)),
+    ]
+}
+
+fn free() -> Discount {
+    Discount::Promotion(Percent::try_from(100).unwrap())
+}
+
+#[test]
+fn a_fully_discounted_cart_is_paid_without_a_charge() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), Outbox::default());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    let order = service.place_discounted_order(cart(), free()).unwrap();
+
+    assert_order(&order)
+        .has_status(OrderStatus::Paid)
+        .has_total_cents(0);
+    assert_eq!(order.discount(), Money(2000));
+    assert!(payment.charges().unwrap().is_empty());
+    assert_eq!(repo.find(order.id).unwrap(), Some(order.clone()));
+    // No policy: confirmed like any other order.
+    assert_eq!(*sender.0.lock().unwrap(), [order.id]);
+}
+
+#[test]
+fn a_gift_card_pays_what_it_can_and_the_rest_is_charged() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), Outbox::default());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    let order = service
+        .place_discounted_order(cart(), Discount::GiftCard(Money(500)))
+        .unwrap();
+
+    assert_order(&order)
+        .has_status(OrderStatus::Paid)
+        .has_total_cents(1500);
+    let charged: Vec<Money> = payment
+        .charges()
+        .unwrap()
+        .iter()
+        .map(|charge| charge.amount)
+        .collect();
+    assert_eq!(charged, [Money(1500)]);
+}
+
+#[test]
+fn a_cart_of_free_items_is_refused() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), Outbox::default());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let stickers = || vec![LineItem::new("Sticker", Money(0))];
+
+    assert_err_variant!(
+        service.place_order(stickers()),
+        OrderError::ZeroTotalNotAllowed
+    );
+    assert_err_variant!(
+        service.place_discounted_order(stickers(), free()),
+        OrderError::ZeroTotalNotAllowed
+    );
+    assert!(repo.list().unwrap().is_empty());
+    assert!(payment.charges().unwrap().is_empty());
+    assert!(sender.0.lock().unwrap().is_empty());
+}
+
+#[test]
+fn the_skipped_charge_is_in_the_events() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), Outbox::default());
+    let journal = Journal::default();
+    let mut service = OrderService::new(&repo, &payment, &sender).with_event_publisher(&journal);
+
+    let order = service.place_discounted_order(cart(), free()).unwrap();
+
+    assert_eq!(
+        *journal.0.lock().unwrap(),
+        [
+            OrderEvent::OrderPlaced {
+                order_id: order.id,
+                item_count: 2,
+                total: Money(0),
+            },
+            OrderEvent::ChargeSkipped {
+                order_id: order.id,
+                covered: Money(2000),
+            },
+            OrderEvent::OrderPaid {
+                order_id: order.id,
+                amount: Money(0),
+            },
+        ]
+    );
+}
+
+#[test]
+fn the_notification_policy_can_keep_free_orders_quiet() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), Outbox::default());
+    let mut service =
+        OrderService::new(&repo, &payment, &sender).with_notification_policy(&ConfirmChargedOnly);
+
+    let given = service.place_discounted_order(cart(), free()).unwrap();
+    let sold = service.place_order(cart()).unwrap();
+
+    assert_eq!(given.status, OrderStatus::Paid);
+    assert_eq!(*sender.0.lock().unwrap(), [sold.id]);
+}
@@ -0,0 +1,193 @@
+// cargo test --test draft_conflicts
+// Two clients, each with its own service, edit the same open cart in one
+// shared repository. The second to write loses the race; its conflict
+// resolver decides what the repository ends up holding.
+use hexa_lite::adapters::conflict::{ItemUnionMerge, LastWriteWins};
+use hexa_lite::ports::{ConflictResolver, Resolution};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+
+fn item(name: &str, cents: u32) -> LineItem {
+    LineItem::new(name, Money(cents))
+}
+
+fn names(order: &Order) -> Vec<&str> {
+    order.items.iter().map(|item| item.name.as_str()).collect()
+}
+
+// A stored open cart: a keyboard, at version 0.
+fn repository() -> InMemoryOrderRepository {
+    let repo = InMemoryOrderRepository::new();
+    repo.save(&Order::new(OrderId(1), vec![item("Keyboard", 12_999)]).unwrap())
+        .unwrap();
+    repo
+}
+
+fn with(base: &Order, added: LineItem) -> Order {
+    let mut edited = base.clone();
+    edited.items.push(added);
+    edited
+}
+
+// Both clients read the cart; the first one writes its edit, which adds
+// `first`; the second one's edit, adding `second`, then meets a conflict
+// and goes to `resolver`.
+fn race(
+    repo: &InMemoryOrderRepository,
+    resolver: &(dyn ConflictResolver + Sync),
+    first: LineItem,
+    second: LineItem,
+) -> Result<Order, OrderError> {
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let mut tab_a = OrderService::new(repo, &payment, &sender);
+    let mut tab_b = OrderService::new(repo, &payment, &sender).with_conflict_resolver(resolver);
+    let base = repo.find(OrderId(1)).unwrap().unwrap();
+
+    tab_a
+        .update_draft_with_resolution(&base, with(&base, first))
+        .unwrap();
+    tab_b.update_draft_with_resolution(&base, with(&base, second))
+}
+
+#[test]
+fn last_write_wins_keeps_the_second_edit_only() {
+    let repo = repository();
+
+    let stored = race(
+        &repo,
+        &LastWriteWins,
+        item("Mouse", 2_500),
+        item("Cable", 500),
+    )
+    .unwrap();
+
+    assert_eq!(names(&stored), ["Keyboard", "Cable"]);
+    assert_order(&stored).has_total_cents(13_499);
+    assert_eq!(stored.version, 2);
+    assert_eq!(repo.find(OrderId(1)).unwrap(), Some(stored));
+}
+
+#[test]
+fn item_union_merge_keeps_both_additions() {
+    let repo = repository();
+
+    let stored = race(
+        &repo,
+        &ItemUnionMerge,
+        item("Mouse", 2_500),
+        item("Cable", 500),
+    )
+    .unwrap();
+
+    assert_eq!(names(&stored), ["Keyboard", "Mouse", "Cable"]);
+    assert_order(&stored).has_total_cents(15_999);
+    assert_eq!(repo.find(OrderId(1)).unwrap(), Some(stored));
+}
+
+#[test]
+fn the_same_item_added_twice_is_a_conflict_for_the_merge() {
+    let repo = repository();
+
+    let refused = race(
+        &repo,
+        &ItemUnionMerge,
+        item("Mouse", 2_500),
+        item("Mouse", 2_500),
+    );
+
+    assert_err_variant!(
+        refused,
+        OrderError::VersionConflict {
+            expected: 0,
+            found: 1,
+            ..
+        }
+    );
+    let stored = repo.find(OrderId(1)).unwrap().unwrap();
+    assert_eq!(
+        (names(&stored), stored.version),
+        (vec!["Keyboard", "Mouse"], 1)
+    );
+}
+
+// Takes theirs, and says what it was shown.
+struct TakeTheirs;
+
+impl ConflictResolver for TakeTheirs {
+    fn resolve(&self, base: &Order, ours: &Order, theirs: &Order) -> Resolution {
+        assert_eq!(names(base), ["Keyboard"]);
+        assert_eq!(names(ours), ["Keyboard", "Cable"]);
+        assert_eq!(names(theirs), ["Keyboard", "Mouse"]);
+        Resolution::TakeTheirs
+    }
+}
+
+#[test]
+fn taking_theirs_writes_nothing() {
+    let repo = repository();
+
+    let stored = race(&repo, &TakeTheirs, item("Mouse", 2_500), item("Cable", 500)).unwrap();
+
+    assert_eq!(
+        (names(&stored), stored.version),
+        (vec!["Keyboard", "Mouse"], 1)
+    );
+    assert_eq!(repo.find(OrderId(1)).unwrap(), Some(stored));
+}
+
+// While it decides, a third client gets its edit in.
+struct Slow<'a>(&'a InMemoryOrderRepository);
+
+impl ConflictResolver for Slow<'_> {
+    fn resolve(&self, _base: &Order, _ours: &Order, theirs: &Order) -> Resolution {
+        self.0.update(&with(theirs, item("Lamp", 3_900))).unwrap();
+        Resolution::TakeOurs
+    }
+}
+
+#[test]
+fn the_resolved_edit_is_retried_once_only() {
+    let repo = repository();
+
+    let refused = race(
+        &repo,
+        &Slow(&repo),
+        item("Mouse", 2_500),
+        item("Cable", 500),
+    );
+
+    assert_err_variant!(
+        refused,
+        OrderError::VersionConflict {
+            expected: 1,
+            found: 2,
+            ..
+        }
+    );
+    let stored = repo.find(OrderId(1)).unwrap().unwrap();
+    assert_eq!(names(&stored), ["Keyboard", "Mouse", "Lamp"]);
+}
+
+#[test]
+fn without_a_resolver_the_conflict_is_returned() {
+    let repo = repository();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let base = repo.find(OrderId(1)).unwrap().unwrap();
+    repo.update(&with(&base, item("Mouse", 2_500))).unwrap();
+
+    assert_err_variant!(
+        service.update_draft_with_resolution(&base, with(&base, item("Cable", 500))),
+        OrderError::VersionConflict { .. }
+    );
+
+    // Not a draft any more: nothing to edit.
+    let mut paid = repo.find(OrderId(1)).unwrap().unwrap();
+    paid.mark_paid().unwrap();
+    repo.update(&paid).unwrap();
+    let base = repo.find(OrderId(1)).unwrap().unwrap();
+    assert_err_variant!(
+        service.update_draft_with_resolution(&base, with(&base, item("Cable", 500))),
+        OrderError::NotADraft { .. }
+    );
+}
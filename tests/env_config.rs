@@ -0,0 +1,275 @@
+// cargo test --test env_config
+// EnvConfig::load() reads the real process environment. The variables are
+// process-wide and the tests of this file run in parallel, so every test
+// takes the same lock and its guard restores the variables on drop.
+use hexa_lite::adapters;
+use hexa_lite::composition::{
+    ConfigError, ConfiguredSender, EVENT_LOG_VAR, EnvConfig, PAYMENT_OPTIONS_VAR, PAYMENT_VAR,
+    REPOSITORY_OPTIONS_VAR, REPOSITORY_VAR, SENDER_OPTIONS_VAR, SENDER_VAR, SENDGRID_KEY_VAR,
+    SenderConfig, WEBHOOK_SECRET_VAR, WEBHOOK_URL_VAR, Wiring, WiringError, build_adapters,
+    build_service,
+};
+use hexa_lite::prelude::*;
+use std::sync::{Mutex, MutexGuard};
+
+const ALL_VARS: [&str; 10] = [
+    SENDER_VAR,
+    SENDGRID_KEY_VAR,
+    WEBHOOK_URL_VAR,
+    WEBHOOK_SECRET_VAR,
+    EVENT_LOG_VAR,
+    REPOSITORY_VAR,
+    PAYMENT_VAR,
+    REPOSITORY_OPTIONS_VAR,
+    PAYMENT_OPTIONS_VAR,
+    SENDER_OPTIONS_VAR,
+];
+
+static ENV_LOCK: Mutex<()> = Mutex::new(());
+
+struct EnvGuard {
+    saved: Vec<(&'static str, Option<String>)>,
+    _lock: MutexGuard<'static, ()>,
+}
+
+impl EnvGuard {
+    // Clears every HEXLITE_ variable, then sets the given ones.
+    fn set(vars: &[(&'static str, &str)]) -> Self {
+        let lock = ENV_LOCK
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let saved = ALL_VARS
+            .iter()
+            .map(|&var| (var, std::env::var(var).ok()))
+            .collect();
+        // SAFETY: every test touching the environment holds ENV_LOCK.
+        unsafe {
+            for var in ALL_VARS {
+                std::env::remove_var(var);
+            }
+            for (var, value) in vars {
+                std::env::set_var(var, value);
+            }
+        }
+        EnvGuard { saved, _lock: lock }
+    }
+}
+
+impl Drop for EnvGuard {
+    fn drop(&mut self) {
+        // SAFETY: still holding ENV_LOCK, released right after.
+        unsafe {
+            for (var, value) in &self.saved {
+                match value {
+                    Some(value) => std::env::set_var(var, value),
+                    None => std::env::remove_var(var),
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn every_problem_is_reported_at_once() {
+    let _env = EnvGuard::set(&[
+        (SENDER_VAR, "webhook"),
+        (WEBHOOK_URL_VAR, "ftp://example.test"),
+        (WEBHOOK_SECRET_VAR, "  "),
+        (EVENT_LOG_VAR, ""),
+    ]);
+
+    let error = EnvConfig::load().unwrap_err();
+
+    let ConfigError::Multiple(errors) = error else {
+        panic!("expected several errors, got {error:?}");
+    };
+    let vars: Vec<&str> = errors
+        .iter()
+        .map(|error| match error {
+            ConfigError::Missing { var } | ConfigError::Invalid { var, .. } => *var,
+            ConfigError::Adapter(_) => "adapter",
+            ConfigError::Multiple(_) => "nested",
+        })
+        .collect();
+    assert_eq!(
+        vars,
+        vec![WEBHOOK_URL_VAR, WEBHOOK_SECRET_VAR, EVENT_LOG_VAR]
+    );
+}
+
+#[test]
+fn a_single_problem_is_not_wrapped() {
+    let _env = EnvGuard::set(&[(SENDER_VAR, "sendgrid")]);
+
+    assert_eq!(
+        EnvConfig::load(),
+        Err(ConfigError::Missing {
+            var: SENDGRID_KEY_VAR
+        })
+    );
+}
+
+#[test]
+fn unknown_sender_is_invalid() {
+    let _env = EnvGuard::set(&[(SENDER_VAR, "carrier-pigeon")]);
+
+    assert!(matches!(
+        EnvConfig::load(),
+        Err(ConfigError::Invalid {
+            var: SENDER_VAR,
+            ..
+        })
+    ));
+}
+
+fn place_one(config: &EnvConfig) -> ConfiguredSender {
+    let adapters = build_adapters(config).unwrap();
+    build_service(&adapters)
+        .place_order(vec![LineItem {
+            name: "Keyboard".to_string(),
+            price: Money(12999),
+        }])
+        .unwrap();
+    adapters.sender
+}
+
+#[test]
+fn defaults_to_the_console_sender() {
+    let _env = EnvGuard::set(&[]);
+
+    let config = EnvConfig::load().unwrap();
+
+    assert_eq!(config.sender, SenderConfig::Console);
+    assert_eq!(config.event_log, None);
+    assert!(matches!(place_one(&config), ConfiguredSender::Console(_)));
+}
+
+#[test]
+fn builds_a_sendgrid_sender() {
+    let _env = EnvGuard::set(&[(SENDER_VAR, "sendgrid"), (SENDGRID_KEY_VAR, "SG.key")]);
+
+    let config = EnvConfig::load().unwrap();
+
+    assert!(matches!(place_one(&config), ConfiguredSender::SendGrid(_)));
+}
+
+#[test]
+fn builds_a_signed_webhook_sender() {
+    let _env = EnvGuard::set(&[
+        (SENDER_VAR, "webhook"),
+        (WEBHOOK_URL_VAR, "https://example.test/hook"),
+        (WEBHOOK_SECRET_VAR, "whsec"),
+    ]);
+
+    let config = EnvConfig::load().unwrap();
+
+    let ConfiguredSender::Webhook(sender) = place_one(&config) else {
+        panic!("expected a webhook sender");
+    };
+    let delivered = sender.delivered();
+    assert_eq!(delivered[0].url, "https://example.test/hook");
+    assert!(delivered[0].header("X-Signature").is_some());
+}
+
+fn adapter_names(config: &EnvConfig) -> Vec<(&'static str, String)> {
+    build_adapters(config)
+        .unwrap()
+        .wiring
+        .describe()
+        .into_iter()
+        .map(|binding| (binding.port, binding.adapter))
+        .collect()
+}
+
+#[test]
+fn default_wiring_lists_exactly_the_in_memory_adapters() {
+    let _env = EnvGuard::set(&[]);
+
+    let config = EnvConfig::load().unwrap();
+
+    assert_eq!(
+        adapter_names(&config),
+        vec![
+            ("OrderReader", "InMemoryOrderRepository".to_string()),
+            ("OrderWriter", "InMemoryOrderRepository".to_string()),
+            ("PaymentGateway", "MockPaymentGateway".to_string()),
+            ("Sender", "ConsoleSender".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn wiring_shows_the_configuration_but_not_the_secrets() {
+    let log = std::env::temp_dir().join("hexa-lite-wiring-orders.log");
+    let _env = EnvGuard::set(&[
+        (SENDER_VAR, "webhook"),
+        (WEBHOOK_URL_VAR, "https://example.test/hook"),
+        (WEBHOOK_SECRET_VAR, "whsec"),
+        (EVENT_LOG_VAR, &log.display().to_string()),
+    ]);
+
+    let config = EnvConfig::load().unwrap();
+    let table = build_adapters(&config).unwrap().wiring.to_string();
+    let _ = std::fs::remove_file(&log);
+
+    assert!(table.contains("https://example.test/hook, signed"));
+    assert!(table.contains("FileEventLog"));
+    assert!(table.contains(&log.display().to_string()));
+    assert!(!table.contains("whsec"));
+}
+
+#[test]
+fn every_adapter_refusing_its_settings_is_reported() {
+    let _env = EnvGuard::set(&[]);
+    let missing_dir = std::env::temp_dir().join("hexa-lite-no-such-dir");
+    // Built by hand: EnvConfig::load() would already refuse the URL.
+    let config = EnvConfig {
+        sender: SenderConfig::Webhook {
+            url: "example.test/hook".to_string(),
+            signing_key: None,
+        },
+        event_log: Some(missing_dir.join("events.log")),
+        repository: None,
+        payment: None,
+    };
+
+    let Err(ConfigError::Multiple(errors)) = build_adapters(&config) else {
+        panic!("expected both adapters to be refused");
+    };
+
+    assert_eq!(
+        errors,
+        vec![
+            ConfigError::Adapter(adapters::ConfigError::InvalidUrl {
+                url: "example.test/hook".to_string(),
+                reason: "expected an http:// or https:// URL",
+            }),
+            ConfigError::Adapter(adapters::ConfigError::IoError {
+                path: missing_dir.join("events.log"),
+                kind: std::io::ErrorKind::NotFound,
+            }),
+        ]
+    );
+}
+
+#[test]
+fn binding_a_port_twice_is_a_conflict() {
+    let mut wiring = Wiring::new();
+    wiring
+        .bind::<dyn PaymentGateway>("MockPaymentGateway")
+        .unwrap();
+
+    let error = wiring
+        .bind::<dyn PaymentGateway>("StripePaymentGateway")
+        .unwrap_err();
+
+    assert_eq!(
+        error,
+        WiringError::Conflict {
+            port: "PaymentGateway",
+            bound: "MockPaymentGateway".to_string(),
+            rejected: "StripePaymentGateway".to_string(),
+        }
+    );
+    assert_eq!(wiring.describe().len(), 1);
+}
@@ -0,0 +1,189 @@
+// cargo test --test event_bus
+// One service publishing to an InMemoryEventBus, several subscribers
+// behind it: each gets the events its filter picks, in order, whatever the
+// others do, panics included.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::event_bus::{EventFilter, InMemoryEventBus};
+use hexa_lite::application::OrderReadModel;
+use hexa_lite::domain::OrderEvent;
+use hexa_lite::ports::{ErrorContext, ErrorReporter, EventPublisher, Port};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::SteppingClock;
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct Alerts(Mutex<Vec<ErrorContext>>);
+
+impl ErrorReporter for Alerts {
+    fn report(&self, context: ErrorContext) {
+        self.0.lock().unwrap().push(context);
+    }
+}
+
+fn pen() -> Vec<LineItem> {
+    vec![LineItem::new("Pen", Money(150))]
+}
+
+fn paid(id: u32) -> OrderEvent {
+    OrderEvent::OrderPaid {
+        order_id: OrderId(id),
+        amount: Money(150),
+    }
+}
+
+#[test]
+fn each_subscriber_gets_what_its_filter_picks_in_order() {
+    let everything = Mutex::new(Vec::new());
+    let payments = Mutex::new(Vec::new());
+    let second = Mutex::new(Vec::new());
+    let bus = InMemoryEventBus::new();
+    bus.subscribe(
+        EventFilter::All,
+        Box::new(|event| {
+            everything.lock().unwrap().push(event.clone());
+            Ok(())
+        }),
+    );
+    bus.subscribe(
+        EventFilter::Matching(|event| matches!(event, OrderEvent::OrderPaid { .. })),
+        Box::new(|event| {
+            payments.lock().unwrap().push(event.order_id());
+            Ok(())
+        }),
+    );
+    bus.subscribe(
+        EventFilter::Order(OrderId(2)),
+        Box::new(|event| {
+            second.lock().unwrap().push(event.clone());
+            Ok(())
+        }),
+    );
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &sender).with_event_publisher(&bus);
+
+    for _ in 0..3 {
+        service.place_order(pen()).unwrap();
+    }
+
+    let everything = everything.lock().unwrap();
+    assert_eq!(everything.len(), 6);
+    let ids: Vec<u32> = everything.iter().map(|event| event.order_id().0).collect();
+    assert_eq!(ids, [1, 1, 2, 2, 3, 3]);
+    assert!(matches!(everything[0], OrderEvent::OrderPlaced { .. }));
+    assert_eq!(
+        payments.lock().unwrap()[..],
+        [OrderId(1), OrderId(2), OrderId(3)]
+    );
+    assert_eq!(second.lock().unwrap()[..], everything[2..4]);
+}
+
+#[test]
+fn the_read_model_is_kept_up_to_date_from_the_bus() {
+    let model = Mutex::new(OrderReadModel::new());
+    let bus = InMemoryEventBus::new();
+    bus.feed(EventFilter::All, &model);
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &sender).with_event_publisher(&bus);
+
+    service.place_order(pen()).unwrap();
+    service
+        .place_order(vec![LineItem::new("Ink", Money(250))])
+        .unwrap();
+
+    let model = model.lock().unwrap();
+    assert_eq!(model.events_applied(), 4);
+    assert_eq!(model.revenue(), Money(400));
+    assert_eq!(model.get(OrderId(2)).unwrap().status, OrderStatus::Paid);
+}
+
+#[test]
+fn an_unsubscribed_handler_hears_nothing_more() {
+    let leaving = Mutex::new(Vec::new());
+    let staying = Mutex::new(Vec::new());
+    let bus = InMemoryEventBus::new();
+    let id = bus.subscribe(
+        EventFilter::All,
+        Box::new(|event| {
+            leaving.lock().unwrap().push(event.order_id());
+            Ok(())
+        }),
+    );
+    bus.subscribe(
+        EventFilter::All,
+        Box::new(|event| {
+            staying.lock().unwrap().push(event.order_id());
+            Ok(())
+        }),
+    );
+
+    for id in 1..=2 {
+        bus.publish(&paid(id)).unwrap();
+    }
+    assert!(bus.unsubscribe(id));
+    for id in 3..=4 {
+        bus.publish(&paid(id)).unwrap();
+    }
+
+    assert_eq!(leaving.lock().unwrap()[..], [OrderId(1), OrderId(2)]);
+    assert_eq!(staying.lock().unwrap().len(), 4);
+    assert_eq!(bus.subscriber_count(), 1);
+    assert!(!bus.unsubscribe(id));
+}
+
+#[test]
+fn a_panicking_or_failing_subscriber_is_reported_and_the_others_still_hear() {
+    let alerts = Alerts::default();
+    let clock = SteppingClock::starting_at(Timestamp(1_700_000_000));
+    let heard = Mutex::new(Vec::new());
+    let bus = InMemoryEventBus::new().with_error_reporter(&alerts, &clock);
+    bus.subscribe(
+        EventFilter::All,
+        Box::new(|event| {
+            if event.order_id() == OrderId(2) {
+                panic!("cannot handle order 2");
+            }
+            Ok(())
+        }),
+    );
+    bus.subscribe(
+        EventFilter::All,
+        Box::new(|_| Err(OrderError::StorageFailed)),
+    );
+    bus.subscribe(
+        EventFilter::All,
+        Box::new(|event| {
+            heard.lock().unwrap().push(event.order_id());
+            Ok(())
+        }),
+    );
+
+    for id in 1..=3 {
+        assert!(bus.publish(&paid(id)).is_ok());
+    }
+
+    assert_eq!(
+        heard.lock().unwrap()[..],
+        [OrderId(1), OrderId(2), OrderId(3)]
+    );
+    let alerts = alerts.0.lock().unwrap();
+    assert_eq!(alerts.len(), 4);
+    assert!(
+        alerts
+            .iter()
+            .all(|alert| alert.port == Some(Port::Events) && alert.use_case == "event_bus")
+    );
+    assert!(matches!(
+        &alerts[1],
+        ErrorContext {
+            order_id: Some(OrderId(2)),
+            error: OrderError::Custom { message },
+            ..
+        } if message == "subscriber 1 panicked: cannot handle order 2"
+    ));
+    assert!(matches!(alerts[0].error, OrderError::StorageFailed));
+    assert!(matches!(alerts[2].error, OrderError::StorageFailed));
+}
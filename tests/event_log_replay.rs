@@ -0,0 +1,110 @@
+// cargo test --test event_log_replay
+// After a restart, the read model is rebuilt from the event log.
+// A crash in the middle of a write must cost one event, not the whole log.
+use hexa_lite::adapters::event_log::{EventLogReader, FileEventLog, ReplayReport};
+use hexa_lite::application::OrderReadModel;
+use hexa_lite::domain::OrderEvent;
+use hexa_lite::ports::EventPublisher;
+use hexa_lite::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+
+// One file per test, removed when the test ends, even on failure.
+struct TempLog(PathBuf);
+
+impl TempLog {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("hexa_lite_{}_{name}.log", std::process::id()));
+        let _ = fs::remove_file(&path);
+        TempLog(path)
+    }
+}
+
+impl Drop for TempLog {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn torn_last_record_is_skipped_and_counted() {
+    let file = TempLog::new("torn");
+    let log = FileEventLog::new(&file.0).unwrap();
+    for n in 1..=100 {
+        log.publish(&OrderEvent::OrderPlaced {
+            order_id: OrderId(n),
+            item_count: 1,
+            total: Money(100 * n),
+        })
+        .unwrap();
+    }
+
+    // Simulate a crash halfway through writing the last record.
+    let bytes = fs::read(&file.0).unwrap();
+    let last_record_start = bytes[..bytes.len() - 1]
+        .iter()
+        .rposition(|&byte| byte == b'\n')
+        .unwrap()
+        + 1;
+    let torn_at = last_record_start + (bytes.len() - last_record_start) / 2;
+    fs::write(&file.0, &bytes[..torn_at]).unwrap();
+
+    let mut model = OrderReadModel::new();
+    let report = EventLogReader::new(&file.0)
+        .replay_into(&mut model)
+        .unwrap();
+
+    assert_eq!(
+        report,
+        ReplayReport {
+            replayed: 99,
+            skipped: 1
+        }
+    );
+    assert_eq!(model.events_applied(), 99);
+    assert_eq!(model.orders().len(), 99);
+    assert!(model.get(OrderId(100)).is_none());
+}
+
+#[test]
+fn service_events_rebuild_the_read_model() {
+    let file = TempLog::new("service");
+    let log = FileEventLog::new(&file.0).unwrap();
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender).with_event_publisher(&log);
+
+    for price in [4999, 12999] {
+        service
+            .place_order(vec![LineItem {
+                name: "Item".to_string(),
+                price: Money(price),
+            }])
+            .unwrap();
+    }
+
+    // "Restart": a brand new model, fed from the file only.
+    let mut model = OrderReadModel::new();
+    let report = EventLogReader::new(&file.0)
+        .replay_into(&mut model)
+        .unwrap();
+
+    assert_eq!(report.skipped, 0);
+    assert_eq!(report.replayed, 4); // placed + paid, twice
+    assert_eq!(model.revenue(), Money(17998));
+    assert_eq!(model.get(OrderId(2)).unwrap().status, OrderStatus::Paid);
+}
+
+#[test]
+fn missing_log_replays_nothing() {
+    let file = TempLog::new("missing");
+    let mut model = OrderReadModel::new();
+
+    let report = EventLogReader::new(&file.0)
+        .replay_into(&mut model)
+        .unwrap();
+
+    assert_eq!(report, ReplayReport::default());
+}
@@ -0,0 +1,119 @@
+// cargo test --test eventual_consistency
+// A read model behind a DelayedProjection: stale right after a write, as
+// far behind as freshness says, and the same as an up-to-date one once it
+// has caught up, however many events piled up meanwhile.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::event_bus::{EventFilter, InMemoryEventBus};
+use hexa_lite::adapters::projection::DelayedProjection;
+use hexa_lite::application::{OrderBrowser, OrderReadModel};
+use hexa_lite::ports::{EventPublisher, Freshness, OrderQueries};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::SteppingClock;
+use std::sync::Mutex;
+
+fn cart(cents: u32) -> Vec<LineItem> {
+    vec![LineItem::new("Lamp", Money(cents))]
+}
+
+#[test]
+fn freshness_counts_what_is_pending_and_since_when() {
+    let clock = SteppingClock::starting_at(Timestamp(1_000));
+    let projection = DelayedProjection::new(OrderReadModel::new(), &clock);
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &sender).with_event_publisher(&projection);
+    assert!(projection.freshness().is_current());
+
+    let first = service.place_order(cart(3_500)).unwrap().id;
+    clock.advance_secs(10);
+    service.place_order(cart(1_200)).unwrap();
+
+    assert_eq!(
+        projection.freshness(),
+        Freshness {
+            pending_events: 4,
+            oldest_pending: Some(Timestamp(1_000)),
+        }
+    );
+    assert_eq!(projection.model().get(first), None);
+    // The repository answers at once, and says so.
+    let browser = OrderBrowser::new(&repo);
+    assert_eq!(browser.list_orders().unwrap().len(), 2);
+    assert!(browser.freshness().unwrap().is_current());
+
+    assert_eq!(projection.advance_until(Timestamp(1_009)), 2);
+    assert_eq!(
+        projection.model().get(first).unwrap().status,
+        OrderStatus::Paid
+    );
+    assert_eq!(
+        projection.freshness(),
+        Freshness {
+            pending_events: 2,
+            oldest_pending: Some(Timestamp(1_010)),
+        }
+    );
+    assert_eq!(projection.advance_until(Timestamp(1_010)), 2);
+    assert!(projection.freshness().is_current());
+}
+
+#[test]
+fn events_applied_late_are_applied_in_order() {
+    let clock = SteppingClock::starting_at(Timestamp(1_000));
+    let projection = DelayedProjection::new(OrderReadModel::new(), &clock);
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &sender).with_event_publisher(&projection);
+
+    let id = service.place_order(cart(3_500)).unwrap().id;
+    service.cancel_order(id).unwrap();
+
+    // Placed, paid, cancelled: applied one at a time, the model goes
+    // through each in turn.
+    let status = || projection.model().get(id).unwrap().status;
+    assert_eq!(projection.advance(1), 1);
+    assert_eq!(status(), OrderStatus::Placed);
+    assert_eq!(projection.advance(1), 1);
+    assert_eq!(status(), OrderStatus::Paid);
+    assert_eq!(projection.advance(5), 1);
+    assert_eq!(status(), OrderStatus::Cancelled);
+}
+
+#[test]
+fn after_a_burst_the_model_catches_up_with_a_live_one() {
+    let clock = SteppingClock::starting_at(Timestamp(1_000));
+    let projection = DelayedProjection::new(OrderReadModel::new(), &clock);
+    let live = Mutex::new(OrderReadModel::new());
+    let bus = InMemoryEventBus::new();
+    bus.feed(EventFilter::All, &live);
+    bus.subscribe(
+        EventFilter::All,
+        Box::new(|event| projection.publish(event)),
+    );
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &sender).with_event_publisher(&bus);
+
+    for n in 1..=200 {
+        let id = service.place_order(cart(100 * n)).unwrap().id;
+        if n.is_multiple_of(3) {
+            service.cancel_order(id).unwrap();
+        }
+        clock.advance_secs(1);
+    }
+    let pending = projection.freshness().pending_events;
+    assert_eq!(pending, 2 * 200 + 200 / 3);
+    assert_eq!(projection.model().revenue(), Money(0));
+
+    assert_eq!(projection.advance(100), 100);
+    assert_eq!(projection.catch_up(), pending - 100);
+
+    let (caught_up, live) = (projection.model(), live.lock().unwrap());
+    assert!(projection.freshness().is_current());
+    assert_eq!(caught_up.events_applied(), live.events_applied());
+    assert_eq!(caught_up.revenue(), live.revenue());
+    assert_eq!(caught_up.orders(), live.orders());
+}
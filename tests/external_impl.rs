@@ -0,0 +1,962 @@
+// cargo test --test external_impl
+// An integration test is a crate of its own: everything here is what a
+// downstream crate could write, with only the public API. Each open trait
+// of the library is implemented once, by a small adapter of a made-up shop;
+// if a port starts depending on something private, this stops compiling.
+//
+// The audit at the bottom keeps it honest: every `pub trait` in src/ is
+// either implemented in this file or sealed, and the sealed ones are the
+// ones listed in SEALED.
+#![allow(deprecated)] // SenderV1 is still implementable, deprecated or not
+
+use hexa_lite::adapters::file_repository::{
+    Envelope, FormatError, Fs, JournaledRepository, StorageFormat,
+};
+use hexa_lite::adapters::sql::{Rows, SqlExecutor, SqlValue};
+use hexa_lite::application::{Hooks, OrderService};
+use hexa_lite::composition::{AdapterConfig, AdapterFactory, ConfigError, PortSet};
+use hexa_lite::domain::{
+    Address, BlobId, Currency, CurrencyTotals, Customer, LineItem, Money, Order, OrderConfirmation,
+    OrderDraft, OrderError, OrderEvent, OrderId, OrderReference, OrderStatus, Price, Redact,
+    SagaId, StoredOrder, Timestamp, TrackingId, Uuid128, WarehouseId, WarehouseStock,
+};
+use hexa_lite::ports::{
+    AmendOrder, AmendOrderUseCase, ApprovalPolicy, BlobStore, CancelOrderUseCase, CancelToken,
+    Capability, ChargeLog, ChargeRecord, Clock, ConflictResolver, Context, ContextualRepository,
+    ContextualSender, DeadLetter, DeadLetterSink, Direction, DraftRepository, ErrorContext,
+    ErrorReporter, EventPublisher, EventSubscriber, ExchangeRates, Flushable, FraudScreen,
+    Freshness, IdGenerator, IdempotencyStore, Inventory, Level, Logger, Metrics,
+    NotificationPolicy, OrderFacade, OrderPreview, OrderQueries, OrderReader, OrderWriter,
+    PaymentGateway, PendingCharge, PendingCharges, PlaceOrder, PlaceOrderUseCase, PortInfo,
+    ReferenceGenerator, ReservationId, Resolution, RetentionPolicy, RiskVerdict, SagaEntry,
+    SagaLog, Sender, SenderV1, ServiceState, ShippingProvider, Shutdown, ShutdownError, StateStore,
+    UnitOfWork, WarehousePicker,
+};
+use std::any::TypeId;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+
+// --- Orders, payments, notifications ---
+
+#[derive(Default)]
+struct Ledger(Mutex<BTreeMap<OrderId, Order>>);
+
+impl OrderReader for Ledger {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        Ok(self.0.lock().unwrap().get(&id).cloned())
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        Ok(self.0.lock().unwrap().values().cloned().collect())
+    }
+}
+
+impl OrderWriter for Ledger {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.0.lock().unwrap().insert(order.id, order.clone());
+        Ok(())
+    }
+}
+
+// No margin for a trace id.
+impl ContextualRepository for Ledger {
+    fn find_in(&self, _context: &Context, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.find(id)
+    }
+
+    fn list_in(&self, _context: &Context) -> Result<Vec<Order>, OrderError> {
+        self.list()
+    }
+
+    fn save_in(&self, _context: &Context, order: &Order) -> Result<(), OrderError> {
+        self.save(order)
+    }
+}
+
+impl Capability for Ledger {}
+
+#[derive(Default)]
+struct Till(Mutex<Vec<ChargeRecord>>);
+
+impl PaymentGateway for Till {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.charge_for(OrderId(0), amount)
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        if amount.0 > 100_000 {
+            return Err(OrderError::custom("the till only takes up to $1000.00"));
+        }
+        self.0
+            .lock()
+            .unwrap()
+            .push(ChargeRecord { order_id, amount });
+        Ok(())
+    }
+
+    fn refund_for(&self, order_id: OrderId, _amount: Money) -> Result<(), OrderError> {
+        self.0
+            .lock()
+            .unwrap()
+            .retain(|charge| charge.order_id != order_id);
+        Ok(())
+    }
+}
+
+impl ChargeLog for Till {
+    fn charges(&self) -> Result<Vec<ChargeRecord>, OrderError> {
+        Ok(self.0.lock().unwrap().clone())
+    }
+}
+
+#[derive(Default)]
+struct Postcards(Mutex<Vec<OrderId>>);
+
+impl Sender for Postcards {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.0.lock().unwrap().push(confirmation.order_id);
+        Ok(())
+    }
+}
+
+impl ContextualSender for Postcards {
+    fn send_in(
+        &self,
+        _context: &Context,
+        confirmation: &OrderConfirmation,
+    ) -> Result<(), OrderError> {
+        self.send(confirmation)
+    }
+}
+
+struct Fax;
+
+impl SenderV1 for Fax {
+    fn send(&self, _order: &Order) -> Result<(), OrderError> {
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct BackOffice(Mutex<Vec<OrderId>>);
+
+impl DeadLetterSink for BackOffice {
+    fn dead_letter(&self, letter: DeadLetter) -> Result<(), OrderError> {
+        self.0.lock().unwrap().push(letter.confirmation.order_id);
+        Ok(())
+    }
+}
+
+// --- Time, ids, rates ---
+
+struct TownHallClock;
+
+impl Clock for TownHallClock {
+    fn now(&self) -> Timestamp {
+        Timestamp::from_secs(1_700_000_000)
+    }
+}
+
+#[derive(Default)]
+struct Counter(AtomicU32);
+
+impl IdGenerator for Counter {
+    fn next_uuid(&self) -> Uuid128 {
+        let n = u64::from(self.0.fetch_add(1, Ordering::SeqCst));
+        Uuid128::v4(n, n)
+    }
+}
+
+impl ReferenceGenerator for Counter {
+    fn next_reference(&self) -> OrderReference {
+        OrderReference(format!("TEA-{}", self.0.fetch_add(1, Ordering::SeqCst)))
+    }
+}
+
+struct Parity;
+
+impl ExchangeRates for Parity {
+    fn convert(&self, from: Price, to: Currency) -> Result<Money, OrderError> {
+        match from.currency == to {
+            true => Ok(from.amount),
+            false => Err(OrderError::NoExchangeRate {
+                from: from.currency,
+                to,
+            }),
+        }
+    }
+}
+
+// --- Policies ---
+
+struct BigOrders;
+
+impl ApprovalPolicy for BigOrders {
+    fn requires_approval(&self, order: &Order) -> bool {
+        order.total.0 > 50_000
+    }
+}
+
+impl NotificationPolicy for BigOrders {
+    fn should_confirm(&self, _order: &Order) -> bool {
+        true
+    }
+}
+
+impl FraudScreen for BigOrders {
+    fn assess(&self, order: &Order, customer: &Customer) -> Result<RiskVerdict, OrderError> {
+        Ok(match (order.total.0 > 90_000, customer.name.is_empty()) {
+            (_, true) => RiskVerdict::Reject {
+                reason: "no name".to_string(),
+            },
+            (true, false) => RiskVerdict::Review,
+            (false, false) => RiskVerdict::Accept,
+        })
+    }
+}
+
+impl ConflictResolver for BigOrders {
+    fn resolve(&self, _base: &Order, _ours: &Order, _theirs: &Order) -> Resolution {
+        Resolution::TakeTheirs
+    }
+}
+
+// --- Stock, shipping, drafts ---
+
+#[derive(Default)]
+struct Cupboard(Mutex<BTreeMap<String, u32>>);
+
+impl Inventory for Cupboard {
+    fn reserve(&self, _reservation: ReservationId, items: &[LineItem]) -> Result<(), OrderError> {
+        let mut shelf = self.0.lock().unwrap();
+        for item in items {
+            let left = shelf.entry(item.name.clone()).or_default();
+            *left = left.checked_sub(1).ok_or(OrderError::OutOfStock {
+                item: item.name.clone(),
+            })?;
+        }
+        Ok(())
+    }
+
+    fn confirm(&self, _reservation: ReservationId) -> Result<(), OrderError> {
+        Ok(())
+    }
+
+    fn release(&self, _reservation: ReservationId) -> Result<(), OrderError> {
+        Ok(())
+    }
+
+    fn restock(&self, item: &str, units: u32) -> Result<(), OrderError> {
+        *self.0.lock().unwrap().entry(item.to_string()).or_default() += units;
+        Ok(())
+    }
+
+    fn stock_level(&self, item: &str) -> Result<u32, OrderError> {
+        Ok(self.0.lock().unwrap().get(item).copied().unwrap_or(0))
+    }
+}
+
+// The back room first, the cellar when the back room is out.
+struct BackRoom;
+
+impl WarehousePicker for BackRoom {
+    fn pick(&self, _item: &LineItem, stock: &[WarehouseStock]) -> Option<WarehouseId> {
+        stock.iter().map(|s| s.warehouse).min()
+    }
+}
+
+// A shop that keeps nothing it does not have to: a cancelled order goes on
+// the first run.
+struct ShortMemory;
+
+impl RetentionPolicy for ShortMemory {
+    fn should_purge(&self, order: &Order, _now: Timestamp) -> bool {
+        order.status == OrderStatus::Cancelled
+    }
+}
+
+struct Bicycle;
+
+impl ShippingProvider for Bicycle {
+    fn ship(
+        &self,
+        order_id: OrderId,
+        _items: &[LineItem],
+        address: &Address,
+    ) -> Result<TrackingId, OrderError> {
+        Ok(TrackingId(format!("BIKE-{}-{}", order_id.0, address.city)))
+    }
+}
+
+// The rider's phone number is theirs: the shop's logs show its end.
+struct Rider {
+    phone: String,
+}
+
+impl Redact for Rider {
+    fn redacted(&self) -> String {
+        let end = self.phone.len().saturating_sub(2);
+        format!("rider ***{}", &self.phone[end..])
+    }
+}
+
+#[derive(Default)]
+struct Notebook(BTreeMap<OrderId, StoredOrder>);
+
+impl DraftRepository for Notebook {
+    fn store(&mut self, order: StoredOrder) -> Result<(), OrderError> {
+        self.0.insert(order.id(), order);
+        Ok(())
+    }
+
+    fn load(&self, id: OrderId) -> Result<Option<StoredOrder>, OrderError> {
+        Ok(self.0.get(&id).cloned())
+    }
+}
+
+// --- Events and state ---
+
+#[derive(Default)]
+struct Journal(Mutex<Vec<OrderEvent>>);
+
+impl EventPublisher for Journal {
+    fn publish(&self, event: &OrderEvent) -> Result<(), OrderError> {
+        self.0.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Tally(usize);
+
+impl EventSubscriber for Tally {
+    fn on_event(&mut self, _event: &OrderEvent) {
+        self.0 += 1;
+    }
+}
+
+#[derive(Default)]
+struct Drawer {
+    state: Mutex<Option<ServiceState>>,
+    pending: Mutex<Vec<PendingCharge>>,
+    sagas: Mutex<BTreeMap<SagaId, Vec<SagaEntry>>>,
+    keys: Mutex<BTreeMap<String, (OrderId, Timestamp)>>,
+    blobs: Mutex<BTreeMap<BlobId, Vec<u8>>>,
+}
+
+impl StateStore for Drawer {
+    fn save_state(&self, state: &ServiceState) -> Result<(), OrderError> {
+        *self.state.lock().unwrap() = Some(state.clone());
+        Ok(())
+    }
+
+    fn load_state(&self) -> Result<Option<ServiceState>, OrderError> {
+        Ok(self.state.lock().unwrap().clone())
+    }
+}
+
+impl PendingCharges for Drawer {
+    fn record(&self, charge: PendingCharge) -> Result<(), OrderError> {
+        self.pending.lock().unwrap().push(charge);
+        Ok(())
+    }
+
+    fn pending(&self) -> Result<Vec<PendingCharge>, OrderError> {
+        Ok(self.pending.lock().unwrap().clone())
+    }
+
+    fn remove(&self, order_id: OrderId) -> Result<(), OrderError> {
+        self.pending
+            .lock()
+            .unwrap()
+            .retain(|charge| charge.order_id != order_id);
+        Ok(())
+    }
+}
+
+impl SagaLog for Drawer {
+    fn append(&self, saga: SagaId, entry: SagaEntry) -> Result<(), OrderError> {
+        self.sagas
+            .lock()
+            .unwrap()
+            .entry(saga)
+            .or_default()
+            .push(entry);
+        Ok(())
+    }
+
+    fn entries(&self, saga: SagaId) -> Result<Vec<SagaEntry>, OrderError> {
+        Ok(self
+            .sagas
+            .lock()
+            .unwrap()
+            .get(&saga)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
+
+impl IdempotencyStore for Drawer {
+    fn find(&self, key: &str) -> Result<Option<OrderId>, OrderError> {
+        Ok(self.keys.lock().unwrap().get(key).map(|&(id, _)| id))
+    }
+
+    fn remember(&self, key: &str, id: OrderId, at: Timestamp) -> Result<(), OrderError> {
+        self.keys.lock().unwrap().insert(key.to_string(), (id, at));
+        Ok(())
+    }
+
+    fn purge_older_than(&self, max_age: Duration, now: Timestamp) -> Result<usize, OrderError> {
+        let oldest_kept = now.minus_secs(max_age.as_secs());
+        let mut keys = self.keys.lock().unwrap();
+        let before = keys.len();
+        keys.retain(|_, &mut (_, at)| at >= oldest_kept);
+        Ok(before - keys.len())
+    }
+}
+
+impl BlobStore for Drawer {
+    fn put(&self, bytes: &[u8]) -> Result<BlobId, OrderError> {
+        let mut blobs = self.blobs.lock().unwrap();
+        let id = BlobId(blobs.keys().last().map_or(1, |last| last.0 + 1));
+        blobs.insert(id, bytes.to_vec());
+        Ok(id)
+    }
+
+    fn get(&self, id: BlobId) -> Result<Option<Vec<u8>>, OrderError> {
+        Ok(self.blobs.lock().unwrap().get(&id).cloned())
+    }
+
+    fn delete(&self, id: BlobId) -> Result<(), OrderError> {
+        self.blobs.lock().unwrap().remove(&id);
+        Ok(())
+    }
+}
+
+// --- Telemetry ---
+
+#[derive(Default)]
+struct Gauges(Mutex<Vec<String>>);
+
+impl Metrics for Gauges {
+    fn increment(&self, name: &str) {
+        self.0.lock().unwrap().push(name.to_string());
+    }
+}
+
+impl ErrorReporter for Gauges {
+    fn report(&self, context: ErrorContext) {
+        self.0.lock().unwrap().push(context.use_case.to_string());
+    }
+}
+
+impl Logger for Gauges {
+    fn log(&self, level: Level, target: &str, message: fmt::Arguments<'_>) {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("{level:?} {target} {message}"));
+    }
+}
+
+impl Hooks for Gauges {
+    fn after_save(&self, order: &Order) -> Result<(), OrderError> {
+        self.increment(&format!("saved {}", order.id));
+        Ok(())
+    }
+}
+
+// --- Capabilities ---
+
+#[derive(Default)]
+struct Tray {
+    held: Vec<OrderId>,
+    open: bool,
+}
+
+impl Flushable for Tray {
+    fn flush(&mut self) -> Result<(), OrderError> {
+        self.held.clear();
+        Ok(())
+    }
+}
+
+impl UnitOfWork for Tray {
+    fn begin(&mut self) {
+        self.open = true;
+    }
+
+    fn commit(&mut self) -> Result<(), OrderError> {
+        self.open = false;
+        Ok(())
+    }
+
+    fn rollback(&mut self) {
+        self.open = false;
+    }
+}
+
+impl Shutdown for Tray {
+    fn shutdown(&mut self) -> Result<(), ShutdownError> {
+        match self.held.len() {
+            0 => Ok(()),
+            count => Err(ShutdownError::Abandoned {
+                count,
+                what: "held orders",
+            }),
+        }
+    }
+}
+
+impl Capability for Tray {
+    fn as_flushable(&mut self) -> Option<&mut dyn Flushable> {
+        Some(self)
+    }
+
+    fn as_transactional(&mut self) -> Option<&mut dyn UnitOfWork> {
+        Some(self)
+    }
+}
+
+// --- Storage back ends ---
+
+struct OneLinePerOrder;
+
+impl StorageFormat for OneLinePerOrder {
+    fn name(&self) -> &'static str {
+        "lines"
+    }
+
+    fn serialize(&self, envelope: &Envelope) -> Vec<u8> {
+        format!("{} {}", envelope.orders.len(), envelope.deleted.len()).into_bytes()
+    }
+
+    fn deserialize(&self, bytes: &[u8]) -> Result<Envelope, FormatError> {
+        match bytes {
+            b"0 0" => Ok(Envelope {
+                orders: Vec::new(),
+                deleted: Vec::new(),
+            }),
+            _ => Err(FormatError::Malformed { format: "lines" }),
+        }
+    }
+}
+
+// Files in memory, gone with the process.
+#[derive(Default)]
+struct RamDisk(Mutex<BTreeMap<PathBuf, Vec<u8>>>);
+
+impl Fs for RamDisk {
+    fn read(&self, path: &Path) -> io::Result<Vec<u8>> {
+        let files = self.0.lock().unwrap();
+        files
+            .get(path)
+            .cloned()
+            .ok_or(io::ErrorKind::NotFound.into())
+    }
+
+    fn write(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .insert(path.to_path_buf(), bytes.to_vec());
+        Ok(())
+    }
+
+    fn append(&self, path: &Path, bytes: &[u8]) -> io::Result<()> {
+        let mut files = self.0.lock().unwrap();
+        files.entry(path.to_path_buf()).or_default().extend(bytes);
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> io::Result<()> {
+        let mut files = self.0.lock().unwrap();
+        let bytes = files.remove(from).ok_or(io::ErrorKind::NotFound)?;
+        files.insert(to.to_path_buf(), bytes);
+        Ok(())
+    }
+
+    fn create_dir_all(&self, _path: &Path) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+struct NoDatabase;
+
+impl SqlExecutor for NoDatabase {
+    fn execute(&self, _sql: &str, params: &[SqlValue]) -> Result<u64, OrderError> {
+        Ok(params.len() as u64)
+    }
+
+    fn query(&self, _sql: &str, _params: &[SqlValue]) -> Result<Rows, OrderError> {
+        Ok(vec![vec![SqlValue::Null]])
+    }
+}
+
+// --- Driving side, and the shop's own ports ---
+
+// The shop puts its own front on the service: writes go through it,
+// reads straight to the ledger.
+struct Shopfront<'a> {
+    service: OrderService<'a, Ledger, Till, Postcards>,
+    ledger: &'a Ledger,
+}
+
+impl PlaceOrderUseCase for Shopfront<'_> {
+    fn place_order(&mut self, command: PlaceOrder) -> Result<Order, OrderError> {
+        PlaceOrderUseCase::place_order(&mut self.service, command)
+    }
+}
+
+impl CancelOrderUseCase for Shopfront<'_> {
+    fn cancel_order(&mut self, id: OrderId) -> Result<Order, OrderError> {
+        self.service.cancel_order(id)
+    }
+}
+
+impl AmendOrderUseCase for Shopfront<'_> {
+    fn amend_order(&mut self, command: AmendOrder) -> Result<Order, OrderError> {
+        self.service.amend_order(command)
+    }
+}
+
+impl OrderQueries for Shopfront<'_> {
+    fn list_orders(&self) -> Result<Vec<Order>, OrderError> {
+        self.ledger.list()
+    }
+
+    fn revenue(&self) -> Result<Money, OrderError> {
+        let kept = self
+            .ledger
+            .list()?
+            .into_iter()
+            .filter(|order| matches!(order.status, OrderStatus::Paid | OrderStatus::Shipped));
+        Ok(Money(kept.map(|order| order.total.0).sum()))
+    }
+
+    fn health(&self) -> Result<(), OrderError> {
+        self.ledger.list().map(drop)
+    }
+}
+
+// And a till screen over the lot, for the staff's tablet.
+struct Kiosk<'a>(Mutex<Shopfront<'a>>);
+
+impl<'a> Kiosk<'a> {
+    fn shop(&self) -> MutexGuard<'_, Shopfront<'a>> {
+        self.0.lock().unwrap()
+    }
+}
+
+impl OrderFacade for Kiosk<'_> {
+    fn place_order(&self, command: PlaceOrder) -> Result<Order, OrderError> {
+        self.shop().place_order(command)
+    }
+
+    fn amend_order(&self, command: AmendOrder) -> Result<Order, OrderError> {
+        self.shop().amend_order(command)
+    }
+
+    fn cancel_order(&self, id: OrderId) -> Result<Order, OrderError> {
+        self.shop().cancel_order(id)
+    }
+
+    fn preview_order(&self, command: &PlaceOrder) -> Result<OrderPreview, OrderError> {
+        let order = Order::new(OrderId(0), command.items.clone())?;
+        Ok(OrderPreview {
+            item_count: order.items.len(),
+            total: order.total,
+            currency: order.currency,
+        })
+    }
+
+    fn list_orders(&self) -> Result<Vec<Order>, OrderError> {
+        self.shop().list_orders()
+    }
+
+    fn revenue(&self) -> Result<Money, OrderError> {
+        self.shop().revenue()
+    }
+
+    fn revenue_cancellable(&self, cancel: &CancelToken) -> Result<Money, OrderError> {
+        self.shop().revenue_cancellable(cancel)
+    }
+
+    fn revenue_by_currency(&self) -> Result<CurrencyTotals, OrderError> {
+        self.shop().revenue_by_currency()
+    }
+
+    fn freshness(&self) -> Result<Freshness, OrderError> {
+        self.shop().freshness()
+    }
+
+    fn health(&self) -> Result<(), OrderError> {
+        self.shop().health()
+    }
+}
+
+trait Loyalty {
+    #[allow(dead_code)] // only listed, never called
+    fn points(&self, order: &Order) -> u32;
+}
+
+impl PortInfo for dyn Loyalty {
+    fn port_name() -> &'static str {
+        "Loyalty"
+    }
+
+    fn direction() -> Direction {
+        Direction::Outbound
+    }
+
+    fn methods() -> &'static [&'static str] {
+        &["points"]
+    }
+}
+
+struct ShopPorts;
+
+impl PortSet for ShopPorts {
+    fn ports() -> Vec<(TypeId, &'static str)> {
+        vec![(TypeId::of::<dyn Loyalty>(), <dyn Loyalty>::port_name())]
+    }
+}
+
+// Hands out the shop's postcards to whoever names "postcards", and nothing
+// else.
+struct PostcardFactory;
+
+impl AdapterFactory for PostcardFactory {
+    fn make_sender(&self, _config: &AdapterConfig) -> Result<Box<dyn Sender + Sync>, ConfigError> {
+        Ok(Box::new(Postcards::default()))
+    }
+}
+
+// --- Using them ---
+
+#[test]
+fn a_service_runs_on_adapters_from_another_crate() {
+    let (ledger, till, postcards) = (Ledger::default(), Till::default(), Postcards::default());
+    let (journal, gauges, ids) = (Journal::default(), Gauges::default(), Counter::default());
+    let service = OrderService::new(&ledger, &till, &postcards)
+        .with_clock(&TownHallClock)
+        .with_id_generator(&ids)
+        .with_reference_generator(&ids)
+        .with_event_publisher(&journal)
+        .with_hooks(&gauges)
+        .with_approval_policy(&BigOrders)
+        .with_fraud_screen(&BigOrders);
+    let mut shop = Shopfront {
+        service,
+        ledger: &ledger,
+    };
+
+    let order = shop
+        .place_order(PlaceOrder {
+            items: vec![LineItem::new("Teapot", Money(2_400))],
+            placed_at: None,
+        })
+        .unwrap();
+
+    assert_eq!(order.status, OrderStatus::Paid);
+    assert_eq!(order.placed_at, Some(Timestamp::from_secs(1_700_000_000)));
+    let reference = OrderReference("TEA-1".to_string());
+    assert_eq!(order.reference.as_ref(), Some(&reference));
+    assert_eq!(
+        ledger.find_by_reference(&reference).unwrap(),
+        Some(order.clone())
+    );
+    assert_eq!(till.charges().unwrap()[0].amount, Money(2_400));
+    assert_eq!(postcards.0.lock().unwrap()[..], [order.id]);
+    assert_eq!(shop.revenue().unwrap(), Money(2_400));
+    assert_eq!(
+        gauges.0.lock().unwrap()[..],
+        [format!("saved {}", order.id)]
+    );
+    let mut tally = Tally::default();
+    for event in journal.0.lock().unwrap().iter() {
+        tally.on_event(event);
+    }
+    assert_eq!(tally.0, 2);
+    let amended = shop
+        .amend_order(AmendOrder {
+            order_id: order.id,
+            new_address: None,
+            gift_note: Some("Enjoy your tea".to_string()),
+        })
+        .unwrap();
+    assert_eq!(amended.gift_note.as_deref(), Some("Enjoy your tea"));
+    assert_eq!(
+        shop.cancel_order(order.id).unwrap().status,
+        OrderStatus::Cancelled
+    );
+    assert!(till.charges().unwrap().is_empty());
+
+    let kiosk = Kiosk(Mutex::new(shop));
+    let cart = PlaceOrder {
+        items: vec![LineItem::new("Tea cosy", Money(900))],
+        placed_at: None,
+    };
+    assert_eq!(kiosk.preview_order(&cart).unwrap().total, Money(900));
+    assert_eq!(kiosk.list_orders().unwrap().len(), 1);
+    kiosk.place_order(cart).unwrap();
+    assert_eq!(kiosk.revenue().unwrap(), Money(900));
+}
+
+#[test]
+fn an_adapter_may_fail_in_its_own_words() {
+    let error = Till::default()
+        .charge_for(OrderId(1), Money(200_000))
+        .unwrap_err();
+
+    assert!(matches!(
+        &error,
+        OrderError::Custom { message } if message == "the till only takes up to $1000.00"
+    ));
+    assert_eq!(
+        error.to_string(),
+        r#"Custom { message: "the till only takes up to $1000.00" }"#
+    );
+}
+
+// --- The audit ---
+
+// The traits no other crate can implement, each for a stated reason in
+// its declaration.
+const SEALED: &[&str] = &["ChargeConfirmed", "PlugsInto", "SendConfirmed"];
+
+fn rust_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    for entry in fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.is_dir() {
+            rust_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+// (name, sealed) of every `pub trait` declared at the top of a file.
+// Sealed means a supertrait from a private `sealed` module.
+fn public_traits(root: &Path) -> Vec<(String, bool)> {
+    let mut files = Vec::new();
+    rust_files(&root.join("src"), &mut files);
+    let mut traits = Vec::new();
+    for file in files {
+        for line in fs::read_to_string(file).unwrap().lines() {
+            let Some(rest) = line.strip_prefix("pub trait ") else {
+                continue;
+            };
+            let end = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            traits.push((rest[..end].to_string(), rest.contains(": sealed::")));
+        }
+    }
+    traits.sort();
+    traits
+}
+
+#[test]
+fn every_public_trait_is_implemented_here_or_sealed() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let this_file = fs::read_to_string(root.join("tests/external_impl.rs")).unwrap();
+    let implemented = |name: &str| {
+        this_file.lines().any(|line| {
+            line.starts_with("impl")
+                && (line.contains(&format!(" {name} for "))
+                    || line.contains(&format!("> {name} for ")))
+        })
+    };
+
+    let traits = public_traits(root);
+    let sealed: Vec<&str> = traits
+        .iter()
+        .filter(|(_, sealed)| *sealed)
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert_eq!(sealed, SEALED, "the sealed traits changed: update SEALED");
+    let missing: Vec<&str> = traits
+        .iter()
+        .filter(|(name, sealed)| !sealed && !implemented(name))
+        .map(|(name, _)| name.as_str())
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "not implemented from outside the crate, nor sealed: {missing:?}"
+    );
+}
+
+// The adapters the service above does not use, each taken as its port.
+#[test]
+fn the_other_adapters_plug_into_their_ports_too() {
+    fn port<T: ?Sized>(_: &T) {}
+    port::<dyn SenderV1>(&Fax);
+    port::<dyn DeadLetterSink>(&BackOffice::default());
+    port::<dyn ExchangeRates>(&Parity);
+    port::<dyn NotificationPolicy>(&BigOrders);
+    port::<dyn ConflictResolver>(&BigOrders);
+    port::<dyn ShippingProvider>(&Bicycle);
+    let rider = Rider {
+        phone: "0612345678".to_string(),
+    };
+    port::<dyn Redact>(&rider);
+    assert_eq!(rider.redacted(), "rider ***78");
+    port::<dyn WarehousePicker>(&BackRoom);
+    port::<dyn RetentionPolicy>(&ShortMemory);
+    port::<dyn StateStore>(&Drawer::default());
+    port::<dyn PendingCharges>(&Drawer::default());
+    port::<dyn SagaLog>(&Drawer::default());
+    port::<dyn IdempotencyStore>(&Drawer::default());
+    let drawer = Drawer::default();
+    let blob = drawer.put(b"receipt").unwrap();
+    assert_eq!(drawer.get(blob).unwrap().as_deref(), Some(&b"receipt"[..]));
+    port::<dyn ErrorReporter>(&Gauges::default());
+    port::<dyn Logger>(&Gauges::default());
+    port::<dyn StorageFormat>(&OneLinePerOrder);
+    port::<dyn SqlExecutor>(&NoDatabase);
+
+    let journaled =
+        JournaledRepository::open_on("/shop", OneLinePerOrder, RamDisk::default()).unwrap();
+    let state = ServiceState {
+        next_id: 7,
+        open_drafts: Vec::new(),
+    };
+    journaled.save_state(&state).unwrap();
+    assert_eq!(journaled.load_state().unwrap(), Some(state));
+    let sequence = journaled.fs().read(Path::new("/shop/sequence"));
+    assert_eq!(sequence.unwrap(), b"7\n");
+
+    let cupboard = Cupboard::default();
+    cupboard.restock("Mug", 1).unwrap();
+    cupboard
+        .reserve(ReservationId(1), &[LineItem::new("Mug", Money(900))])
+        .unwrap();
+    assert_eq!(cupboard.stock_level("Mug").unwrap(), 0);
+
+    let mut notebook = Notebook::default();
+    notebook
+        .store(StoredOrder::Draft(OrderDraft::new(OrderId(1))))
+        .unwrap();
+    assert!(notebook.load(OrderId(1)).unwrap().is_some());
+
+    let mut tray = Tray::default();
+    tray.as_transactional().unwrap().begin();
+    tray.as_flushable().unwrap().flush().unwrap();
+    tray.shutdown().unwrap();
+
+    assert_eq!(ShopPorts::ports()[0].1, "Loyalty");
+    let config = AdapterConfig {
+        var: "HEXLITE_SENDER",
+        name: "postcards".to_string(),
+        options: Vec::new(),
+    };
+    assert!(PostcardFactory.make_sender(&config).is_ok());
+    assert!(PostcardFactory.make_payment(&config).is_err());
+}
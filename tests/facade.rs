@@ -0,0 +1,120 @@
+// cargo test --test facade
+// The whole service behind one OrderFacade, as a GUI holds it: every
+// command and query through &self, from a Box<dyn OrderFacade> or from an
+// Arc shared between threads. A preview checks the cart and keeps nothing.
+use hexa_lite::adapters::Console;
+use hexa_lite::application::OrderBrowser;
+use hexa_lite::composition::{EnvConfig, SenderConfig, build_adapters, build_facade};
+use hexa_lite::domain::Currency;
+use hexa_lite::facade::DefaultFacade;
+use hexa_lite::ports::{AmendOrder, CancelToken, ChargeLog, OrderFacade};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::thread;
+
+fn cart(price: u32) -> PlaceOrder {
+    PlaceOrder {
+        items: vec![LineItem::new("Keyboard", Money(price))],
+        placed_at: None,
+    }
+}
+
+#[test]
+fn every_command_and_query_goes_through_the_facade() {
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let service = OrderService::new(&repo, &payment, &sender);
+    let facade: Box<dyn OrderFacade + '_> =
+        Box::new(DefaultFacade::new(service, OrderBrowser::new(&repo)));
+
+    let first = facade.place_order(cart(12999)).unwrap();
+    let second = facade.place_order(cart(2500)).unwrap();
+    let amended = facade
+        .amend_order(AmendOrder {
+            order_id: first.id,
+            new_address: None,
+            gift_note: Some("For the office".to_string()),
+        })
+        .unwrap();
+    assert_eq!(amended.gift_note.as_deref(), Some("For the office"));
+    assert_eq!(
+        facade.cancel_order(second.id).unwrap().status,
+        OrderStatus::Cancelled
+    );
+
+    assert_eq!(facade.list_orders().unwrap().len(), 2);
+    assert_eq!(facade.revenue().unwrap(), Money(12999));
+    assert_eq!(
+        facade.revenue_cancellable(&CancelToken::new()).unwrap(),
+        Money(12999)
+    );
+    assert_eq!(
+        facade.revenue_by_currency().unwrap().get(Currency::Usd),
+        Money(12999)
+    );
+    assert!(facade.freshness().unwrap().is_current());
+    assert!(facade.health().is_ok());
+}
+
+#[test]
+fn a_preview_checks_the_cart_and_saves_nothing() {
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let service = OrderService::new(&repo, &payment, &sender);
+    let facade = DefaultFacade::new(service, OrderBrowser::new(&repo));
+    let mut cart = cart(12999);
+    cart.items.push(LineItem::new("USB cable", Money(999)));
+
+    let preview = facade.preview_order(&cart).unwrap();
+
+    assert_eq!(preview.item_count, 2);
+    assert_eq!(preview.total, Money(13998));
+    assert_eq!(preview.currency, Currency::Usd);
+    assert_err_variant!(
+        facade.preview_order(&PlaceOrder {
+            items: Vec::new(),
+            placed_at: None,
+        }),
+        OrderError::InvalidOrder
+    );
+    assert!(repo.list().unwrap().is_empty());
+    assert!(payment.charges().unwrap().is_empty());
+    // The next order placed still gets the first id.
+    assert_eq!(facade.place_order(cart).unwrap().id, OrderId(1));
+}
+
+#[test]
+fn the_composed_facade_is_shared_between_threads() {
+    let adapters = build_adapters(&EnvConfig {
+        sender: SenderConfig::Console,
+        event_log: None,
+        repository: None,
+        payment: None,
+    })
+    .unwrap();
+    let facade = build_facade(&adapters);
+
+    thread::scope(|scope| {
+        for _ in 0..4 {
+            let facade = facade.clone();
+            scope.spawn(move || {
+                for _ in 0..5 {
+                    facade.place_order(cart(1000)).unwrap();
+                    facade.revenue().unwrap();
+                }
+            });
+        }
+    });
+
+    let mut ids: Vec<u32> = facade
+        .list_orders()
+        .unwrap()
+        .iter()
+        .map(|order| order.id.0)
+        .collect();
+    ids.sort();
+    assert_eq!(ids, (1..=20).collect::<Vec<_>>());
+    assert_eq!(facade.revenue().unwrap(), Money(20_000));
+}
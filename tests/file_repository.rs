@@ -0,0 +1,224 @@
+// cargo test --test file_repository
+// The same repository contract in each storage format: orders, deleted ones
+// included, come back from the file exactly as they were saved. A file is
+// only ever read in the format it was written in: handed to another one, it
+// is refused with a reason, never half-read.
+#![cfg(all(feature = "json", feature = "ron", feature = "msgpack"))]
+use hexa_lite::adapters::ConfigError;
+use hexa_lite::adapters::file_repository::{
+    Envelope, FileOrderRepository, FormatError, JsonFormat, MsgPackFormat, RonFormat, StorageFormat,
+};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::{assert_orders_eq, diff_orders};
+use std::fs;
+use std::path::PathBuf;
+
+// One file per test, removed even on failure.
+struct TempFile(PathBuf);
+
+impl TempFile {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("hexa_lite_{}_{name}.orders", std::process::id()));
+        let _ = fs::remove_file(&path);
+        TempFile(path)
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn order(id: u32, name: &str) -> Order {
+    Order::new(
+        OrderId(id),
+        vec![
+            LineItem {
+                name: name.to_string(),
+                price: Money(4999),
+            },
+            LineItem {
+                name: "Keyboard".to_string(),
+                price: Money(12999),
+            },
+        ],
+    )
+    .unwrap()
+}
+
+const FORMATS: [&dyn StorageFormat; 3] = [&JsonFormat, &RonFormat, &MsgPackFormat];
+
+fn orders_survive_a_restart<F: StorageFormat>(format: impl Fn() -> F) {
+    let name = format().name();
+    let file = TempFile::new(&format!("restart_{name}"));
+    let mut paid = order(3, "Rust \"Book\"\n2nd edition");
+    paid.mark_paid().unwrap();
+    paid.placed_at = Some(Timestamp(1_700_000_000));
+    {
+        let repo = FileOrderRepository::open(&file.0, format()).unwrap();
+        repo.save(&paid).unwrap();
+        repo.save(&order(1, "Pen")).unwrap();
+        repo.save(&order(2, "Ink")).unwrap();
+        repo.soft_delete(OrderId(2)).unwrap();
+    }
+
+    let repo = FileOrderRepository::open(&file.0, format()).unwrap();
+    let ids: Vec<u32> = repo.list().unwrap().iter().map(|o| o.id.0).collect();
+    assert_eq!(ids, [1, 3], "{name}");
+    let found = Vec::from_iter(repo.find(OrderId(3)).unwrap());
+    let diff = diff_orders(&[paid], &found);
+    assert!(diff.is_empty(), "{name}: {diff}");
+    let diff = diff_orders(&[order(2, "Ink")], &repo.list_deleted().unwrap());
+    assert!(diff.is_empty(), "{name}: {diff}");
+    assert_eq!(repo.find(OrderId(2)).unwrap(), None, "{name}");
+}
+
+#[test]
+fn orders_survive_a_restart_in_json() {
+    orders_survive_a_restart(|| JsonFormat);
+}
+
+#[test]
+fn orders_survive_a_restart_in_ron() {
+    orders_survive_a_restart(|| RonFormat);
+}
+
+#[test]
+fn orders_survive_a_restart_in_msgpack() {
+    orders_survive_a_restart(|| MsgPackFormat);
+}
+
+#[test]
+fn a_missing_file_is_an_empty_repository() {
+    let file = TempFile::new("missing");
+    let repo = FileOrderRepository::open(&file.0, MsgPackFormat).unwrap();
+    assert!(repo.list().unwrap().is_empty());
+    assert!(
+        !file.0.exists(),
+        "nothing is written before the first change"
+    );
+
+    repo.save(&order(1, "Pen")).unwrap();
+    assert!(file.0.exists());
+}
+
+#[test]
+fn a_file_is_refused_by_every_other_format() {
+    let envelope = Envelope {
+        orders: vec![order(1, "Pen")],
+        deleted: vec![],
+    };
+    for writer in FORMATS {
+        let bytes = writer.serialize(&envelope);
+        for reader in FORMATS {
+            let (written, read) = (writer.name(), reader.name());
+            let result = reader.deserialize(&bytes);
+            if written == read {
+                assert_eq!(result.as_ref(), Ok(&envelope), "{written}");
+            } else {
+                assert_eq!(
+                    result,
+                    Err(FormatError::Malformed { format: read }),
+                    "{written} bytes read as {read}"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn a_file_in_the_wrong_format_does_not_open() {
+    let file = TempFile::new("wrong_format");
+    let repo = FileOrderRepository::open(&file.0, JsonFormat).unwrap();
+    repo.save(&order(1, "Pen")).unwrap();
+
+    let opened = FileOrderRepository::open(&file.0, MsgPackFormat);
+
+    assert!(matches!(
+        opened,
+        Err(ConfigError::InvalidFile { ref reason, .. }) if reason == "not a MessagePack document"
+    ));
+    assert!(
+        fs::read_to_string(&file.0).unwrap().contains("\"Pen\""),
+        "the file is left as it was"
+    );
+}
+
+#[test]
+fn a_file_from_a_newer_version_does_not_open() {
+    let file = TempFile::new("newer_version");
+    fs::write(&file.0, r#"{"version":6,"orders":[],"deleted":[]}"#).unwrap();
+
+    let opened = FileOrderRepository::open(&file.0, JsonFormat);
+
+    assert!(matches!(
+        opened,
+        Err(ConfigError::InvalidFile { ref reason, .. })
+            if reason == "version 6 is newer than this program, which reads up to 5"
+    ));
+}
+
+#[test]
+fn deleting_and_restoring_follow_the_repository_contract() {
+    let file = TempFile::new("delete_restore");
+    let repo = FileOrderRepository::open(&file.0, RonFormat).unwrap();
+    repo.save(&order(1, "Pen")).unwrap();
+
+    repo.soft_delete(OrderId(1)).unwrap();
+    repo.soft_delete(OrderId(1)).unwrap();
+    assert!(matches!(
+        repo.soft_delete(OrderId(9)),
+        Err(OrderError::NotFound { .. })
+    ));
+    repo.restore(OrderId(1)).unwrap();
+    assert!(matches!(
+        repo.restore(OrderId(1)),
+        Err(OrderError::NotDeleted { .. })
+    ));
+
+    let reopened = FileOrderRepository::open(&file.0, RonFormat).unwrap();
+    assert_orders_eq(
+        &[order(1, "Pen")],
+        &Vec::from_iter(reopened.find(OrderId(1)).unwrap()),
+    );
+    assert!(reopened.list_deleted().unwrap().is_empty());
+}
+
+#[test]
+fn a_purged_order_is_gone_from_the_file_deleted_or_not() {
+    let file = TempFile::new("purge");
+    let repo = FileOrderRepository::open(&file.0, JsonFormat).unwrap();
+    repo.save(&order(1, "Pen")).unwrap();
+    repo.save(&order(2, "Ink")).unwrap();
+    repo.save(&order(3, "Pad")).unwrap();
+    repo.soft_delete(OrderId(2)).unwrap();
+
+    repo.purge(OrderId(1)).unwrap();
+    repo.purge(OrderId(2)).unwrap();
+    assert!(matches!(
+        repo.purge(OrderId(2)),
+        Err(OrderError::NotFound { .. })
+    ));
+
+    let reopened = FileOrderRepository::open(&file.0, JsonFormat).unwrap();
+    assert_orders_eq(&[order(3, "Pad")], &reopened.list().unwrap());
+    assert!(reopened.list_deleted().unwrap().is_empty());
+}
+
+#[test]
+fn a_failed_write_changes_nothing() {
+    let directory = TempFile::new("unwritable");
+    let _ = fs::remove_dir(&directory.0);
+    fs::create_dir(&directory.0).unwrap();
+    let file = directory.0.join("missing").join("orders.json");
+    let repo = FileOrderRepository::open(&file, JsonFormat).unwrap();
+
+    assert!(matches!(
+        repo.save(&order(1, "Pen")),
+        Err(OrderError::StorageFailed)
+    ));
+    assert_eq!(repo.find(OrderId(1)).unwrap(), None);
+    fs::remove_dir(&directory.0).unwrap();
+}
@@ -0,0 +1,150 @@
+// cargo test --test fraud_screening
+// Every order meets the fraud screen before the payment gateway: accepted,
+// it goes on as usual; rejected, it is refused and nothing is charged or
+// stored; doubtful, it waits UnderReview, uncharged, until resolve_review.
+use hexa_lite::adapters::fraud::{RuleBasedScreen, SpyFraudScreen};
+use hexa_lite::domain::{Customer, OrderEvent, ReviewDecision};
+use hexa_lite::ports::{ChargeLog, EventPublisher, RiskVerdict};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::sync::Mutex;
+
+#[derive(Default)]
+struct Journal(Mutex<Vec<OrderEvent>>);
+
+impl EventPublisher for Journal {
+    fn publish(&self, event: &OrderEvent) -> Result<(), OrderError> {
+        self.0.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+}
+
+fn cart() -> Vec<LineItem> {
+    vec![LineItem::new("Headphones", Money(19_900))]
+}
+
+fn alice() -> Customer {
+    Customer::new("Alice")
+}
+
+#[test]
+fn an_accepted_order_is_charged_as_usual() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let screen = SpyFraudScreen::new(RiskVerdict::Accept);
+    let mut service = OrderService::new(&repo, &payment, &sender).with_fraud_screen(&screen);
+
+    let order = service.place_order_for(&alice(), cart()).unwrap();
+
+    assert_order(&order).has_status(OrderStatus::Paid);
+    assert_eq!(payment.charges().unwrap().len(), 1);
+    assert_eq!(screen.assessed(), [(order.id, alice())]);
+}
+
+#[test]
+fn a_rejected_order_never_reaches_the_payment_gateway() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let screen = RuleBasedScreen::new().with_denied_name("Mallory");
+    let mut service = OrderService::new(&repo, &payment, &sender).with_fraud_screen(&screen);
+
+    let refused = service.place_order_for(&Customer::new("Mallory"), cart());
+
+    assert!(matches!(
+        refused,
+        Err(OrderError::FraudSuspected { reason }) if reason == "Mallory is denylisted"
+    ));
+    assert!(payment.charges().unwrap().is_empty());
+    assert!(repo.list().unwrap().is_empty());
+    // Whoever else buys is not affected.
+    let order = service.place_order_for(&alice(), cart()).unwrap();
+    assert_order(&order).has_status(OrderStatus::Paid);
+}
+
+#[test]
+fn a_doubtful_order_waits_uncharged() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let screen = SpyFraudScreen::new(RiskVerdict::Review);
+    let journal = Journal::default();
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_fraud_screen(&screen)
+        .with_event_publisher(&journal);
+
+    let order = service.place_order(cart()).unwrap();
+
+    assert_order(&order).has_status(OrderStatus::UnderReview);
+    assert_eq!(repo.find(order.id).unwrap(), Some(order.clone()));
+    assert!(payment.charges().unwrap().is_empty());
+    // No customer given: the screen weighs up a guest.
+    assert_eq!(screen.assessed(), [(order.id, Customer::guest())]);
+    assert_eq!(
+        journal.0.lock().unwrap()[1],
+        OrderEvent::ReviewRequested {
+            order_id: order.id,
+            total: Money(19_900),
+        }
+    );
+}
+
+#[test]
+fn an_accepted_review_charges_the_order() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let screen = SpyFraudScreen::new(RiskVerdict::Review);
+    let mut service = OrderService::new(&repo, &payment, &sender).with_fraud_screen(&screen);
+    let order = service.place_order_for(&alice(), cart()).unwrap();
+
+    let resolved = service
+        .resolve_review(order.id, ReviewDecision::Accept)
+        .unwrap();
+
+    assert_order(&resolved).has_status(OrderStatus::Paid);
+    assert_eq!(repo.find(order.id).unwrap(), Some(resolved));
+    let charged: Vec<Money> = payment
+        .charges()
+        .unwrap()
+        .iter()
+        .map(|charge| charge.amount)
+        .collect();
+    assert_eq!(charged, [Money(19_900)]);
+    // Resolved once only.
+    assert_err_variant!(
+        service.resolve_review(order.id, ReviewDecision::Accept),
+        OrderError::NotUnderReview {
+            status: OrderStatus::Paid,
+            ..
+        }
+    );
+}
+
+#[test]
+fn a_rejected_review_cancels_the_order_uncharged() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let screen = SpyFraudScreen::new(RiskVerdict::Review);
+    let journal = Journal::default();
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_fraud_screen(&screen)
+        .with_event_publisher(&journal);
+    let order = service.place_order_for(&alice(), cart()).unwrap();
+
+    let reason = "card reported stolen".to_string();
+    let resolved = service
+        .resolve_review(order.id, ReviewDecision::Reject { reason })
+        .unwrap();
+
+    assert_order(&resolved).has_status(OrderStatus::Cancelled);
+    assert!(payment.charges().unwrap().is_empty());
+    assert_eq!(
+        journal.0.lock().unwrap().last(),
+        Some(&OrderEvent::OrderRejected {
+            order_id: order.id,
+            reason: "card reported stolen".to_string(),
+        })
+    );
+    assert_err_variant!(
+        service.resolve_review(OrderId(99), ReviewDecision::Accept),
+        OrderError::NotFound { .. }
+    );
+}
@@ -0,0 +1,246 @@
+// cargo test --test generated_ports
+// The ports build.rs generates from ports.toml, against the handwritten
+// ones they describe. Each side is implemented for the other, method for
+// method: a parameter or a return type that drifts apart in either stops
+// this file compiling. Then the generated Spy and Noop at work.
+use hexa_lite::adapters::Console;
+use hexa_lite::domain::{Notice, OrderConfirmation, OrderKey, OrderReference};
+use hexa_lite::ports::generated::{self, Noop, Spy};
+use hexa_lite::ports::{self, CancelToken, ChargeOutcome};
+use hexa_lite::prelude::*;
+
+// A handwritten adapter, seen through a generated port.
+struct AsGenerated<T>(T);
+
+// A generated adapter, seen through a handwritten port.
+struct AsHandwritten<T>(T);
+
+impl<T: ports::OrderReader> generated::OrderReader for AsGenerated<T> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.0.find(id)
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.0.list()
+    }
+
+    fn exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        self.0.exists(id)
+    }
+
+    fn total_of(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        self.0.total_of(id)
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        self.0.for_each(visit)
+    }
+
+    fn for_each_cancellable(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        self.0.for_each_cancellable(cancel, visit)
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        self.0.list_deleted()
+    }
+
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.0.find_by_key(key)
+    }
+
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        self.0.find_by_reference(reference)
+    }
+}
+
+impl<T: ports::OrderWriter> generated::OrderWriter for AsGenerated<T> {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.0.save(order)
+    }
+
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        self.0.update(order)
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.0.soft_delete(id)
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.0.restore(id)
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.0.purge(id)
+    }
+}
+
+impl<T: ports::PaymentGateway> generated::PaymentGateway for AsGenerated<T> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.0.charge(amount)
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.0.charge_for(order_id, amount)
+    }
+
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.0.charge_order(order_id, amount)
+    }
+
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.0.refund_for(order_id, amount)
+    }
+}
+
+impl<T: ports::Sender> generated::Sender for AsGenerated<T> {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.0.send(confirmation)
+    }
+
+    fn send_notice(&self, notice: &Notice) -> Result<(), OrderError> {
+        self.0.send_notice(notice)
+    }
+}
+
+// Every method, defaults included, so that each signature is compared.
+impl<T: generated::OrderReader> ports::OrderReader for AsHandwritten<T> {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.0.find(id)
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.0.list()
+    }
+
+    fn exists(&self, id: OrderId) -> Result<bool, OrderError> {
+        self.0.exists(id)
+    }
+
+    fn total_of(&self, id: OrderId) -> Result<Option<Money>, OrderError> {
+        self.0.total_of(id)
+    }
+
+    fn for_each(&self, visit: &mut dyn FnMut(&Order)) -> Result<(), OrderError> {
+        self.0.for_each(visit)
+    }
+
+    fn for_each_cancellable(
+        &self,
+        cancel: &CancelToken,
+        visit: &mut dyn FnMut(&Order),
+    ) -> Result<(), OrderError> {
+        self.0.for_each_cancellable(cancel, visit)
+    }
+
+    fn list_deleted(&self) -> Result<Vec<Order>, OrderError> {
+        self.0.list_deleted()
+    }
+
+    fn find_by_key(&self, key: OrderKey) -> Result<Option<Order>, OrderError> {
+        self.0.find_by_key(key)
+    }
+
+    fn find_by_reference(&self, reference: &OrderReference) -> Result<Option<Order>, OrderError> {
+        self.0.find_by_reference(reference)
+    }
+}
+
+impl<T: generated::OrderWriter> ports::OrderWriter for AsHandwritten<T> {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.0.save(order)
+    }
+
+    fn update(&self, order: &Order) -> Result<(), OrderError> {
+        self.0.update(order)
+    }
+
+    fn soft_delete(&self, id: OrderId) -> Result<(), OrderError> {
+        self.0.soft_delete(id)
+    }
+
+    fn restore(&self, id: OrderId) -> Result<(), OrderError> {
+        self.0.restore(id)
+    }
+
+    fn purge(&self, id: OrderId) -> Result<(), OrderError> {
+        self.0.purge(id)
+    }
+}
+
+impl<T: generated::PaymentGateway> ports::PaymentGateway for AsHandwritten<T> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.0.charge(amount)
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.0.charge_for(order_id, amount)
+    }
+
+    fn charge_order(&self, order_id: OrderId, amount: Money) -> Result<ChargeOutcome, OrderError> {
+        self.0.charge_order(order_id, amount)
+    }
+
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.0.refund_for(order_id, amount)
+    }
+}
+
+impl<T: generated::Sender> ports::Sender for AsHandwritten<T> {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        self.0.send(confirmation)
+    }
+
+    fn send_notice(&self, notice: &Notice) -> Result<(), OrderError> {
+        self.0.send_notice(notice)
+    }
+}
+
+fn spied<T>(adapter: T) -> AsHandwritten<Spy<AsGenerated<T>>> {
+    AsHandwritten(Spy::new(AsGenerated(adapter)))
+}
+
+#[test]
+fn spies_note_every_call_on_the_way_through() {
+    let repo = spied(InMemoryOrderRepository::new().with_console(Console::silent()));
+    let payment = spied(MockPaymentGateway::new().with_console(Console::silent()));
+    let sender = spied(ConsoleSender::new().with_console(Console::silent()));
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    let order = service
+        .place_order(vec![LineItem::new("Pen", Money(150))])
+        .unwrap();
+
+    assert_eq!(payment.0.calls(), ["charge_order(OrderId(1), Money(150))"]);
+    assert_eq!(repo.0.calls(), [format!("save({order:?})")]);
+    assert_eq!(sender.0.calls().len(), 1);
+    assert!(sender.0.calls()[0].starts_with("send(OrderConfirmation { order_id: OrderId(1)"));
+    // What went through reached the adapter behind.
+    assert_eq!(repo.0.inner().0.list().unwrap(), [order]);
+    let mut visited = 0;
+    ports::OrderReader::for_each(&repo, &mut |_| visited += 1).unwrap();
+    assert_eq!(visited, 1);
+    assert_eq!(repo.0.calls().last().unwrap(), "for_each(..)");
+}
+
+#[test]
+fn noop_accepts_everything_and_stores_nothing() {
+    let noop = AsHandwritten(Noop);
+    let mut service = OrderService::new(&noop, &noop, &noop);
+
+    let order = service
+        .place_order(vec![LineItem::new("Pen", Money(150))])
+        .unwrap();
+
+    assert_eq!(order.status, OrderStatus::Paid);
+    assert_eq!(ports::OrderReader::find(&noop, order.id).unwrap(), None);
+    assert!(ports::OrderReader::list(&noop).unwrap().is_empty());
+    assert_eq!(
+        generated::PaymentGateway::charge_order(&Noop, OrderId(1), Money(150)).unwrap(),
+        ChargeOutcome::Charged
+    );
+}
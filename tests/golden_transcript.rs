@@ -0,0 +1,114 @@
+// cargo test --test golden_transcript
+// The standard demo, run against both sets of adapters, with everything the
+// adapters print captured and compared to tests/golden/. When the output
+// changes on purpose:
+//
+//     UPDATE_GOLDEN=1 cargo test --test golden_transcript
+use hexa_lite::adapters::SecretString;
+use hexa_lite::adapters::error_reporting::ConsoleErrorReporter;
+use hexa_lite::adapters::external::{SendGridSender, StripePaymentGateway};
+use hexa_lite::adapters::sql::{FakeExecutor, SqlOrderRepository};
+use hexa_lite::domain::Timestamp;
+use hexa_lite::ports::{OrderWriter, PaymentGateway, Sender};
+use hexa_lite::prelude::testing::{ScenarioTranscript, SteppingClock, assert_err_variant};
+use hexa_lite::prelude::*;
+
+// Three orders and one failure: the cart that arrives empty.
+fn demo<R, P, S>(transcript: &ScenarioTranscript, repo: &R, payment: &P, sender: &S)
+where
+    R: OrderWriter,
+    P: PaymentGateway + Sync,
+    S: Sender + Sync,
+{
+    let reporter = ConsoleErrorReporter::new().with_console(transcript.console());
+    let clock = SteppingClock::starting_at(Timestamp(1_700_000_000));
+    let mut service =
+        OrderService::new(repo, payment, sender).with_error_reporter(&reporter, &clock);
+
+    transcript.section("A book");
+    service
+        .place_order(vec![LineItem {
+            name: "Rust Book".to_string(),
+            price: Money(4999),
+        }])
+        .unwrap();
+
+    transcript.section("A keyboard and a mouse");
+    service
+        .place_order(vec![
+            LineItem {
+                name: "Keyboard".to_string(),
+                price: Money(12999),
+            },
+            LineItem {
+                name: "Mouse".to_string(),
+                price: Money(2500),
+            },
+        ])
+        .unwrap();
+
+    transcript.section("An empty cart");
+    clock.advance_secs(60);
+    assert_err_variant!(service.place_order(vec![]), OrderError::InvalidOrder);
+
+    transcript.section("A monitor");
+    service
+        .place_order(vec![LineItem {
+            name: "Monitor".to_string(),
+            price: Money(18900),
+        }])
+        .unwrap();
+
+    transcript.section("Looking order 2 up");
+    let order = service.get_order(OrderId(2)).unwrap().unwrap();
+    transcript.note(&format!("  order {} totals {}", order.id, order.total));
+}
+
+#[test]
+fn in_memory_demo_matches_its_golden_file() {
+    let transcript = ScenarioTranscript::new();
+    let repo = InMemoryOrderRepository::new().with_console(transcript.console());
+    let payment = MockPaymentGateway::new().with_console(transcript.console());
+    let sender = ConsoleSender::new().with_console(transcript.console());
+
+    demo(&transcript, &repo, &payment, &sender);
+
+    transcript.assert_matches_golden("tests/golden/demo_in_memory.txt");
+}
+
+#[test]
+fn external_demo_matches_its_golden_file() {
+    let transcript = ScenarioTranscript::new();
+    let repo = SqlOrderRepository::new(FakeExecutor::new())
+        .unwrap()
+        .with_console(transcript.console());
+    let payment = StripePaymentGateway::new().with_console(transcript.console());
+    let sender = SendGridSender::new(SecretString::new("SG.demo-key"))
+        .unwrap()
+        .with_console(transcript.console());
+
+    demo(&transcript, &repo, &payment, &sender);
+
+    transcript.assert_matches_golden("tests/golden/demo_external.txt");
+}
+
+#[test]
+fn every_adapter_line_lands_in_the_transcript() {
+    let transcript = ScenarioTranscript::new();
+    let repo = InMemoryOrderRepository::new().with_console(transcript.console());
+    let payment = MockPaymentGateway::new().with_console(transcript.console());
+    let sender = ConsoleSender::new().with_console(transcript.console());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    service
+        .place_order(vec![LineItem {
+            name: "Mouse".to_string(),
+            price: Money(2500),
+        }])
+        .unwrap();
+
+    let text = transcript.text();
+    for adapter in ["[InMemory]", "[MockPayment]", "[Console]"] {
+        assert!(text.contains(adapter), "{adapter} missing from:\n{text}");
+    }
+}
@@ -0,0 +1,206 @@
+// cargo test --test hexagon_diagram
+// The ports describe themselves, the Wiring says what is plugged into them:
+// together they draw the hexagon. The standard wiring's diagram is pinned:
+// when a port changes, this is the picture that changes with it.
+use hexa_lite::composition::{EnvConfig, SenderConfig, Wiring, build_adapters, hexagon_diagram};
+use hexa_lite::domain::Timestamp;
+use hexa_lite::ports::{
+    Clock, Direction, OrderReader, OrderWriter, PlaceOrderUseCase, PortInfo, PortSpec, all_ports,
+};
+
+const STANDARD: &str = r#"flowchart LR
+    subgraph hexagon [Hexagon]
+        subgraph inbound [Inbound ports]
+            port_PlaceOrderUseCase["PlaceOrderUseCase<br/>place_order"]
+            port_CancelOrderUseCase["CancelOrderUseCase<br/>cancel_order"]
+            port_AmendOrderUseCase["AmendOrderUseCase<br/>amend_order"]
+            port_OrderQueries["OrderQueries<br/>list_orders, revenue, health, revenue_cancellable, revenue_by_currency, freshness"]
+            port_OrderFacade["OrderFacade<br/>place_order, amend_order, cancel_order, preview_order, list_orders, revenue, revenue_cancellable, revenue_by_currency, freshness, health"]
+        end
+        domain{{"Domain"}}
+        subgraph outbound [Outbound ports]
+            port_OrderReader["OrderReader<br/>find, list, exists, total_of, for_each, for_each_cancellable, list_deleted, find_by_key, find_by_reference"]
+            port_OrderWriter["OrderWriter<br/>save, update, soft_delete, restore, purge"]
+            port_PaymentGateway["PaymentGateway<br/>charge, charge_for, charge_order, refund_for"]
+            port_ChargeLog["ChargeLog<br/>charges"]
+            port_ExchangeRates["ExchangeRates<br/>convert"]
+            port_Sender["Sender<br/>send, send_notice"]
+            port_DraftRepository["DraftRepository<br/>store, load"]
+            port_ShippingProvider["ShippingProvider<br/>ship"]
+            port_Inventory["Inventory<br/>reserve, confirm, release, restock, stock_level, sources, release_expired"]
+            port_WarehousePicker["WarehousePicker<br/>pick"]
+            port_ApprovalPolicy["ApprovalPolicy<br/>requires_approval"]
+            port_NotificationPolicy["NotificationPolicy<br/>should_confirm"]
+            port_FraudScreen["FraudScreen<br/>assess"]
+            port_ConflictResolver["ConflictResolver<br/>resolve"]
+            port_RetentionPolicy["RetentionPolicy<br/>should_purge"]
+            port_Clock["Clock<br/>now"]
+            port_IdGenerator["IdGenerator<br/>next_uuid"]
+            port_ReferenceGenerator["ReferenceGenerator<br/>next_reference"]
+            port_EventPublisher["EventPublisher<br/>publish, publish_inventory"]
+            port_EventSubscriber["EventSubscriber<br/>on_event"]
+            port_StateStore["StateStore<br/>save_state, load_state"]
+            port_PendingCharges["PendingCharges<br/>record, pending, remove"]
+            port_DeadLetterSink["DeadLetterSink<br/>dead_letter"]
+            port_SagaLog["SagaLog<br/>append, entries"]
+            port_IdempotencyStore["IdempotencyStore<br/>find, remember, purge_older_than, forget_order"]
+            port_BlobStore["BlobStore<br/>put, get, delete"]
+            port_Metrics["Metrics<br/>increment, observe_histogram"]
+            port_ErrorReporter["ErrorReporter<br/>report"]
+            port_Logger["Logger<br/>log"]
+        end
+    end
+    subgraph driven [Driven adapters]
+        adapter_InMemoryOrderRepository(["InMemoryOrderRepository"])
+        adapter_MockPaymentGateway(["MockPaymentGateway"])
+        adapter_ConsoleSender(["ConsoleSender"])
+    end
+    port_PlaceOrderUseCase --> domain
+    port_CancelOrderUseCase --> domain
+    port_AmendOrderUseCase --> domain
+    port_OrderQueries --> domain
+    port_OrderFacade --> domain
+    domain --> port_OrderReader
+    port_OrderReader --> adapter_InMemoryOrderRepository
+    domain --> port_OrderWriter
+    port_OrderWriter --> adapter_InMemoryOrderRepository
+    domain --> port_PaymentGateway
+    port_PaymentGateway --> adapter_MockPaymentGateway
+    domain --> port_ChargeLog
+    domain --> port_ExchangeRates
+    domain --> port_Sender
+    port_Sender --> adapter_ConsoleSender
+    domain --> port_DraftRepository
+    domain --> port_ShippingProvider
+    domain --> port_Inventory
+    domain --> port_WarehousePicker
+    domain --> port_ApprovalPolicy
+    domain --> port_NotificationPolicy
+    domain --> port_FraudScreen
+    domain --> port_ConflictResolver
+    domain --> port_RetentionPolicy
+    domain --> port_Clock
+    domain --> port_IdGenerator
+    domain --> port_ReferenceGenerator
+    domain --> port_EventPublisher
+    domain --> port_EventSubscriber
+    domain --> port_StateStore
+    domain --> port_PendingCharges
+    domain --> port_DeadLetterSink
+    domain --> port_SagaLog
+    domain --> port_IdempotencyStore
+    domain --> port_BlobStore
+    domain --> port_Metrics
+    domain --> port_ErrorReporter
+    domain --> port_Logger"#;
+
+fn standard_wiring() -> Wiring {
+    let config = EnvConfig {
+        sender: SenderConfig::Console,
+        event_log: None,
+        repository: None,
+        payment: None,
+    };
+    build_adapters(&config).unwrap().wiring
+}
+
+// Index of the first line of `diagram` containing `text`.
+fn line_of(diagram: &str, text: &str) -> usize {
+    diagram
+        .lines()
+        .position(|line| line.contains(text))
+        .unwrap_or_else(|| panic!("{text} is not in the diagram"))
+}
+
+#[test]
+fn standard_wiring_draws_the_pinned_hexagon() {
+    assert_eq!(hexagon_diagram(&standard_wiring()), STANDARD);
+}
+
+#[test]
+fn ports_describe_themselves() {
+    assert_eq!(
+        PortSpec::of::<dyn PlaceOrderUseCase>(),
+        PortSpec {
+            name: "PlaceOrderUseCase",
+            direction: Direction::Inbound,
+            methods: &["place_order"],
+        }
+    );
+    assert_eq!(<dyn Clock as PortInfo>::direction(), Direction::Outbound);
+    assert!(<dyn OrderReader as PortInfo>::methods().contains(&"find_by_key"));
+    assert!(!<dyn OrderWriter as PortInfo>::methods().contains(&"find"));
+
+    let ports = all_ports();
+    let mut names: Vec<&str> = ports.iter().map(|port| port.name).collect();
+    names.sort();
+    names.dedup();
+    assert_eq!(names.len(), ports.len(), "a port is listed twice");
+    assert!(ports.iter().all(|port| !port.methods.is_empty()));
+}
+
+#[test]
+fn driving_adapters_sit_left_of_the_inbound_ports() {
+    let mut wiring = standard_wiring();
+    wiring.bind::<dyn PlaceOrderUseCase>("HttpAdapter").unwrap();
+    let diagram = hexagon_diagram(&wiring);
+
+    let driving = line_of(&diagram, "subgraph driving");
+    let http = line_of(&diagram, "adapter_HttpAdapter([");
+    let hexagon = line_of(&diagram, "subgraph hexagon");
+    let inbound = line_of(&diagram, "subgraph inbound");
+    let use_case = line_of(&diagram, "port_PlaceOrderUseCase[");
+    let domain = line_of(&diagram, "domain{{");
+    let outbound = line_of(&diagram, "subgraph outbound");
+    let repository = line_of(&diagram, "port_OrderReader[");
+    let driven = line_of(&diagram, "subgraph driven");
+    let in_memory = line_of(&diagram, "adapter_InMemoryOrderRepository([");
+    assert!(driving < http && http < hexagon);
+    assert!(hexagon < inbound && inbound < use_case && use_case < domain);
+    assert!(domain < outbound && outbound < repository && repository < driven);
+    assert!(driven < in_memory);
+
+    // Calls go in from the driving side and out to the driven side.
+    assert!(diagram.contains("\n    adapter_HttpAdapter --> port_PlaceOrderUseCase\n"));
+    assert!(diagram.contains("\n    port_PlaceOrderUseCase --> domain\n"));
+    assert!(diagram.contains("\n    domain --> port_OrderReader\n"));
+    assert!(diagram.contains("\n    port_OrderReader --> adapter_InMemoryOrderRepository\n"));
+}
+
+// A port the library does not know, declared by an application.
+trait AuditTrail {
+    #[allow(dead_code)] // only drawn, never called
+    fn record(&self, at: Timestamp, line: &str);
+}
+
+impl PortInfo for dyn AuditTrail {
+    fn port_name() -> &'static str {
+        "AuditTrail"
+    }
+
+    fn direction() -> Direction {
+        Direction::Outbound
+    }
+
+    fn methods() -> &'static [&'static str] {
+        &["record"]
+    }
+}
+
+#[test]
+fn a_bound_port_of_ones_own_is_drawn_too() {
+    let mut wiring = standard_wiring();
+    wiring
+        .bind_with::<dyn AuditTrail>("SyslogAudit<Udp>", "udp://127.0.0.1:514")
+        .unwrap();
+    let diagram = hexagon_diagram(&wiring);
+
+    assert!(
+        line_of(&diagram, r#"port_AuditTrail["AuditTrail<br/>record"]"#)
+            < line_of(&diagram, "subgraph driven")
+    );
+    assert!(diagram.contains(r#"adapter_SyslogAudit_Udp_(["SyslogAudit<Udp>"])"#));
+    assert!(diagram.ends_with(
+        "\n    domain --> port_AuditTrail\n    port_AuditTrail --> adapter_SyslogAudit_Udp_"
+    ));
+}
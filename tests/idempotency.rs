@@ -0,0 +1,195 @@
+// cargo test --test idempotency
+// A retried request carries the key of the first one: it gets the order
+// placed the first time back, charged once. The keys outlive a restart when
+// kept in a file, and are forgotten by maintenance once old enough.
+use hexa_lite::adapters::error_reporting::InMemoryErrorReporter;
+use hexa_lite::adapters::idempotency::FileIdempotencyStore;
+use hexa_lite::adapters::in_memory::InMemoryIdempotencyStore;
+use hexa_lite::application::MaintenanceReport;
+use hexa_lite::ports::{ChargeLog, IdempotencyStore, Port};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const NOW: u64 = 1_700_000_000;
+const A_DAY: Duration = Duration::from_secs(24 * 60 * 60);
+
+// One file per test, removed with what was set aside, even on failure.
+struct TempKeys(PathBuf);
+
+impl TempKeys {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("hexa_lite_{}_{name}.keys", std::process::id()));
+        let keys = TempKeys(path);
+        keys.remove();
+        keys
+    }
+
+    fn with_suffix(&self, suffix: &str) -> PathBuf {
+        let mut name = self.0.clone().into_os_string();
+        name.push(suffix);
+        PathBuf::from(name)
+    }
+
+    fn remove(&self) {
+        for path in [self.0.clone(), self.with_suffix(".corrupt")] {
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
+impl Drop for TempKeys {
+    fn drop(&mut self) {
+        self.remove();
+    }
+}
+
+fn cart() -> Vec<LineItem> {
+    vec![LineItem {
+        name: "Keyboard".to_string(),
+        price: Money(12999),
+    }]
+}
+
+#[test]
+fn a_retry_gets_the_first_order_back() {
+    let keys = InMemoryIdempotencyStore::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let repo = InMemoryOrderRepository::new();
+    let mut service =
+        OrderService::new(&repo, &payment, &sender).with_idempotency_store(&keys, A_DAY);
+
+    let first = service.place_order_once("retry-1", cart()).unwrap();
+    let retried = service.place_order_once("retry-1", cart()).unwrap();
+    let other = service.place_order_once("retry-2", cart()).unwrap();
+
+    assert_eq!(retried, first);
+    assert_ne!(other.id, first.id);
+    assert_eq!(payment.charges().unwrap().len(), 2);
+}
+
+#[test]
+fn keys_kept_in_a_file_survive_a_restart() {
+    let file = TempKeys::new("restart");
+    let reporter = InMemoryErrorReporter::new();
+    let clock = SteppingClock::starting_at(Timestamp(NOW));
+    let repo = InMemoryOrderRepository::new();
+
+    let first = {
+        let keys = FileIdempotencyStore::open(&file.0, &reporter, &clock).unwrap();
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        OrderService::new(&repo, &payment, &sender)
+            .with_clock(&clock)
+            .with_idempotency_store(&keys, A_DAY)
+            .place_order_once("retry-1", cart())
+            .unwrap()
+    }; // the process stops
+
+    let keys = FileIdempotencyStore::open(&file.0, &reporter, &clock).unwrap();
+    assert_eq!(keys.find("retry-1").unwrap(), Some(first.id));
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let retried = OrderService::new(&repo, &payment, &sender)
+        .with_clock(&clock)
+        .with_idempotency_store(&keys, A_DAY)
+        .place_order_once("retry-1", cart())
+        .unwrap();
+
+    assert_eq!(retried, first);
+    assert!(payment.charges().unwrap().is_empty(), "charged again");
+    assert!(reporter.reports().is_empty());
+}
+
+#[test]
+fn maintenance_forgets_keys_older_than_the_ttl() {
+    let file = TempKeys::new("ttl");
+    let reporter = InMemoryErrorReporter::new();
+    let clock = SteppingClock::starting_at(Timestamp(NOW));
+    let keys = FileIdempotencyStore::open(&file.0, &reporter, &clock).unwrap();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let repo = InMemoryOrderRepository::new();
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_clock(&clock)
+        .with_idempotency_store(&keys, A_DAY);
+
+    let old = service.place_order_once("old", cart()).unwrap();
+    clock.advance_secs(12 * 60 * 60);
+    service.place_order_once("recent", cart()).unwrap();
+
+    // Exactly a day old is still kept.
+    clock.advance_secs(12 * 60 * 60);
+    assert_eq!(service.maintenance().unwrap().idempotency_keys_purged, 0);
+    clock.advance_secs(1);
+    assert_eq!(
+        service.maintenance().unwrap(),
+        MaintenanceReport {
+            idempotency_keys_purged: 1,
+            ..MaintenanceReport::default()
+        }
+    );
+
+    // Forgotten on disk too, and a late retry is a new order.
+    let reopened = FileIdempotencyStore::open(&file.0, &reporter, &clock).unwrap();
+    assert_eq!(reopened.find("old").unwrap(), None);
+    assert!(reopened.find("recent").unwrap().is_some());
+    let late = service.place_order_once("old", cart()).unwrap();
+    assert_ne!(late.id, old.id);
+}
+
+#[test]
+fn without_a_clock_maintenance_forgets_nothing() {
+    let keys = InMemoryIdempotencyStore::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let repo = InMemoryOrderRepository::new();
+    let mut service =
+        OrderService::new(&repo, &payment, &sender).with_idempotency_store(&keys, A_DAY);
+
+    service.place_order_once("retry-1", cart()).unwrap();
+
+    assert_eq!(service.maintenance().unwrap(), MaintenanceReport::default());
+    assert_eq!(keys.len(), 1);
+}
+
+#[test]
+fn a_corrupt_file_is_set_aside_and_reported() {
+    let file = TempKeys::new("corrupt");
+    fs::write(
+        &file.0,
+        "{\"key\":\"retry-1\",\"order_id\":1,\"at\":17\n\u{0}\u{0}",
+    )
+    .unwrap();
+    let reporter = InMemoryErrorReporter::new();
+    let clock = SteppingClock::starting_at(Timestamp(NOW));
+
+    let keys = FileIdempotencyStore::open(&file.0, &reporter, &clock).unwrap();
+
+    assert_eq!(keys.find("retry-1").unwrap(), None);
+    let reports = reporter.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].port, Some(Port::Idempotency));
+    assert_eq!(reports[0].at, Timestamp(NOW));
+    assert_err_variant!(
+        Err::<(), _>(reports[0].error.clone()),
+        OrderError::StorageFailed
+    );
+    assert!(!file.0.exists());
+    assert!(
+        fs::read_to_string(file.with_suffix(".corrupt"))
+            .unwrap()
+            .contains("retry-1")
+    );
+
+    // The store works again from scratch, and the new file is sound.
+    keys.remember("retry-2", OrderId(2), Timestamp(NOW))
+        .unwrap();
+    let reopened = FileIdempotencyStore::open(&file.0, &reporter, &clock).unwrap();
+    assert_eq!(reopened.find("retry-2").unwrap(), Some(OrderId(2)));
+    assert_eq!(reporter.reports().len(), 1);
+}
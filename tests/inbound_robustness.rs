@@ -0,0 +1,292 @@
+// cargo test --test inbound_robustness
+// Fuzz-style tests for the driving adapters that parse untrusted input.
+// A fixed corpus of malformed inputs, then thousands of mutations of it
+// from a seeded generator: same seed, same inputs, so a failure can be
+// replayed. Whatever comes in, the adapters must not panic, and every
+// problem must be reported as InvalidOrder (422 over HTTP), never as a 500.
+use hexa_lite::adapters::cli::{CliAdapter, MAX_LINE_BYTES};
+use hexa_lite::adapters::http::{HttpAdapter, HttpRequest};
+use hexa_lite::application::OrderBrowser;
+use hexa_lite::facade::DefaultFacade;
+use hexa_lite::ports::{AmendOrder, AmendOrderUseCase, CancelOrderUseCase, OrderFacade};
+use hexa_lite::prelude::*;
+
+const SEED: u64 = 0x5eed_0386;
+const ROUNDS: usize = 3_000;
+
+// Builds the order like the real service would, without the side effects.
+#[derive(Default)]
+struct Accepting {
+    next_id: u32,
+}
+
+impl PlaceOrderUseCase for Accepting {
+    fn place_order(&mut self, command: PlaceOrder) -> Result<Order, OrderError> {
+        self.next_id += 1;
+        Order::new(OrderId(self.next_id), command.items)
+    }
+}
+
+// Nothing it placed was kept: there is nothing to amend or cancel.
+impl AmendOrderUseCase for Accepting {
+    fn amend_order(&mut self, command: AmendOrder) -> Result<Order, OrderError> {
+        Err(OrderError::NotFound {
+            id: command.order_id,
+        })
+    }
+}
+
+impl CancelOrderUseCase for Accepting {
+    fn cancel_order(&mut self, id: OrderId) -> Result<Order, OrderError> {
+        Err(OrderError::NotFound { id })
+    }
+}
+
+// The HTTP adapter wants the whole service, queries included; they see an
+// empty shop.
+fn http_adapter(repo: &InMemoryOrderRepository) -> HttpAdapter<impl OrderFacade + '_> {
+    HttpAdapter::new(DefaultFacade::new(
+        Accepting::default(),
+        OrderBrowser::new(repo),
+    ))
+}
+
+// xorshift64*: tiny, deterministic, good enough to shuffle bytes.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn mutate(&mut self, input: &[u8]) -> Vec<u8> {
+        let mut bytes = input.to_vec();
+        for _ in 0..=self.below(4) {
+            let at = self.below(bytes.len() + 1);
+            match self.below(5) {
+                0 => bytes.insert(at, self.next() as u8),
+                1 if at < bytes.len() => {
+                    bytes.remove(at);
+                }
+                2 if at < bytes.len() => bytes[at] = self.next() as u8,
+                3 => {
+                    let end = (at + self.below(16)).min(bytes.len());
+                    let chunk = bytes[at..end].to_vec();
+                    bytes.splice(at..at, chunk.iter().cycle().take(chunk.len() * 8).copied());
+                }
+                _ => {
+                    let digits = b"99999999999";
+                    bytes.splice(at..at, digits.iter().copied());
+                }
+            }
+        }
+        bytes
+    }
+}
+
+fn cli_corpus() -> Vec<Vec<u8>> {
+    let mut corpus: Vec<Vec<u8>> = [
+        "place Keyboard=12999, USB cable=999",
+        "place Keyboard=4294967295",
+        "place Keyboard=4294967296",
+        "place Keyboard=99999999999999999999999999",
+        "place Gold=4294967295, Wrap=1",
+        "place Keyboard=-1",
+        "place Keyboard=+1",
+        "place Keyboard=12.99",
+        "place Keyboard=",
+        "place =12999",
+        "place Keyboard",
+        "place , , ,",
+        "place",
+        "ship 1",
+        "",
+        "\r",
+        "place Tab\there=1",
+    ]
+    .iter()
+    .map(|line| line.as_bytes().to_vec())
+    .collect();
+    corpus.push(format!("place {}=1", "x".repeat(10_000)).into_bytes());
+    corpus.push(format!("place {}", "A=1,".repeat(1_000)).into_bytes());
+    corpus.push(b"place Caf\xe9=350".to_vec());
+    corpus.push(b"place \xff\xfe\xfd=1".to_vec());
+    corpus
+}
+
+fn http_corpus() -> Vec<Vec<u8>> {
+    let mut corpus: Vec<Vec<u8>> = [
+        r#"{"items":[{"name":"Keyboard","price_cents":12999}]}"#,
+        r#"{"items":[{"name":"Keyboard","price_cents":"12999"}]}"#,
+        r#"{"items":[{"name":12999,"price_cents":12999}]}"#,
+        r#"{"items":[{"name":"Keyboard","price_cents":-1}]}"#,
+        r#"{"items":[{"name":"Keyboard","price_cents":4294967296}]}"#,
+        r#"{"items":[{"name":"Keyboard","price_cents":1.5}]}"#,
+        r#"{"items":[{"name":"Keyboard","price_cents":12999,"color":"red"}]}"#,
+        r#"{"items":[{"name":"Keyboard","price_cents":1}],"coupon":"FREE"}"#,
+        r#"{"items":[{"name":"A","price_cents":4294967295},{"name":"B","price_cents":1}]}"#,
+        r#"{"items":[]}"#,
+        r#"{"items":{}}"#,
+        r#"{"items":null}"#,
+        r#"{"items":[null,true,false]}"#,
+        r#"[]"#,
+        r#""items""#,
+        r#"{"#,
+        "",
+    ]
+    .iter()
+    .map(|body| body.as_bytes().to_vec())
+    .collect();
+    corpus.push(format!("{}{}", "[".repeat(100_000), "]".repeat(100_000)).into_bytes());
+    corpus.push(
+        format!(
+            r#"{{"items":[{{"name":"{}","price_cents":1}}]}}"#,
+            "x".repeat(10_000)
+        )
+        .into_bytes(),
+    );
+    corpus.push(b"{\"items\":[{\"name\":\"\xff\",\"price_cents\":1}]}".to_vec());
+    corpus
+}
+
+// Every reply is either a placed order or InvalidOrder.
+fn assert_cli_replies(input: &[u8]) {
+    let mut cli = CliAdapter::new(Accepting::default());
+    let mut output = Vec::new();
+    cli.run(input, &mut output).unwrap();
+
+    let output = String::from_utf8(output).expect("replies are UTF-8");
+    for reply in output.lines() {
+        assert!(
+            reply.starts_with("ok #") || reply == "error InvalidOrder",
+            "unexpected reply {reply:?} for input {:?}",
+            String::from_utf8_lossy(input)
+        );
+    }
+}
+
+fn assert_http_status(body: &[u8]) {
+    let repo = InMemoryOrderRepository::new();
+    let http = http_adapter(&repo);
+    let response = http.handle(&HttpRequest {
+        method: "POST".to_string(),
+        path: "/orders".to_string(),
+        headers: Vec::new(),
+        body: body.to_vec(),
+    });
+    match response.status {
+        201 => {}
+        422 => assert_eq!(
+            response.body,
+            r#"{"error":"InvalidOrder","code":"ORD-001"}"#
+        ),
+        status => panic!(
+            "status {status} for body {:?}",
+            String::from_utf8_lossy(body)
+        ),
+    }
+}
+
+#[test]
+fn cli_corpus_is_refused_line_by_line() {
+    let corpus = cli_corpus();
+    let mut input = Vec::new();
+    for line in &corpus {
+        input.extend_from_slice(line);
+        input.push(b'\n');
+    }
+
+    let mut cli = CliAdapter::new(Accepting::default());
+    let mut output = Vec::new();
+    cli.run(input.as_slice(), &mut output).unwrap();
+    let output = String::from_utf8(output).unwrap();
+    let replies: Vec<&str> = output.lines().collect();
+
+    // Blank lines get no reply; all the others get exactly one.
+    assert_eq!(replies.len(), corpus.len() - 2);
+    assert!(replies[0].starts_with("ok #000001"));
+    assert!(replies[1].starts_with("ok #000002"));
+    assert!(
+        replies[2..]
+            .iter()
+            .all(|reply| *reply == "error InvalidOrder")
+    );
+}
+
+#[test]
+fn cli_survives_mutated_input() {
+    let corpus = cli_corpus();
+    let mut rng = Rng(SEED);
+    for _ in 0..ROUNDS {
+        let seed = &corpus[rng.below(corpus.len())];
+        assert_cli_replies(&rng.mutate(seed));
+    }
+}
+
+#[test]
+fn cli_refuses_an_endless_line_and_carries_on() {
+    let mut input = vec![b'x'; MAX_LINE_BYTES * 4];
+    input.extend_from_slice(b"\nplace Mouse=2500\n");
+
+    let mut cli = CliAdapter::new(Accepting::default());
+    let mut output = Vec::new();
+    cli.run(input.as_slice(), &mut output).unwrap();
+
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "error InvalidOrder\nok #000001 $25.00\n"
+    );
+}
+
+#[test]
+fn http_corpus_is_accepted_or_refused_with_422() {
+    let corpus = http_corpus();
+    for body in &corpus {
+        assert_http_status(body);
+    }
+
+    let repo = InMemoryOrderRepository::new();
+    let http = http_adapter(&repo);
+    let created = http.handle(&HttpRequest {
+        method: "POST".to_string(),
+        path: "/orders".to_string(),
+        headers: Vec::new(),
+        body: corpus[0].clone(),
+    });
+    assert_eq!(created.status, 201);
+    assert_eq!(
+        created.body,
+        r#"{"id":1,"status":"Placed","total_cents":12999}"#
+    );
+    for body in &corpus[1..] {
+        let response = http.handle(&HttpRequest {
+            method: "POST".to_string(),
+            path: "/orders".to_string(),
+            headers: Vec::new(),
+            body: body.clone(),
+        });
+        assert_eq!(
+            response.status,
+            422,
+            "for {:?}",
+            String::from_utf8_lossy(body)
+        );
+    }
+}
+
+#[test]
+fn http_survives_mutated_bodies() {
+    let corpus = http_corpus();
+    let mut rng = Rng(SEED);
+    for _ in 0..ROUNDS {
+        let seed = &corpus[rng.below(corpus.len())];
+        assert_http_status(&rng.mutate(seed));
+    }
+}
@@ -0,0 +1,171 @@
+// cargo test --test journaled_repository
+// A crash at any write leaves the orders, the sequence and the outbox in
+// step. The same run (two orders placed, each in a unit of work, then the
+// first confirmation acknowledged) is crashed at every write it makes in
+// turn, through a FailpointFs; the directory is then opened again on the
+// real file system, and what it holds must be the state before or after
+// one of the steps, never a mix of two.
+#![cfg(feature = "json")]
+
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::file_repository::{Fs, JournaledRepository, JsonFormat, RealFs};
+use hexa_lite::ports::{ServiceState, StateStore, UnitOfWork};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::FailpointFs;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// (orders, next id, queued confirmations) after each step of the run.
+const STEPS: [(&[u32], Option<u32>, &[u32]); 4] = [
+    (&[], None, &[]),
+    (&[1], Some(2), &[1]),
+    (&[1, 2], Some(3), &[1, 2]),
+    (&[1, 2], Some(3), &[2]),
+];
+
+struct TempDir(PathBuf);
+
+impl TempDir {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("hexa_lite_{}_journal_{name}", std::process::id()));
+        let _ = fs::remove_dir_all(&path);
+        Self(path)
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn open<S: Fs>(dir: &Path, fs: S) -> JournaledRepository<JsonFormat, S> {
+    JournaledRepository::open_on(dir, JsonFormat, fs)
+        .unwrap()
+        .with_console(Console::silent())
+}
+
+// What a placement is to the repository: the order saved, the sequence
+// moved past it, its confirmation queued, all in one unit of work.
+fn place<S: Fs>(
+    repository: &mut JournaledRepository<JsonFormat, S>,
+    id: u32,
+) -> Result<(), OrderError> {
+    let order = Order::new(OrderId(id), vec![LineItem::new("Pen", Money(150))])?;
+    let state = ServiceState {
+        next_id: id + 1,
+        open_drafts: Vec::new(),
+    };
+    repository.begin();
+    let staged = repository
+        .save(&order)
+        .and_then(|()| repository.save_state(&state))
+        .and_then(|()| repository.send(&order.confirmation()));
+    match staged {
+        Ok(()) => repository.commit(),
+        Err(error) => {
+            repository.rollback();
+            Err(error)
+        }
+    }
+}
+
+// Stops at the first step that fails: the process is dead by then.
+fn run<S: Fs>(repository: &mut JournaledRepository<JsonFormat, S>) -> Result<(), OrderError> {
+    place(repository, 1)?;
+    place(repository, 2)?;
+    repository.acknowledge(1)
+}
+
+// Which of STEPS the directory holds.
+fn step_on_disk(dir: &Path) -> usize {
+    let repository = open(dir, RealFs);
+    let ids = |orders: Vec<Order>| orders.iter().map(|order| order.id.0).collect::<Vec<_>>();
+    let orders = ids(repository.list().unwrap());
+    let next_id = repository.load_state().unwrap().map(|state| state.next_id);
+    let queued: Vec<u32> = repository
+        .outbox()
+        .unwrap()
+        .iter()
+        .map(|confirmation| confirmation.order_id.0)
+        .collect();
+    let found = (orders.as_slice(), next_id, queued.as_slice());
+    STEPS
+        .iter()
+        .position(|step| *step == found)
+        .unwrap_or_else(|| panic!("out of step: {found:?}"))
+}
+
+#[test]
+fn a_crash_at_any_write_leaves_the_files_in_step() {
+    let counted = TempDir::new("counted");
+    let mut repository = open(&counted.0, FailpointFs::new(RealFs, usize::MAX));
+    run(&mut repository).unwrap();
+    let writes = repository.fs().writes();
+    assert_eq!(step_on_disk(&counted.0), STEPS.len() - 1);
+
+    let mut reached = 0;
+    for survive in 0..writes {
+        let dir = TempDir::new(&format!("crash_{survive}"));
+        let mut repository = open(&dir.0, FailpointFs::new(RealFs, survive));
+        // Whatever it says: a step it reports done may be only logged.
+        let _ = run(&mut repository);
+        assert!(repository.fs().crashed(), "{survive}");
+        drop(repository);
+
+        let step = step_on_disk(&dir.0);
+        assert!(
+            step >= reached,
+            "crashed after {survive} writes: back to {step}"
+        );
+        reached = step;
+        // Recovered once, it stays recovered.
+        assert_eq!(step_on_disk(&dir.0), step);
+    }
+    assert_eq!(reached, STEPS.len() - 1);
+}
+
+#[test]
+fn a_step_logged_before_the_crash_is_kept() {
+    let counted = TempDir::new("first");
+    let mut repository = open(&counted.0, FailpointFs::new(RealFs, usize::MAX));
+    place(&mut repository, 1).unwrap();
+    let first = repository.fs().writes();
+
+    // The intent is its first write: past it, the placement is made.
+    for survive in 1..first {
+        let dir = TempDir::new(&format!("first_{survive}"));
+        let mut repository = open(&dir.0, FailpointFs::new(RealFs, survive));
+        assert!(place(&mut repository, 1).is_ok(), "{survive}");
+        drop(repository);
+        assert_eq!(step_on_disk(&dir.0), 1, "{survive}");
+    }
+    let dir = TempDir::new("first_0");
+    let mut repository = open(&dir.0, FailpointFs::new(RealFs, 0));
+    assert!(matches!(
+        place(&mut repository, 1),
+        Err(OrderError::StorageFailed)
+    ));
+    drop(repository);
+    assert_eq!(step_on_disk(&dir.0), 0);
+}
+
+#[test]
+fn a_rolled_back_unit_leaves_nothing_behind() {
+    let dir = TempDir::new("rollback");
+    let mut repository = open(&dir.0, RealFs);
+    let order = Order::new(OrderId(1), vec![LineItem::new("Pen", Money(150))]).unwrap();
+
+    repository.begin();
+    repository.save(&order).unwrap();
+    repository.send(&order.confirmation()).unwrap();
+    // The unit sees its own changes, and nothing else does yet.
+    assert!(repository.find(OrderId(1)).unwrap().is_some());
+    assert_eq!(step_on_disk(&dir.0), 0);
+    repository.rollback();
+
+    assert_eq!(repository.find(OrderId(1)).unwrap(), None);
+    assert!(repository.outbox().unwrap().is_empty());
+    assert_eq!(step_on_disk(&dir.0), 0);
+}
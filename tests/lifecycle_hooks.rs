@@ -0,0 +1,156 @@
+// cargo test --test lifecycle_hooks
+// Hooks are called between the ports, at fixed points of place_order, and
+// never change how it ends: a hook's error is reported and forgotten, a
+// hook's panic is caught.
+use hexa_lite::adapters::error_reporting::InMemoryErrorReporter;
+use hexa_lite::application::{CompositeHooks, Hooks};
+use hexa_lite::ports::{OrderReader, Port};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use hexa_lite::testing::stubs::OkSender;
+
+// A charge answering `.0`, whatever the amount.
+struct Payment(Result<(), OrderError>);
+
+impl PaymentGateway for Payment {
+    fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+        self.0.clone()
+    }
+}
+
+// Does nothing: wrapped in a Recorded, it only shows when it was called.
+struct Noting;
+
+impl Hooks for Noting {}
+
+// Every port, and the hooks, reporting into one recorder.
+struct Wired {
+    recorder: GlobalRecorder,
+    repo: Recorded<InMemoryOrderRepository>,
+    payment: Recorded<Payment>,
+    sender: Recorded<OkSender>,
+    hooks: Recorded<Noting>,
+}
+
+fn wired(charge: Result<(), OrderError>) -> Wired {
+    let recorder = GlobalRecorder::new();
+    Wired {
+        repo: Recorded::new(InMemoryOrderRepository::new(), &recorder, "repository"),
+        payment: Recorded::new(Payment(charge), &recorder, "payment"),
+        sender: Recorded::new(OkSender, &recorder, "sender"),
+        hooks: Recorded::new(Noting, &recorder, "hooks"),
+        recorder,
+    }
+}
+
+struct Failing;
+
+impl Hooks for Failing {
+    fn before_charge(&self, _order: &Order) -> Result<(), OrderError> {
+        Err(OrderError::StorageFailed)
+    }
+}
+
+struct Panicking;
+
+impl Hooks for Panicking {
+    fn before_charge(&self, _order: &Order) -> Result<(), OrderError> {
+        panic!("the fraud service is down");
+    }
+
+    fn after_save(&self, _order: &Order) -> Result<(), OrderError> {
+        panic!("the audit log is full");
+    }
+
+    fn on_failure(&self, _error: &OrderError) -> Result<(), OrderError> {
+        panic!("the pager is broken");
+    }
+}
+
+fn cart() -> Vec<LineItem> {
+    vec![LineItem::new("Keyboard", Money(4999))]
+}
+
+#[test]
+fn hooks_are_called_between_the_ports() {
+    let w = wired(Ok(()));
+    let mut service = OrderService::new(&w.repo, &w.payment, &w.sender).with_hooks(&w.hooks);
+
+    service.place_order(cart()).unwrap();
+
+    expect_sequence!(
+        w.recorder,
+        [
+            "hooks.before_charge",
+            "payment.charge_order",
+            "repository.save",
+            "hooks.after_save",
+            "sender.send",
+        ]
+    );
+}
+
+#[test]
+fn a_declined_charge_ends_with_on_failure() {
+    let w = wired(Err(OrderError::PaymentFailed));
+    let mut service = OrderService::new(&w.repo, &w.payment, &w.sender).with_hooks(&w.hooks);
+
+    assert_err_variant!(service.place_order(cart()), OrderError::PaymentFailed);
+
+    expect_sequence!(
+        w.recorder,
+        [
+            "hooks.before_charge",
+            "payment.charge_order",
+            "hooks.on_failure"
+        ]
+    );
+    assert!(w.recorder.calls_to("repository").is_empty());
+    assert!(w.recorder.calls_to("sender").is_empty());
+}
+
+#[test]
+fn a_hook_error_is_reported_and_the_order_goes_through() {
+    let w = wired(Ok(()));
+    let reporter = InMemoryErrorReporter::new();
+    let clock = SteppingClock::starting_at(Timestamp(0));
+    let hooks = CompositeHooks::new().with(&Failing).with(&w.hooks);
+    let mut service = OrderService::new(&w.repo, &w.payment, &w.sender)
+        .with_error_reporter(&reporter, &clock)
+        .with_hooks(&hooks);
+
+    let order = service.place_order(cart()).unwrap();
+
+    assert_order(&order).has_status(OrderStatus::Paid);
+    expect_sequence!(
+        w.recorder,
+        [
+            "hooks.before_charge",
+            "payment.charge_order",
+            "repository.save",
+            "hooks.after_save",
+            "sender.send",
+        ]
+    );
+    let reports = reporter.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].port, Some(Port::Hooks));
+    assert_eq!(reports[0].operation, "before_charge");
+    assert_eq!(reports[0].order_id, Some(order.id));
+}
+
+#[test]
+fn a_panicking_hook_is_contained() {
+    let w = wired(Ok(()));
+    let mut service = OrderService::new(&w.repo, &w.payment, &w.sender).with_hooks(&Panicking);
+
+    let order = service.place_order(cart()).unwrap();
+    assert_eq!(w.repo.find(order.id).unwrap(), Some(order));
+    expect_sequence!(
+        w.recorder,
+        ["payment.charge_order", "repository.save", "sender.send"]
+    );
+
+    // The error still comes back as it would without the hook.
+    assert_err_variant!(service.place_order(vec![]), OrderError::InvalidOrder);
+}
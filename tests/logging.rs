@@ -0,0 +1,61 @@
+// cargo test --test logging
+// The adapters log through the Logger behind their Console. A VecLogger
+// keeps the entries, with their level and target, so a test asserts on
+// them instead of reading stdout; Console::silent() keeps a test's output
+// clean.
+use hexa_lite::adapters::{Console, VecLogger};
+use hexa_lite::ports::Level;
+use hexa_lite::prelude::*;
+
+fn carts() -> Vec<Vec<LineItem>> {
+    vec![
+        vec![LineItem::new("Mouse", Money(2500))],
+        vec![LineItem::new("Keyboard", Money(12999))],
+        vec![LineItem::new("Cable", Money(500))],
+    ]
+}
+
+#[test]
+fn the_repository_logs_one_save_per_order() {
+    let logger = VecLogger::new();
+    let repo = InMemoryOrderRepository::new().with_console(Console::logger(logger.clone()));
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    let ids: Vec<OrderId> = carts()
+        .into_iter()
+        .map(|cart| service.place_order(cart).unwrap().id)
+        .collect();
+
+    let saves: Vec<String> = logger
+        .entries()
+        .into_iter()
+        .filter(|entry| entry.target == "InMemory" && entry.message.contains("Saving"))
+        .map(|entry| entry.message)
+        .collect();
+    let expected: Vec<String> = ids
+        .iter()
+        .map(|id| format!("  [InMemory] Saving order {id:?}"))
+        .collect();
+    assert_eq!(saves, expected);
+}
+
+#[test]
+fn adapters_sharing_a_logger_are_told_apart_by_target() {
+    let logger = VecLogger::new();
+    let console = Console::logger(logger.clone());
+    let repo = InMemoryOrderRepository::new().with_console(console.clone());
+    let payment = MockPaymentGateway::new().with_console(console.clone());
+    let sender = ConsoleSender::new().with_console(console);
+
+    OrderService::new(&repo, &payment, &sender)
+        .place_order(carts().remove(0))
+        .unwrap();
+
+    let entries = logger.entries();
+    assert!(entries.iter().all(|entry| entry.level == Level::Info));
+    let mut targets: Vec<&str> = entries.iter().map(|entry| entry.target.as_str()).collect();
+    targets.dedup();
+    assert_eq!(targets, ["MockPayment", "InMemory", "Console"]);
+}
@@ -0,0 +1,135 @@
+// cargo test --test low_stock
+// Stock as a small domain of its own: restocked, reserved, watched. When a
+// reservation takes an item under the threshold, the inventory publishes
+// LowStock, and the ops contact gets a Notice through their Sender.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::event_bus::InMemoryEventBus;
+use hexa_lite::adapters::in_memory::InMemoryInventory;
+use hexa_lite::adapters::stock::{LowStockAlerts, OpsNotifier};
+use hexa_lite::domain::{InventoryEvent, Notice, OrderEvent};
+use hexa_lite::ports::{EventPublisher, Inventory, ReservationId};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::stubs::OkNotifier;
+use std::sync::Mutex;
+use std::thread;
+
+// Ops' inbox: every notice, and not a single customer confirmation.
+#[derive(Default)]
+struct Inbox(Mutex<Vec<Notice>>);
+
+impl Sender for Inbox {
+    fn send(&self, _confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        panic!("ops are not customers")
+    }
+
+    fn send_notice(&self, notice: &Notice) -> Result<(), OrderError> {
+        self.0.lock().unwrap().push(notice.clone());
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Journal(Mutex<Vec<InventoryEvent>>);
+
+impl EventPublisher for Journal {
+    fn publish(&self, _event: &OrderEvent) -> Result<(), OrderError> {
+        Ok(())
+    }
+
+    fn publish_inventory(&self, event: &InventoryEvent) -> Result<(), OrderError> {
+        self.0.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+}
+
+fn mug() -> Vec<LineItem> {
+    vec![LineItem::new("Mug", Money(900))]
+}
+
+#[test]
+fn two_threads_reserving_at_once_never_oversell() {
+    let journal = Journal::default();
+    let shelf = InMemoryInventory::new()
+        .with_stock("Mug", 100)
+        .with_console(Console::silent());
+    let inventory = LowStockAlerts::new(shelf, &journal, 10);
+
+    // 60 reservations each, 120 for 100 mugs.
+    let reserved: Vec<usize> = thread::scope(|scope| {
+        let threads: Vec<_> = (0..2u32)
+            .map(|thread| {
+                let inventory = &inventory;
+                scope.spawn(move || {
+                    (0..60u32)
+                        .filter(|n| {
+                            inventory
+                                .reserve(ReservationId(thread * 1000 + n), &mug())
+                                .is_ok()
+                        })
+                        .count()
+                })
+            })
+            .collect();
+        threads.into_iter().map(|t| t.join().unwrap()).collect()
+    });
+
+    assert_eq!(reserved.iter().sum::<usize>(), 100);
+    assert_eq!(inventory.stock_level("Mug").unwrap(), 0);
+    // Whichever thread took the 91st mug, it was the only one told.
+    assert_eq!(
+        journal.0.lock().unwrap()[..],
+        [InventoryEvent::LowStock {
+            item: "Mug".to_string(),
+            remaining: 9,
+        }]
+    );
+}
+
+#[test]
+fn ops_are_told_what_ran_low_and_how_many_are_left() {
+    let inbox = Inbox::default();
+    let ops = OpsNotifier::new(&inbox, "ops@shop.test");
+    // Ops as one subscriber among others.
+    let bus = InMemoryEventBus::new();
+    bus.subscribe_inventory(Box::new(|event| ops.publish_inventory(event)));
+    let shelf = InMemoryInventory::new()
+        .with_stock("Mug", 3)
+        .with_stock("Lid", 8)
+        .with_console(Console::silent());
+    let inventory = LowStockAlerts::new(shelf, &bus, 2);
+
+    inventory.restock("Mug", 1).unwrap();
+    let cart = vec![
+        LineItem::new("Mug", Money(900)),
+        LineItem::new("Mug", Money(900)),
+        LineItem::new("Mug", Money(900)),
+        LineItem::new("Lid", Money(200)),
+    ];
+    inventory.reserve(ReservationId(1), &cart).unwrap();
+
+    // The restock and the lids are no news to ops.
+    assert_eq!(
+        inbox.0.lock().unwrap()[..],
+        [Notice {
+            to: "ops@shop.test".to_string(),
+            subject: "Low stock: Mug".to_string(),
+            lines: vec![
+                "1 Mug left on the shelf.".to_string(),
+                "Restock Mug before it runs out.".to_string(),
+            ],
+        }]
+    );
+}
+
+#[test]
+fn a_sender_reaching_only_customers_refuses_the_notice() {
+    let ops = OpsNotifier::new(&OkNotifier, "ops@shop.test");
+
+    assert!(matches!(
+        ops.publish_inventory(&InventoryEvent::LowStock {
+            item: "Mug".to_string(),
+            remaining: 1,
+        }),
+        Err(OrderError::NotificationFailed)
+    ));
+}
@@ -0,0 +1,138 @@
+// cargo test --test merge_drafts
+// Two carts still open (Placed, nothing charged) become one: the source's
+// items join the target's, identical lines side by side, the total is
+// summed again, and the source is deleted, reversibly.
+use hexa_lite::domain::OrderEvent;
+use hexa_lite::ports::ChargeLog;
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::sync::Mutex;
+
+fn item(name: &str, cents: u32) -> LineItem {
+    LineItem {
+        name: name.to_string(),
+        price: Money(cents),
+    }
+}
+
+// Carts left open: stored as placed, never charged.
+fn repository_with(carts: &[(u32, Vec<LineItem>)]) -> InMemoryOrderRepository {
+    let repo = InMemoryOrderRepository::new();
+    for (id, items) in carts {
+        repo.save(&Order::new(OrderId(*id), items.clone()).unwrap())
+            .unwrap();
+    }
+    repo
+}
+
+fn ids(orders: &[Order]) -> Vec<OrderId> {
+    orders.iter().map(|order| order.id).collect()
+}
+
+#[test]
+fn the_source_cart_moves_into_the_target() {
+    let repo = repository_with(&[
+        (1, vec![item("Cable", 500), item("Mouse", 2500)]),
+        (2, vec![item("Keyboard", 12999), item("Cable", 500)]),
+        (3, vec![item("Sticker", 150)]),
+    ]);
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let published = Mutex::new(Vec::new());
+    // A closure publishing into `published`.
+    let events = |event: &OrderEvent| {
+        published.lock().unwrap().push(event.clone());
+        Ok(())
+    };
+    let mut service = OrderService::new(&repo, &payment, &sender).with_event_publisher(&events);
+
+    let merged = service.merge_drafts(OrderId(1), OrderId(2)).unwrap();
+
+    assert_order(&merged)
+        .has_id(1)
+        .has_item_count(4)
+        .has_total_cents(500 + 2500 + 12999 + 500)
+        .has_status(OrderStatus::Placed);
+    let names: Vec<&str> = merged.items.iter().map(|i| i.name.as_str()).collect();
+    assert_eq!(names, vec!["Cable", "Cable", "Mouse", "Keyboard"]);
+    assert_eq!(service.get_order(OrderId(1)).unwrap(), Some(merged.clone()));
+    assert_eq!(
+        *published.lock().unwrap(),
+        vec![OrderEvent::OrdersMerged {
+            order_id: OrderId(1),
+            from: OrderId(2),
+            item_count: 4,
+            total: merged.total,
+        }]
+    );
+    assert!(payment.charges().unwrap().is_empty());
+
+    assert_eq!(ids(&repo.list().unwrap()), vec![OrderId(1), OrderId(3)]);
+    assert_eq!(ids(&repo.list_deleted().unwrap()), vec![OrderId(2)]);
+}
+
+#[test]
+fn a_total_past_money_merges_nothing() {
+    let repo = repository_with(&[
+        (1, vec![item("Yacht", u32::MAX - 100)]),
+        (2, vec![item("Dinghy", 60), item("Oars", 41)]),
+    ]);
+    let before = repo.list().unwrap();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    assert_err_variant!(
+        service.merge_drafts(OrderId(1), OrderId(2)),
+        OrderError::InvalidOrder
+    );
+
+    assert_eq!(repo.list().unwrap(), before);
+    assert!(repo.list_deleted().unwrap().is_empty());
+}
+
+#[test]
+fn only_open_carts_can_be_merged() {
+    let repo = repository_with(&[(10, vec![item("Cable", 500)])]);
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let paid = service.place_order(vec![item("Mouse", 2500)]).unwrap();
+    assert_eq!(paid.id, OrderId(1));
+
+    assert_err_variant!(
+        service.merge_drafts(OrderId(10), paid.id),
+        OrderError::NotADraft { id: OrderId(1) }
+    );
+    assert_err_variant!(
+        service.merge_drafts(paid.id, OrderId(10)),
+        OrderError::NotADraft { id: OrderId(1) }
+    );
+    assert_err_variant!(
+        service.merge_drafts(OrderId(10), OrderId(10)),
+        OrderError::InvalidOrder
+    );
+    assert_err_variant!(
+        service.merge_drafts(OrderId(10), OrderId(9)),
+        OrderError::NotFound { id: OrderId(9) }
+    );
+    assert!(repo.list_deleted().unwrap().is_empty());
+}
+
+#[test]
+fn a_merged_away_cart_can_be_restored() {
+    let repo = repository_with(&[
+        (1, vec![item("Cable", 500)]),
+        (2, vec![item("Mouse", 2500)]),
+    ]);
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    service.merge_drafts(OrderId(1), OrderId(2)).unwrap();
+    let restored = service.restore_order(OrderId(2)).unwrap();
+
+    assert_order(&restored)
+        .has_item_named("Mouse")
+        .has_status(OrderStatus::Placed);
+}
@@ -0,0 +1,157 @@
+// cargo test --test multi_currency
+// A cart with prices in several currencies, converted before it is charged.
+use hexa_lite::adapters::rates::FixedRates;
+use hexa_lite::application::OrderBrowser;
+use hexa_lite::domain::{Currency, ExchangeRate, ForeignLineItem, Price};
+use hexa_lite::ports::{OrderQueries, OrderReader};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+
+fn line(name: &str, cents: u32, currency: Currency) -> ForeignLineItem {
+    ForeignLineItem {
+        name: name.to_string(),
+        price: Price {
+            amount: Money(cents),
+            currency,
+        },
+    }
+}
+
+fn rates() -> FixedRates {
+    FixedRates::new().with_rate(Currency::Eur, Currency::Usd, ExchangeRate(108_500_000))
+}
+
+#[test]
+fn eur_and_usd_lines_are_charged_in_usd() {
+    let repo = InMemoryOrderRepository::new();
+    let rates = rates();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender).with_exchange_rates(&rates);
+
+    let converted = service
+        .place_order_in(
+            Currency::Usd,
+            vec![
+                line("Rust Book", 4000, Currency::Eur), // 40.00 EUR -> 43.40 USD
+                line("Keyboard", 12999, Currency::Usd),
+            ],
+        )
+        .unwrap();
+
+    assert_eq!(converted.currency, Currency::Usd);
+    assert_order(&converted.order)
+        .has_total_cents(4340 + 12999)
+        .has_status(OrderStatus::Paid);
+    // The original prices are kept next to the converted ones.
+    assert_eq!(converted.lines[0].original.currency, Currency::Eur);
+    assert_eq!(converted.lines[0].original.amount, Money(4000));
+    assert_eq!(converted.lines[0].item.price, Money(4340));
+    assert_eq!(converted.lines[1].item.price, Money(12999));
+}
+
+#[test]
+fn rounding_is_per_line_so_the_receipt_adds_up() {
+    let repo = InMemoryOrderRepository::new();
+    let rates =
+        FixedRates::new().with_rate(Currency::Eur, Currency::Usd, ExchangeRate(150_000_000));
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender).with_exchange_rates(&rates);
+
+    // Three lines of 0.01 EUR at 1.5: each becomes 0.015 -> 0.02 USD.
+    // Converting the 0.03 EUR total once would give 0.045 -> 0.05 USD.
+    let converted = service
+        .place_order_in(
+            Currency::Usd,
+            vec![
+                line("Sticker A", 1, Currency::Eur),
+                line("Sticker B", 1, Currency::Eur),
+                line("Sticker C", 1, Currency::Eur),
+            ],
+        )
+        .unwrap();
+
+    assert_order(&converted.order).has_total_cents(6);
+    let sum: u32 = converted.lines.iter().map(|line| line.item.price.0).sum();
+    assert_eq!(sum, converted.order.total.0);
+}
+
+#[test]
+fn missing_rate_is_reported_and_nothing_is_charged() {
+    let repo = InMemoryOrderRepository::new();
+    let rates = rates();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender).with_exchange_rates(&rates);
+
+    assert_err_variant!(
+        service.place_order_in(
+            Currency::Usd,
+            vec![
+                line("Mouse", 2500, Currency::Usd),
+                line("Tea", 500, Currency::Gbp)
+            ]
+        ),
+        OrderError::NoExchangeRate {
+            from: Currency::Gbp,
+            to: Currency::Usd
+        }
+    );
+    assert!(repo.list().unwrap().is_empty());
+}
+
+#[test]
+fn without_rates_only_same_currency_carts_are_accepted() {
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    assert!(
+        service
+            .place_order_in(Currency::Eur, vec![line("Book", 4999, Currency::Eur)])
+            .is_ok()
+    );
+    assert_err_variant!(
+        service.place_order_in(Currency::Eur, vec![line("Book", 4999, Currency::Usd)]),
+        OrderError::NoExchangeRate { .. }
+    );
+}
+
+#[test]
+fn revenue_is_reported_per_currency_once_orders_mix_them() {
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let book = service
+        .place_order_in(Currency::Eur, vec![line("Book", 4999, Currency::Eur)])
+        .unwrap()
+        .order;
+    service
+        .place_order_in(Currency::Eur, vec![line("Map", 1001, Currency::Eur)])
+        .unwrap();
+    service
+        .place_order(vec![LineItem::new("Pen", Money(150))])
+        .unwrap();
+    let browser = OrderBrowser::new(&repo);
+
+    assert_eq!(repo.find(book.id).unwrap().unwrap().currency, Currency::Eur);
+    let totals = browser.revenue_by_currency().unwrap();
+    assert_eq!(
+        totals.iter().collect::<Vec<_>>(),
+        [(Currency::Eur, Money(6_000)), (Currency::Usd, Money(150))]
+    );
+    assert_eq!(totals.to_string(), "€60.00, $1.50");
+    // €60 and $1.50 are not $61.50: the one-amount query refuses to add them.
+    assert!(matches!(
+        browser.revenue(),
+        Err(OrderError::MixedCurrencies { expected: Currency::Usd, others })
+            if others == [Currency::Eur]
+    ));
+    assert_err_variant!(
+        totals.require_single(Currency::Eur),
+        OrderError::MixedCurrencies { .. }
+    );
+}
@@ -0,0 +1,185 @@
+// cargo test --test multi_warehouse
+// One cart, several warehouses. Each line is reserved from the warehouse a
+// WarehousePicker names, the reservation remembers which, and shipping
+// sends one parcel per warehouse, holding exactly the lines reserved there.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::warehouse::{MostStockFirst, MultiWarehouseInventory, NearestFirst};
+use hexa_lite::application::ShippingService;
+use hexa_lite::domain::{Address, TrackingId, WarehouseId};
+use hexa_lite::ports::{Inventory, ReservationId, ShippingProvider};
+use hexa_lite::prelude::testing::SteppingClock;
+use hexa_lite::prelude::*;
+use std::sync::Mutex;
+
+const NORTH: WarehouseId = WarehouseId(1);
+const SOUTH: WarehouseId = WarehouseId(2);
+
+// Every parcel handed over, by item name.
+#[derive(Default)]
+struct Carrier(Mutex<Vec<Vec<String>>>);
+
+impl ShippingProvider for Carrier {
+    fn ship(
+        &self,
+        order_id: OrderId,
+        items: &[LineItem],
+        _address: &Address,
+    ) -> Result<TrackingId, OrderError> {
+        let mut parcels = self.0.lock().unwrap();
+        parcels.push(items.iter().map(|item| item.name.clone()).collect());
+        Ok(TrackingId(format!("{}-{}", order_id.0, parcels.len())))
+    }
+}
+
+fn address() -> Address {
+    Address {
+        street: "1 Main St".to_string(),
+        city: "Springfield".to_string(),
+        postal_code: "12345".to_string(),
+        country: "US".to_string(),
+    }
+}
+
+fn cart() -> Vec<LineItem> {
+    vec![
+        LineItem::new("Mug", Money(900)),
+        LineItem::new("Mug", Money(900)),
+        LineItem::new("Lid", Money(200)),
+        LineItem::new("Mug", Money(900)),
+    ]
+}
+
+// The nearest warehouse has one mug and one lid; the rest is down south.
+fn shelves(picker: &NearestFirst) -> MultiWarehouseInventory<'_> {
+    MultiWarehouseInventory::new(picker, NORTH)
+        .with_stock(NORTH, "Mug", 1)
+        .with_stock(NORTH, "Lid", 1)
+        .with_stock(SOUTH, "Mug", 5)
+        .with_console(Console::silent())
+}
+
+#[test]
+fn a_cart_too_big_for_one_warehouse_is_split_across_two() {
+    let picker = NearestFirst(vec![NORTH, SOUTH]);
+    let inventory = shelves(&picker);
+
+    inventory.reserve(ReservationId(1), &cart()).unwrap();
+
+    assert_eq!(
+        inventory.sources(ReservationId(1)).unwrap(),
+        Some(vec![NORTH, SOUTH, NORTH, SOUTH])
+    );
+    assert_eq!(inventory.available_in(NORTH, "Mug"), 0);
+    assert_eq!(inventory.available_in(NORTH, "Lid"), 0);
+    assert_eq!(inventory.available_in(SOUTH, "Mug"), 3);
+    assert_eq!(inventory.stock_level("Mug").unwrap(), 3);
+}
+
+#[test]
+fn short_across_every_warehouse_holds_nothing() {
+    let picker = NearestFirst(vec![NORTH, SOUTH]);
+    let inventory = shelves(&picker);
+    let mut lids = cart();
+    lids.push(LineItem::new("Lid", Money(200)));
+
+    assert!(matches!(
+        inventory.reserve(ReservationId(1), &lids),
+        Err(OrderError::OutOfStock { item }) if item == "Lid"
+    ));
+    assert_eq!(inventory.sources(ReservationId(1)).unwrap(), None);
+    assert_eq!(inventory.available_in(NORTH, "Mug"), 1);
+    assert_eq!(inventory.available_in(SOUTH, "Mug"), 5);
+
+    // Nor does a warehouse the picker will not use make up the difference.
+    let north_only = NearestFirst(vec![NORTH]);
+    let inventory = shelves(&north_only);
+    assert!(matches!(
+        inventory.reserve(ReservationId(2), &cart()),
+        Err(OrderError::OutOfStock { item }) if item == "Mug"
+    ));
+    assert_eq!(inventory.stock_level("Mug").unwrap(), 6);
+}
+
+#[test]
+fn the_same_cart_on_the_same_shelves_always_splits_the_same_way() {
+    let split = || {
+        let inventory = MultiWarehouseInventory::new(&MostStockFirst, NORTH)
+            .with_stock(SOUTH, "Mug", 2)
+            .with_stock(NORTH, "Mug", 2)
+            .with_stock(SOUTH, "Lid", 1)
+            .with_console(Console::silent());
+        inventory.reserve(ReservationId(1), &cart()).unwrap();
+        inventory.sources(ReservationId(1)).unwrap()
+    };
+
+    // A tie goes to the lowest id, then each mug to whoever has more left.
+    assert_eq!(split(), Some(vec![NORTH, SOUTH, SOUTH, NORTH]));
+    assert_eq!(split(), split());
+}
+
+#[test]
+fn one_parcel_leaves_each_warehouse_with_the_lines_reserved_there() {
+    let picker = NearestFirst(vec![NORTH, SOUTH]);
+    let inventory = shelves(&picker);
+    let (carrier, clock) = (
+        Carrier::default(),
+        SteppingClock::starting_at(Timestamp(1_000)),
+    );
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let order = OrderService::new(&repo, &payment, &sender)
+        .place_order(cart())
+        .unwrap();
+    inventory
+        .reserve(ReservationId::of_order(order.id), &order.items)
+        .unwrap();
+
+    let shipped = ShippingService::new(&repo, &carrier, &clock)
+        .with_inventory(&inventory)
+        .ship_by_warehouse(order.id, &address())
+        .unwrap();
+
+    assert_eq!(shipped.status, OrderStatus::Shipped);
+    let sources = inventory
+        .sources(ReservationId::of_order(order.id))
+        .unwrap()
+        .unwrap();
+    let parcels: Vec<&Vec<usize>> = shipped.shipments.iter().map(|s| &s.item_indices).collect();
+    assert_eq!(parcels, [&vec![0, 2], &vec![1, 3]]);
+    for parcel in parcels {
+        assert!(
+            parcel
+                .iter()
+                .all(|&index| sources[index] == sources[parcel[0]])
+        );
+    }
+    assert_eq!(
+        carrier.0.lock().unwrap()[..],
+        [vec!["Mug", "Lid"], vec!["Mug", "Mug"]]
+    );
+}
+
+#[test]
+fn without_warehouses_what_is_left_ships_in_one_parcel() {
+    let (carrier, clock) = (
+        Carrier::default(),
+        SteppingClock::starting_at(Timestamp(1_000)),
+    );
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let order = OrderService::new(&repo, &payment, &sender)
+        .place_order(cart())
+        .unwrap();
+    let mut shipping = ShippingService::new(&repo, &carrier, &clock);
+    shipping.ship_items(order.id, &[2], &address()).unwrap();
+
+    let shipped = shipping.ship_by_warehouse(order.id, &address()).unwrap();
+
+    assert_eq!(shipped.status, OrderStatus::Shipped);
+    assert_eq!(shipped.shipments[1].item_indices, [0, 1, 3]);
+    // Nothing left: no empty parcel.
+    assert!(matches!(
+        shipping.ship_by_warehouse(order.id, &address()),
+        Err(OrderError::InvalidTransition { .. })
+    ));
+}
@@ -0,0 +1,235 @@
+// cargo test --test network_partition
+// The ex17 scenario, seeded: a batch of orders over one simulated network
+// that loses calls and partitions mid-batch, then the rounds of settlement,
+// outbox dispatch, resubmitted carts and refunds that follow the heal. Each
+// seed loses different calls; the invariants must hold for all of them.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::NetworkConditions;
+use hexa_lite::adapters::external::StripePaymentGateway;
+use hexa_lite::adapters::offline::{InMemoryPendingCharges, OfflineCapablePaymentGateway};
+use hexa_lite::adapters::outbox::{InMemoryDeadLetters, OutboxSender, RetryPolicy};
+use hexa_lite::adapters::parse_any_confirmation;
+use hexa_lite::adapters::sql::{FakeExecutor, SqlOrderRepository};
+use hexa_lite::adapters::webhook::WebhookSender;
+use hexa_lite::application::{Reconciliation, ReconciliationReport};
+use hexa_lite::ports::{ChargeLog, PaymentGateway, PendingCharges};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use hexa_lite::testing::OrderGenerator;
+use std::sync::Arc;
+use std::time::Duration;
+
+const BATCH: usize = 30;
+const MAX_ROUNDS: usize = 30;
+
+// Everything left once the rounds are over. The final reads go over the
+// same network, and are retried until they get through.
+struct Outcome {
+    network: Arc<NetworkConditions>,
+    orders: Vec<Order>,
+    report: ReconciliationReport,
+    // Order ids, in the order their webhooks were delivered.
+    confirmed: Vec<OrderId>,
+    dead_letters: usize,
+    queued: usize,
+    handed_back: usize,
+    rounds: usize,
+}
+
+fn run(seed: u64, lost_percent: u32) -> Outcome {
+    let network = Arc::new(
+        NetworkConditions::new(seed)
+            .with_latency(Duration::from_millis(10)..=Duration::from_millis(200))
+            .with_failure_probability(Percent::try_from(lost_percent).unwrap()),
+    );
+    let repo = loop {
+        let executor = FakeExecutor::new().with_network(network.clone());
+        if let Ok(repo) = SqlOrderRepository::new(executor) {
+            break repo.with_console(Console::silent());
+        }
+    };
+    let stripe = StripePaymentGateway::new()
+        .with_network(network.clone())
+        .with_console(Console::silent());
+    let pending = InMemoryPendingCharges::new();
+    let payment =
+        OfflineCapablePaymentGateway::new(stripe.clone(), &pending).with_console(Console::silent());
+    let webhook = WebhookSender::new("https://hooks.example.com/orders")
+        .unwrap()
+        .with_network(network.clone())
+        .with_console(Console::silent());
+    let dead_letters = InMemoryDeadLetters::new();
+    let outbox = OutboxSender::new(
+        webhook,
+        SteppingClock::starting_at(Timestamp(0)),
+        &dead_letters,
+    )
+    .with_policy(RetryPolicy::new(
+        Duration::from_secs(1),
+        Duration::from_secs(8),
+        25,
+    ))
+    .with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &outbox).with_pending_charges(&pending);
+    let dispatch = || {
+        outbox.clock().advance_secs(8);
+        outbox.run_dispatcher(outbox.clock().now()).unwrap();
+    };
+
+    let mut generator = OrderGenerator::new(seed);
+    let mut handed_back = Vec::new();
+    for i in 0..BATCH {
+        if i == BATCH / 3 {
+            network.partitioned();
+        }
+        if i == 2 * BATCH / 3 {
+            network.heal();
+        }
+        let cart = generator.next_cart();
+        if service.place_order(cart.clone()).is_err() {
+            handed_back.push(cart);
+        }
+        dispatch();
+    }
+
+    let mut rounds = 0;
+    while rounds < MAX_ROUNDS {
+        rounds += 1;
+        service.settle_pending().unwrap();
+        dispatch();
+        for cart in std::mem::take(&mut handed_back) {
+            if service.place_order(cart.clone()).is_err() {
+                handed_back.push(cart);
+            }
+        }
+        let Ok(charges) = stripe.charges() else {
+            continue;
+        };
+        let Ok(known) = repo.list() else {
+            continue;
+        };
+        let mut orphans = 0;
+        for charge in charges {
+            if !known.iter().any(|order| order.id == charge.order_id)
+                && stripe.refund_for(charge.order_id, charge.amount).is_err()
+            {
+                orphans += 1;
+            }
+        }
+        let settled = pending.pending().unwrap().is_empty();
+        if handed_back.is_empty() && settled && outbox.entries().is_empty() && orphans == 0 {
+            break;
+        }
+    }
+
+    let (orders, report) = loop {
+        if let (Ok(orders), Ok(report)) =
+            (repo.list(), Reconciliation::new(&repo, &stripe).reconcile())
+        {
+            break (orders, report);
+        }
+    };
+    let confirmed = outbox
+        .inner()
+        .delivered()
+        .iter()
+        .map(|request| order_id_of(&request.body))
+        .collect();
+    Outcome {
+        network,
+        orders,
+        report,
+        confirmed,
+        dead_letters: dead_letters.letters().len(),
+        queued: outbox.entries().len(),
+        handed_back: handed_back.len(),
+        rounds,
+    }
+}
+
+fn order_id_of(body: &str) -> OrderId {
+    parse_any_confirmation(body.as_bytes()).unwrap().order_id
+}
+
+fn assert_invariants(outcome: &Outcome) {
+    // No Paid order without its charge, or charged twice; no charge left
+    // for an order that was never saved.
+    assert!(outcome.report.missing_charges.is_empty());
+    assert!(outcome.report.orphan_charges.is_empty());
+    for mismatch in &outcome.report.amount_mismatches {
+        let order = outcome.orders.iter().find(|o| o.id == mismatch.order_id);
+        assert!(order.is_some_and(|order| order.status != OrderStatus::Paid));
+    }
+    // No lost notifications: every Paid order was confirmed, exactly once.
+    for order in &outcome.orders {
+        let sent = outcome
+            .confirmed
+            .iter()
+            .filter(|id| **id == order.id)
+            .count();
+        let expected = usize::from(order.status == OrderStatus::Paid);
+        assert_eq!(sent, expected, "{} is {:?}", order.id, order.status);
+    }
+    assert_eq!((outcome.dead_letters, outcome.queued), (0, 0));
+    // Every cart became an order.
+    assert_eq!(outcome.handed_back, 0);
+    assert!(outcome.rounds < MAX_ROUNDS);
+}
+
+#[test]
+fn a_partition_alone_ends_with_every_order_paid_charged_and_confirmed() {
+    for seed in 1..=5 {
+        let outcome = run(seed, 0);
+
+        assert_invariants(&outcome);
+        assert!(outcome.report.is_consistent());
+        assert_eq!(outcome.orders.len(), BATCH);
+        assert!(
+            outcome
+                .orders
+                .iter()
+                .all(|order| order.status == OrderStatus::Paid)
+        );
+        let stats = outcome.network.stats();
+        assert!(stats.partitioned > 0);
+        assert_eq!(stats.lost, 0);
+    }
+}
+
+// With calls lost as well, a settlement can charge a customer and then fail
+// to save the Paid status: settlement's documented "needs a look" case. Such
+// an order stays PaymentPending with its charge taken, and is never confirmed;
+// anything else pending is a bug.
+#[test]
+fn a_lossy_partition_holds_the_invariants_for_every_seed() {
+    for seed in 1..=20 {
+        let outcome = run(seed, 5);
+
+        assert_invariants(&outcome);
+        assert_eq!(outcome.orders.len(), BATCH);
+        for order in &outcome.orders {
+            if order.status != OrderStatus::Paid {
+                assert_eq!(order.status, OrderStatus::PaymentPending);
+                assert!(
+                    outcome
+                        .report
+                        .amount_mismatches
+                        .iter()
+                        .any(|mismatch| mismatch.order_id == order.id
+                            && mismatch.charged == order.total)
+                );
+            }
+        }
+        assert!(outcome.network.stats().lost > 0);
+    }
+}
+
+#[test]
+fn the_same_seed_plays_out_the_same_way() {
+    let first = run(11, 5);
+    let again = run(11, 5);
+
+    assert_eq!(first.confirmed, again.confirmed);
+    assert_eq!(first.network.stats(), again.network.stats());
+    assert_eq!(first.rounds, again.rounds);
+}
@@ -0,0 +1,162 @@
+// cargo test --test offline_payments
+// The payment provider goes away: orders are still taken, as PaymentPending,
+// and settle_pending charges them once it is back. A declined card is never
+// deferred.
+use hexa_lite::adapters::offline::{InMemoryPendingCharges, OfflineCapablePaymentGateway};
+use hexa_lite::ports::{PendingCharge, PendingCharges};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::sync::Mutex;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Provider {
+    Up,
+    Down,
+    Declining,
+}
+
+// A provider the test can switch off and on.
+struct SwitchableGateway {
+    provider: Mutex<Provider>,
+    charged: Mutex<Vec<Money>>,
+}
+
+impl SwitchableGateway {
+    fn new(provider: Provider) -> Self {
+        Self {
+            provider: Mutex::new(provider),
+            charged: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn switch(&self, provider: Provider) {
+        *self.provider.lock().unwrap() = provider;
+    }
+
+    fn charged(&self) -> Vec<Money> {
+        self.charged.lock().unwrap().clone()
+    }
+}
+
+impl PaymentGateway for SwitchableGateway {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        match *self.provider.lock().unwrap() {
+            Provider::Up => {
+                self.charged.lock().unwrap().push(amount);
+                Ok(())
+            }
+            Provider::Down => Err(OrderError::PaymentUnavailable),
+            Provider::Declining => Err(OrderError::PaymentFailed),
+        }
+    }
+}
+
+fn mouse() -> Vec<LineItem> {
+    vec![LineItem {
+        name: "Mouse".to_string(),
+        price: Money(2500),
+    }]
+}
+
+#[test]
+fn unreachable_provider_leaves_a_pending_charge() {
+    let transcript = ScenarioTranscript::new();
+    let pending = InMemoryPendingCharges::new();
+    let payment =
+        OfflineCapablePaymentGateway::new(SwitchableGateway::new(Provider::Down), &pending)
+            .with_console(transcript.console());
+    let sender = ConsoleSender::new().with_console(transcript.console());
+    let repo = InMemoryOrderRepository::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    let order = service.place_order(mouse()).unwrap();
+
+    assert_order(&order).has_status(OrderStatus::PaymentPending);
+    assert_eq!(
+        pending.pending().unwrap(),
+        vec![PendingCharge {
+            order_id: order.id,
+            amount: Money(2500),
+        }]
+    );
+    let stored = service.get_order(order.id).unwrap().unwrap();
+    assert_order(&stored).has_status(OrderStatus::PaymentPending);
+    // Nothing charged, so nothing confirmed yet.
+    assert!(!transcript.text().contains("confirmed"));
+}
+
+#[test]
+fn settlement_charges_and_confirms_once_the_provider_is_back() {
+    let transcript = ScenarioTranscript::new();
+    let pending = InMemoryPendingCharges::new();
+    let payment =
+        OfflineCapablePaymentGateway::new(SwitchableGateway::new(Provider::Down), &pending)
+            .with_console(transcript.console());
+    let sender = ConsoleSender::new().with_console(transcript.console());
+    let repo = InMemoryOrderRepository::new();
+    let mut service = OrderService::new(&repo, &payment, &sender).with_pending_charges(&pending);
+    let order = service.place_order(mouse()).unwrap();
+
+    let report = service.settle_pending().unwrap();
+    assert_eq!(report.still_pending, vec![order.id]);
+    assert_eq!(pending.pending().unwrap().len(), 1);
+
+    payment.inner().switch(Provider::Up);
+    let report = service.settle_pending().unwrap();
+
+    assert_eq!(report.paid, vec![order.id]);
+    assert!(report.failed.is_empty());
+    assert!(pending.pending().unwrap().is_empty());
+    assert_eq!(payment.inner().charged(), vec![Money(2500)]);
+    let settled = service.get_order(order.id).unwrap().unwrap();
+    assert_order(&settled).has_status(OrderStatus::Paid);
+    assert!(transcript.text().contains("Order #000001 confirmed"));
+
+    // Settled charges are gone: a second run charges nothing.
+    let report = service.settle_pending().unwrap();
+    assert!(report.paid.is_empty());
+    assert_eq!(payment.inner().charged(), vec![Money(2500)]);
+}
+
+#[test]
+fn card_declined_at_settlement_cancels_the_order() {
+    let pending = InMemoryPendingCharges::new();
+    let payment =
+        OfflineCapablePaymentGateway::new(SwitchableGateway::new(Provider::Down), &pending);
+    let sender = ConsoleSender::new();
+    let repo = InMemoryOrderRepository::new();
+    let mut service = OrderService::new(&repo, &payment, &sender).with_pending_charges(&pending);
+    let order = service.place_order(mouse()).unwrap();
+
+    payment.inner().switch(Provider::Declining);
+    let report = service.settle_pending().unwrap();
+
+    assert_eq!(report.declined, vec![order.id]);
+    assert!(pending.pending().unwrap().is_empty());
+    let cancelled = service.get_order(order.id).unwrap().unwrap();
+    assert_order(&cancelled).has_status(OrderStatus::Cancelled);
+}
+
+#[test]
+fn declined_card_fails_right_away_even_offline_capable() {
+    let pending = InMemoryPendingCharges::new();
+    let payment =
+        OfflineCapablePaymentGateway::new(SwitchableGateway::new(Provider::Declining), &pending);
+    let sender = ConsoleSender::new();
+    let repo = InMemoryOrderRepository::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    assert_err_variant!(service.place_order(mouse()), OrderError::PaymentFailed);
+    assert!(pending.pending().unwrap().is_empty());
+    assert!(!service.order_exists(OrderId(1)).unwrap());
+}
+
+#[test]
+fn plain_gateway_reports_the_outage() {
+    let payment = SwitchableGateway::new(Provider::Down);
+    let sender = ConsoleSender::new();
+    let repo = InMemoryOrderRepository::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    assert_err_variant!(service.place_order(mouse()), OrderError::PaymentUnavailable);
+}
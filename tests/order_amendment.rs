@@ -0,0 +1,293 @@
+// cargo test --test order_amendment
+// Changing an order after checkout: a new address, a gift note, or both,
+// until it ships. What the customer got wrong comes back field by field,
+// every field at once, through the service, over HTTP and on the command
+// line alike.
+use hexa_lite::adapters::cli::CliAdapter;
+use hexa_lite::adapters::http::{HttpAdapter, HttpRequest};
+use hexa_lite::application::{OrderBrowser, ShippingService};
+use hexa_lite::domain::{Address, MAX_GIFT_NOTE_CHARS, TrackingId};
+use hexa_lite::facade::DefaultFacade;
+use hexa_lite::ports::{AmendOrder, ShippingProvider};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+
+// Takes every parcel, tracks none of them.
+struct Carrier;
+
+impl ShippingProvider for Carrier {
+    fn ship(
+        &self,
+        order_id: OrderId,
+        _items: &[LineItem],
+        _address: &Address,
+    ) -> Result<TrackingId, OrderError> {
+        Ok(TrackingId(format!("parcel-{}", order_id.0)))
+    }
+}
+
+fn address() -> Address {
+    Address {
+        street: "1 Main St".to_string(),
+        city: "Springfield".to_string(),
+        postal_code: "12345".to_string(),
+        country: "US".to_string(),
+    }
+}
+
+fn cart() -> Vec<LineItem> {
+    vec![LineItem::new("Teapot", Money(2_400))]
+}
+
+fn amend(order_id: OrderId, new_address: Option<Address>, gift_note: Option<&str>) -> AmendOrder {
+    AmendOrder {
+        order_id,
+        new_address,
+        gift_note: gift_note.map(str::to_string),
+    }
+}
+
+fn field_errors(result: Result<Order, OrderError>) -> FieldErrorsText {
+    match result {
+        Err(OrderError::InvalidFields { errors }) => FieldErrorsText(errors.to_string()),
+        other => panic!("expected field errors, got {other:?}"),
+    }
+}
+
+// What a customer reads.
+#[derive(Debug, PartialEq)]
+struct FieldErrorsText(String);
+
+#[test]
+fn a_paid_order_takes_a_new_address_and_a_note() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let order = service.place_order(cart()).unwrap();
+
+    let amended = service
+        .amend_order(amend(
+            order.id,
+            Some(address()),
+            Some("  Happy birthday!\nLove, Sam "),
+        ))
+        .unwrap();
+
+    assert_eq!(amended.status, OrderStatus::Paid);
+    assert_eq!(amended.shipping_address, Some(address()));
+    assert_eq!(
+        amended.gift_note.as_deref(),
+        Some("Happy birthday!\nLove, Sam")
+    );
+    assert_eq!(repo.find(order.id).unwrap().unwrap(), amended);
+
+    // An empty note takes the note away, and leaves the address alone.
+    let amended = service
+        .amend_order(amend(order.id, None, Some("")))
+        .unwrap();
+    assert_eq!(amended.gift_note, None);
+    assert_eq!(amended.shipping_address, Some(address()));
+}
+
+#[test]
+fn every_field_is_checked_and_nothing_changes_on_a_refusal() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let order = service.place_order(cart()).unwrap();
+    let mut bad = address();
+    bad.city = " ".to_string();
+    bad.postal_code = "12345678901".to_string();
+    bad.country = "usa".to_string();
+
+    let errors =
+        field_errors(service.amend_order(amend(order.id, Some(bad), Some(&"x".repeat(312)))));
+
+    assert_eq!(
+        errors,
+        FieldErrorsText(
+            "new_address.city: required; new_address.postal_code: too long (11/10); \
+             new_address.country: must be a two-letter country code, like US; \
+             gift_note: too long (312/200)"
+                .to_string()
+        )
+    );
+    let stored = repo.find(order.id).unwrap().unwrap();
+    assert_eq!((stored.shipping_address, stored.gift_note), (None, None));
+
+    // Right at the limit is fine; a control character is not.
+    let note = "é".repeat(MAX_GIFT_NOTE_CHARS);
+    assert!(
+        service
+            .amend_order(amend(order.id, None, Some(&note)))
+            .is_ok()
+    );
+    assert_eq!(
+        field_errors(service.amend_order(amend(order.id, None, Some("hi\u{7}")))),
+        FieldErrorsText("gift_note: only printable characters and line breaks".to_string())
+    );
+    assert_eq!(
+        field_errors(service.amend_order(amend(order.id, None, None))),
+        FieldErrorsText("amendment: nothing to change".to_string())
+    );
+}
+
+#[test]
+fn a_shipped_or_cancelled_order_is_no_longer_amendable() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let shipped = service.place_order(cart()).unwrap();
+    let clock = SteppingClock::starting_at(Timestamp(1_000));
+    ShippingService::new(&repo, &Carrier, &clock)
+        .ship_items(shipped.id, &[0], &address())
+        .unwrap();
+    let cancelled = service.place_order(cart()).unwrap();
+    service.cancel_order(cancelled.id).unwrap();
+
+    assert!(matches!(
+        service.amend_order(amend(shipped.id, None, Some("Too late?"))),
+        Err(OrderError::NotAmendable {
+            status: OrderStatus::Shipped
+        })
+    ));
+    assert!(matches!(
+        service.amend_order(amend(cancelled.id, Some(address()), None)),
+        Err(OrderError::NotAmendable {
+            status: OrderStatus::Cancelled
+        })
+    ));
+    assert_eq!(repo.find(shipped.id).unwrap().unwrap().gift_note, None);
+    assert_err_variant!(
+        service.amend_order(amend(OrderId(99), None, Some("Hello"))),
+        OrderError::NotFound { .. }
+    );
+}
+
+#[test]
+fn over_http_each_field_error_is_its_own_entry() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let service = OrderService::new(&repo, &payment, &sender);
+    let http = HttpAdapter::new(DefaultFacade::new(service, OrderBrowser::new(&repo)));
+    let request = |method: &str, path: &str, body: &str| HttpRequest {
+        method: method.to_string(),
+        path: path.to_string(),
+        headers: Vec::new(),
+        body: body.as_bytes().to_vec(),
+    };
+    let placed = http.handle(&request(
+        "POST",
+        "/orders",
+        r#"{"items":[{"name":"Teapot","price_cents":2400}]}"#,
+    ));
+    assert_eq!(placed.status, 201);
+
+    let amended = http.handle(&request(
+        "PATCH",
+        "/orders/1",
+        r#"{"gift_note":"Happy birthday"}"#,
+    ));
+    assert_eq!(
+        (amended.status, amended.body.as_str()),
+        (200, r#"{"id":1,"status":"Paid","total_cents":2400}"#)
+    );
+
+    let long = format!(r#"{{"gift_note":"{}","colour":"red"}}"#, "x".repeat(312));
+    let refused = http.handle(&request("PATCH", "/orders/1", &long));
+    assert_eq!(refused.status, 422);
+    assert_eq!(
+        refused.body,
+        r#"{"error":"InvalidFields","code":"REQ-031","fields":[{"field":"colour","message":"unknown field"}]}"#
+    );
+    let long = format!(r#"{{"gift_note":"{}"}}"#, "x".repeat(312));
+    let refused = http.handle(&request("PATCH", "/orders/1", &long));
+    assert_eq!(
+        (refused.status, refused.body.as_str()),
+        (
+            422,
+            r#"{"error":"InvalidFields","code":"REQ-031","fields":[{"field":"gift_note","message":"too long (312/200)"}]}"#
+        )
+    );
+
+    assert_eq!(
+        http.handle(&request("PATCH", "/orders/7", r#"{"gift_note":""}"#))
+            .status,
+        404
+    );
+    assert_eq!(http.handle(&request("GET", "/orders/1", "")).status, 405);
+    assert_eq!(
+        http.handle(&request("PATCH", "/orders/x", "{}")).status,
+        404
+    );
+    assert_eq!(
+        repo.find(OrderId(1)).unwrap().unwrap().gift_note.as_deref(),
+        Some("Happy birthday")
+    );
+}
+
+#[test]
+fn over_http_a_shipped_order_is_a_conflict() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let order = service.place_order(cart()).unwrap();
+    let clock = SteppingClock::starting_at(Timestamp(1_000));
+    ShippingService::new(&repo, &Carrier, &clock)
+        .ship_items(order.id, &[0], &address())
+        .unwrap();
+    let http = HttpAdapter::new(DefaultFacade::new(service, OrderBrowser::new(&repo)));
+
+    let response = http.handle(&HttpRequest {
+        method: "PATCH".to_string(),
+        path: format!("/orders/{}", order.id.0),
+        headers: Vec::new(),
+        body: br#"{"gift_note":"Too late?"}"#.to_vec(),
+    });
+
+    assert_eq!(
+        (response.status, response.body.as_str()),
+        (
+            409,
+            r#"{"error":"NotAmendable","code":"ORD-030","status":"Shipped"}"#
+        )
+    );
+}
+
+#[test]
+fn on_the_command_line_field_errors_share_one_reply() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let mut cli = CliAdapter::new(OrderService::new(&repo, &payment, &sender));
+    let input = format!(
+        "place Teapot=2400\n\
+         amend #000001 address=1 Main St|Springfield|12345|US; note=Happy birthday\n\
+         amend 1 address=1 Main St||12345|usa\n\
+         amend 1 note={}\n\
+         amend 2 note=Hello\n\
+         amend one note=Hello\n",
+        "x".repeat(312)
+    );
+    let mut output = Vec::new();
+
+    cli.run_with_amendments(input.as_bytes(), &mut output)
+        .unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    let replies: Vec<&str> = output.lines().collect();
+    assert_eq!(
+        replies,
+        [
+            "ok #000001 $24.00",
+            "ok #000001 amended",
+            "error new_address.city: required; \
+             new_address.country: must be a two-letter country code, like US",
+            "error gift_note: too long (312/200)",
+            "error NotFound { id: OrderId(2) }",
+            "error InvalidOrder",
+        ]
+    );
+    let stored = repo.find(OrderId(1)).unwrap().unwrap();
+    assert_eq!(stored.shipping_address, Some(address()));
+    assert_eq!(stored.gift_note.as_deref(), Some("Happy birthday"));
+}
@@ -0,0 +1,145 @@
+// cargo test --test order_attachments
+// Notes and files added to placed orders: the caps on both, the files kept
+// in a BlobStore, in memory or in a directory, the order naming them only,
+// and purging an order deleting them with it.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::blob_store::FileBlobStore;
+use hexa_lite::adapters::in_memory::InMemoryBlobStore;
+use hexa_lite::adapters::retention::AgeAndStatusRetention;
+use hexa_lite::domain::{MAX_ATTACHMENT_BYTES, MAX_NOTE_BYTES, MAX_NOTES};
+use hexa_lite::ports::BlobStore;
+use hexa_lite::prelude::testing::SteppingClock;
+use hexa_lite::prelude::*;
+use std::env;
+use std::fs;
+use std::time::Duration;
+
+const DAY: u64 = 24 * 60 * 60;
+
+fn cart() -> Vec<LineItem> {
+    vec![LineItem::new("Lamp", Money(3_500))]
+}
+
+#[test]
+fn an_order_takes_so_many_notes_of_so_many_bytes() {
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let id = service.place_order(cart()).unwrap().id;
+    let at = Timestamp(1_700_000_000);
+
+    let too_long = "x".repeat(MAX_NOTE_BYTES + 1);
+    assert!(matches!(
+        service.add_note(id, "ann", &too_long, at),
+        Err(OrderError::NoteTooLong { bytes, limit: MAX_NOTE_BYTES }) if bytes == MAX_NOTE_BYTES + 1
+    ));
+    for n in 0..MAX_NOTES {
+        service
+            .add_note(id, "ann", &format!("call {n}"), at)
+            .unwrap();
+    }
+    assert!(matches!(
+        service.add_note(id, "bob", "one more", at),
+        Err(OrderError::TooManyNotes { limit: MAX_NOTES })
+    ));
+
+    let stored = repo.find(id).unwrap().unwrap();
+    assert_eq!(stored.notes.len(), MAX_NOTES);
+    assert_eq!(stored.notes[0].text, "call 0");
+    assert!(matches!(
+        service.add_note(OrderId(99), "ann", "hello", at),
+        Err(OrderError::NotFound { .. })
+    ));
+}
+
+#[test]
+fn a_file_is_kept_in_the_store_and_only_named_by_the_order() {
+    let blobs = InMemoryBlobStore::new().with_console(Console::silent());
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &sender).with_blob_store(&blobs);
+    let id = service.place_order(cart()).unwrap().id;
+
+    let too_large = vec![0; MAX_ATTACHMENT_BYTES + 1];
+    assert!(matches!(
+        service.attach_file(id, &too_large, "scan.tiff"),
+        Err(OrderError::AttachmentTooLarge {
+            limit: MAX_ATTACHMENT_BYTES,
+            ..
+        })
+    ));
+    assert!(blobs.is_empty());
+
+    let largest = vec![7; MAX_ATTACHMENT_BYTES];
+    let scan = service.attach_file(id, &largest, "scan.tiff").unwrap();
+    let invoice = service
+        .attach_file(id, b"%PDF-1.7", "../../My invoice (2).pdf")
+        .unwrap();
+
+    assert_eq!(invoice.filename, "My_invoice__2_.pdf");
+    assert_eq!(invoice.bytes, 8);
+    assert_eq!(blobs.get(scan.blob).unwrap(), Some(largest));
+    assert_eq!(
+        blobs.get(invoice.blob).unwrap().as_deref(),
+        Some(&b"%PDF-1.7"[..])
+    );
+    assert_eq!(repo.find(id).unwrap().unwrap().attachments, [scan, invoice]);
+}
+
+#[test]
+fn files_kept_in_a_directory_are_read_back_after_a_restart() {
+    let dir = env::temp_dir().join(format!("hexa_attachments_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+
+    let receipt = {
+        let blobs = FileBlobStore::open(&dir).unwrap();
+        let mut service = OrderService::new(&repo, &payment, &sender).with_blob_store(&blobs);
+        let id = service.place_order(cart()).unwrap().id;
+        service
+            .attach_file(id, b"paid in full", "receipt.txt")
+            .unwrap()
+    };
+    let blobs = FileBlobStore::open(&dir).unwrap();
+
+    assert_eq!(
+        blobs.get(receipt.blob).unwrap().as_deref(),
+        Some(&b"paid in full"[..])
+    );
+    blobs.delete(receipt.blob).unwrap();
+    assert_eq!(blobs.get(receipt.blob).unwrap(), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn purging_an_order_deletes_its_files() {
+    let blobs = InMemoryBlobStore::new().with_console(Console::silent());
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let clock = SteppingClock::starting_at(Timestamp(DAY));
+    let policy = AgeAndStatusRetention::new(Duration::from_secs(30 * DAY));
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_clock(&clock)
+        .with_blob_store(&blobs)
+        .with_retention_policy(&policy);
+    let gone = service.place_order(cart()).unwrap().id;
+    let old = service.attach_file(gone, b"old", "old.txt").unwrap();
+    service.cancel_order(gone).unwrap();
+    service.delete_order(gone).unwrap();
+    let kept = service.place_order(cart()).unwrap().id;
+    let current = service
+        .attach_file(kept, b"current", "current.txt")
+        .unwrap();
+
+    let report = service.purge(Timestamp(100 * DAY)).unwrap();
+
+    assert_eq!(report.purged, [gone]);
+    assert_eq!(blobs.get(old.blob).unwrap(), None);
+    assert!(blobs.get(current.blob).unwrap().is_some());
+    assert_eq!(blobs.len(), 1);
+}
@@ -0,0 +1,119 @@
+// cargo test --test order_delta
+// Order::diff against orders nobody wrote by hand: pairs of generated
+// carts, with the other fields changed or not depending on the round.
+// Whatever the pair, applying the diff to the first gives the second.
+use hexa_lite::domain::{
+    Address, Approval, ApproverId, Attachment, BlobId, Currency, OrderDelta, Shipment, TrackingId,
+};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::OrderGenerator;
+
+const ROUNDS: u64 = 500;
+
+// The next cart that makes an order: an empty one does not.
+fn order(generator: &mut OrderGenerator) -> Order {
+    loop {
+        if let Ok(order) = Order::new(OrderId(7), generator.next_cart()) {
+            return order;
+        }
+    }
+}
+
+// Round n changes the fields whose bit is set in n.
+fn reworked(mut order: Order, n: u64) -> Order {
+    let bit = |k: u32| n & (1 << k) != 0;
+    if bit(0) {
+        order.status = OrderStatus::Paid;
+    }
+    if bit(1) {
+        order.currency = Currency::Eur;
+    }
+    if bit(2) {
+        order.shipments.push(Shipment {
+            tracking: TrackingId(format!("T{n}")),
+            item_indices: vec![0],
+            shipped_at: Timestamp(n),
+        });
+    }
+    if bit(3) {
+        order.version = n as u32;
+        order.placed_at = Some(Timestamp(1_700_000_000 + n));
+    }
+    if bit(4) {
+        order.approval = Some(Approval::Approved {
+            by: ApproverId("ann".to_string()),
+        });
+        order.gift_note = Some(format!("note {n}"));
+    }
+    if bit(5) {
+        order.shipping_address = Some(Address {
+            street: "1 Main St".to_string(),
+            city: "Springfield".to_string(),
+            postal_code: "12345".to_string(),
+            country: "US".to_string(),
+        });
+    }
+    if bit(6) {
+        order
+            .add_note("bob", format!("note {n}"), Timestamp(n))
+            .unwrap();
+    }
+    if bit(7) {
+        order.attachments.push(Attachment {
+            blob: BlobId(n),
+            filename: format!("scan{n}.png"),
+            bytes: n,
+        });
+    }
+    order
+}
+
+#[test]
+fn applying_the_diff_gives_the_newer_order_exactly() {
+    let mut generator = OrderGenerator::new(42).items_per_cart(0..=6);
+    for n in 0..ROUNDS {
+        let old = reworked(order(&mut generator), n / 64);
+        // The same cart edited here and there, or another one altogether.
+        let mut new = if n % 2 == 0 {
+            let mut new = old.clone();
+            new.items.extend(generator.next_cart());
+            if new.items.len() > 2 {
+                new.items.remove(1);
+            }
+            new
+        } else {
+            order(&mut generator)
+        };
+        new = reworked(new, n);
+
+        let deltas = old.diff(&new);
+        let mut applied = old.clone();
+        applied.apply(&deltas).unwrap();
+
+        assert_eq!(applied, new, "round {n}: {deltas:?}");
+        assert!(old.diff(&old).is_empty());
+    }
+}
+
+#[test]
+fn a_single_field_changed_is_a_single_delta() {
+    let mut generator = OrderGenerator::new(7);
+    for k in 0..8 {
+        let old = order(&mut generator);
+        let new = reworked(old.clone(), 1 << k);
+
+        let deltas = old.diff(&new);
+
+        // Bits 3 and 4 change two fields each.
+        let fields = if k == 3 || k == 4 { 2 } else { 1 };
+        assert_eq!(deltas.len(), fields, "{deltas:?}");
+    }
+
+    let old = order(&mut generator);
+    let mut new = old.clone();
+    new.items[0].price = Money(new.items[0].price.0 + 1);
+    assert!(matches!(
+        old.diff(&new)[..],
+        [OrderDelta::ItemChanged { index: 0, .. }]
+    ));
+}
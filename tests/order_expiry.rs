@@ -0,0 +1,162 @@
+// cargo test --test order_expiry
+// Orders left unpaid past the TTL are cancelled by expire_stale: open carts
+// and offline orders never settled. Their stock goes back on the shelf,
+// OrderExpired is published, and nothing paid is ever touched.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::in_memory::InMemoryInventory;
+use hexa_lite::domain::OrderEvent;
+use hexa_lite::ports::{EventPublisher, Inventory, ReservationId};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const TTL: Duration = Duration::from_secs(3600);
+const NOW: Timestamp = Timestamp(10_000);
+
+#[derive(Default)]
+struct Recorder(Mutex<Vec<OrderEvent>>);
+
+impl EventPublisher for Recorder {
+    fn publish(&self, event: &OrderEvent) -> Result<(), OrderError> {
+        self.0.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+}
+
+// One mug per order, held under the order's own id.
+fn seed(
+    repo: &InMemoryOrderRepository,
+    inventory: &InMemoryInventory,
+    orders: &[(u32, OrderStatus, u64)],
+) {
+    for &(id, status, placed_at) in orders {
+        let mut order = Order::new(OrderId(id), vec![LineItem::new("Mug", Money(900))]).unwrap();
+        order.placed_at = Some(Timestamp(placed_at));
+        if status != OrderStatus::Placed {
+            order.transition_to(status).unwrap();
+        }
+        repo.save(&order).unwrap();
+        inventory.reserve(ReservationId(id), &order.items).unwrap();
+    }
+}
+
+fn status_of(repo: &InMemoryOrderRepository, id: u32) -> OrderStatus {
+    repo.find(OrderId(id)).unwrap().unwrap().status
+}
+
+#[test]
+fn only_unpaid_orders_older_than_the_ttl_expire() {
+    let clock = SteppingClock::starting_at(NOW);
+    let repo = InMemoryOrderRepository::new();
+    let inventory = InMemoryInventory::new()
+        .with_stock("Mug", 10)
+        .with_console(Console::silent());
+    let cutoff = NOW.0 - TTL.as_secs();
+    seed(
+        &repo,
+        &inventory,
+        &[
+            (1, OrderStatus::Placed, cutoff - 1),
+            (2, OrderStatus::Placed, cutoff),
+            (3, OrderStatus::PaymentPending, cutoff - 600),
+            (4, OrderStatus::PaymentPending, cutoff + 1),
+            (5, OrderStatus::Paid, cutoff - 5_000),
+            (6, OrderStatus::PendingApproval, cutoff - 5_000),
+        ],
+    );
+    let (payment, sender, events) = (
+        MockPaymentGateway::new(),
+        ConsoleSender::new(),
+        Recorder::default(),
+    );
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_inventory(&inventory)
+        .with_event_publisher(&events)
+        .with_order_ttl(TTL);
+
+    let report = service.expire_stale(clock.now()).unwrap();
+
+    // Exactly on the TTL is not older than it.
+    assert_eq!(report.expired, vec![OrderId(1), OrderId(3)]);
+    assert!(report.failed.is_empty());
+    assert_eq!(status_of(&repo, 1), OrderStatus::Cancelled);
+    assert_eq!(status_of(&repo, 3), OrderStatus::Cancelled);
+    for (id, status) in [
+        (2, OrderStatus::Placed),
+        (4, OrderStatus::PaymentPending),
+        (5, OrderStatus::Paid),
+        (6, OrderStatus::PendingApproval),
+    ] {
+        assert_eq!(status_of(&repo, id), status);
+    }
+    assert_eq!(
+        events.0.lock().unwrap()[..],
+        [
+            OrderEvent::OrderExpired {
+                order_id: OrderId(1)
+            },
+            OrderEvent::OrderExpired {
+                order_id: OrderId(3)
+            },
+        ]
+    );
+    // Six reserved, two released.
+    assert_eq!(inventory.stock_level("Mug").unwrap(), 10 - 6 + 2);
+
+    // An hour later, the next run finds the two that were too young.
+    clock.advance_secs(TTL.as_secs());
+    let report = service.expire_stale(clock.now()).unwrap();
+    assert_eq!(report.expired, vec![OrderId(2), OrderId(4)]);
+    assert_eq!(status_of(&repo, 5), OrderStatus::Paid);
+    assert_eq!(inventory.stock_level("Mug").unwrap(), 10 - 6 + 4);
+}
+
+// An inventory that cannot release anything.
+struct StuckShelf;
+
+impl Inventory for StuckShelf {
+    fn reserve(&self, _reservation: ReservationId, _items: &[LineItem]) -> Result<(), OrderError> {
+        Ok(())
+    }
+
+    fn confirm(&self, _reservation: ReservationId) -> Result<(), OrderError> {
+        Ok(())
+    }
+
+    fn release(&self, _reservation: ReservationId) -> Result<(), OrderError> {
+        Err(OrderError::StorageFailed)
+    }
+
+    fn restock(&self, _item: &str, _units: u32) -> Result<(), OrderError> {
+        Ok(())
+    }
+
+    fn stock_level(&self, _item: &str) -> Result<u32, OrderError> {
+        Ok(0)
+    }
+}
+
+#[test]
+fn a_failed_release_is_reported_with_its_reason() {
+    let repo = InMemoryOrderRepository::new();
+    let shelf = InMemoryInventory::new()
+        .with_stock("Mug", 1)
+        .with_console(Console::silent());
+    seed(&repo, &shelf, &[(1, OrderStatus::Placed, 0)]);
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_inventory(&StuckShelf)
+        .with_order_ttl(TTL);
+
+    let report = service.expire_stale(NOW).unwrap();
+
+    assert!(report.expired.is_empty());
+    assert!(matches!(
+        report.failed[..],
+        [(OrderId(1), OrderError::StorageFailed)]
+    ));
+    // The order stays expired: the next run has nothing left to do.
+    assert_eq!(status_of(&repo, 1), OrderStatus::Cancelled);
+    assert!(service.expire_stale(NOW).unwrap().failed.is_empty());
+}
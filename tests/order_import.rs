@@ -0,0 +1,178 @@
+// cargo test --test order_import
+// Orders brought over from another system's export, under its field names.
+// The fixture holds what such an export tends to: good orders, one whose
+// total does not add up, one with a status nobody here knows, an id given
+// twice and a stray value that is no order at all. A dry run reports on
+// exactly what a commit would do, and writes nothing.
+use hexa_lite::adapters::import::{
+    FieldMapping, ImportMode, ImportReport, Rejection, import_external,
+};
+use hexa_lite::domain::Timestamp;
+use hexa_lite::prelude::*;
+
+const LEGACY: &str = include_str!("fixtures/legacy_orders.json");
+
+const LEGACY_MAPPING: FieldMapping<'static> = FieldMapping {
+    id: "orderNumber",
+    items: "lines",
+    item_name: "title",
+    item_price: "price",
+    total: Some("amount_cents"),
+    status: Some("state"),
+    placed_at: Some("created"),
+};
+
+// Already here before the import: 1005 came over by hand.
+fn repository() -> InMemoryOrderRepository {
+    let repo = InMemoryOrderRepository::new();
+    let order = Order::new(OrderId(1005), vec![LineItem::new("Strainer", Money(800))]).unwrap();
+    repo.save(&order).unwrap();
+    repo
+}
+
+fn import(repo: &InMemoryOrderRepository, mode: ImportMode) -> ImportReport {
+    import_external(repo, LEGACY.as_bytes(), &LEGACY_MAPPING, mode).unwrap()
+}
+
+fn ids(ids: &[u32]) -> Vec<OrderId> {
+    ids.iter().copied().map(OrderId).collect()
+}
+
+// Reads from the orders given; any write is a test failure.
+struct ReadOnly(InMemoryOrderRepository);
+
+impl OrderReader for ReadOnly {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.0.find(id)
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.0.list()
+    }
+}
+
+impl OrderWriter for ReadOnly {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        panic!("a dry run saved order {:?}", order.id)
+    }
+}
+
+#[test]
+fn a_dry_run_reports_what_a_commit_does_and_leaves_the_repository_alone() {
+    let (dry, committed) = (repository(), repository());
+
+    let preview = import(&dry, ImportMode::DryRun { overwrite: false });
+    let report = import(&committed, ImportMode::Commit { overwrite: false });
+
+    assert_eq!(format!("{preview:?}"), format!("{report:?}"));
+    assert_eq!(report.imported, ids(&[1001, 1002]));
+    assert_eq!(report.duplicates, ids(&[1005, 1002]));
+    assert!(report.overwritten.is_empty() && report.failed.is_empty());
+    let rejected: Vec<usize> = report.rejected.iter().map(|r| r.record).collect();
+    assert_eq!(rejected, [2, 3, 6]);
+    assert!(matches!(
+        report.rejected[0].reason,
+        Rejection::TotalMismatch {
+            stated: Money(999),
+            computed: Money(4500)
+        }
+    ));
+    assert!(matches!(
+        &report.rejected[1].reason,
+        Rejection::UnknownStatus { status } if status == "lost in transit"
+    ));
+    assert!(matches!(report.rejected[2].reason, Rejection::NotAnObject));
+
+    let stored = |repo: &InMemoryOrderRepository| -> Vec<OrderId> {
+        repo.list().unwrap().iter().map(|order| order.id).collect()
+    };
+    assert_eq!(stored(&dry), ids(&[1005]));
+    assert_eq!(stored(&committed), ids(&[1001, 1002, 1005]));
+}
+
+#[test]
+fn imported_orders_come_in_as_the_domain_would_have_made_them() {
+    let repo = repository();
+    import(&repo, ImportMode::Commit { overwrite: false });
+
+    let teapot = repo.find(OrderId(1001)).unwrap().unwrap();
+    assert_eq!(teapot.status, OrderStatus::Shipped);
+    assert_eq!(teapot.total, Money(2400));
+    assert_eq!(teapot.items.len(), 2);
+    assert_eq!(teapot.placed_at, Some(Timestamp(1_700_000_000)));
+    // Given as a string of digits in the export.
+    let tea = repo.find(OrderId(1002)).unwrap().unwrap();
+    assert_eq!(tea.status, OrderStatus::Paid);
+}
+
+#[test]
+fn a_dry_run_writes_through_no_port() {
+    let repo = ReadOnly(repository());
+
+    for overwrite in [false, true] {
+        let report = import_external(
+            &repo,
+            LEGACY.as_bytes(),
+            &LEGACY_MAPPING,
+            ImportMode::DryRun { overwrite },
+        )
+        .unwrap();
+        assert_eq!(report.imported, ids(&[1001, 1002]));
+    }
+}
+
+#[test]
+fn an_existing_order_is_replaced_only_when_asked() {
+    let repo = repository();
+
+    let report = import(&repo, ImportMode::Commit { overwrite: true });
+
+    assert_eq!(report.overwritten, ids(&[1005]));
+    // The second 1002 in the file is still a duplicate.
+    assert_eq!(report.duplicates, ids(&[1002]));
+    let strainer = repo.find(OrderId(1005)).unwrap().unwrap();
+    assert_eq!(strainer.status, OrderStatus::Cancelled);
+}
+
+#[test]
+fn a_mapping_naming_a_field_the_export_lacks_rejects_every_record() {
+    let repo = repository();
+    let mapping = FieldMapping {
+        total: Some("grand_total"),
+        ..LEGACY_MAPPING
+    };
+
+    let report = import_external(
+        &repo,
+        LEGACY.as_bytes(),
+        &mapping,
+        ImportMode::Commit { overwrite: false },
+    )
+    .unwrap();
+
+    assert!(report.imported.is_empty() && report.duplicates.is_empty());
+    assert_eq!(report.rejected.len(), 7);
+    for rejected in &report.rejected[..6] {
+        assert!(
+            matches!(&rejected.reason, Rejection::MissingField { field } if field == "grand_total"),
+            "{rejected:?}"
+        );
+    }
+    assert_eq!(repo.list().unwrap().len(), 1);
+}
+
+#[test]
+fn input_that_is_no_array_of_records_fails_the_whole_import() {
+    let repo = repository();
+    for input in ["", "{\"orderNumber\":1}", "[{\"orderNumber\":"] {
+        assert!(matches!(
+            import_external(
+                &repo,
+                input.as_bytes(),
+                &LEGACY_MAPPING,
+                ImportMode::DryRun { overwrite: false }
+            ),
+            Err(OrderError::InvalidOrder)
+        ));
+    }
+}
@@ -0,0 +1,104 @@
+// cargo test --test order_keys
+// With an IdGenerator, every new order also gets a random UUID, and can be
+// found by it. Without one, orders are numbered exactly as before.
+use hexa_lite::adapters::ids::RandomIdGenerator;
+use hexa_lite::adapters::shared::SharedRepository;
+use hexa_lite::adapters::sql::{FakeExecutor, SqlOrderRepository};
+use hexa_lite::application::OrderBrowser;
+use hexa_lite::domain::{OrderKey, Uuid128};
+use hexa_lite::prelude::*;
+
+fn cart() -> Vec<LineItem> {
+    vec![LineItem {
+        name: "Keyboard".to_string(),
+        price: Money(12999),
+    }]
+}
+
+fn place_three(repository: &impl OrderWriter, ids: Option<&RandomIdGenerator>) -> Vec<Order> {
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(repository, &payment, &sender);
+    if let Some(ids) = ids {
+        service = service.with_id_generator(ids);
+    }
+    (0..3)
+        .map(|_| service.place_order(cart()).unwrap())
+        .collect()
+}
+
+#[test]
+fn generated_orders_are_found_by_their_uuid() {
+    let ids = RandomIdGenerator::new();
+    let repo = InMemoryOrderRepository::new();
+    let orders = place_three(&repo, Some(&ids));
+
+    let browser = OrderBrowser::new(&repo);
+    for order in &orders {
+        let uuid = order.uuid.expect("every order gets a UUID");
+        assert_eq!(order.key(), OrderKey::Random(uuid));
+        assert_eq!(
+            browser.get_by_key(order.key()).unwrap().as_ref(),
+            Some(order)
+        );
+        // Still reachable by number.
+        assert_eq!(browser.get(order.id).unwrap().as_ref(), Some(order));
+    }
+    assert_ne!(orders[0].uuid, orders[1].uuid);
+    assert_eq!(
+        browser
+            .get_by_key(OrderKey::Random(Uuid128::v4(7, 7)))
+            .unwrap(),
+        None
+    );
+}
+
+#[test]
+fn uuid_quoted_by_a_customer_finds_the_order_in_every_repository() {
+    let ids = RandomIdGenerator::new();
+    let in_memory = InMemoryOrderRepository::new();
+    let sql = SqlOrderRepository::new(FakeExecutor::new()).unwrap();
+    let shared = SharedRepository::new(InMemoryOrderRepository::new());
+
+    for orders in [
+        place_three(&in_memory, Some(&ids)),
+        place_three(&sql, Some(&ids)),
+        place_three(&shared, Some(&ids)),
+    ] {
+        let quoted: OrderKey = orders[1].key().to_string().parse().unwrap();
+        let found = [
+            in_memory.find_by_key(quoted).unwrap(),
+            sql.find_by_key(quoted).unwrap(),
+            shared.find_by_key(quoted).unwrap(),
+        ];
+        // Exactly the repository that stored it knows it.
+        assert_eq!(found.iter().flatten().collect::<Vec<_>>(), vec![&orders[1]]);
+    }
+}
+
+#[test]
+fn deleted_orders_are_not_found_by_uuid() {
+    let ids = RandomIdGenerator::new();
+    let repo = InMemoryOrderRepository::new();
+    let order = place_three(&repo, Some(&ids)).remove(0);
+
+    repo.soft_delete(order.id).unwrap();
+    assert_eq!(repo.find_by_key(order.key()).unwrap(), None);
+
+    repo.restore(order.id).unwrap();
+    assert_eq!(repo.find_by_key(order.key()).unwrap(), Some(order));
+}
+
+#[test]
+fn sequential_mode_is_unchanged() {
+    let repo = InMemoryOrderRepository::new();
+    let orders = place_three(&repo, None);
+
+    let ids: Vec<OrderId> = orders.iter().map(|order| order.id).collect();
+    assert_eq!(ids, vec![OrderId(1), OrderId(2), OrderId(3)]);
+    for order in &orders {
+        assert_eq!(order.uuid, None);
+        assert_eq!(order.key().to_string(), order.id.to_string());
+        assert_eq!(repo.find_by_key(order.key()).unwrap().as_ref(), Some(order));
+    }
+}
@@ -0,0 +1,135 @@
+// cargo test --test order_references
+// References are what a customer reads out over the phone: the service
+// gives each order one from whichever numbering scheme it is wired with,
+// the confirmation and the receipt carry it, and every repository finds the
+// order by it again. Without a generator orders have none, as before.
+use hexa_lite::adapters::references::{Checksummed, PrefixedRandom, YearSequence};
+use hexa_lite::domain::{OrderConfirmation, OrderReference};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::sync::Mutex;
+
+// 2024-12-31T23:59:00Z.
+const NEW_YEARS_EVE: Timestamp = Timestamp(1_735_689_540);
+
+fn pen() -> Vec<LineItem> {
+    vec![LineItem::new("Pen", Money(150))]
+}
+
+fn reference(text: &str) -> OrderReference {
+    OrderReference(text.to_string())
+}
+
+// Places two orders either side of midnight, through `repository`.
+fn place_over_new_year<R>(repository: &R) -> Vec<OrderConfirmation>
+where
+    R: OrderWriter + Sync + ?Sized,
+{
+    let clock = SteppingClock::starting_at(NEW_YEARS_EVE);
+    let sequence = YearSequence::new(&clock);
+    let sent = Mutex::new(Vec::new());
+    let sender = |confirmation: &OrderConfirmation| {
+        sent.lock().unwrap().push(confirmation.clone());
+        Ok(())
+    };
+    let payment = MockPaymentGateway::new();
+    let mut service = OrderService::new(repository, &payment, &sender)
+        .with_clock(&clock)
+        .with_reference_generator(&sequence);
+
+    service.place_order(pen()).unwrap();
+    clock.advance_secs(60);
+    service.place_order(pen()).unwrap();
+    sent.into_inner().unwrap()
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn every_repository_finds_an_order_by_its_reference() {
+    use hexa_lite::adapters::file_repository::{FileOrderRepository, JsonFormat};
+    use hexa_lite::adapters::sql::{FakeExecutor, SqlOrderRepository};
+    use std::fs;
+
+    let path = std::env::temp_dir().join(format!(
+        "hexa_lite_{}_order_references.orders",
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&path);
+    let in_memory = InMemoryOrderRepository::new();
+    let sql = SqlOrderRepository::new(FakeExecutor::new()).unwrap();
+    let file = FileOrderRepository::open(&path, JsonFormat).unwrap();
+    let repositories: [&(dyn OrderWriter + Sync); 3] = [&in_memory, &sql, &file];
+
+    for repository in repositories {
+        place_over_new_year(repository);
+
+        let first = repository.find_by_reference(&reference("2024-000001"));
+        assert_eq!(first.unwrap().map(|order| order.id), Some(OrderId(1)));
+        let second = repository.find_by_reference(&reference("2025-000001"));
+        assert_eq!(second.unwrap().map(|order| order.id), Some(OrderId(2)));
+        assert_eq!(
+            repository
+                .find_by_reference(&reference("2024-000002"))
+                .unwrap(),
+            None
+        );
+    }
+    // Read back from the file, not from what it kept in memory.
+    let reopened = FileOrderRepository::open(&path, JsonFormat).unwrap();
+    let found = reopened.find_by_reference(&reference("2025-000001"));
+    assert_eq!(found.unwrap().map(|order| order.id), Some(OrderId(2)));
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn the_confirmation_and_its_receipt_carry_the_reference() {
+    let repository = InMemoryOrderRepository::new();
+    let sent = place_over_new_year(&repository);
+
+    assert_eq!(sent[0].reference, Some(reference("2024-000001")));
+    assert_eq!(sent[0].receipt_lines()[0], "Reference 2024-000001");
+    assert_eq!(sent[1].receipt_lines()[0], "Reference 2025-000001");
+}
+
+#[test]
+fn without_a_generator_orders_have_no_reference() {
+    let repository = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repository, &payment, &sender);
+
+    let order = service.place_order(pen()).unwrap();
+
+    assert_eq!(order.reference, None);
+    assert!(!order.receipt_lines()[0].starts_with("Reference"));
+}
+
+#[test]
+fn a_mistyped_reference_is_caught_before_any_lookup() {
+    let checked = Checksummed::new(PrefixedRandom::new("ORD"));
+    let repository = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service =
+        OrderService::new(&repository, &payment, &sender).with_reference_generator(&checked);
+    let order = service.place_order(pen()).unwrap();
+    let given = order.reference.clone().unwrap();
+
+    // Read out over the phone, in lower case, one character wrong.
+    let heard = given.0.to_lowercase();
+    let last = heard.chars().next_back().unwrap();
+    let code = heard.len() - 2;
+    let wrong = if heard.as_bytes()[code] == b'2' {
+        "3"
+    } else {
+        "2"
+    };
+    let mistyped = format!("{}{wrong}{last}", &heard[..code]);
+
+    assert!(checked.validate(&OrderReference(heard)));
+    assert!(!checked.validate(&OrderReference(mistyped)));
+    assert_eq!(
+        repository.find_by_reference(&given).unwrap().map(|o| o.id),
+        Some(order.id)
+    );
+}
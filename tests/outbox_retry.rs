@@ -0,0 +1,181 @@
+// cargo test --test outbox_retry
+// Confirmations that cannot be sent are retried with exponential backoff,
+// not on every dispatcher run, and end up in the dead letters once out of
+// attempts. A SteppingClock makes the schedule exact to the second.
+use hexa_lite::adapters::outbox::{DispatchReport, InMemoryDeadLetters, OutboxSender, RetryPolicy};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::sync::Mutex;
+use std::time::Duration;
+
+const START: u64 = 1_700_000_000;
+
+// Fails while `failures` is above zero, counting down, then sends.
+struct FlakySender {
+    failures: Mutex<u32>,
+    sent: Mutex<Vec<OrderId>>,
+}
+
+impl FlakySender {
+    fn failing(failures: u32) -> Self {
+        Self {
+            failures: Mutex::new(failures),
+            sent: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn sent(&self) -> Vec<OrderId> {
+        self.sent.lock().unwrap().clone()
+    }
+}
+
+impl Sender for FlakySender {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        let mut failures = self.failures.lock().unwrap();
+        if *failures > 0 {
+            *failures -= 1;
+            return Err(OrderError::NotificationFailed);
+        }
+        self.sent.lock().unwrap().push(confirmation.order_id);
+        Ok(())
+    }
+}
+
+fn confirmation(id: u32) -> OrderConfirmation {
+    Order::new(
+        OrderId(id),
+        vec![LineItem {
+            name: "Rust Book".to_string(),
+            price: Money(4999),
+        }],
+    )
+    .unwrap()
+    .confirmation()
+}
+
+fn policy() -> RetryPolicy {
+    RetryPolicy::new(Duration::from_secs(10), Duration::from_secs(60), 5)
+}
+
+fn outbox<'a>(
+    sender: FlakySender,
+    dead_letters: &'a InMemoryDeadLetters,
+) -> OutboxSender<'a, FlakySender, SteppingClock> {
+    OutboxSender::new(
+        sender,
+        SteppingClock::starting_at(Timestamp(START)),
+        dead_letters,
+    )
+    .with_policy(policy())
+    .with_console(hexa_lite::adapters::Console::silent())
+}
+
+fn next_attempt(outbox: &OutboxSender<'_, FlakySender, SteppingClock>) -> Option<u64> {
+    outbox
+        .entries()
+        .first()
+        .map(|entry| entry.next_attempt_at.0 - START)
+}
+
+#[test]
+fn failed_sends_back_off_then_become_dead_letters() {
+    let dead_letters = InMemoryDeadLetters::new();
+    let outbox = outbox(FlakySender::failing(u32::MAX), &dead_letters);
+    outbox.send(&confirmation(1)).unwrap();
+    assert_eq!(next_attempt(&outbox), Some(0), "due as soon as queued");
+
+    // (when the dispatcher runs, when the next attempt is due)
+    let schedule = [(0, 10), (10, 30), (30, 70), (70, 130)];
+    for (attempt, (at, next)) in schedule.into_iter().enumerate() {
+        let report = outbox.run_dispatcher(Timestamp(START + at)).unwrap();
+        assert_eq!(report.rescheduled, 1, "attempt {}", attempt + 1);
+        assert_eq!(next_attempt(&outbox), Some(next));
+        assert_eq!(outbox.entries()[0].attempts, attempt as u32 + 1);
+
+        // One second early: nothing is tried.
+        let early = outbox.run_dispatcher(Timestamp(START + next - 1)).unwrap();
+        assert_eq!(
+            early,
+            DispatchReport {
+                waiting: 1,
+                ..DispatchReport::default()
+            }
+        );
+    }
+    assert!(dead_letters.letters().is_empty());
+
+    let last = outbox.run_dispatcher(Timestamp(START + 130)).unwrap();
+
+    assert_eq!(last.dead_lettered, 1);
+    assert!(outbox.entries().is_empty());
+    let letters = dead_letters.letters();
+    assert_eq!(letters.len(), 1);
+    assert_eq!(letters[0].confirmation, confirmation(1));
+    assert_eq!(letters[0].attempts, 5);
+    assert_eq!(letters[0].failed_at, Timestamp(START + 130));
+    assert_err_variant!(
+        Err::<(), _>(letters[0].last_error.clone()),
+        OrderError::NotificationFailed
+    );
+}
+
+#[test]
+fn a_send_that_succeeds_on_retry_leaves_the_outbox() {
+    let dead_letters = InMemoryDeadLetters::new();
+    let outbox = outbox(FlakySender::failing(2), &dead_letters);
+    outbox.send(&confirmation(1)).unwrap();
+
+    outbox.run_dispatcher(Timestamp(START)).unwrap();
+    outbox.run_dispatcher(Timestamp(START + 10)).unwrap();
+    let report = outbox.run_dispatcher(Timestamp(START + 30)).unwrap();
+
+    assert_eq!(report.sent, 1);
+    assert!(outbox.entries().is_empty());
+    assert_eq!(outbox.inner().sent(), [OrderId(1)]);
+    assert!(dead_letters.letters().is_empty());
+}
+
+#[test]
+fn a_waiting_entry_does_not_hold_back_newer_ones() {
+    let dead_letters = InMemoryDeadLetters::new();
+    let outbox = outbox(FlakySender::failing(1), &dead_letters);
+    outbox.send(&confirmation(1)).unwrap();
+    outbox.run_dispatcher(Timestamp(START)).unwrap();
+
+    outbox.clock().advance_secs(5);
+    outbox.send(&confirmation(2)).unwrap();
+    let report = outbox.run_dispatcher(Timestamp(START + 5)).unwrap();
+
+    assert_eq!(
+        report,
+        DispatchReport {
+            sent: 1,
+            waiting: 1,
+            ..DispatchReport::default()
+        }
+    );
+    assert_eq!(outbox.inner().sent(), [OrderId(2)]);
+    assert_eq!(
+        outbox.entries()[0].confirmation().unwrap().order_id,
+        OrderId(1)
+    );
+}
+
+#[test]
+fn the_service_only_queues() {
+    let dead_letters = InMemoryDeadLetters::new();
+    let sender = outbox(FlakySender::failing(0), &dead_letters);
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+
+    let order = OrderService::new(&repo, &payment, &sender)
+        .place_order(vec![LineItem {
+            name: "Keyboard".to_string(),
+            price: Money(12999),
+        }])
+        .unwrap();
+
+    assert!(sender.inner().sent().is_empty());
+    sender.run_dispatcher(Timestamp(START)).unwrap();
+    assert_eq!(sender.inner().sent(), [order.id]);
+}
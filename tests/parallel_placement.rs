@@ -0,0 +1,109 @@
+// cargo test --test parallel_placement
+// A thousand carts placed on eight workers, through a payment provider that
+// turns some of them down and keeps others waiting, behind a limit of three
+// concurrent calls. Whatever order the workers get to them in, cart i comes
+// back i-th with the i-th id, no id is given twice, and no order is stored
+// that was not charged.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::limited::ConcurrencyLimited;
+use hexa_lite::prelude::*;
+use hexa_lite::testing::OrderGenerator;
+use std::collections::HashSet;
+use std::sync::Mutex;
+use std::thread;
+
+const CARTS: usize = 1000;
+const WORKERS: usize = 8;
+
+// Which orders fail, and how, follows from their ids, so that every run
+// fails the same ones, whichever worker charges them.
+#[derive(Default)]
+struct Chaotic {
+    charged: Mutex<Vec<OrderId>>,
+}
+
+impl PaymentGateway for Chaotic {
+    fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+        Ok(())
+    }
+
+    fn charge_for(&self, order_id: OrderId, _amount: Money) -> Result<(), OrderError> {
+        let roll = order_id.0.wrapping_mul(2_654_435_761) >> 24;
+        if roll.is_multiple_of(3) {
+            thread::yield_now();
+        }
+        match roll % 10 {
+            0 => return Err(OrderError::PaymentUnavailable),
+            1 => return Err(OrderError::PaymentFailed),
+            _ => {}
+        }
+        self.charged.lock().unwrap().push(order_id);
+        Ok(())
+    }
+}
+
+#[test]
+fn parallel_placement_keeps_order_ids_and_charges_straight() {
+    let mut generator = OrderGenerator::new(452).items_per_cart(1..=4);
+    let carts: Vec<_> = (0..CARTS).map(|_| generator.next_cart()).collect();
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = ConcurrencyLimited::new(Chaotic::default(), 3);
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    let results = service.place_orders_parallel(carts.clone(), WORKERS);
+
+    assert_eq!(results.len(), CARTS);
+    let charged: HashSet<OrderId> = payment
+        .inner()
+        .charged
+        .lock()
+        .unwrap()
+        .iter()
+        .copied()
+        .collect();
+    let mut ids = HashSet::new();
+    let mut refused = 0;
+    for (at, (result, cart)) in results.iter().zip(&carts).enumerate() {
+        let id = OrderId(at as u32 + 1);
+        match result {
+            Ok(order) => {
+                assert_eq!(order.id, id);
+                assert_eq!(&order.items, cart);
+                assert!(ids.insert(order.id), "{id:?} given twice");
+                assert!(charged.contains(&id), "{id:?} stored without a charge");
+                assert_eq!(service.get_order(id).unwrap().as_ref(), Some(order));
+            }
+            Err(error) => {
+                assert!(matches!(
+                    error,
+                    OrderError::PaymentUnavailable | OrderError::PaymentFailed
+                ));
+                assert!(service.get_order(id).unwrap().is_none());
+                refused += 1;
+            }
+        }
+    }
+    assert!(refused > 0 && refused < CARTS / 2, "{refused} refused");
+    assert_eq!(charged.len(), CARTS - refused);
+}
+
+#[test]
+fn one_worker_or_none_places_the_carts_in_turn() {
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let pen = || vec![LineItem::new("Pen", Money(150))];
+
+    let results = service.place_orders_parallel(vec![pen(), Vec::new(), pen()], 0);
+
+    assert_eq!(results[0].as_ref().unwrap().id, OrderId(1));
+    assert!(matches!(results[1], Err(OrderError::InvalidOrder)));
+    assert_eq!(results[2].as_ref().unwrap().id, OrderId(3));
+    assert!(
+        service
+            .place_orders_parallel(Vec::new(), WORKERS)
+            .is_empty()
+    );
+}
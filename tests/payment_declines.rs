@@ -0,0 +1,170 @@
+// cargo test --test payment_declines
+// A declined card, and why. The simulated gateways decline by the last two
+// digits of the amount in cents (01 InsufficientFunds, 02 CardExpired, 03
+// SuspectedFraud, 04 TryAgainLater); place_order reports the reason, the
+// CLI and HTTP adapters pass its code on. Only TryAgainLater takes the
+// retry path: made again by a RetryingPaymentGateway, deferred by an
+// offline gateway and tried again at settlement. The others fail at once.
+use hexa_lite::adapters::cli::CliAdapter;
+use hexa_lite::adapters::external::StripePaymentGateway;
+use hexa_lite::adapters::http::{HttpAdapter, HttpRequest};
+use hexa_lite::adapters::offline::{InMemoryPendingCharges, OfflineCapablePaymentGateway};
+use hexa_lite::adapters::retry::RetryingPaymentGateway;
+use hexa_lite::adapters::{Console, DeclineScript};
+use hexa_lite::application::OrderBrowser;
+use hexa_lite::domain::DeclineReason;
+use hexa_lite::facade::DefaultFacade;
+use hexa_lite::ports::{ChargeLog, PendingCharges};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// What each reason's test amount ends in.
+const BY_CENTS: [(u32, DeclineReason); 4] = [
+    (12_901, DeclineReason::InsufficientFunds),
+    (12_902, DeclineReason::CardExpired),
+    (12_903, DeclineReason::SuspectedFraud),
+    (12_904, DeclineReason::TryAgainLater),
+];
+
+fn keyboard(cents: u32) -> Vec<LineItem> {
+    vec![LineItem::new("Keyboard", Money(cents))]
+}
+
+fn declining() -> MockPaymentGateway {
+    MockPaymentGateway::new()
+        .with_declines(DeclineScript::by_cents())
+        .with_console(Console::silent())
+}
+
+fn declined_for<T: std::fmt::Debug>(result: Result<T, OrderError>) -> DeclineReason {
+    match result {
+        Err(OrderError::PaymentDeclined { reason }) => reason,
+        other => panic!("expected a decline, got {other:?}"),
+    }
+}
+
+#[test]
+fn the_amount_says_which_reason_the_mock_declines_with() {
+    let payment = declining();
+    let (repo, sender) = (InMemoryOrderRepository::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    for (cents, reason) in BY_CENTS {
+        assert_eq!(declined_for(service.place_order(keyboard(cents))), reason);
+    }
+    let paid = service.place_order(keyboard(12_999)).unwrap();
+
+    assert_eq!(repo.list().unwrap(), vec![paid.clone()]);
+    assert_eq!(payment.charges().unwrap().len(), 1);
+    assert_eq!(payment.charges().unwrap()[0].order_id, paid.id);
+}
+
+#[test]
+fn the_fake_stripe_declines_the_same_amounts_and_any_it_is_told() {
+    let payment = StripePaymentGateway::new()
+        .with_declines(DeclineScript::by_cents().decline(Money(5_000), DeclineReason::CardExpired))
+        .with_console(Console::silent());
+
+    for (cents, reason) in BY_CENTS {
+        assert_eq!(declined_for(payment.charge(Money(cents))), reason);
+    }
+    assert_err_variant!(
+        payment.charge_for(OrderId(1), Money(5_000)),
+        OrderError::PaymentDeclined {
+            reason: DeclineReason::CardExpired
+        }
+    );
+    assert!(payment.charge_for(OrderId(2), Money(5_001)).is_err());
+    assert!(payment.charge_for(OrderId(3), Money(5_100)).is_ok());
+    assert_eq!(payment.charges().unwrap().len(), 1);
+}
+
+#[test]
+fn only_try_again_later_is_charged_again() {
+    for (cents, reason) in BY_CENTS {
+        let payment = RetryingPaymentGateway::new(declining())
+            .with_attempts(3)
+            .with_console(Console::silent());
+        let (repo, sender) = (InMemoryOrderRepository::new(), ConsoleSender::new());
+        let mut service = OrderService::new(&repo, &payment, &sender);
+
+        assert_eq!(declined_for(service.place_order(keyboard(cents))), reason);
+
+        let retries = if reason.is_retryable() { 2 } else { 0 };
+        assert_eq!(payment.retries(), retries, "{reason:?}");
+        assert!(repo.list().unwrap().is_empty());
+    }
+}
+
+#[test]
+fn an_issuer_that_asked_to_try_later_may_then_accept() {
+    let calls = AtomicU32::new(0);
+    let busy_once = |_: Money| match calls.fetch_add(1, Ordering::SeqCst) {
+        0 => Err(OrderError::PaymentDeclined {
+            reason: DeclineReason::TryAgainLater,
+        }),
+        _ => Ok(()),
+    };
+    let payment = RetryingPaymentGateway::new(&busy_once).with_console(Console::silent());
+    let (repo, sender) = (InMemoryOrderRepository::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    let order = service.place_order(keyboard(12_999)).unwrap();
+
+    assert_order(&order).has_status(OrderStatus::Paid);
+    assert_eq!(calls.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn offline_only_try_again_later_waits_for_settlement() {
+    let pending = InMemoryPendingCharges::new();
+    let payment =
+        OfflineCapablePaymentGateway::new(declining(), &pending).with_console(Console::silent());
+    let (repo, sender) = (InMemoryOrderRepository::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender).with_pending_charges(&pending);
+
+    for (cents, reason) in &BY_CENTS[..3] {
+        assert_eq!(declined_for(service.place_order(keyboard(*cents))), *reason);
+    }
+    assert!(pending.pending().unwrap().is_empty());
+
+    let later = service.place_order(keyboard(12_904)).unwrap();
+    assert_order(&later).has_status(OrderStatus::PaymentPending);
+
+    // The issuer still says later: the charge stays for the next run.
+    let report = service.settle_pending().unwrap();
+    assert_eq!(report.still_pending, vec![later.id]);
+    assert_eq!(pending.pending().unwrap().len(), 1);
+}
+
+#[test]
+fn the_cli_and_http_replies_carry_the_reasons_code() {
+    let payment = declining();
+    let (repo, sender) = (InMemoryOrderRepository::new(), ConsoleSender::new());
+    let mut cli = CliAdapter::new(OrderService::new(&repo, &payment, &sender));
+    assert_eq!(
+        cli.handle_line("place Keyboard=12901").as_deref(),
+        Some("error PaymentDeclined insufficient_funds")
+    );
+    assert_eq!(
+        cli.handle_line("place Keyboard=12904").as_deref(),
+        Some("error PaymentDeclined try_again_later")
+    );
+
+    let service = OrderService::new(&repo, &payment, &sender);
+    let http = HttpAdapter::new(DefaultFacade::new(service, OrderBrowser::new(&repo)));
+    let response = http.handle(&HttpRequest {
+        method: "POST".to_string(),
+        path: "/orders".to_string(),
+        headers: Vec::new(),
+        body: br#"{"items":[{"name":"Keyboard","price_cents":12903}]}"#.to_vec(),
+    });
+    assert_eq!(
+        (response.status, response.body.as_str()),
+        (
+            402,
+            r#"{"error":"PaymentDeclined","code":"PAY-039","reason":"fraudulent"}"#
+        )
+    );
+}
@@ -0,0 +1,172 @@
+// cargo test --test payment_gateway_contract
+// What every payment gateway owes the service, in any currency: the amount
+// charged is the amount recorded, in the same minor units, never scaled on
+// the way; it is printed the way its currency is written; and a refund of
+// it takes it back, all at once or a part at a time. Each adapter, bare or decorated, runs the same checks
+// in EUR (cents), JPY (no minor unit) and KWD (fils, three decimals).
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::SharedBuffer;
+use hexa_lite::adapters::breaker::CircuitBreaker;
+use hexa_lite::adapters::capped::CappedPaymentGateway;
+use hexa_lite::adapters::external::StripePaymentGateway;
+use hexa_lite::adapters::offline::{InMemoryPendingCharges, OfflineCapablePaymentGateway};
+use hexa_lite::adapters::rates::FixedRates;
+use hexa_lite::domain::{Currency, ExchangeRate, ForeignLineItem};
+use hexa_lite::ports::{ChargeLog, ChargeRecord};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+
+// One amount per currency, and how a receipt writes it.
+const AMOUNTS: [(Currency, Money, &str); 3] = [
+    (Currency::Eur, Money(4999), "€49.99"),
+    (Currency::Jpy, Money(500), "¥500"),
+    (Currency::Kwd, Money(1_250), "KD 1.250"),
+];
+
+trait Gateway: PaymentGateway + ChargeLog {}
+impl<G: PaymentGateway + ChargeLog> Gateway for G {}
+
+// A gateway under test, printing to `console`, set up for `currency`.
+type Setup = fn(Currency, Console) -> Box<dyn Gateway>;
+
+fn mock(currency: Currency, console: Console) -> MockPaymentGateway {
+    MockPaymentGateway::new()
+        .with_currency(currency)
+        .with_console(console)
+}
+
+fn gateways() -> Vec<(&'static str, Setup)> {
+    vec![
+        ("MockPaymentGateway", |currency, console| {
+            Box::new(mock(currency, console))
+        }),
+        ("StripePaymentGateway", |currency, console| {
+            Box::new(
+                StripePaymentGateway::new()
+                    .with_currency(currency)
+                    .with_console(console),
+            )
+        }),
+        ("CappedPaymentGateway", |currency, console| {
+            let clock = SteppingClock::starting_at(Timestamp(1_000));
+            Box::new(CappedPaymentGateway::new(
+                mock(currency, console),
+                clock,
+                Money(1_000_000),
+            ))
+        }),
+        ("CircuitBreaker", |currency, console| {
+            let clock = SteppingClock::starting_at(Timestamp(1_000));
+            Box::new(CircuitBreaker::new(mock(currency, console), clock))
+        }),
+        ("OfflineCapablePaymentGateway", |currency, console| {
+            // The decorator borrows its queue; a test may as well leak it.
+            let pending: &'static InMemoryPendingCharges =
+                Box::leak(Box::new(InMemoryPendingCharges::new()));
+            Box::new(
+                OfflineCapablePaymentGateway::new(mock(currency, console), pending)
+                    .with_console(Console::silent()),
+            )
+        }),
+    ]
+}
+
+#[test]
+fn every_gateway_charges_the_exact_minor_units_it_is_given() {
+    for (name, setup) in gateways() {
+        for (currency, amount, shown) in AMOUNTS {
+            let buffer = SharedBuffer::new();
+            let gateway = setup(currency, Console::to(buffer.clone()));
+
+            gateway.charge_for(OrderId(1), amount).unwrap();
+
+            assert_eq!(
+                gateway.charges().unwrap(),
+                [ChargeRecord {
+                    order_id: OrderId(1),
+                    amount
+                }],
+                "{name} in {currency}"
+            );
+            let printed = buffer.contents();
+            assert!(
+                printed.contains(&format!("Charging {shown}\n")),
+                "{name} in {currency}: {printed}"
+            );
+        }
+    }
+}
+
+#[test]
+fn every_gateway_refunds_what_it_charged() {
+    for (name, setup) in gateways() {
+        for (currency, amount, _) in AMOUNTS {
+            let gateway = setup(currency, Console::silent());
+            gateway.charge_for(OrderId(1), amount).unwrap();
+
+            // The same amount in another scale is not that charge.
+            assert!(
+                gateway
+                    .refund_for(OrderId(1), Money(amount.0 * 100))
+                    .is_err(),
+                "{name} in {currency}"
+            );
+            gateway.refund_for(OrderId(1), amount).unwrap();
+            assert!(
+                gateway.charges().unwrap().is_empty(),
+                "{name} in {currency}"
+            );
+        }
+    }
+}
+
+#[test]
+fn every_gateway_refunds_a_charge_a_part_at_a_time() {
+    for (name, setup) in gateways() {
+        for (currency, amount, _) in AMOUNTS {
+            let gateway = setup(currency, Console::silent());
+            gateway.charge_for(OrderId(1), amount).unwrap();
+            let part = Money(amount.0 / 5);
+
+            gateway.refund_for(OrderId(1), part).unwrap();
+            let left = Money(amount.0 - part.0);
+            assert_eq!(
+                gateway.charges().unwrap(),
+                [ChargeRecord {
+                    order_id: OrderId(1),
+                    amount: left
+                }],
+                "{name} in {currency}"
+            );
+            // No more than what is left.
+            assert!(gateway.refund_for(OrderId(1), amount).is_err());
+            gateway.refund_for(OrderId(1), left).unwrap();
+            assert!(
+                gateway.charges().unwrap().is_empty(),
+                "{name} in {currency}"
+            );
+        }
+    }
+}
+
+#[test]
+fn a_euro_cart_is_charged_in_yen_once_converted() {
+    let repo = InMemoryOrderRepository::new();
+    let rates =
+        FixedRates::new().with_rate(Currency::Eur, Currency::Jpy, ExchangeRate(16_000_000_000));
+    let buffer = SharedBuffer::new();
+    let payment = mock(Currency::Jpy, Console::to(buffer.clone()));
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let mut service = OrderService::new(&repo, &payment, &sender).with_exchange_rates(&rates);
+
+    let mug = ForeignLineItem {
+        name: "Mug".to_string(),
+        price: Money(1_250).in_currency(Currency::Eur), // 12.50 EUR at 160.00
+    };
+    let converted = service.place_order_in(Currency::Jpy, vec![mug]).unwrap();
+
+    assert_order(&converted.order)
+        .has_total_cents(2_000)
+        .has_status(OrderStatus::Paid);
+    assert!(buffer.contents().contains("Charging ¥2000\n"));
+}
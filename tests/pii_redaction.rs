@@ -0,0 +1,85 @@
+// cargo test --test pii_redaction
+// A customer's email, phone and street must not leak through Debug, even
+// when an adapter puts the whole Customer into an error: what the error
+// reporter captures, and the JSON line it would send on, show the redacted
+// forms only.
+use hexa_lite::adapters::error_reporting::{InMemoryErrorReporter, to_json_line};
+use hexa_lite::domain::{Address, Customer, Redact};
+use hexa_lite::ports::{FraudScreen, RiskVerdict};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::SteppingClock;
+
+const EMAIL: &str = "jane.doe@example.com";
+
+// Careless, as one outside this crate could be: the reason is the whole
+// customer, Debug-printed.
+struct Careless;
+
+impl FraudScreen for Careless {
+    fn assess(&self, _order: &Order, customer: &Customer) -> Result<RiskVerdict, OrderError> {
+        Ok(RiskVerdict::Reject {
+            reason: format!("suspicious {customer:?}"),
+        })
+    }
+}
+
+fn address() -> Address {
+    Address {
+        street: "12 rue de la Paix".to_string(),
+        city: "Lyon".to_string(),
+        postal_code: "69001".to_string(),
+        country: "FR".to_string(),
+    }
+}
+
+fn jane() -> Customer {
+    Customer::new("Jane")
+        .with_email(EMAIL)
+        .with_phone("+33 6 12 34 56 78")
+        .with_address(address())
+}
+
+#[test]
+fn a_captured_error_report_holds_no_raw_email() {
+    let reporter = InMemoryErrorReporter::new();
+    let clock = SteppingClock::starting_at(Timestamp(1_700_000_000));
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_fraud_screen(&Careless)
+        .with_error_reporter(&reporter, &clock);
+
+    let result = service.place_order_for(&jane(), vec![LineItem::new("Pen", Money(150))]);
+
+    assert!(matches!(result, Err(OrderError::FraudSuspected { .. })));
+    let reports = reporter.reports();
+    assert_eq!(reports.len(), 1);
+    for rendering in [format!("{:?}", reports[0]), to_json_line(&reports[0])] {
+        assert!(!rendering.contains(EMAIL), "{rendering}");
+        assert!(!rendering.contains("rue de la Paix"), "{rendering}");
+        assert!(!rendering.contains("56 78"), "{rendering}");
+        assert!(rendering.contains("j***@example.com"), "{rendering}");
+    }
+}
+
+#[test]
+fn redacted_renderings_are_pinned() {
+    let jane = jane();
+
+    assert_eq!(jane.redacted(), "Jane (j***@example.com, ***78, Lyon)");
+    assert_eq!(jane.contact.redacted(), "j***@example.com, ***78");
+    assert_eq!(address().redacted(), "Lyon");
+    assert_eq!(
+        format!("{:?}", jane.contact),
+        r#"Contact { email: Some("j***@example.com"), phone: Some("***78") }"#
+    );
+    assert_eq!(
+        format!("{:?}", address()),
+        r#"Address { city: "Lyon", .. }"#
+    );
+
+    // An order shipping to the address shows no more of it.
+    let mut order = Order::new(OrderId(1), vec![LineItem::new("Pen", Money(150))]).unwrap();
+    order.shipping_address = Some(address());
+    assert!(!format!("{order:?}").contains("rue de la Paix"));
+}
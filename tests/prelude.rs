@@ -0,0 +1,65 @@
+// cargo test --test prelude
+// Compile test: a whole hexagon wired with nothing but the prelude.
+// If a public signature reached through the prelude mentioned a type that
+// is not exported, this file would stop compiling.
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+
+// A user-defined adapter, implemented only with prelude names.
+struct DecliningGateway;
+
+impl PaymentGateway for DecliningGateway {
+    fn charge(&self, _amount: Money) -> Result<(), OrderError> {
+        Err(OrderError::PaymentFailed)
+    }
+}
+
+fn keyboard() -> LineItem {
+    LineItem {
+        name: "Keyboard".to_string(),
+        price: Money(12999),
+    }
+}
+
+#[test]
+fn prelude_is_enough_to_wire_and_run_the_service() {
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    let order: Order = PlaceOrderUseCase::place_order(
+        &mut service,
+        PlaceOrder {
+            items: vec![keyboard()],
+            placed_at: None,
+        },
+    )
+    .unwrap();
+
+    let asserter: OrderAssert<'_> = assert_order(&order);
+    asserter.has_id(1).has_total_cents(12999);
+    let found: Option<Order> = service.get_order(OrderId(1)).unwrap();
+    assert!(found.is_some());
+}
+
+#[test]
+fn prelude_is_enough_to_write_an_adapter() {
+    let repo = InMemoryOrderRepository::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &DecliningGateway, &sender);
+
+    assert_err_variant!(
+        service.place_order(vec![keyboard()]),
+        OrderError::PaymentFailed
+    );
+}
+
+#[test]
+fn rates_are_exported() {
+    let vat = BasisPoints::try_from(2000).unwrap();
+    let tip = Percent::try_from(10).unwrap();
+
+    assert_eq!(vat.of(Money(1000)), Money(200));
+    assert_eq!(tip.of(Money(1000)), Money(100));
+}
@@ -0,0 +1,150 @@
+// cargo test --test reconciliation
+// Stored orders against the gateway's charge log: every paid order charged
+// its total, once, and no charge without an order. The inconsistencies are
+// seeded by going around OrderService, straight to the adapters.
+use hexa_lite::adapters::capped::CappedPaymentGateway;
+use hexa_lite::application::{AmountMismatch, Reconciliation};
+use hexa_lite::ports::{ChargeLog, ChargeRecord};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+
+fn cart(name: &str, cents: u32) -> Vec<LineItem> {
+    vec![LineItem {
+        name: name.to_string(),
+        price: Money(cents),
+    }]
+}
+
+// Three orders placed and charged the normal way: #1 $25.00, #2 $49.99,
+// #3 $129.99.
+fn seed(repo: &InMemoryOrderRepository, payment: &MockPaymentGateway) {
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(repo, payment, &sender);
+    service.place_order(cart("Mouse", 2500)).unwrap();
+    service.place_order(cart("Keyboard", 4999)).unwrap();
+    service.place_order(cart("Monitor", 12999)).unwrap();
+}
+
+// What a payment forgotten by the gateway looks like: paid, never charged.
+fn paid_behind_the_gateways_back(id: u32, cents: u32) -> Order {
+    let mut order = Order::new(OrderId(id), cart("Webcam", cents)).unwrap();
+    order.transition_to(OrderStatus::Paid).unwrap();
+    order
+}
+
+#[test]
+fn orders_placed_through_the_service_reconcile() {
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    seed(&repo, &payment);
+
+    let report = Reconciliation::new(&repo, &payment).reconcile().unwrap();
+
+    assert!(report.is_consistent(), "{report:?}");
+    assert_eq!(payment.charges().unwrap().len(), 3);
+}
+
+#[test]
+fn paid_order_without_a_charge_is_missing() {
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    seed(&repo, &payment);
+    repo.save(&paid_behind_the_gateways_back(4, 3000)).unwrap();
+
+    let report = Reconciliation::new(&repo, &payment).reconcile().unwrap();
+
+    assert_eq!(report.missing_charges, vec![OrderId(4)]);
+    assert!(report.orphan_charges.is_empty());
+    assert!(report.amount_mismatches.is_empty());
+}
+
+#[test]
+fn charge_for_an_unknown_order_is_an_orphan() {
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    seed(&repo, &payment);
+    payment.charge_for(OrderId(99), Money(1500)).unwrap();
+
+    let report = Reconciliation::new(&repo, &payment).reconcile().unwrap();
+
+    assert_eq!(
+        report.orphan_charges,
+        vec![ChargeRecord {
+            order_id: OrderId(99),
+            amount: Money(1500),
+        }]
+    );
+    assert!(report.missing_charges.is_empty());
+    assert!(report.amount_mismatches.is_empty());
+}
+
+#[test]
+fn wrong_double_and_unexpected_charges_are_mismatches() {
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    seed(&repo, &payment);
+    // #1 charged twice.
+    payment.charge_for(OrderId(1), Money(2500)).unwrap();
+    // #4 paid, but charged the wrong amount.
+    repo.save(&paid_behind_the_gateways_back(4, 3000)).unwrap();
+    payment.charge_for(OrderId(4), Money(300)).unwrap();
+    // #5 never paid, charged all the same.
+    repo.save(&Order::new(OrderId(5), cart("Headset", 7000)).unwrap())
+        .unwrap();
+    payment.charge_for(OrderId(5), Money(7000)).unwrap();
+
+    let report = Reconciliation::new(&repo, &payment).reconcile().unwrap();
+
+    assert_eq!(
+        report.amount_mismatches,
+        vec![
+            AmountMismatch {
+                order_id: OrderId(1),
+                expected: Money(2500),
+                charged: Money(5000),
+            },
+            AmountMismatch {
+                order_id: OrderId(4),
+                expected: Money(3000),
+                charged: Money(300),
+            },
+            AmountMismatch {
+                order_id: OrderId(5),
+                expected: Money(0),
+                charged: Money(7000),
+            },
+        ]
+    );
+    assert!(report.missing_charges.is_empty());
+    assert!(report.orphan_charges.is_empty());
+    assert!(!report.is_consistent());
+}
+
+#[test]
+fn decorated_gateway_reports_the_inner_log() {
+    let repo = InMemoryOrderRepository::new();
+    let payment = CappedPaymentGateway::new(
+        MockPaymentGateway::new(),
+        SteppingClock::starting_at(Timestamp(1_700_000_000)),
+        Money(10_000),
+    );
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    service.place_order(cart("Mouse", 2500)).unwrap();
+    // Refused by the cap: no charge, and no order.
+    assert_err_variant!(
+        service.place_order(cart("Monitor", 12999)),
+        OrderError::DailyCapExceeded { .. }
+    );
+
+    let report = Reconciliation::new(&repo, &payment).reconcile().unwrap();
+
+    assert!(report.is_consistent(), "{report:?}");
+    assert_eq!(
+        payment.charges().unwrap(),
+        vec![ChargeRecord {
+            order_id: OrderId(1),
+            amount: Money(2500),
+        }]
+    );
+}
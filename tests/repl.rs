@@ -0,0 +1,79 @@
+// cargo test --test repl
+// A scripted session through adapters::repl, every reply pinned in
+// tests/golden/repl_session.txt. When the output changes on purpose:
+//
+//     UPDATE_GOLDEN=1 cargo test --test repl
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::repl::{PROMPT, Repl, USAGE};
+use hexa_lite::application::OrderBrowser;
+use hexa_lite::facade::DefaultFacade;
+use hexa_lite::prelude::testing::ScenarioTranscript;
+use hexa_lite::prelude::*;
+
+const SESSION: &str = r#"health
+order list
+order add Keyboard 12999
+order add "USB cable" 999
+order checkout
+order add Monitor 18900
+order checkout
+order checkout
+order add Sticker 0
+order checkout
+order cancel 1
+order cancel #000009
+order list
+report revenue
+help status
+ship 2
+
+quit
+order list
+"#;
+
+// What the session printed, with each command after its prompt as a
+// terminal would show it.
+fn play(script: &str) -> String {
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let payment = MockPaymentGateway::new().with_console(Console::silent());
+    let sender = ConsoleSender::new().with_console(Console::silent());
+    let service = OrderService::new(&repo, &payment, &sender);
+    let mut repl = Repl::new(DefaultFacade::new(service, OrderBrowser::new(&repo)));
+
+    let mut output = Vec::new();
+    repl.run(script.as_bytes(), &mut output).unwrap();
+    let mut commands = script.lines();
+    String::from_utf8(output)
+        .unwrap()
+        .split(PROMPT)
+        .skip(1)
+        .map(|reply| format!("{PROMPT}{}\n{reply}", commands.next().unwrap_or("")))
+        .collect()
+}
+
+#[test]
+fn a_scripted_session_matches_its_golden_file() {
+    let transcript = ScenarioTranscript::new();
+    transcript.note(play(SESSION).trim_end());
+
+    transcript.assert_matches_golden("tests/golden/repl_session.txt");
+}
+
+#[test]
+fn end_of_input_ends_the_session_cleanly() {
+    let transcript = play("order add Mouse 2500");
+
+    assert_eq!(
+        transcript,
+        format!(
+            "{PROMPT}order add Mouse 2500\nadded Mouse $25.00, 1 item in the cart for $25.00\n{PROMPT}\n\n"
+        )
+    );
+}
+
+#[test]
+fn unknown_commands_print_the_usage() {
+    for line in ["hello", "order", r#"order add "unterminated 5"#] {
+        assert!(play(line).contains(USAGE), "{line:?}");
+    }
+}
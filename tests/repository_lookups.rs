@@ -0,0 +1,89 @@
+// cargo test --test repository_lookups
+// exists() and total_of() have default implementations going through find().
+// Adapters overriding them must answer exactly like the defaults would.
+use hexa_lite::adapters::shared::SharedRepository;
+use hexa_lite::adapters::sql::{FakeExecutor, SqlOrderRepository};
+use hexa_lite::prelude::*;
+
+// Implements only the required methods, so it uses the defaults.
+struct DefaultsOnly(InMemoryOrderRepository);
+
+impl OrderReader for DefaultsOnly {
+    fn find(&self, id: OrderId) -> Result<Option<Order>, OrderError> {
+        self.0.find(id)
+    }
+
+    fn list(&self) -> Result<Vec<Order>, OrderError> {
+        self.0.list()
+    }
+}
+
+impl OrderWriter for DefaultsOnly {
+    fn save(&self, order: &Order) -> Result<(), OrderError> {
+        self.0.save(order)
+    }
+}
+
+// Orders 1 and 3 exist, 2 does not.
+fn seed(repository: &impl OrderWriter) {
+    for (id, price) in [(1, 4999), (3, 12999)] {
+        let order = Order::new(
+            OrderId(id),
+            vec![LineItem {
+                name: "Item".to_string(),
+                price: Money(price),
+            }],
+        )
+        .unwrap();
+        repository.save(&order).unwrap();
+    }
+}
+
+fn answers(repository: &impl OrderWriter) -> Vec<(bool, Option<Money>)> {
+    (0..=4)
+        .map(|id| {
+            (
+                repository.exists(OrderId(id)).unwrap(),
+                repository.total_of(OrderId(id)).unwrap(),
+            )
+        })
+        .collect()
+}
+
+#[test]
+fn overrides_agree_with_the_defaults() {
+    let defaults = DefaultsOnly(InMemoryOrderRepository::new());
+    let in_memory = InMemoryOrderRepository::new();
+    let sql = SqlOrderRepository::new(FakeExecutor::new()).unwrap();
+    let shared = SharedRepository::new(InMemoryOrderRepository::new());
+    seed(&defaults);
+    seed(&in_memory);
+    seed(&sql);
+    seed(&shared);
+
+    let expected = answers(&defaults);
+    assert_eq!(expected[1], (true, Some(Money(4999))));
+    assert_eq!(expected[2], (false, None));
+    assert_eq!(answers(&in_memory), expected);
+    assert_eq!(answers(&sql), expected);
+    assert_eq!(answers(&shared), expected);
+}
+
+#[test]
+fn service_answers_without_returning_the_order() {
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    let order = service
+        .place_order(vec![LineItem {
+            name: "Keyboard".to_string(),
+            price: Money(12999),
+        }])
+        .unwrap();
+
+    assert!(service.order_exists(order.id).unwrap());
+    assert!(!service.order_exists(OrderId(42)).unwrap());
+    assert_eq!(service.order_total(order.id).unwrap(), Some(Money(12999)));
+    assert_eq!(service.order_total(OrderId(42)).unwrap(), None);
+}
@@ -0,0 +1,112 @@
+// cargo test --test repository_ordering
+// list(), for_each() and list_deleted() come out by ascending id, however
+// the orders were saved: the same repository content always prints the
+// same way. Every adapter is fed the same ids in several shuffled orders.
+use hexa_lite::adapters::shared::SharedRepository;
+use hexa_lite::adapters::sql::{FakeExecutor, SqlOrderRepository};
+use hexa_lite::ports::Capability;
+use hexa_lite::prelude::*;
+
+// xorshift64*: the same shuffles on every run.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    // Fisher-Yates.
+    fn shuffle(&mut self, ids: &mut [u32]) {
+        for i in (1..ids.len()).rev() {
+            let j = (self.next() % (i as u64 + 1)) as usize;
+            ids.swap(i, j);
+        }
+    }
+}
+
+fn order(id: u32) -> Order {
+    Order::new(
+        OrderId(id),
+        vec![LineItem {
+            name: format!("Item {id}"),
+            price: Money(100 * id),
+        }],
+    )
+    .unwrap()
+}
+
+fn shuffled_ids(seed: u64) -> Vec<u32> {
+    let mut ids: Vec<u32> = (1..=40).collect();
+    Rng(seed).shuffle(&mut ids);
+    ids
+}
+
+fn ids_of(orders: &[Order]) -> Vec<u32> {
+    orders.iter().map(|order| order.id.0).collect()
+}
+
+fn assert_listed_in_order(repository: &impl OrderWriter, seed: u64) {
+    let ids = shuffled_ids(seed);
+    for &id in &ids {
+        repository.save(&order(id)).unwrap();
+    }
+    let expected: Vec<u32> = (1..=40).collect();
+
+    assert_eq!(ids_of(&repository.list().unwrap()), expected, "seed {seed}");
+    let mut visited = Vec::new();
+    repository
+        .for_each(&mut |order| visited.push(order.id.0))
+        .unwrap();
+    assert_eq!(visited, expected, "seed {seed}");
+}
+
+#[test]
+fn every_repository_lists_by_ascending_id() {
+    for seed in [1, 7, 42, 0x5eed_0001] {
+        assert_ne!(shuffled_ids(seed), (1..=40).collect::<Vec<_>>());
+        assert_listed_in_order(&InMemoryOrderRepository::new(), seed);
+        assert_listed_in_order(&SqlOrderRepository::new(FakeExecutor::new()).unwrap(), seed);
+        assert_listed_in_order(&SharedRepository::new(InMemoryOrderRepository::new()), seed);
+    }
+}
+
+#[test]
+fn staged_and_stored_rows_are_merged_in_order() {
+    let mut sql = SqlOrderRepository::new(FakeExecutor::new()).unwrap();
+    let ids = shuffled_ids(3);
+    let (stored, staged) = ids.split_at(20);
+    for &id in stored {
+        sql.save(&order(id)).unwrap();
+    }
+    sql.as_transactional().unwrap().begin();
+    for &id in staged {
+        sql.save(&order(id)).unwrap();
+    }
+
+    assert_eq!(ids_of(&sql.list().unwrap()), (1..=40).collect::<Vec<_>>());
+}
+
+fn assert_deleted_listed_in_order(repo: &impl OrderWriter) {
+    for id in shuffled_ids(11) {
+        repo.save(&order(id)).unwrap();
+    }
+    let mut doomed: Vec<u32> = (1..=40).filter(|id| id % 3 == 0).collect();
+    Rng(12).shuffle(&mut doomed);
+    for &id in &doomed {
+        repo.soft_delete(OrderId(id)).unwrap();
+    }
+
+    doomed.sort();
+    assert_eq!(ids_of(&repo.list_deleted().unwrap()), doomed);
+    let kept: Vec<u32> = (1..=40).filter(|id| id % 3 != 0).collect();
+    assert_eq!(ids_of(&repo.list().unwrap()), kept);
+}
+
+#[test]
+fn deleted_orders_are_listed_by_ascending_id() {
+    assert_deleted_listed_in_order(&InMemoryOrderRepository::new());
+    assert_deleted_listed_in_order(&SqlOrderRepository::new(FakeExecutor::new()).unwrap());
+}
@@ -0,0 +1,184 @@
+// cargo test --test reservation_expiry
+// Stock a checkout reserved and never came back for goes back on the shelf
+// once the order TTL is up, through maintenance: once, however often it
+// runs. A saga resumed afterwards reserves again, or fails OutOfStock.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::in_memory::{InMemoryInventory, InMemorySagaLog};
+use hexa_lite::application::{CheckoutSaga, MaintenanceReport};
+use hexa_lite::domain::{InventoryEvent, OrderEvent, SagaId};
+use hexa_lite::ports::{
+    ChargeLog, EventPublisher, Inventory, OrderReader, ReservationId, SagaEntry, SagaLog,
+};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+const TTL: Duration = Duration::from_secs(3600);
+const SAGA: SagaId = SagaId(7);
+
+#[derive(Default)]
+struct Journal(Mutex<Vec<InventoryEvent>>);
+
+impl EventPublisher for Journal {
+    fn publish(&self, _event: &OrderEvent) -> Result<(), OrderError> {
+        Ok(())
+    }
+
+    fn publish_inventory(&self, event: &InventoryEvent) -> Result<(), OrderError> {
+        self.0.lock().unwrap().push(event.clone());
+        Ok(())
+    }
+}
+
+fn mugs(count: usize) -> Vec<LineItem> {
+    vec![LineItem::new("Mug", Money(900)); count]
+}
+
+// Dated by the clock the test moves.
+fn shelf(clock: &Arc<SteppingClock>, mugs: u32) -> InMemoryInventory {
+    let clock = Arc::clone(clock);
+    InMemoryInventory::new()
+        .with_stock("Mug", mugs)
+        .with_clock(move || clock.now())
+        .with_console(Console::silent())
+}
+
+// What a saga that crashed right after reserving leaves behind.
+fn abandoned(inventory: &InMemoryInventory, log: &InMemorySagaLog) {
+    log.append(SAGA, SagaEntry::Started { items: mugs(2) })
+        .unwrap();
+    // The one CheckoutSaga::reservation gives it.
+    let reservation = ReservationId(SAGA.0);
+    inventory.reserve(reservation, &mugs(2)).unwrap();
+    log.append(SAGA, SagaEntry::Reserved { reservation })
+        .unwrap();
+}
+
+#[test]
+fn a_reservation_is_released_exactly_at_the_ttl_and_only_once() {
+    let clock = Arc::new(SteppingClock::starting_at(Timestamp(1_700_000_000)));
+    let inventory = shelf(&clock, 5);
+    inventory.reserve(ReservationId(1), &mugs(2)).unwrap();
+    inventory.reserve(ReservationId(2), &mugs(1)).unwrap();
+    inventory.confirm(ReservationId(2)).unwrap();
+    let (repo, payment, sender) = (
+        InMemoryOrderRepository::new(),
+        MockPaymentGateway::new(),
+        ConsoleSender::new(),
+    );
+    let journal = Journal::default();
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_clock(&*clock)
+        .with_inventory(&inventory)
+        .with_event_publisher(&journal)
+        .with_order_ttl(TTL);
+
+    clock.advance_secs(TTL.as_secs() - 1);
+    assert_eq!(service.maintenance().unwrap().reservations_released, 0);
+    assert_eq!(inventory.available("Mug"), 2);
+
+    clock.advance_secs(1);
+    assert_eq!(service.maintenance().unwrap().reservations_released, 1);
+    // The confirmed one is sold: it never expires.
+    assert_eq!(inventory.available("Mug"), 4);
+    assert_eq!(
+        journal.0.lock().unwrap()[..],
+        [InventoryEvent::ReservationExpired { reservation: 1 }]
+    );
+
+    // Nothing left to release, nothing published twice.
+    clock.advance_secs(TTL.as_secs());
+    assert_eq!(service.maintenance().unwrap(), MaintenanceReport::default());
+    assert_eq!(inventory.available("Mug"), 4);
+    assert_eq!(journal.0.lock().unwrap().len(), 1);
+    assert!(
+        inventory
+            .release_expired(clock.now(), TTL)
+            .unwrap()
+            .is_empty()
+    );
+}
+
+#[test]
+fn without_a_clock_an_inventory_keeps_its_reservations() {
+    let clock = SteppingClock::starting_at(Timestamp(1_700_000_000));
+    let inventory = InMemoryInventory::new()
+        .with_stock("Mug", 5)
+        .with_console(Console::silent());
+    inventory.reserve(ReservationId(1), &mugs(2)).unwrap();
+    clock.advance_secs(10 * TTL.as_secs());
+
+    assert!(
+        inventory
+            .release_expired(clock.now(), TTL)
+            .unwrap()
+            .is_empty()
+    );
+    assert_eq!(inventory.available("Mug"), 3);
+}
+
+#[test]
+fn a_saga_resumed_after_its_reservation_expired_reserves_again() {
+    let clock = Arc::new(SteppingClock::starting_at(Timestamp(1_700_000_000)));
+    let (inventory, log) = (shelf(&clock, 5), InMemorySagaLog::new());
+    abandoned(&inventory, &log);
+    let (repo, payment, sender) = (
+        InMemoryOrderRepository::new(),
+        MockPaymentGateway::new(),
+        ConsoleSender::new(),
+    );
+    clock.advance_secs(TTL.as_secs());
+    OrderService::new(&repo, &payment, &sender)
+        .with_clock(&*clock)
+        .with_inventory(&inventory)
+        .with_order_ttl(TTL)
+        .maintenance()
+        .unwrap();
+    assert_eq!(inventory.available("Mug"), 5);
+
+    let orders = OrderService::new(&repo, &payment, &sender);
+    let order = CheckoutSaga::new(orders, &inventory, &log)
+        .resume(SAGA)
+        .unwrap();
+
+    assert_eq!(order.status, OrderStatus::Paid);
+    assert_eq!(inventory.available("Mug"), 3);
+    assert!(matches!(
+        log.entries(SAGA).unwrap().last(),
+        Some(SagaEntry::Confirmed)
+    ));
+}
+
+#[test]
+fn a_saga_resumed_once_the_stock_is_gone_fails_out_of_stock() {
+    let clock = Arc::new(SteppingClock::starting_at(Timestamp(1_700_000_000)));
+    let (inventory, log) = (shelf(&clock, 2), InMemorySagaLog::new());
+    abandoned(&inventory, &log);
+    let (repo, payment, sender) = (
+        InMemoryOrderRepository::new(),
+        MockPaymentGateway::new(),
+        ConsoleSender::new(),
+    );
+    clock.advance_secs(TTL.as_secs());
+    OrderService::new(&repo, &payment, &sender)
+        .with_clock(&*clock)
+        .with_inventory(&inventory)
+        .with_order_ttl(TTL)
+        .maintenance()
+        .unwrap();
+    // Someone else gets the mugs meanwhile.
+    inventory.reserve(ReservationId(99), &mugs(1)).unwrap();
+
+    let orders = OrderService::new(&repo, &payment, &sender);
+    match CheckoutSaga::new(orders, &inventory, &log).resume(SAGA) {
+        Err(OrderError::OutOfStock { item }) => assert_eq!(item, "Mug"),
+        other => panic!("expected OutOfStock, got {other:?}"),
+    }
+
+    // The order placed on the way is cancelled and refunded.
+    let order = repo.find(OrderId(1)).unwrap().unwrap();
+    assert_eq!(order.status, OrderStatus::Cancelled);
+    assert!(payment.charges().unwrap().is_empty());
+    assert_eq!(inventory.available("Mug"), 1);
+}
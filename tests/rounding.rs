@@ -0,0 +1,112 @@
+// cargo test --test rounding
+// One rounding strategy per service: what its promotions take off, and,
+// through FixedRates::with_rounding, what its converted prices come to.
+// Banker's rounding (HalfEven) charges, over many ties, exactly what the
+// exact amounts add up to; half up charges a little more every time.
+use hexa_lite::adapters::rates::FixedRates;
+use hexa_lite::domain::{
+    Currency, Discount, ExchangeRate, ForeignLineItem, Percent, Price, RoundingStrategy,
+};
+use hexa_lite::prelude::*;
+
+// Carts of 0.01, 0.03... 0.39: half of each is a tie.
+fn odd_cents() -> impl Iterator<Item = u32> {
+    (1..40).step_by(2)
+}
+
+fn charged_at_half_price(rounding: RoundingStrategy) -> u32 {
+    let (repo, payment, sender) = (
+        InMemoryOrderRepository::new(),
+        MockPaymentGateway::new(),
+        ConsoleSender::new(),
+    );
+    let mut service = OrderService::new(&repo, &payment, &sender).with_rounding(rounding);
+    let half = Discount::Promotion(Percent::try_from(50).unwrap());
+    odd_cents()
+        .map(|cents| {
+            let cart = vec![LineItem::new("Stamp", Money(cents))];
+            service.place_discounted_order(cart, half).unwrap().total.0
+        })
+        .sum()
+}
+
+fn charged_in_usd(rounding: RoundingStrategy) -> u32 {
+    let (repo, payment, sender) = (
+        InMemoryOrderRepository::new(),
+        MockPaymentGateway::new(),
+        ConsoleSender::new(),
+    );
+    // 1.5 dollars to the euro.
+    let rates = FixedRates::new()
+        .with_rate(Currency::Eur, Currency::Usd, ExchangeRate(150_000_000))
+        .with_rounding(rounding);
+    let mut service = OrderService::new(&repo, &payment, &sender).with_exchange_rates(&rates);
+    odd_cents()
+        .map(|cents| {
+            let line = ForeignLineItem {
+                name: "Stamp".to_string(),
+                price: Price {
+                    amount: Money(cents),
+                    currency: Currency::Eur,
+                },
+            };
+            let converted = service.place_order_in(Currency::Usd, vec![line]).unwrap();
+            converted.order.total.0
+        })
+        .sum()
+}
+
+#[test]
+fn half_even_promotions_charge_the_exact_sum_over_ties() {
+    // 400 cents of carts, 20 ties of half a cent.
+    let exact_half = odd_cents().sum::<u32>() / 2;
+
+    assert_eq!(
+        charged_at_half_price(RoundingStrategy::HalfEven),
+        exact_half
+    );
+    // HalfUp takes 0.005 more off every cart, so charges 0.005 less.
+    assert_eq!(
+        charged_at_half_price(RoundingStrategy::HalfUp),
+        exact_half - 10
+    );
+    assert_eq!(
+        charged_at_half_price(RoundingStrategy::Down),
+        exact_half + 10
+    );
+}
+
+#[test]
+fn half_even_conversions_charge_the_exact_sum_over_ties() {
+    // 1.5 times 400 cents, 20 ties of half a cent.
+    let exact = odd_cents().sum::<u32>() * 3 / 2;
+
+    assert_eq!(charged_in_usd(RoundingStrategy::HalfEven), exact);
+    assert_eq!(charged_in_usd(RoundingStrategy::HalfUp), exact + 10);
+    assert_eq!(charged_in_usd(RoundingStrategy::Down), exact - 10);
+}
+
+#[test]
+fn the_service_rounding_leaves_conversions_to_the_rates() {
+    let (repo, payment, sender) = (
+        InMemoryOrderRepository::new(),
+        MockPaymentGateway::new(),
+        ConsoleSender::new(),
+    );
+    let rates =
+        FixedRates::new().with_rate(Currency::Eur, Currency::Usd, ExchangeRate(150_000_000));
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_exchange_rates(&rates)
+        .with_rounding(RoundingStrategy::Down);
+
+    // 0.01 EUR is 0.015 USD: half up, as the rates were told.
+    let line = ForeignLineItem {
+        name: "Stamp".to_string(),
+        price: Price {
+            amount: Money(1),
+            currency: Currency::Eur,
+        },
+    };
+    let converted = service.place_order_in(Currency::Usd, vec![line]).unwrap();
+    assert_eq!(converted.order.total, Money(2));
+}
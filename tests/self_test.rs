@@ -0,0 +1,143 @@
+// cargo test --test self_test
+// The start-up smoke test: a synthetic order through the adapters the
+// composition root built from the environment, and nothing of it left in
+// the repository or the charge log afterwards.
+use hexa_lite::composition::{
+    CheckOutcome, EVENT_LOG_VAR, EnvConfig, SENDER_VAR, SelfTestOptions, WEBHOOK_URL_VAR,
+    build_adapters, build_service, self_test,
+};
+use hexa_lite::ports::ChargeLog;
+use hexa_lite::prelude::*;
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+fn config(vars: &[(&str, &str)]) -> EnvConfig {
+    let vars: HashMap<String, String> = vars
+        .iter()
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+    EnvConfig::from_lookup(|key| vars.get(key).cloned()).unwrap()
+}
+
+fn no_time() -> Duration {
+    Duration::ZERO
+}
+
+// (port, step) of every check, in the order they ran.
+fn steps(report: &hexa_lite::composition::SelfTestReport) -> Vec<(&str, &str)> {
+    report
+        .checks
+        .iter()
+        .map(|check| (check.port, check.step))
+        .collect()
+}
+
+#[test]
+fn the_self_test_leaves_the_repository_and_the_charges_as_they_were() {
+    let adapters = build_adapters(&config(&[])).unwrap();
+    let mut service = build_service(&adapters);
+    service
+        .place_order(vec![LineItem::new("Keyboard", Money(12_999))])
+        .unwrap();
+    let (orders, charges) = (
+        adapters.repository.list().unwrap(),
+        adapters.payment.charges().unwrap(),
+    );
+
+    let report = self_test(&adapters, &SelfTestOptions::new(&no_time));
+
+    assert!(report.passed(), "{report}");
+    assert!(report.order_id.is_self_test());
+    assert_eq!(
+        steps(&report),
+        [
+            ("PaymentGateway", "charge_for"),
+            ("OrderWriter", "save"),
+            ("OrderReader", "find"),
+            ("Sender", "send"),
+            ("Sender", "send_notice"),
+            ("OrderWriter", "soft_delete"),
+            ("PaymentGateway", "refund_for"),
+        ]
+    );
+    assert_eq!(report.checks[0].adapter, "MockPaymentGateway");
+    assert_eq!(report.left_behind().count(), 0);
+    assert_eq!(adapters.repository.list().unwrap(), orders);
+    assert_eq!(adapters.repository.find(report.order_id).unwrap(), None);
+    assert_eq!(adapters.payment.charges().unwrap(), charges);
+}
+
+// One file per test, removed when the test ends, even on failure.
+struct TempLog(PathBuf);
+
+impl Drop for TempLog {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+#[test]
+fn what_cannot_be_undone_is_reported_and_the_rest_is_cleaned_up_anyway() {
+    let log = TempLog(
+        std::env::temp_dir().join(format!("hexa_lite_{}_self_test.log", std::process::id())),
+    );
+    let adapters = build_adapters(&config(&[
+        (SENDER_VAR, "webhook"),
+        (WEBHOOK_URL_VAR, "https://example.test/hook"),
+        (EVENT_LOG_VAR, log.0.to_str().unwrap()),
+    ]))
+    .unwrap();
+    let ticks = std::cell::Cell::new(0);
+    let elapsed = || {
+        ticks.set(ticks.get() + 5);
+        Duration::from_millis(ticks.get())
+    };
+    let options = SelfTestOptions {
+        operator: Some("ops@shop.test".to_string()),
+        publish_events: true,
+        ..SelfTestOptions::new(&elapsed)
+    };
+
+    let report = self_test(&adapters, &options);
+
+    // A webhook reaches customers' systems, not operators.
+    let notice = &report.checks[4];
+    assert_eq!(
+        (notice.step, notice.adapter.as_str()),
+        ("send_notice", "WebhookSender")
+    );
+    assert!(matches!(
+        notice.outcome,
+        CheckOutcome::Failed(OrderError::NotificationFailed)
+    ));
+    assert!(!report.passed());
+    let left: Vec<_> = report.left_behind().map(|check| check.port).collect();
+    assert_eq!(left, ["EventPublisher"]);
+    assert!(fs::read_to_string(&log.0).unwrap().contains("4000000000"));
+    // Five milliseconds between two readings of the clock.
+    assert_eq!(report.checks[0].took, Duration::from_millis(5));
+    assert!(adapters.repository.list().unwrap().is_empty());
+    assert!(adapters.payment.charges().unwrap().is_empty());
+    assert!(
+        report
+            .to_string()
+            .contains("left behind: the event stays in the log")
+    );
+}
+
+#[test]
+fn an_order_the_domain_refuses_touches_no_adapter() {
+    let adapters = build_adapters(&config(&[])).unwrap();
+    let options = SelfTestOptions {
+        amount: Money(0),
+        ..SelfTestOptions::new(&no_time)
+    };
+
+    let report = self_test(&adapters, &options);
+
+    assert_eq!(steps(&report), [("Domain", "validate")]);
+    assert!(!report.passed());
+    assert!(adapters.payment.charges().unwrap().is_empty());
+}
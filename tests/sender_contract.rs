@@ -0,0 +1,180 @@
+// cargo test --test sender_contract
+// What every sender owes the service, bare or decorated: confirmations with
+// emoji, right-to-left names, control characters, 10 KB names, nothing to
+// pay or the most a Money holds are sent or fail with NotificationFailed,
+// never a panic. Those writing a receipt or posting an envelope are held to
+// what they wrote.
+#![allow(deprecated)]
+
+use hexa_lite::adapters::breaker::CircuitBreaker;
+use hexa_lite::adapters::budget::WithinBudget;
+use hexa_lite::adapters::buffered::BufferedSender;
+use hexa_lite::adapters::compat::V1Compat;
+use hexa_lite::adapters::external::SendGridSender;
+use hexa_lite::adapters::limited::ConcurrencyLimited;
+use hexa_lite::adapters::metrics::InMemoryMetrics;
+use hexa_lite::adapters::outbox::{InMemoryDeadLetters, OutboxSender};
+use hexa_lite::adapters::timing::Timed;
+use hexa_lite::adapters::webhook::WebhookSender;
+use hexa_lite::adapters::{Console, SecretString, SharedBuffer};
+use hexa_lite::composition::ConfiguredSender;
+use hexa_lite::ports::{Budget, Flushable, SenderV1};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::stubs::{CountingNotifier, ErrNotifier, OkNotifier, OkSender};
+use hexa_lite::testing::{
+    GlobalRecorder, Recorded, SenderContract, SteppingClock, sender_contract,
+};
+use std::time::Duration;
+
+// A sender printing to `buffer`, for a decorator to wrap.
+fn console(buffer: &SharedBuffer) -> ConsoleSender {
+    ConsoleSender::new().with_console(Console::to(buffer.clone()))
+}
+
+fn webhook() -> WebhookSender {
+    WebhookSender::new("https://shop.example.test/hooks")
+        .unwrap()
+        .with_console(Console::silent())
+}
+
+// The decorators borrow what they report to; a test may as well leak it.
+fn leak<T>(value: T) -> &'static T {
+    Box::leak(Box::new(value))
+}
+
+struct LegacyMailer;
+
+impl SenderV1 for LegacyMailer {
+    fn send(&self, _order: &Order) -> Result<(), OrderError> {
+        Ok(())
+    }
+}
+
+#[test]
+fn the_console_prints_every_receipt_whole() {
+    let buffer = SharedBuffer::new();
+    let shared = buffer.clone();
+    SenderContract::new(move || console(&shared))
+        .printing_to(&buffer)
+        .run();
+}
+
+#[test]
+fn the_webhook_posts_envelopes_that_read_back_as_sent() {
+    let bodies = |sender: &WebhookSender| sender.delivered().into_iter().map(|r| r.body).collect();
+    SenderContract::new(webhook).posting_envelopes(bodies).run();
+    SenderContract::new(|| {
+        webhook()
+            .with_signing_key(SecretString::new("whsec_test"))
+            .with_clock(SteppingClock::starting_at(Timestamp(1_700_000_000)))
+    })
+    .posting_envelopes(bodies)
+    .run();
+}
+
+#[test]
+fn the_outbox_keeps_envelopes_that_read_back_as_sent_and_delivers_them() {
+    let buffer = SharedBuffer::new();
+    let shared = buffer.clone();
+    SenderContract::new(move || {
+        let clock = SteppingClock::starting_at(Timestamp(1_000));
+        OutboxSender::new(console(&shared), clock, leak(InMemoryDeadLetters::new()))
+            .with_console(Console::silent())
+    })
+    .posting_envelopes(|outbox| outbox.entries().into_iter().map(|e| e.envelope).collect())
+    .run();
+    let shared = buffer.clone();
+    SenderContract::new(move || {
+        let clock = SteppingClock::starting_at(Timestamp(1_000));
+        OutboxSender::new(console(&shared), clock, leak(InMemoryDeadLetters::new()))
+            .with_console(Console::silent())
+    })
+    .after_send(|outbox| outbox.run_dispatcher(Timestamp(1_000)).map(|_| ()))
+    .printing_to(&buffer)
+    .run();
+}
+
+#[test]
+fn a_buffered_sender_prints_every_receipt_whole_once_flushed() {
+    let buffer = SharedBuffer::new();
+    let shared = buffer.clone();
+    SenderContract::new(move || {
+        BufferedSender::new(console(&shared)).with_console(Console::silent())
+    })
+    .after_send(|buffered| buffered.flush())
+    .printing_to(&buffer)
+    .run();
+    SenderContract::new(|| BufferedSender::new(webhook()).with_console(Console::silent()))
+        .after_send(|buffered| buffered.flush())
+        .posting_envelopes(|buffered| {
+            buffered
+                .inner()
+                .delivered()
+                .into_iter()
+                .map(|r| r.body)
+                .collect()
+        })
+        .run();
+}
+
+#[test]
+fn the_decorators_pass_every_confirmation_through() {
+    let buffer = SharedBuffer::new();
+    let shared = buffer.clone();
+    SenderContract::new(move || {
+        CircuitBreaker::new(
+            console(&shared),
+            SteppingClock::starting_at(Timestamp(1_000)),
+        )
+    })
+    .printing_to(&buffer)
+    .run();
+    let shared = buffer.clone();
+    SenderContract::new(move || ConcurrencyLimited::new(console(&shared), 1))
+        .printing_to(&buffer)
+        .run();
+    let shared = buffer.clone();
+    SenderContract::new(move || {
+        let clock = leak(SteppingClock::starting_at(Timestamp(1_000)));
+        WithinBudget::new(
+            console(&shared),
+            leak(Budget::new(clock, Duration::from_secs(5))),
+        )
+    })
+    .printing_to(&buffer)
+    .run();
+    let shared = buffer.clone();
+    SenderContract::new(move || {
+        Timed::new(
+            console(&shared),
+            leak(InMemoryMetrics::new()),
+            "sender",
+            || Duration::from_millis(3),
+        )
+    })
+    .printing_to(&buffer)
+    .run();
+    let shared = buffer.clone();
+    SenderContract::new(move || Recorded::new(console(&shared), &GlobalRecorder::new(), "sender"))
+        .printing_to(&buffer)
+        .run();
+    let shared = buffer.clone();
+    SenderContract::new(move || ConfiguredSender::Console(console(&shared)))
+        .printing_to(&buffer)
+        .run();
+}
+
+#[test]
+fn every_other_sender_sends_or_says_notification_failed() {
+    sender_contract(|| {
+        SendGridSender::new(SecretString::new("SG.test"))
+            .unwrap()
+            .with_console(Console::silent())
+    });
+    sender_contract(|| ConfiguredSender::Webhook(webhook()));
+    sender_contract(|| V1Compat::new(LegacyMailer, leak(InMemoryMetrics::new())));
+    sender_contract(|| OkSender);
+    sender_contract(|| OkNotifier);
+    sender_contract(CountingNotifier::new);
+    sender_contract(|| ErrNotifier(OrderError::NotificationFailed));
+}
@@ -0,0 +1,87 @@
+// cargo test --test sender_migration
+// Migrating a port without breaking its adapters: the same notification,
+// once through a native (v2) Sender, once through an old SenderV1 adapter
+// behind the V1Compat shim. The customer must not see the difference.
+//
+// This file implements the deprecated trait on purpose, hence the allow:
+// without it every use below would warn.
+#![allow(deprecated)]
+
+use hexa_lite::adapters::compat::{V1_SEND_COUNTER, V1Compat};
+use hexa_lite::adapters::metrics::InMemoryMetrics;
+use hexa_lite::ports::SenderV1;
+use hexa_lite::prelude::*;
+use std::cell::RefCell;
+
+// Both render the email the same way, each from what its port offers.
+#[derive(Default)]
+struct NativeEmailSender {
+    outbox: RefCell<Vec<String>>,
+}
+
+impl Sender for NativeEmailSender {
+    fn send(&self, confirmation: &OrderConfirmation) -> Result<(), OrderError> {
+        let mut email = format!("Order {} confirmed\n", confirmation.order_id);
+        email.push_str(&confirmation.receipt_lines().join("\n"));
+        self.outbox.borrow_mut().push(email);
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct LegacyEmailSender {
+    outbox: RefCell<Vec<String>>,
+}
+
+impl SenderV1 for LegacyEmailSender {
+    fn send(&self, order: &Order) -> Result<(), OrderError> {
+        let mut email = format!("Order {} confirmed\n", order.id);
+        email.push_str(&order.receipt_lines().join("\n"));
+        self.outbox.borrow_mut().push(email);
+        Ok(())
+    }
+}
+
+fn cart() -> Vec<LineItem> {
+    vec![
+        LineItem {
+            name: "Rust Book".to_string(),
+            price: Money(4999),
+        },
+        LineItem {
+            name: "Keyboard".to_string(),
+            price: Money(12999),
+        },
+    ]
+}
+
+fn place_with<N: Sender>(sender: &N) {
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let mut service = OrderService::new(&repo, &payment, sender);
+    service.place_order(cart()).unwrap();
+    service.place_order(cart()).unwrap();
+}
+
+#[test]
+fn shimmed_v1_sender_produces_the_same_emails() {
+    let native = NativeEmailSender::default();
+    place_with(&native);
+
+    let metrics = InMemoryMetrics::new();
+    let shimmed = V1Compat::new(LegacyEmailSender::default(), &metrics);
+    place_with(&shimmed);
+
+    assert_eq!(native.outbox.borrow().len(), 2);
+    assert_eq!(*native.outbox.borrow(), *shimmed.inner().outbox.borrow());
+}
+
+#[test]
+fn v1_usage_is_counted() {
+    let metrics = InMemoryMetrics::new();
+    let shimmed = V1Compat::new(LegacyEmailSender::default(), &metrics);
+
+    place_with(&shimmed);
+
+    assert_eq!(metrics.counter(V1_SEND_COUNTER), 2);
+}
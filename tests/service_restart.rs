@@ -0,0 +1,135 @@
+// cargo test --test service_restart
+// The process stops, a new one starts over the same repositories: with the
+// saved ServiceState, ids carry on where they were and no draft is lost.
+use hexa_lite::adapters::in_memory::InMemoryDraftRepository;
+use hexa_lite::adapters::state_file::FileStateStore;
+use hexa_lite::application::CheckoutService;
+use hexa_lite::domain::{ConfirmPolicy, StoredOrder};
+use hexa_lite::ports::{ServiceState, StateStore};
+use hexa_lite::prelude::testing::assert_order;
+use hexa_lite::prelude::*;
+use std::fs;
+use std::path::PathBuf;
+
+// One file per test, removed when the test ends, even on failure.
+struct TempState(PathBuf);
+
+impl TempState {
+    fn new(name: &str) -> Self {
+        let path =
+            std::env::temp_dir().join(format!("hexa_lite_{}_{name}.state", std::process::id()));
+        let _ = fs::remove_file(&path);
+        TempState(path)
+    }
+}
+
+impl Drop for TempState {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.0);
+    }
+}
+
+fn item(cents: u32) -> LineItem {
+    LineItem {
+        name: "Cable".to_string(),
+        price: Money(cents),
+    }
+}
+
+#[test]
+fn fourth_order_after_a_restart_gets_id_4() {
+    let file = TempState::new("orders");
+    let store = FileStateStore::new(&file.0);
+    let repo = InMemoryOrderRepository::new();
+
+    {
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &payment, &sender);
+        for cents in [100, 200, 300] {
+            service.place_order(vec![item(cents)]).unwrap();
+        }
+        store.save_state(&service.state()).unwrap();
+    } // the process stops
+
+    let state = store.load_state().unwrap().expect("state was saved");
+    assert_eq!(state.next_id, 4);
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::restore(&repo, &payment, &sender, &state).unwrap();
+    let fourth = service.place_order(vec![item(400)]).unwrap();
+
+    assert_eq!(fourth.id, OrderId(4));
+    assert_eq!(repo.list().unwrap().len(), 4);
+}
+
+#[test]
+fn stale_state_does_not_overwrite_newer_orders() {
+    let repo = InMemoryOrderRepository::new();
+    let stale = {
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut service = OrderService::new(&repo, &payment, &sender);
+        let stale = service.state();
+        service.place_order(vec![item(100)]).unwrap();
+        service.place_order(vec![item(200)]).unwrap();
+        stale
+    };
+
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::restore(&repo, &payment, &sender, &stale).unwrap();
+    let order = service.place_order(vec![item(300)]).unwrap();
+
+    assert_eq!(order.id, OrderId(3));
+    assert_order(&repo.find(OrderId(1)).unwrap().unwrap()).has_total_cents(100);
+}
+
+#[test]
+fn open_drafts_survive_a_restart() {
+    let file = TempState::new("drafts");
+    let store = FileStateStore::new(&file.0);
+    let mut repo = InMemoryDraftRepository::new();
+
+    {
+        let payment = MockPaymentGateway::new();
+        let sender = ConsoleSender::new();
+        let mut checkout = CheckoutService::new(&mut repo, &payment, &sender);
+        for _ in 0..3 {
+            let id = checkout.start_draft().unwrap();
+            checkout.add_item(id, item(500)).unwrap();
+        }
+        checkout
+            .confirm(OrderId(2), &ConfirmPolicy::default())
+            .unwrap();
+        store.save_state(&checkout.state()).unwrap();
+    }
+
+    let state = store.load_state().unwrap().unwrap();
+    assert_eq!(
+        state,
+        ServiceState {
+            next_id: 4,
+            open_drafts: vec![OrderId(1), OrderId(3)],
+        }
+    );
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut checkout = CheckoutService::restore(&mut repo, &payment, &sender, &state);
+    for id in checkout.open_drafts() {
+        let Some(StoredOrder::Draft(draft)) = checkout.get(id).unwrap() else {
+            panic!("draft {id} is gone");
+        };
+        assert_eq!(draft.items().len(), 1);
+    }
+    assert_eq!(checkout.start_draft().unwrap(), OrderId(4));
+}
+
+#[test]
+fn nothing_saved_means_a_fresh_start() {
+    let file = TempState::new("missing");
+    assert_eq!(FileStateStore::new(&file.0).load_state().unwrap(), None);
+
+    fs::write(&file.0, "not json").unwrap();
+    assert!(FileStateStore::new(&file.0).load_state().is_err());
+}
@@ -0,0 +1,195 @@
+// cargo test --test shared_repository
+// The repository's writes take &self: services borrow it with a plain &,
+// and any number of them hold the same one at once, on one thread or
+// several. Before, each writer needed its own &mut, and only one could
+// exist at a time.
+#[cfg(feature = "json")]
+use hexa_lite::adapters::file_repository::{FileOrderRepository, JournaledRepository, JsonFormat};
+use hexa_lite::adapters::in_memory::MockShippingProvider;
+use hexa_lite::adapters::sql::{FakeExecutor, SqlOrderRepository};
+use hexa_lite::application::{OrderBrowser, ShippingService};
+use hexa_lite::domain::Address;
+use hexa_lite::ports::ServiceState;
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+#[cfg(feature = "json")]
+use std::fs;
+use std::sync::Barrier;
+use std::thread;
+
+fn cart(name: &str, cents: u32) -> Vec<LineItem> {
+    vec![
+        LineItem::new(name, Money(cents)),
+        LineItem::new("Cable", Money(500)),
+    ]
+}
+
+fn address() -> Address {
+    Address {
+        street: "1 Main St".to_string(),
+        city: "Springfield".to_string(),
+        postal_code: "12345".to_string(),
+        country: "US".to_string(),
+    }
+}
+
+#[test]
+fn the_shop_and_the_warehouse_hold_one_repository_at_once() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let (carrier, clock) = (
+        MockShippingProvider::new(),
+        SteppingClock::starting_at(Timestamp(0)),
+    );
+
+    let mut shop = OrderService::new(&repo, &payment, &sender);
+    let mut warehouse = ShippingService::new(&repo, &carrier, &clock);
+    let browser = OrderBrowser::new(&repo);
+
+    let first = shop.place_order(cart("Mouse", 2500)).unwrap();
+    warehouse.ship_items(first.id, &[0], &address()).unwrap();
+    let second = shop.place_order(cart("Keyboard", 12999)).unwrap();
+    warehouse.ship_items(first.id, &[1], &address()).unwrap();
+
+    // Each sees what the other wrote, with all three still alive.
+    assert_eq!(
+        shop.get_order(first.id).unwrap().unwrap().status,
+        OrderStatus::Shipped
+    );
+    assert_eq!(browser.list().unwrap().len(), 2);
+    assert_eq!(
+        repo.find(second.id).unwrap().unwrap().status,
+        OrderStatus::Paid
+    );
+}
+
+#[test]
+fn two_threads_place_orders_through_one_reference() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    // Numbered apart, so the two services never hand out the same id.
+    let from_1000 = ServiceState {
+        next_id: 1000,
+        open_drafts: Vec::new(),
+    };
+
+    thread::scope(|scope| {
+        let mut counter = OrderService::new(&repo, &payment, &sender);
+        let mut web = OrderService::restore(&repo, &payment, &sender, &from_1000).unwrap();
+        scope.spawn(move || {
+            for _ in 0..20 {
+                counter.place_order(cart("Mouse", 2500)).unwrap();
+            }
+        });
+        scope.spawn(move || {
+            for _ in 0..20 {
+                web.place_order(cart("Keyboard", 12999)).unwrap();
+            }
+        });
+    });
+
+    let orders = repo.list().unwrap();
+    assert_eq!(orders.len(), 40);
+    assert_eq!((orders[0].id, orders[39].id), (OrderId(1), OrderId(1019)));
+}
+
+// Shared writers make the version check matter: of two updates read at
+// the same version, the second is refused instead of overwriting the first.
+#[test]
+fn the_second_of_two_stale_updates_conflicts() {
+    let repo = InMemoryOrderRepository::new();
+    let (payment, sender) = (MockPaymentGateway::new(), ConsoleSender::new());
+    let order = OrderService::new(&repo, &payment, &sender)
+        .place_order(cart("Mouse", 2500))
+        .unwrap();
+    let (mut ours, mut theirs) = (order.clone(), order);
+
+    ours.transition_to(OrderStatus::Shipped).unwrap();
+    theirs.transition_to(OrderStatus::Cancelled).unwrap();
+    repo.update(&ours).unwrap();
+
+    assert_err_variant!(
+        repo.update(&theirs),
+        OrderError::VersionConflict {
+            expected: 0,
+            found: 1,
+            ..
+        }
+    );
+    assert_eq!(
+        repo.find(ours.id).unwrap().unwrap().status,
+        OrderStatus::Shipped
+    );
+}
+
+// The same, with the two updates made at one moment from two threads: the
+// check and the write must be one step, or both may pass the check and
+// the second silently overwrite the first.
+fn one_of_two_racing_updates_wins(repo: &(impl OrderWriter + Sync)) {
+    for id in 1..=50 {
+        let order = Order::new(OrderId(id), cart("Mouse", 2500)).unwrap();
+        repo.save(&order).unwrap();
+        let barrier = Barrier::new(2);
+
+        let outcomes: Vec<Result<OrderStatus, OrderError>> = thread::scope(|scope| {
+            [OrderStatus::Paid, OrderStatus::Cancelled]
+                .map(|status| {
+                    let (mut stale, barrier) = (order.clone(), &barrier);
+                    scope.spawn(move || {
+                        stale.transition_to(status).unwrap();
+                        barrier.wait();
+                        repo.update(&stale).map(|()| status)
+                    })
+                })
+                .map(|racer| racer.join().unwrap())
+                .into()
+        });
+
+        let won: Vec<OrderStatus> = outcomes.iter().flatten().copied().collect();
+        assert_eq!(won.len(), 1, "order {id}: {outcomes:?}");
+        assert!(outcomes.iter().any(|outcome| matches!(
+            outcome,
+            Err(OrderError::VersionConflict {
+                expected: 0,
+                found: 1,
+                ..
+            })
+        )));
+        let stored = repo.find(OrderId(id)).unwrap().unwrap();
+        assert_eq!((stored.status, stored.version), (won[0], 1));
+    }
+}
+
+#[test]
+fn racing_updates_in_memory() {
+    one_of_two_racing_updates_wins(&InMemoryOrderRepository::new());
+}
+
+#[test]
+fn racing_updates_in_sql() {
+    one_of_two_racing_updates_wins(&SqlOrderRepository::new(FakeExecutor::new()).unwrap());
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn racing_updates_in_a_file() {
+    let path = std::env::temp_dir().join(format!(
+        "hexa_lite_{}_racing_updates.orders",
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&path);
+    one_of_two_racing_updates_wins(&FileOrderRepository::open(&path, JsonFormat).unwrap());
+    let _ = fs::remove_file(&path);
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn racing_updates_in_a_journaled_directory() {
+    let dir = std::env::temp_dir().join(format!(
+        "hexa_lite_{}_racing_updates_journal",
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&dir);
+    one_of_two_racing_updates_wins(&JournaledRepository::open(&dir, JsonFormat).unwrap());
+    let _ = fs::remove_dir_all(&dir);
+}
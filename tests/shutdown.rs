@@ -0,0 +1,132 @@
+// cargo test --test shutdown
+// The composition root shutting its adapters down: senders, then
+// repositories, then logs, whatever order they were registered in, and
+// every one of them even when one before it fails.
+use hexa_lite::adapters::Console;
+use hexa_lite::adapters::buffered::BufferedSender;
+use hexa_lite::adapters::event_log::FileEventLog;
+use hexa_lite::adapters::outbox::{InMemoryDeadLetters, OutboxSender, RetryPolicy};
+use hexa_lite::composition::{ShutdownCoordinator, ShutdownPriority};
+use hexa_lite::domain::OrderConfirmation;
+use hexa_lite::ports::{Shutdown, ShutdownError};
+use hexa_lite::prelude::*;
+use hexa_lite::testing::SteppingClock;
+use hexa_lite::testing::stubs::ErrNotifier;
+use std::sync::Mutex;
+use std::time::Duration;
+
+// Says in `calls` when it was shut down; fails if told to.
+struct Step<'a> {
+    name: &'static str,
+    calls: &'a Mutex<Vec<&'static str>>,
+    fails: bool,
+}
+
+impl Shutdown for Step<'_> {
+    fn shutdown(&mut self) -> Result<(), ShutdownError> {
+        self.calls.lock().unwrap().push(self.name);
+        if self.fails {
+            return Err(OrderError::StorageFailed.into());
+        }
+        Ok(())
+    }
+}
+
+fn step<'a>(name: &'static str, calls: &'a Mutex<Vec<&'static str>>) -> Step<'a> {
+    Step {
+        name,
+        calls,
+        fails: false,
+    }
+}
+
+fn confirmation(id: u32) -> OrderConfirmation {
+    Order::new(OrderId(id), vec![LineItem::new("Pen", Money(150))])
+        .unwrap()
+        .confirmation()
+}
+
+#[test]
+fn adapters_shut_down_by_priority_and_a_failure_stops_none_after_it() {
+    use ShutdownPriority::*;
+    let calls = Mutex::new(Vec::new());
+    let mut audit = step("audit", &calls);
+    let mut orders = Step {
+        fails: true,
+        ..step("orders", &calls)
+    };
+    let mut mailer = step("mailer", &calls);
+    let mut events = step("events", &calls);
+    let mut drafts = step("drafts", &calls);
+    let mut webhook = step("webhook", &calls);
+
+    let report = ShutdownCoordinator::new()
+        .register("audit", CloseLogs, &mut audit)
+        .register("orders", CloseRepositories, &mut orders)
+        .register("mailer", FlushSenders, &mut mailer)
+        .register("events", CloseLogs, &mut events)
+        .register("drafts", CloseRepositories, &mut drafts)
+        .register("webhook", FlushSenders, &mut webhook)
+        .run();
+
+    let order = ["mailer", "webhook", "orders", "drafts", "audit", "events"];
+    assert_eq!(calls.lock().unwrap()[..], order);
+    let ran: Vec<_> = report.steps.iter().map(|step| step.name).collect();
+    assert_eq!(ran, order);
+    assert!(!report.is_clean());
+    let failed: Vec<_> = report.failures().map(|step| step.name).collect();
+    assert_eq!(failed, ["orders"]);
+    assert!(matches!(
+        report.steps[2].outcome,
+        Err(ShutdownError::Failed(OrderError::StorageFailed))
+    ));
+    assert!(report.to_string().contains("orders"));
+}
+
+#[test]
+fn held_back_confirmations_are_sent_or_reported_at_shutdown() {
+    let mut buffered = BufferedSender::new(ConsoleSender::new().with_console(Console::silent()))
+        .with_console(Console::silent());
+    buffered.send(&confirmation(1)).unwrap();
+
+    let dead_letters = InMemoryDeadLetters::new();
+    let mut outbox = OutboxSender::new(
+        ErrNotifier(OrderError::NotificationFailed),
+        SteppingClock::starting_at(Timestamp(1_700_000_000)),
+        &dead_letters,
+    )
+    .with_policy(RetryPolicy::new(
+        Duration::from_secs(10),
+        Duration::from_secs(60),
+        5,
+    ))
+    .with_console(Console::silent());
+    outbox.send(&confirmation(2)).unwrap();
+
+    let report = ShutdownCoordinator::new()
+        .register("outbox", ShutdownPriority::FlushSenders, &mut outbox)
+        .register("buffered", ShutdownPriority::FlushSenders, &mut buffered)
+        .run();
+
+    assert_eq!(buffered.pending(), 0);
+    assert!(matches!(
+        report.steps[0].outcome,
+        Err(ShutdownError::Abandoned { count: 1, .. })
+    ));
+    assert!(report.steps[1].outcome.is_ok());
+    assert_eq!(outbox.entries()[0].attempts, 1);
+}
+
+#[test]
+fn a_file_log_syncs_what_it_wrote() {
+    let path = std::env::temp_dir().join(format!("hexa_lite_{}_shutdown.log", std::process::id()));
+    let mut log = FileEventLog::new(&path).unwrap();
+    let report = ShutdownCoordinator::new()
+        .register("events", ShutdownPriority::CloseLogs, &mut log)
+        .run();
+    assert!(report.is_clean());
+
+    std::fs::remove_file(&path).unwrap();
+    // Gone: nothing was written, nothing to sync.
+    assert!(log.shutdown().is_ok());
+}
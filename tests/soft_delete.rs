@@ -0,0 +1,120 @@
+// cargo test --test soft_delete
+// Deleted orders vanish from every lookup but can be restored, unchanged.
+// Only cancelled orders may be deleted.
+use hexa_lite::adapters::approval::ThresholdApproval;
+use hexa_lite::application::OrderBrowser;
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+
+fn cart(name: &str, cents: u32) -> Vec<LineItem> {
+    vec![LineItem {
+        name: name.to_string(),
+        price: Money(cents),
+    }]
+}
+
+// Order 1 is paid, order 2 was rejected during approval.
+fn seed(repo: &InMemoryOrderRepository) {
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let policy = ThresholdApproval::new(Money(100_000));
+    let mut service = OrderService::new(repo, &payment, &sender).with_approval_policy(&policy);
+    service.place_order(cart("Mouse", 2500)).unwrap();
+    let large = service.place_order(cart("Monitor", 149_900)).unwrap();
+    service.reject_order(large.id, "over budget").unwrap();
+}
+
+#[test]
+fn deleted_order_is_invisible_until_restored() {
+    let repo = InMemoryOrderRepository::new();
+    seed(&repo);
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    service.delete_order(OrderId(2)).unwrap();
+
+    assert!(service.get_order(OrderId(2)).unwrap().is_none());
+    assert!(!service.order_exists(OrderId(2)).unwrap());
+    assert_eq!(service.order_total(OrderId(2)).unwrap(), None);
+
+    let restored = service.restore_order(OrderId(2)).unwrap();
+    assert_order(&restored)
+        .has_status(OrderStatus::Cancelled)
+        .has_item_named("Monitor");
+    assert!(service.order_exists(OrderId(2)).unwrap());
+}
+
+#[test]
+fn list_and_search_skip_deleted_orders() {
+    let repo = InMemoryOrderRepository::new();
+    seed(&repo);
+    repo.soft_delete(OrderId(2)).unwrap();
+
+    let browser = OrderBrowser::new(&repo);
+
+    let listed: Vec<OrderId> = browser.list().unwrap().iter().map(|o| o.id).collect();
+    assert_eq!(listed, vec![OrderId(1)]);
+    assert!(browser.search("monitor").unwrap().is_empty());
+    let deleted: Vec<OrderId> = browser
+        .list_deleted()
+        .unwrap()
+        .iter()
+        .map(|o| o.id)
+        .collect();
+    assert_eq!(deleted, vec![OrderId(2)]);
+}
+
+#[test]
+fn only_cancelled_orders_can_be_deleted() {
+    let repo = InMemoryOrderRepository::new();
+    seed(&repo);
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    assert_err_variant!(
+        service.delete_order(OrderId(1)),
+        OrderError::CannotDelete {
+            status: OrderStatus::Paid,
+            ..
+        }
+    );
+    assert!(service.order_exists(OrderId(1)).unwrap());
+}
+
+#[test]
+fn deleting_twice_is_harmless() {
+    let repo = InMemoryOrderRepository::new();
+    seed(&repo);
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    service.delete_order(OrderId(2)).unwrap();
+    service.delete_order(OrderId(2)).unwrap();
+
+    assert_eq!(repo.list_deleted().unwrap().len(), 1);
+}
+
+#[test]
+fn restore_tells_live_orders_from_unknown_ids() {
+    let repo = InMemoryOrderRepository::new();
+    seed(&repo);
+    let payment = MockPaymentGateway::new();
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    assert_err_variant!(
+        service.restore_order(OrderId(1)),
+        OrderError::NotDeleted { id: OrderId(1) }
+    );
+    assert_err_variant!(
+        service.restore_order(OrderId(9)),
+        OrderError::NotFound { id: OrderId(9) }
+    );
+    assert_err_variant!(
+        service.delete_order(OrderId(9)),
+        OrderError::NotFound { id: OrderId(9) }
+    );
+}
@@ -0,0 +1,143 @@
+// cargo test --test time_budget
+// One time budget for a whole use case, whichever ports it calls: the
+// decorated ports refuse to start once it is spent, and the report names
+// the step that was refused. Slow adapters move a stepping clock forward,
+// standing in for the time a real call takes.
+use hexa_lite::adapters::budget::WithinBudget;
+use hexa_lite::adapters::error_reporting::InMemoryErrorReporter;
+use hexa_lite::application::Hooks;
+use hexa_lite::ports::{Budget, ChargeLog, Port};
+use hexa_lite::prelude::testing::*;
+use hexa_lite::prelude::*;
+use std::time::Duration;
+
+// A payment provider taking `secs` to answer.
+struct SlowGateway<'a> {
+    inner: MockPaymentGateway,
+    clock: &'a SteppingClock,
+    secs: u64,
+}
+
+impl<'a> SlowGateway<'a> {
+    fn new(clock: &'a SteppingClock, secs: u64) -> Self {
+        Self {
+            inner: MockPaymentGateway::new(),
+            clock,
+            secs,
+        }
+    }
+}
+
+impl PaymentGateway for SlowGateway<'_> {
+    fn charge(&self, amount: Money) -> Result<(), OrderError> {
+        self.clock.advance_secs(self.secs);
+        self.inner.charge(amount)
+    }
+
+    fn charge_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.clock.advance_secs(self.secs);
+        self.inner.charge_for(order_id, amount)
+    }
+
+    fn refund_for(&self, order_id: OrderId, amount: Money) -> Result<(), OrderError> {
+        self.inner.refund_for(order_id, amount)
+    }
+}
+
+fn cart() -> Vec<LineItem> {
+    vec![LineItem::new("Keyboard", Money(12_999))]
+}
+
+const CHECKOUT: Duration = Duration::from_secs(2);
+
+#[test]
+fn a_slow_charge_leaves_nothing_for_the_save() {
+    let clock = SteppingClock::starting_at(Timestamp(1_000));
+    let budget = Budget::new(&clock, CHECKOUT);
+    let repo = WithinBudget::new(InMemoryOrderRepository::new(), &budget);
+    let payment = WithinBudget::new(SlowGateway::new(&clock, 3), &budget);
+    let sender = WithinBudget::new(ConsoleSender::new(), &budget);
+    let reporter = InMemoryErrorReporter::new();
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_budget(&budget)
+        .with_error_reporter(&reporter, &clock);
+
+    assert_err_variant!(
+        service.place_order(cart()),
+        OrderError::BudgetExhausted { spent_ms: 3_000 }
+    );
+
+    // The charge got going in time; the save was the step refused.
+    assert_eq!(payment.inner().inner.charges().unwrap().len(), 1);
+    assert!(repo.inner().list().unwrap().is_empty());
+    let reports = reporter.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].use_case, "place_order");
+    assert_eq!(reports[0].port, Some(Port::Repository));
+    assert_eq!(reports[0].operation, "save");
+    assert!(!budget.is_running());
+}
+
+// Work between the ports taking `secs`, after the order is saved.
+struct Pause<'a> {
+    clock: &'a SteppingClock,
+    secs: u64,
+}
+
+impl Hooks for Pause<'_> {
+    fn after_save(&self, _order: &Order) -> Result<(), OrderError> {
+        self.clock.advance_secs(self.secs);
+        Ok(())
+    }
+}
+
+#[test]
+fn the_budget_spans_every_port_not_each() {
+    let clock = SteppingClock::starting_at(Timestamp(1_000));
+    let budget = Budget::new(&clock, CHECKOUT);
+    let repo = WithinBudget::new(InMemoryOrderRepository::new(), &budget);
+    let payment = WithinBudget::new(SlowGateway::new(&clock, 1), &budget);
+    let sender = WithinBudget::new(ConsoleSender::new(), &budget);
+    let pause = Pause {
+        clock: &clock,
+        secs: 1,
+    };
+    let reporter = InMemoryErrorReporter::new();
+    let mut service = OrderService::new(&repo, &payment, &sender)
+        .with_budget(&budget)
+        .with_error_reporter(&reporter, &clock)
+        .with_hooks(&pause);
+
+    // One second each, both under the two of the budget; together, they
+    // leave nothing for the confirmation.
+    assert_err_variant!(
+        service.place_order(cart()),
+        OrderError::BudgetExhausted { spent_ms: 2_000 }
+    );
+    let reports = reporter.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].port, Some(Port::Sender));
+    assert_eq!(reports[0].operation, "send");
+}
+
+#[test]
+fn every_use_case_starts_with_the_whole_budget() {
+    let clock = SteppingClock::starting_at(Timestamp(1_000));
+    let budget = Budget::new(&clock, CHECKOUT);
+    let repo = WithinBudget::new(InMemoryOrderRepository::new(), &budget);
+    let payment = WithinBudget::new(SlowGateway::new(&clock, 1), &budget);
+    let sender = ConsoleSender::new();
+    let mut service = OrderService::new(&repo, &payment, &sender).with_budget(&budget);
+
+    // One second each, five seconds in all: each fits in its own two.
+    let mut ids = Vec::new();
+    for _ in 0..5 {
+        ids.push(service.place_order(cart()).unwrap().id);
+    }
+    assert_eq!(clock.now(), Timestamp(1_005));
+
+    // Between use cases the budget is not running: reads are never refused.
+    clock.advance_secs(60);
+    assert_eq!(repo.list().unwrap().len(), 5);
+    assert_order(&service.cancel_order(ids[0]).unwrap()).has_status(OrderStatus::Cancelled);
+}
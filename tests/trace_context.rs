@@ -0,0 +1,249 @@
+// cargo test --test trace_context
+// One use case, one trace: the service starts it at the use case's entry,
+// every port call is a span of it, and what goes downstream says so. The
+// webhook request has the traceparent header, the SQL statements the same
+// in a comment, the Traced decorators log the ids with every call. Called
+// over HTTP with a traceparent of its own, the use case joins the caller's
+// trace instead.
+use hexa_lite::adapters::http::{HttpAdapter, HttpRequest};
+use hexa_lite::adapters::sql::{FakeExecutor, SqlOrderRepository};
+use hexa_lite::adapters::tracing::{Contextless, InContext, Traced};
+use hexa_lite::adapters::webhook::WebhookSender;
+use hexa_lite::adapters::{Console, VecLogger};
+use hexa_lite::application::OrderBrowser;
+use hexa_lite::domain::OrderKey;
+use hexa_lite::facade::DefaultFacade;
+use hexa_lite::ports::{TRACEPARENT_HEADER, TraceContext, Tracer};
+use hexa_lite::prelude::*;
+
+const CALLER: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+fn keyboard() -> Vec<LineItem> {
+    vec![LineItem::new("Keyboard", Money(12999))]
+}
+
+fn post(traceparent: Option<&str>) -> HttpRequest {
+    HttpRequest {
+        method: "POST".to_string(),
+        path: "/orders".to_string(),
+        headers: traceparent
+            .map(|header| (TRACEPARENT_HEADER.to_string(), header.to_string()))
+            .into_iter()
+            .collect(),
+        body: br#"{"items":[{"name":"Keyboard","price_cents":12999}]}"#.to_vec(),
+    }
+}
+
+// (operation, trace, span, parent) of every line the Traced decorators
+// logged, in order.
+fn traced_calls(logger: &VecLogger) -> Vec<(String, String, String, String)> {
+    logger
+        .entries()
+        .into_iter()
+        .filter(|entry| entry.target == "Trace")
+        .map(|entry| {
+            let words: Vec<&str> = entry.message.split_whitespace().collect();
+            let value = |key: &str| words[2..].iter().find_map(|word| word.strip_prefix(key));
+            (
+                words[1].to_string(),
+                value("trace=").unwrap().to_string(),
+                value("span=").unwrap().to_string(),
+                value("parent=").unwrap().to_string(),
+            )
+        })
+        .collect()
+}
+
+// What the comment at the end of a statement says.
+fn statement_trace(sql: &str) -> TraceContext {
+    let (_, comment) = sql.rsplit_once("/*traceparent='").unwrap();
+    TraceContext::parse_traceparent(comment.trim_end_matches("'*/")).unwrap()
+}
+
+#[test]
+fn the_calls_downstream_carry_the_callers_trace() {
+    let tracer = Tracer::new();
+    let repo = InContext::new(
+        Traced::new(SqlOrderRepository::new(FakeExecutor::new()).unwrap())
+            .with_console(Console::silent()),
+        &tracer,
+    );
+    let sender = InContext::new(
+        Traced::new(WebhookSender::new("https://example.test/hook").unwrap())
+            .with_console(Console::silent()),
+        &tracer,
+    );
+    let payment = MockPaymentGateway::new();
+    let service = OrderService::new(&repo, &payment, &sender).with_tracer(&tracer);
+    let facade = DefaultFacade::new(service, OrderBrowser::new(&repo));
+    let http = HttpAdapter::new(facade).with_tracer(tracer.clone());
+
+    assert_eq!(http.handle(&post(Some(CALLER))).status, 201);
+
+    let caller = TraceContext::parse_traceparent(CALLER).unwrap();
+    let delivered = sender.inner().inner().delivered();
+    let header = delivered[0].header(TRACEPARENT_HEADER).unwrap();
+    let webhook = TraceContext::parse_traceparent(header).unwrap();
+    assert_eq!(webhook.trace_id, caller.trace_id);
+    assert_ne!(webhook.span_id, caller.span_id);
+
+    let statements = repo.inner().inner().executor().statements();
+    let upsert = statements
+        .iter()
+        .find(|statement| statement.sql.starts_with("INSERT"))
+        .unwrap();
+    let saved = statement_trace(&upsert.sql);
+    assert_eq!(saved.trace_id, caller.trace_id);
+    assert_ne!(saved.span_id, webhook.span_id);
+    // Past the use case, nothing is left of it.
+    assert_eq!(tracer.current(), None);
+}
+
+#[test]
+fn one_use_case_logs_one_trace_with_a_span_per_call() {
+    let logger = VecLogger::new();
+    let console = Console::logger(logger.clone());
+    let tracer = Tracer::new();
+    let repo = InContext::new(
+        Traced::new(Contextless(
+            InMemoryOrderRepository::new().with_console(Console::silent()),
+        ))
+        .with_console(console.clone()),
+        &tracer,
+    );
+    let sender = InContext::new(
+        Traced::new(Contextless(
+            ConsoleSender::new().with_console(Console::silent()),
+        ))
+        .with_console(console),
+        &tracer,
+    );
+    let payment = MockPaymentGateway::new();
+    let mut service = OrderService::new(&repo, &payment, &sender).with_tracer(&tracer);
+
+    service.place_order(keyboard()).unwrap();
+    let first = traced_calls(&logger);
+    service.place_order(keyboard()).unwrap();
+    let second = traced_calls(&logger).split_off(first.len());
+
+    for calls in [&first, &second] {
+        let operations: Vec<&str> = calls.iter().map(|call| call.0.as_str()).collect();
+        assert!(operations.contains(&"OrderWriter.save"), "{operations:?}");
+        assert!(operations.contains(&"Sender.send"), "{operations:?}");
+        // The same trace, and the same parent: the use case's own span.
+        assert!(calls.iter().all(|call| call.1 == calls[0].1));
+        assert!(calls.iter().all(|call| call.3 == calls[0].3));
+        assert_ne!(calls[0].3, "-");
+        let mut spans: Vec<&str> = calls.iter().map(|call| call.2.as_str()).collect();
+        spans.sort();
+        spans.dedup();
+        assert_eq!(spans.len(), calls.len());
+    }
+    assert_ne!(first[0].1, second[0].1);
+}
+
+#[test]
+fn a_traceparent_that_does_not_parse_starts_a_trace_of_its_own() {
+    let tracer = Tracer::new();
+    let repo = InMemoryOrderRepository::new().with_console(Console::silent());
+    let sender = InContext::new(
+        WebhookSender::new("https://example.test/hook")
+            .unwrap()
+            .with_console(Console::silent()),
+        &tracer,
+    );
+    let payment = MockPaymentGateway::new();
+    let service = OrderService::new(&repo, &payment, &sender).with_tracer(&tracer);
+    let facade = DefaultFacade::new(service, OrderBrowser::new(&repo));
+    let http = HttpAdapter::new(facade).with_tracer(tracer.clone());
+
+    let foreign = "01-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+    for header in [
+        foreign,
+        "00-00000000000000000000000000000000-00f067aa0ba902b7-01",
+    ] {
+        assert_eq!(http.handle(&post(Some(header))).status, 201);
+    }
+    assert_eq!(http.handle(&post(None)).status, 201);
+
+    let traces: Vec<u128> = sender
+        .inner()
+        .delivered()
+        .iter()
+        .map(|request| {
+            let header = request.header(TRACEPARENT_HEADER).unwrap();
+            TraceContext::parse_traceparent(header).unwrap().trace_id
+        })
+        .collect();
+    assert_eq!(traces.len(), 3);
+    assert!(!traces.contains(&0x4bf9_2f35_77b3_4da6_a3ce_929d_0e0e_4736));
+    assert!(!traces.contains(&0));
+    assert!(traces[0] != traces[1] && traces[1] != traces[2]);
+}
+
+// In context, a repository still deletes, restores and updates: InContext
+// forwards those too, and not to OrderWriter's defaults.
+fn deletes_and_restores_in_context(repo: &impl OrderWriter) {
+    let order = Order::new(OrderId(1), keyboard()).unwrap();
+    repo.save(&order).unwrap();
+
+    repo.soft_delete(order.id).unwrap();
+    assert_eq!(repo.find(order.id).unwrap(), None);
+    let deleted = repo.list_deleted().unwrap();
+    assert_eq!(deleted.len(), 1);
+    assert_eq!(deleted[0].id, order.id);
+
+    repo.restore(order.id).unwrap();
+    let restored = repo.find(order.id).unwrap().unwrap();
+    assert!(repo.list_deleted().unwrap().is_empty());
+    assert_eq!(
+        repo.find_by_key(OrderKey::Sequential(order.id)).unwrap(),
+        Some(restored.clone())
+    );
+
+    repo.update(&restored).unwrap();
+    assert!(matches!(
+        repo.update(&restored),
+        Err(OrderError::VersionConflict { .. })
+    ));
+    repo.purge(order.id).unwrap();
+    assert_eq!(repo.find(order.id).unwrap(), None);
+}
+
+#[test]
+fn an_in_memory_repository_deletes_and_restores_in_context() {
+    let tracer = Tracer::new();
+    let repo = InContext::new(
+        Contextless(InMemoryOrderRepository::new().with_console(Console::silent())),
+        &tracer,
+    );
+    deletes_and_restores_in_context(&repo);
+}
+
+#[test]
+fn a_sql_repository_deletes_and_restores_in_context() {
+    let tracer = Tracer::new();
+    let repo = InContext::new(
+        SqlOrderRepository::new(FakeExecutor::new())
+            .unwrap()
+            .with_console(Console::silent()),
+        &tracer,
+    );
+    deletes_and_restores_in_context(&repo);
+
+    // Every statement past the schema's says whose span sent it.
+    let statements = repo.inner().executor().statements();
+    let sent: Vec<&str> = statements
+        .iter()
+        .map(|statement| statement.sql.as_str())
+        .skip_while(|sql| !sql.starts_with("INSERT"))
+        .collect();
+    assert!(
+        sent.iter()
+            .any(|sql| sql.starts_with("UPDATE orders SET deleted = 1"))
+    );
+    assert!(
+        sent.iter().all(|sql| sql.contains("/*traceparent='")),
+        "{sent:?}"
+    );
+}
@@ -0,0 +1,108 @@
+// cargo test --test webhook_signing
+// The receiving end of a signed webhook: place an order through the service,
+// take the request the WebhookSender "posted" and verify it like a server would.
+use hexa_lite::adapters::SecretString;
+use hexa_lite::adapters::webhook::{
+    SIGNATURE_HEADER, SignatureError, TIMESTAMP_HEADER, WebhookRequest, WebhookSender, sign,
+    verify_signature, verify_signature_at,
+};
+use hexa_lite::prelude::*;
+use std::time::Duration;
+
+const KEY: &str = "whsec_tutorial";
+const SKEW: Duration = Duration::from_secs(300);
+
+fn delivered_request() -> WebhookRequest {
+    let repo = InMemoryOrderRepository::new();
+    let payment = MockPaymentGateway::new();
+    let sender = WebhookSender::new("https://example.test/hook")
+        .unwrap()
+        .with_signing_key(KEY.into());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+
+    service
+        .place_order(vec![LineItem {
+            name: "Keyboard".to_string(),
+            price: Money(12999),
+        }])
+        .unwrap();
+
+    sender.delivered().remove(0)
+}
+
+#[test]
+fn valid_signature_is_accepted() {
+    let request = delivered_request();
+
+    assert!(request.header(SIGNATURE_HEADER).is_some());
+    assert_eq!(
+        verify_signature(&request.body, &request.headers, &KEY.into(), SKEW),
+        Ok(())
+    );
+}
+
+#[test]
+fn tampered_body_is_detected() {
+    let request = delivered_request();
+    let tampered = request.body.replace("12999", "1");
+
+    assert_eq!(
+        verify_signature(&tampered, &request.headers, &KEY.into(), SKEW),
+        Err(SignatureError::Mismatch)
+    );
+}
+
+#[test]
+fn wrong_key_is_detected() {
+    let request = delivered_request();
+
+    assert_eq!(
+        verify_signature(&request.body, &request.headers, &"other".into(), SKEW),
+        Err(SignatureError::Mismatch)
+    );
+}
+
+#[test]
+fn timestamp_older_than_skew_is_rejected() {
+    let key = SecretString::new(KEY);
+    let body = r#"{"order_id":1}"#;
+    let now = 1_700_000_000;
+    let headers_at = |timestamp: u64| {
+        vec![
+            (TIMESTAMP_HEADER.to_string(), timestamp.to_string()),
+            (SIGNATURE_HEADER.to_string(), sign(&key, timestamp, body)),
+        ]
+    };
+
+    // Exactly at the edge of the window is still fine...
+    assert_eq!(
+        verify_signature_at(body, &headers_at(now - 300), &key, SKEW, now),
+        Ok(())
+    );
+    // ...one second beyond is a replay.
+    assert_eq!(
+        verify_signature_at(body, &headers_at(now - 301), &key, SKEW, now),
+        Err(SignatureError::OutsideSkew { delta_secs: 301 })
+    );
+    // The skew is configurable.
+    assert_eq!(
+        verify_signature_at(
+            body,
+            &headers_at(now - 301),
+            &key,
+            Duration::from_secs(600),
+            now
+        ),
+        Ok(())
+    );
+}
+
+#[test]
+fn missing_headers_are_reported() {
+    let key = SecretString::new(KEY);
+
+    assert_eq!(
+        verify_signature_at("{}", &[], &key, SKEW, 0),
+        Err(SignatureError::MissingHeader(TIMESTAMP_HEADER))
+    );
+}
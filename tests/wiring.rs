@@ -0,0 +1,116 @@
+// cargo test --test wiring
+// The wirings compared by benches/wiring, on a smaller profile: generic,
+// dyn, shared across threads, and placed in parallel, the same carts must
+// leave the same orders behind, under the same ids. A benchmark of paths
+// that disagree would compare nothing.
+use hexa_lite::adapters::Console;
+use hexa_lite::application::DynOrderService;
+use hexa_lite::ports::ServiceState;
+use hexa_lite::prelude::*;
+use hexa_lite::testing::{BenchProfile, bench_profile};
+use std::sync::{Arc, Barrier};
+use std::thread;
+
+fn profile() -> BenchProfile {
+    BenchProfile {
+        orders: 200,
+        runs: 1,
+        ..bench_profile()
+    }
+}
+
+fn repository() -> InMemoryOrderRepository {
+    InMemoryOrderRepository::new().with_console(Console::silent())
+}
+
+fn payment() -> MockPaymentGateway {
+    MockPaymentGateway::new().with_console(Console::silent())
+}
+
+fn sender() -> ConsoleSender {
+    ConsoleSender::new().with_console(Console::silent())
+}
+
+fn generic(carts: &[Vec<LineItem>]) -> Vec<Order> {
+    let (repo, payment, sender) = (repository(), payment(), sender());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    for cart in carts {
+        service.place_order(cart.clone()).unwrap();
+    }
+    repo.list().unwrap()
+}
+
+fn dynamic(carts: &[Vec<LineItem>]) -> Vec<Order> {
+    let repo: Box<dyn OrderWriter + Sync> = Box::new(repository());
+    let payment: Box<dyn PaymentGateway + Sync> = Box::new(payment());
+    let sender: Box<dyn Sender + Sync> = Box::new(sender());
+    let mut service = DynOrderService::new(&*repo, &*payment, &*sender);
+    for cart in carts {
+        service.place_order(cart.clone()).unwrap();
+    }
+    repo.list().unwrap()
+}
+
+// Thread t places its share of the carts under ids of its own, starting
+// where thread t - 1 stops, as in the benchmark.
+fn shared(carts: &[Vec<LineItem>], threads: usize) -> Vec<Order> {
+    let adapters = Arc::new((repository(), payment(), sender()));
+    let share = carts.len().div_ceil(threads);
+    let ready = Arc::new(Barrier::new(threads));
+    let workers: Vec<_> = carts
+        .chunks(share)
+        .enumerate()
+        .map(|(t, chunk)| {
+            let (adapters, ready, chunk) =
+                (Arc::clone(&adapters), Arc::clone(&ready), chunk.to_vec());
+            thread::spawn(move || {
+                let (repo, payment, sender) = &*adapters;
+                let state = ServiceState {
+                    next_id: (t * share + 1) as u32,
+                    open_drafts: Vec::new(),
+                };
+                let mut service = OrderService::restore(repo, payment, sender, &state).unwrap();
+                ready.wait();
+                for cart in chunk {
+                    service.place_order(cart).unwrap();
+                }
+            })
+        })
+        .collect();
+    for worker in workers {
+        worker.join().unwrap();
+    }
+    adapters.0.list().unwrap()
+}
+
+fn parallel(carts: &[Vec<LineItem>], workers: usize) -> Vec<Order> {
+    let (repo, payment, sender) = (repository(), payment(), sender());
+    let mut service = OrderService::new(&repo, &payment, &sender);
+    for outcome in service.place_orders_parallel(carts.to_vec(), workers) {
+        outcome.unwrap();
+    }
+    repo.list().unwrap()
+}
+
+#[test]
+fn every_wiring_leaves_the_same_orders() {
+    let carts = profile().carts();
+
+    let expected = generic(&carts);
+
+    assert_eq!(expected.len(), 200);
+    assert_eq!(dynamic(&carts), expected);
+    assert_eq!(shared(&carts, 1), expected);
+    assert_eq!(parallel(&carts, 1), expected);
+}
+
+#[test]
+fn threads_sharing_the_adapters_lose_no_order() {
+    let carts = profile().carts();
+
+    let orders = shared(&carts, 4);
+
+    // Ids and carts line up as if one thread had placed them all.
+    assert_eq!(orders, generic(&carts));
+    assert_eq!(parallel(&carts, 4), orders);
+}